@@ -0,0 +1,102 @@
+/*!
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Benchmarks of the tag operations that the RTI performs for every
+ * connection on every scheduling event. Run with "cargo bench".
+ */
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use lf_tag::{msec, Interval, Tag, FOREVER, NEVER, NO_DELAY};
+
+/**
+ * Tags that mix the cases of the grant logic: equal times with different
+ * microsteps, NEVER, FOREVER, and ordinary times.
+ */
+fn tags() -> Vec<Tag> {
+    (0..256u32)
+        .map(|i| match i % 16 {
+            0 => Tag::never_tag(),
+            1 => Tag::forever_tag(),
+            _ => Tag::new(msec(i64::from(i / 4)), i % 3),
+        })
+        .collect()
+}
+
+/**
+ * Delays as found on connections: none, zero (one microstep), and positive.
+ */
+fn intervals() -> Vec<Interval> {
+    (0..256i64)
+        .map(|i| match i % 4 {
+            0 => NO_DELAY,
+            1 => Some(0),
+            2 => Some(FOREVER),
+            _ => Some(msec(i)),
+        })
+        .collect()
+}
+
+/**
+ * The branchless alternative to Ord for Tag: a single 128-bit key that orders
+ * as (time, microstep), with the sign bit of the time flipped.
+ */
+fn key(tag: &Tag) -> u128 {
+    ((((tag.time() as u64) ^ (1 << 63)) as u128) << 32) | u128::from(tag.microstep())
+}
+
+fn bench_compare(c: &mut Criterion) {
+    let tags = tags();
+    c.bench_function("lf_tag_compare", |b| {
+        b.iter(|| {
+            let mut sum = 0;
+            for pair in tags.windows(2) {
+                sum += Tag::lf_tag_compare(black_box(&pair[0]), black_box(&pair[1]));
+            }
+            sum
+        })
+    });
+    c.bench_function("min by Ord", |b| {
+        b.iter(|| black_box(&tags).iter().min().cloned())
+    });
+    c.bench_function("min by 128-bit key", |b| {
+        b.iter(|| black_box(&tags).iter().min_by_key(|tag| key(tag)).cloned())
+    });
+}
+
+fn bench_add(c: &mut Criterion) {
+    let tags = tags();
+    let deltas: Vec<Tag> = tags
+        .iter()
+        .map(|tag| match tag.time() {
+            NEVER | FOREVER => Tag::new(0, 1),
+            time => Tag::new(time / 2, tag.microstep()),
+        })
+        .collect();
+    c.bench_function("lf_tag_add", |b| {
+        b.iter(|| {
+            tags.iter()
+                .zip(&deltas)
+                .map(|(tag, delta)| Tag::lf_tag_add(black_box(tag), black_box(delta)))
+                .max()
+        })
+    });
+}
+
+fn bench_delay_strict(c: &mut Criterion) {
+    let tags = tags();
+    let intervals = intervals();
+    c.bench_function("lf_delay_strict", |b| {
+        b.iter(|| {
+            tags.iter()
+                .zip(&intervals)
+                .map(|(tag, interval)| Tag::lf_delay_strict(black_box(tag), black_box(*interval)))
+                .min()
+        })
+    });
+}
+
+criterion_group!(benches, bench_compare, bench_add, bench_delay_strict);
+criterion_main!(benches);
@@ -0,0 +1,260 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the public API of the lf_tag crate, on which other tooling
+ * for Lingua Franca relies.
+ */
+use std::cmp::Ordering;
+
+use lf_tag::*;
+
+#[test]
+fn never_and_forever_bound_all_tags() {
+    assert_eq!(NEVER, i64::MIN);
+    assert_eq!(FOREVER, i64::MAX);
+    assert_eq!(FOREVER_MICROSTEP, u32::MAX);
+    assert_eq!(NO_DELAY, Some(NEVER));
+    let never = Tag::never_tag();
+    let forever = Tag::forever_tag();
+    assert_eq!((never.time(), never.microstep()), (NEVER, 0));
+    assert_eq!(
+        (forever.time(), forever.microstep()),
+        (FOREVER, FOREVER_MICROSTEP)
+    );
+    for tag in [
+        Tag::new(NEVER, 1),
+        Tag::new(-1, 0),
+        Tag::new(0, 0),
+        Tag::new(0, FOREVER_MICROSTEP),
+        Tag::new(FOREVER - 1, FOREVER_MICROSTEP),
+        Tag::new(FOREVER, 0),
+    ] {
+        assert!(never < tag, "{:?}", tag);
+        assert!(tag < forever, "{:?}", tag);
+    }
+}
+
+#[test]
+fn tags_are_ordered_by_time_then_microstep() {
+    let mut tags = vec![
+        Tag::new(10, 0),
+        Tag::new(5, 7),
+        Tag::forever_tag(),
+        Tag::new(5, 0),
+        Tag::never_tag(),
+        Tag::new(-3, 2),
+        Tag::new(10, 0),
+    ];
+    tags.sort();
+    assert_eq!(
+        tags,
+        vec![
+            Tag::never_tag(),
+            Tag::new(-3, 2),
+            Tag::new(5, 0),
+            Tag::new(5, 7),
+            Tag::new(10, 0),
+            Tag::new(10, 0),
+            Tag::forever_tag(),
+        ]
+    );
+    assert_eq!(Tag::new(1, 0).cmp(&Tag::new(0, 9)), Ordering::Greater);
+    assert_eq!(
+        Tag::new(1, 2).partial_cmp(&Tag::new(1, 2)),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(Tag::lf_tag_compare(&Tag::new(1, 1), &Tag::new(1, 2)), -1);
+    assert_eq!(Tag::lf_tag_compare(&Tag::new(1, 2), &Tag::new(1, 2)), 0);
+    assert_eq!(Tag::lf_tag_compare(&Tag::new(2, 0), &Tag::new(1, 2)), 1);
+}
+
+#[test]
+fn tags_convert_to_and_from_pairs() {
+    let tag = Tag::from((42, 3));
+    assert_eq!(tag, Tag::new(42, 3));
+    assert_eq!(<(Instant, Microstep)>::from(tag), (42, 3));
+}
+
+#[test]
+fn delay_realizes_the_after_keyword() {
+    let tag = Tag::new(100, 3);
+    // A zero delay is one microstep.
+    assert_eq!(Tag::lf_delay_tag(&tag, Some(0)), Tag::new(100, 4));
+    // A positive delay resets the microstep.
+    assert_eq!(Tag::lf_delay_tag(&tag, Some(50)), Tag::new(150, 0));
+    // No delay, or a negative one, leaves the tag alone.
+    assert_eq!(Tag::lf_delay_tag(&tag, None), tag);
+    assert_eq!(Tag::lf_delay_tag(&tag, NO_DELAY), tag);
+    assert_eq!(Tag::lf_delay_tag(&tag, Some(-5)), tag);
+    // NEVER is absorbing, and the time saturates at FOREVER.
+    assert_eq!(
+        Tag::lf_delay_tag(&Tag::never_tag(), Some(50)),
+        Tag::never_tag()
+    );
+    assert_eq!(
+        Tag::lf_delay_tag(&Tag::new(FOREVER - 10, 0), Some(50)),
+        Tag::new(FOREVER, 0)
+    );
+    assert_eq!(
+        Tag::lf_delay_tag(&Tag::new(0, FOREVER_MICROSTEP), Some(0)),
+        Tag::new(0, 0)
+    );
+}
+
+#[test]
+fn strict_delay_is_just_before_the_delayed_tag() {
+    let tag = Tag::new(100, 3);
+    assert_eq!(
+        Tag::lf_delay_strict(&tag, Some(50)),
+        Tag::new(149, FOREVER_MICROSTEP)
+    );
+    // A zero delay, NO_DELAY, and FOREVER are as in lf_delay_tag.
+    assert_eq!(Tag::lf_delay_strict(&tag, Some(0)), Tag::new(100, 4));
+    assert_eq!(Tag::lf_delay_strict(&tag, NO_DELAY), tag);
+    assert_eq!(
+        Tag::lf_delay_strict(&tag, Some(FOREVER)),
+        Tag::new(FOREVER, 0)
+    );
+    // So are tags of NEVER and delays that saturate at FOREVER.
+    assert_eq!(
+        Tag::lf_delay_strict(&Tag::never_tag(), Some(50)),
+        Tag::never_tag()
+    );
+    assert_eq!(
+        Tag::lf_delay_strict(&Tag::new(FOREVER - 10, 0), Some(50)),
+        Tag::new(FOREVER, 0)
+    );
+    // Negative delays and no delay are just before the tag itself.
+    assert_eq!(
+        Tag::lf_delay_strict(&tag, Some(-5)),
+        Tag::new(99, FOREVER_MICROSTEP)
+    );
+    assert_eq!(
+        Tag::lf_delay_strict(&tag, None),
+        Tag::new(99, FOREVER_MICROSTEP)
+    );
+}
+
+#[test]
+fn checked_arithmetic_fails_on_overflow() {
+    let tag = Tag::new(10, 2);
+    assert_eq!(tag.checked_add(5), Ok(Tag::new(15, 2)));
+    assert_eq!(tag.checked_sub(5), Ok(Tag::new(5, 2)));
+    assert_eq!(tag.checked_add(FOREVER), Err(TagOverflow));
+    assert_eq!(Tag::new(-10, 2).checked_sub(FOREVER), Err(TagOverflow));
+    // Results of exactly NEVER or FOREVER are overflows too.
+    assert_eq!(tag.checked_add(FOREVER - 10), Err(TagOverflow));
+    assert_eq!(tag.checked_add(FOREVER - 11), Ok(Tag::new(FOREVER - 1, 2)));
+    assert_eq!(Tag::new(NEVER + 1, 0).checked_sub(1), Err(TagOverflow));
+    // NEVER and FOREVER are absorbing.
+    for interval in [NEVER, -1, 0, 1, FOREVER] {
+        assert_eq!(Tag::never_tag().checked_add(interval), Ok(Tag::never_tag()));
+        assert_eq!(Tag::never_tag().checked_sub(interval), Ok(Tag::never_tag()));
+        assert_eq!(
+            Tag::forever_tag().checked_add(interval),
+            Ok(Tag::forever_tag())
+        );
+        assert_eq!(
+            Tag::forever_tag().checked_sub(interval),
+            Ok(Tag::forever_tag())
+        );
+    }
+    assert_eq!(tag.checked_next_microstep(), Ok(Tag::new(10, 3)));
+    assert_eq!(
+        Tag::new(10, FOREVER_MICROSTEP).checked_next_microstep(),
+        Err(TagOverflow)
+    );
+    assert_eq!(
+        Tag::forever_tag().checked_next_microstep(),
+        Ok(Tag::forever_tag())
+    );
+    assert_eq!(
+        TagOverflow.to_string(),
+        "the tag is out of the range of representable times"
+    );
+}
+
+#[test]
+fn saturating_arithmetic_stops_at_never_and_forever() {
+    let tag = Tag::new(10, 2);
+    assert_eq!(tag.saturating_add(5), Tag::new(15, 2));
+    assert_eq!(tag.saturating_add(FOREVER), Tag::forever_tag());
+    assert_eq!(Tag::new(-10, 2).saturating_add(NEVER), Tag::never_tag());
+    assert_eq!(Tag::new(-10, 2).saturating_sub(FOREVER), Tag::never_tag());
+    assert_eq!(tag.saturating_sub(NEVER), Tag::forever_tag());
+    assert_eq!(Tag::never_tag().saturating_add(FOREVER), Tag::never_tag());
+    assert_eq!(
+        Tag::forever_tag().saturating_sub(FOREVER),
+        Tag::forever_tag()
+    );
+}
+
+#[test]
+fn tags_display_the_elapsed_time() {
+    let start_time = sec(100);
+    let tag = Tag::new(start_time + msec(5), 1);
+    assert_eq!(tag.to_string(), "(100005000000,1)");
+    assert_eq!(tag.elapsed(start_time).to_string(), "(5000000,1)");
+    assert_eq!(format!("{:?}", tag.elapsed(start_time)), "(5000000,1)");
+    assert_eq!(
+        Tag::new(sec(99), 0).elapsed(start_time).to_string(),
+        "(-1000000000,0)"
+    );
+    assert_eq!(Tag::never_tag().elapsed(start_time).to_string(), "NEVER");
+    assert_eq!(
+        Tag::forever_tag().elapsed(start_time).to_string(),
+        "FOREVER"
+    );
+    // The elapsed time does not overflow even if the start time is far away.
+    assert_eq!(
+        Tag::new(FOREVER - 1, 0).elapsed(NEVER + 1).to_string(),
+        format!("({},0)", u64::MAX - 2)
+    );
+}
+
+#[test]
+fn tag_add_adds_microsteps_only_for_zero_times() {
+    assert_eq!(
+        Tag::lf_tag_add(&Tag::new(10, 2), &Tag::new(0, 3)),
+        Tag::new(10, 5)
+    );
+    assert_eq!(
+        Tag::lf_tag_add(&Tag::new(10, 2), &Tag::new(5, 3)),
+        Tag::new(15, 3)
+    );
+    assert_eq!(
+        Tag::lf_tag_add(&Tag::never_tag(), &Tag::forever_tag()),
+        Tag::never_tag()
+    );
+    assert_eq!(
+        Tag::lf_tag_add(&Tag::new(10, 0), &Tag::forever_tag()),
+        Tag::forever_tag()
+    );
+    assert_eq!(
+        Tag::lf_tag_add(&Tag::new(FOREVER - 1, 0), &Tag::new(5, 0)),
+        Tag::forever_tag()
+    );
+}
+
+#[test]
+fn time_units_convert_to_nanoseconds_and_saturate() {
+    assert_eq!(nsec(7), 7);
+    assert_eq!(usec(7), 7_000);
+    assert_eq!(msec(7), 7_000_000);
+    assert_eq!(sec(7), 7_000_000_000);
+    assert_eq!(minutes(2), 120_000_000_000);
+    assert_eq!(hours(1), 3_600_000_000_000);
+    assert_eq!(hours(i64::MAX / 1_000), FOREVER);
+    assert_eq!(sec(i64::MIN / 1_000), NEVER);
+}
+
+#[test]
+fn start_time_is_never_until_set() {
+    let mut start_time = StartTime::default();
+    assert_eq!(start_time.start_time(), NEVER);
+    start_time.set_start_time(sec(1));
+    assert_eq!(start_time.start_time(), 1_000_000_000);
+}
@@ -0,0 +1,783 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Console through which operators control a running federation.
+ *
+ * If the RTI is given tokens, each client of the console or the status server
+ * must authenticate with one of them, which decides its role: the read-only
+ * role may only query the state of the federation, so that observability can
+ * be exposed broadly, and the control role may also change it.
+ */
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+
+use crate::lock_stats::{lock_rti, read_rti};
+use crate::server::{Server, StopGranted};
+use crate::snapshot::Snapshot;
+use crate::tag::{StartTime, Tag};
+use crate::trace::{debug, error, info, warn};
+use crate::Enclave;
+use crate::FederationRTI;
+
+/**
+ * Permission of a client of the admin console or the status server.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AdminRole {
+    /// May query the state of the federation.
+    Read,
+    /// May also pause, step, and break the federation.
+    Control,
+}
+
+impl AdminRole {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdminRole::Read => "read",
+            AdminRole::Control => "control",
+        }
+    }
+}
+
+/**
+ * Read a token for the admin console from the given file, without the
+ * surrounding whitespace.
+ */
+pub fn read_token(path: &str) -> Result<String, String> {
+    let token = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let token = token.trim();
+    if token.is_empty() || token.contains(char::is_whitespace) {
+        return Err(String::from("the token must be a non-empty word"));
+    }
+    Ok(token.to_string())
+}
+
+/**
+ * Return the role of a client that presents the given token, or None if the
+ * token is not valid. If the RTI has no token, every client has the control
+ * role without a token.
+ */
+pub fn authorize(locked_rti: &FederationRTI, token: Option<&str>) -> Option<AdminRole> {
+    if locked_rti.admin_token().is_none() && locked_rti.admin_read_token().is_none() {
+        return Some(AdminRole::Control);
+    }
+    let token = token?;
+    let matches = |expected: &Option<String>| {
+        expected
+            .as_ref()
+            .is_some_and(|expected| tokens_match(expected.as_bytes(), token.as_bytes()))
+    };
+    if matches(locked_rti.admin_token()) {
+        Some(AdminRole::Control)
+    } else if matches(locked_rti.admin_read_token()) {
+        Some(AdminRole::Read)
+    } else {
+        None
+    }
+}
+
+/**
+ * Compare two tokens in a time that does not depend on where they differ.
+ */
+fn tokens_match(expected: &[u8], presented: &[u8]) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .iter()
+            .zip(presented)
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/**
+ * Line-based text console. Each line received from a connection is executed
+ * as a command and answered with one line that starts with "OK" or "ERROR".
+ */
+pub struct AdminConsole {
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    start_time: Arc<Mutex<StartTime>>,
+    sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+    stop_granted: Arc<Mutex<StopGranted>>,
+}
+
+impl AdminConsole {
+    pub(crate) fn new(
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> AdminConsole {
+        AdminConsole {
+            _f_rti,
+            start_time,
+            sent_start_time,
+            stop_granted,
+        }
+    }
+
+    /**
+     * Spawn a thread that accepts connections on the given listener until all
+     * federates have exited. Each connection is served by its own thread so
+     * that an idle operator does not block others.
+     */
+    pub fn start_admin_thread(self, listener: TcpListener) -> thread::JoinHandle<()> {
+        let console = Arc::new(self);
+        thread::spawn(move || {
+            // NOTE: The RTI wakes up the thread with a connection once all
+            // federates have exited.
+            loop {
+                let result = listener.accept();
+                {
+                    let locked_rti = lock_rti(&console._f_rti);
+                    if locked_rti.all_federates_exited() {
+                        break;
+                    }
+                }
+                match result {
+                    Ok((stream, address)) => {
+                        info!("RTI: Admin console connected from {}.", address);
+                        let console = console.clone();
+                        thread::spawn(move || console.serve(stream));
+                    }
+                    Err(e) => {
+                        error!("RTI: Admin console failed to accept a connection: {}.", e);
+                    }
+                }
+            }
+        })
+    }
+
+    /**
+     * Execute the commands received from one client. A client must first
+     * authenticate with "auth <token>" if the RTI has tokens.
+     */
+    fn serve(&self, stream: TcpStream) {
+        let mut writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(e) => {
+                error!("RTI: Admin console failed to set up a connection: {}.", e);
+                return;
+            }
+        };
+        let mut role = authorize(&lock_rti(&self._f_rti), None);
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let line = line.trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            // NOTE: The token is not logged.
+            let response = match (words.as_slice(), role) {
+                (["auth", token], _) => match authorize(&lock_rti(&self._f_rti), Some(token)) {
+                    Some(granted) => {
+                        role = Some(granted);
+                        format!("OK authenticated as {}", granted.name())
+                    }
+                    None => {
+                        warn!("RTI: Admin console rejected an invalid token.");
+                        String::from("ERROR invalid token")
+                    }
+                },
+                (_, Some(role)) => self.execute(role, line),
+                (_, None) => String::from("ERROR authenticate first with auth <token>"),
+            };
+            if writeln!(writer, "{}", response).is_err() {
+                break;
+            }
+        }
+    }
+
+    /**
+     * Execute one command with the permission of the given role and return
+     * the response line.
+     */
+    pub fn execute(&self, role: AdminRole, command: &str) -> String {
+        debug!("RTI: Admin console command: {}", command);
+        let words: Vec<&str> = command.split_whitespace().collect();
+        if role == AdminRole::Read {
+            if let Some(word) = words.first().filter(|word| Self::changes_federation(word)) {
+                return format!("ERROR '{}' needs the control role", word);
+            }
+        }
+        match words.as_slice() {
+            ["start"] => self.start(),
+            ["pause"] => self.pause(),
+            ["resume"] => self.resume(),
+            ["step", "on"] => self.step_on(),
+            ["step", "off"] => self.step_off(),
+            ["pending"] => self.pending(),
+            ["release"] => match self.release_next_grant() {
+                Some(description) => format!("OK released {}", description),
+                None => String::from("ERROR no pending grant"),
+            },
+            ["break", target, time, microstep] => self.set_breakpoint(target, time, microstep),
+            ["breakpoints"] => self.breakpoints(),
+            ["continue", target] => self.continue_federates(target),
+            ["relisten"] => self.relisten(),
+            ["drain"] => self.drain(None),
+            ["drain", time, microstep] => self.drain(Some((time, microstep))),
+            ["eimt", target] => self.explain_eimt(target),
+            ["info", target] => self.info(target),
+            ["history", target] => self.history(target),
+            ["ptags", target] => self.provisional_grants(target),
+            ["memory"] => self.memory(),
+            ["waits"] => self.waits(),
+            ["snapshot"] => self.snapshot(),
+            ["help"] => String::from(
+                "OK commands: start, pause, resume, step on, step off, pending, release, \
+                 break all|<federate> <time> <microstep>, breakpoints, \
+                 continue all|<federate>, relisten, drain [<time> <microstep>], \
+                 eimt <federate>, \
+                 info all|<federate>, history all|<federate>, \
+                 ptags all|<federate>, memory, waits, snapshot, auth <token>, help",
+            ),
+            _ => format!("ERROR unknown command '{}'", command),
+        }
+    }
+
+    /**
+     * Return true if the command with the given first word changes the
+     * federation, so that it needs the control role.
+     */
+    fn changes_federation(word: &str) -> bool {
+        matches!(
+            word,
+            "start"
+                | "pause"
+                | "resume"
+                | "step"
+                | "release"
+                | "break"
+                | "continue"
+                | "relisten"
+                | "drain"
+        )
+    }
+
+    /**
+     * Make the RTI close the socket on which it accepts federates and bind a
+     * new one to the same port, without affecting the connected federates.
+     */
+    fn relisten(&self) -> String {
+        let port = {
+            let mut locked_rti = lock_rti(&self._f_rti);
+            let Some(port) = locked_rti.listening_port() else {
+                return String::from("ERROR all federates have connected");
+            };
+            locked_rti.set_relisten_requested(true);
+            port
+        };
+        Server::wake_up_listener(port);
+        format!("OK reopening the listening socket on port {}", port)
+    }
+
+    /**
+     * Drain the federation until the given tag, relative to the start time,
+     * or, if None, only until the federates can stop, and then stop it. See
+     * Server::drain.
+     */
+    fn drain(&self, tag: Option<(&str, &str)>) -> String {
+        if !*self.sent_start_time.0.lock().unwrap() {
+            return String::from("ERROR the start time has not been sent");
+        }
+        let start_time_value = self.start_time.lock().unwrap().start_time();
+        let drain_tag = match tag {
+            None => Tag::new(start_time_value, 0),
+            Some((time, microstep)) => {
+                let (Ok(time), Ok(microstep)) = (time.parse::<i64>(), microstep.parse::<u32>())
+                else {
+                    return format!("ERROR invalid tag ({},{})", time, microstep);
+                };
+                // The time is given in units of the time resolution.
+                let time_resolution = lock_rti(&self._f_rti).time_resolution();
+                match time_resolution.to_nanoseconds(time) {
+                    Some(time_ns) if time_ns >= 0 => {
+                        Tag::new(start_time_value.saturating_add(time_ns), microstep)
+                    }
+                    _ => return format!("ERROR invalid tag ({},{})", time, microstep),
+                }
+            }
+        };
+        if !Server::drain(
+            drain_tag.clone(),
+            self._f_rti.clone(),
+            start_time_value,
+            self.stop_granted.clone(),
+        ) {
+            return String::from("ERROR the federation is stopping already");
+        }
+        format!(
+            "OK draining until {}",
+            Enclave::relative_tag(&drain_tag, start_time_value)
+        )
+    }
+
+    /**
+     * Send the start time held back by --hold, or, if some federates have not
+     * proposed one yet, let it be sent as soon as they have.
+     */
+    fn start(&self) -> String {
+        {
+            let mut locked_rti = lock_rti(&self._f_rti);
+            if !locked_rti.start_held() {
+                return String::from("ERROR the start time is not held");
+            }
+            locked_rti.set_start_held(false);
+            let Some(held_since) = locked_rti.held_since() else {
+                info!("RTI: Start released before all federates have proposed a start time.");
+                return String::from(
+                    "OK the start time is sent once all federates have proposed one",
+                );
+            };
+            locked_rti.set_held_since(None);
+            // Start as if the last federate had proposed its start time now,
+            // so that the federates do not start behind physical time.
+            let held_for = locked_rti
+                .physical_clock()
+                .now()
+                .saturating_sub(held_since)
+                .max(0);
+            let max_start_time = locked_rti.max_start_time();
+            locked_rti.set_max_start_time(max_start_time.saturating_add(held_for));
+            info!("RTI: Start released after {} ms.", held_for / 1_000_000);
+        }
+        Server::send_start_time(
+            self._f_rti.clone(),
+            self.start_time.clone(),
+            self.sent_start_time.clone(),
+        );
+        String::from("OK start time sent")
+    }
+
+    fn pause(&self) -> String {
+        let mut locked_rti = lock_rti(&self._f_rti);
+        if locked_rti.paused() {
+            return String::from("ERROR already paused");
+        }
+        // Grants are sent while holding the RTI lock, so none is sent after this.
+        locked_rti.set_paused(true);
+        info!("RTI: Federation paused. Withholding all TAG and PTAG messages.");
+        String::from("OK paused")
+    }
+
+    /**
+     * Resume the federation and send every federate the grant that the NET
+     * and LTC messages received while paused allow.
+     */
+    fn resume(&self) -> String {
+        {
+            let mut locked_rti = lock_rti(&self._f_rti);
+            if !locked_rti.paused() {
+                return String::from("ERROR not paused");
+            }
+            locked_rti.set_paused(false);
+        }
+        info!("RTI: Federation resumed.");
+        self.grant_all();
+        String::from("OK resumed")
+    }
+
+    /**
+     * Send every federate the grant that the NET and LTC messages received so
+     * far allow. Used when grants that were held back may be sent again.
+     */
+    fn grant_all(&self) {
+        let start_time_value;
+        {
+            let locked_start_time = self.start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let locked_rti = read_rti(&self._f_rti);
+        Enclave::grant_all(&locked_rti, start_time_value);
+    }
+
+    fn step_on(&self) -> String {
+        let mut locked_rti = lock_rti(&self._f_rti);
+        locked_rti.set_step_mode(true);
+        info!("RTI: Step mode on. Each TAG and PTAG is sent only once released.");
+        String::from("OK step mode on")
+    }
+
+    /**
+     * Leave step mode and send the pending grants in order.
+     */
+    fn step_off(&self) -> String {
+        {
+            let mut locked_rti = lock_rti(&self._f_rti);
+            locked_rti.set_step_mode(false);
+        }
+        info!("RTI: Step mode off.");
+        let mut released = 0;
+        while self.release_next_grant().is_some() {
+            released += 1;
+        }
+        format!("OK step mode off, released {} pending grant(s)", released)
+    }
+
+    fn pending(&self) -> String {
+        let start_time_value;
+        {
+            let locked_start_time = self.start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let locked_rti = lock_rti(&self._f_rti);
+        let pending_grants = locked_rti.pending_grants();
+        if pending_grants.is_empty() {
+            return String::from("OK no pending grants");
+        }
+        let described: Vec<String> = pending_grants
+            .iter()
+            .enumerate()
+            .map(|(i, pending)| {
+                format!(
+                    "#{} {} to federate {} because {}",
+                    i + 1,
+                    pending.grant().describe(start_time_value),
+                    locked_rti.federate_label(pending.fed_id()),
+                    pending.grant().justification()
+                )
+            })
+            .collect();
+        format!(
+            "OK {} pending: {}",
+            pending_grants.len(),
+            described.join("; ")
+        )
+    }
+
+    /**
+     * Parse the target of a breakpoint command: "all" for None, or the ID or
+     * the name of a federate.
+     */
+    fn parse_target(&self, target: &str) -> Result<Option<u16>, String> {
+        if target == "all" {
+            return Ok(None);
+        }
+        let locked_rti = lock_rti(&self._f_rti);
+        match target.parse::<u16>() {
+            Ok(fed_id) if i32::from(fed_id) < locked_rti.number_of_enclaves() => Ok(Some(fed_id)),
+            Ok(_) => Err(format!("ERROR unknown federate '{}'", target)),
+            Err(_) => match locked_rti.federate_id_by_name(target) {
+                Some(fed_id) => Ok(Some(fed_id)),
+                None => Err(format!("ERROR unknown federate '{}'", target)),
+            },
+        }
+    }
+
+    /**
+     * Describe the metadata that one federate, or each of them, presented at
+     * the handshake.
+     */
+    fn info(&self, target: &str) -> String {
+        let target = match self.parse_target(target) {
+            Ok(target) => target,
+            Err(response) => return response,
+        };
+        let mut locked_rti = lock_rti(&self._f_rti);
+        let described: Vec<String> = locked_rti
+            .enclaves()
+            .iter()
+            .enumerate()
+            .filter(|(id, _)| target.is_none_or(|fed_id| usize::from(fed_id) == *id))
+            .map(|(_, fed)| match fed.metadata() {
+                Some(metadata) => format!("federate {} {}", fed.label(), metadata.to_text()),
+                None => format!("federate {} no metadata", fed.label()),
+            })
+            .collect();
+        format!("OK {}", described.join("; "))
+    }
+
+    /**
+     * Describe the recent scheduling events of one federate, or of each of
+     * them, oldest first.
+     */
+    fn history(&self, target: &str) -> String {
+        let target = match self.parse_target(target) {
+            Ok(target) => target,
+            Err(response) => return response,
+        };
+        let start_time_value;
+        {
+            let locked_start_time = self.start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let mut locked_rti = lock_rti(&self._f_rti);
+        if locked_rti.history_length() == 0 {
+            return String::from("ERROR the history is disabled");
+        }
+        let described: Vec<String> = locked_rti
+            .enclaves()
+            .iter_mut()
+            .enumerate()
+            .filter(|(id, _)| target.is_none_or(|fed_id| usize::from(fed_id) == *id))
+            .map(|(_, fed)| {
+                let label = fed.label();
+                let entries: Vec<String> = fed
+                    .history()
+                    .entries()
+                    .iter()
+                    .map(|entry| entry.describe(start_time_value))
+                    .collect();
+                if entries.is_empty() {
+                    format!("federate {} no events", label)
+                } else {
+                    format!("federate {} {}", label, entries.join(", "))
+                }
+            })
+            .collect();
+        format!("OK {}", described.join("; "))
+    }
+
+    /**
+     * Return a snapshot of the state of the federation in JSON, which tools
+     * read with Snapshot::parse.
+     */
+    fn snapshot(&self) -> String {
+        let start_time_value = self.start_time.lock().unwrap().start_time();
+        let locked_rti = read_rti(&self._f_rti);
+        format!(
+            "OK {}",
+            Snapshot::capture(&locked_rti, start_time_value).to_json()
+        )
+    }
+
+    /**
+     * Describe the outcomes of the PTAGs sent to one federate, or to each of
+     * them.
+     */
+    fn provisional_grants(&self, target: &str) -> String {
+        let target = match self.parse_target(target) {
+            Ok(target) => target,
+            Err(response) => return response,
+        };
+        let mut locked_rti = lock_rti(&self._f_rti);
+        let described: Vec<String> = locked_rti
+            .enclaves()
+            .iter_mut()
+            .enumerate()
+            .filter(|(id, _)| target.is_none_or(|fed_id| usize::from(fed_id) == *id))
+            .map(|(_, fed)| {
+                format!(
+                    "federate {} {}",
+                    fed.label(),
+                    fed.provisional_grants().describe()
+                )
+            })
+            .collect();
+        format!("OK {}", described.join("; "))
+    }
+
+    /**
+     * Explain how the earliest incoming message tag of a federate is derived
+     * from the state of its (transitive) upstream federates.
+     */
+    fn explain_eimt(&self, target: &str) -> String {
+        let fed_id = match self.parse_target(target) {
+            Ok(Some(fed_id)) => fed_id,
+            Ok(None) => return String::from("ERROR eimt needs a single federate"),
+            Err(response) => return response,
+        };
+        let start_time_value;
+        {
+            let locked_start_time = self.start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let mut locked_rti = lock_rti(&self._f_rti);
+        format!(
+            "OK {}",
+            Enclave::explain_earliest_incoming_message_tag(
+                locked_rti.enclaves(),
+                fed_id,
+                start_time_value
+            )
+        )
+    }
+
+    /**
+     * Describe the usage of each memory budget given with --memory_budget.
+     */
+    fn memory(&self) -> String {
+        let budgets = lock_rti(&self._f_rti).memory_budgets();
+        if budgets.is_empty() {
+            return String::from("ERROR no memory budget");
+        }
+        let described: Vec<String> = budgets
+            .iter()
+            .map(|budget| format!("{} {}", budget.name(), budget.describe()))
+            .collect();
+        format!("OK {}", described.join("; "))
+    }
+
+    /**
+     * Describe when the federates proposed their start times and how long
+     * the RTI threads waited for the start time to be sent.
+     */
+    fn waits(&self) -> String {
+        let locked_rti = read_rti(&self._f_rti);
+        let wait_stats = locked_rti.wait_stats();
+        let mut described = Vec::new();
+        described.extend(wait_stats.describe_start_time_barrier());
+        described.extend(wait_stats.report());
+        if described.is_empty() {
+            return String::from("ERROR the start time has not been sent");
+        }
+        format!("OK {}", described.join("; "))
+    }
+
+    /**
+     * Set a breakpoint, relative to the start time, for one federate or for
+     * all of them. Grants already sent beyond it are not taken back.
+     */
+    fn set_breakpoint(&self, target: &str, time: &str, microstep: &str) -> String {
+        let target = match self.parse_target(target) {
+            Ok(target) => target,
+            Err(response) => return response,
+        };
+        let (Ok(time), Ok(microstep)) = (time.parse::<i64>(), microstep.parse::<u32>()) else {
+            return format!("ERROR invalid tag ({},{})", time, microstep);
+        };
+        let mut locked_rti = lock_rti(&self._f_rti);
+        // The time is given in units of the time resolution.
+        let breakpoint = match locked_rti.time_resolution().to_nanoseconds(time) {
+            Some(time_ns) if time_ns >= 0 => Tag::new(time_ns, microstep),
+            _ => return format!("ERROR invalid tag ({},{})", time, microstep),
+        };
+        match target {
+            None => {
+                locked_rti.set_breakpoint(Some(breakpoint));
+                info!(
+                    "RTI: Breakpoint set at ({},{}) for all federates.",
+                    time, microstep
+                );
+                format!(
+                    "OK breakpoint at ({},{}) for all federates",
+                    time, microstep
+                )
+            }
+            Some(fed_id) => {
+                locked_rti.enclaves()[fed_id as usize]
+                    .enclave()
+                    .set_breakpoint(Some(breakpoint));
+                let label = locked_rti.federate_label(fed_id);
+                info!(
+                    "RTI: Breakpoint set at ({},{}) for federate {}.",
+                    time, microstep, label
+                );
+                format!(
+                    "OK breakpoint at ({},{}) for federate {}",
+                    time, microstep, label
+                )
+            }
+        }
+    }
+
+    fn breakpoints(&self) -> String {
+        let mut locked_rti = lock_rti(&self._f_rti);
+        let time_resolution = locked_rti.time_resolution();
+        let mut described = Vec::new();
+        if let Some(breakpoint) = locked_rti.breakpoint() {
+            described.push(format!(
+                "all ({},{})",
+                time_resolution.to_units(breakpoint.time()),
+                breakpoint.microstep()
+            ));
+        }
+        for fed in locked_rti.enclaves().iter() {
+            if let Some(breakpoint) = fed.e().breakpoint() {
+                described.push(format!(
+                    "federate {} ({},{})",
+                    fed.label(),
+                    time_resolution.to_units(breakpoint.time()),
+                    breakpoint.microstep()
+                ));
+            }
+        }
+        if described.is_empty() {
+            return String::from("OK no breakpoints");
+        }
+        format!("OK breakpoints: {}", described.join("; "))
+    }
+
+    /**
+     * Clear the breakpoint of one federate, or every breakpoint, and send the
+     * grants that were held back. A federate that is continued on its own
+     * still stops at the breakpoint for all federates.
+     */
+    fn continue_federates(&self, target: &str) -> String {
+        let target = match self.parse_target(target) {
+            Ok(target) => target,
+            Err(response) => return response,
+        };
+        let response;
+        {
+            let mut locked_rti = lock_rti(&self._f_rti);
+            match target {
+                None => {
+                    locked_rti.set_breakpoint(None);
+                    for fed in locked_rti.enclaves().iter_mut() {
+                        fed.enclave().set_breakpoint(None);
+                    }
+                    info!("RTI: Breakpoints cleared. Continuing all federates.");
+                    response = String::from("OK continued all federates");
+                }
+                Some(fed_id) => {
+                    locked_rti.enclaves()[fed_id as usize]
+                        .enclave()
+                        .set_breakpoint(None);
+                    let label = locked_rti.federate_label(fed_id);
+                    info!("RTI: Breakpoint cleared. Continuing federate {}.", label);
+                    response = format!("OK continued federate {}", label);
+                }
+            }
+        }
+        self.grant_all();
+        response
+    }
+
+    /**
+     * Send the oldest pending grant and return its description, or None if no
+     * grant is pending. A grant that has become redundant in the meantime,
+     * e.g., because a later grant was released first, is not sent.
+     */
+    fn release_next_grant(&self) -> Option<String> {
+        let start_time_value;
+        {
+            let locked_start_time = self.start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let mut locked_rti = lock_rti(&self._f_rti);
+        let pending = locked_rti.release_pending_grant()?;
+        let number_of_enclaves = locked_rti.number_of_enclaves();
+        let label = locked_rti.federate_label(pending.fed_id());
+        let grant = pending.grant();
+        if grant.is_provisional() {
+            Enclave::notify_provisional_tag_advance_grant(
+                &locked_rti,
+                pending.fed_id(),
+                number_of_enclaves,
+                grant.tag(),
+                grant.justification(),
+                start_time_value,
+            );
+        } else {
+            Enclave::notify_tag_advance_grant(
+                &locked_rti,
+                pending.fed_id(),
+                grant.tag(),
+                grant.justification(),
+                start_time_value,
+            );
+        }
+        locked_rti.clear_released_grant();
+        Some(format!(
+            "{} to federate {}",
+            grant.describe(start_time_value),
+            label
+        ))
+    }
+}
@@ -0,0 +1,139 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Placement of the threads of the RTI on CPUs and their scheduling
+ * priority, which reduce the jitter of grants for realtime federations on
+ * shared hosts.
+ */
+use std::io;
+
+use crate::trace::warn;
+
+/**
+ * Number of CPUs that a CPU set of the operating system can hold, which is
+ * CPU_SETSIZE in <sched.h>.
+ */
+const MAX_CPUS: usize = 1024;
+
+/**
+ * Parse a list of CPUs such as "0,2-3". Return a description of the error if
+ * the list is invalid.
+ */
+pub fn parse_cpu_list(text: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = Vec::new();
+    for item in text.split(',') {
+        let invalid = || format!("invalid CPU '{}'", item);
+        let (first, last) = match item.split_once('-') {
+            Some((first, last)) => (
+                first.trim().parse::<usize>().map_err(|_| invalid())?,
+                last.trim().parse::<usize>().map_err(|_| invalid())?,
+            ),
+            None => {
+                let cpu = item.trim().parse::<usize>().map_err(|_| invalid())?;
+                (cpu, cpu)
+            }
+        };
+        if first > last || last >= MAX_CPUS {
+            return Err(invalid());
+        }
+        cpus.extend(first..=last);
+    }
+    Ok(cpus)
+}
+
+/**
+ * Where and how a thread of the RTI runs.
+ */
+#[derive(Clone)]
+pub struct ThreadPlacement {
+    name: String,
+    cpu: Option<usize>,
+    realtime_priority: Option<i32>,
+}
+
+impl ThreadPlacement {
+    /**
+     * Place the thread with the given index among the threads of its kind on
+     * one of the given CPUs, in turn, or on any CPU if the list is empty.
+     */
+    pub fn new(
+        name: String,
+        cpus: &[usize],
+        index: usize,
+        realtime_priority: Option<i32>,
+    ) -> ThreadPlacement {
+        ThreadPlacement {
+            name,
+            cpu: if cpus.is_empty() {
+                None
+            } else {
+                Some(cpus[index % cpus.len()])
+            },
+            realtime_priority,
+        }
+    }
+
+    /**
+     * Apply the placement to the calling thread. Failures, such as a lack of
+     * permission to use realtime scheduling, are reported but not fatal.
+     */
+    pub fn apply(&self) {
+        if let Some(cpu) = self.cpu {
+            if let Err(e) = pin_current_thread(cpu) {
+                warn!(
+                    "RTI: WARNING: Failed to pin the {} to CPU {}: {}.",
+                    self.name, cpu, e
+                );
+            }
+        }
+        if let Some(priority) = self.realtime_priority {
+            if let Err(e) = set_current_thread_realtime_priority(priority) {
+                warn!(
+                    "RTI: WARNING: Failed to set the realtime priority of the {} to {}: {}.",
+                    self.name, priority, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin_current_thread(cpu: usize) -> io::Result<()> {
+    // SAFETY: An all-zero cpu_set_t is an empty set.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    // SAFETY: cpu is below MAX_CPUS, which is CPU_SETSIZE.
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+    // SAFETY: set is a valid cpu_set_t, and pid 0 is the calling thread.
+    if unsafe { libc::sched_setaffinity(0, std::mem::size_of_val(&set), &set) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_cpu: usize) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+#[cfg(unix)]
+fn set_current_thread_realtime_priority(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    // SAFETY: pthread_self() is the calling thread and param is a valid
+    // sched_param.
+    let error =
+        unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    if error != 0 {
+        return Err(io::Error::from_raw_os_error(error));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_current_thread_realtime_priority(_priority: i32) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
@@ -0,0 +1,2000 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Parsing of the command-line arguments of the RTI, which accepts the
+ * options of the RTI of the C runtime, so that the launch scripts generated
+ * by lfc work unchanged, and options of its own.
+ */
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::admin;
+use crate::affinity::parse_cpu_list;
+use crate::clock::ClockSource;
+use crate::constants::*;
+use crate::egress::{EgressRate, ExcessEgressPolicy};
+use crate::encryption::OutputKey;
+use crate::error::RtiError;
+use crate::event_stream::EventStream;
+use crate::fault::FaultInjector;
+use crate::federation_metadata::FederationMetadata;
+use crate::federation_rti::{number_of_cpus, FederationRTI};
+use crate::group::{FederateGroup, GroupPolicy};
+use crate::log_sink::LogSink;
+use crate::memory::MemoryBudget;
+use crate::notifier::GrantWritePolicy;
+use crate::rate_limit::ExcessControlPolicy;
+use crate::snapshot::Snapshot;
+use crate::tag::{TimeFormat, TimeResolution, NO_DELAY};
+use crate::tls::TlsConfig;
+use crate::trace::{self, error, info, warn, LogLevel, DEFAULT_TRACE_LINES_PER_SECOND};
+use crate::{ClockSyncStat, EarlyNetPolicy, FailurePolicy};
+
+/**
+ * Configure the RTI with the given command-line arguments, which start with
+ * the name of the program. An invalid argument is reported and returned as
+ * RtiError::InvalidArguments.
+ */
+pub fn process_args(rti: &mut FederationRTI, argv: &[String]) -> Result<(), RtiError> {
+    parse_args(rti, argv).map_err(RtiError::InvalidArguments)
+}
+
+fn parse_args(rti: &mut FederationRTI, argv: &[String]) -> Result<(), &'static str> {
+    rti.set_arguments(argv.iter().skip(1).cloned().collect());
+    let mut idx = 1;
+    let argc = argv.len();
+    // Given in units of the time resolution, which may be specified later.
+    let mut stop_time = None;
+    let mut max_lead = None;
+    let mut lookahead = None;
+    let mut max_lag = None;
+    // Loaded once both the certificate and the key are known.
+    let mut tls_cert = None;
+    let mut tls_key = None;
+    // Loaded once the time resolution, in whose units the rules are given, is known.
+    let mut fault_rules = None;
+    let mut fault_seed = None;
+    // Opened once the key, under which it may be encrypted, is known.
+    let mut event_stream = None;
+    // Loaded once the federation ID and the number of federates are known.
+    let mut federation_metadata = None;
+    let mut restore_snapshot = None;
+    // Applied once the number of federates is known and the federation
+    // metadata, which may also assign federates to groups, is loaded.
+    let mut group_members: Vec<(String, Vec<u16>)> = Vec::new();
+    let mut group_policies: Vec<(String, String)> = Vec::new();
+    // Delays given in units of the time resolution.
+    let mut parent_upstream: Vec<(u16, Option<i64>)> = Vec::new();
+    while idx < argc {
+        let arg = argv[idx].as_str();
+        // println!("arg = {}", arg); // TODO: Remove this debugging code
+        if arg == "-i" || arg == "--id" {
+            if argc < idx + 2 {
+                error!("--id needs a string argument.");
+                usage(argc, argv);
+                return Err("Fail to handle id option");
+            }
+            idx += 1;
+            // println!("idx = {}", idx); // TODO: Remove this debugging code
+            info!("RTI: Federation ID: {}", argv[idx]);
+            rti.set_federation_id(argv[idx].clone());
+        } else if arg == "-n" || arg == "--number_of_federates" {
+            if argc < idx + 2 {
+                error!("--number_of_federates needs an integer argument.");
+                usage(argc, argv);
+                return Err("Fail to handle number_of_federates option");
+            }
+            idx += 1;
+            let num_federates: i64;
+            match argv[idx].parse::<i64>() {
+                Ok(parsed_value) => {
+                    if parsed_value == 0 || parsed_value == i64::MAX || parsed_value == i64::MIN {
+                        error!("--number_of_federates needs a valid positive integer argument.");
+                        usage(argc, argv);
+                        return Err("Fail to handle number_of_federates option");
+                    }
+                    num_federates = parsed_value;
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to i64");
+                }
+            };
+            rti.set_number_of_enclaves(num_federates.try_into().unwrap()); // FIXME: panic if the converted value doesn't fit
+            info!("RTI: Number of federates: {}", rti.number_of_enclaves());
+        } else if arg == "-p" || arg == "--port" {
+            if argc < idx + 2 {
+                error!(
+                    "--port needs a short unsigned integer argument ( > 0 and < {}).",
+                    u16::MAX
+                );
+                usage(argc, argv);
+                return Err("Fail to handle port option");
+            }
+            idx += 1;
+            let rti_port: u16;
+            match argv[idx].parse::<u16>() {
+                Ok(parsed_value) => {
+                    if parsed_value <= 0 || parsed_value >= u16::MAX {
+                        error!(
+                            "--port needs a short unsigned integer argument ( > 0 and < {}).",
+                            u16::MAX
+                        );
+                        usage(argc, argv);
+                        return Err("Fail to handle number_of_federates option");
+                    }
+                    rti_port = parsed_value;
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u16");
+                }
+            }
+            rti.set_port(rti_port.try_into().unwrap());
+        } else if arg == "-c" || arg == "--clock_sync" {
+            if argc < idx + 2 {
+                error!("--clock-sync needs off|init|on.");
+                usage(argc, argv);
+                return Err("Fail to handle clock_sync option");
+            }
+            idx = process_clock_sync_args(rti, argc, argv, idx + 1)?;
+        } else if arg == "-a" || arg == "--auth" {
+            if !cfg!(feature = "auth") {
+                error!("--auth requires the RTI to be built with the auth feature.");
+                usage(argc, argv);
+                return Err("Fail to handle auth option");
+            }
+            info!("RTI: Federates must authenticate themselves.");
+            rti.set_authentication_enabled(true);
+        } else if arg == "-t" || arg == "--tracing" {
+            rti.set_tracing_enabled(true);
+        } else if arg == "-d" || arg == "--disable_dnet" {
+            info!("RTI: DNET messages are disabled.");
+            rti.set_dnet_disabled(true);
+        } else if arg == "--timeout" {
+            // NOTE: -t is --tracing, as in the C RTI, so this has no short form.
+            if argc < idx + 2 {
+                error!("--timeout needs an integer argument (in seconds).");
+                usage(argc, argv);
+                return Err("Fail to handle timeout option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_connect_timeout_s(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u64");
+                }
+            }
+        } else if arg == "--trace_file" {
+            if argc < idx + 2 {
+                error!("--trace_file needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle trace_file option");
+            }
+            idx += 1;
+            rti.set_tracing_enabled(true);
+            rti.set_trace_file(argv[idx].clone());
+        } else if arg == "--fast" {
+            info!("RTI: The federation runs in fast mode.");
+            rti.set_fast_mode(true);
+        } else if arg == "--stop_time" {
+            if argc < idx + 2 {
+                error!("--stop_time needs a positive integer argument (in units of the time resolution).");
+                usage(argc, argv);
+                return Err("Fail to handle stop_time option");
+            }
+            idx += 1;
+            match argv[idx].parse::<i64>() {
+                Ok(parsed_value) if parsed_value > 0 => {
+                    stop_time = Some(parsed_value);
+                }
+                _ => {
+                    error!("--stop_time needs a positive integer argument (in units of the time resolution).");
+                    usage(argc, argv);
+                    return Err("Fail to handle stop_time option");
+                }
+            }
+        } else if arg == "--max_lead" {
+            if argc < idx + 2 {
+                error!("--max_lead needs a non-negative integer argument (in units of the time resolution).");
+                usage(argc, argv);
+                return Err("Fail to handle max_lead option");
+            }
+            idx += 1;
+            match argv[idx].parse::<i64>() {
+                Ok(parsed_value) if parsed_value >= 0 => {
+                    max_lead = Some(parsed_value);
+                }
+                _ => {
+                    error!("--max_lead needs a non-negative integer argument (in units of the time resolution).");
+                    usage(argc, argv);
+                    return Err("Fail to handle max_lead option");
+                }
+            }
+        } else if arg == "--lookahead" {
+            if argc < idx + 2 {
+                error!("--lookahead needs a positive integer argument (in units of the time resolution).");
+                usage(argc, argv);
+                return Err("Fail to handle lookahead option");
+            }
+            idx += 1;
+            match argv[idx].parse::<i64>() {
+                Ok(parsed_value) if parsed_value > 0 => {
+                    lookahead = Some(parsed_value);
+                }
+                _ => {
+                    error!("--lookahead needs a positive integer argument (in units of the time resolution).");
+                    usage(argc, argv);
+                    return Err("Fail to handle lookahead option");
+                }
+            }
+        } else if arg == "--max_lag" {
+            if argc < idx + 2 {
+                error!("--max_lag needs a positive integer argument (in units of the time resolution).");
+                usage(argc, argv);
+                return Err("Fail to handle max_lag option");
+            }
+            idx += 1;
+            match argv[idx].parse::<i64>() {
+                Ok(parsed_value) if parsed_value > 0 => {
+                    max_lag = Some(parsed_value);
+                }
+                _ => {
+                    error!("--max_lag needs a positive integer argument (in units of the time resolution).");
+                    usage(argc, argv);
+                    return Err("Fail to handle max_lag option");
+                }
+            }
+        } else if arg == "--silent_grace" {
+            if argc < idx + 2 {
+                error!("--silent_grace needs an integer argument (in milliseconds).");
+                usage(argc, argv);
+                return Err("Fail to handle silent_grace option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_silent_grace_ms(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u64");
+                }
+            }
+        } else if arg == "--resume_window" {
+            if !cfg!(feature = "auth") {
+                error!("--resume_window requires the RTI to be built with the auth feature.");
+                usage(argc, argv);
+                return Err("Fail to handle resume_window option");
+            }
+            if argc < idx + 2 {
+                error!("--resume_window needs an integer argument (in milliseconds).");
+                usage(argc, argv);
+                return Err("Fail to handle resume_window option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_resume_window_ms(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u64");
+                }
+            }
+        } else if arg == "--dynamic_ids" {
+            info!("RTI: Federates may join without a federate ID.");
+            rti.set_dynamic_ids(true);
+        } else if arg == "--clock_source" {
+            if argc < idx + 2 {
+                error!("--clock_source needs monotonic, realtime, or tai.");
+                usage(argc, argv);
+                return Err("Fail to handle clock_source option");
+            }
+            idx += 1;
+            match ClockSource::from_name(argv[idx].as_str()) {
+                Some(clock_source) if clock_source.is_available() => {
+                    info!(
+                        "RTI: Physical time is read from the {} clock.",
+                        clock_source.name()
+                    );
+                    rti.set_clock_source(clock_source);
+                }
+                Some(clock_source) => {
+                    error!(
+                        "--clock_source {} is not available on this platform.",
+                        clock_source.name()
+                    );
+                    usage(argc, argv);
+                    return Err("Fail to handle clock_source option");
+                }
+                None => {
+                    error!("--clock_source needs monotonic, realtime, or tai.");
+                    usage(argc, argv);
+                    return Err("Fail to handle clock_source option");
+                }
+            }
+        } else if arg == "--time_resolution" {
+            if argc < idx + 2 {
+                error!("--time_resolution needs ns, us, or ms.");
+                usage(argc, argv);
+                return Err("Fail to handle time_resolution option");
+            }
+            idx += 1;
+            match TimeResolution::from_unit(argv[idx].as_str()) {
+                Some(time_resolution) => {
+                    info!(
+                        "RTI: The resolution of logical time is 1 {}.",
+                        time_resolution.unit()
+                    );
+                    rti.set_time_resolution(time_resolution);
+                }
+                None => {
+                    error!("--time_resolution needs ns, us, or ms.");
+                    usage(argc, argv);
+                    return Err("Fail to handle time_resolution option");
+                }
+            }
+        } else if arg == "--time_format" {
+            if argc < idx + 2 {
+                error!("--time_format needs elapsed, absolute, or raw.");
+                usage(argc, argv);
+                return Err("Fail to handle time_format option");
+            }
+            idx += 1;
+            match TimeFormat::from_name(argv[idx].as_str()) {
+                Some(time_format) => {
+                    info!("RTI: Printing times in the {} format.", time_format.name());
+                    rti.set_time_format(time_format);
+                }
+                None => {
+                    error!("--time_format needs elapsed, absolute, or raw.");
+                    usage(argc, argv);
+                    return Err("Fail to handle time_format option");
+                }
+            }
+        } else if arg == "--log_timestamps" {
+            rti.set_log_timestamps(true);
+        } else if arg == "--progress_period" {
+            if argc < idx + 2 {
+                error!("--progress_period needs an integer argument (in milliseconds).");
+                usage(argc, argv);
+                return Err("Fail to handle progress_period option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_progress_period_ms(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u64");
+                }
+            }
+        } else if arg == "--handshake_timeout" {
+            if argc < idx + 2 {
+                error!("--handshake_timeout needs an integer argument (in milliseconds).");
+                usage(argc, argv);
+                return Err("Fail to handle handshake_timeout option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_handshake_timeout_ms(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u64");
+                }
+            }
+        } else if arg == "--keepalive" {
+            if argc < idx + 2 {
+                error!("--keepalive needs an integer argument (in seconds).");
+                usage(argc, argv);
+                return Err("Fail to handle keepalive option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_keepalive_s(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u64");
+                }
+            }
+        } else if arg == "--history_length" {
+            if argc < idx + 2 {
+                error!("--history_length needs an integer argument.");
+                usage(argc, argv);
+                return Err("Fail to handle history_length option");
+            }
+            idx += 1;
+            match argv[idx].parse::<usize>() {
+                Ok(parsed_value) => {
+                    rti.set_history_length(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to usize");
+                }
+            }
+        } else if arg == "--on_failure" {
+            if argc < idx + 2 {
+                error!("--on_failure needs abort|degrade.");
+                usage(argc, argv);
+                return Err("Fail to handle on_failure option");
+            }
+            idx += 1;
+            match argv[idx].as_str() {
+                "abort" => rti.set_failure_policy(FailurePolicy::Abort),
+                "degrade" => rti.set_failure_policy(FailurePolicy::Degrade),
+                _ => {
+                    error!("--on_failure needs abort|degrade.");
+                    usage(argc, argv);
+                    return Err("Fail to handle on_failure option");
+                }
+            }
+            info!("RTI: Failure policy: {:?}", rti.failure_policy());
+        } else if arg == "--group" {
+            if argc < idx + 2 {
+                error!("--group needs <name>=<comma-separated list of federate IDs>.");
+                usage(argc, argv);
+                return Err("Fail to handle group option");
+            }
+            idx += 1;
+            let parsed = argv[idx]
+                .split_once('=')
+                .filter(|(name, _)| FederateGroup::is_valid_name(name))
+                .and_then(|(name, fed_ids)| {
+                    let fed_ids: Result<Vec<u16>, _> = fed_ids
+                        .split(',')
+                        .map(|id| id.trim().parse::<u16>())
+                        .collect();
+                    Some((String::from(name), fed_ids.ok()?))
+                });
+            match parsed {
+                Some(members) => group_members.push(members),
+                None => {
+                    error!("--group needs <name>=<comma-separated list of federate IDs>.");
+                    usage(argc, argv);
+                    return Err("Fail to handle group option");
+                }
+            }
+        } else if arg == "--group_policy" {
+            if argc < idx + 2 {
+                error!("--group_policy needs <name>:<comma-separated list of <policy>=<value>>.");
+                usage(argc, argv);
+                return Err("Fail to handle group_policy option");
+            }
+            idx += 1;
+            match argv[idx].split_once(':') {
+                Some((name, policies)) if FederateGroup::is_valid_name(name) => {
+                    group_policies.push((String::from(name), String::from(policies)));
+                }
+                _ => {
+                    error!(
+                        "--group_policy needs <name>:<comma-separated list of <policy>=<value>>."
+                    );
+                    usage(argc, argv);
+                    return Err("Fail to handle group_policy option");
+                }
+            }
+        } else if arg == "--early_net" {
+            if argc < idx + 2 {
+                error!("--early_net needs clamp|reject.");
+                usage(argc, argv);
+                return Err("Fail to handle early_net option");
+            }
+            idx += 1;
+            match argv[idx].as_str() {
+                "clamp" => rti.set_early_net_policy(EarlyNetPolicy::Clamp),
+                "reject" => rti.set_early_net_policy(EarlyNetPolicy::Reject),
+                _ => {
+                    error!("--early_net needs clamp|reject.");
+                    usage(argc, argv);
+                    return Err("Fail to handle early_net option");
+                }
+            }
+            info!(
+                "RTI: Policy for NETs earlier than the start time: {:?}",
+                rti.early_net_policy()
+            );
+        } else if arg == "--quarantine" {
+            info!("RTI: Federates violating the protocol will be disconnected.");
+            rti.set_quarantine_enabled(true);
+        } else if arg == "--strict" {
+            info!("RTI: Protocol warnings will shut down the RTI.");
+            rti.set_strict(true);
+        } else if arg == "--io_threads" || arg == "--scheduling_threads" {
+            if argc < idx + 2 {
+                error!("{} needs a positive integer argument.", arg);
+                usage(argc, argv);
+                return Err("Fail to handle thread count option");
+            }
+            idx += 1;
+            match argv[idx].parse::<usize>() {
+                Ok(parsed_value) if parsed_value > 0 => {
+                    if arg == "--io_threads" {
+                        rti.set_io_threads(parsed_value);
+                    } else {
+                        rti.set_scheduling_threads(parsed_value);
+                    }
+                }
+                _ => {
+                    error!("{} needs a positive integer argument.", arg);
+                    usage(argc, argv);
+                    return Err("Fail to handle thread count option");
+                }
+            }
+        } else if arg == "--io_cpus" || arg == "--scheduling_cpus" {
+            if argc < idx + 2 {
+                error!("{} needs a list of CPUs such as 0,2-3.", arg);
+                usage(argc, argv);
+                return Err("Fail to handle CPU list option");
+            }
+            idx += 1;
+            match parse_cpu_list(&argv[idx]) {
+                Ok(cpus) => {
+                    if arg == "--io_cpus" {
+                        rti.set_io_cpus(cpus);
+                    } else {
+                        rti.set_scheduling_cpus(cpus);
+                    }
+                }
+                Err(e) => {
+                    error!("{} needs a list of CPUs such as 0,2-3: {}.", arg, e);
+                    usage(argc, argv);
+                    return Err("Fail to handle CPU list option");
+                }
+            }
+        } else if arg == "--realtime_priority" {
+            if argc < idx + 2 {
+                error!("--realtime_priority needs an integer argument between 1 and 99.");
+                usage(argc, argv);
+                return Err("Fail to handle realtime_priority option");
+            }
+            idx += 1;
+            match argv[idx].parse::<i32>() {
+                Ok(parsed_value) if (1..=99).contains(&parsed_value) => {
+                    rti.set_realtime_priority(parsed_value);
+                }
+                _ => {
+                    error!("--realtime_priority needs an integer argument between 1 and 99.");
+                    usage(argc, argv);
+                    return Err("Fail to handle realtime_priority option");
+                }
+            }
+        } else if arg == "--log_level" {
+            if argc < idx + 2 {
+                error!("--log_level needs error|warn|info|debug|trace.");
+                usage(argc, argv);
+                return Err("Fail to handle log_level option");
+            }
+            idx += 1;
+            match LogLevel::from_name(argv[idx].as_str()) {
+                Some(log_level) => rti.set_log_level(log_level),
+                None => {
+                    error!("--log_level needs error|warn|info|debug|trace.");
+                    usage(argc, argv);
+                    return Err("Fail to handle log_level option");
+                }
+            }
+        } else if arg == "--log_sink" {
+            if argc < idx + 2 {
+                error!("--log_sink needs stdout, syslog, or journald.");
+                usage(argc, argv);
+                return Err("Fail to handle log_sink option");
+            }
+            idx += 1;
+            match LogSink::from_name(argv[idx].as_str()) {
+                Some(log_sink) if log_sink.is_available() => rti.set_log_sink(log_sink),
+                Some(log_sink) => {
+                    error!(
+                        "--log_sink {} is not available on this platform.",
+                        log_sink.name()
+                    );
+                    usage(argc, argv);
+                    return Err("Fail to handle log_sink option");
+                }
+                None => {
+                    error!("--log_sink needs stdout, syslog, or journald.");
+                    usage(argc, argv);
+                    return Err("Fail to handle log_sink option");
+                }
+            }
+        } else if arg == "--log_socket" {
+            if argc < idx + 2 {
+                error!("--log_socket needs a socket path.");
+                usage(argc, argv);
+                return Err("Fail to handle log_socket option");
+            }
+            idx += 1;
+            rti.set_log_socket(argv[idx].clone());
+        } else if arg == "--log_file" {
+            if argc < idx + 2 {
+                error!("--log_file needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle log_file option");
+            }
+            idx += 1;
+            rti.set_log_file(argv[idx].clone());
+        } else if arg == "--trace_rate" {
+            if argc < idx + 2 {
+                error!("--trace_rate needs an integer argument (in lines per second).");
+                usage(argc, argv);
+                return Err("Fail to handle trace_rate option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_trace_lines_per_second(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u64");
+                }
+            }
+        } else if arg == "--max_message_size" {
+            if argc < idx + 2 {
+                error!("--max_message_size needs an integer argument (in bytes).");
+                usage(argc, argv);
+                return Err("Fail to handle max_message_size option");
+            }
+            idx += 1;
+            match argv[idx].parse::<usize>() {
+                Ok(parsed_value) => {
+                    rti.set_max_message_size(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to usize");
+                }
+            }
+        } else if arg == "--max_control_rate" {
+            if argc < idx + 2 {
+                error!("--max_control_rate needs an integer argument (in messages per second).");
+                usage(argc, argv);
+                return Err("Fail to handle max_control_rate option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u32>() {
+                Ok(parsed_value) => {
+                    rti.set_max_control_rate(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u32");
+                }
+            }
+        } else if arg == "--on_excess_control" {
+            if argc < idx + 2 {
+                error!("--on_excess_control needs coalesce|flag.");
+                usage(argc, argv);
+                return Err("Fail to handle on_excess_control option");
+            }
+            idx += 1;
+            match argv[idx].as_str() {
+                "coalesce" => rti.set_excess_control_policy(ExcessControlPolicy::Coalesce),
+                "flag" => rti.set_excess_control_policy(ExcessControlPolicy::Flag),
+                _ => {
+                    error!("--on_excess_control needs coalesce|flag.");
+                    usage(argc, argv);
+                    return Err("Fail to handle on_excess_control option");
+                }
+            }
+        } else if arg == "--grant_write_policy" {
+            if argc < idx + 2 {
+                error!("--grant_write_policy needs a comma-separated list of [<id>=]block|buffer:<n>|timeout:<ms>|supersede.");
+                usage(argc, argv);
+                return Err("Fail to handle grant_write_policy option");
+            }
+            idx += 1;
+            for entry in argv[idx].split(',') {
+                let (fed_id, policy) = match entry.split_once('=') {
+                    Some((fed_id, policy)) => (Some(fed_id.trim().parse::<u16>()), policy),
+                    None => (None, entry),
+                };
+                match (fed_id, parse_grant_write_policy(policy.trim())) {
+                    (None, Some(policy)) => rti.set_default_grant_write_policy(policy),
+                    (Some(Ok(fed_id)), Some(policy)) => rti.set_grant_write_policy(fed_id, policy),
+                    _ => {
+                        error!("--grant_write_policy needs a comma-separated list of [<id>=]block|buffer:<n>|timeout:<ms>|supersede.");
+                        usage(argc, argv);
+                        return Err("Fail to handle grant_write_policy option");
+                    }
+                }
+            }
+        } else if arg == "--egress_rate" {
+            if argc < idx + 2 {
+                error!("--egress_rate needs a comma-separated list of [<id>=]<bytes per second>[:<burst bytes>].");
+                usage(argc, argv);
+                return Err("Fail to handle egress_rate option");
+            }
+            idx += 1;
+            for entry in argv[idx].split(',') {
+                let (fed_id, rate) = match entry.split_once('=') {
+                    Some((fed_id, rate)) => (Some(fed_id.trim().parse::<u16>()), rate),
+                    None => (None, entry),
+                };
+                match (fed_id, parse_egress_rate(rate.trim())) {
+                    (None, Some(rate)) => rti.set_default_egress_rate(rate),
+                    (Some(Ok(fed_id)), Some(rate)) => rti.set_egress_rate(fed_id, rate),
+                    _ => {
+                        error!("--egress_rate needs a comma-separated list of [<id>=]<bytes per second>[:<burst bytes>].");
+                        usage(argc, argv);
+                        return Err("Fail to handle egress_rate option");
+                    }
+                }
+            }
+        } else if arg == "--on_excess_egress" {
+            if argc < idx + 2 {
+                error!("--on_excess_egress needs queue|drop.");
+                usage(argc, argv);
+                return Err("Fail to handle on_excess_egress option");
+            }
+            idx += 1;
+            match argv[idx].as_str() {
+                "queue" => rti.set_excess_egress_policy(ExcessEgressPolicy::Queue),
+                "drop" => rti.set_excess_egress_policy(ExcessEgressPolicy::Drop),
+                _ => {
+                    error!("--on_excess_egress needs queue|drop.");
+                    usage(argc, argv);
+                    return Err("Fail to handle on_excess_egress option");
+                }
+            }
+        } else if arg == "--memory_budget" {
+            if argc < idx + 2 {
+                error!("--memory_budget needs a comma-separated list of in_transit|trace|history=<KiB>.");
+                usage(argc, argv);
+                return Err("Fail to handle memory_budget option");
+            }
+            idx += 1;
+            for entry in argv[idx].split(',') {
+                let budget = entry.split_once('=').and_then(|(kind, kib)| {
+                    let bytes = kib.trim().parse::<usize>().ok()?.checked_mul(1024)?;
+                    Some((kind.trim(), bytes))
+                });
+                match budget {
+                    Some(("in_transit", bytes)) => rti.set_in_transit_budget(Some(Arc::new(
+                        MemoryBudget::new("in-transit records", bytes),
+                    ))),
+                    Some(("trace", bytes)) => rti
+                        .set_trace_budget(Some(Arc::new(MemoryBudget::new("trace buffer", bytes)))),
+                    Some(("history", bytes)) => rti.set_history_budget(Some(Arc::new(
+                        MemoryBudget::new("scheduling histories", bytes),
+                    ))),
+                    _ => {
+                        error!("--memory_budget needs a comma-separated list of in_transit|trace|history=<KiB>.");
+                        usage(argc, argv);
+                        return Err("Fail to handle memory_budget option");
+                    }
+                }
+            }
+        } else if arg == "--microstep_warning_threshold" {
+            if argc < idx + 2 {
+                error!("--microstep_warning_threshold needs an integer argument.");
+                usage(argc, argv);
+                return Err("Fail to handle microstep_warning_threshold option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u32>() {
+                Ok(parsed_value) => {
+                    rti.set_microstep_warning_threshold(parsed_value);
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u32");
+                }
+            }
+        } else if arg == "--admin_port" {
+            if argc < idx + 2 {
+                error!(
+                    "--admin_port needs a short unsigned integer argument ( > 0 and < {}).",
+                    u16::MAX
+                );
+                usage(argc, argv);
+                return Err("Fail to handle admin_port option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u16>() {
+                Ok(parsed_value) if parsed_value > 0 && parsed_value < u16::MAX => {
+                    rti.set_admin_port(parsed_value);
+                }
+                Ok(_) => {
+                    error!(
+                        "--admin_port needs a short unsigned integer argument ( > 0 and < {}).",
+                        u16::MAX
+                    );
+                    usage(argc, argv);
+                    return Err("Fail to handle admin_port option");
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u16");
+                }
+            }
+        } else if arg == "--status_port" {
+            if argc < idx + 2 {
+                error!(
+                    "--status_port needs a short unsigned integer argument ( > 0 and < {}).",
+                    u16::MAX
+                );
+                usage(argc, argv);
+                return Err("Fail to handle status_port option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u16>() {
+                Ok(parsed_value) if parsed_value > 0 && parsed_value < u16::MAX => {
+                    rti.set_status_port(parsed_value);
+                }
+                Ok(_) => {
+                    error!(
+                        "--status_port needs a short unsigned integer argument ( > 0 and < {}).",
+                        u16::MAX
+                    );
+                    usage(argc, argv);
+                    return Err("Fail to handle status_port option");
+                }
+                Err(_e) => {
+                    return Err("Fail to parse a string to u16");
+                }
+            }
+        } else if arg == "--admin_token_file" || arg == "--admin_read_token_file" {
+            if argc < idx + 2 {
+                error!("{} needs a file path.", arg);
+                usage(argc, argv);
+                return Err("Fail to handle admin token options");
+            }
+            idx += 1;
+            match admin::read_token(&argv[idx]) {
+                Ok(token) if arg == "--admin_token_file" => rti.set_admin_token(token),
+                Ok(token) => rti.set_admin_read_token(token),
+                Err(e) => {
+                    error!("{} failed to read a token from {}: {}.", arg, argv[idx], e);
+                    return Err("Fail to read an admin token");
+                }
+            }
+        } else if arg == "--parent_rti" {
+            if argc < idx + 2 {
+                error!("--parent_rti needs the address of the parent RTI as <host>:<port>.");
+                usage(argc, argv);
+                return Err("Fail to handle parent_rti option");
+            }
+            idx += 1;
+            info!(
+                "RTI: Experimental: Coordinating with the parent RTI at {}.",
+                argv[idx]
+            );
+            rti.set_parent_address(argv[idx].clone());
+        } else if arg == "--parent_id" {
+            if argc < idx + 2 {
+                error!("--parent_id needs a federate ID.");
+                usage(argc, argv);
+                return Err("Fail to handle parent_id option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u16>() {
+                Ok(parsed_value) => rti.set_parent_id(parsed_value),
+                Err(_e) => {
+                    error!("--parent_id needs a federate ID.");
+                    usage(argc, argv);
+                    return Err("Fail to handle parent_id option");
+                }
+            }
+        } else if arg == "--parent_upstream" {
+            if argc < idx + 2 {
+                error!("--parent_upstream needs a comma-separated list of federate IDs, each optionally followed by :<delay>.");
+                usage(argc, argv);
+                return Err("Fail to handle parent_upstream option");
+            }
+            idx += 1;
+            let parsed: Result<Vec<(u16, Option<i64>)>, std::num::ParseIntError> = argv[idx]
+                .split(',')
+                .map(|upstream| match upstream.trim().split_once(':') {
+                    Some((id, delay)) => Ok((id.parse()?, Some(delay.parse()?))),
+                    None => Ok((upstream.trim().parse()?, None)),
+                })
+                .collect();
+            match parsed {
+                Ok(parsed_value)
+                    if parsed_value
+                        .iter()
+                        .all(|(_, delay)| delay.is_none_or(|d| d >= 0)) =>
+                {
+                    parent_upstream = parsed_value;
+                }
+                _ => {
+                    error!("--parent_upstream needs a comma-separated list of federate IDs, each optionally followed by :<delay>.");
+                    usage(argc, argv);
+                    return Err("Fail to handle parent_upstream option");
+                }
+            }
+        } else if arg == "--parent_downstream" {
+            if argc < idx + 2 {
+                error!("--parent_downstream needs a comma-separated list of federate IDs.");
+                usage(argc, argv);
+                return Err("Fail to handle parent_downstream option");
+            }
+            idx += 1;
+            let parsed: Result<Vec<u16>, _> = argv[idx]
+                .split(',')
+                .map(|id| id.trim().parse::<u16>())
+                .collect();
+            match parsed {
+                Ok(fed_ids) => rti.set_parent_downstream(fed_ids),
+                Err(_e) => {
+                    error!("--parent_downstream needs a comma-separated list of federate IDs.");
+                    usage(argc, argv);
+                    return Err("Fail to handle parent_downstream option");
+                }
+            }
+        } else if arg == "--lock_stats" {
+            rti.enable_lock_stats();
+        } else if arg == "--no_grant_cache" {
+            rti.disable_grant_cache();
+        } else if arg == "--step" {
+            info!("RTI: Step mode. Each TAG and PTAG is sent only once released.");
+            rti.set_step_mode(true);
+        } else if arg == "--run_db" {
+            if !cfg!(feature = "run_db") {
+                error!("--run_db requires the RTI to be built with the run_db feature (cargo build --features run_db).");
+                usage(argc, argv);
+                return Err("Fail to handle run_db option");
+            }
+            if argc < idx + 2 {
+                error!("--run_db needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle run_db option");
+            }
+            idx += 1;
+            rti.set_run_db(argv[idx].clone());
+        } else if arg == "--hold" {
+            info!("RTI: Hold mode. The start time is sent only once released.");
+            rti.set_start_held(true);
+        } else if arg == "--topology_file" {
+            if argc < idx + 2 {
+                error!("--topology_file needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle topology_file option");
+            }
+            idx += 1;
+            rti.set_topology_file(argv[idx].clone());
+        } else if arg == "--snapshot_file" {
+            if argc < idx + 2 {
+                error!("--snapshot_file needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle snapshot_file option");
+            }
+            idx += 1;
+            rti.set_snapshot_file(argv[idx].clone());
+        } else if arg == "--checkpoint_period" {
+            if argc < idx + 2 {
+                error!("--checkpoint_period needs an integer argument (in milliseconds).");
+                usage(argc, argv);
+                return Err("Fail to handle checkpoint_period option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => {
+                    rti.set_checkpoint_period_ms(parsed_value);
+                }
+                Err(_e) => {
+                    error!("--checkpoint_period needs an integer argument (in milliseconds).");
+                    usage(argc, argv);
+                    return Err("Fail to handle checkpoint_period option");
+                }
+            }
+        } else if arg == "--restore_snapshot" {
+            if argc < idx + 2 {
+                error!("--restore_snapshot needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle restore_snapshot option");
+            }
+            idx += 1;
+            restore_snapshot = Some(argv[idx].clone());
+        } else if arg == "--federation_metadata" {
+            if argc < idx + 2 {
+                error!("--federation_metadata needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle federation_metadata option");
+            }
+            idx += 1;
+            federation_metadata = Some(argv[idx].clone());
+        } else if arg == "--wire_dump" {
+            if argc < idx + 2 {
+                error!("--wire_dump needs a comma-separated list of federate IDs or names.");
+                usage(argc, argv);
+                return Err("Fail to handle wire_dump option");
+            }
+            idx += 1;
+            rti.set_wire_dump_federates(
+                argv[idx]
+                    .split(',')
+                    .map(|federate| federate.trim().to_string())
+                    .collect(),
+            );
+        } else if arg == "--wire_dump_dir" {
+            if argc < idx + 2 {
+                error!("--wire_dump_dir needs a directory path.");
+                usage(argc, argv);
+                return Err("Fail to handle wire_dump_dir option");
+            }
+            idx += 1;
+            rti.set_wire_dump_dir(argv[idx].clone());
+        } else if arg == "--fault_rules" {
+            if argc < idx + 2 {
+                error!("--fault_rules needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle fault_rules option");
+            }
+            idx += 1;
+            fault_rules = Some(argv[idx].clone());
+        } else if arg == "--fault_seed" {
+            if argc < idx + 2 {
+                error!("--fault_seed needs an unsigned integer argument.");
+                usage(argc, argv);
+                return Err("Fail to handle fault_seed option");
+            }
+            idx += 1;
+            match argv[idx].parse::<u64>() {
+                Ok(parsed_value) => fault_seed = Some(parsed_value),
+                Err(_) => {
+                    error!("--fault_seed needs an unsigned integer argument.");
+                    usage(argc, argv);
+                    return Err("Fail to handle fault_seed option");
+                }
+            }
+        } else if arg == "--event_stream" {
+            if argc < idx + 2 {
+                error!("--event_stream needs a file path or fd:<n> for a file descriptor.");
+                usage(argc, argv);
+                return Err("Fail to handle event_stream option");
+            }
+            idx += 1;
+            event_stream = Some(argv[idx].clone());
+        } else if arg == "--output_key_file" {
+            if argc < idx + 2 {
+                error!("--output_key_file needs a file path.");
+                usage(argc, argv);
+                return Err("Fail to handle output_key_file option");
+            }
+            if !cfg!(feature = "encryption") {
+                error!(
+                    "--output_key_file requires the RTI to be built with the encryption feature."
+                );
+                usage(argc, argv);
+                return Err("Fail to handle output_key_file option");
+            }
+            idx += 1;
+            match OutputKey::read(&argv[idx]) {
+                Ok(output_key) => rti.set_output_key(output_key),
+                Err(e) => {
+                    error!(
+                        "--output_key_file failed to read a key from {}: {}.",
+                        argv[idx], e
+                    );
+                    return Err("Fail to read the output key");
+                }
+            }
+        } else if arg == "--tls_cert" || arg == "--tls_key" {
+            if argc < idx + 2 {
+                error!("{} needs a file path.", arg);
+                usage(argc, argv);
+                return Err("Fail to handle TLS options");
+            }
+            if !cfg!(feature = "tls") {
+                error!("{} requires the RTI to be built with the tls feature.", arg);
+                usage(argc, argv);
+                return Err("Fail to handle TLS options");
+            }
+            idx += 1;
+            if arg == "--tls_cert" {
+                tls_cert = Some(argv[idx].clone());
+            } else {
+                tls_key = Some(argv[idx].clone());
+            }
+        } else if arg == "--decentralized" {
+            if argc < idx + 2 {
+                error!("--decentralized needs a comma-separated list of federate IDs.");
+                usage(argc, argv);
+                return Err("Fail to handle decentralized option");
+            }
+            idx += 1;
+            let parsed: Result<Vec<u16>, _> = argv[idx]
+                .split(',')
+                .map(|id| id.trim().parse::<u16>())
+                .collect();
+            match parsed {
+                Ok(fed_ids) => {
+                    info!(
+                        "RTI: Federates using decentralized coordination: {:?}",
+                        fed_ids
+                    );
+                    rti.set_decentralized_federates(fed_ids);
+                }
+                Err(_e) => {
+                    error!("--decentralized needs a comma-separated list of federate IDs.");
+                    usage(argc, argv);
+                    return Err("Fail to handle decentralized option");
+                }
+            }
+        } else if arg == "--transient" {
+            if argc < idx + 2 {
+                error!("--transient needs a comma-separated list of federate IDs.");
+                usage(argc, argv);
+                return Err("Fail to handle transient option");
+            }
+            idx += 1;
+            let parsed: Result<Vec<u16>, _> = argv[idx]
+                .split(',')
+                .map(|id| id.trim().parse::<u16>())
+                .collect();
+            match parsed {
+                Ok(mut fed_ids) => {
+                    fed_ids.sort_unstable();
+                    fed_ids.dedup();
+                    info!("RTI: Transient federates: {:?}", fed_ids);
+                    rti.set_transient_federates(fed_ids);
+                }
+                Err(_e) => {
+                    error!("--transient needs a comma-separated list of federate IDs.");
+                    usage(argc, argv);
+                    return Err("Fail to handle transient option");
+                }
+            }
+        } else if arg == " " {
+            // Tolerate spaces
+            continue;
+        } else {
+            error!("Unrecognized command-line argument: {}", arg);
+            usage(argc, argv);
+            return Err("Invalid argument");
+        }
+        idx += 1;
+    }
+    if rti.number_of_enclaves() == 0 {
+        error!("--number_of_federates needs a valid positive integer argument.");
+        usage(argc, argv);
+        return Err("Invalid number of enclaves");
+    }
+    if rti
+        .decentralized_federates()
+        .iter()
+        .any(|fed_id| i32::from(*fed_id) >= rti.number_of_enclaves())
+    {
+        error!("--decentralized needs federate IDs smaller than the number of federates.");
+        usage(argc, argv);
+        return Err("Invalid decentralized federate ID");
+    }
+    if rti
+        .transient_federates()
+        .iter()
+        .any(|fed_id| i32::from(*fed_id) >= rti.number_of_enclaves())
+    {
+        error!("--transient needs federate IDs smaller than the number of federates.");
+        usage(argc, argv);
+        return Err("Invalid transient federate ID");
+    }
+    if rti
+        .grant_write_policies()
+        .keys()
+        .any(|fed_id| i32::from(*fed_id) >= rti.number_of_enclaves())
+    {
+        error!("--grant_write_policy needs federate IDs smaller than the number of federates.");
+        usage(argc, argv);
+        return Err("Invalid grant write policy federate ID");
+    }
+    if rti
+        .egress_rates()
+        .keys()
+        .any(|fed_id| i32::from(*fed_id) >= rti.number_of_enclaves())
+    {
+        error!("--egress_rate needs federate IDs smaller than the number of federates.");
+        usage(argc, argv);
+        return Err("Invalid egress rate federate ID");
+    }
+    if rti.number_of_persistent_federates() == 0 {
+        error!("--transient needs at least one federate that is not transient.");
+        usage(argc, argv);
+        return Err("Invalid transient federate ID");
+    }
+    if let Some(stop_time) = stop_time {
+        match rti.time_resolution().to_nanoseconds(stop_time) {
+            Some(stop_time_ns) => rti.set_stop_time(stop_time_ns),
+            None => {
+                error!("--stop_time is too large for the time resolution.");
+                usage(argc, argv);
+                return Err("Fail to handle stop_time option");
+            }
+        }
+    }
+    if let Some(max_lead) = max_lead {
+        match rti.time_resolution().to_nanoseconds(max_lead) {
+            Some(max_lead_ns) => rti.set_max_lead(Some(max_lead_ns)),
+            None => {
+                error!("--max_lead is too large for the time resolution.");
+                usage(argc, argv);
+                return Err("Fail to handle max_lead option");
+            }
+        }
+    }
+    match (tls_cert, tls_key) {
+        (Some(tls_cert), Some(tls_key)) => match TlsConfig::load(&tls_cert, &tls_key) {
+            Ok(tls_config) => {
+                info!("RTI: Federates must connect over TLS.");
+                rti.set_tls_config(tls_config);
+            }
+            Err(e) => {
+                error!("RTI failed to load the TLS certificate and key: {}.", e);
+                return Err("Fail to load the TLS certificate and key");
+            }
+        },
+        (None, None) => {}
+        _ => {
+            error!("--tls_cert and --tls_key must be given together.");
+            usage(argc, argv);
+            return Err("Fail to handle TLS options");
+        }
+    }
+    if let Some(max_lag) = max_lag {
+        match rti.time_resolution().to_nanoseconds(max_lag) {
+            Some(max_lag_ns) => rti.set_max_lag(Some(max_lag_ns)),
+            None => {
+                error!("--max_lag is too large for the time resolution.");
+                usage(argc, argv);
+                return Err("Fail to handle max_lag option");
+            }
+        }
+    }
+    if let Some(lookahead) = lookahead {
+        match rti.time_resolution().to_nanoseconds(lookahead) {
+            Some(lookahead_ns) => rti.set_lookahead(Some(lookahead_ns)),
+            None => {
+                error!("--lookahead is too large for the time resolution.");
+                usage(argc, argv);
+                return Err("Fail to handle lookahead option");
+            }
+        }
+    }
+    if rti.parent_address().is_none()
+        && (!parent_upstream.is_empty() || !rti.parent_downstream().is_empty())
+    {
+        error!("--parent_upstream and --parent_downstream need --parent_rti.");
+        usage(argc, argv);
+        return Err("Parent neighbors without parent RTI");
+    }
+    let mut parent_upstream_ns = Vec::new();
+    for (fed_id, delay) in parent_upstream {
+        match delay.map(|delay| rti.time_resolution().to_nanoseconds(delay)) {
+            None => parent_upstream_ns.push((fed_id, NO_DELAY)),
+            Some(Some(delay_ns)) => parent_upstream_ns.push((fed_id, Some(delay_ns))),
+            Some(None) => {
+                error!("--parent_upstream has a delay that is too large for the time resolution.");
+                usage(argc, argv);
+                return Err("Fail to handle parent_upstream option");
+            }
+        }
+    }
+    rti.set_parent_upstream(parent_upstream_ns);
+    if let Some(path) = fault_rules {
+        let rules = match std::fs::read_to_string(&path) {
+            Ok(rules) => rules,
+            Err(e) => {
+                error!("--fault_rules failed to read {}: {}.", path, e);
+                return Err("Fail to read the fault rules");
+            }
+        };
+        // Without a given seed, pick one that is printed so that the run can be reproduced.
+        let seed = fault_seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_nanos() as u64)
+        });
+        match FaultInjector::parse(&rules, rti.time_resolution(), seed) {
+            Ok(fault_injector) => {
+                info!(
+                    "RTI: Injecting faults with {} rule(s) from {} and seed {}.",
+                    fault_injector.number_of_rules(),
+                    path,
+                    fault_injector.seed()
+                );
+                rti.set_fault_injector(fault_injector);
+            }
+            Err(e) => {
+                error!("--fault_rules has an invalid rule in {}: {}.", path, e);
+                return Err("Invalid fault rule");
+            }
+        }
+    } else if fault_seed.is_some() {
+        error!("--fault_seed needs --fault_rules.");
+        usage(argc, argv);
+        return Err("Fault seed without fault rules");
+    }
+    if let Some(path) = federation_metadata {
+        let metadata = match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| FederationMetadata::parse(&text))
+        {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                error!("--federation_metadata failed to load {}: {}.", path, e);
+                return Err("Fail to load the federation metadata");
+            }
+        };
+        let diff = metadata.diff_arguments(
+            &rti.federation_id(),
+            usize::try_from(rti.number_of_enclaves()).unwrap_or(0),
+        );
+        if !diff.is_empty() {
+            error!(
+                "RTI: The arguments do not match the federation metadata in {}:\n{}",
+                path,
+                diff.join("\n")
+            );
+            return Err("Arguments that do not match the federation metadata");
+        }
+        info!(
+            "RTI: Checking the federates against the federation metadata in {}.",
+            path
+        );
+        for (fed_id, name) in metadata.groups() {
+            group_members.push((String::from(name), vec![fed_id]));
+        }
+        rti.set_federation_metadata(metadata);
+    }
+    for (name, fed_ids) in group_members {
+        for fed_id in fed_ids {
+            if i32::from(fed_id) >= rti.number_of_enclaves() {
+                error!(
+                    "--group {} has federate {}, but there are only {} federates.",
+                    name,
+                    fed_id,
+                    rti.number_of_enclaves()
+                );
+                usage(argc, argv);
+                return Err("Fail to handle group option");
+            }
+            if let Err(e) = rti.add_to_group(&name, fed_id) {
+                error!("--group failed: {}.", e);
+                usage(argc, argv);
+                return Err("Fail to handle group option");
+            }
+        }
+    }
+    for (name, policies) in group_policies {
+        let result = GroupPolicy::parse(&policies, rti.time_resolution())
+            .and_then(|policy| rti.set_group_policy(&name, &policy));
+        if let Err(e) = result {
+            error!("--group_policy {} failed: {}.", name, e);
+            usage(argc, argv);
+            return Err("Fail to handle group_policy option");
+        }
+    }
+    for group in rti.groups() {
+        info!(
+            "RTI: Group {} of federates {:?} with {}.",
+            group.name(),
+            group.members(),
+            group.policy()
+        );
+    }
+    if rti.checkpoint_period_ms() > 0 && rti.snapshot_file().is_none() {
+        error!("--checkpoint_period needs --snapshot_file.");
+        usage(argc, argv);
+        return Err("Checkpoint period without snapshot file");
+    }
+    if let Some(path) = restore_snapshot {
+        let snapshot = match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Snapshot::parse(&text))
+        {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                error!("--restore_snapshot failed to load {}: {}.", path, e);
+                return Err("Fail to load the snapshot to restore");
+            }
+        };
+        let number_of_federates = usize::try_from(rti.number_of_enclaves()).unwrap_or(0);
+        if let Err(e) = snapshot.check_restorable(&rti.federation_id(), number_of_federates) {
+            error!("--restore_snapshot cannot restore {} because {}.", path, e);
+            return Err("Snapshot that cannot be restored");
+        }
+        if rti.parent_address().is_some() {
+            error!("--restore_snapshot cannot be combined with --parent_rti, which decides the start time.");
+            usage(argc, argv);
+            return Err("Restored snapshot with a parent RTI");
+        }
+        info!(
+            "RTI: Restarting the federation from the snapshot in {}.",
+            path
+        );
+        rti.set_restored_snapshot(snapshot);
+    }
+    if let Some(target) = event_stream {
+        match EventStream::open(&target, rti.output_key().as_ref()) {
+            Ok(event_stream) => {
+                info!("RTI: Writing scheduling events to {}.", target);
+                rti.set_event_stream(event_stream);
+            }
+            Err(e) => {
+                error!("--event_stream failed to open {}: {}.", target, e);
+                return Err("Fail to open the event stream");
+            }
+        }
+    }
+    if rti.output_key().is_some() {
+        info!("RTI: Encrypting the trace, event stream, wire dump, and log files.");
+    }
+    if rti.log_file().is_some() && rti.log_sink() != LogSink::Stdout {
+        error!(
+            "--log_file cannot be combined with --log_sink {}.",
+            rti.log_sink().name()
+        );
+        usage(argc, argv);
+        return Err("Log file with another log sink");
+    }
+    if rti.step_mode() && rti.admin_port().is_none() {
+        error!("--step needs --admin_port to release the grants.");
+        usage(argc, argv);
+        return Err("Step mode without admin console");
+    }
+    if rti.start_held() && rti.admin_port().is_none() {
+        error!("--hold needs --admin_port to release the start time.");
+        usage(argc, argv);
+        return Err("Hold mode without admin console");
+    }
+    if rti.fast_mode() && rti.clock_sync_global_status() != ClockSyncStat::ClockSyncOff {
+        // Physical clocks are irrelevant when no federate waits for physical time.
+        info!("RTI: Clock synchronization is disabled because the federation runs in fast mode.");
+        rti.set_clock_sync_global_status(ClockSyncStat::ClockSyncOff);
+    }
+    if rti.fast_mode() && rti.max_lead().is_some() {
+        warn!("RTI: --max_lead is ignored because the federation runs in fast mode.");
+        rti.set_max_lead(None);
+    }
+    // Apply the log level to the lines printed before the server starts.
+    trace::configure(rti.log_level(), rti.trace_lines_per_second());
+    Ok(())
+}
+
+/**
+ * Parse a policy of --grant_write_policy: block, buffer:<n>, timeout:<ms>, or
+ * supersede. Return None if it is invalid.
+ */
+fn parse_grant_write_policy(policy: &str) -> Option<GrantWritePolicy> {
+    match policy.split_once(':') {
+        None if policy == "block" => Some(GrantWritePolicy::Block),
+        None if policy == "supersede" => Some(GrantWritePolicy::Supersede),
+        Some(("buffer", max_grants)) => match max_grants.parse::<usize>() {
+            Ok(max_grants) if max_grants > 0 => Some(GrantWritePolicy::Buffer(max_grants)),
+            _ => None,
+        },
+        Some(("timeout", ms)) => match ms.parse::<u64>() {
+            Ok(ms) if ms > 0 => Some(GrantWritePolicy::Timeout(ms)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn parse_egress_rate(rate: &str) -> Option<EgressRate> {
+    let (bytes_per_second, burst_bytes) = match rate.split_once(':') {
+        Some((bytes_per_second, burst_bytes)) => (bytes_per_second, Some(burst_bytes)),
+        None => (rate, None),
+    };
+    let bytes_per_second = bytes_per_second
+        .parse::<u64>()
+        .ok()
+        .filter(|rate| *rate > 0)?;
+    let burst_bytes = match burst_bytes {
+        Some(burst_bytes) => Some(burst_bytes.parse::<u64>().ok().filter(|burst| *burst > 0)?),
+        None => None,
+    };
+    Some(EgressRate::new(bytes_per_second, burst_bytes))
+}
+
+fn usage(argc: usize, argv: &[String]) {
+    // The options that the RTI of the C runtime also has, described as there.
+    println!("\nCommand-line arguments: \n");
+    println!("  -i, --id <n>");
+    println!("   The ID of the federation that this RTI will control.\n");
+    println!("  -n, --number_of_federates <n>");
+    println!("   The number of federates in the federation that this RTI will control.\n");
+    println!("  -p, --port <n>");
+    println!("   The port number to use for the RTI. Must be larger than 0 and smaller than {}. Default is {}.\n", u16::MAX, STARTING_PORT);
+    println!("  -c, --clock_sync [off|init|on] [period <n>] [exchanges-per-interval <n>]");
+    println!("   The status of clock synchronization for this federate.");
+    println!("       - off: Clock synchronization is off.");
+    println!("       - init (default): Clock synchronization is done only during startup.");
+    println!(
+        "       - on: Clock synchronization is done both at startup and during the execution."
+    );
+    println!("   Relevant parameters that can be set: ");
+    println!("       - period <n>(in nanoseconds): Controls how often a clock synchronization attempt is made");
+    println!("          (period in nanoseconds, default is 10 msec). Only applies to 'on'.");
+    println!("       - exchanges-per-interval <n>: Controls the number of messages that are exchanged for each");
+    println!("          clock sync attempt (default is 10). Applies to 'init' and 'on'.\n");
+    println!("  -a, --auth Turn on HMAC authentication options.\n");
+    println!("  -t, --tracing Turn on tracing.\n");
+    println!("  -d, --disable_dnet Turn off the use of DNET signals.\n");
+    println!("Options of this RTI only:");
+    println!("  --timeout <n>");
+    println!(
+        "   Shut down, notifying the connected federates, if not all federates have connected"
+    );
+    println!("   within n seconds. Default is 0 (wait forever).");
+    println!("  --trace_file <path>");
+    println!(
+        "   Turn on tracing into the given file instead of {}. Summarize a trace with:",
+        DEFAULT_TRACE_FILE
+    );
+    println!("   rti trace_summary <file>.");
+    println!("  --fast");
+    println!(
+        "   Declare that the whole federation runs in fast mode (no waiting for physical time)."
+    );
+    println!("   Clock synchronization and other physical-clock-based checks are disabled.");
+    println!("   Federates that offer the fast_mode capability are told to run in fast mode;");
+    println!("   the others must be started in fast mode on their own.");
+    println!("  --stop_time <n>");
+    println!(
+        "   The expected stop time of the federation in units of the time resolution relative"
+    );
+    println!("   to the start time (e.g., the timeout of the program). Used only for progress");
+    println!("   reporting.");
+    println!("  --max_lead <n>");
+    println!("   Never grant a tag that is more than n units of the time resolution ahead of the");
+    println!("   physical time of the RTI. Such grants are held back until physical time catches");
+    println!("   up, which spaces the grants of realtime federates, e.g., in hardware-in-the-loop");
+    println!("   setups, instead of letting them run ahead in bursts. Ignored in fast mode.");
+    println!("  --lookahead <n>");
+    println!(
+        "   Never grant a tag that is more than n units of the time resolution of logical time"
+    );
+    println!("   ahead of the earliest tag completed by the connected federates. Such grants are");
+    println!("   held back until the slowest federate catches up, which bounds the events that");
+    println!("   federates buffer for the far future. n must be positive.");
+    println!("  --max_lag <n>");
+    println!("   Warn, stamped with the earliest granted tag, once the physical time of the RTI");
+    println!("   is more than n units of the time resolution ahead of the earliest tag granted");
+    println!("   to the connected federates, naming the slowest federate upstream of the one");
+    println!("   lagging behind. The warning is also written to the event stream (see");
+    println!("   --event_stream) and repeated only after the lag has recovered. n must be");
+    println!("   positive.");
+    println!("  --silent_grace <n>");
+    println!("   Presume that a federate that has started but sent no NET within n milliseconds,");
+    println!("   e.g., because it deadlocked internally, has a NET of FOREVER, so that the");
+    println!("   federates downstream of it can proceed. A NET that it sends later is used again.");
+    println!("   Silent federates are reported by the eimt command of the admin console and by");
+    println!("   the status server either way. Default is 0 (disabled).");
+    println!("  --resume_window <n>");
+    println!("   Hold the state of a federate whose connection is lost for n milliseconds, so");
+    println!("   that it can resume its session by connecting again with the resumption token");
+    println!("   that the RTI issued to it, e.g., after a brief network outage. The federates");
+    println!("   downstream of it wait for it meanwhile. Only federates that offer the");
+    println!("   resumption capability get a token. Messages relayed to the federate while");
+    println!("   its connection is lost are dropped. Requires the auth feature. Default is 0");
+    println!("   (disabled).");
+    println!("  --dynamic_ids");
+    println!(
+        "   Let federates join with the federate ID {} and have the RTI assign the",
+        u16::MAX
+    );
+    println!("   lowest free ID, which is returned after the MsgType::Ack of the handshake.");
+    println!("   Useful for identical worker federates whose number varies between runs.");
+    println!("  --clock_source [monotonic|realtime|tai]");
+    println!("   The clock from which the RTI reads physical time for clock synchronization and");
+    println!("   statistics such as the progress ETA.");
+    println!("       - monotonic (default): Anchored to the realtime clock at startup. Never");
+    println!("         stepped or smeared by leap seconds, but may drift from the wall clock.");
+    println!("       - realtime: The wall clock. Steps and smears, e.g., by leap seconds, are");
+    println!("         detected and reported because they skew measurements.");
+    println!("       - tai: International Atomic Time, which has no leap seconds (64-bit Linux).");
+    println!("  --time_resolution [ns|us|ms]");
+    println!("   The resolution of logical time in the federation. Times are still encoded in");
+    println!("   nanoseconds on the wire, but tags and delays that are not multiples of the");
+    println!("   resolution are reported as protocol violations. Times printed by the RTI and");
+    println!("   given to it by users are in this unit. Default is ns.");
+    println!("  --time_format [elapsed|absolute|raw]");
+    println!("   How the RTI prints times, e.g., of tags, in its output and in the admin console.");
+    println!("       - elapsed (default): The time since the start time, in the time resolution.");
+    println!("       - absolute: The date and time in UTC, e.g., 2023-05-01T12:00:00.000000000Z.");
+    println!("       - raw: The nanoseconds since the epoch of the clock, as on the wire.");
+    println!("   Times given to the RTI by users are still relative to the start time.");
+    println!("  --log_timestamps");
+    println!("   Start each line printed by the RTI with the current time in the time format.");
+    println!("   Elapsed timestamps are - until the start time is known.");
+    println!("  --progress_period <n>");
+    println!("   Report the progress toward the stop tag every n milliseconds, if the stop tag is known.");
+    println!("   Default is 0 (disabled).");
+    println!("  --handshake_timeout <n>");
+    println!(
+        "   Close a new connection that has not completed its handshake within n milliseconds,"
+    );
+    println!(
+        "   so that it does not occupy an I/O thread. Default is {}. 0 disables the deadline.",
+        DEFAULT_HANDSHAKE_TIMEOUT_MS
+    );
+    println!("  --keepalive <n>");
+    println!("   Probe a federate connection that has been idle for n seconds, and handle the");
+    println!(
+        "   federate as failed if {} probes, n seconds apart, are not answered, e.g., because",
+        KEEPALIVE_PROBES
+    );
+    println!("   its host crashed. Default is 0 (disabled).");
+    println!("  --history_length <n>");
+    println!("   The number of recent scheduling events of each federate that the admin console");
+    println!(
+        "   shows with the history command. Default is {}. 0 disables the history.",
+        DEFAULT_HISTORY_LENGTH
+    );
+    println!("  --memory_budget <kind>=<n>[,<kind>=<n>...]");
+    println!("   Bound the memory of bookkeeping that grows with the traffic of the federates");
+    println!("   to n KiB, shared by all federates, so that the worst-case memory use does not");
+    println!("   depend on bursts. The usage and the evictions are reported at the end of the");
+    println!("   run and by the memory command of the admin console.");
+    println!("       - in_transit: Records of the messages in transit to federates. Beyond the");
+    println!("         budget, the records of a federate are summarized by their earliest and");
+    println!("         latest tags, which may delay its grants until it completes the latest.");
+    println!("       - trace: Records buffered for the trace file (see --tracing). Beyond the");
+    println!("         budget, records are dropped until the buffer has been written.");
+    println!("       - history: Entries of the histories (see --history_length). Beyond the");
+    println!("         budget, the oldest entry of the longest history is evicted.");
+    println!("  --on_failure [abort|degrade]");
+    println!("   What to do when a federate fails or disconnects without resigning.");
+    println!("       - abort: Request all other federates to stop as soon as possible.");
+    println!("       - degrade (default): Treat the failed federate as resigned and continue.");
+    println!("  --group <name>=<id>[,<id>...]");
+    println!("   Assign the federates with the given IDs to the named group, e.g., sensors=0,1,2.");
+    println!("   A federate is in at most one group. May be given once per group.");
+    println!("  --group_policy <name>:<policy>=<value>[,<policy>=<value>...]");
+    println!("   Override the policies of the RTI for the federates of the named group:");
+    println!("       - max_control_rate=<n>: Like --max_control_rate, where 0 means no limit.");
+    println!("       - lookahead=<n>: Like --lookahead, bounding the grants to the group.");
+    println!("       - log_level=<level>: Like --log_level, for the lines about the messages");
+    println!("         exchanged with the federates of the group and their grants.");
+    println!("       - on_failure=abort|degrade: Like --on_failure.");
+    println!("  --early_net [clamp|reject]");
+    println!("   What to do when a federate sends a NET earlier than the start time.");
+    println!("       - clamp (default): Warn and use the start tag as the NET instead.");
+    println!("       - reject: Reject the federate and handle it as a failed federate.");
+    println!("  --quarantine");
+    println!(
+        "   Disconnect federates that violate the protocol (e.g., by sending a tag earlier than"
+    );
+    println!("   the start time). By default, such messages are only reported and then ignored.");
+    println!("  --strict");
+    println!(
+        "   Shut down the RTI on the conditions that are otherwise reported and tolerated, such as"
+    );
+    println!("   unexpected or duplicate messages, tags earlier than the start time, and neighbor");
+    println!("   structures on which federates disagree. Useful in continuous integration.");
+    println!("  --io_threads <n>");
+    println!("   The number of threads that perform handshakes with connecting federates.");
+    println!(
+        "   Default is the number of CPUs ({}). More threads let many federates join faster,",
+        number_of_cpus()
+    );
+    println!("   but are idle once all federates have joined.");
+    println!("  --scheduling_threads <n>");
+    println!("   The number of threads that handle messages from federates and compute grants.");
+    println!(
+        "   Default is the number of CPUs ({}). With fewer threads than federates, each thread",
+        number_of_cpus()
+    );
+    println!(
+        "   serves several federates in turn, which saves memory but delays a message from one"
+    );
+    println!("   federate while a message from another one is handled. Messages that let grants");
+    println!("   advance (LTC, stop, and resign messages) are handled first, then NET and other");
+    println!("   control messages, then forwarded messages. At most one thread per federate is");
+    println!("   used. The federates of independent groups, i.e., weakly connected components,");
+    println!("   are served by different threads where possible, and their grants are computed");
+    println!("   under separate locks, so that unrelated pipelines do not wait for each other.");
+    println!("   The groups are learned from the federates as they connect, unless");
+    println!("   --federation_metadata tells them in advance, in which case threads are shared");
+    println!("   out among the groups at once rather than kept for groups that may still connect.");
+    println!("  --io_cpus <cpus>");
+    println!("  --scheduling_cpus <cpus>");
+    println!("   Pin the I/O or scheduling threads to the CPUs in the given list, such as 0,2-3,");
+    println!("   in turn. By default, threads run on any CPU.");
+    println!("  --realtime_priority <n>");
+    println!("   Run the I/O and scheduling threads with the SCHED_FIFO policy at priority n");
+    println!("   (1 to 99) to reduce the jitter of grants. This usually needs the CAP_SYS_NICE");
+    println!("   capability. If it is not permitted, the RTI warns and uses the default policy.");
+    println!("  --decentralized <id>[,<id>...]");
+    println!("   The IDs of the federates that use decentralized coordination. They take part in");
+    println!("   the startup and the stop protocol, but they are not granted tags and their");
+    println!("   connections do not constrain the grants of other federates.");
+    println!("  --transient <id>[,<id>...]");
+    println!("   The IDs of the federates that may join after the start, and resign and join");
+    println!("   again. The federation starts and ends without waiting for them, and a federate");
+    println!("   that joins late starts at a tag after the grants of its downstream federates.");
+    println!("  --log_level [error|warn|info|debug|trace]");
+    println!("   How much the RTI prints.");
+    println!("       - error: Failures of the RTI and of federates.");
+    println!("       - warn: Also warnings, e.g., about rejected federates and dropped messages.");
+    println!("       - info (default): Also connections, resignations, and other events of the");
+    println!("         federation.");
+    println!("       - debug: Also messages exchanged with federates and grants.");
+    println!("       - trace: Also the steps of the grant computations, which grow with the");
+    println!("         number of pairs of connected federates.");
+    println!("  --log_sink [stdout|syslog|journald]");
+    println!("   Where the RTI prints.");
+    println!("       - stdout (default): The standard output.");
+    println!("       - syslog: The local syslog daemon, with the daemon facility.");
+    println!("       - journald: The systemd journal.");
+    println!("   Lines get the priority of their level: err, warning, info, or debug for debug");
+    println!("   and trace lines. Lines that cannot be sent are printed to the standard output.");
+    println!("  --log_socket <path>");
+    println!("   The socket of the syslog daemon or journald. Defaults to /dev/log and");
+    println!("   /run/systemd/journal/socket, respectively.");
+    println!("  --log_file <path>");
+    println!("   Append the output to the given file instead of printing it to the standard");
+    println!("   output. Cannot be combined with --log_sink syslog or journald.");
+    println!("  --trace_rate <n>");
+    println!(
+        "   The maximum number of trace lines printed per second. Default is {}. 0 means no limit.",
+        DEFAULT_TRACE_LINES_PER_SECOND
+    );
+    println!("  --max_message_size <n>");
+    println!("   The maximum size in bytes of the payload of a message from a federate. Larger");
+    println!("   messages are dropped and the sender is sent a MsgType::Reject message. During");
+    println!("   the handshake, the federate is rejected. Default is 0 (no limit).");
+    println!("  --max_control_rate <n>");
+    println!("   The maximum number of NET and LTC messages per second that the RTI handles from");
+    println!("   each federate, allowing bursts of up to one second worth of messages. Default is");
+    println!("   0 (no limit).");
+    println!("  --on_excess_control [coalesce|flag]");
+    println!("   What to do with the NET and LTC messages in excess of --max_control_rate.");
+    println!("       - coalesce (default): Hold them back and handle only the latest NET and the");
+    println!("         latest LTC once the rate allows it.");
+    println!("       - flag: Handle them anyway, but warn about the federate.");
+    println!("   The number of messages in excess is printed in the run summary.");
+    println!("  --grant_write_policy [<id>=]<policy>[,[<id>=]<policy>...]");
+    println!("   What to do when the socket of a federate does not take a TAG or PTAG because its");
+    println!("   send buffer is full. A policy with an ID applies to that federate, and one");
+    println!("   without an ID to all others.");
+    println!("       - block (default): Wait for the socket, which holds back the grants to all");
+    println!("         other federates meanwhile.");
+    println!("       - buffer:<n>: Write the grants from a separate thread, and disconnect the");
+    println!("         federate if more than n grants are waiting for its socket.");
+    println!(
+        "       - timeout:<ms>: Wait at most ms milliseconds, and then disconnect the federate."
+    );
+    println!("       - supersede: Write the grants from a separate thread, and while it waits,");
+    println!("         keep only the latest TAG and the latest PTAG after it.");
+    println!("   A disconnected federate is handled as a failed federate (see --on_failure).");
+    println!("  --egress_rate [<id>=]<bytes per second>[:<burst bytes>][,...]");
+    println!("   The maximum rate at which the RTI relays data messages, headers included, to a");
+    println!("   federate, allowing bursts of the given number of bytes, or of up to one second");
+    println!("   worth of bytes by default. A rate with an ID applies to that federate, and one");
+    println!("   without an ID to all others. Grants and other control messages are not limited.");
+    println!("   Default is no limit.");
+    println!("  --on_excess_egress [queue|drop]");
+    println!("   What to do with the data messages in excess of --egress_rate.");
+    println!("       - queue (default): Keep them in memory until the rate allows it. The");
+    println!("         grants to the destination federate are written after them.");
+    println!("       - drop: Drop them, and warn about the destination federate.");
+    println!("   The number of messages delayed or dropped is printed in the run summary.");
+    println!("  --microstep_warning_threshold <n>");
+    println!("   Warn that the federation may be livelocked in a microstep loop once a federate");
+    println!("   has been granted n consecutive tags at the same time with increasing");
+    println!(
+        "   microsteps. Default is {}. 0 disables the warning.",
+        DEFAULT_MICROSTEP_WARNING_THRESHOLD
+    );
+    println!("  --admin_port <n>");
+    println!("   Accept admin console connections on port n of the loopback interface. Each line");
+    println!("   sent to the console is a command, which is answered with one line:");
+    println!("       - start: Send the start time held back by --hold.");
+    println!("       - pause: Withhold all TAG and PTAG messages. NET and LTC messages are still");
+    println!("         recorded, so that the grants they allow are sent when resuming.");
+    println!("       - resume: Send the grants withheld while paused and continue.");
+    println!("       - step on|off: Enter or leave step mode (see --step). Leaving step mode");
+    println!("         sends the pending grants.");
+    println!("       - pending: List the grants pending in step mode with their justification.");
+    println!("       - release: Send the oldest pending grant.");
+    println!("       - break all|<federate> <time> <microstep>: Grant all federates, or the given");
+    println!("         federate, up to but not beyond the tag, relative to the start time and in");
+    println!("         units of the time resolution.");
+    println!("       - breakpoints: List the breakpoints.");
+    println!("       - continue all|<federate>: Clear every breakpoint, or the breakpoint of the");
+    println!("         given federate, and send the grants held back.");
+    println!("       - auth <token>: Authenticate with a token (see --admin_token_file).");
+    println!("       - help: List the commands.");
+    println!("   A <federate> is its ID or the name that it presented at the handshake.");
+    println!("   Disabled by default.");
+    println!("  --status_port <n>");
+    println!("   Answer HTTP requests for /status on port n of the loopback interface with the");
+    println!("   state of each federate in JSON: its connection state, last NET, LTC, TAG, and");
+    println!("   PTAG, its upstream and downstream federates, and the cycle without delay that it");
+    println!("   is part of, if any. Disabled by default. The snapshot command of the admin");
+    println!("   console returns the same JSON, whose schema is versioned. Check a snapshot");
+    println!("   saved to a file with: rti validate_snapshot <file>.");
+    println!("  --snapshot_file <path>");
+    println!("   Write a snapshot of the federation in the same JSON to the given file when the");
+    println!("   RTI receives SIGUSR1 and when the federation ends. The file is replaced");
+    println!("   atomically. Disabled by default.");
+    println!("  --checkpoint_period <ms>");
+    println!("   Also write a snapshot to --snapshot_file every given number of milliseconds.");
+    println!("   Disabled by default.");
+    println!("  --restore_snapshot <path>");
+    println!("   Restart the federation from a snapshot written by --snapshot_file: send the");
+    println!("   federates the start time of the snapshot instead of a new one, and restore the");
+    println!("   completed tag (LTC) of each federate until it reports a later one. The snapshot");
+    println!("   must be of the same federation ID and number of federates, and taken after the");
+    println!("   start time was decided and before a stop. The federates restore their own state.");
+    println!("  --admin_token_file <path>");
+    println!("   Require the clients of the admin console and the status server to authenticate");
+    println!("   with a token, and give those that present the token in the given file the");
+    println!("   control role, with which they may use every command. Clients of the admin");
+    println!("   console authenticate with the auth command, and those of the status server with");
+    println!("   an Authorization: Bearer <token> header.");
+    println!("  --admin_read_token_file <path>");
+    println!("   Like --admin_token_file, but give the clients that present the token in the");
+    println!("   given file the read-only role, with which they may read the status and use the");
+    println!("   commands that do not change the federation, such as pending, info, and history.");
+    println!("  --parent_rti <host>:<port>");
+    println!("   Experimental: Connect to a parent RTI as one of its federates, so that a large");
+    println!("   federation is coordinated hierarchically in partitions. The parent RTI is sent");
+    println!("   the earliest NET and LTC of the federates, which are not granted beyond the tags");
+    println!("   granted by the parent RTI. Tagged messages between partitions are not forwarded,");
+    println!("   and stop requests are not propagated across partitions.");
+    println!("  --parent_id <n>");
+    println!("   Federate ID of this RTI at the parent RTI. Default is 0.");
+    println!("  --parent_upstream <id>[:<delay>],...");
+    println!("   Upstream federates of this RTI at the parent RTI, each with the delay of its");
+    println!("   connection in units of the time resolution, or without a delay.");
+    println!("  --parent_downstream <id>,...");
+    println!("   Downstream federates of this RTI at the parent RTI.");
+    println!("  --step");
+    println!("   Start in step mode, in which each TAG and PTAG is printed with the reason why it");
+    println!("   is safe and sent only once released with the release command of the admin");
+    println!("   console. Requires --admin_port.");
+    println!("  --hold");
+    println!("   Complete the handshakes and collect the proposed start times, but send the start");
+    println!("   time only once released with the start command of the admin console, so that");
+    println!("   tools can be set up before the federation starts. The start time is as late as");
+    println!("   if the last federate had proposed one when released. Requires --admin_port.");
+    println!("  --run_db <file>");
+    println!("   Record the summary of the run (duration, grants, relayed messages, lag, and");
+    println!("   failures) into the given SQLite file, which is created if needed. Compare two");
+    println!("   recorded runs with: rti compare <file> [<run ID> <run ID>], which defaults to");
+    println!("   the last two runs. Requires the RTI to be built with the run_db feature,");
+    println!("   which is not a default one: cargo build --features run_db.");
+    println!("  --topology_file <file>");
+    println!("   Write the topology of the federation (neighbors, delays, problems found, cycles");
+    println!("   without delay, and minimum delays between federates) to the file once all");
+    println!("   federates have connected. If the file describes the same neighbors as the ones");
+    println!("   reported in a later run, the topology is loaded from it instead of recomputed.");
+    println!("  --federation_metadata <file>");
+    println!("   Check the federation ID, the number of federates, and the name and neighbors of");
+    println!("   each federate as it connects against the federation metadata emitted by the code");
+    println!(
+        "   generator. On a mismatch, print the differences and fail: a mismatched federate is"
+    );
+    println!("   rejected and the RTI shuts down. The file has the lines \"federation <ID>\",");
+    println!("   \"federate <ID> [name=<name>] [group=<name>]\", where the group is as for");
+    println!("   --group, and \"connection <from ID> <to ID>");
+    println!("   [delay=<ns>|delay=none]\".");
+    println!("  --wire_dump <federates>");
+    println!("   Dump all bytes exchanged with the given federates (a comma-separated list of IDs");
+    println!("   or names) to one file per federate, named federate_<ID>.wiredump, with one line");
+    println!("   per read or write: time since the UNIX epoch, RX or TX, length, and the bytes in");
+    println!("   hex.");
+    println!("  --wire_dump_dir <directory>");
+    println!("   Directory in which the files of --wire_dump are written. Default: the current");
+    println!("   directory.");
+    println!("  --fault_rules <file>");
+    println!("   Inject faults into the messages sent to federates, for testing the resilience of");
+    println!("   federate runtimes. Each line of the file is a rule such as");
+    println!("       drop TAG federate=1 from=100 to=200 probability=0.5");
+    println!("       delay PTAG ms=50");
+    println!("       corrupt STOP_GRANTED");
+    println!("   The action is drop, delay (by ms milliseconds), or corrupt (flip bits of one");
+    println!("   byte after the message type). The message is TAG, PTAG, STOP_REQUEST, or");
+    println!("   STOP_GRANTED. The optional federate, from, and to (in units of the time");
+    println!("   resolution relative to the start time), and probability restrict the rule.");
+    println!("   The first matching rule applies.");
+    println!("  --fault_seed <n>");
+    println!("   Seed that determines which messages are faulted. Default is derived from the");
+    println!("   current time and printed, so that a run can be reproduced.");
+    println!("  --lock_stats");
+    println!("   Measure how long each call site in the RTI waits for the lock of the RTI state");
+    println!("   and print the call sites that waited the longest in the run summary.");
+    println!("  --no_grant_cache");
+    println!("   Recompute every grant decision instead of reusing the decision made while none");
+    println!("   of the tags and connections that it depends on has changed.");
+    println!("  --event_stream <path|fd:n>");
+    println!("   Write every scheduling decision as it happens to the file at path, or to the");
+    println!("   inherited file descriptor n, as newline-delimited JSON for external analyzers:");
+    println!("   NET and LTC messages received, TAG and PTAG messages sent with their reason,");
+    println!("   grants pending in step mode, the granted stop tag, and resigned and failed");
+    println!("   federates.");
+    println!("  --output_key_file <path>");
+    println!("   Encrypt the trace file, the event stream, the wire dumps, and the log file with");
+    println!("   AES-256-GCM under the key in the given file, which holds 64 hexadecimal digits,");
+    println!("   e.g., from openssl rand -hex 32, and must not be accessible by other users.");
+    println!("   Decrypt them with rti_decrypt. Requires the RTI to be built with the encryption");
+    println!("   feature (enabled by default).");
+    println!("  --tls_cert <path> --tls_key <path>");
+    println!("   Accept federates only over TLS, with the certificate chain and the private key");
+    println!("   in the given PEM files. The TLS handshake is part of the handshake of a");
+    println!("   federate and has to complete within the handshake timeout. Requires the RTI to");
+    println!("   be built with the tls feature (enabled by default).");
+
+    println!("Command given:");
+    let mut idx = 0;
+    while idx < argc {
+        println!("{} ", argv[idx]);
+        idx += 1;
+    }
+}
+
+/**
+ * Process command-line arguments related to clock synchronization: the status
+ * at argv[idx], optionally followed by "period <n>" and
+ * "exchanges-per-interval <n>".
+ *
+ * @param idx: Position of the status in argv
+ * @return Position of the last argument related to clock synchronization
+ */
+fn process_clock_sync_args(
+    rti: &mut FederationRTI,
+    argc: usize,
+    argv: &[String],
+    mut idx: usize,
+) -> Result<usize, &'static str> {
+    match argv[idx].as_str() {
+        "off" => rti.set_clock_sync_global_status(ClockSyncStat::ClockSyncOff),
+        "init" | "initial" => rti.set_clock_sync_global_status(ClockSyncStat::ClockSyncInit),
+        "on" => rti.set_clock_sync_global_status(ClockSyncStat::ClockSyncOn),
+        _ => {
+            error!("--clock_sync needs off|init|on.");
+            usage(argc, argv);
+            return Err("Fail to handle clock_sync option");
+        }
+    }
+    info!("RTI: Clock sync: {}", argv[idx]);
+    while idx + 1 < argc {
+        match argv[idx + 1].as_str() {
+            "period" => {
+                if rti.clock_sync_global_status() != ClockSyncStat::ClockSyncOn {
+                    error!("clock sync period can only be set if --clock_sync is set to on.");
+                    usage(argc, argv);
+                    return Err("Fail to handle clock_sync option");
+                }
+                match argv.get(idx + 2).map(|value| value.parse::<u64>()) {
+                    Some(Ok(period_ns)) if period_ns > 0 => {
+                        info!("RTI: Clock sync period: {}", period_ns);
+                        rti.set_clock_sync_period_ns(period_ns);
+                    }
+                    _ => {
+                        error!(
+                            "clock sync period needs a positive time (in nanoseconds) argument."
+                        );
+                        usage(argc, argv);
+                        return Err("Fail to handle clock_sync option");
+                    }
+                }
+            }
+            "exchanges-per-interval" => {
+                if rti.clock_sync_global_status() == ClockSyncStat::ClockSyncOff {
+                    error!("clock sync exchanges-per-interval can only be set if --clock_sync is set to on or init.");
+                    usage(argc, argv);
+                    return Err("Fail to handle clock_sync option");
+                }
+                match argv.get(idx + 2).map(|value| value.parse::<i32>()) {
+                    Some(Ok(exchanges)) if exchanges > 0 => {
+                        info!("RTI: Clock sync exchanges per interval: {}", exchanges);
+                        rti.set_clock_sync_exchanges_per_interval(exchanges);
+                    }
+                    _ => {
+                        error!(
+                            "clock sync exchanges-per-interval needs a positive integer argument."
+                        );
+                        usage(argc, argv);
+                        return Err("Fail to handle clock_sync option");
+                    }
+                }
+            }
+            _ => break,
+        }
+        idx += 2;
+    }
+    Ok(idx)
+}
@@ -0,0 +1,55 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief HMAC-SHA256 authentication of federates, as in the C RTI built with
+ * AUTH=ON. See MSG_TYPE_FED_NONCE_LENGTH for the protocol.
+ */
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::net_common::{NONCE_LENGTH, SHA256_HMAC_LENGTH};
+use crate::trace::error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/**
+ * Return a new random nonce, or None if the operating system provides no
+ * randomness.
+ */
+pub fn nonce() -> Option<[u8; NONCE_LENGTH]> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    match getrandom::getrandom(&mut nonce) {
+        Ok(()) => Some(nonce),
+        Err(e) => {
+            error!("RTI: Failed to generate a nonce: {}.", e);
+            None
+        }
+    }
+}
+
+/**
+ * Return the HMAC-SHA256 tag of the concatenation of the given parts under the
+ * given key.
+ */
+pub fn hmac_tag(key: &[u8], parts: &[&[u8]]) -> [u8; SHA256_HMAC_LENGTH] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/**
+ * Return true if the given tag is the HMAC-SHA256 tag of the concatenation of
+ * the given parts under the given key. The comparison takes constant time.
+ */
+pub fn verify_hmac_tag(key: &[u8], parts: &[&[u8]], tag: &[u8]) -> bool {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.verify_slice(tag).is_ok()
+}
@@ -0,0 +1,54 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Decryption of the files that the RTI encrypts with --output_key_file.
+ *
+ * Usage: rti_decrypt <key file> [<input> [<output>]]
+ *
+ * The input and output default to the standard input and output, so that an
+ * encrypted event stream can be followed as the RTI writes it.
+ */
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 4 {
+        println!("Usage: {} <key file> [<input> [<output>]]", args[0]);
+        process::exit(1);
+    }
+    let key = rti::OutputKey::read(&args[1]).unwrap_or_else(|e| {
+        eprintln!("Failed to read a key from {}: {}.", args[1], e);
+        process::exit(1);
+    });
+    let reader: Box<dyn Read> = match args.get(2) {
+        Some(path) => Box::new(File::open(path).unwrap_or_else(|e| {
+            eprintln!("Failed to open {}: {}.", path, e);
+            process::exit(1);
+        })),
+        None => Box::new(io::stdin().lock()),
+    };
+    let writer: Box<dyn Write> = match args.get(3) {
+        Some(path) => Box::new(File::create(path).unwrap_or_else(|e| {
+            eprintln!("Failed to create {}: {}.", path, e);
+            process::exit(1);
+        })),
+        None => Box::new(io::stdout().lock()),
+    };
+    match rti::decrypt(&key, reader, writer) {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("WARNING: The file is truncated, e.g., because the RTI was killed.");
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("Failed to decrypt: {}.", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief A builder of the RTI for the tools that embed it.
+ *
+ * The builder takes the options of the command line, so that every option of
+ * the RTI is available without a method per option, and the hooks, which
+ * cannot be given on the command line.
+ */
+use std::sync::Arc;
+
+use crate::clock::VirtualClock;
+use crate::error::RtiError;
+use crate::inspector::PayloadInspector;
+use crate::FederationRTI;
+
+/**
+ * Builds an RTI ready to be started with start_rti_server(), e.g.,
+ * RtiBuilder::new().args(["-n", "2", "--fast"]).build().
+ */
+#[derive(Default)]
+pub struct RtiBuilder {
+    args: Vec<String>,
+    payload_inspectors: Vec<Arc<dyn PayloadInspector>>,
+    virtual_clock: Option<VirtualClock>,
+}
+
+impl RtiBuilder {
+    pub fn new() -> RtiBuilder {
+        RtiBuilder::default()
+    }
+
+    /**
+     * Add an option of the command line, or the value of one.
+     */
+    pub fn arg(mut self, arg: impl Into<String>) -> RtiBuilder {
+        self.args.push(arg.into());
+        self
+    }
+
+    /**
+     * Add options of the command line, or their values.
+     */
+    pub fn args<I, S>(mut self, args: I) -> RtiBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /**
+     * Add a hook that inspects the payloads of the forwarded messages.
+     */
+    pub fn payload_inspector(mut self, inspector: Arc<dyn PayloadInspector>) -> RtiBuilder {
+        self.payload_inspectors.push(inspector);
+        self
+    }
+
+    /**
+     * Read physical time from the given clock, which the caller advances,
+     * instead of the clock source.
+     */
+    pub fn virtual_clock(mut self, virtual_clock: VirtualClock) -> RtiBuilder {
+        self.virtual_clock = Some(virtual_clock);
+        self
+    }
+
+    /**
+     * Process the options and initialize the federates. The options are
+     * reported as on the command line, and an invalid one is returned as
+     * RtiError::InvalidArguments.
+     */
+    pub fn build(self) -> Result<FederationRTI, RtiError> {
+        let mut rti = crate::initialize_rti();
+        // The arguments of process_args() start with the name of the program.
+        let argv: Vec<String> = std::iter::once(String::from("rti"))
+            .chain(self.args)
+            .collect();
+        crate::process_args(&mut rti, &argv)?;
+        for inspector in self.payload_inspectors {
+            rti.add_payload_inspector(inspector);
+        }
+        if let Some(virtual_clock) = self.virtual_clock {
+            rti.set_virtual_clock(virtual_clock);
+        }
+        crate::initialize_federates(&mut rti);
+        Ok(rti)
+    }
+}
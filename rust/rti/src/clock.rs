@@ -0,0 +1,415 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Physical time of the RTI used for clock synchronization and statistics.
+ *
+ * The periodic threads of the RTI, such as the ones of clock synchronization
+ * and of the silence and lag checks, sleep on this clock, so that with a
+ * virtual clock (see VirtualClock) they follow the virtual time. Timeouts of
+ * sockets, such as the handshake timeout, are in real time.
+ */
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{self, Duration, SystemTime, UNIX_EPOCH};
+
+use crate::constants::VIRTUAL_CLOCK_POLL_PERIOD_MS;
+use crate::tag::Instant;
+use crate::trace::warn;
+
+/**
+ * Difference between the advance of the source and of the monotonic clock
+ * between two readings beyond which the source is considered to have stepped,
+ * e.g., because a leap second was inserted or NTP corrected the clock.
+ */
+const STEP_THRESHOLD_NS: i64 = 100_000_000;
+
+/**
+ * Minimum time between two readings over which the rate of the source is
+ * compared with the rate of the monotonic clock to detect smearing.
+ */
+const SMEAR_WINDOW_NS: i64 = 60_000_000_000;
+
+/**
+ * Rate difference, in parts per million, beyond which the source is considered
+ * to be smeared. A leap smear over 24 hours changes the rate by about 11.6 ppm,
+ * while the drift corrected by NTP is usually much smaller.
+ */
+const SMEAR_THRESHOLD_PPM: i64 = 5;
+
+/**
+ * Clock from which the RTI reads physical time.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ClockSource {
+    /// Never steps or smears. Anchored to the realtime clock when the RTI starts.
+    Monotonic,
+    /// The system's realtime clock, which leap seconds may step or smear.
+    Realtime,
+    /// International Atomic Time, which has no leap seconds (64-bit Linux only).
+    Tai,
+}
+
+impl ClockSource {
+    pub fn from_name(name: &str) -> Option<ClockSource> {
+        match name {
+            "monotonic" => Some(ClockSource::Monotonic),
+            "realtime" => Some(ClockSource::Realtime),
+            "tai" => Some(ClockSource::Tai),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClockSource::Monotonic => "monotonic",
+            ClockSource::Realtime => "realtime",
+            ClockSource::Tai => "tai",
+        }
+    }
+
+    /**
+     * Return true if the source can be read on this platform.
+     */
+    pub fn is_available(self) -> bool {
+        self != ClockSource::Tai || tai_time_ns().is_some()
+    }
+}
+
+/**
+ * Time of a virtual clock, the offset by which it has been stepped, and the
+ * number of threads sleeping on it.
+ */
+struct VirtualTime {
+    now: Instant,
+    offset: i64,
+    sleeping_threads: usize,
+}
+
+/**
+ * Physical time that passes only when it is advanced, so that tests can
+ * simulate hours of a federation, e.g., of clock synchronization rounds and
+ * silence checks, in milliseconds. Clones share the time. It is given to the
+ * RTI with RtiBuilder::virtual_clock().
+ */
+#[derive(Clone)]
+pub struct VirtualClock {
+    time: Arc<(Mutex<VirtualTime>, Condvar)>,
+}
+
+impl VirtualClock {
+    /**
+     * Create a clock that reads the given time in nanoseconds since the epoch
+     * until it is advanced.
+     */
+    pub fn new(now: Instant) -> VirtualClock {
+        VirtualClock {
+            time: Arc::new((
+                Mutex::new(VirtualTime {
+                    now,
+                    offset: 0,
+                    sleeping_threads: 0,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        let time = self.time.0.lock().unwrap();
+        time.now.saturating_add(time.offset)
+    }
+
+    /**
+     * Advance the time by the given duration, and wake up the threads whose
+     * sleep has then elapsed.
+     */
+    pub fn advance(&self, duration: Duration) {
+        let (lock, condvar) = &*self.time;
+        let mut time = lock.lock().unwrap();
+        let duration_ns = i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX);
+        time.now = time.now.saturating_add(duration_ns);
+        condvar.notify_all();
+    }
+
+    /**
+     * Step the time read from the clock by the given offset in nanoseconds
+     * without time passing, as a leap second or an NTP correction steps the
+     * realtime clock. Sleeps are not affected. Readings of a clock that is
+     * stepped by small offsets at a steady rate are those of a smeared clock.
+     */
+    pub fn step(&self, offset: i64) {
+        let mut time = self.time.0.lock().unwrap();
+        time.offset = time.offset.saturating_add(offset);
+    }
+
+    /**
+     * Return the number of threads sleeping on the clock, e.g., to wait until
+     * the periodic threads of the RTI have caught up with an advance before
+     * advancing again.
+     */
+    pub fn sleeping_threads(&self) -> usize {
+        self.time.0.lock().unwrap().sleeping_threads
+    }
+
+    /**
+     * Return the time that has passed, which steps do not change, and the
+     * time read from the clock.
+     */
+    fn readings(&self) -> (Instant, Instant) {
+        let time = self.time.0.lock().unwrap();
+        (time.now, time.now.saturating_add(time.offset))
+    }
+
+    /**
+     * Sleep until the clock has been advanced by the given duration or until
+     * released is set.
+     */
+    fn sleep(&self, duration: Duration, released: &AtomicBool) {
+        let (lock, condvar) = &*self.time;
+        let mut time = lock.lock().unwrap();
+        let duration_ns = i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX);
+        let deadline = time.now.saturating_add(duration_ns);
+        time.sleeping_threads += 1;
+        while time.now < deadline && !released.load(Ordering::SeqCst) {
+            time = condvar.wait(time).unwrap();
+        }
+        time.sleeping_threads -= 1;
+    }
+
+    /**
+     * Wake up the sleeping threads so that they check whether they have been
+     * released.
+     */
+    fn wake_up(&self) {
+        let (lock, condvar) = &*self.time;
+        let _time = lock.lock().unwrap();
+        condvar.notify_all();
+    }
+}
+
+/**
+ * Previous reading of the monotonic clock, in nanoseconds since the anchor,
+ * and of the source, the start of the current window over which smearing is
+ * detected, and what has been detected so far.
+ */
+struct Readings {
+    last: (i64, Instant),
+    window_start: (i64, Instant),
+    steps: Vec<i64>,
+    smear_ppm: Option<i64>,
+}
+
+/**
+ * Physical clock of the RTI. Readings of the realtime and TAI sources are
+ * compared with the monotonic clock so that a leap second that steps or smears
+ * the source is reported instead of silently skewing the measurements that
+ * span it.
+ */
+pub struct PhysicalClock {
+    source: ClockSource,
+    anchor: (time::Instant, Instant),
+    readings: Mutex<Option<Readings>>,
+    // Replaces the source in tests.
+    virtual_clock: Option<VirtualClock>,
+    // Indicates that the threads sleeping on a virtual clock are to return.
+    released: AtomicBool,
+}
+
+impl PhysicalClock {
+    pub(crate) fn new(source: ClockSource) -> PhysicalClock {
+        let anchor = (time::Instant::now(), realtime_ns());
+        if source == ClockSource::Tai {
+            if let Some(tai) = tai_time_ns() {
+                if (tai - anchor.1).abs() < STEP_THRESHOLD_NS {
+                    warn!("RTI: WARNING: The kernel does not know the offset of TAI from UTC, so the tai clock follows the realtime clock.");
+                }
+            }
+        }
+        PhysicalClock {
+            source,
+            anchor,
+            readings: Mutex::new(None),
+            virtual_clock: None,
+            released: AtomicBool::new(false),
+        }
+    }
+
+    /**
+     * Create a clock that reads the given virtual clock instead of a source.
+     * Unless the source is monotonic, the readings are checked for steps and
+     * smears as the readings of the source would be, with the time that has
+     * passed on the virtual clock in place of the monotonic clock.
+     */
+    pub(crate) fn new_virtual(virtual_clock: VirtualClock, source: ClockSource) -> PhysicalClock {
+        PhysicalClock {
+            source,
+            anchor: (time::Instant::now(), virtual_clock.now()),
+            readings: Mutex::new(None),
+            virtual_clock: Some(virtual_clock),
+            released: AtomicBool::new(false),
+        }
+    }
+
+    /**
+     * Return the current physical time in nanoseconds since the epoch of the
+     * source.
+     */
+    pub fn now(&self) -> Instant {
+        let (monotonic, now) = match &self.virtual_clock {
+            Some(virtual_clock) => virtual_clock.readings(),
+            None => {
+                let monotonic = elapsed_ns(self.anchor.0, time::Instant::now());
+                let now = match self.source {
+                    ClockSource::Monotonic => self.anchor.1 + monotonic,
+                    ClockSource::Realtime => realtime_ns(),
+                    // Availability is checked when the source is chosen.
+                    ClockSource::Tai => tai_time_ns().unwrap_or_else(realtime_ns),
+                };
+                (monotonic, now)
+            }
+        };
+        if self.source != ClockSource::Monotonic {
+            self.check_against_monotonic(monotonic, now);
+        }
+        now
+    }
+
+    /**
+     * Sleep for the given duration of physical time, which, on a virtual
+     * clock, passes only when the clock is advanced.
+     */
+    pub(crate) fn sleep(&self, duration: Duration) {
+        match &self.virtual_clock {
+            Some(virtual_clock) => virtual_clock.sleep(duration, &self.released),
+            None => thread::sleep(duration),
+        }
+    }
+
+    /**
+     * Return the real time to wait on a condition variable for the given
+     * duration of physical time to pass: the duration itself, or, on a
+     * virtual clock, whose advances do not notify the condition variable, the
+     * period at which the virtual time is polled.
+     */
+    pub(crate) fn poll_timeout(&self, duration: Duration) -> Duration {
+        match &self.virtual_clock {
+            Some(_) => duration.min(Duration::from_millis(VIRTUAL_CLOCK_POLL_PERIOD_MS)),
+            None => duration,
+        }
+    }
+
+    /**
+     * Let the threads sleeping on a virtual clock return, and later sleeps
+     * return at once, so that the periodic threads see that all federates
+     * have exited without the clock being advanced.
+     */
+    pub(crate) fn release(&self) {
+        self.released.store(true, Ordering::SeqCst);
+        if let Some(virtual_clock) = &self.virtual_clock {
+            virtual_clock.wake_up();
+        }
+    }
+
+    /**
+     * Return the steps of the source, in nanoseconds, detected so far.
+     */
+    pub fn detected_steps(&self) -> Vec<i64> {
+        match self.readings.lock().unwrap().as_ref() {
+            Some(readings) => readings.steps.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /**
+     * Return the rate difference, in parts per million, of the source from the
+     * monotonic clock when a smear was first detected, if one was.
+     */
+    pub fn detected_smear_ppm(&self) -> Option<i64> {
+        self.readings
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|readings| readings.smear_ppm)
+    }
+
+    /**
+     * Compare the advance of the source since the previous reading with the
+     * advance of the monotonic clock, given in nanoseconds since the anchor,
+     * and report a step or a smear.
+     */
+    fn check_against_monotonic(&self, monotonic: i64, now: Instant) {
+        let mut locked_readings = self.readings.lock().unwrap();
+        let Some(readings) = locked_readings.as_mut() else {
+            *locked_readings = Some(Readings {
+                last: (monotonic, now),
+                window_start: (monotonic, now),
+                steps: Vec::new(),
+                smear_ppm: None,
+            });
+            return;
+        };
+        let step = (now - readings.last.1) - (monotonic - readings.last.0);
+        if step.abs() >= STEP_THRESHOLD_NS {
+            warn!(
+                "RTI: WARNING: The {} clock stepped by {} ns, e.g., because of a leap second. Physical time measurements that span the step are skewed. Consider --clock_source monotonic.",
+                self.source.name(),
+                step
+            );
+            readings.steps.push(step);
+            // Do not count the step as a smear.
+            readings.window_start = (monotonic, now);
+        }
+        readings.last = (monotonic, now);
+
+        let window = monotonic - readings.window_start.0;
+        if window < SMEAR_WINDOW_NS {
+            return;
+        }
+        let drift = (now - readings.window_start.1) - window;
+        let drift_ppm = drift.saturating_mul(1_000_000) / window;
+        if drift_ppm.abs() >= SMEAR_THRESHOLD_PPM && readings.smear_ppm.is_none() {
+            warn!(
+                "RTI: WARNING: The {} clock runs {} ppm off the monotonic clock, which suggests a leap smear. Clock synchronization with federates whose clocks are not smeared is skewed. Consider --clock_source monotonic or tai.",
+                self.source.name(),
+                drift_ppm
+            );
+            readings.smear_ppm = Some(drift_ppm);
+        }
+        readings.window_start = (monotonic, now);
+    }
+}
+
+fn elapsed_ns(earlier: time::Instant, later: time::Instant) -> i64 {
+    i64::try_from(later.duration_since(earlier).as_nanos()).unwrap_or(i64::MAX)
+}
+
+fn realtime_ns() -> Instant {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => i64::try_from(since_epoch.as_nanos()).unwrap_or(i64::MAX),
+        Err(before_epoch) => -i64::try_from(before_epoch.duration().as_nanos()).unwrap_or(i64::MAX),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn tai_time_ns() -> Option<Instant> {
+    let mut tp = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: tp is a valid timespec.
+    if unsafe { libc::clock_gettime(libc::CLOCK_TAI, &mut tp) } != 0 {
+        return None;
+    }
+    // time_t and long are 32 bits wide on some targets.
+    #[allow(clippy::useless_conversion)]
+    Some(i64::from(tp.tv_sec) * 1_000_000_000 + i64::from(tp.tv_nsec))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn tai_time_ns() -> Option<Instant> {
+    None
+}
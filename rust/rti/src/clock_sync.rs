@@ -0,0 +1,235 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Synchronization of the physical clocks of the federates with the clock
+ * of the RTI, as in the C RTI. See MSG_TYPE_CLOCK_SYNC_TIME_LENGTH for the
+ * protocol.
+ */
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::PhysicalClock;
+use crate::federate::federate_label;
+use crate::lock_stats::lock_rti;
+use crate::net_common::*;
+use crate::net_util::NetUtil;
+use crate::tag::StartTime;
+use crate::trace::{info, warn};
+use crate::wire_dump::{Direction, WireDump};
+use crate::FedState;
+use crate::FederationRTI;
+
+/**
+ * Time to wait for the MsgType::ClockSyncT3 reply of a federate in a runtime
+ * clock synchronization round.
+ */
+const UDP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/**
+ * Number of UDP messages read while waiting for the reply of a federate before
+ * its round is skipped, e.g., because late replies of earlier rounds arrive.
+ */
+const MAX_READS_PER_ROUND: usize = 5;
+
+/**
+ * Encode a clock synchronization message of the given type that carries the
+ * current physical time of the RTI.
+ */
+pub fn clock_sync_message(msg_type: MsgType, clock: &PhysicalClock) -> Vec<u8> {
+    let mut buffer = vec![0u8; MSG_TYPE_CLOCK_SYNC_TIME_LENGTH];
+    buffer[0] = msg_type.to_byte();
+    NetUtil::encode_int64(clock.now(), &mut buffer, 1);
+    buffer
+}
+
+/**
+ * Bind the UDP socket for runtime clock synchronization to the given port,
+ * which federates expect to be the port of the TCP socket of the RTI. Return
+ * None if it fails, in which case there is no runtime clock synchronization.
+ */
+pub fn bind_udp_socket(port: u16) -> Option<UdpSocket> {
+    let socket = match UdpSocket::bind(("0.0.0.0", port)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!(
+                "RTI: WARNING: Failed to bind the UDP socket for clock synchronization to port {}: {}. Clock synchronization is only done at startup.",
+                port, e
+            );
+            return None;
+        }
+    };
+    if let Err(e) = socket.set_read_timeout(Some(UDP_TIMEOUT)) {
+        warn!(
+            "RTI: WARNING: Failed to set the timeout of the UDP socket for clock synchronization: {}. Clock synchronization is only done at startup.",
+            e
+        );
+        return None;
+    }
+    info!("RTI: Clock synchronization uses UDP port {}.", port);
+    Some(socket)
+}
+
+/**
+ * Spawn the thread that performs a clock synchronization round with each
+ * federate that does runtime clock synchronization every `period_ns`
+ * nanoseconds, starting at the start time. The thread exits once no such
+ * federate is connected.
+ */
+pub fn start_clock_sync_thread(
+    socket: UdpSocket,
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    start_time: Arc<Mutex<StartTime>>,
+    sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+    clock: Arc<PhysicalClock>,
+    period_ns: u64,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let (lock, condvar) = &*sent_start_time;
+        loop {
+            {
+                let notified = lock.lock().unwrap();
+                if *notified {
+                    break;
+                }
+                let _ = condvar
+                    .wait_timeout(notified, Duration::from_millis(100))
+                    .unwrap();
+            }
+            // The lock of the RTI state is taken before the one of the start
+            // time elsewhere, so it is not taken while holding the latter.
+            if lock_rti(&_f_rti).all_federates_exited() {
+                return;
+            }
+        }
+        let start_time_value = start_time.lock().unwrap().start_time();
+        let ns_to_wait = start_time_value - clock.now();
+        if ns_to_wait > 0 {
+            clock.sleep(Duration::from_nanos(ns_to_wait as u64));
+        }
+        loop {
+            clock.sleep(Duration::from_nanos(period_ns));
+            let mut federates = Vec::new();
+            {
+                let mut locked_rti = lock_rti(&_f_rti);
+                if locked_rti.all_federates_exited() {
+                    break;
+                }
+                for fed in locked_rti.enclaves().iter() {
+                    if fed.e().state() == FedState::NotConnected
+                        || !fed.clock_synchronization_enabled()
+                    {
+                        continue;
+                    }
+                    let ip = fed
+                        .stream()
+                        .as_ref()
+                        .and_then(|stream| stream.peer_addr().ok())
+                        .map(|address| address.ip());
+                    if let (Some(ip), Some(udp_port)) = (ip, fed.udp_port()) {
+                        federates.push((
+                            fed.e().id(),
+                            SocketAddr::new(ip, udp_port),
+                            fed.wire_dump().clone(),
+                            fed.has_capability(CAPABILITY_CODED_PROBES),
+                        ));
+                    }
+                }
+            }
+            if federates.is_empty() {
+                break;
+            }
+            for (fed_id, address, wire_dump, coded_probes) in federates {
+                synchronize(
+                    &socket,
+                    fed_id,
+                    address,
+                    &clock,
+                    wire_dump.as_deref(),
+                    coded_probes,
+                );
+            }
+        }
+    })
+}
+
+/**
+ * Perform one runtime clock synchronization round with a federate, followed
+ * by a coded probe if the federate handles them.
+ */
+fn synchronize(
+    socket: &UdpSocket,
+    fed_id: u16,
+    address: SocketAddr,
+    clock: &PhysicalClock,
+    wire_dump: Option<&WireDump>,
+    coded_probes: bool,
+) {
+    let send = |msg_type: MsgType| {
+        let buffer = clock_sync_message(msg_type, clock);
+        match socket.send_to(&buffer, address) {
+            Ok(_) => {
+                if let Some(wire_dump) = wire_dump {
+                    wire_dump.record(Direction::Sent, &buffer);
+                }
+                true
+            }
+            Err(e) => {
+                warn!(
+                    "RTI: WARNING: Clock sync: Failed to send to federate {}: {}.",
+                    federate_label(fed_id),
+                    e
+                );
+                false
+            }
+        }
+    };
+    if !send(MsgType::ClockSyncT1) {
+        return;
+    }
+    let mut buffer = [0u8; MSG_TYPE_CLOCK_SYNC_T3_LENGTH];
+    for _ in 0..MAX_READS_PER_ROUND {
+        let bytes_read = match socket.recv_from(&mut buffer) {
+            Ok((bytes_read, _)) => bytes_read,
+            Err(e) => {
+                warn!(
+                    "RTI: WARNING: Clock sync: Read from UDP socket failed: {}. Skipping clock sync round for federate {}.",
+                    e,
+                    federate_label(fed_id)
+                );
+                return;
+            }
+        };
+        if let Some(wire_dump) = wire_dump {
+            wire_dump.record(Direction::Received, &buffer[..bytes_read]);
+        }
+        if bytes_read < MSG_TYPE_CLOCK_SYNC_T3_LENGTH || buffer[0] != MsgType::ClockSyncT3.to_byte()
+        {
+            warn!(
+                "RTI: WARNING: Clock sync: Unexpected UDP message {} while waiting for MsgType::ClockSyncT3 from federate {}. Discarding message.",
+                buffer[0],
+                federate_label(fed_id)
+            );
+            continue;
+        }
+        let sender_id = i32::from_le_bytes(buffer[1..5].try_into().unwrap());
+        if sender_id != i32::from(fed_id) {
+            warn!(
+                "RTI: WARNING: Clock sync: Received MsgType::ClockSyncT3 from federate {}, but expected one from federate {}. Discarding message.",
+                sender_id,
+                federate_label(fed_id)
+            );
+            continue;
+        }
+        // The coded probe follows the T4 message immediately so that the
+        // federate can tell whether the network delayed either of them.
+        if send(MsgType::ClockSyncT4) && coded_probes {
+            send(MsgType::ClockSyncCodedProbe);
+        }
+        return;
+    }
+}
@@ -0,0 +1,208 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief The protocol state of the connection between the RTI and a federate.
+ */
+use crate::net_common::MsgType;
+
+/**
+ * The protocol state of a connection to a federate. Each state accepts only
+ * the message types that the protocol allows at that point; any other message
+ * is out of order.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ConnectionState {
+    /// Waiting for the MsgType::FedNonce message that opens the handshake if
+    /// the RTI authenticates federates.
+    AwaitingAuthentication,
+    /// Waiting for the MsgType::FedIds message that opens the handshake.
+    AwaitingFedIds,
+    /// Waiting for the MsgType::NeighborStructure message.
+    AwaitingNeighbors,
+    /// Waiting for the MsgType::UdpPort message that completes the handshake.
+    AwaitingUdpPort,
+    /// Waiting for the federate to propose a start time.
+    AwaitingTimestamp,
+    /// Executing.
+    Running,
+    /// Executing while the stop protocol is in progress.
+    Stopping,
+    /// The federate has resigned, failed, or been rejected.
+    Closed,
+    /// A control connection of a federate, on which the RTI only sends grants.
+    ControlChannel,
+    /// A connection shared by several federates. See
+    /// MULTIPLEX_FRAME_HEADER_LENGTH.
+    Multiplexed,
+}
+
+impl ConnectionState {
+    /**
+     * Return the state after receiving a message of the given type in this
+     * state, or None if the message is out of order.
+     */
+    pub fn next(&self, message_type: u8) -> Option<ConnectionState> {
+        let is = |msg_type: MsgType| message_type == msg_type.to_byte();
+        match self {
+            ConnectionState::AwaitingAuthentication if is(MsgType::FedNonce) => {
+                Some(ConnectionState::AwaitingFedIds)
+            }
+            // A federate that resumes its session skips the rest of the
+            // handshake, including the authentication.
+            ConnectionState::AwaitingAuthentication | ConnectionState::AwaitingFedIds
+                if is(MsgType::Resume) =>
+            {
+                Some(ConnectionState::Running)
+            }
+            ConnectionState::AwaitingFedIds if is(MsgType::FedIds) => {
+                Some(ConnectionState::AwaitingNeighbors)
+            }
+            ConnectionState::AwaitingFedIds if is(MsgType::ControlChannel) => {
+                Some(ConnectionState::ControlChannel)
+            }
+            // The federates on a shared connection authenticate separately.
+            ConnectionState::AwaitingAuthentication | ConnectionState::AwaitingFedIds
+                if is(MsgType::Multiplex) =>
+            {
+                Some(ConnectionState::Multiplexed)
+            }
+            // A federate may present its name and metadata, negotiate its
+            // capabilities, and announce a control connection before its
+            // neighbor structure.
+            ConnectionState::AwaitingNeighbors
+                if is(MsgType::FederateName)
+                    || is(MsgType::FederateMetadata)
+                    || is(MsgType::Capabilities)
+                    || is(MsgType::ControlChannel) =>
+            {
+                Some(ConnectionState::AwaitingNeighbors)
+            }
+            ConnectionState::AwaitingNeighbors if is(MsgType::NeighborStructure) => {
+                Some(ConnectionState::AwaitingUdpPort)
+            }
+            ConnectionState::AwaitingUdpPort if is(MsgType::UdpPort) => {
+                Some(ConnectionState::AwaitingTimestamp)
+            }
+            ConnectionState::AwaitingTimestamp if is(MsgType::Timestamp) => {
+                Some(ConnectionState::Running)
+            }
+            ConnectionState::AwaitingTimestamp
+            | ConnectionState::Running
+            | ConnectionState::Stopping
+                if is(MsgType::Resign) || is(MsgType::Failed) =>
+            {
+                Some(ConnectionState::Closed)
+            }
+            ConnectionState::Running | ConnectionState::Stopping
+                if is(MsgType::TaggedMessage)
+                    || is(MsgType::NextEventTag)
+                    || is(MsgType::LogicalTagComplete)
+                    || is(MsgType::PortAbsent) =>
+            {
+                Some(*self)
+            }
+            ConnectionState::Running | ConnectionState::Stopping if is(MsgType::StopRequest) => {
+                Some(ConnectionState::Stopping)
+            }
+            // A federate replies to a stop request only after the RTI has sent one.
+            ConnectionState::Stopping if is(MsgType::StopRequestReply) => {
+                Some(ConnectionState::Stopping)
+            }
+            _ => None,
+        }
+    }
+
+    /**
+     * Return true if the federate has not yet completed the handshake.
+     */
+    pub fn is_handshaking(&self) -> bool {
+        matches!(
+            self,
+            ConnectionState::AwaitingAuthentication
+                | ConnectionState::AwaitingFedIds
+                | ConnectionState::AwaitingNeighbors
+                | ConnectionState::AwaitingUdpPort
+        )
+    }
+}
+
+/**
+ * A connection to a federate, whose ID is known once the federate has sent
+ * its MsgType::FedIds message.
+ */
+pub struct Connection {
+    fed_id: Option<u16>,
+    state: ConnectionState,
+    control_channel_announced: bool,
+    received_once: Vec<u8>,
+}
+
+impl Connection {
+    pub fn new() -> Connection {
+        Connection {
+            fed_id: None,
+            state: ConnectionState::AwaitingFedIds,
+            control_channel_announced: false,
+            received_once: Vec::new(),
+        }
+    }
+
+    /**
+     * Return true if the federate has announced that it opens a control
+     * connection. See MSG_TYPE_CONTROL_CHANNEL_HEADER_LENGTH.
+     */
+    pub fn control_channel_announced(&self) -> bool {
+        self.control_channel_announced
+    }
+
+    pub fn set_control_channel_announced(&mut self, control_channel_announced: bool) {
+        self.control_channel_announced = control_channel_announced;
+    }
+
+    /**
+     * Record that a message of the given type has been received. Return false
+     * if a federate sends messages of this type at most once on a connection
+     * and one has been received already.
+     */
+    pub fn record_received(&mut self, message_type: u8) -> bool {
+        let is = |msg_type: MsgType| message_type == msg_type.to_byte();
+        if !(is(MsgType::FederateName)
+            || is(MsgType::FederateMetadata)
+            || is(MsgType::Capabilities)
+            || is(MsgType::ControlChannel)
+            || is(MsgType::StopRequest))
+        {
+            return true;
+        }
+        if self.received_once.contains(&message_type) {
+            return false;
+        }
+        self.received_once.push(message_type);
+        true
+    }
+
+    pub fn fed_id(&self) -> Option<u16> {
+        self.fed_id
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub fn set_fed_id(&mut self, fed_id: u16) {
+        self.fed_id = Some(fed_id);
+    }
+
+    pub fn set_state(&mut self, state: ConnectionState) {
+        self.state = state;
+    }
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -10,3 +10,113 @@
 pub const STARTING_PORT: u16 = 15045;
 
 pub const INET_ADDRSTRLEN: usize = 16;
+
+/**
+ * Minimum time in milliseconds that a scheduling thread serving several
+ * federates waits for a message from one federate before polling the next one.
+ */
+pub const FEDERATE_POLL_TIMEOUT_MS: u64 = 1;
+
+/**
+ * Maximum time in milliseconds that a scheduling thread serving several
+ * federates waits for a message in one pass over them while they are idle.
+ */
+pub const FEDERATE_POLL_MAX_TIMEOUT_MS: u64 = 32;
+
+/**
+ * Maximum number of payload bytes of a forwarded message that the RTI holds in
+ * memory at once. Larger payloads are streamed to the destination federate one
+ * chunk at a time.
+ */
+pub const FORWARD_CHUNK_SIZE: usize = 64 * 1024;
+/**
+ * Time in milliseconds between attempts to bind the listening socket while
+ * its address is not available, such as while another process holds it.
+ */
+pub const LISTENER_BIND_RETRY_PERIOD_MS: u64 = 100;
+/**
+ * Number of attempts to bind the listening socket before the RTI gives up.
+ */
+pub const LISTENER_BIND_ATTEMPTS: u32 = 50;
+
+/**
+ * Time in milliseconds between checks of whether the federates that are not
+ * transient have all exited, after which the RTI stops accepting transient
+ * federates.
+ */
+pub const PERSISTENT_EXIT_POLL_PERIOD_MS: u64 = 50;
+
+/**
+ * Maximum time in milliseconds that a thread waiting for physical time to pass
+ * on a condition variable waits before reading a virtual clock again.
+ */
+pub const VIRTUAL_CLOCK_POLL_PERIOD_MS: u64 = 1;
+
+/**
+ * Default time in milliseconds that a newly accepted connection has to
+ * complete its handshake.
+ */
+pub const DEFAULT_HANDSHAKE_TIMEOUT_MS: u64 = 10000;
+
+/**
+ * Trace file written with --tracing, which is the one written by the C RTI.
+ */
+pub const DEFAULT_TRACE_FILE: &str = "rti.lft";
+/**
+ * Default number of scheduling events of each federate that the RTI keeps for
+ * the admin console.
+ */
+pub const DEFAULT_HISTORY_LENGTH: usize = 64;
+/**
+ * Default number of consecutive grants to a federate at the same time with
+ * increasing microsteps after which the RTI warns about a microstep loop.
+ */
+pub const DEFAULT_MICROSTEP_WARNING_THRESHOLD: u32 = 1000;
+
+/**
+ * Number of unanswered keepalive probes after which a federate connection is
+ * considered broken.
+ */
+pub const KEEPALIVE_PROBES: u32 = 3;
+
+/**
+ * Time in milliseconds that the RTI waits for a federate to take the failure
+ * message sent when the RTI shuts down.
+ */
+pub const SHUTDOWN_WRITE_TIMEOUT_MS: u64 = 100;
+
+/**
+ * Period in milliseconds at which the RTI compares the physical time with the
+ * earliest granted tag if --max_lag is given.
+ */
+pub const LAG_CHECK_PERIOD_MS: u64 = 50;
+
+/**
+ * Period in milliseconds at which the RTI looks for federates that have sent
+ * no NET within the grace period if --silent_grace is given.
+ */
+pub const SILENCE_CHECK_PERIOD_MS: u64 = 50;
+
+/**
+ * Period in milliseconds at which the RTI looks for suspended federates whose
+ * resumption window has expired if --resume_window is given.
+ */
+pub const RESUMPTION_CHECK_PERIOD_MS: u64 = 50;
+
+/**
+ * Time in milliseconds that the status server waits for a client to send its
+ * request.
+ */
+pub const STATUS_READ_TIMEOUT_MS: u64 = 5000;
+
+/**
+ * Number of bytes that the RTI reads at once from the socket of a federate
+ * connected over TLS, which holds a TLS record of the largest size.
+ */
+pub const TLS_READ_BUFFER_SIZE: usize = 16 * 1024 + 256;
+
+/**
+ * Number of bytes that the RTI reads at once from a frame on a connection
+ * shared by several federates.
+ */
+pub const MULTIPLEX_READ_BUFFER_SIZE: usize = 16 * 1024;
@@ -0,0 +1,229 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Limit on the rate at which the RTI relays data messages to each
+ * federate, so that one high-volume connection cannot take all the bandwidth
+ * of the RTI and delay the grants to the other federates.
+ *
+ * Each federate with a limit has a bucket of bytes that is refilled at the
+ * configured rate and holds at most the configured burst. A relayed message,
+ * header included, consumes its size in bytes. Control messages, such as
+ * grants, are not limited and consume nothing.
+ *
+ * Under the Queue policy, a message in excess of the rate is read from its
+ * sender at once and kept in the queue of its destination until the rate
+ * allows it, so that the thread that reads from the sender, which also handles
+ * the NETs and LTCs of other federates, never waits for the bucket.
+ */
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/**
+ * What to do with a data message in excess of the rate of its destination.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ExcessEgressPolicy {
+    /// Delay the message until the rate allows it. The message is kept in the
+    /// queue of its destination meanwhile, and the grants to the destination
+    /// are written after it.
+    Queue,
+    /// Drop the message, and warn about the destination once.
+    Drop,
+}
+
+/**
+ * A rate in bytes per second, with the largest burst of bytes allowed.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct EgressRate {
+    bytes_per_second: u64,
+    burst_bytes: u64,
+}
+
+impl EgressRate {
+    /**
+     * Create a rate allowing bursts of the given number of bytes, or, if None,
+     * of up to one second worth of bytes.
+     */
+    pub fn new(bytes_per_second: u64, burst_bytes: Option<u64>) -> EgressRate {
+        let bytes_per_second = bytes_per_second.max(1);
+        EgressRate {
+            bytes_per_second,
+            burst_bytes: burst_bytes.unwrap_or(bytes_per_second).max(1),
+        }
+    }
+
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second
+    }
+}
+
+pub struct EgressShaper {
+    rate: f64,  // Bytes per second.
+    burst: f64, // Bytes.
+    tokens: f64,
+    last_refill: Instant,
+    delayed_messages: u64,
+    total_delay: Duration,
+    max_delay: Duration,
+    dropped_messages: u64,
+    dropped_bytes: u64,
+    flagged: bool,
+}
+
+impl EgressShaper {
+    pub fn new(rate: EgressRate) -> EgressShaper {
+        let burst = rate.burst_bytes as f64;
+        EgressShaper {
+            rate: rate.bytes_per_second as f64,
+            burst,
+            tokens: burst,
+            last_refill: Instant::now(),
+            delayed_messages: 0,
+            total_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            dropped_messages: 0,
+            dropped_bytes: 0,
+            flagged: false,
+        }
+    }
+
+    /**
+     * Consume the bytes of a message to be relayed under the Queue policy,
+     * and return how long to wait before writing it. The bytes are consumed
+     * even if they are not available yet, so that the messages reserved later
+     * wait for this one as well, and a message larger than the burst is
+     * delayed rather than held back forever.
+     */
+    pub fn reserve(&mut self, bytes: usize) -> Duration {
+        self.refill();
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        let delay = Duration::from_secs_f64(-self.tokens / self.rate);
+        self.delayed_messages += 1;
+        self.total_delay += delay;
+        self.max_delay = self.max_delay.max(delay);
+        delay
+    }
+
+    /**
+     * Consume the bytes of a message to be relayed under the Drop policy.
+     * Return false if they are not available, in which case the message is to
+     * be dropped. A message larger than the burst is relayed if the bucket is
+     * full.
+     */
+    pub fn admit(&mut self, bytes: usize) -> bool {
+        self.refill();
+        let bytes = bytes as f64;
+        if self.tokens >= bytes.min(self.burst) {
+            self.tokens -= bytes;
+            return true;
+        }
+        self.dropped_messages += 1;
+        self.dropped_bytes += bytes as u64;
+        false
+    }
+
+    /**
+     * Flag the destination. Return true if it was not flagged yet.
+     */
+    pub fn flag(&mut self) -> bool {
+        !std::mem::replace(&mut self.flagged, true)
+    }
+
+    /**
+     * Return a description of the messages delayed or dropped, or None if
+     * the rate has not been exceeded.
+     */
+    pub fn describe(&self) -> Option<String> {
+        if self.dropped_messages > 0 {
+            Some(format!(
+                "{} relayed message(s) of {} bytes in total dropped in excess of the egress rate",
+                self.dropped_messages, self.dropped_bytes
+            ))
+        } else if self.delayed_messages > 0 {
+            Some(format!(
+                "{} relayed message(s) delayed by the egress rate, {} ms in total, {} ms at most",
+                self.delayed_messages,
+                self.total_delay.as_millis(),
+                self.max_delay.as_millis()
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last_refill = now;
+    }
+}
+
+/**
+ * A write of a delayed message, with the time at which it is due.
+ */
+type DelayedWrite = (Instant, Box<dyn FnOnce() + Send>);
+
+#[derive(Default)]
+struct DelayedWrites {
+    writes: VecDeque<DelayedWrite>,
+    // Whether a thread is writing the delayed messages.
+    draining: bool,
+}
+
+/**
+ * Messages to a federate that are delayed by its egress rate, in the order in
+ * which they were relayed. A thread started when the first one is queued
+ * writes each once it is due, and exits once the queue is empty.
+ */
+#[derive(Default)]
+pub struct EgressQueue {
+    delayed: Mutex<DelayedWrites>,
+}
+
+impl EgressQueue {
+    pub fn new() -> Arc<EgressQueue> {
+        Arc::new(EgressQueue::default())
+    }
+
+    /**
+     * Queue a write to be done once the given time has come, after the writes
+     * queued before it.
+     */
+    pub fn push(self: &Arc<Self>, due: Instant, write: Box<dyn FnOnce() + Send>) {
+        let mut delayed = self.delayed.lock().unwrap();
+        delayed.writes.push_back((due, write));
+        if delayed.draining {
+            return;
+        }
+        delayed.draining = true;
+        let queue = self.clone();
+        thread::spawn(move || queue.drain());
+    }
+
+    fn drain(&self) {
+        loop {
+            let (due, write) = {
+                let mut delayed = self.delayed.lock().unwrap();
+                match delayed.writes.pop_front() {
+                    Some(delayed_write) => delayed_write,
+                    None => {
+                        delayed.draining = false;
+                        return;
+                    }
+                }
+            };
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+            write();
+        }
+    }
+}
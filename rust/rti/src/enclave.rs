@@ -1,8 +1,14 @@
-use crate::net_common::MsgType;
+use crate::fault::Fault;
+use crate::federate::federate_label;
+use crate::lft::TraceEvent;
+use crate::net_common::{MsgType, MSG_TYPE_DOWNSTREAM_NEXT_EVENT_TAG_LENGTH};
 use crate::net_util::NetUtil;
+use crate::notifier::QueuedGrant;
 use crate::tag;
 use crate::tag::{Instant, Interval, Tag};
-use crate::FedState::*;
+use crate::topology;
+use crate::trace::{debug, debug_for, error, trace, warn};
+use crate::visited::VisitedSet;
 use crate::Federate;
 /**
  * @file enclave.rs
@@ -20,14 +26,9 @@ use crate::Federate;
  */
 use crate::FederationRTI;
 
-use std::io::Write;
 use std::mem;
-use std::sync::{Arc, Condvar, Mutex};
-
-enum ExecutionMode {
-    FAST,
-    REALTIME,
-}
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum FedState {
@@ -36,9 +37,11 @@ pub enum FedState {
     Pending,      // Waiting for upstream federates.
 }
 
-struct TagAdvanceGrant {
+#[derive(Clone)]
+pub struct TagAdvanceGrant {
     tag: Tag,
     is_provisional: bool,
+    justification: String, // Why the grant is safe, for step mode.
 }
 
 impl TagAdvanceGrant {
@@ -46,6 +49,7 @@ impl TagAdvanceGrant {
         TagAdvanceGrant {
             tag,
             is_provisional,
+            justification: String::new(),
         }
     }
 
@@ -64,41 +68,302 @@ impl TagAdvanceGrant {
     pub fn set_provisional(&mut self, is_provisional: bool) {
         self.is_provisional = is_provisional;
     }
+
+    pub fn justification(&self) -> &str {
+        &self.justification
+    }
+
+    pub fn set_justification(&mut self, justification: String) {
+        self.justification = justification;
+    }
+
+    /**
+     * Return a description such as "TAG (10,0)" with the time relative to the
+     * given start time.
+     */
+    pub fn describe(&self, start_time: Instant) -> String {
+        let kind = if self.is_provisional { "PTAG" } else { "TAG" };
+        format!("{} {}", kind, Enclave::relative_tag(&self.tag, start_time))
+    }
 }
 
-pub struct Enclave {
-    id: u16,                         // ID of this enclave.
+/**
+ * A grant computed while the RTI is in step mode, which is sent only once an
+ * operator releases it.
+ */
+#[derive(Clone)]
+pub struct PendingGrant {
+    fed_id: u16,
+    grant: TagAdvanceGrant,
+}
+
+impl PendingGrant {
+    pub fn new(fed_id: u16, grant: TagAdvanceGrant) -> PendingGrant {
+        PendingGrant { fed_id, grant }
+    }
+
+    pub fn fed_id(&self) -> u16 {
+        self.fed_id
+    }
+
+    pub fn grant(&self) -> &TagAdvanceGrant {
+        &self.grant
+    }
+
+    /**
+     * Return true if both grants send the same message to the same federate.
+     */
+    pub fn same_as(&self, other: &PendingGrant) -> bool {
+        self.fed_id == other.fed_id
+            && self.grant.is_provisional == other.grant.is_provisional
+            && self.grant.tag == other.grant.tag
+    }
+}
+
+/**
+ * A counter of an enclave that is incremented whenever the enclave changes a
+ * tag or a part of the topology on which grant decisions depend, i.e., its
+ * NET, LTC, grants, state, or upstream connections.
+ */
+#[derive(Default)]
+pub struct GrantGeneration(AtomicU64);
+
+impl GrantGeneration {
+    pub fn new() -> GrantGeneration {
+        GrantGeneration::default()
+    }
+
+    pub fn current(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn advance(&self) {
+        self.0.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/**
+ * The key of a grant decision for a federate: the generations of the federate
+ * and of the federates upstream of it, directly or transitively, at which it
+ * was made, and the start time. The decision depends on nothing else, so the
+ * same inputs always lead to the same decision, and a change at a federate
+ * that is not upstream does not invalidate it.
+ */
+#[derive(PartialEq, Clone)]
+pub struct GrantInputs {
+    generations: Vec<(u16, u64)>,
+    start_time: Instant,
+}
+
+impl GrantInputs {
+    pub fn collect(enclaves: &[Federate], fed_id: u16, start_time: Instant) -> GrantInputs {
+        let mut generations = Vec::new();
+        let mut visited = VisitedSet::new(enclaves.len());
+        visited.insert(usize::from(fed_id));
+        let mut stack = vec![fed_id];
+        while let Some(id) = stack.pop() {
+            let e = enclaves[usize::from(id)].e();
+            generations.push((id, e.generation.current()));
+            for &upstream_id in e.upstream() {
+                // FIXME: Replace "as usize" properly.
+                if !visited.contains(upstream_id as usize) {
+                    visited.insert(upstream_id as usize);
+                    stack.push(upstream_id as u16);
+                }
+            }
+        }
+        GrantInputs {
+            generations,
+            start_time,
+        }
+    }
+}
+
+/**
+ * Where the transitive next event tag of an enclave comes from, as found by
+ * Enclave::traced_transitive_next_event.
+ */
+struct NextEventOrigin {
+    // The tag that bounds the result, e.g. "next event" or "completed tag".
+    reason: &'static str,
+    // IDs of the enclaves from the one at which the tag was found down to the
+    // one that was asked, each with the delay of the connection leading to it.
+    // Empty if the candidate given by the caller bounds the result.
+    path: Vec<(u16, Interval)>,
+}
+
+impl NextEventOrigin {
+    fn candidate() -> NextEventOrigin {
+        NextEventOrigin {
+            reason: "candidate",
+            path: Vec::new(),
+        }
+    }
+
+    fn at(reason: &'static str, fed_id: u16) -> NextEventOrigin {
+        NextEventOrigin {
+            reason,
+            path: vec![(fed_id, None)],
+        }
+    }
+
+    /**
+     * Return the ID of the enclave at which the tag was found, or None if the
+     * candidate bounds the result.
+     */
+    fn origin_id(&self) -> Option<u16> {
+        self.path.first().map(|(fed_id, _)| *fed_id)
+    }
+
+    /**
+     * Extend the path by a connection with the given delay to a downstream
+     * enclave.
+     */
+    fn through(mut self, fed_id: u16, delay: Interval) -> NextEventOrigin {
+        self.path.push((fed_id, delay));
+        self
+    }
+
+    fn describe(&self) -> String {
+        let Some(((origin_id, _), hops)) = self.path.split_first() else {
+            return String::from("next event of the upstream federate");
+        };
+        let mut described = format!("{} of federate {}", self.reason, federate_label(*origin_id));
+        if !hops.is_empty() {
+            let path: Vec<String> = hops
+                .iter()
+                .map(|(fed_id, delay)| {
+                    format!(
+                        "{} (delay {})",
+                        federate_label(*fed_id),
+                        describe_delay(*delay)
+                    )
+                })
+                .collect();
+            described.push_str(&format!(" via {}", path.join(" -> ")));
+        }
+        described
+    }
+}
+
+/**
+ * Describe why the given federate holds back the federates downstream of it
+ * if it has started but sent no NET, or return None otherwise.
+ */
+fn describe_silence(enclaves: &[Federate], fed_id: u16) -> Option<String> {
+    let fed = &enclaves[usize::from(fed_id)];
+    if !fed.silent() {
+        return None;
+    }
+    let presumption = if fed.presumed_idle() {
+        ", and is presumed to have a NET of FOREVER"
+    } else {
+        ", so it may be deadlocked"
+    };
+    Some(format!(
+        "federate {} has sent no NET since it started{}",
+        federate_label(fed_id),
+        presumption
+    ))
+}
+
+/**
+ * Describe the delay of a connection, where NEVER encodes no delay.
+ */
+fn describe_delay(delay: Interval) -> String {
+    match delay {
+        _ if delay == tag::NO_DELAY => String::from("none"),
+        Some(delay_ns) => format!("{} ns", delay_ns),
+        None => String::from("unknown"),
+    }
+}
+
+/**
+ * The tags and the state of an enclave that the grant decisions read and
+ * change, guarded by the lock of the enclave.
+ */
+struct EnclaveTags {
     completed: Tag, // The largest logical tag completed by the federate (or NEVER if no LTC has been received).
     last_granted: Tag, // The maximum Tag that has been granted so far (or NEVER if none granted)
     last_provisionally_granted: Tag, // The maximum PTAG that has been provisionally granted (or NEVER if none granted)
     next_event: Tag, // Most recent NET received from the federate (or NEVER if none received).
     state: FedState, // State of the federate.
-    upstream: Vec<i32>, // Array of upstream federate ids.
+    microstep_streak: u32, // Number of consecutive grants at the same time with increasing microsteps.
+    last_dnet: Tag,        // Most recent DNET sent to the federate (or NEVER if none sent).
+}
+
+impl EnclaveTags {
+    fn new() -> EnclaveTags {
+        EnclaveTags {
+            completed: Tag::never_tag(),
+            last_granted: Tag::never_tag(),
+            last_provisionally_granted: Tag::never_tag(),
+            next_event: Tag::never_tag(),
+            state: FedState::NotConnected,
+            microstep_streak: 0,
+            last_dnet: Tag::never_tag(),
+        }
+    }
+}
+
+/**
+ * An enclave, i.e., a federate as far as the grants are concerned. Its tags
+ * and state are behind a lock of its own, so that the threads handling NET and
+ * LTC messages can update them while holding the lock of the RTI state only
+ * shared. The neighbor structure and the configuration only change with the
+ * lock of the RTI state held exclusively.
+ */
+pub struct Enclave {
+    id: u16,                       // ID of this enclave.
+    tags: Mutex<EnclaveTags>,      // NET, LTC, grants, and state of the enclave.
+    upstream: Vec<i32>,            // Array of upstream federate ids.
     upstream_delay: Vec<Interval>, // Minimum delay on connections from upstream federates.
     // Here, NEVER encodes no delay. 0LL is a microstep delay.
-    num_upstream: i32,    // Size of the array of upstream federates and delays.
-    downstream: Vec<i32>, // Array of downstream federate ids.
-    num_downstream: i32,  // Size of the array of downstream federates.
-    mode: ExecutionMode,  // FAST or REALTIME.
-                          // TODO: lf_cond_t next_event_condition; // Condition variable used by enclaves to notify an enclave
-                          // that it's call to next_event_tag() should unblock.
+    num_upstream: i32,       // Size of the array of upstream federates and delays.
+    downstream: Vec<i32>,    // Array of downstream federate ids.
+    num_downstream: i32,     // Size of the array of downstream federates.
+    decentralized: bool,     // Indicates that the federate uses decentralized coordination.
+    transient: bool, // Indicates that the federate may join after the start and resign and rejoin.
+    start_tag: Tag,  // Effective start tag of the federate (or NEVER if it has not started).
+    breakpoint: Option<Tag>, // Tag relative to the start time beyond which this federate is not granted (or None).
+    generation: GrantGeneration, // Advanced on each change that may change a grant decision.
+                             // TODO: lf_cond_t next_event_condition; // Condition variable used by enclaves to notify an enclave
+                             // that it's call to next_event_tag() should unblock.
 }
 
 impl Enclave {
+    /**
+     * Format a tag relative to the start time for logging, naming NEVER and
+     * FOREVER instead of printing an overflowed time.
+     */
+    pub fn relative_tag(tag: &Tag, start_time: Instant) -> String {
+        if tag.time() == tag::NEVER {
+            String::from("NEVER")
+        } else if tag.time() == tag::FOREVER {
+            String::from("FOREVER")
+        } else {
+            format!(
+                "({},{})",
+                tag::format_time(tag.time(), start_time),
+                tag.microstep()
+            )
+        }
+    }
+
     pub fn new() -> Enclave {
         Enclave {
             id: 0,
-            completed: Tag::never_tag(),
-            last_granted: Tag::never_tag(),
-            last_provisionally_granted: Tag::never_tag(),
-            next_event: Tag::never_tag(),
-            state: FedState::NotConnected,
+            tags: Mutex::new(EnclaveTags::new()),
             upstream: Vec::new(),
             upstream_delay: Vec::new(),
             num_upstream: 0,
             downstream: Vec::new(),
             num_downstream: 0,
-            mode: ExecutionMode::REALTIME,
+            decentralized: false,
+            transient: false,
+            start_tag: Tag::never_tag(),
+            breakpoint: None,
+            generation: GrantGeneration::new(),
             // TODO: lf_cond_t next_event_condition;
         }
     }
@@ -113,24 +378,32 @@ impl Enclave {
         self.id
     }
 
+    fn tags(&self) -> MutexGuard<'_, EnclaveTags> {
+        self.tags.lock().unwrap()
+    }
+
     pub fn completed(&self) -> Tag {
-        self.completed.clone()
+        self.tags().completed.clone()
     }
 
     pub fn last_granted(&self) -> Tag {
-        self.last_granted.clone()
+        self.tags().last_granted.clone()
     }
 
     pub fn last_provisionally_granted(&self) -> Tag {
-        self.last_provisionally_granted.clone()
+        self.tags().last_provisionally_granted.clone()
     }
 
     pub fn next_event(&self) -> Tag {
-        self.next_event.clone()
+        self.tags().next_event.clone()
+    }
+
+    pub fn last_dnet(&self) -> Tag {
+        self.tags().last_dnet.clone()
     }
 
     pub fn state(&self) -> FedState {
-        self.state.clone()
+        self.tags().state.clone()
     }
 
     pub fn upstream(&self) -> &Vec<i32> {
@@ -153,36 +426,157 @@ impl Enclave {
         self.num_downstream
     }
 
-    pub fn set_last_granted(&mut self, tag: Tag) {
-        self.last_granted = tag;
+    /**
+     * Return true if the federate uses decentralized coordination. Such a
+     * federate takes part in the startup and the stop protocol, but it neither
+     * waits for grants nor constrains the grants of its downstream federates.
+     */
+    pub fn decentralized(&self) -> bool {
+        self.decentralized
+    }
+
+    pub fn transient(&self) -> bool {
+        self.transient
+    }
+
+    /**
+     * Return true if the federate takes no part in the execution, because it
+     * is not connected or because it is a transient federate that has not
+     * been sent its start tag yet.
+     */
+    pub fn absent(&self) -> bool {
+        self.state() == FedState::NotConnected
+            || (self.transient && self.start_tag.time() == tag::NEVER)
+    }
+
+    pub fn start_tag(&self) -> Tag {
+        self.start_tag.clone()
+    }
+
+    pub fn breakpoint(&self) -> Option<Tag> {
+        self.breakpoint.clone()
+    }
+
+    pub fn set_decentralized(&mut self, decentralized: bool) {
+        self.decentralized = decentralized;
+        self.generation.advance();
+    }
+
+    pub fn set_transient(&mut self, transient: bool) {
+        self.transient = transient;
+        self.generation.advance();
+    }
+
+    pub fn set_start_tag(&mut self, start_tag: Tag) {
+        if start_tag != self.start_tag {
+            self.start_tag = start_tag;
+            self.generation.advance();
+        }
+    }
+
+    /**
+     * Forget the tags and the neighbor structure of a previous connection of
+     * a transient federate that joins again, which reports them anew.
+     */
+    pub fn reset_for_rejoin(&mut self) {
+        let state = self.state();
+        *self.tags() = EnclaveTags {
+            state,
+            ..EnclaveTags::new()
+        };
+        self.start_tag = Tag::never_tag();
+        self.upstream.clear();
+        self.upstream_delay.clear();
+        self.num_upstream = 0;
+        self.downstream.clear();
+        self.num_downstream = 0;
+        self.generation.advance();
+    }
+
+    pub fn set_breakpoint(&mut self, breakpoint: Option<Tag>) {
+        self.breakpoint = breakpoint;
+    }
+
+    /**
+     * Count a grant (TAG or PTAG) of the given tag toward the streak of grants
+     * at the same time with increasing microsteps. Return true if this grant
+     * makes the number of microstep increments in the streak reach the given
+     * threshold. Must be called before the grant is recorded.
+     */
+    pub fn count_microstep_grant(&self, tag: &Tag, threshold: u32) -> bool {
+        let mut tags = self.tags();
+        let previous = if tags.last_granted >= tags.last_provisionally_granted {
+            tags.last_granted.clone()
+        } else {
+            tags.last_provisionally_granted.clone()
+        };
+        if previous.time() == tag::NEVER || tag.time() != previous.time() {
+            tags.microstep_streak = 0;
+        } else if tag.microstep() > previous.microstep() {
+            tags.microstep_streak += 1;
+            return tags.microstep_streak == threshold;
+        }
+        // A TAG that follows a PTAG of the same tag neither extends nor breaks the streak.
+        false
+    }
+
+    pub fn set_last_granted(&self, tag: Tag) {
+        let mut tags = self.tags();
+        if tag != tags.last_granted {
+            tags.last_granted = tag;
+            self.generation.advance();
+        }
+    }
+
+    pub fn set_last_provisionally_granted(&self, tag: Tag) {
+        let mut tags = self.tags();
+        if tag != tags.last_provisionally_granted {
+            tags.last_provisionally_granted = tag;
+            self.generation.advance();
+        }
     }
 
-    pub fn set_last_provisionally_granted(&mut self, tag: Tag) {
-        self.last_provisionally_granted = tag;
+    pub fn set_last_dnet(&self, tag: Tag) {
+        self.tags().last_dnet = tag;
     }
 
-    pub fn set_next_event(&mut self, next_event_tag: Tag) {
-        self.next_event = next_event_tag;
+    pub fn set_next_event(&self, next_event_tag: Tag) {
+        let mut tags = self.tags();
+        if next_event_tag != tags.next_event {
+            tags.next_event = next_event_tag;
+            self.generation.advance();
+        }
     }
 
-    pub fn set_state(&mut self, state: FedState) {
-        self.state = state;
+    pub fn set_state(&self, state: FedState) {
+        let mut tags = self.tags();
+        if state != tags.state {
+            tags.state = state;
+            self.generation.advance();
+        }
     }
 
     pub fn set_upstream_id_at(&mut self, upstream_id: u16, idx: usize) {
         self.upstream.insert(idx, upstream_id as i32);
+        self.generation.advance();
     }
 
-    pub fn set_completed(&mut self, completed: Tag) {
-        self.completed = completed.clone()
+    pub fn set_completed(&self, completed: Tag) {
+        let mut tags = self.tags();
+        if completed != tags.completed {
+            tags.completed = completed;
+            self.generation.advance();
+        }
     }
 
     pub fn set_upstream_delay_at(&mut self, upstream_delay: tag::Interval, idx: usize) {
         self.upstream_delay.insert(idx, upstream_delay);
+        self.generation.advance();
     }
 
     pub fn set_num_upstream(&mut self, num_upstream: i32) {
         self.num_upstream = num_upstream;
+        self.generation.advance();
     }
 
     pub fn set_downstream_id_at(&mut self, downstream_id: u16, idx: usize) {
@@ -193,31 +587,31 @@ impl Enclave {
         self.num_downstream = num_downstream;
     }
 
+    /**
+     * Record the next event tag of the given enclave and send the grants that
+     * it allows to the enclave and to the enclaves downstream of it. Called
+     * with the lock of the RTI state held, like all functions that decide or
+     * send grants, so that each decision is made on a consistent state and
+     * the state is updated before another thread decides.
+     */
     pub fn update_enclave_next_event_tag_locked(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        locked_rti: &FederationRTI,
         fed_id: u16,
         next_event_tag: Tag,
         start_time: Instant,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
     ) {
-        let id;
-        let num_upstream;
-        let number_of_enclaves;
-        {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            number_of_enclaves = locked_rti.number_of_enclaves();
-            let idx: usize = fed_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            let e = fed.enclave();
-            e.set_next_event(next_event_tag.clone());
-
-            id = e.id();
-            num_upstream = e.num_upstream();
-        }
-        println!(
+        let number_of_enclaves = locked_rti.number_of_enclaves();
+        let idx: usize = fed_id.into();
+        let fed: &Federate = &locked_rti.federates()[idx];
+        let e = fed.e();
+        e.set_next_event(next_event_tag.clone());
+
+        let id = e.id();
+        let num_upstream = e.num_upstream();
+        debug!(
             "RTI: Updated the recorded next event tag for federate/enclave {} to ({},{})",
             id,
-            next_event_tag.time() - start_time,
+            tag::format_time(next_event_tag.time(), start_time),
             next_event_tag.microstep()
         );
 
@@ -225,61 +619,283 @@ impl Enclave {
         // If the enclave has no upstream enclaves, then it does not wait for
         // nor expect a reply. It just proceeds to advance time.
         if num_upstream > 0 {
-            Self::notify_advance_grant_if_safe(
-                _f_rti.clone(),
-                fed_id,
-                number_of_enclaves,
-                start_time,
-                sent_start_time.clone(),
-            );
+            Self::notify_advance_grant_if_safe(locked_rti, fed_id, number_of_enclaves, start_time);
         }
         // Check downstream enclaves to see whether they should now be granted a TAG.
-        // To handle cycles, need to create a boolean array to keep
-        // track of which upstream enclaves have been visited.
-        let mut visited = vec![false as bool; number_of_enclaves as usize]; // Initializes to 0.
         Self::notify_downstream_advance_grant_if_safe(
-            _f_rti.clone(),
+            locked_rti,
+            fed_id,
+            number_of_enclaves,
+            start_time,
+        );
+        // The NETs that the upstream federates need to send may have changed.
+        Self::notify_upstream_downstream_next_event_tag(locked_rti, fed_id, start_time);
+    }
+
+    /**
+     * Record a next event tag that the RTI presumes for the given enclave
+     * rather than received from it, and notify the enclaves downstream of it.
+     * Unlike a received NET, it does not lead to a grant to the enclave
+     * itself, which has not asked for one.
+     */
+    pub fn presume_enclave_next_event_tag_locked(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        next_event_tag: Tag,
+        start_time: Instant,
+    ) {
+        let number_of_enclaves = locked_rti.number_of_enclaves();
+        let idx: usize = fed_id.into();
+        locked_rti.federates()[idx]
+            .e()
+            .set_next_event(next_event_tag.clone());
+        debug!(
+            "RTI: Presumed the next event tag for federate/enclave {} to be ({},{})",
+            fed_id,
+            tag::format_time(next_event_tag.time(), start_time),
+            next_event_tag.microstep()
+        );
+        Self::notify_downstream_advance_grant_if_safe(
+            locked_rti,
             fed_id,
             number_of_enclaves,
             start_time,
-            &mut visited,
-            sent_start_time,
         );
+        Self::notify_upstream_downstream_next_event_tag(locked_rti, fed_id, start_time);
+    }
+
+    /**
+     * Send a DNET to each federate upstream of the given one whose DNET has
+     * changed, unless DNET messages are disabled. See
+     * MSG_TYPE_DOWNSTREAM_NEXT_EVENT_TAG_LENGTH.
+     */
+    fn notify_upstream_downstream_next_event_tag(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        start_time: Instant,
+    ) {
+        if locked_rti.dnet_disabled() {
+            return;
+        }
+        let Some(topology) = locked_rti.topology() else {
+            return;
+        };
+        let upstream: Vec<u16> = (0..locked_rti.number_of_enclaves() as u16)
+            .filter(|&upstream_id| {
+                upstream_id != fed_id && topology.min_delay(upstream_id, fed_id).is_some()
+            })
+            .collect();
+        let mut dnets = Vec::new();
+        for upstream_id in upstream {
+            let fed: &Federate = &locked_rti.federates()[usize::from(upstream_id)];
+            let e = fed.e();
+            if e.num_upstream() > 0 || e.decentralized() || e.state() != FedState::Granted {
+                continue;
+            }
+            let last_dnet = e.last_dnet();
+            let dnet = Self::downstream_next_event_tag(locked_rti, upstream_id, start_time);
+            if dnet != last_dnet {
+                dnets.push((upstream_id, dnet));
+            }
+        }
+        for (upstream_id, dnet) in dnets {
+            Self::notify_downstream_next_event_tag(locked_rti, upstream_id, dnet, start_time);
+        }
+    }
+
+    /**
+     * Return the DNET of the given federate, which is the latest tag up to
+     * which none of its downstream federates needs its NETs. A NET of the
+     * federate matters to a downstream federate only if a message sent at that
+     * tag could arrive before the next event tag of the downstream federate.
+     * As the minimum delays only count time, the DNET is the last tag before
+     * the earliest such arrival time.
+     */
+    fn downstream_next_event_tag(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        start_time: Instant,
+    ) -> Tag {
+        let Some(topology) = locked_rti.topology() else {
+            return Tag::never_tag();
+        };
+        let Some(downstream) = topology.downstream_closure(fed_id) else {
+            return Tag::never_tag();
+        };
+        let min_delays: Vec<(u16, i64)> = downstream
+            .iter()
+            .filter_map(|&downstream_id| {
+                topology
+                    .min_delay(fed_id, downstream_id)
+                    .map(|min_delay| (downstream_id, min_delay))
+            })
+            .collect();
+        let mut result = Tag::forever_tag();
+        for (downstream_id, min_delay) in min_delays {
+            let e = locked_rti.federates()[usize::from(downstream_id)].e();
+            if e.absent() || e.decentralized() {
+                continue;
+            }
+            let next_event = e.next_event();
+            if next_event.time() == tag::FOREVER {
+                continue;
+            }
+            let candidate = match next_event
+                .time()
+                .checked_sub(min_delay)
+                .and_then(|time| time.checked_sub(1))
+            {
+                Some(time) if next_event.time() != tag::NEVER && time >= start_time => {
+                    Tag::new(time, tag::FOREVER_MICROSTEP)
+                }
+                _ => Tag::never_tag(),
+            };
+            if candidate < result {
+                result = candidate;
+            }
+        }
+        result
+    }
+
+    /**
+     * Send the given DNET to the federate on the connection on which it sends
+     * its NETs, and record it as the last DNET of the federate.
+     */
+    fn notify_downstream_next_event_tag(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        dnet: Tag,
+        start_time: Instant,
+    ) {
+        let mut buffer = vec![0u8; MSG_TYPE_DOWNSTREAM_NEXT_EVENT_TAG_LENGTH];
+        buffer[0] = MsgType::DownstreamNextEventTag.to_byte();
+        NetUtil::encode_int64(dnet.time(), &mut buffer, 1);
+        NetUtil::encode_int32(
+            dnet.microstep() as i32,
+            &mut buffer,
+            1 + mem::size_of::<i64>(),
+        );
+        {
+            let fed: &Federate = &locked_rti.federates()[usize::from(fed_id)];
+            let Some(stream) = fed.stream() else {
+                return;
+            };
+            let outgoing_turn = fed.outgoing_lock().reserve();
+            if let Err(e) = outgoing_turn.write(stream, &buffer, fed.wire_dump()) {
+                // The scheduling thread of the federate handles the closed
+                // connection.
+                error!(
+                    "RTI failed to send the DNET to federate {}: {}.",
+                    federate_label(fed_id),
+                    e
+                );
+                return;
+            }
+        }
+        locked_rti.federates()[usize::from(fed_id)]
+            .e()
+            .set_last_dnet(dnet.clone());
+        debug_for!(
+            fed_id,
+            "RTI sent to federate {} the Downstream Next Event Tag (DNET) {}.",
+            federate_label(fed_id),
+            Self::relative_tag(&dnet, start_time)
+        );
+        locked_rti.record_event("dnet", Some(fed_id), Some((&dnet, start_time)), None);
+    }
+
+    /**
+     * Send every federate the grant that the NET and LTC messages received so
+     * far allow. Used when grants that were held back may be sent again. Each
+     * grant is decided under the grant lock of the component of the federate,
+     * so none may be held by the caller.
+     */
+    pub fn grant_all(locked_rti: &FederationRTI, start_time: Instant) {
+        let mut constrained_federates = Vec::new();
+        let mut isolated_federates = Vec::new();
+        let number_of_enclaves = locked_rti.number_of_enclaves();
+        let unconstrained_tag = locked_rti.unconstrained_tag(start_time);
+        for fed in locked_rti.federates().iter() {
+            let e = fed.e();
+            // Federates that have not been sent the start time are granted
+            // when they receive it.
+            if e.state() != FedState::Granted || e.decentralized() {
+                continue;
+            }
+            if e.num_upstream() == 0 && e.num_downstream() == 0 {
+                isolated_federates.push(e.id());
+            } else {
+                constrained_federates.push(e.id());
+            }
+        }
+        for id in isolated_federates {
+            let _grants = locked_rti.lock_grants(id);
+            Self::notify_tag_advance_grant(
+                locked_rti,
+                id,
+                unconstrained_tag.clone(),
+                "the federate has no upstream or downstream federates",
+                start_time,
+            );
+        }
+        for id in constrained_federates {
+            let _grants = locked_rti.lock_grants(id);
+            Self::notify_advance_grant_if_safe(locked_rti, id, number_of_enclaves, start_time);
+        }
     }
 
-    fn notify_advance_grant_if_safe(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+    pub fn notify_advance_grant_if_safe(
+        locked_rti: &FederationRTI,
         fed_id: u16,
         number_of_enclaves: i32,
         start_time: Instant,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
     ) {
         let grant =
-            Self::tag_advance_grant_if_safe(_f_rti.clone(), fed_id, number_of_enclaves, start_time);
-        if Tag::lf_tag_compare(&grant.tag(), &Tag::never_tag()) != 0 {
+            Self::tag_advance_grant_if_safe(locked_rti, fed_id, number_of_enclaves, start_time);
+        if grant.tag() != Tag::never_tag() {
             if grant.is_provisional() {
                 Self::notify_provisional_tag_advance_grant(
-                    _f_rti,
+                    locked_rti,
                     fed_id,
                     number_of_enclaves,
                     grant.tag(),
+                    grant.justification(),
                     start_time,
-                    sent_start_time,
                 );
             } else {
                 Self::notify_tag_advance_grant(
-                    _f_rti,
+                    locked_rti,
                     fed_id,
                     grant.tag(),
+                    grant.justification(),
                     start_time,
-                    sent_start_time,
                 );
             }
         }
     }
 
+    /**
+     * Return the grant that is safe to send to the given federate, reusing the
+     * decision made for the same inputs if none of them has changed since.
+     */
     fn tag_advance_grant_if_safe(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        number_of_enclaves: i32,
+        start_time: Instant,
+    ) -> TagAdvanceGrant {
+        let inputs = GrantInputs::collect(locked_rti.federates(), fed_id, start_time);
+        if let Some(grant) = locked_rti.cached_grant_decision(fed_id, &inputs) {
+            return grant;
+        }
+        let grant =
+            Self::compute_tag_advance_grant(locked_rti, fed_id, number_of_enclaves, start_time);
+        locked_rti.cache_grant_decision(fed_id, inputs, grant.clone());
+        grant
+    }
+
+    fn compute_tag_advance_grant(
+        locked_rti: &FederationRTI,
         fed_id: u16,
         number_of_enclaves: i32,
         start_time: Instant,
@@ -289,42 +905,72 @@ impl Enclave {
         // Find the earliest LTC of upstream enclaves (M).
         {
             let mut min_upstream_completed = Tag::forever_tag();
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut min_upstream_id = None;
+            let mut min_upstream_absent = false;
             let idx: usize = fed_id.into();
-            let enclaves = locked_rti.enclaves();
+            let enclaves = locked_rti.federates();
             let fed = &enclaves[idx];
             let e = fed.e();
+            // A federate using decentralized coordination does not wait for grants.
+            if e.decentralized() {
+                return result;
+            }
             let upstreams = e.upstream();
             let upstream_delay = e.upstream_delay();
             for j in 0..upstreams.len() {
                 let delay = upstream_delay[j];
                 // FIXME: Replace "as usize" properly.
                 let upstream = &enclaves[upstreams[j] as usize].e();
-                // Ignore this enclave if it no longer connected.
-                if upstream.state() == FedState::NotConnected {
+                // Ignore this enclave if it no longer connected or does not
+                // take part in centralized coordination.
+                if upstream.decentralized() || (upstream.absent() && !upstream.transient()) {
                     continue;
                 }
 
-                // Adjust by the "after" delay.
-                // Note that "no delay" is encoded as NEVER,
-                // whereas one microstep delay is encoded as 0LL.
-                let candidate = Tag::lf_delay_strict(&upstream.completed(), delay);
+                let candidate = if upstream.absent() {
+                    // An absent transient federate may join again, with a
+                    // start tag after the grants of this enclave, so this
+                    // enclave is not granted beyond its next event.
+                    e.next_event()
+                } else {
+                    // Adjust by the "after" delay.
+                    // Note that "no delay" is encoded as NEVER,
+                    // whereas one microstep delay is encoded as 0LL.
+                    Tag::lf_delay_strict(&upstream.completed(), delay)
+                };
 
-                if Tag::lf_tag_compare(&candidate, &min_upstream_completed) < 0 {
+                if candidate < min_upstream_completed {
                     min_upstream_completed = candidate.clone();
+                    min_upstream_id = Some(upstream.id());
+                    min_upstream_absent = upstream.absent();
                 }
             }
-            println!(
+            trace!(
                 "Minimum upstream LTC for federate/enclave {} is ({},{}) (adjusted by after delay).",
                 e.id(),
                 // FIXME: Check the below calculation
                 min_upstream_completed.time(), // - start_time,
                 min_upstream_completed.microstep()
             );
-            if Tag::lf_tag_compare(&min_upstream_completed, &e.last_granted()) > 0
-                && Tag::lf_tag_compare(&min_upstream_completed, &e.next_event()) >= 0
+            if min_upstream_completed > e.last_granted() && min_upstream_completed >= e.next_event()
             // The enclave has to advance its tag
             {
+                result.set_justification(match min_upstream_id {
+                    Some(upstream_id) if min_upstream_absent => format!(
+                        "transient federate {} is absent, so the grant is the next event {}",
+                        federate_label(upstream_id),
+                        Self::relative_tag(&e.next_event(), start_time)
+                    ),
+                    Some(upstream_id) => format!(
+                        "the earliest tag completed by an upstream federate is {} at federate {} (adjusted by the after delay), which is not before the next event {}",
+                        Self::relative_tag(&min_upstream_completed, start_time),
+                        federate_label(upstream_id),
+                        Self::relative_tag(&e.next_event(), start_time)
+                    ),
+                    None => String::from(
+                        "no upstream federate takes part in centralized coordination",
+                    ),
+                });
                 result.set_tag(min_upstream_completed);
                 return result;
             }
@@ -337,9 +983,9 @@ impl Enclave {
         // Find the earliest event time of each such upstream enclave,
         // adjusted by delays on the connections.
 
-        // To handle cycles, need to create a boolean array to keep
-        // track of which upstream enclave have been visited.
-        let mut visited = vec![false as bool; number_of_enclaves.try_into().unwrap()];
+        // To handle cycles, need to keep track of which upstream enclaves
+        // have been visited.
+        let mut visited = VisitedSet::new(number_of_enclaves.try_into().unwrap());
 
         // Find the tag of the earliest possible incoming message from
         // upstream enclaves.
@@ -351,7 +997,10 @@ impl Enclave {
         // when potentially sending a PTAG because we must not send a PTAG for a tag at which data may
         // still be received over nonzero-delay connections.
         let mut t_d_zero_delay = Tag::forever_tag();
-        println!(
+        // The upstream federates at which t_d_nonzero_delay and t_d_zero_delay were found.
+        let mut t_d_nonzero_delay_id = None;
+        let mut t_d_zero_delay_id = None;
+        trace!(
             "NOTE: FOREVER is displayed as ({},{}) and NEVER as ({},{})",
             i64::MAX - start_time,
             u32::MAX,
@@ -364,9 +1013,8 @@ impl Enclave {
         let last_provisionally_granted_tag;
         let last_granted_tag;
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
             let idx: usize = fed_id.into();
-            let enclaves = locked_rti.enclaves();
+            let enclaves = locked_rti.federates();
             let fed = &enclaves[idx];
             let e = fed.e();
             next_event_tag = e.next_event();
@@ -374,10 +1022,12 @@ impl Enclave {
             last_granted_tag = e.last_granted();
             let upstreams = e.upstream();
             for j in 0..upstreams.len() {
-                let upstream = &enclaves[j].e();
+                // FIXME: Replace "as usize" properly.
+                let upstream = &enclaves[upstreams[j] as usize].e();
 
-                // Ignore this enclave if it is no longer connected.
-                if upstream.state() == FedState::NotConnected {
+                // Ignore this enclave if it is no longer connected or does not
+                // take part in centralized coordination.
+                if upstream.absent() || upstream.decentralized() {
                     continue;
                 }
 
@@ -390,11 +1040,11 @@ impl Enclave {
                     start_time,
                 );
 
-                println!(
+                trace!(
                     "Earliest next event upstream of fed/encl {} at fed/encl {} has tag ({},{}).",
                     e.id(),
                     upstream.id(),
-                    upstream_next_event.time() - start_time,
+                    tag::format_time(upstream_next_event.time(), start_time),
                     upstream_next_event.microstep()
                 );
 
@@ -405,65 +1055,87 @@ impl Enclave {
                 let candidate = Tag::lf_delay_strict(&upstream_next_event, e.upstream_delay[j]);
 
                 if e.upstream_delay[j] == Some(i64::MIN) {
-                    if Tag::lf_tag_compare(&candidate, &t_d_zero_delay) < 0 {
+                    if candidate < t_d_zero_delay {
                         t_d_zero_delay = candidate;
+                        t_d_zero_delay_id = Some(upstream.id());
                     }
                 } else {
-                    if Tag::lf_tag_compare(&candidate, &t_d_nonzero_delay) < 0 {
+                    if candidate < t_d_nonzero_delay {
                         t_d_nonzero_delay = candidate;
+                        t_d_nonzero_delay_id = Some(upstream.id());
                     }
                 }
             }
         }
 
         let t_d;
-        if Tag::lf_tag_compare(&t_d_zero_delay, &t_d_nonzero_delay) < 0 {
+        let t_d_id;
+        if t_d_zero_delay < t_d_nonzero_delay {
             t_d = t_d_zero_delay.clone();
+            t_d_id = t_d_zero_delay_id;
         } else {
             t_d = t_d_nonzero_delay.clone();
+            t_d_id = t_d_nonzero_delay_id;
         }
-        println!(
+        trace!(
             "Earliest next event upstream has tag ({},{}).",
-            t_d.time() - start_time,
+            tag::format_time(t_d.time(), start_time),
             t_d.microstep()
         );
 
-        println!("t_d={}, e.next_event={}", t_d.time(), next_event_tag.time());
-        println!(
+        trace!("t_d={}, e.next_event={}", t_d.time(), next_event_tag.time());
+        trace!(
             "t_d={}, e.last_provisionally_granted={}",
             t_d.time(),
             last_provisionally_granted_tag.time()
         );
-        println!(
+        trace!(
             "t_d={}, e.last_granted={}",
             t_d.time(),
             last_granted_tag.time()
         );
-        if Tag::lf_tag_compare(&t_d, &next_event_tag) > 0       // The enclave has something to do.
-            && Tag::lf_tag_compare(&t_d, &last_provisionally_granted_tag) >= 0  // The grant is not redundant
+        if t_d > next_event_tag       // The enclave has something to do.
+            && t_d >= last_provisionally_granted_tag  // The grant is not redundant
                                                                         // (equal is important to override any previous
                                                                         // PTAGs).
-            && Tag::lf_tag_compare(&t_d, &last_granted_tag) > 0
+            && t_d > last_granted_tag
         // The grant is not redundant.
         {
             // All upstream enclaves have events with a larger tag than fed, so it is safe to send a TAG.
-            println!("Earliest upstream message time for fed/encl {} is ({},{}) (adjusted by after delay). Granting tag advance for ({},{})",
+            debug!("Earliest upstream message time for fed/encl {} is ({},{}) (adjusted by after delay). Granting tag advance for ({},{})",
                     fed_id,
-                    t_d.time() - start_time, t_d.microstep(),
+                    tag::format_time(t_d.time(), start_time), t_d.microstep(),
                     next_event_tag.time(), // - start_time,
                     next_event_tag.microstep());
+            result.set_justification(match t_d_id {
+                Some(upstream_id) => format!(
+                    "the earliest possible incoming message is at {} from upstream federate {} (adjusted by the after delay), which is after the next event {}",
+                    Self::relative_tag(&t_d, start_time),
+                    federate_label(upstream_id),
+                    Self::relative_tag(&next_event_tag, start_time)
+                ),
+                None => format!(
+                    "no upstream federate can send a message before or at the next event {}",
+                    Self::relative_tag(&next_event_tag, start_time)
+                ),
+            });
             result.set_tag(next_event_tag);
-        } else if Tag::lf_tag_compare(&t_d_zero_delay, &next_event_tag) == 0      // The enclave has something to do.
-            && Tag::lf_tag_compare(&t_d_zero_delay, &t_d_nonzero_delay) < 0  // The statuses of nonzero-delay connections are known at tag t_d_zero_delay
-            && Tag::lf_tag_compare(&t_d_zero_delay, &last_provisionally_granted_tag) > 0  // The grant is not redundant.
-            && Tag::lf_tag_compare(&t_d_zero_delay, &last_granted_tag) > 0
+        } else if t_d_zero_delay == next_event_tag      // The enclave has something to do.
+            && t_d_zero_delay < t_d_nonzero_delay  // The statuses of nonzero-delay connections are known at tag t_d_zero_delay
+            && t_d_zero_delay > last_provisionally_granted_tag  // The grant is not redundant.
+            && t_d_zero_delay > last_granted_tag
         // The grant is not redundant.
         {
             // Some upstream enclaves has an event that has the same tag as fed's next event, so we can only provisionally
             // grant a TAG (via a PTAG).
-            println!("Earliest upstream message time for fed/encl {} is ({},{}) (adjusted by after delay). Granting provisional tag advance.",
+            debug!("Earliest upstream message time for fed/encl {} is ({},{}) (adjusted by after delay). Granting provisional tag advance.",
                 fed_id,
-                t_d_zero_delay.time() - start_time, t_d_zero_delay.microstep());
+                tag::format_time(t_d_zero_delay.time(), start_time), t_d_zero_delay.microstep());
+            result.set_justification(format!(
+                "upstream federate {} may send a message at the next event {} over a zero-delay connection, and no message can arrive earlier",
+                federate_label(t_d_zero_delay_id.unwrap_or_default()),
+                Self::relative_tag(&t_d_zero_delay, start_time)
+            ));
             result.set_tag(t_d_zero_delay);
             result.set_provisional(true);
         }
@@ -475,29 +1147,49 @@ impl Enclave {
         enclaves: &Vec<Federate>,
         e: &Enclave,
         candidate: Tag,
-        visited: &mut Vec<bool>,
+        visited: &mut VisitedSet,
         start_time: Instant,
     ) -> Tag {
+        Self::traced_transitive_next_event(enclaves, e, candidate, visited, start_time).0
+    }
+
+    /**
+     * Same as transitive_next_event, but also return where the resulting tag
+     * comes from.
+     */
+    fn traced_transitive_next_event(
+        enclaves: &Vec<Federate>,
+        e: &Enclave,
+        candidate: Tag,
+        visited: &mut VisitedSet,
+        start_time: Instant,
+    ) -> (Tag, NextEventOrigin) {
         // FIXME: Replace "as usize" properly.
-        if visited[e.id() as usize] || e.state() == FedState::NotConnected {
-            // Enclave has stopped executing or we have visited it before.
+        if visited.contains(e.id() as usize) || e.absent() || e.decentralized() {
+            // Enclave has stopped executing, does not take part in centralized
+            // coordination, or we have visited it before.
             // No point in checking upstream enclaves.
-            return candidate.clone();
+            return (candidate.clone(), NextEventOrigin::candidate());
         }
 
         // FIXME: Replace "as usize" properly.
-        visited[e.id() as usize] = true;
+        visited.insert(e.id() as usize);
         let mut result = e.next_event();
+        let mut origin = NextEventOrigin::at("next event", e.id());
 
         // If the candidate is less than this enclave's next_event, use the candidate.
-        if Tag::lf_tag_compare(&candidate, &result) < 0 {
+        if candidate < result {
             result = candidate.clone();
+            origin = NextEventOrigin::candidate();
         }
 
-        // The result cannot be earlier than the start time.
+        // The result cannot be earlier than the start time. NETs earlier than
+        // the start time are handled according to the EarlyNetPolicy when they
+        // are received, so this only applies to enclaves that have not sent a
+        // NET yet, whose next event is NEVER.
         if result.time() < start_time {
-            // Earliest next event cannot be before the start time.
             result = Tag::new(start_time, 0);
+            origin = NextEventOrigin::at("start time", e.id());
         }
 
         // Check upstream enclaves to see whether any of them might send
@@ -505,7 +1197,7 @@ impl Enclave {
         for i in 0..e.upstream().len() {
             // FIXME: Replace "as usize" properly.
             let upstream = enclaves[e.upstream()[i] as usize].e();
-            let mut upstream_result = Self::transitive_next_event(
+            let (mut upstream_result, upstream_origin) = Self::traced_transitive_next_event(
                 enclaves,
                 upstream,
                 result.clone(),
@@ -517,46 +1209,364 @@ impl Enclave {
             upstream_result = Tag::lf_delay_tag(&upstream_result, e.upstream_delay()[i]);
 
             // If the adjusted event time is less than the result so far, update the result.
-            if Tag::lf_tag_compare(&upstream_result, &result) < 0 {
+            if upstream_result < result {
                 result = upstream_result;
+                if !upstream_origin.path.is_empty() {
+                    origin = upstream_origin.through(e.id(), e.upstream_delay()[i]);
+                }
             }
         }
         let completed = e.completed();
-        if Tag::lf_tag_compare(&result, &completed) < 0 {
+        if result < completed {
             result = completed;
+            origin = NextEventOrigin::at("completed tag", e.id());
         }
 
-        result
+        (result, origin)
+    }
+
+    /**
+     * Explain how the earliest incoming message tag (EIMT) of the given
+     * federate is derived: for each upstream federate, the enclave whose tag
+     * bounds the earliest message it can send, the path and delays along which
+     * that tag propagates, and the resulting candidate. The explanation
+     * mirrors tag_advance_grant_if_safe without changing any state.
+     */
+    pub fn explain_earliest_incoming_message_tag(
+        enclaves: &Vec<Federate>,
+        fed_id: u16,
+        start_time: Instant,
+    ) -> String {
+        let e = enclaves[usize::from(fed_id)].e();
+        if e.decentralized() {
+            return format!(
+                "federate {} uses decentralized coordination and has no EIMT",
+                federate_label(fed_id)
+            );
+        }
+        let mut visited = VisitedSet::new(enclaves.len());
+        let mut steps = Vec::new();
+        let mut eimt = Tag::forever_tag();
+        let mut eimt_upstream = None;
+        for (j, &upstream_id) in e.upstream().iter().enumerate() {
+            let delay = e.upstream_delay()[j];
+            // FIXME: Replace "as usize" properly.
+            let upstream = enclaves[upstream_id as usize].e();
+            let upstream_id = upstream.id();
+            if upstream.absent() || upstream.decentralized() {
+                steps.push(format!(
+                    "upstream federate {} is ignored because it is not connected or uses decentralized coordination",
+                    federate_label(upstream_id)
+                ));
+                continue;
+            }
+            let (upstream_next_event, origin) = Self::traced_transitive_next_event(
+                enclaves,
+                upstream,
+                upstream.next_event(),
+                &mut visited,
+                start_time,
+            );
+            let candidate = Tag::lf_delay_strict(&upstream_next_event, delay);
+            steps.push(format!(
+                "upstream federate {} over a connection with delay {}: earliest next event {} is the {}, so the earliest message is at {}",
+                federate_label(upstream_id),
+                describe_delay(delay),
+                Self::relative_tag(&upstream_next_event, start_time),
+                origin.describe(),
+                Self::relative_tag(&candidate, start_time)
+            ));
+            // A silent federate bounds the EIMT with its start tag until it
+            // sends a NET, which is reported apart from the NETs of others.
+            let mut silent_ids = vec![upstream_id];
+            silent_ids.extend(origin.origin_id().filter(|&id| id != upstream_id));
+            steps.extend(
+                silent_ids
+                    .into_iter()
+                    .filter_map(|id| describe_silence(enclaves, id)),
+            );
+            if candidate < eimt {
+                eimt = candidate;
+                eimt_upstream = Some(upstream_id);
+            }
+        }
+        let summary = match eimt_upstream {
+            Some(upstream_id) => format!(
+                "EIMT of federate {} is {} from upstream federate {}",
+                federate_label(fed_id),
+                Self::relative_tag(&eimt, start_time),
+                federate_label(upstream_id)
+            ),
+            None => format!(
+                "EIMT of federate {} is FOREVER because no upstream federate takes part in centralized coordination",
+                federate_label(fed_id)
+            ),
+        };
+        steps.insert(0, summary);
+        steps.join("; ")
+    }
+
+    /**
+     * In step mode, record the grant as pending and return true, unless it is
+     * the grant that an operator has just released.
+     */
+    fn withheld_in_step_mode(
+        locked_rti: &FederationRTI,
+        pending: PendingGrant,
+        start_time: Instant,
+    ) -> bool {
+        if !locked_rti.step_mode() || locked_rti.take_released_grant(&pending) {
+            return false;
+        }
+        let grant = pending.grant().clone();
+        let fed_id = pending.fed_id();
+        if locked_rti.add_pending_grant(pending) {
+            debug!(
+                "RTI: Step mode. Pending {} to federate {} because {}.",
+                grant.describe(start_time),
+                federate_label(fed_id),
+                grant.justification()
+            );
+            locked_rti.record_event(
+                "pending",
+                Some(fed_id),
+                Some((&grant.tag(), start_time)),
+                Some(grant.justification()),
+            );
+        }
+        true
+    }
+
+    /**
+     * Return the breakpoint of the federate if the given grant would advance
+     * it beyond the breakpoint.
+     */
+    fn exceeded_breakpoint(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        tag: &Tag,
+        start_time: Instant,
+    ) -> Option<Tag> {
+        locked_rti
+            .breakpoint_of(fed_id, start_time)
+            .filter(|breakpoint| tag > breakpoint)
     }
 
-    fn notify_tag_advance_grant(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+    /**
+     * Grant the federate up to its breakpoint instead of a later tag. Every
+     * tag up to the breakpoint is safe to process because the withheld grant
+     * is later, so the federate stops exactly at the breakpoint.
+     */
+    fn hold_at_breakpoint(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        breakpoint: Tag,
+        start_time: Instant,
+    ) {
+        trace!(
+            "RTI: Holding federate {} at the breakpoint {}.",
+            federate_label(fed_id),
+            Self::relative_tag(&breakpoint, start_time)
+        );
+        let justification = format!(
+            "the federate stops at the breakpoint {}",
+            Self::relative_tag(&breakpoint, start_time)
+        );
+        Self::notify_tag_advance_grant(locked_rti, fed_id, breakpoint, &justification, start_time);
+    }
+
+    /**
+     * Grant the federate up to the tag granted by the parent RTI instead of a
+     * later tag, as for a breakpoint. The withheld grant is sent once the
+     * parent RTI grants more.
+     */
+    fn hold_at_parent_grant(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        parent_grant: Tag,
+        start_time: Instant,
+    ) {
+        trace!(
+            "RTI: Holding federate {} at the tag {} granted by the parent RTI.",
+            federate_label(fed_id),
+            Self::relative_tag(&parent_grant, start_time)
+        );
+        let justification = format!(
+            "the parent RTI has granted up to {}",
+            Self::relative_tag(&parent_grant, start_time)
+        );
+        Self::notify_tag_advance_grant(
+            locked_rti,
+            fed_id,
+            parent_grant,
+            &justification,
+            start_time,
+        );
+    }
+
+    /**
+     * Grant the federate up to the end of the lookahead window instead of a
+     * later tag, as for a breakpoint. The withheld grant is sent once the
+     * earliest LTC of the federation advances.
+     */
+    fn hold_at_lookahead_limit(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        limit: Tag,
+        start_time: Instant,
+    ) {
+        trace!(
+            "RTI: Holding federate {} at the end {} of the lookahead window.",
+            federate_label(fed_id),
+            Self::relative_tag(&limit, start_time)
+        );
+        let justification = format!(
+            "the lookahead window ends at {}",
+            Self::relative_tag(&limit, start_time)
+        );
+        Self::notify_tag_advance_grant(locked_rti, fed_id, limit, &justification, start_time);
+    }
+
+    /**
+     * Count a grant sent to the federate and warn once its streak of grants
+     * at the same time with increasing microsteps reaches the threshold.
+     */
+    fn check_microstep_streak(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        tag: &Tag,
+        start_time: Instant,
+    ) {
+        let threshold = locked_rti.microstep_warning_threshold();
+        let reached = locked_rti.federates()[fed_id as usize]
+            .e()
+            .count_microstep_grant(tag, threshold);
+        if threshold == 0 || !reached {
+            return;
+        }
+        let cycle = match locked_rti.topology() {
+            Some(topology) => topology.zero_delay_cycle(fed_id).cloned(),
+            None => topology::zero_delay_cycle(locked_rti.federates(), fed_id),
+        };
+        let cycle = match cycle {
+            Some(cycle) => {
+                let labels: Vec<String> = cycle
+                    .iter()
+                    .map(|&id| locked_rti.federate_label(id))
+                    .collect();
+                format!(
+                    "the cycle of connections without delay through federates {}",
+                    labels.join(" -> ")
+                )
+            }
+            None => String::from("no cycle of connections without delay"),
+        };
+        warn!(
+            "RTI: WARNING: Federate {} has been granted {} consecutive tags at time {} with increasing microsteps (now {}). The federation may be livelocked in a microstep loop involving {}.",
+            federate_label(fed_id),
+            threshold + 1,
+            tag::format_time(tag.time(), start_time),
+            tag.microstep(),
+            cycle
+        );
+    }
+
+    /**
+     * Queue an encoded grant to be written to the federate by the notifier
+     * thread, on its control connection if it has one. Return false if the
+     * connection to the federate has failed.
+     */
+    fn queue_grant(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        tag: &Tag,
+        provisional: bool,
+        mut buffer: Vec<u8>,
+        fault: Option<Fault>,
+    ) -> bool {
+        let notifier = locked_rti.grant_notifier();
+        // FIXME: Replace "as usize" properly.
+        let fed: &Federate = &locked_rti.federates()[fed_id as usize];
+        let (stream, outgoing_lock, relayed_messages) = fed.grant_channel();
+        if let Some(relayed_messages) = relayed_messages {
+            buffer.extend_from_slice(&relayed_messages.to_le_bytes());
+        }
+        let Ok(stream) = stream.try_clone() else {
+            return false;
+        };
+        notifier.enqueue(
+            fed_id,
+            tag,
+            provisional,
+            QueuedGrant::new(
+                buffer,
+                fault,
+                stream,
+                outgoing_lock,
+                fed.wire_dump().clone(),
+            ),
+        );
+        true
+    }
+
+    /**
+     * Send the federate its last TAG, and its last PTAG if that is later,
+     * again, e.g., after it has resumed its session on a new connection.
+     */
+    pub fn resend_last_grant_locked(locked_rti: &FederationRTI, fed_id: u16, start_time: Instant) {
+        let (last_granted, last_provisionally_granted) = {
+            let e = locked_rti.federates()[usize::from(fed_id)].e();
+            (e.last_granted(), e.last_provisionally_granted())
+        };
+        locked_rti.grant_notifier().forget_written(fed_id);
+        let mut grants = Vec::new();
+        if last_granted != Tag::never_tag() {
+            grants.push((MsgType::TagAdvanceGrant, last_granted.clone(), false));
+        }
+        if last_provisionally_granted > last_granted {
+            grants.push((
+                MsgType::PropositionalTagAdvanceGrant,
+                last_provisionally_granted,
+                true,
+            ));
+        }
+        for (message_type, tag, provisional) in grants {
+            let mut buffer = vec![0u8; 1 + mem::size_of::<i64>() + mem::size_of::<u32>()];
+            buffer[0] = message_type.to_byte();
+            NetUtil::encode_int64(tag.time(), &mut buffer, 1);
+            NetUtil::encode_int32(
+                tag.microstep() as i32,
+                &mut buffer,
+                1 + mem::size_of::<i64>(),
+            );
+            if Self::queue_grant(locked_rti, fed_id, &tag, provisional, buffer, None) {
+                debug_for!(
+                    fed_id,
+                    "RTI sent to federate {} its last grant ({},{}) again.",
+                    federate_label(fed_id),
+                    tag::format_time(tag.time(), start_time),
+                    tag.microstep()
+                );
+            }
+        }
+    }
+
+    pub fn notify_tag_advance_grant(
+        locked_rti: &FederationRTI,
         fed_id: u16,
         tag: Tag,
+        justification: &str,
         start_time: Instant,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
     ) {
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let enclaves = locked_rti.enclaves();
-            let idx: usize = fed_id.into();
-            let fed: &Federate = &enclaves[idx];
-            let e = fed.e();
-            if e.state() == FedState::NotConnected
-                || Tag::lf_tag_compare(&tag, &e.last_granted()) <= 0
-                || Tag::lf_tag_compare(&tag, &e.last_provisionally_granted()) <= 0
-            {
+            let e = locked_rti.federates()[usize::from(fed_id)].e();
+            if e.absent() || tag <= e.last_granted() || tag < e.last_provisionally_granted() {
                 return;
             }
-            // Need to make sure that the destination federate's thread has already
-            // sent the starting MSG_TYPE_TIMESTAMP message.
-            while e.state() == FedState::Pending {
-                // Need to wait here.
-                let (lock, condvar) = &*sent_start_time;
-                let mut notified = lock.lock().unwrap();
-                while !*notified {
-                    notified = condvar.wait(notified).unwrap();
-                }
+            // A federate that has not been sent the start time yet is granted
+            // once it is, without waiting for it while holding the locks.
+            if e.state() == FedState::Pending {
+                return;
             }
         }
         let message_length = 1 + mem::size_of::<i64>() + mem::size_of::<u32>();
@@ -571,80 +1581,94 @@ impl Enclave {
             1 + mem::size_of::<i64>(),
         );
 
-        // This function is called in notify_advance_grant_if_safe(), which is a long
-        // function. During this call, the socket might close, causing the following write_to_socket
-        // to fail. Consider a failure here a soft failure and update the federate's status.
-        let mut error_occurred = false;
+        // The socket might have closed, so that the grant cannot be queued.
+        // Consider a failure here a soft failure and update the federate's
+        // status. Failures to write a queued grant are handled by the notifier
+        // thread.
+        if locked_rti.paused() {
+            trace!(
+                "RTI: Federation is paused. Withholding TAG ({},{}) to federate {}.",
+                tag::format_time(tag.time(), start_time),
+                tag.microstep(),
+                federate_label(fed_id)
+            );
+            return;
+        }
+        if let Some(breakpoint) = Self::exceeded_breakpoint(locked_rti, fed_id, &tag, start_time) {
+            Self::hold_at_breakpoint(locked_rti, fed_id, breakpoint, start_time);
+            return;
+        }
+        if let Some(parent_grant) = locked_rti
+            .parent_grant()
+            .clone()
+            .filter(|limit| tag > *limit)
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let enclaves = locked_rti.enclaves();
-            // FIXME: Replace "as usize" properly.
-            let fed: &Federate = &enclaves[fed_id as usize];
-            let e = fed.e();
-            let mut stream = fed.stream().as_ref().unwrap();
-            match stream.write(&buffer) {
-                Ok(bytes_written) => {
-                    if bytes_written < message_length {
-                        println!(
-                            "RTI failed to send tag advance grant to federate {}.",
-                            e.id()
-                        );
-                    }
-                }
-                Err(_err) => {
-                    error_occurred = true;
-                }
-            }
+            Self::hold_at_parent_grant(locked_rti, fed_id, parent_grant, start_time);
+            return;
         }
+        if let Some(limit) = locked_rti
+            .lookahead_limit(fed_id, start_time)
+            .filter(|limit| tag > *limit)
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            // FIXME: Replace "as usize" properly.
-            let mut_fed: &mut Federate = &mut locked_rti.enclaves()[fed_id as usize];
-            let enclave = mut_fed.enclave();
-            if error_occurred {
-                enclave.set_state(FedState::NotConnected);
-                // FIXME: We need better error handling, but don't stop other execution here.
-            } else {
-                enclave.set_last_granted(tag.clone());
-                println!(
-                    "RTI sent to federate {} the Tag Advance Grant (TAG) ({},{}).",
-                    enclave.id(),
-                    tag.time() - start_time,
-                    tag.microstep()
-                );
-            }
+            Self::hold_at_lookahead_limit(locked_rti, fed_id, limit, start_time);
+            return;
+        }
+        let mut grant = TagAdvanceGrant::new(tag.clone(), false);
+        grant.set_justification(justification.to_string());
+        if Self::withheld_in_step_mode(locked_rti, PendingGrant::new(fed_id, grant), start_time) {
+            return;
+        }
+        let fault = locked_rti.inject_fault(MsgType::TagAdvanceGrant, fed_id, &tag, start_time);
+        let error_occurred = !Self::queue_grant(locked_rti, fed_id, &tag, false, buffer, fault);
+        if !error_occurred {
+            Self::check_microstep_streak(locked_rti, fed_id, &tag, start_time);
+        }
+        // FIXME: Replace "as usize" properly.
+        let mut_fed: &Federate = &locked_rti.federates()[fed_id as usize];
+        if !error_occurred {
+            mut_fed.provisional_grants().record_tag(&tag);
+        }
+        let enclave = mut_fed.e();
+        if error_occurred {
+            enclave.set_state(FedState::NotConnected);
+            // FIXME: We need better error handling, but don't stop other execution here.
+        } else {
+            enclave.set_last_granted(tag.clone());
+            debug_for!(
+                enclave.id(),
+                "RTI sent to federate {} the Tag Advance Grant (TAG) ({},{}).",
+                federate_label(enclave.id()),
+                tag::format_time(tag.time(), start_time),
+                tag.microstep()
+            );
+        }
+        if !error_occurred {
+            locked_rti.tracepoint_rti_to_federate(TraceEvent::SendTag, fed_id, Some(&tag));
+            locked_rti.record_event(
+                "tag",
+                Some(fed_id),
+                Some((&tag, start_time)),
+                Some(justification),
+            );
         }
     }
 
-    fn notify_provisional_tag_advance_grant(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+    pub fn notify_provisional_tag_advance_grant(
+        locked_rti: &FederationRTI,
         fed_id: u16,
         number_of_enclaves: i32,
         tag: Tag,
+        justification: &str,
         start_time: Instant,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
     ) {
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let enclaves = locked_rti.enclaves();
-            let idx: usize = fed_id.into();
-            let fed: &Federate = &enclaves[idx];
-            let e = fed.e();
-            if e.state() == FedState::NotConnected
-                || Tag::lf_tag_compare(&tag, &e.last_granted()) <= 0
-                || Tag::lf_tag_compare(&tag, &e.last_provisionally_granted()) <= 0
-            {
+            let e = locked_rti.federates()[usize::from(fed_id)].e();
+            if e.absent() || tag <= e.last_granted() || tag <= e.last_provisionally_granted() {
                 return;
             }
-            // Need to make sure that the destination federate's thread has already
-            // sent the starting MSG_TYPE_TIMESTAMP message.
-            while e.state() == FedState::Pending {
-                // Need to wait here.
-                let (lock, condvar) = &*sent_start_time;
-                let mut notified = lock.lock().unwrap();
-                while !*notified {
-                    notified = condvar.wait(notified).unwrap();
-                }
+            // See notify_tag_advance_grant.
+            if e.state() == FedState::Pending {
+                return;
             }
         }
         let message_length = 1 + mem::size_of::<i64>() + mem::size_of::<u32>();
@@ -658,48 +1682,79 @@ impl Enclave {
             1 + mem::size_of::<i64>(),
         );
 
-        // This function is called in notify_advance_grant_if_safe(), which is a long
-        // function. During this call, the socket might close, causing the following write_to_socket
-        // to fail. Consider a failure here a soft failure and update the federate's status.
-        let mut error_occurred = false;
+        // The socket might have closed, so that the grant cannot be queued.
+        // Consider a failure here a soft failure and update the federate's
+        // status. Failures to write a queued grant are handled by the notifier
+        // thread.
+        if locked_rti.paused() {
+            trace!(
+                "RTI: Federation is paused. Withholding PTAG ({},{}) to federate {}.",
+                tag::format_time(tag.time(), start_time),
+                tag.microstep(),
+                federate_label(fed_id)
+            );
+            return;
+        }
+        if let Some(breakpoint) = Self::exceeded_breakpoint(locked_rti, fed_id, &tag, start_time) {
+            Self::hold_at_breakpoint(locked_rti, fed_id, breakpoint, start_time);
+            return;
+        }
+        if let Some(parent_grant) = locked_rti
+            .parent_grant()
+            .clone()
+            .filter(|limit| tag > *limit)
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let enclaves = locked_rti.enclaves();
-            // FIXME: Replace "as usize" properly.
-            let fed: &Federate = &enclaves[fed_id as usize];
-            let e = fed.e();
-            let mut stream = fed.stream().as_ref().unwrap();
-            match stream.write(&buffer) {
-                Ok(bytes_written) => {
-                    if bytes_written < message_length {
-                        println!(
-                            "RTI failed to send tag advance grant to federate {}.",
-                            e.id()
-                        );
-                        return;
-                    }
-                }
-                Err(_err) => {
-                    error_occurred = true;
-                }
-            }
+            Self::hold_at_parent_grant(locked_rti, fed_id, parent_grant, start_time);
+            return;
         }
+        if let Some(limit) = locked_rti
+            .lookahead_limit(fed_id, start_time)
+            .filter(|limit| tag > *limit)
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            // FIXME: Replace "as usize" properly.
-            let mut_fed: &mut Federate = &mut locked_rti.enclaves()[fed_id as usize];
-            let enclave = mut_fed.enclave();
-            if error_occurred {
-                enclave.set_state(FedState::NotConnected);
-                // FIXME: We need better error handling, but don't stop other execution here.
-            }
+            Self::hold_at_lookahead_limit(locked_rti, fed_id, limit, start_time);
+            return;
+        }
+        let mut grant = TagAdvanceGrant::new(tag.clone(), true);
+        grant.set_justification(justification.to_string());
+        if Self::withheld_in_step_mode(locked_rti, PendingGrant::new(fed_id, grant), start_time) {
+            return;
+        }
+        let fault = locked_rti.inject_fault(
+            MsgType::PropositionalTagAdvanceGrant,
+            fed_id,
+            &tag,
+            start_time,
+        );
+        let error_occurred = !Self::queue_grant(locked_rti, fed_id, &tag, true, buffer, fault);
+        if !error_occurred {
+            Self::check_microstep_streak(locked_rti, fed_id, &tag, start_time);
+        }
+        // FIXME: Replace "as usize" properly.
+        let mut_fed: &Federate = &locked_rti.federates()[fed_id as usize];
+        if !error_occurred {
+            mut_fed.provisional_grants().record_ptag(&tag);
+        }
+        let enclave = mut_fed.e();
+        if error_occurred {
+            enclave.set_state(FedState::NotConnected);
+            // FIXME: We need better error handling, but don't stop other execution here.
+        }
 
-            enclave.set_last_provisionally_granted(tag.clone());
-            println!(
-                "RTI sent to federate {} the Provisional Tag Advance Grant (PTAG) ({},{}).",
-                enclave.id(),
-                tag.time() - start_time,
-                tag.microstep()
+        enclave.set_last_provisionally_granted(tag.clone());
+        debug_for!(
+            enclave.id(),
+            "RTI sent to federate {} the Provisional Tag Advance Grant (PTAG) ({},{}).",
+            federate_label(enclave.id()),
+            tag::format_time(tag.time(), start_time),
+            tag.microstep()
+        );
+        if !error_occurred {
+            locked_rti.tracepoint_rti_to_federate(TraceEvent::SendPtag, fed_id, Some(&tag));
+            locked_rti.record_event(
+                "ptag",
+                Some(fed_id),
+                Some((&tag, start_time)),
+                Some(justification),
             );
         }
 
@@ -709,40 +1764,27 @@ impl Enclave {
         // NOTE: This could later be replaced with a TNET mechanism once
         // we have an available encoding of causality interfaces.
         // That might be more efficient.
-        let num_upstream;
-        {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let enclaves = locked_rti.enclaves();
-            let idx: usize = fed_id.into();
-            let fed: &Federate = &enclaves[idx];
-            let e = fed.e();
-            num_upstream = e.num_upstream();
-        }
-        for j in 0..num_upstream {
-            let e_id;
+        let idx: usize = fed_id.into();
+        let upstreams = locked_rti.federates()[idx].e().upstream().clone();
+        for e_id in upstreams {
             let upstream_next_event;
             {
-                let mut locked_rti = _f_rti.lock().unwrap();
-                let enclaves = locked_rti.enclaves();
-                let idx: usize = fed_id.into();
-                let fed: &Federate = &enclaves[idx];
-                // FIXME: Replace "as usize" properly.
-                e_id = fed.e().upstream()[j as usize];
+                let enclaves = locked_rti.federates();
                 // FIXME: Replace "as usize" properly.
                 let upstream: &Federate = &enclaves[e_id as usize];
 
-                // Ignore this federate if it has resigned.
-                if upstream.e().state() == NotConnected {
+                // Ignore this federate if it has resigned or uses decentralized coordination.
+                if upstream.e().absent() || upstream.e().decentralized() {
                     continue;
                 }
-                // To handle cycles, need to create a boolean array to keep
-                // track of which upstream federates have been visited.
+                // To handle cycles, need to keep track of which upstream
+                // federates have been visited.
                 // FIXME: Replace "as usize" properly.
-                let mut visited = vec![false; number_of_enclaves as usize];
+                let mut visited = VisitedSet::new(number_of_enclaves as usize);
 
                 // Find the (transitive) next event tag upstream.
                 upstream_next_event = Self::transitive_next_event(
-                    &enclaves,
+                    enclaves,
                     upstream.e(),
                     upstream.e().next_event(),
                     &mut visited,
@@ -753,133 +1795,122 @@ impl Enclave {
             // a TAG or PTAG should have already been granted,
             // in which case, another will not be sent. But it
             // may not have been already granted.
-            if Tag::lf_tag_compare(&upstream_next_event, &tag) >= 0 {
+            if upstream_next_event >= tag {
                 Self::notify_provisional_tag_advance_grant(
-                    _f_rti.clone(),
+                    locked_rti,
                     // FIXME: Handle unwrap properly.
                     e_id.try_into().unwrap(),
                     number_of_enclaves,
                     tag.clone(),
+                    &format!(
+                        "downstream federate {} has been provisionally granted {}, and the earliest next event upstream of federate {} is not earlier",
+                        federate_label(fed_id),
+                        Self::relative_tag(&tag, start_time),
+                        federate_label(e_id as u16)
+                    ),
                     start_time,
-                    sent_start_time.clone(),
                 );
             }
         }
     }
 
+    /**
+     * Notify the enclaves downstream of the given one, directly or
+     * transitively, of a grant if it is safe. Once the topology is known, they
+     * are taken from its precomputed downstream closures.
+     */
     pub fn notify_downstream_advance_grant_if_safe(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        locked_rti: &FederationRTI,
         fed_id: u16,
         number_of_enclaves: i32,
         start_time: Instant,
-        visited: &mut Vec<bool>,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
     ) {
-        // FIXME: Replace "as usize" properly.
-        visited[fed_id as usize] = true;
-        let num_downstream;
-        {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let idx: usize = fed_id.into();
-            let fed: &Federate = &locked_rti.enclaves()[idx];
-            let e = fed.e();
-            num_downstream = e.num_downstream();
-        }
-        for i in 0..num_downstream {
-            let e_id;
-            {
-                let mut locked_rti = _f_rti.lock().unwrap();
-                let enclaves = locked_rti.enclaves();
-                let idx: usize = fed_id.into();
-                let fed: &Federate = &enclaves[idx];
-                let downstreams = fed.e().downstream();
-                // FIXME: Replace "as u16" properly.
-                e_id = downstreams[i as usize] as u16;
-                // FIXME: Replace "as usize" properly.
-                if visited[e_id as usize] {
-                    continue;
-                }
-            }
-            Self::notify_advance_grant_if_safe(
-                _f_rti.clone(),
-                e_id,
+        let downstream_closure = locked_rti
+            .topology()
+            .as_ref()
+            .and_then(|topology| topology.downstream_closure(fed_id));
+        let Some(downstream_closure) = downstream_closure else {
+            // To handle cycles, need to keep track of which downstream
+            // enclaves have been visited.
+            let mut visited = VisitedSet::new(number_of_enclaves as usize);
+            Self::traverse_downstream_advance_grant_if_safe(
+                locked_rti,
+                fed_id,
                 number_of_enclaves,
                 start_time,
-                sent_start_time.clone(),
+                &mut visited,
             );
-            Self::notify_downstream_advance_grant_if_safe(
-                _f_rti.clone(),
+            return;
+        };
+        for &e_id in downstream_closure.iter() {
+            Self::notify_advance_grant_if_safe(locked_rti, e_id, number_of_enclaves, start_time);
+        }
+    }
+
+    fn traverse_downstream_advance_grant_if_safe(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        number_of_enclaves: i32,
+        start_time: Instant,
+        visited: &mut VisitedSet,
+    ) {
+        // FIXME: Replace "as usize" properly.
+        visited.insert(fed_id as usize);
+        let idx: usize = fed_id.into();
+        let downstreams = locked_rti.federates()[idx].e().downstream().clone();
+        for downstream in downstreams {
+            // FIXME: Replace "as u16" properly.
+            let e_id = downstream as u16;
+            // FIXME: Replace "as usize" properly.
+            if visited.contains(e_id as usize) {
+                continue;
+            }
+            Self::notify_advance_grant_if_safe(locked_rti, e_id, number_of_enclaves, start_time);
+            Self::traverse_downstream_advance_grant_if_safe(
+                locked_rti,
                 e_id,
                 number_of_enclaves,
                 start_time,
                 visited,
-                sent_start_time.clone(),
             );
         }
     }
 
     pub fn logical_tag_complete(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        locked_rti: &FederationRTI,
         fed_id: u16,
         number_of_enclaves: i32,
         start_time: Instant,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
         completed: Tag,
     ) {
         // FIXME: Consolidate this message with NET to get NMR (Next Message Request).
         // Careful with handling startup and shutdown.
-        {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let idx: usize = fed_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            let enclave = fed.enclave();
-            enclave.set_completed(completed);
-
-            println!(
-                "RTI received from federate/enclave {} the Logical Tag Complete (LTC) ({},{}).",
-                enclave.id(),
-                enclave.completed().time() - start_time,
-                enclave.completed().microstep()
-            );
-        }
+        let idx: usize = fed_id.into();
+        let fed: &Federate = &locked_rti.federates()[idx];
+        let enclave = fed.e();
+        enclave.set_completed(completed);
+
+        debug_for!(
+            enclave.id(),
+            "RTI received from federate/enclave {} the Logical Tag Complete (LTC) ({},{}).",
+            enclave.id(),
+            tag::format_time(enclave.completed().time(), start_time),
+            enclave.completed().microstep()
+        );
 
         // Check downstream enclaves to see whether they should now be granted a TAG.
-        let num_downstream;
-        {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let idx: usize = fed_id.into();
-            let fed: &Federate = &locked_rti.enclaves()[idx];
-            let e = fed.e();
-            num_downstream = e.num_downstream();
-        }
-        for i in 0..num_downstream {
-            let e_id;
-            {
-                let mut locked_rti = _f_rti.lock().unwrap();
-                let enclaves = locked_rti.enclaves();
-                let idx: usize = fed_id.into();
-                let fed: &Federate = &enclaves[idx];
-                let downstreams = fed.e().downstream();
-                // FIXME: Replace "as u16" properly.
-                e_id = downstreams[i as usize] as u16;
-            }
+        let downstreams = enclave.downstream().clone();
+        for downstream in downstreams {
+            // FIXME: Replace "as u16" properly.
+            let e_id = downstream as u16;
             // Notify downstream enclave if appropriate.
-            Self::notify_advance_grant_if_safe(
-                _f_rti.clone(),
-                e_id,
-                number_of_enclaves,
-                start_time,
-                sent_start_time.clone(),
-            );
-            let mut visited = vec![false as bool; number_of_enclaves as usize]; // Initializes to 0.
-                                                                                // Notify enclaves downstream of downstream if appropriate.
+            Self::notify_advance_grant_if_safe(locked_rti, e_id, number_of_enclaves, start_time);
+            // Notify enclaves downstream of downstream if appropriate.
             Self::notify_downstream_advance_grant_if_safe(
-                _f_rti.clone(),
+                locked_rti,
                 e_id,
                 number_of_enclaves,
                 start_time,
-                &mut visited,
-                sent_start_time.clone(),
             );
         }
     }
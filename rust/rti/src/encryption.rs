@@ -0,0 +1,315 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Encryption at rest of the files that record an execution (the trace
+ * file, the event stream, the wire dumps, and the log file) with AES-256-GCM
+ * under a key given with --output_key_file.
+ *
+ * An encrypted file starts with the magic "LFRTIENC", a version byte, and a
+ * random 8-byte nonce prefix, and continues with chunks. Each chunk is the
+ * 4-byte little-endian length of its ciphertext, a flag byte that is 1 for
+ * the last chunk and 0 otherwise, and the ciphertext with its 16-byte tag. The
+ * nonce of a chunk is the prefix followed by the 4-byte big-endian index of
+ * the chunk, and the flag is authenticated as associated data, so reordered,
+ * modified, or removed chunks are detected. A chunk is written on every flush
+ * so that the file can be decrypted up to the last flush if the RTI is killed;
+ * such a file has no last chunk and is reported as truncated.
+ */
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::consts::U12;
+#[cfg(feature = "encryption")]
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+#[cfg(feature = "encryption")]
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+#[cfg(feature = "encryption")]
+use std::io::Read;
+
+/**
+ * Length of the key in bytes.
+ */
+pub const KEY_LENGTH: usize = 32;
+
+#[cfg(feature = "encryption")]
+const MAGIC: &[u8; 8] = b"LFRTIENC";
+#[cfg(feature = "encryption")]
+const VERSION: u8 = 1;
+#[cfg(feature = "encryption")]
+const NONCE_PREFIX_LENGTH: usize = 8;
+#[cfg(feature = "encryption")]
+const TAG_LENGTH: usize = 16;
+
+/**
+ * Number of bytes of plaintext after which a chunk is written even without a
+ * flush.
+ */
+#[cfg(feature = "encryption")]
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/**
+ * Key with which the RTI encrypts the files that it writes. It is not printed
+ * by Debug so that it does not end up in the output.
+ */
+#[derive(Clone, PartialEq)]
+pub struct OutputKey([u8; KEY_LENGTH]);
+
+impl OutputKey {
+    /**
+     * Parse a key written as 64 hexadecimal digits, e.g., by
+     * "openssl rand -hex 32". Surrounding whitespace is ignored.
+     */
+    pub fn from_hex(text: &str) -> Option<OutputKey> {
+        let text = text.trim();
+        if text.len() != 2 * KEY_LENGTH || !text.is_ascii() {
+            return None;
+        }
+        let mut key = [0u8; KEY_LENGTH];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&text[2 * i..2 * i + 2], 16).ok()?;
+        }
+        Some(OutputKey(key))
+    }
+
+    /**
+     * Read a key from the file at the given path. On Unix, a file that other
+     * users can read is rejected, as ssh does for private keys.
+     */
+    pub fn read(path: &str) -> Result<OutputKey, String> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path)
+                .map_err(|e| e.to_string())?
+                .permissions()
+                .mode();
+            if mode & 0o077 != 0 {
+                return Err(format!(
+                    "the file is accessible by other users (mode {:o})",
+                    mode & 0o777
+                ));
+            }
+        }
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_hex(&text)
+            .ok_or_else(|| format!("the file must hold {} hexadecimal digits", 2 * KEY_LENGTH))
+    }
+}
+
+impl fmt::Debug for OutputKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OutputKey(..)")
+    }
+}
+
+/**
+ * Create the file at the given path, or truncate it, and return a writer that
+ * encrypts what is written to it under the given key, if any.
+ */
+pub fn create(path: &str, key: Option<&OutputKey>) -> io::Result<Box<dyn Write + Send>> {
+    wrap(File::create(path)?, key)
+}
+
+/**
+ * Return a writer that encrypts what is written to the given file under the
+ * given key, if any, or the file itself.
+ */
+pub fn wrap(file: File, key: Option<&OutputKey>) -> io::Result<Box<dyn Write + Send>> {
+    match key {
+        None => Ok(Box::new(file)),
+        #[cfg(feature = "encryption")]
+        Some(key) => Ok(Box::new(EncryptedWriter::new(file, key)?)),
+        #[cfg(not(feature = "encryption"))]
+        Some(_) => Err(io::Error::from(io::ErrorKind::Unsupported)),
+    }
+}
+
+/**
+ * Writer that encrypts what is written to it in chunks (see the format above).
+ * The last chunk is written when the writer is finished or dropped.
+ */
+#[cfg(feature = "encryption")]
+pub struct EncryptedWriter<W: Write> {
+    inner: W,
+    cipher: Aes256Gcm,
+    nonce_prefix: [u8; NONCE_PREFIX_LENGTH],
+    index: u32,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+#[cfg(feature = "encryption")]
+impl<W: Write> EncryptedWriter<W> {
+    pub fn new(mut inner: W, key: &OutputKey) -> io::Result<EncryptedWriter<W>> {
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LENGTH];
+        getrandom::getrandom(&mut nonce_prefix).map_err(|e| io::Error::other(e.to_string()))?;
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[VERSION])?;
+        inner.write_all(&nonce_prefix)?;
+        Ok(EncryptedWriter {
+            inner,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.0)),
+            nonce_prefix,
+            index: 0,
+            buffer: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /**
+     * Write the remaining bytes as the last chunk. Later writes fail.
+     */
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.write_chunk(true)?;
+        self.finished = true;
+        self.inner.flush()
+    }
+
+    fn write_chunk(&mut self, last: bool) -> io::Result<()> {
+        let flag = [last as u8];
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce(&self.nonce_prefix, self.index),
+                Payload {
+                    msg: &self.buffer,
+                    aad: &flag,
+                },
+            )
+            .map_err(|_| io::Error::other("AES-GCM encryption failed"))?;
+        // The nonce must never be reused, so the file ends after 2^32 chunks.
+        self.index = self
+            .index
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other("too many chunks in the encrypted file"))?;
+        self.inner
+            .write_all(&(ciphertext.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&flag)?;
+        self.inner.write_all(&ciphertext)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(io::Error::other("the encrypted file is finished"));
+        }
+        let length = bytes.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&bytes[..length]);
+        if self.buffer.len() == CHUNK_SIZE {
+            self.write_chunk(false)?;
+        }
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.finished && !self.buffer.is_empty() {
+            self.write_chunk(false)?;
+        }
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<W: Write> Drop for EncryptedWriter<W> {
+    fn drop(&mut self) {
+        // NOTE: Errors cannot be reported here, and a file without a last
+        // chunk is reported as truncated when it is decrypted.
+        let _ = self.finish();
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn nonce(prefix: &[u8; NONCE_PREFIX_LENGTH], index: u32) -> Nonce<U12> {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LENGTH].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LENGTH..].copy_from_slice(&index.to_be_bytes());
+    Nonce::clone_from_slice(&nonce)
+}
+
+/**
+ * Decrypt a file written by an EncryptedWriter from the given reader into the
+ * given writer, chunk by chunk so that a file that is still being written can
+ * be followed. Return true if the file is complete and false if it has been
+ * truncated, e.g., because the RTI has been killed. A file that has not been
+ * encrypted under the given key, or has been modified, is an InvalidData error.
+ */
+#[cfg(feature = "encryption")]
+pub fn decrypt(key: &OutputKey, mut reader: impl Read, mut writer: impl Write) -> io::Result<bool> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut header = [0u8; MAGIC.len() + 1 + NONCE_PREFIX_LENGTH];
+    reader.read_exact(&mut header)?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not an encrypted file of the RTI"));
+    }
+    if header[MAGIC.len()] != VERSION {
+        return Err(invalid("unsupported version of the encrypted file"));
+    }
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LENGTH];
+    nonce_prefix.copy_from_slice(&header[MAGIC.len() + 1..]);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.0));
+    let mut index: u32 = 0;
+    loop {
+        let mut chunk_header = [0u8; 5];
+        match read_fully(&mut reader, &mut chunk_header)? {
+            0 => return Ok(false),
+            length if length < chunk_header.len() => return Ok(false),
+            _ => {}
+        }
+        let length = u32::from_le_bytes(chunk_header[..4].try_into().unwrap()) as usize;
+        let flag = chunk_header[4];
+        if !(TAG_LENGTH..=CHUNK_SIZE + TAG_LENGTH).contains(&length) || flag > 1 {
+            return Err(invalid("invalid chunk in the encrypted file"));
+        }
+        let mut ciphertext = vec![0u8; length];
+        if read_fully(&mut reader, &mut ciphertext)? < length {
+            return Ok(false);
+        }
+        let plaintext = cipher
+            .decrypt(
+                &nonce(&nonce_prefix, index),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &[flag],
+                },
+            )
+            .map_err(|_| invalid("wrong key or modified encrypted file"))?;
+        writer.write_all(&plaintext)?;
+        writer.flush()?;
+        if flag == 1 {
+            return Ok(true);
+        }
+        index = index
+            .checked_add(1)
+            .ok_or_else(|| invalid("too many chunks in the encrypted file"))?;
+    }
+}
+
+/**
+ * Read into the given buffer until it is full or the reader ends, and return
+ * the number of bytes read.
+ */
+#[cfg(feature = "encryption")]
+fn read_fully(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
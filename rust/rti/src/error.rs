@@ -0,0 +1,51 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Errors returned to the tools that embed the RTI.
+ */
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/**
+ * An error of the RTI. New variants may be added in minor releases, so
+ * matches on it need a wildcard arm.
+ */
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RtiError {
+    /// The arguments were invalid, with the reason, as for the command line.
+    InvalidArguments(&'static str),
+    /// An I/O operation failed, e.g., reading a file named in the arguments.
+    Io(io::Error),
+    /// The database of runs could not be read or written, with the reason.
+    RunDatabase(String),
+}
+
+impl fmt::Display for RtiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RtiError::InvalidArguments(reason) => write!(f, "invalid arguments: {}", reason),
+            RtiError::Io(e) => write!(f, "I/O error: {}", e),
+            RtiError::RunDatabase(reason) => write!(f, "run database error: {}", reason),
+        }
+    }
+}
+
+impl Error for RtiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RtiError::InvalidArguments(_) | RtiError::RunDatabase(_) => None,
+            RtiError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for RtiError {
+    fn from(e: io::Error) -> RtiError {
+        RtiError::Io(e)
+    }
+}
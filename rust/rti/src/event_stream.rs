@@ -0,0 +1,177 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Stream of scheduling events in newline-delimited JSON for external
+ * analyzers.
+ *
+ * Each line is one JSON object with the following fields:
+ *
+ * - "physical_time": nanoseconds since the epoch of the clock source;
+ * - "event": "net" or "ltc" for a message received from a federate, "tag" or
+ *   "ptag" for a grant sent to a federate, "dnet" for a DNET sent to a
+ *   federate, "pending" for a grant withheld in step mode, "stop_granted",
+ *   "joined" for a transient federate that joins the running federation at
+ *   the given start tag, "resigned", "failed", "silent" for a federate presumed
+ *   to have a NET of FOREVER after --silent_grace, or "lag" for logical time
+ *   lagging behind physical time beyond --max_lag at the tag granted to the
+ *   federate;
+ * - "federate" and "name": the ID and, if presented, the name of the federate;
+ * - "tag": [time, microstep] relative to the start time in nanoseconds, or
+ *   "NEVER" or "FOREVER";
+ * - "reason": why a grant is safe, why a federate has failed, or which
+ *   federate is the slowest upstream of a lagging one.
+ *
+ * Fields that do not apply to an event are omitted.
+ */
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::clock::PhysicalClock;
+use crate::encryption::{self, OutputKey};
+use crate::tag::{self, Instant, Tag};
+
+pub struct EventStream {
+    // None once the stream is closed.
+    writer: Mutex<Option<LineWriter<Box<dyn Write + Send>>>>,
+    clock: Option<Arc<PhysicalClock>>,
+}
+
+impl EventStream {
+    /**
+     * Open the event stream on the given target: a file descriptor that the
+     * RTI has inherited if the target is of the form `fd:<n>`, or the path of
+     * a file, which is created or truncated, otherwise. The stream is
+     * encrypted under the given key, if any, so analyzers must decrypt it as
+     * it is written.
+     */
+    pub fn open(target: &str, key: Option<&OutputKey>) -> io::Result<EventStream> {
+        let file = match target.strip_prefix("fd:") {
+            Some(fd) => match fd.parse::<i32>() {
+                Ok(fd) => Self::file_from_fd(fd)?,
+                Err(_) => return Err(io::Error::from(io::ErrorKind::InvalidInput)),
+            },
+            None => File::create(target)?,
+        };
+        Ok(EventStream {
+            writer: Mutex::new(Some(LineWriter::new(encryption::wrap(file, key)?))),
+            clock: None,
+        })
+    }
+
+    #[cfg(unix)]
+    fn file_from_fd(fd: i32) -> io::Result<File> {
+        use std::os::unix::io::{FromRawFd, OwnedFd};
+        if fd < 0 {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+        // Check that the descriptor is open before using it at all.
+        if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // Duplicate the descriptor, so the inherited one is not closed by the
+        // RTI and the duplicate is owned by the returned file.
+        let duplicate = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+        if duplicate == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: duplicate is a newly opened descriptor that nothing else owns.
+        let owned = unsafe { OwnedFd::from_raw_fd(duplicate) };
+        Ok(File::from(owned))
+    }
+
+    #[cfg(not(unix))]
+    fn file_from_fd(_fd: i32) -> io::Result<File> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    /**
+     * Set the clock from which the physical time of each event is read.
+     */
+    pub fn set_clock(&mut self, clock: Arc<PhysicalClock>) {
+        self.clock = Some(clock);
+    }
+
+    /**
+     * Write one event as a line of JSON and flush it so that analyzers see it
+     * as it happens. Nothing is written once the stream is closed.
+     */
+    pub fn write_event(
+        &self,
+        event: &str,
+        federate: Option<(u16, Option<&str>)>,
+        tag: Option<(&Tag, Instant)>,
+        reason: Option<&str>,
+    ) -> io::Result<()> {
+        let mut line = String::from("{");
+        if let Some(clock) = &self.clock {
+            line.push_str(&format!("\"physical_time\":{},", clock.now()));
+        }
+        line.push_str(&format!("\"event\":{}", json_string(event)));
+        if let Some((fed_id, name)) = federate {
+            line.push_str(&format!(",\"federate\":{}", fed_id));
+            if let Some(name) = name {
+                line.push_str(&format!(",\"name\":{}", json_string(name)));
+            }
+        }
+        if let Some((tag, start_time)) = tag {
+            line.push_str(&format!(",\"tag\":{}", json_tag(tag, start_time)));
+        }
+        if let Some(reason) = reason {
+            line.push_str(&format!(",\"reason\":{}", json_string(reason)));
+        }
+        line.push_str("}\n");
+        match self.writer.lock().unwrap().as_mut() {
+            Some(writer) => writer.write_all(line.as_bytes()),
+            None => Ok(()),
+        }
+    }
+
+    /**
+     * Close the stream, e.g., after a write has failed.
+     */
+    pub fn close(&self) {
+        self.writer.lock().unwrap().take();
+    }
+}
+
+/**
+ * Render a tag as [time, microstep] relative to the given start time in
+ * nanoseconds, or as "NEVER" or "FOREVER".
+ */
+pub(crate) fn json_tag(tag: &Tag, start_time: Instant) -> String {
+    if tag.time() == tag::NEVER {
+        String::from("\"NEVER\"")
+    } else if tag.time() == tag::FOREVER {
+        String::from("\"FOREVER\"")
+    } else {
+        format!(
+            "[{},{}]",
+            tag.time().saturating_sub(start_time),
+            tag.microstep()
+        )
+    }
+}
+
+/**
+ * Quote and escape a string as a JSON string.
+ */
+pub(crate) fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,287 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Injection of faults into the messages that the RTI sends, for testing
+ * the resilience of federate runtimes.
+ *
+ * Faults are described by rules, one per line, such as
+ * "drop TAG federate=1 from=100 to=200 probability=0.5", "delay PTAG ms=50",
+ * or "corrupt STOP_GRANTED federate=0".
+ * The first word is the action: drop the message, delay it by the given
+ * number of milliseconds, or corrupt one of the bytes after its type. The
+ * second word is the message: TAG, PTAG, STOP_REQUEST, or STOP_GRANTED. The
+ * options restrict the rule to one federate, to the tags whose time, relative
+ * to the start time and in units of the time resolution, is within the given
+ * bounds, and to the given fraction of the matching messages. Empty lines and
+ * lines starting with '#' are ignored. The first rule that matches a message
+ * applies. Which messages are faulted is determined by a seed, so that a run
+ * can be reproduced as long as the federates send the same messages.
+ */
+use std::io::Write;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::federate::federate_label;
+use crate::net_common::MsgType;
+use crate::outgoing::OutgoingLock;
+use crate::tag::{Instant, Tag, TimeResolution};
+use crate::trace::debug;
+use crate::transport::FederateStream;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum FaultAction {
+    Drop,
+    /// Delay by the given number of milliseconds.
+    Delay(u64),
+    Corrupt,
+}
+
+struct FaultRule {
+    action: FaultAction,
+    msg_type: u8,
+    fed_id: Option<u16>,
+    from: Option<Instant>, // Relative to the start time, in nanoseconds.
+    to: Option<Instant>,   // Relative to the start time, in nanoseconds.
+    probability: f64,
+}
+
+impl FaultRule {
+    /**
+     * Parse a rule, whose times are given in units of the given resolution.
+     */
+    fn parse(line: &str, time_resolution: TimeResolution) -> Result<FaultRule, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (action, message, options) = match words.as_slice() {
+            [action, message, options @ ..] => (*action, *message, options),
+            _ => return Err(String::from("expected an action and a message")),
+        };
+        let mut rule = FaultRule {
+            action: match action {
+                "drop" => FaultAction::Drop,
+                "delay" => FaultAction::Delay(0),
+                "corrupt" => FaultAction::Corrupt,
+                _ => return Err(format!("unknown action '{}'", action)),
+            },
+            msg_type: match message {
+                "TAG" => MsgType::TagAdvanceGrant.to_byte(),
+                "PTAG" => MsgType::PropositionalTagAdvanceGrant.to_byte(),
+                "STOP_REQUEST" => MsgType::StopRequest.to_byte(),
+                "STOP_GRANTED" => MsgType::StopGranted.to_byte(),
+                _ => return Err(format!("unknown message '{}'", message)),
+            },
+            fed_id: None,
+            from: None,
+            to: None,
+            probability: 1.0,
+        };
+        let time = |value: &str| {
+            value
+                .parse::<i64>()
+                .ok()
+                .and_then(|time| time_resolution.to_nanoseconds(time))
+        };
+        for option in options {
+            let Some((key, value)) = option.split_once('=') else {
+                return Err(format!("expected key=value instead of '{}'", option));
+            };
+            let invalid = || format!("invalid value '{}' of {}", value, key);
+            match key {
+                "federate" => rule.fed_id = Some(value.parse().map_err(|_| invalid())?),
+                "from" => rule.from = Some(time(value).ok_or_else(invalid)?),
+                "to" => rule.to = Some(time(value).ok_or_else(invalid)?),
+                "probability" => match value.parse::<f64>() {
+                    Ok(probability) if (0.0..=1.0).contains(&probability) => {
+                        rule.probability = probability;
+                    }
+                    _ => return Err(invalid()),
+                },
+                "ms" if matches!(rule.action, FaultAction::Delay(_)) => {
+                    rule.action = FaultAction::Delay(value.parse().map_err(|_| invalid())?);
+                }
+                _ => return Err(format!("unknown option '{}'", key)),
+            }
+        }
+        if rule.action == FaultAction::Delay(0) {
+            return Err(String::from("delay needs ms=<milliseconds>"));
+        }
+        Ok(rule)
+    }
+
+    fn matches(&self, msg_type: u8, fed_id: u16, tag: &Tag, start_time: Instant) -> bool {
+        let time = tag.time().saturating_sub(start_time);
+        self.msg_type == msg_type
+            && self.fed_id.is_none_or(|id| id == fed_id)
+            && self.from.is_none_or(|from| time >= from)
+            && self.to.is_none_or(|to| time <= to)
+    }
+}
+
+/**
+ * A fault to inject into one message.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Fault {
+    Drop,
+    /// Send the message after the given number of milliseconds. Messages sent
+    /// to the federate in the meantime overtake it.
+    Delay(u64),
+    /// Flip the bits of the given mask in the byte at the given offset after
+    /// the message type, modulo the length of the message.
+    Corrupt {
+        offset: usize,
+        mask: u8,
+    },
+}
+
+impl Fault {
+    /**
+     * Apply the fault to a message that is about to be written to the stream
+     * of the given federate while holding its outgoing lock. Return true if
+     * the (possibly corrupted) message is still to be written now.
+     */
+    pub fn apply(
+        &self,
+        buffer: &mut [u8],
+        fed_id: u16,
+        stream: &FederateStream,
+        outgoing_lock: Arc<OutgoingLock>,
+    ) -> bool {
+        let message_type = buffer[0];
+        match *self {
+            Fault::Drop => {
+                debug!(
+                    "RTI: Fault injection: dropped message type {} to federate {}.",
+                    message_type,
+                    federate_label(fed_id)
+                );
+                false
+            }
+            Fault::Delay(ms) => {
+                debug!(
+                    "RTI: Fault injection: delaying message type {} to federate {} by {} ms.",
+                    message_type,
+                    federate_label(fed_id),
+                    ms
+                );
+                let Ok(mut stream) = stream.try_clone() else {
+                    return true;
+                };
+                let buffer = buffer.to_vec();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(ms));
+                    let _outgoing_guard = outgoing_lock.lock();
+                    // NOTE: Ignore errors because the federate may have exited.
+                    let _ = stream.write_all(&buffer);
+                });
+                false
+            }
+            Fault::Corrupt { offset, mask } => {
+                if buffer.len() > 1 {
+                    let index = 1 + offset % (buffer.len() - 1);
+                    buffer[index] ^= mask;
+                    debug!(
+                        "RTI: Fault injection: corrupted byte {} of message type {} to federate {}.",
+                        index,
+                        message_type,
+                        federate_label(fed_id)
+                    );
+                }
+                true
+            }
+        }
+    }
+}
+
+/**
+ * Decides which messages to fault according to a list of rules.
+ */
+pub struct FaultInjector {
+    rules: Vec<FaultRule>,
+    seed: u64,
+    random_state: u64,
+}
+
+impl FaultInjector {
+    /**
+     * Parse the rules in the given text, whose times are given in units of the
+     * given resolution. Return a description of the first invalid rule, if any.
+     */
+    pub fn parse(
+        text: &str,
+        time_resolution: TimeResolution,
+        seed: u64,
+    ) -> Result<FaultInjector, String> {
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = FaultRule::parse(line, time_resolution)
+                .map_err(|e| format!("line {}: {}", number + 1, e))?;
+            rules.push(rule);
+        }
+        Ok(FaultInjector {
+            rules,
+            seed,
+            random_state: seed,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn number_of_rules(&self) -> usize {
+        self.rules.len()
+    }
+
+    /**
+     * Return the fault to inject into a message of the given type and tag to
+     * the given federate, if any.
+     */
+    pub fn decide(
+        &mut self,
+        msg_type: MsgType,
+        fed_id: u16,
+        tag: &Tag,
+        start_time: Instant,
+    ) -> Option<Fault> {
+        let msg_type = msg_type.to_byte();
+        let index = self
+            .rules
+            .iter()
+            .position(|rule| rule.matches(msg_type, fed_id, tag, start_time))?;
+        let (action, probability) = (self.rules[index].action, self.rules[index].probability);
+        // Compare 53 random bits with the probability, as in a uniform f64.
+        if ((self.next_random() >> 11) as f64) / ((1u64 << 53) as f64) >= probability {
+            return None;
+        }
+        Some(match action {
+            FaultAction::Drop => Fault::Drop,
+            FaultAction::Delay(ms) => Fault::Delay(ms),
+            FaultAction::Corrupt => {
+                let random = self.next_random();
+                Fault::Corrupt {
+                    offset: (random >> 8) as usize,
+                    mask: (random as u8).max(1),
+                }
+            }
+        })
+    }
+
+    /**
+     * Return the next number of the SplitMix64 sequence of the seed.
+     */
+    fn next_random(&mut self) -> u64 {
+        self.random_state = self.random_state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.random_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
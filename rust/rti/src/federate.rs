@@ -12,11 +12,116 @@
  * This file extends enclave.h with RTI features that are specific to federations and are not
  * used by scheduling enclaves.
  */
+use crate::egress::{EgressQueue, EgressShaper};
 use crate::enclave::*;
+use crate::history::{ProvisionalGrantStats, SchedulingHistory};
 use crate::message_record::message_record::InTransitMessageRecordQueue;
+use crate::net_common::{DEFAULT_CAPABILITIES, RESUME_TOKEN_LENGTH};
+use crate::outgoing::OutgoingLock;
+use crate::rate_limit::ControlRateLimiter;
+use crate::tag::Instant;
+use crate::transport::FederateStream;
+use crate::wire_dump::{Direction, WireDump};
 
-use std::net::TcpStream;
+use std::collections::HashMap;
 use std::option::Option;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+/**
+ * Names that federates presented at the handshake, by federate ID, used to
+ * label federates in the output of the RTI. Like the trace configuration, this
+ * is process-wide because the output is.
+ */
+static FEDERATE_LABELS: RwLock<Option<HashMap<u16, String>>> = RwLock::new(None);
+
+/**
+ * Record the name of a federate for its label in the output of the RTI.
+ */
+pub fn register_federate_label(fed_id: u16, name: &str) {
+    FEDERATE_LABELS
+        .write()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(fed_id, String::from(name));
+}
+
+/**
+ * Return the label of the federate with the given ID in the output of the RTI:
+ * its name followed by its ID, such as "sensor (3)", or the bare ID if the
+ * federate has not presented a name.
+ */
+pub fn federate_label(fed_id: impl Into<i64>) -> String {
+    let fed_id = fed_id.into();
+    let name = u16::try_from(fed_id).ok().and_then(|id| {
+        FEDERATE_LABELS
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|labels| labels.get(&id).cloned())
+    });
+    match name {
+        Some(name) => format!("{} ({})", name, fed_id),
+        None => fed_id.to_string(),
+    }
+}
+
+/**
+ * What a federate tells about itself in a MsgType::FederateMetadata message.
+ */
+#[derive(PartialEq, Clone, Debug)]
+pub struct FederateMetadata {
+    runtime: String,
+    host_name: String,
+    process_id: u32,
+}
+
+impl FederateMetadata {
+    pub fn new(runtime: String, host_name: String, process_id: u32) -> FederateMetadata {
+        FederateMetadata {
+            runtime,
+            host_name,
+            process_id,
+        }
+    }
+
+    pub fn runtime(&self) -> &str {
+        &self.runtime
+    }
+
+    pub fn host_name(&self) -> &str {
+        &self.host_name
+    }
+
+    pub fn process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /**
+     * Parse the metadata from the format of to_text(), as in a topology file.
+     */
+    pub fn parse(text: &str) -> Option<FederateMetadata> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let [runtime, host_name, process_id] = words.as_slice() else {
+            return None;
+        };
+        Some(FederateMetadata {
+            runtime: runtime.strip_prefix("runtime=")?.to_string(),
+            host_name: host_name.strip_prefix("host=")?.to_string(),
+            process_id: process_id.strip_prefix("pid=")?.parse().ok()?,
+        })
+    }
+
+    /**
+     * Return the metadata as "runtime=<runtime> host=<host name> pid=<ID>".
+     */
+    pub fn to_text(&self) -> String {
+        format!(
+            "runtime={} host={} pid={}",
+            self.runtime, self.host_name, self.process_id
+        )
+    }
+}
 
 /**
  * Information about a federate known to the RTI, including its runtime state,
@@ -25,6 +130,9 @@ use std::option::Option;
  * those that are connected via a "physical" connection (one
  * denoted with ~>) because those connections do not impose
  * any scheduling constraints.
+ * The state that the RTI updates while handling the NET and LTC messages of
+ * federates, which it does while sharing the lock of the RTI state, is guarded
+ * by a lock of its own or updated atomically.
  */
 pub struct Federate {
     enclave: Enclave,
@@ -32,20 +140,51 @@ pub struct Federate {
     // to a request for stop from the RTI. Used to prevent double-counting
     // a federate when handling lf_request_stop().
     // TODO: lf_thread_t thread_id;    // The ID of the thread handling communication with this federate.
-    stream: Option<TcpStream>, // The TCP socket descriptor for communicating with this federate.
-    // TODO: struct sockaddr_in UDP_addr;           // The UDP address for the federate.
+    stream: Option<FederateStream>, // The connection for communicating with this federate.
+    outgoing_lock: Arc<OutgoingLock>, // Held while writing a message to the stream so that
+    // a message streamed in chunks is not interleaved with other messages.
+    udp_port: Option<u16>, // Port of the UDP socket of the federate for runtime clock
+    // synchronization, at the address of its TCP connection, or None.
     clock_synchronization_enabled: bool, // Indicates the status of clock synchronization
     // for this federate. Enabled by default.
-    in_transit_message_tags: InTransitMessageRecordQueue, // Record of in-transit messages to this federate that are not
+    in_transit_message_tags: Mutex<InTransitMessageRecordQueue>, // Record of in-transit messages to this federate that are not
     // yet processed. This record is ordered based on the time
     // value of each message for a more efficient access.
-    server_hostname: String, // Human-readable IP address and
-    server_port: i32,        // port number of the socket server of the federate
-                             // if it has any incoming direct connections from other federates.
-                             // The port number will be -1 if there is no server or if the
-                             // RTI has not been informed of the port number.
-                             // TODO: struct in_addr server_ip_addr; // Information about the IP address of the socket
-                             // server of the federate.
+    // TODO: struct in_addr server_ip_addr; // Information about the IP address of the socket
+    // server of the federate.
+    failure_reason: Option<String>, // Reason of the failure if the federate has failed
+    // rather than resigned, or None otherwise.
+    name: Option<String>, // Human-readable name presented by the federate, or None.
+    metadata: Option<FederateMetadata>, // Metadata presented by the federate, or None.
+    unknown_neighbors: Vec<(u16, bool)>, // IDs of the neighbors listed by the federate that are
+    // not in the federation, which are left out of its neighbor structure, with whether each is
+    // listed as upstream.
+    control_rate_limiter: Mutex<Option<ControlRateLimiter>>, // Limit on the rate of the NET and LTC
+    // messages of the federate, or None.
+    egress_shaper: Mutex<Option<EgressShaper>>, // Limit on the rate of the data messages relayed
+    // to the federate, or None.
+    egress_queue: Arc<EgressQueue>, // Messages to the federate delayed by its egress rate.
+    control_stream: Option<FederateStream>, // Connection on which grants are sent if the federate
+    // has opened one. See MSG_TYPE_CONTROL_CHANNEL_HEADER_LENGTH.
+    control_lock: Arc<OutgoingLock>, // Held while writing a message to the control stream.
+    relayed_messages: AtomicU64, // Number of messages the RTI has started to relay to the federate.
+    wire_dump: Option<Arc<WireDump>>, // Dump of the bytes exchanged with the federate, or None.
+    history: Mutex<SchedulingHistory>, // Recent scheduling events of the federate.
+    provisional_grants: Mutex<ProvisionalGrantStats>, // Outcomes of the PTAGs sent to the federate.
+    capabilities: u32,           // Bitmask of the capabilities enabled for the federate.
+    // See MSG_TYPE_CAPABILITIES_LENGTH.
+    start_time_proposed: bool, // Indicates that the federate has proposed a start time
+    // on its current connection.
+    started_at: Option<Instant>, // Physical time at which the federate starts executing,
+    // i.e., the later of the start time and the time at which it was sent, or None.
+    net_received: AtomicBool, // Indicates that the federate has sent a NET on its current connection.
+    presumed_idle: AtomicBool, // Indicates that the RTI uses FOREVER as the NET of the federate
+    // because it has sent none within --silent_grace.
+    resume_token: Option<[u8; RESUME_TOKEN_LENGTH]>, // Token with which the federate can
+    // resume its session once, or None. See MSG_TYPE_RESUME_LENGTH.
+    suspended_at: Option<Instant>, // Physical time at which the connection of the federate
+    // was lost while the RTI holds its state for it to resume, or None.
+    dropped_while_suspended: AtomicU64, // Number of messages to the federate dropped while suspended.
 }
 
 impl Federate {
@@ -53,11 +192,50 @@ impl Federate {
         Federate {
             enclave: Enclave::new(),
             requested_stop: false,
-            stream: None::<TcpStream>,
+            stream: None::<FederateStream>,
+            outgoing_lock: OutgoingLock::new(),
+            udp_port: None,
             clock_synchronization_enabled: true,
-            in_transit_message_tags: InTransitMessageRecordQueue::new(),
-            server_hostname: String::from("localhost"),
-            server_port: -1,
+            in_transit_message_tags: Mutex::new(InTransitMessageRecordQueue::new()),
+            failure_reason: None,
+            name: None,
+            metadata: None,
+            unknown_neighbors: Vec::new(),
+            control_rate_limiter: Mutex::new(None),
+            egress_shaper: Mutex::new(None),
+            egress_queue: EgressQueue::new(),
+            control_stream: None,
+            control_lock: OutgoingLock::new(),
+            relayed_messages: AtomicU64::new(0),
+            wire_dump: None,
+            history: Mutex::new(SchedulingHistory::new()),
+            provisional_grants: Mutex::new(ProvisionalGrantStats::new()),
+            capabilities: DEFAULT_CAPABILITIES,
+            start_time_proposed: false,
+            started_at: None,
+            net_received: AtomicBool::new(false),
+            presumed_idle: AtomicBool::new(false),
+            resume_token: None,
+            suspended_at: None,
+            dropped_while_suspended: AtomicU64::new(0),
+        }
+    }
+
+    pub fn wire_dump(&self) -> &Option<Arc<WireDump>> {
+        &self.wire_dump
+    }
+
+    pub fn set_wire_dump(&mut self, wire_dump: Arc<WireDump>) {
+        self.wire_dump = Some(wire_dump);
+    }
+
+    /**
+     * Record bytes written to the federate other than through the stream of
+     * its scheduling thread, if its bytes are dumped.
+     */
+    pub fn record_sent(&self, bytes: &[u8]) {
+        if let Some(wire_dump) = &self.wire_dump {
+            wire_dump.record(Direction::Sent, bytes);
         }
     }
 
@@ -73,10 +251,22 @@ impl Federate {
         self.requested_stop
     }
 
-    pub fn stream(&self) -> &Option<TcpStream> {
+    pub fn stream(&self) -> &Option<FederateStream> {
         &self.stream
     }
 
+    pub fn outgoing_lock(&self) -> Arc<OutgoingLock> {
+        self.outgoing_lock.clone()
+    }
+
+    pub fn udp_port(&self) -> Option<u16> {
+        self.udp_port
+    }
+
+    pub fn set_udp_port(&mut self, udp_port: u16) {
+        self.udp_port = Some(udp_port);
+    }
+
     pub fn clock_synchronization_enabled(&self) -> bool {
         self.clock_synchronization_enabled
     }
@@ -85,7 +275,7 @@ impl Federate {
         self.requested_stop = requested_stop;
     }
 
-    pub fn set_stream(&mut self, stream: TcpStream) {
+    pub fn set_stream(&mut self, stream: FederateStream) {
         self.stream = Some(stream);
     }
 
@@ -93,15 +283,251 @@ impl Federate {
         self.clock_synchronization_enabled = clock_synchronization_enabled;
     }
 
-    pub fn in_transit_message_tags(&mut self) -> &mut InTransitMessageRecordQueue {
-        &mut self.in_transit_message_tags
+    pub fn in_transit_message_tags(&self) -> MutexGuard<'_, InTransitMessageRecordQueue> {
+        self.in_transit_message_tags.lock().unwrap()
+    }
+
+    pub fn set_in_transit_message_tags(
+        &mut self,
+        in_transit_message_tags: InTransitMessageRecordQueue,
+    ) {
+        *self.in_transit_message_tags.get_mut().unwrap() = in_transit_message_tags;
+    }
+
+    pub fn failure_reason(&self) -> &Option<String> {
+        &self.failure_reason
     }
 
-    pub fn set_server_hostname(&mut self, server_hostname: String) {
-        self.server_hostname = server_hostname;
+    pub fn set_failure_reason(&mut self, failure_reason: String) {
+        self.failure_reason = Some(failure_reason);
     }
 
-    pub fn set_server_port(&mut self, server_port: i32) {
-        self.server_port = server_port;
+    pub fn name(&self) -> &Option<String> {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    pub fn metadata(&self) -> &Option<FederateMetadata> {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, metadata: FederateMetadata) {
+        self.metadata = Some(metadata);
+    }
+
+    /**
+     * Return the IDs of the neighbors listed by the federate that are not in
+     * the federation, with whether each is listed as upstream.
+     */
+    pub fn unknown_neighbors(&self) -> &[(u16, bool)] {
+        &self.unknown_neighbors
+    }
+
+    pub fn add_unknown_neighbor(&mut self, neighbor_id: u16, is_upstream: bool) {
+        self.unknown_neighbors.push((neighbor_id, is_upstream));
+    }
+
+    pub fn history(&self) -> MutexGuard<'_, SchedulingHistory> {
+        self.history.lock().unwrap()
+    }
+
+    pub fn provisional_grants(&self) -> MutexGuard<'_, ProvisionalGrantStats> {
+        self.provisional_grants.lock().unwrap()
+    }
+
+    pub fn capabilities(&self) -> u32 {
+        self.capabilities
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: u32) {
+        self.capabilities = capabilities;
+    }
+
+    pub fn has_capability(&self, capability: u32) -> bool {
+        self.capabilities & capability != 0
+    }
+
+    pub fn start_time_proposed(&self) -> bool {
+        self.start_time_proposed
+    }
+
+    pub fn set_start_time_proposed(&mut self, start_time_proposed: bool) {
+        self.start_time_proposed = start_time_proposed;
+    }
+
+    /**
+     * Forget the state of a previous connection of a transient federate that
+     * joins again. Its scheduling history, statistics on provisional grants,
+     * wire dump, and rate limit are kept because they describe the federate
+     * rather than one connection.
+     */
+    pub fn reset_for_rejoin(&mut self) {
+        self.enclave.reset_for_rejoin();
+        self.requested_stop = false;
+        self.stream = None;
+        self.udp_port = None;
+        self.clock_synchronization_enabled = true;
+        let in_transit_message_tags = self.in_transit_message_tags.get_mut().unwrap();
+        *in_transit_message_tags =
+            InTransitMessageRecordQueue::with_budget(in_transit_message_tags.budget().clone());
+        self.failure_reason = None;
+        self.name = None;
+        self.metadata = None;
+        self.unknown_neighbors.clear();
+        self.control_stream = None;
+        *self.relayed_messages.get_mut() = 0;
+        self.capabilities = DEFAULT_CAPABILITIES;
+        self.start_time_proposed = false;
+        self.started_at = None;
+        *self.net_received.get_mut() = false;
+        *self.presumed_idle.get_mut() = false;
+        self.resume_token = None;
+        self.suspended_at = None;
+        *self.dropped_while_suspended.get_mut() = 0;
+    }
+
+    pub fn started_at(&self) -> Option<Instant> {
+        self.started_at
+    }
+
+    pub fn set_started_at(&mut self, started_at: Instant) {
+        self.started_at = Some(started_at);
+    }
+
+    pub fn net_received(&self) -> bool {
+        self.net_received.load(Ordering::SeqCst)
+    }
+
+    pub fn set_net_received(&self, net_received: bool) {
+        self.net_received.store(net_received, Ordering::SeqCst);
+    }
+
+    pub fn presumed_idle(&self) -> bool {
+        self.presumed_idle.load(Ordering::SeqCst)
+    }
+
+    pub fn set_presumed_idle(&self, presumed_idle: bool) {
+        self.presumed_idle.store(presumed_idle, Ordering::SeqCst);
+    }
+
+    pub fn resume_token(&self) -> &Option<[u8; RESUME_TOKEN_LENGTH]> {
+        &self.resume_token
+    }
+
+    pub fn set_resume_token(&mut self, resume_token: Option<[u8; RESUME_TOKEN_LENGTH]>) {
+        self.resume_token = resume_token;
+    }
+
+    pub fn suspended_at(&self) -> Option<Instant> {
+        self.suspended_at
+    }
+
+    /**
+     * Return true if the connection of the federate is lost and the RTI holds
+     * its state for it to resume its session.
+     */
+    pub fn suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
+
+    pub fn set_suspended_at(&mut self, suspended_at: Option<Instant>) {
+        self.suspended_at = suspended_at;
+    }
+
+    pub fn dropped_while_suspended(&self) -> u64 {
+        self.dropped_while_suspended.load(Ordering::SeqCst)
+    }
+
+    /**
+     * Count a message to the federate that the RTI dropped while the
+     * federate was suspended.
+     */
+    pub fn count_dropped_while_suspended(&self) {
+        self.dropped_while_suspended.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn reset_dropped_while_suspended(&self) {
+        self.dropped_while_suspended.store(0, Ordering::SeqCst);
+    }
+
+    /**
+     * Return true if the federate has started executing but has not sent a
+     * NET yet, which is typical of a federate that deadlocks internally before
+     * it reaches its first tag. Federates with decentralized coordination send
+     * no NET and are never silent.
+     */
+    pub fn silent(&self) -> bool {
+        self.started_at.is_some()
+            && !self.net_received()
+            && self.enclave.state() != FedState::NotConnected
+            && !self.enclave.decentralized()
+    }
+
+    pub fn control_rate_limiter(&self) -> MutexGuard<'_, Option<ControlRateLimiter>> {
+        self.control_rate_limiter.lock().unwrap()
+    }
+
+    pub fn egress_shaper(&self) -> MutexGuard<'_, Option<EgressShaper>> {
+        self.egress_shaper.lock().unwrap()
+    }
+
+    pub fn egress_queue(&self) -> Arc<EgressQueue> {
+        self.egress_queue.clone()
+    }
+
+    pub fn control_stream(&self) -> &Option<FederateStream> {
+        &self.control_stream
+    }
+
+    pub fn set_control_stream(&mut self, control_stream: FederateStream) {
+        self.control_stream = Some(control_stream);
+    }
+
+    /**
+     * Count a message that the RTI starts to relay to the federate. Must be
+     * called while holding both the grant lock of the federate and the
+     * outgoing lock, so that a grant sent on the control stream afterwards
+     * accounts for the message.
+     */
+    pub fn relayed_messages(&self) -> u64 {
+        self.relayed_messages.load(Ordering::SeqCst)
+    }
+
+    pub fn count_relayed_message(&self) {
+        self.relayed_messages.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /**
+     * Return the stream on which to send a grant to the federate, the lock to
+     * hold while writing to it, and, if it is the control stream, the number
+     * of relayed messages to append to the grant.
+     */
+    pub fn grant_channel(&self) -> (&FederateStream, Arc<OutgoingLock>, Option<u64>) {
+        match &self.control_stream {
+            Some(control_stream) => (
+                control_stream,
+                self.control_lock.clone(),
+                Some(self.relayed_messages()),
+            ),
+            None => (
+                self.stream.as_ref().unwrap(),
+                self.outgoing_lock.clone(),
+                None,
+            ),
+        }
+    }
+
+    /**
+     * Return the name of the federate followed by its ID, such as "sensor (3)",
+     * or the bare ID if the federate has not presented a name.
+     */
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({})", name, self.e().id()),
+            None => self.e().id().to_string(),
+        }
     }
 }
@@ -0,0 +1,265 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief The federation metadata emitted by the code generator, which
+ * describes the expected federates and connections, enabled with
+ * --federation_metadata.
+ *
+ * The RTI cross-checks the metadata against its arguments at startup and
+ * against the name and neighbor structure of each federate as it connects,
+ * and fails fast with a diff on a mismatch, which usually means that a
+ * federate runs a binary generated from another version of the program.
+ */
+use crate::group::FederateGroup;
+use crate::tag::{Interval, NO_DELAY};
+use crate::Federate;
+
+/**
+ * A federate as the metadata describes it. Its upstream federates and the
+ * minimum delays of the connections from them, and its downstream federates,
+ * follow from the connections.
+ */
+#[derive(Clone, Debug, Default)]
+struct ExpectedFederate {
+    name: Option<String>,
+    group: Option<String>,
+    upstream: Vec<(i32, Interval)>,
+    downstream: Vec<i32>,
+}
+
+/**
+ * The expected federation, parsed from a file with one item per line:
+ * "federation <ID>", which is optional, then
+ * "federate <ID> [name=<name>] [group=<name>]" for each federate in the order
+ * of the IDs, where the group is as for --group, and
+ * "connection <from ID> <to ID> [delay=<nanoseconds>|delay=none]" for each
+ * connection, where "none", the default, is a connection without an "after"
+ * delay. Lines starting with '#' are ignored.
+ */
+#[derive(Clone, Debug)]
+pub struct FederationMetadata {
+    federation_id: Option<String>,
+    federates: Vec<ExpectedFederate>,
+}
+
+impl FederationMetadata {
+    /**
+     * Parse a federation metadata file. Return a description of the first
+     * invalid line, if any.
+     */
+    pub fn parse(text: &str) -> Result<FederationMetadata, String> {
+        let mut metadata = FederationMetadata {
+            federation_id: None,
+            federates: Vec::new(),
+        };
+        let mut connections = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let invalid = || format!("line {}: invalid line '{}'", number + 1, line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["federation", federation_id] => {
+                    metadata.federation_id = Some(federation_id.to_string())
+                }
+                ["federate", id, rest @ ..] if rest.len() <= 2 => {
+                    if id.parse::<usize>().ok() != Some(metadata.federates.len()) {
+                        return Err(invalid());
+                    }
+                    let mut federate = ExpectedFederate::default();
+                    for attribute in rest {
+                        match attribute.split_once('=') {
+                            Some(("name", name)) if federate.name.is_none() => {
+                                federate.name = Some(String::from(name))
+                            }
+                            Some(("group", group))
+                                if federate.group.is_none()
+                                    && FederateGroup::is_valid_name(group) =>
+                            {
+                                federate.group = Some(String::from(group))
+                            }
+                            _ => return Err(invalid()),
+                        }
+                    }
+                    metadata.federates.push(federate);
+                }
+                ["connection", from, to, rest @ ..] if rest.len() <= 1 => {
+                    let delay = match rest {
+                        [] | ["delay=none"] => NO_DELAY,
+                        [delay] => Some(
+                            delay
+                                .strip_prefix("delay=")
+                                .and_then(|delay| delay.parse::<i64>().ok())
+                                .filter(|delay| *delay >= 0)
+                                .ok_or_else(invalid)?,
+                        ),
+                        _ => unreachable!(),
+                    };
+                    connections.push((
+                        number + 1,
+                        from.parse::<i32>().map_err(|_| invalid())?,
+                        to.parse::<i32>().map_err(|_| invalid())?,
+                        delay,
+                    ));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        let number_of_federates = metadata.federates.len();
+        for (number, from, to, delay) in connections {
+            let (Some(from_index), Some(to_index)) = (
+                usize::try_from(from)
+                    .ok()
+                    .filter(|id| *id < number_of_federates),
+                usize::try_from(to)
+                    .ok()
+                    .filter(|id| *id < number_of_federates),
+            ) else {
+                return Err(format!(
+                    "line {}: connection between unknown federates {} and {}",
+                    number, from, to
+                ));
+            };
+            // Like the code generator, list each neighbor once, with the
+            // minimum delay of the connections from an upstream federate.
+            let upstream = &mut metadata.federates[to_index].upstream;
+            match upstream.iter_mut().find(|(id, _)| *id == from) {
+                Some((_, min_delay)) => *min_delay = (*min_delay).min(delay),
+                None => upstream.push((from, delay)),
+            }
+            let downstream = &mut metadata.federates[from_index].downstream;
+            if !downstream.contains(&to) {
+                downstream.push(to);
+            }
+        }
+        Ok(metadata)
+    }
+
+    /**
+     * Return the differences between the metadata and the federation ID and
+     * number of federates given to the RTI, one per line, prefixed with "-"
+     * for what the metadata expects and with "+" for what was given.
+     */
+    pub fn diff_arguments(&self, federation_id: &str, number_of_federates: usize) -> Vec<String> {
+        let mut diff = Vec::new();
+        if let Some(expected_id) = &self.federation_id {
+            if expected_id != federation_id {
+                diff.push(format!("- federation {}", expected_id));
+                diff.push(format!("+ federation {}", federation_id));
+            }
+        }
+        if self.federates.len() != number_of_federates {
+            diff.push(format!("- {} federates", self.federates.len()));
+            diff.push(format!("+ {} federates", number_of_federates));
+        }
+        diff
+    }
+
+    /**
+     * Return the differences between the metadata and the name and neighbor
+     * structure that the given federate reported, in the format of
+     * diff_arguments(). A federate that presented no name matches any name.
+     */
+    pub fn diff_federate(&self, fed: &Federate) -> Vec<String> {
+        let e = fed.e();
+        let Some(expected) = self.federates.get(usize::from(e.id())) else {
+            return vec![format!("+ federate {}", e.id())];
+        };
+        let mut diff = Vec::new();
+        if let (Some(expected_name), Some(name)) = (&expected.name, fed.name()) {
+            if expected_name != name {
+                diff.push(format!("- name {}", expected_name));
+                diff.push(format!("+ name {}", name));
+            }
+        }
+        let upstream: Vec<(i32, Interval)> = e
+            .upstream()
+            .iter()
+            .copied()
+            .zip(e.upstream_delay().iter().copied())
+            .collect();
+        let describe_upstream = |&(id, delay): &(i32, Interval)| match delay {
+            _ if delay == NO_DELAY => format!("upstream {} with no delay", id),
+            Some(delay_ns) => format!("upstream {} with a delay of {} ns", id, delay_ns),
+            None => format!("upstream {} with an unknown delay", id),
+        };
+        let describe_downstream = |id: &i32| format!("downstream {}", id);
+        Self::diff_lists(&expected.upstream, &upstream, describe_upstream, &mut diff);
+        Self::diff_lists(
+            &expected.downstream,
+            e.downstream(),
+            describe_downstream,
+            &mut diff,
+        );
+        diff
+    }
+
+    /**
+     * Return the IDs of the federates that the metadata assigns to a group,
+     * with the name of the group.
+     */
+    pub fn groups(&self) -> Vec<(u16, &str)> {
+        self.federates
+            .iter()
+            .enumerate()
+            .filter_map(|(id, federate)| Some((id as u16, federate.group.as_deref()?)))
+            .collect()
+    }
+
+    /**
+     * Return the weakly connected components of the federation, each as the
+     * sorted IDs of its federates, in the order of their smallest IDs.
+     */
+    pub fn components(&self) -> Vec<Vec<u16>> {
+        let number_of_federates = self.federates.len();
+        let mut component_of: Vec<Option<usize>> = vec![None; number_of_federates];
+        let mut components = Vec::new();
+        for first in 0..number_of_federates {
+            if component_of[first].is_some() {
+                continue;
+            }
+            let index = components.len();
+            let mut component = Vec::new();
+            let mut to_visit = vec![first];
+            component_of[first] = Some(index);
+            while let Some(id) = to_visit.pop() {
+                component.push(id as u16);
+                let federate = &self.federates[id];
+                let neighbors = federate
+                    .upstream
+                    .iter()
+                    .map(|(id, _)| id)
+                    .chain(federate.downstream.iter());
+                for neighbor in neighbors {
+                    // The IDs were checked when parsing.
+                    let neighbor = *neighbor as usize;
+                    if component_of[neighbor].is_none() {
+                        component_of[neighbor] = Some(index);
+                        to_visit.push(neighbor);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+        components
+    }
+
+    fn diff_lists<T: PartialEq>(
+        expected: &[T],
+        actual: &[T],
+        describe: impl Fn(&T) -> String,
+        diff: &mut Vec<String>,
+    ) {
+        for item in expected.iter().filter(|item| !actual.contains(item)) {
+            diff.push(format!("- {}", describe(item)));
+        }
+        for item in actual.iter().filter(|item| !expected.contains(item)) {
+            diff.push(format!("+ {}", describe(item)));
+        }
+    }
+}
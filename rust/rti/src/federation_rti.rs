@@ -12,10 +12,49 @@
  * This file extends enclave.h with RTI features that are specific to federations and are not
  * used by scheduling enclaves.
  */
+use std::collections::HashMap;
+use std::panic::Location;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::{ClockSource, PhysicalClock, VirtualClock};
 use crate::constants::*;
+use crate::egress::{EgressRate, EgressShaper, ExcessEgressPolicy};
+use crate::enclave::{GrantInputs, PendingGrant, TagAdvanceGrant};
+use crate::encryption::OutputKey;
+use crate::event_stream::EventStream;
+use crate::fault::{Fault, FaultInjector};
 use crate::federate::*;
-use crate::tag::Tag;
+use crate::federation_metadata::FederationMetadata;
+use crate::group::{FederateGroup, GroupPolicy};
+use crate::history::HISTORY_ENTRY_SIZE;
+use crate::inspector::PayloadInspector;
+use crate::lft::{TraceEvent, TraceHeader};
+use crate::lock_stats::LockStats;
+use crate::log_sink::LogSink;
+use crate::memory::MemoryBudget;
+use crate::net_common::{
+    MsgType, CAPABILITY_CODED_PROBES, CAPABILITY_CONTROL_CHANNEL, CAPABILITY_FAST_MODE,
+    CAPABILITY_RESUMPTION,
+};
+use crate::notifier::{GrantNotifier, GrantWritePolicy};
+use crate::parent::ParentLink;
+use crate::partition::Partitions;
+use crate::rate_limit::{ControlRateLimiter, ExcessControlPolicy};
+use crate::shutdown::ShutdownHandle;
+use crate::snapshot::{Snapshot, SnapshotTrigger};
+use crate::tag::{Instant, Interval, Tag, TimeFormat, TimeResolution};
+use crate::tls::TlsConfig;
+use crate::topology::Topology;
+use crate::trace::{debug, error, warn, LogLevel, DEFAULT_TRACE_LINES_PER_SECOND};
+use crate::tracer::Tracer;
+use crate::wait_stats::WaitStats;
+use crate::wire_dump::WireDump;
 use crate::ClockSyncStat;
+use crate::EarlyNetPolicy;
+use crate::FailurePolicy;
+use crate::FedState;
 
 /**
  * Structure that an RTI instance uses to keep track of its own and its
@@ -39,8 +78,11 @@ pub struct FederationRTI {
     // Boolean indicating that tracing is enabled.
     tracing_enabled: bool,
 
-    // Pointer to a tracing object
-    // TODO: trace:Trace,
+    // Path of the trace file.
+    trace_file: String,
+
+    // Tracing object, which exists once the RTI has started if tracing is enabled.
+    tracer: Option<Arc<Tracer>>,
     ////////////// Federation only specific attributes //////////////
 
     // Maximum start time seen so far from the federates.
@@ -57,7 +99,7 @@ pub struct FederationRTI {
      * the socket, which will cause accept() to return with an error code
      * in respond_to_erroneous_connections().
      */
-    // TODO: volatile bool all_federates_exited;
+    all_federates_exited: bool,
 
     /**
      * The ID of the federation that this RTI will supervise.
@@ -103,7 +145,17 @@ pub struct FederationRTI {
     clock_sync_exchanges_per_interval: i32,
 
     /**
-     * Boolean indicating that authentication is enabled.
+     * Boolean indicating that the whole federation runs in fast mode, i.e.,
+     * without waiting for physical time. Physical-clock-based features such as
+     * clock synchronization are suppressed in this mode, and the federates
+     * that offer CAPABILITY_FAST_MODE are enabled it.
+     */
+    fast_mode: bool,
+
+    /**
+     * Boolean indicating that authentication is enabled, i.e., that federates
+     * have to prove that they know the federation ID before their
+     * MsgType::FedIds message is accepted.
      */
     authentication_enabled: bool,
 
@@ -111,108 +163,2162 @@ pub struct FederationRTI {
      * Boolean indicating that a stop request is already in progress.
      */
     stop_in_progress: bool,
-}
 
-impl FederationRTI {
-    pub fn new() -> FederationRTI {
-        FederationRTI {
-            enclaves: Vec::new(),
-            // enclave_rti related initializations
-            max_stop_tag: Tag::never_tag(),
-            number_of_enclaves: 0,
-            num_enclaves_handling_stop: 0,
-            // federation_rti related initializations
-            max_start_time: 0,
-            num_feds_proposed_start: 0,
-            // all_federates_exited:false,
-            federation_id: String::from("Unidentified Federation"),
-            user_specified_port: STARTING_PORT,
-            final_port_tcp: 0,
-            socket_descriptor_tcp: -1,
-            final_port_udp: u16::MAX,
-            socket_descriptor_udp: -1,
-            clock_sync_global_status: ClockSyncStat::ClockSyncInit,
-            clock_sync_period_ns: 10 * 1000000,
-            clock_sync_exchanges_per_interval: 10,
-            authentication_enabled: false,
-            tracing_enabled: false,
-            stop_in_progress: false,
-        }
-    }
+    /**
+     * Boolean indicating that the federation is draining before it stops, in
+     * which case no transient federate is admitted.
+     */
+    draining: bool,
 
-    pub fn enclaves(&mut self) -> &mut Vec<Federate> {
-        &mut self.enclaves
-    }
+    /**
+     * The expected stop time of the federation in nanoseconds relative to the
+     * start time (e.g., the timeout of the LF program), if specified by the user.
+     */
+    stop_time: Option<i64>,
 
-    pub fn max_stop_tag(&self) -> Tag {
-        self.max_stop_tag.clone()
-    }
+    /**
+     * The largest amount in nanoseconds by which a granted tag may be ahead of
+     * the physical time of the RTI, if specified by the user. Grants further
+     * ahead are held back until physical time catches up.
+     */
+    max_lead: Option<i64>,
 
-    pub fn number_of_enclaves(&self) -> i32 {
-        self.number_of_enclaves
-    }
+    /**
+     * The largest logical interval in nanoseconds by which a granted tag may
+     * be ahead of the earliest LTC of the connected federates, if specified by
+     * the user. Grants further ahead are held back until that LTC advances.
+     */
+    lookahead: Option<i64>,
 
-    pub fn num_enclaves_handling_stop(&self) -> i32 {
-        self.num_enclaves_handling_stop
-    }
+    /**
+     * The largest amount in nanoseconds by which the physical time of the RTI
+     * may be ahead of the earliest granted tag, if specified by the user.
+     * Beyond it, the RTI warns about the slowest federate.
+     */
+    max_lag: Option<i64>,
 
-    pub fn max_start_time(&self) -> i64 {
-        self.max_start_time
-    }
+    /**
+     * The largest lag in nanoseconds observed with --max_lag, or None, and
+     * the number of times that it exceeded the maximum.
+     */
+    max_lag_observed: Option<i64>,
+    lag_warnings: u64,
 
-    pub fn num_feds_proposed_start(&self) -> i32 {
-        self.num_feds_proposed_start
-    }
+    /**
+     * The arguments given to the RTI, without the name of the program.
+     */
+    arguments: Vec<String>,
 
-    pub fn federation_id(&self) -> String {
-        self.federation_id.clone()
-    }
+    /**
+     * File of the database in which the summary of the run is recorded, or
+     * None. See --run_db.
+     */
+    run_db: Option<String>,
 
-    pub fn user_specified_port(&self) -> u16 {
-        self.user_specified_port
-    }
+    /**
+     * Time in milliseconds after which a federate that has started but sent
+     * no NET is presumed to have a NET of FOREVER, so that the federates
+     * downstream of it can proceed. 0 disables the presumption.
+     */
+    silent_grace_ms: u64,
 
-    pub fn final_port_udp(&self) -> u16 {
-        self.final_port_udp
-    }
+    /**
+     * Time in milliseconds for which the RTI holds the state of a federate
+     * whose connection is lost for it to resume its session with its
+     * resumption token. 0 disables resumption.
+     */
+    resume_window_ms: u64,
 
-    pub fn clock_sync_global_status(&self) -> ClockSyncStat {
-        self.clock_sync_global_status.clone()
-    }
+    /**
+     * Period in milliseconds between progress reports. 0 disables reporting.
+     */
+    progress_period_ms: u64,
 
-    pub fn stop_in_progress(&self) -> bool {
-        self.stop_in_progress
-    }
+    /**
+     * Time in milliseconds that a newly accepted connection has to complete
+     * its handshake before the RTI closes it. 0 disables the deadline.
+     */
+    handshake_timeout_ms: u64,
 
-    pub fn set_max_stop_tag(&mut self, max_stop_tag: Tag) {
-        self.max_stop_tag = max_stop_tag.clone();
-    }
+    /**
+     * Time in seconds that the RTI waits for all federates to connect before
+     * it shuts down. 0 waits forever.
+     */
+    connect_timeout_s: u64,
 
-    pub fn set_number_of_enclaves(&mut self, number_of_enclaves: i32) {
-        self.number_of_enclaves = number_of_enclaves;
-    }
+    /**
+     * Whether DNET messages are disabled (see --disable_dnet).
+     */
+    dnet_disabled: bool,
 
-    pub fn set_num_enclaves_handling_stop(&mut self, num_enclaves_handling_stop: i32) {
-        self.num_enclaves_handling_stop = num_enclaves_handling_stop;
-    }
+    /**
+     * File to which the topology of the federation is written, and from which
+     * it is loaded by later runs, if specified by the user.
+     */
+    topology_file: Option<String>,
 
-    pub fn set_max_start_time(&mut self, max_start_time: i64) {
-        self.max_start_time = max_start_time;
-    }
+    /**
+     * Expected federates and connections, emitted by the code generator, if
+     * specified by the user.
+     */
+    federation_metadata: Option<FederationMetadata>,
 
-    pub fn set_num_feds_proposed_start(&mut self, num_feds_proposed_start: i32) {
-        self.num_feds_proposed_start = num_feds_proposed_start;
-    }
+    /**
+     * Topology of the federation, once all federates have connected.
+     */
+    topology: Option<Topology>,
 
-    pub fn set_federation_id(&mut self, federation_id: String) {
-        self.federation_id = federation_id;
-    }
+    /**
+     * IDs or names of the federates whose exchanged bytes are dumped to files.
+     */
+    wire_dump_federates: Vec<String>,
 
-    pub fn set_port(&mut self, user_specified_port: u16) {
-        self.user_specified_port = user_specified_port;
-    }
+    /**
+     * Directory in which the wire dumps are written.
+     */
+    wire_dump_dir: String,
 
-    pub fn set_stop_in_progress(&mut self, stop_in_progress: bool) {
-        self.stop_in_progress = stop_in_progress;
-    }
+    /**
+     * What to do when a federate fails.
+     */
+    failure_policy: FailurePolicy,
+
+    /**
+     * Named groups of federates whose policies override the ones of the RTI
+     * for their members, and the index of the group of each federate in one.
+     */
+    groups: Vec<FederateGroup>,
+    group_of: HashMap<u16, usize>,
+
+    /**
+     * What to do when a federate sends a NET earlier than the start time.
+     */
+    early_net_policy: EarlyNetPolicy,
+
+    /**
+     * Boolean indicating that federates violating the protocol (e.g., by
+     * sending invalid tags) are disconnected rather than only reported.
+     */
+    quarantine_enabled: bool,
+
+    /**
+     * Boolean indicating that the conditions that the RTI otherwise reports
+     * and tolerates, such as protocol violations, duplicate messages, and
+     * inconsistent neighbor structures, shut down the RTI instead.
+     */
+    strict: bool,
+
+    /**
+     * Number of threads that perform handshakes with connecting federates.
+     */
+    io_threads: usize,
+
+    /**
+     * Number of threads that handle messages from connected federates.
+     */
+    scheduling_threads: usize,
+
+    /**
+     * CPUs to which the I/O threads are pinned in turn. Empty means any CPU.
+     */
+    io_cpus: Vec<usize>,
+
+    /**
+     * CPUs to which the scheduling threads are pinned in turn. Empty means
+     * any CPU.
+     */
+    scheduling_cpus: Vec<usize>,
+
+    /**
+     * SCHED_FIFO priority of the I/O and scheduling threads, or None to keep
+     * the default scheduling policy.
+     */
+    realtime_priority: Option<i32>,
+
+    /**
+     * How much the RTI prints.
+     */
+    log_level: LogLevel,
+
+    /**
+     * Maximum number of trace lines printed per second. 0 means no limit.
+     */
+    trace_lines_per_second: u64,
+
+    /**
+     * Where the RTI prints, and the socket of the daemon if not the default one.
+     */
+    log_sink: LogSink,
+    log_socket: Option<String>,
+
+    /**
+     * The file that the RTI prints to instead of the standard output, if any.
+     */
+    log_file: Option<String>,
+
+    /**
+     * Key under which the trace file, the event stream, the wire dumps, and
+     * the log file are encrypted, or None to write them in the clear.
+     */
+    output_key: Option<OutputKey>,
+
+    /**
+     * Certificate and key with which federates are accepted over TLS, or None
+     * to accept them over plain TCP.
+     */
+    tls_config: Option<TlsConfig>,
+
+    /**
+     * Time in seconds that a federate connection may be idle before the
+     * operating system probes whether the federate is still alive. 0 disables
+     * the probes.
+     */
+    keepalive_s: u64,
+
+    /**
+     * Handle through which a shutdown of the RTI is requested.
+     */
+    shutdown: ShutdownHandle,
+
+    /**
+     * IDs of the federates that use decentralized coordination.
+     */
+    decentralized_federates: Vec<u16>,
+
+    /**
+     * IDs of the federates that may join after the start, and resign and
+     * join again. The federation starts without waiting for them.
+     */
+    transient_federates: Vec<u16>,
+
+    /**
+     * Maximum size in bytes of the payload of a message from a federate.
+     * 0 means no limit.
+     */
+    max_message_size: usize,
+
+    /**
+     * Port of the admin console on the loopback interface, if enabled.
+     */
+    admin_port: Option<u16>,
+
+    /**
+     * Port of the HTTP status server on the loopback interface, if enabled.
+     */
+    status_port: Option<u16>,
+
+    /**
+     * File to which snapshots of the federation are written, if any.
+     */
+    snapshot_file: Option<String>,
+
+    /**
+     * Period in milliseconds of the snapshots written to the snapshot file.
+     * 0 means only on request and at the end of the federation.
+     */
+    checkpoint_period_ms: u64,
+
+    /**
+     * Trigger of the snapshots written to the snapshot file.
+     */
+    snapshot_trigger: SnapshotTrigger,
+
+    /**
+     * Snapshot from which the federation is restarted, if any.
+     */
+    restored_snapshot: Option<Snapshot>,
+
+    /**
+     * Token with which clients of the admin console get the control role, if
+     * clients must authenticate.
+     */
+    admin_token: Option<String>,
+
+    /**
+     * Token with which clients of the admin console and the status server get
+     * the read-only role, if clients must authenticate.
+     */
+    admin_read_token: Option<String>,
+
+    /**
+     * Address of the parent RTI to which the RTI connects as a federate, if
+     * the federation is coordinated hierarchically.
+     */
+    parent_address: Option<String>,
+
+    /**
+     * ID of this RTI as a federate of the parent RTI.
+     */
+    parent_id: u16,
+
+    /**
+     * Upstream federates of this RTI at the parent RTI with the delays of
+     * their connections.
+     */
+    parent_upstream: Vec<(u16, Interval)>,
+
+    /**
+     * Downstream federates of this RTI at the parent RTI.
+     */
+    parent_downstream: Vec<u16>,
+
+    /**
+     * Connection to the parent RTI once it is established.
+     */
+    parent_link: Option<ParentLink>,
+
+    /**
+     * Latest tag up to which the parent RTI allows the federates to advance,
+     * or None if there is no parent RTI.
+     */
+    parent_grant: Option<Tag>,
+
+    /**
+     * Port on which the RTI accepts connections from federates, or None once
+     * all federates have connected.
+     */
+    listening_port: Option<u16>,
+
+    /**
+     * Boolean indicating that an operator has asked the RTI to close and
+     * reopen the listening socket.
+     */
+    relisten_requested: bool,
+
+    /**
+     * Boolean indicating that an operator has paused the federation, so that
+     * no TAG or PTAG is sent until it is resumed.
+     */
+    paused: bool,
+
+    /**
+     * Boolean indicating that each grant is withheld until an operator
+     * releases it.
+     */
+    step_mode: bool,
+
+    /**
+     * Boolean indicating that the start time is withheld, once all federates
+     * have proposed one, until an operator releases it.
+     */
+    start_held: bool,
+
+    /**
+     * Physical time at which the start time became held back because all
+     * federates had proposed one, or None.
+     */
+    held_since: Option<Instant>,
+
+    /**
+     * Grants withheld in step mode, in the order in which they were computed.
+     */
+    pending_grants: Mutex<Vec<PendingGrant>>,
+
+    /**
+     * The weakly connected components of the federation, whose grants are
+     * decided under locks of their own. See lock_grants.
+     */
+    partitions: Partitions,
+
+    /**
+     * Indicates whether grant decisions are reused while their inputs are
+     * unchanged. See --no_grant_cache.
+     */
+    grant_cache: bool,
+
+    /**
+     * Queues of grants that have been decided but not yet written.
+     */
+    grant_notifier: Arc<GrantNotifier>,
+
+    /**
+     * The grant that an operator has released and that is being sent.
+     */
+    released_grant: Mutex<Option<PendingGrant>>,
+
+    /**
+     * Tag relative to the start time beyond which no federate is granted
+     * (or None). Federates may also have their own breakpoints.
+     */
+    breakpoint: Option<Tag>,
+
+    /**
+     * Number of consecutive grants to a federate at the same time with
+     * increasing microsteps after which the RTI warns about a microstep loop.
+     * 0 disables the warning.
+     */
+    microstep_warning_threshold: u32,
+
+    /**
+     * Resolution of logical time in the federation.
+     */
+    time_resolution: TimeResolution,
+
+    /**
+     * Format in which the RTI prints times, e.g., of tags.
+     */
+    time_format: TimeFormat,
+
+    /**
+     * Boolean indicating that each line printed by the RTI starts with the
+     * current time in the time format.
+     */
+    log_timestamps: bool,
+
+    /**
+     * Clock from which the RTI reads physical time.
+     */
+    clock_source: ClockSource,
+
+    /**
+     * Physical clock of the RTI, read from the clock source.
+     */
+    physical_clock: OnceLock<Arc<PhysicalClock>>,
+
+    /**
+     * Clock read instead of the clock source, given by a test that advances
+     * physical time itself.
+     */
+    virtual_clock: Option<VirtualClock>,
+
+    /**
+     * Boolean indicating that federates may join without a federate ID and
+     * have the RTI assign one.
+     */
+    dynamic_ids: bool,
+
+    /**
+     * IDs assigned by the RTI, in the order in which the federates joined.
+     */
+    assigned_federate_ids: Vec<u16>,
+
+    /**
+     * Stream to which scheduling events are written for external analyzers,
+     * or None.
+     */
+    event_stream: Option<EventStream>,
+
+    /**
+     * Number of recent scheduling events of each federate that are kept for
+     * the admin console. 0 disables the history.
+     */
+    history_length: usize,
+
+    /**
+     * Memory budgets shared by the in-transit records of all federates, by
+     * the trace buffer, and by the scheduling histories of all federates, if
+     * specified by the user. See memory.rs.
+     */
+    in_transit_budget: Option<Arc<MemoryBudget>>,
+    trace_budget: Option<Arc<MemoryBudget>>,
+    history_budget: Option<Arc<MemoryBudget>>,
+
+    /**
+     * Observers of the messages forwarded between federates, registered by
+     * applications that embed the RTI.
+     */
+    payload_inspectors: Vec<Arc<dyn PayloadInspector>>,
+
+    /**
+     * Rules for injecting faults into the messages sent to federates, or None.
+     */
+    fault_injector: Option<Mutex<FaultInjector>>,
+
+    /**
+     * Maximum number of NET and LTC messages per second from each federate.
+     * 0 means no limit.
+     */
+    max_control_rate: u32,
+
+    /**
+     * What to do with the NET and LTC messages in excess of the rate.
+     */
+    excess_control_policy: ExcessControlPolicy,
+
+    /**
+     * What the notifier does when the socket of a federate does not take its
+     * grants, for the federates given a policy of their own and then for all
+     * others.
+     */
+    grant_write_policies: HashMap<u16, GrantWritePolicy>,
+    default_grant_write_policy: GrantWritePolicy,
+
+    /**
+     * Limits on the rate of the data messages relayed to the federates given
+     * a limit of their own and then to all others, or None for no limit.
+     */
+    egress_rates: HashMap<u16, EgressRate>,
+    default_egress_rate: Option<EgressRate>,
+
+    /**
+     * What to do with the data messages in excess of the egress rate.
+     */
+    excess_egress_policy: ExcessEgressPolicy,
+
+    /**
+     * Statistics of the contention on the lock of this state by call site, or
+     * None if they are not collected.
+     */
+    lock_stats: Option<Mutex<LockStats>>,
+
+    /**
+     * Statistics of the waits of the RTI threads for the start time to be
+     * sent and of the times at which the federates proposed their start
+     * times.
+     */
+    wait_stats: Mutex<WaitStats>,
+}
+
+impl FederationRTI {
+    pub fn new() -> FederationRTI {
+        FederationRTI {
+            enclaves: Vec::new(),
+            // enclave_rti related initializations
+            max_stop_tag: Tag::never_tag(),
+            number_of_enclaves: 0,
+            num_enclaves_handling_stop: 0,
+            // federation_rti related initializations
+            max_start_time: 0,
+            num_feds_proposed_start: 0,
+            all_federates_exited: false,
+            federation_id: String::from("Unidentified Federation"),
+            user_specified_port: STARTING_PORT,
+            final_port_tcp: 0,
+            socket_descriptor_tcp: -1,
+            final_port_udp: u16::MAX,
+            socket_descriptor_udp: -1,
+            clock_sync_global_status: ClockSyncStat::ClockSyncInit,
+            clock_sync_period_ns: 10 * 1000000,
+            clock_sync_exchanges_per_interval: 10,
+            fast_mode: false,
+            authentication_enabled: false,
+            tracing_enabled: false,
+            trace_file: String::from(DEFAULT_TRACE_FILE),
+            tracer: None,
+            stop_in_progress: false,
+            draining: false,
+            stop_time: None,
+            max_lead: None,
+            lookahead: None,
+            max_lag: None,
+            max_lag_observed: None,
+            lag_warnings: 0,
+            arguments: Vec::new(),
+            run_db: None,
+            silent_grace_ms: 0,
+            resume_window_ms: 0,
+            progress_period_ms: 0,
+            handshake_timeout_ms: DEFAULT_HANDSHAKE_TIMEOUT_MS,
+            connect_timeout_s: 0,
+            dnet_disabled: false,
+            topology_file: None,
+            federation_metadata: None,
+            topology: None,
+            wire_dump_federates: Vec::new(),
+            wire_dump_dir: String::from("."),
+            failure_policy: FailurePolicy::Degrade,
+            groups: Vec::new(),
+            group_of: HashMap::new(),
+            early_net_policy: EarlyNetPolicy::Clamp,
+            quarantine_enabled: false,
+            strict: false,
+            io_threads: number_of_cpus(),
+            scheduling_threads: number_of_cpus(),
+            io_cpus: Vec::new(),
+            scheduling_cpus: Vec::new(),
+            realtime_priority: None,
+            log_level: LogLevel::Info,
+            trace_lines_per_second: DEFAULT_TRACE_LINES_PER_SECOND,
+            log_sink: LogSink::Stdout,
+            log_socket: None,
+            log_file: None,
+            output_key: None,
+            tls_config: None,
+            keepalive_s: 0,
+            shutdown: ShutdownHandle::new(),
+            decentralized_federates: Vec::new(),
+            transient_federates: Vec::new(),
+            max_message_size: 0,
+            admin_port: None,
+            status_port: None,
+            snapshot_file: None,
+            checkpoint_period_ms: 0,
+            snapshot_trigger: SnapshotTrigger::new(),
+            restored_snapshot: None,
+            admin_token: None,
+            admin_read_token: None,
+            parent_address: None,
+            parent_id: 0,
+            parent_upstream: Vec::new(),
+            parent_downstream: Vec::new(),
+            parent_link: None,
+            parent_grant: None,
+            listening_port: None,
+            relisten_requested: false,
+            paused: false,
+            step_mode: false,
+            start_held: false,
+            held_since: None,
+            pending_grants: Mutex::new(Vec::new()),
+            partitions: Partitions::new(0),
+            grant_cache: true,
+            grant_notifier: Arc::new(GrantNotifier::new()),
+            released_grant: Mutex::new(None),
+            breakpoint: None,
+            microstep_warning_threshold: DEFAULT_MICROSTEP_WARNING_THRESHOLD,
+            time_resolution: TimeResolution::Nanoseconds,
+            time_format: TimeFormat::Elapsed,
+            log_timestamps: false,
+            clock_source: ClockSource::Monotonic,
+            physical_clock: OnceLock::new(),
+            virtual_clock: None,
+            dynamic_ids: false,
+            assigned_federate_ids: Vec::new(),
+            event_stream: None,
+            history_length: DEFAULT_HISTORY_LENGTH,
+            in_transit_budget: None,
+            trace_budget: None,
+            history_budget: None,
+            payload_inspectors: Vec::new(),
+            fault_injector: None,
+            max_control_rate: 0,
+            excess_control_policy: ExcessControlPolicy::Coalesce,
+            grant_write_policies: HashMap::new(),
+            default_grant_write_policy: GrantWritePolicy::Block,
+            egress_rates: HashMap::new(),
+            default_egress_rate: None,
+            excess_egress_policy: ExcessEgressPolicy::Queue,
+            lock_stats: None,
+            wait_stats: Mutex::new(WaitStats::new()),
+        }
+    }
+
+    pub(crate) fn enclaves(&mut self) -> &mut Vec<Federate> {
+        &mut self.enclaves
+    }
+
+    /**
+     * Return the federates for reading their state, or updating the part of
+     * it that has a lock of its own, while sharing the lock of this state.
+     */
+    pub(crate) fn federates(&self) -> &Vec<Federate> {
+        &self.enclaves
+    }
+
+    pub(crate) fn max_stop_tag(&self) -> Tag {
+        self.max_stop_tag.clone()
+    }
+
+    pub fn number_of_enclaves(&self) -> i32 {
+        self.number_of_enclaves
+    }
+
+    pub(crate) fn num_enclaves_handling_stop(&self) -> i32 {
+        self.num_enclaves_handling_stop
+    }
+
+    pub(crate) fn max_start_time(&self) -> i64 {
+        self.max_start_time
+    }
+
+    pub(crate) fn num_feds_proposed_start(&self) -> i32 {
+        self.num_feds_proposed_start
+    }
+
+    pub(crate) fn all_federates_exited(&self) -> bool {
+        self.all_federates_exited
+    }
+
+    pub fn federation_id(&self) -> String {
+        self.federation_id.clone()
+    }
+
+    pub fn user_specified_port(&self) -> u16 {
+        self.user_specified_port
+    }
+
+    pub(crate) fn clock_sync_global_status(&self) -> ClockSyncStat {
+        self.clock_sync_global_status.clone()
+    }
+
+    pub(crate) fn clock_sync_period_ns(&self) -> u64 {
+        self.clock_sync_period_ns
+    }
+
+    pub(crate) fn clock_sync_exchanges_per_interval(&self) -> i32 {
+        self.clock_sync_exchanges_per_interval
+    }
+
+    pub(crate) fn fast_mode(&self) -> bool {
+        self.fast_mode
+    }
+
+    pub(crate) fn stop_in_progress(&self) -> bool {
+        self.stop_in_progress
+    }
+
+    pub(crate) fn draining(&self) -> bool {
+        self.draining
+    }
+
+    pub(crate) fn stop_time(&self) -> Option<i64> {
+        self.stop_time
+    }
+
+    pub(crate) fn max_lead(&self) -> Option<i64> {
+        self.max_lead
+    }
+
+    pub(crate) fn max_lag(&self) -> Option<i64> {
+        self.max_lag
+    }
+
+    #[cfg(feature = "run_db")]
+    pub(crate) fn max_lag_observed(&self) -> Option<i64> {
+        self.max_lag_observed
+    }
+
+    #[cfg(feature = "run_db")]
+    pub(crate) fn lag_warnings(&self) -> u64 {
+        self.lag_warnings
+    }
+
+    /**
+     * Record a lag in nanoseconds observed by the lag thread.
+     */
+    pub(crate) fn record_lag(&mut self, lag: i64) {
+        self.max_lag_observed = Some(self.max_lag_observed.map_or(lag, |max| max.max(lag)));
+    }
+
+    pub(crate) fn count_lag_warning(&mut self) {
+        self.lag_warnings += 1;
+    }
+
+    #[cfg(feature = "run_db")]
+    pub(crate) fn arguments(&self) -> &Vec<String> {
+        &self.arguments
+    }
+
+    pub(crate) fn set_arguments(&mut self, arguments: Vec<String>) {
+        self.arguments = arguments;
+    }
+
+    #[cfg(feature = "run_db")]
+    pub(crate) fn run_db(&self) -> &Option<String> {
+        &self.run_db
+    }
+
+    pub(crate) fn set_run_db(&mut self, run_db: String) {
+        self.run_db = Some(run_db);
+    }
+
+    pub(crate) fn silent_grace_ms(&self) -> u64 {
+        self.silent_grace_ms
+    }
+
+    pub(crate) fn resume_window_ms(&self) -> u64 {
+        self.resume_window_ms
+    }
+
+    pub(crate) fn progress_period_ms(&self) -> u64 {
+        self.progress_period_ms
+    }
+
+    pub(crate) fn handshake_timeout_ms(&self) -> u64 {
+        self.handshake_timeout_ms
+    }
+
+    pub(crate) fn connect_timeout_s(&self) -> u64 {
+        self.connect_timeout_s
+    }
+
+    pub(crate) fn dnet_disabled(&self) -> bool {
+        self.dnet_disabled
+    }
+
+    /**
+     * Return the capabilities that the RTI enables for the federates that
+     * offer them. See MSG_TYPE_CAPABILITIES_LENGTH.
+     */
+    pub(crate) fn supported_capabilities(&self) -> u32 {
+        let mut capabilities = CAPABILITY_CONTROL_CHANNEL | CAPABILITY_CODED_PROBES;
+        if self.resume_window_ms > 0 {
+            capabilities |= CAPABILITY_RESUMPTION;
+        }
+        if self.fast_mode {
+            capabilities |= CAPABILITY_FAST_MODE;
+        }
+        capabilities
+    }
+
+    pub(crate) fn topology_file(&self) -> &Option<String> {
+        &self.topology_file
+    }
+
+    pub(crate) fn federation_metadata(&self) -> &Option<FederationMetadata> {
+        &self.federation_metadata
+    }
+
+    pub(crate) fn topology(&self) -> &Option<Topology> {
+        &self.topology
+    }
+
+    pub(crate) fn failure_policy(&self) -> FailurePolicy {
+        self.failure_policy
+    }
+
+    pub fn groups(&self) -> &Vec<FederateGroup> {
+        &self.groups
+    }
+
+    /**
+     * Return the group of the given federate, if it is in one.
+     */
+    pub fn group_of(&self, fed_id: u16) -> Option<&FederateGroup> {
+        self.group_of.get(&fed_id).map(|index| &self.groups[*index])
+    }
+
+    fn group_policy(&self, fed_id: u16) -> Option<&GroupPolicy> {
+        self.group_of(fed_id).map(|group| group.policy())
+    }
+
+    /**
+     * Return the failure policy of the given federate: the one of its group,
+     * if the group sets one, or else the one of the RTI.
+     */
+    pub(crate) fn federate_failure_policy(&self, fed_id: u16) -> FailurePolicy {
+        self.group_policy(fed_id)
+            .and_then(|policy| policy.failure_policy())
+            .unwrap_or(self.failure_policy)
+    }
+
+    /**
+     * Return the maximum rate of the control messages of the given federate,
+     * where 0 means no limit, like federate_failure_policy().
+     */
+    pub(crate) fn federate_max_control_rate(&self, fed_id: u16) -> u32 {
+        self.group_policy(fed_id)
+            .and_then(|policy| policy.max_control_rate())
+            .unwrap_or(self.max_control_rate)
+    }
+
+    /**
+     * Return the lookahead of the given federate, like
+     * federate_failure_policy().
+     */
+    pub(crate) fn federate_lookahead(&self, fed_id: u16) -> Option<i64> {
+        self.group_policy(fed_id)
+            .and_then(|policy| policy.lookahead())
+            .or(self.lookahead)
+    }
+
+    /**
+     * Return true if the grants of some federate are bounded by a lookahead,
+     * either the one of the RTI or the one of its group.
+     */
+    pub(crate) fn has_lookahead(&self) -> bool {
+        self.lookahead.is_some()
+            || self
+                .groups
+                .iter()
+                .any(|group| group.policy().lookahead().is_some())
+    }
+
+    /**
+     * Return the log levels of the federates whose group sets one, by
+     * federate ID.
+     */
+    pub(crate) fn federate_log_levels(&self) -> HashMap<u16, LogLevel> {
+        self.group_of
+            .iter()
+            .filter_map(|(fed_id, index)| {
+                let log_level = self.groups[*index].policy().log_level()?;
+                Some((*fed_id, log_level))
+            })
+            .collect()
+    }
+
+    pub(crate) fn early_net_policy(&self) -> EarlyNetPolicy {
+        self.early_net_policy
+    }
+
+    pub(crate) fn quarantine_enabled(&self) -> bool {
+        self.quarantine_enabled
+    }
+
+    pub(crate) fn io_threads(&self) -> usize {
+        self.io_threads
+    }
+
+    pub(crate) fn scheduling_threads(&self) -> usize {
+        self.scheduling_threads
+    }
+
+    pub(crate) fn io_cpus(&self) -> &Vec<usize> {
+        &self.io_cpus
+    }
+
+    pub(crate) fn scheduling_cpus(&self) -> &Vec<usize> {
+        &self.scheduling_cpus
+    }
+
+    pub(crate) fn realtime_priority(&self) -> Option<i32> {
+        self.realtime_priority
+    }
+
+    pub(crate) fn log_level(&self) -> LogLevel {
+        self.log_level
+    }
+
+    pub(crate) fn trace_lines_per_second(&self) -> u64 {
+        self.trace_lines_per_second
+    }
+
+    pub(crate) fn log_sink(&self) -> LogSink {
+        self.log_sink
+    }
+
+    pub(crate) fn log_socket(&self) -> &Option<String> {
+        &self.log_socket
+    }
+
+    pub(crate) fn log_file(&self) -> &Option<String> {
+        &self.log_file
+    }
+
+    pub(crate) fn output_key(&self) -> &Option<OutputKey> {
+        &self.output_key
+    }
+
+    pub(crate) fn tls_config(&self) -> &Option<TlsConfig> {
+        &self.tls_config
+    }
+
+    pub(crate) fn keepalive_s(&self) -> u64 {
+        self.keepalive_s
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    pub(crate) fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    pub(crate) fn admin_port(&self) -> Option<u16> {
+        self.admin_port
+    }
+
+    pub(crate) fn status_port(&self) -> Option<u16> {
+        self.status_port
+    }
+
+    pub(crate) fn snapshot_file(&self) -> &Option<String> {
+        &self.snapshot_file
+    }
+
+    pub(crate) fn checkpoint_period_ms(&self) -> u64 {
+        self.checkpoint_period_ms
+    }
+
+    /**
+     * Return the trigger through which a snapshot is written to the snapshot
+     * file, e.g., on SIGUSR1.
+     */
+    pub fn snapshot_trigger(&self) -> SnapshotTrigger {
+        self.snapshot_trigger.clone()
+    }
+
+    pub(crate) fn restored_snapshot(&self) -> &Option<Snapshot> {
+        &self.restored_snapshot
+    }
+
+    pub(crate) fn admin_token(&self) -> &Option<String> {
+        &self.admin_token
+    }
+
+    pub(crate) fn admin_read_token(&self) -> &Option<String> {
+        &self.admin_read_token
+    }
+
+    pub(crate) fn parent_address(&self) -> &Option<String> {
+        &self.parent_address
+    }
+
+    pub(crate) fn parent_id(&self) -> u16 {
+        self.parent_id
+    }
+
+    pub(crate) fn parent_upstream(&self) -> &Vec<(u16, Interval)> {
+        &self.parent_upstream
+    }
+
+    pub(crate) fn parent_downstream(&self) -> &Vec<u16> {
+        &self.parent_downstream
+    }
+
+    pub(crate) fn parent_link(&self) -> &Option<ParentLink> {
+        &self.parent_link
+    }
+
+    pub(crate) fn parent_grant(&self) -> &Option<Tag> {
+        &self.parent_grant
+    }
+
+    pub(crate) fn listening_port(&self) -> Option<u16> {
+        self.listening_port
+    }
+
+    pub(crate) fn relisten_requested(&self) -> bool {
+        self.relisten_requested
+    }
+
+    pub(crate) fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub(crate) fn step_mode(&self) -> bool {
+        self.step_mode
+    }
+
+    pub(crate) fn start_held(&self) -> bool {
+        self.start_held
+    }
+
+    pub(crate) fn held_since(&self) -> Option<Instant> {
+        self.held_since
+    }
+
+    pub(crate) fn pending_grants(&self) -> Vec<PendingGrant> {
+        self.pending_grants.lock().unwrap().clone()
+    }
+
+    pub(crate) fn dynamic_ids(&self) -> bool {
+        self.dynamic_ids
+    }
+
+    pub(crate) fn authentication_enabled(&self) -> bool {
+        self.authentication_enabled
+    }
+
+    pub(crate) fn assigned_federate_ids(&self) -> &Vec<u16> {
+        &self.assigned_federate_ids
+    }
+
+    /**
+     * Return the physical clock of the RTI, which is created from the clock
+     * source, or the virtual clock if there is one, when it is first needed.
+     */
+    pub fn physical_clock(&self) -> Arc<PhysicalClock> {
+        let clock_source = self.clock_source;
+        let virtual_clock = &self.virtual_clock;
+        self.physical_clock
+            .get_or_init(|| match virtual_clock {
+                Some(virtual_clock) => Arc::new(PhysicalClock::new_virtual(
+                    virtual_clock.clone(),
+                    clock_source,
+                )),
+                None => Arc::new(PhysicalClock::new(clock_source)),
+            })
+            .clone()
+    }
+
+    pub fn time_resolution(&self) -> TimeResolution {
+        self.time_resolution
+    }
+
+    pub(crate) fn time_format(&self) -> TimeFormat {
+        self.time_format
+    }
+
+    pub(crate) fn log_timestamps(&self) -> bool {
+        self.log_timestamps
+    }
+
+    pub(crate) fn microstep_warning_threshold(&self) -> u32 {
+        self.microstep_warning_threshold
+    }
+
+    pub(crate) fn breakpoint(&self) -> Option<Tag> {
+        self.breakpoint.clone()
+    }
+
+    /**
+     * Return the absolute tag beyond which the given federate is not granted:
+     * the earlier of the global breakpoint and the federate's own, or None if
+     * neither is set.
+     */
+    pub(crate) fn breakpoint_of(&self, fed_id: u16, start_time: Instant) -> Option<Tag> {
+        let own = self.enclaves[fed_id as usize].e().breakpoint();
+        let earliest = match (self.breakpoint.clone(), own) {
+            (Some(global), Some(own)) => {
+                if own < global {
+                    Some(own)
+                } else {
+                    Some(global)
+                }
+            }
+            (global, own) => global.or(own),
+        };
+        earliest.map(|tag| tag.saturating_add(start_time))
+    }
+
+    /**
+     * Return the tag to which a federate without upstream and downstream
+     * federates can advance right away: the stop tag if the stop time is
+     * known, or FOREVER otherwise.
+     */
+    pub(crate) fn unconstrained_tag(&self, start_time: Instant) -> Tag {
+        match self.stop_time {
+            Some(stop_time) => Tag::new(start_time, 0).saturating_add(stop_time),
+            None => Tag::forever_tag(),
+        }
+    }
+
+    /**
+     * Return the latest tag that the lookahead window of the given federate
+     * allows to be granted to it: its lookahead (see federate_lookahead())
+     * after the earliest LTC of the connected federates, or after the start
+     * tag if one of them has not completed a tag yet. Transient federates,
+     * which join at a later tag, do not hold the window back. Return None
+     * without a lookahead or once no federate is connected.
+     */
+    pub(crate) fn lookahead_limit(&self, fed_id: u16, start_time: Instant) -> Option<Tag> {
+        let lookahead = self.federate_lookahead(fed_id)?;
+        Some(
+            self.lookahead_window_start(start_time)?
+                .saturating_add(lookahead),
+        )
+    }
+
+    /**
+     * Return the tag from which the lookahead windows extend, see
+     * lookahead_limit(), or None once no federate is connected.
+     */
+    pub(crate) fn lookahead_window_start(&self, start_time: Instant) -> Option<Tag> {
+        let earliest_completed = self
+            .enclaves
+            .iter()
+            .map(|fed| fed.e())
+            .filter(|e| e.state() != FedState::NotConnected && !e.decentralized() && !e.transient())
+            .map(|e| e.completed())
+            .min()?;
+        Some(earliest_completed.max(Tag::new(start_time, 0)))
+    }
+
+    pub(crate) fn decentralized_federates(&self) -> &Vec<u16> {
+        &self.decentralized_federates
+    }
+
+    pub(crate) fn transient_federates(&self) -> &Vec<u16> {
+        &self.transient_federates
+    }
+
+    /**
+     * Return the number of federates that the federation waits for before it
+     * starts and before it ends, that is, those that are not transient.
+     */
+    pub(crate) fn number_of_persistent_federates(&self) -> i32 {
+        self.number_of_enclaves - self.transient_federates.len() as i32
+    }
+
+    pub(crate) fn set_max_stop_tag(&mut self, max_stop_tag: Tag) {
+        self.max_stop_tag = max_stop_tag.clone();
+    }
+
+    pub(crate) fn set_number_of_enclaves(&mut self, number_of_enclaves: i32) {
+        self.number_of_enclaves = number_of_enclaves;
+    }
+
+    pub(crate) fn set_num_enclaves_handling_stop(&mut self, num_enclaves_handling_stop: i32) {
+        self.num_enclaves_handling_stop = num_enclaves_handling_stop;
+    }
+
+    pub(crate) fn set_max_start_time(&mut self, max_start_time: i64) {
+        self.max_start_time = max_start_time;
+    }
+
+    pub(crate) fn set_num_feds_proposed_start(&mut self, num_feds_proposed_start: i32) {
+        self.num_feds_proposed_start = num_feds_proposed_start;
+    }
+
+    pub(crate) fn set_federation_id(&mut self, federation_id: String) {
+        self.federation_id = federation_id;
+    }
+
+    pub(crate) fn set_port(&mut self, user_specified_port: u16) {
+        self.user_specified_port = user_specified_port;
+    }
+
+    pub(crate) fn set_clock_sync_global_status(&mut self, clock_sync_global_status: ClockSyncStat) {
+        self.clock_sync_global_status = clock_sync_global_status;
+    }
+
+    pub(crate) fn set_clock_sync_period_ns(&mut self, clock_sync_period_ns: u64) {
+        self.clock_sync_period_ns = clock_sync_period_ns;
+    }
+
+    pub(crate) fn set_clock_sync_exchanges_per_interval(
+        &mut self,
+        clock_sync_exchanges_per_interval: i32,
+    ) {
+        self.clock_sync_exchanges_per_interval = clock_sync_exchanges_per_interval;
+    }
+
+    pub(crate) fn set_final_port_udp(&mut self, final_port_udp: u16) {
+        self.final_port_udp = final_port_udp;
+    }
+
+    pub(crate) fn set_fast_mode(&mut self, fast_mode: bool) {
+        self.fast_mode = fast_mode;
+    }
+
+    pub(crate) fn set_stop_in_progress(&mut self, stop_in_progress: bool) {
+        self.stop_in_progress = stop_in_progress;
+    }
+
+    #[doc(hidden)]
+    pub fn set_draining(&mut self, draining: bool) {
+        self.draining = draining;
+    }
+
+    pub(crate) fn set_all_federates_exited(&mut self, all_federates_exited: bool) {
+        self.all_federates_exited = all_federates_exited;
+    }
+
+    pub(crate) fn set_stop_time(&mut self, stop_time: i64) {
+        self.stop_time = Some(stop_time);
+    }
+
+    pub(crate) fn set_max_lead(&mut self, max_lead: Option<i64>) {
+        self.max_lead = max_lead;
+    }
+
+    pub(crate) fn set_lookahead(&mut self, lookahead: Option<i64>) {
+        self.lookahead = lookahead;
+    }
+
+    pub(crate) fn set_max_lag(&mut self, max_lag: Option<i64>) {
+        self.max_lag = max_lag;
+    }
+
+    pub(crate) fn set_silent_grace_ms(&mut self, silent_grace_ms: u64) {
+        self.silent_grace_ms = silent_grace_ms;
+    }
+
+    pub(crate) fn set_resume_window_ms(&mut self, resume_window_ms: u64) {
+        self.resume_window_ms = resume_window_ms;
+    }
+
+    pub(crate) fn set_progress_period_ms(&mut self, progress_period_ms: u64) {
+        self.progress_period_ms = progress_period_ms;
+    }
+
+    pub(crate) fn set_handshake_timeout_ms(&mut self, handshake_timeout_ms: u64) {
+        self.handshake_timeout_ms = handshake_timeout_ms;
+    }
+
+    pub(crate) fn set_connect_timeout_s(&mut self, connect_timeout_s: u64) {
+        self.connect_timeout_s = connect_timeout_s;
+    }
+
+    pub(crate) fn set_dnet_disabled(&mut self, dnet_disabled: bool) {
+        self.dnet_disabled = dnet_disabled;
+    }
+
+    pub(crate) fn set_topology_file(&mut self, topology_file: String) {
+        self.topology_file = Some(topology_file);
+    }
+
+    pub(crate) fn set_federation_metadata(&mut self, federation_metadata: FederationMetadata) {
+        self.federation_metadata = Some(federation_metadata);
+    }
+
+    pub(crate) fn set_topology(&mut self, topology: Topology) {
+        self.topology = Some(topology);
+    }
+
+    /**
+     * Remove the connections of a federate that has left the federation for
+     * good from the topology, if it is known. Transient federates may join
+     * again, so their connections are kept.
+     */
+    pub(crate) fn remove_from_topology(&mut self, fed_id: u16) {
+        if self.enclaves[usize::from(fed_id)].e().transient() {
+            return;
+        }
+        if let Some(topology) = self.topology.as_mut() {
+            if topology.remove_federate(fed_id) {
+                debug!(
+                    "RTI: Removed the connections of federate {} from the topology.",
+                    federate_label(fed_id)
+                );
+            }
+        }
+    }
+
+    pub(crate) fn set_failure_policy(&mut self, failure_policy: FailurePolicy) {
+        self.failure_policy = failure_policy;
+    }
+
+    /**
+     * Add the given federate to the group with the given name, which is
+     * created if needed. Return an error if the federate is already in
+     * another group.
+     */
+    pub(crate) fn add_to_group(&mut self, name: &str, fed_id: u16) -> Result<(), String> {
+        let index = match self.groups.iter().position(|group| group.name() == name) {
+            Some(index) => index,
+            None => {
+                self.groups.push(FederateGroup::new(name));
+                self.groups.len() - 1
+            }
+        };
+        match self.group_of.get(&fed_id) {
+            Some(other) if *other != index => Err(format!(
+                "federate {} is in both group {} and group {}",
+                fed_id,
+                self.groups[*other].name(),
+                name
+            )),
+            _ => {
+                self.groups[index].add_member(fed_id);
+                self.group_of.insert(fed_id, index);
+                Ok(())
+            }
+        }
+    }
+
+    /**
+     * Override the policies of the group with the given name with the ones
+     * that the given policy sets. Return an error if no federate is in the
+     * group.
+     */
+    pub(crate) fn set_group_policy(
+        &mut self,
+        name: &str,
+        policy: &GroupPolicy,
+    ) -> Result<(), String> {
+        match self.groups.iter_mut().find(|group| group.name() == name) {
+            Some(group) => {
+                group.policy_mut().merge(policy);
+                Ok(())
+            }
+            None => Err(format!("group {} has no federates", name)),
+        }
+    }
+
+    pub(crate) fn set_early_net_policy(&mut self, early_net_policy: EarlyNetPolicy) {
+        self.early_net_policy = early_net_policy;
+    }
+
+    pub(crate) fn set_quarantine_enabled(&mut self, quarantine_enabled: bool) {
+        self.quarantine_enabled = quarantine_enabled;
+    }
+
+    pub(crate) fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub(crate) fn set_io_threads(&mut self, io_threads: usize) {
+        self.io_threads = io_threads;
+    }
+
+    pub(crate) fn set_scheduling_threads(&mut self, scheduling_threads: usize) {
+        self.scheduling_threads = scheduling_threads;
+    }
+
+    pub(crate) fn set_io_cpus(&mut self, io_cpus: Vec<usize>) {
+        self.io_cpus = io_cpus;
+    }
+
+    pub(crate) fn set_scheduling_cpus(&mut self, scheduling_cpus: Vec<usize>) {
+        self.scheduling_cpus = scheduling_cpus;
+    }
+
+    pub(crate) fn set_realtime_priority(&mut self, realtime_priority: i32) {
+        self.realtime_priority = Some(realtime_priority);
+    }
+
+    pub(crate) fn set_log_level(&mut self, log_level: LogLevel) {
+        self.log_level = log_level;
+    }
+
+    pub(crate) fn set_trace_lines_per_second(&mut self, trace_lines_per_second: u64) {
+        self.trace_lines_per_second = trace_lines_per_second;
+    }
+
+    pub(crate) fn set_log_sink(&mut self, log_sink: LogSink) {
+        self.log_sink = log_sink;
+    }
+
+    pub(crate) fn set_log_socket(&mut self, log_socket: String) {
+        self.log_socket = Some(log_socket);
+    }
+
+    pub(crate) fn set_log_file(&mut self, log_file: String) {
+        self.log_file = Some(log_file);
+    }
+
+    pub(crate) fn set_output_key(&mut self, output_key: OutputKey) {
+        self.output_key = Some(output_key);
+    }
+
+    pub(crate) fn set_tls_config(&mut self, tls_config: TlsConfig) {
+        self.tls_config = Some(tls_config);
+    }
+
+    pub(crate) fn set_keepalive_s(&mut self, keepalive_s: u64) {
+        self.keepalive_s = keepalive_s;
+    }
+
+    pub(crate) fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    pub(crate) fn set_admin_port(&mut self, admin_port: u16) {
+        self.admin_port = Some(admin_port);
+    }
+
+    pub(crate) fn set_status_port(&mut self, status_port: u16) {
+        self.status_port = Some(status_port);
+    }
+
+    pub(crate) fn set_snapshot_file(&mut self, snapshot_file: String) {
+        self.snapshot_file = Some(snapshot_file);
+    }
+
+    pub(crate) fn set_checkpoint_period_ms(&mut self, checkpoint_period_ms: u64) {
+        self.checkpoint_period_ms = checkpoint_period_ms;
+    }
+
+    pub(crate) fn set_restored_snapshot(&mut self, snapshot: Snapshot) {
+        self.restored_snapshot = Some(snapshot);
+    }
+
+    pub(crate) fn set_admin_token(&mut self, admin_token: String) {
+        self.admin_token = Some(admin_token);
+    }
+
+    pub(crate) fn set_admin_read_token(&mut self, admin_read_token: String) {
+        self.admin_read_token = Some(admin_read_token);
+    }
+
+    pub(crate) fn set_parent_address(&mut self, parent_address: String) {
+        self.parent_address = Some(parent_address);
+    }
+
+    pub(crate) fn set_parent_id(&mut self, parent_id: u16) {
+        self.parent_id = parent_id;
+    }
+
+    pub(crate) fn set_parent_upstream(&mut self, parent_upstream: Vec<(u16, Interval)>) {
+        self.parent_upstream = parent_upstream;
+    }
+
+    pub(crate) fn set_parent_downstream(&mut self, parent_downstream: Vec<u16>) {
+        self.parent_downstream = parent_downstream;
+    }
+
+    pub(crate) fn set_parent_link(&mut self, parent_link: ParentLink) {
+        self.parent_link = Some(parent_link);
+    }
+
+    pub(crate) fn set_parent_grant(&mut self, parent_grant: Option<Tag>) {
+        self.parent_grant = parent_grant;
+    }
+
+    pub(crate) fn set_listening_port(&mut self, listening_port: Option<u16>) {
+        self.listening_port = listening_port;
+    }
+
+    pub(crate) fn set_relisten_requested(&mut self, relisten_requested: bool) {
+        self.relisten_requested = relisten_requested;
+    }
+
+    pub(crate) fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub(crate) fn set_step_mode(&mut self, step_mode: bool) {
+        self.step_mode = step_mode;
+    }
+
+    pub(crate) fn set_start_held(&mut self, start_held: bool) {
+        self.start_held = start_held;
+    }
+
+    pub(crate) fn set_held_since(&mut self, held_since: Option<Instant>) {
+        self.held_since = held_since;
+    }
+
+    /**
+     * Return the grant decision made for the federate from the given inputs,
+     * if it is the last decision made for it.
+     */
+    pub(crate) fn cached_grant_decision(
+        &self,
+        fed_id: u16,
+        inputs: &GrantInputs,
+    ) -> Option<TagAdvanceGrant> {
+        if !self.grant_cache {
+            return None;
+        }
+        match self.partitions.grant_decisions(fed_id).get(&fed_id) {
+            Some((cached_inputs, grant)) if cached_inputs == inputs => Some(grant.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn grant_notifier(&self) -> Arc<GrantNotifier> {
+        self.grant_notifier.clone()
+    }
+
+    /**
+     * Recompute every grant decision rather than reusing the one made from
+     * the same inputs.
+     */
+    pub(crate) fn disable_grant_cache(&mut self) {
+        self.grant_cache = false;
+    }
+
+    pub(crate) fn cache_grant_decision(
+        &self,
+        fed_id: u16,
+        inputs: GrantInputs,
+        grant: TagAdvanceGrant,
+    ) {
+        self.partitions
+            .grant_decisions(fed_id)
+            .insert(fed_id, (inputs, grant));
+    }
+
+    /**
+     * Acquire the lock under which the grants of the federates of the weakly
+     * connected component of the given federate are decided and sent while
+     * sharing the lock of this state. It must be acquired after that lock and
+     * before the lock of the tags of any federate, and a thread may hold the
+     * lock of a single component at a time. A thread that holds the exclusive
+     * lock of this state need not acquire it.
+     */
+    pub(crate) fn lock_grants(&self, fed_id: u16) -> MutexGuard<'_, ()> {
+        self.partitions.lock_grants(fed_id)
+    }
+
+    pub(crate) fn partitions(&self) -> &Partitions {
+        &self.partitions
+    }
+
+    pub(crate) fn set_partitions(&mut self, partitions: Partitions) {
+        self.partitions = partitions;
+    }
+
+    /**
+     * Merge the weakly connected component of the given federate with the
+     * ones of the neighbors that it has declared.
+     */
+    pub(crate) fn merge_partitions(&mut self, fed_id: u16) {
+        let e = self.enclaves[usize::from(fed_id)].e();
+        let neighbors: Vec<u16> = e
+            .upstream()
+            .iter()
+            .chain(e.downstream().iter())
+            .filter_map(|id| u16::try_from(*id).ok())
+            .collect();
+        self.partitions.merge(fed_id, &neighbors);
+    }
+
+    /**
+     * Add a grant withheld in step mode unless the same grant is already
+     * pending. Return true if it was added.
+     */
+    pub(crate) fn add_pending_grant(&self, pending: PendingGrant) -> bool {
+        let mut pending_grants = self.pending_grants.lock().unwrap();
+        if pending_grants.iter().any(|other| other.same_as(&pending)) {
+            return false;
+        }
+        pending_grants.push(pending);
+        true
+    }
+
+    /**
+     * Remove the oldest pending grant and mark it as released so that it is
+     * sent by the next attempt to send it.
+     */
+    pub(crate) fn release_pending_grant(&mut self) -> Option<PendingGrant> {
+        let pending_grants = self.pending_grants.get_mut().unwrap();
+        if pending_grants.is_empty() {
+            return None;
+        }
+        let pending = pending_grants.remove(0);
+        *self.released_grant.get_mut().unwrap() = Some(pending.clone());
+        Some(pending)
+    }
+
+    /**
+     * Return true, and forget the release, if the given grant is the one
+     * that has been released.
+     */
+    pub(crate) fn take_released_grant(&self, pending: &PendingGrant) -> bool {
+        let mut released_grant = self.released_grant.lock().unwrap();
+        match &*released_grant {
+            Some(released) if released.same_as(pending) => {
+                *released_grant = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn clear_released_grant(&mut self) {
+        *self.released_grant.get_mut().unwrap() = None;
+    }
+
+    pub(crate) fn set_dynamic_ids(&mut self, dynamic_ids: bool) {
+        self.dynamic_ids = dynamic_ids;
+    }
+
+    pub(crate) fn set_authentication_enabled(&mut self, authentication_enabled: bool) {
+        self.authentication_enabled = authentication_enabled;
+    }
+
+    /**
+     * Assign the lowest federate ID that no federate has connected with yet,
+     * mark it as pending so that no other federate gets it, and record the
+     * assignment. Return None if every ID is taken.
+     */
+    pub(crate) fn assign_federate_id(&mut self) -> Option<u16> {
+        let fed = self
+            .enclaves
+            .iter_mut()
+            .find(|fed| fed.e().state() == FedState::NotConnected)?;
+        let enclave = fed.enclave();
+        enclave.set_state(FedState::Pending);
+        let fed_id = enclave.id();
+        self.assigned_federate_ids.push(fed_id);
+        Some(fed_id)
+    }
+
+    /**
+     * Return the ID of the federate that presented the given name, if any.
+     */
+    pub(crate) fn federate_id_by_name(&self, name: &str) -> Option<u16> {
+        self.enclaves
+            .iter()
+            .find(|fed| fed.name().as_deref() == Some(name))
+            .map(|fed| fed.e().id())
+    }
+
+    pub(crate) fn wire_dump_enabled(&self) -> bool {
+        !self.wire_dump_federates.is_empty()
+    }
+
+    /**
+     * Return the wire dump of the federate with the given ID, creating it if
+     * the federate is selected by its ID or name and has none yet.
+     */
+    pub(crate) fn open_wire_dump(&mut self, fed_id: u16) -> Option<Arc<WireDump>> {
+        let fed = self.enclaves.get_mut(usize::from(fed_id))?;
+        if fed.wire_dump().is_some() {
+            return fed.wire_dump().clone();
+        }
+        let selected = self.wire_dump_federates.iter().any(|selected| {
+            *selected == fed_id.to_string() || fed.name().as_deref() == Some(selected.as_str())
+        });
+        if !selected {
+            return None;
+        }
+        match WireDump::create(
+            &self.wire_dump_dir,
+            fed_id,
+            &fed.label(),
+            self.output_key.as_ref(),
+        ) {
+            Ok(dump) => {
+                let dump = Arc::new(dump);
+                fed.set_wire_dump(dump.clone());
+                Some(dump)
+            }
+            Err(e) => {
+                warn!(
+                    "RTI: WARNING: Failed to create the wire dump of federate {}: {}.",
+                    fed.label(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /**
+     * Return the label of the federate with the given ID. See Federate::label.
+     */
+    pub(crate) fn federate_label(&self, fed_id: u16) -> String {
+        match self.enclaves.get(usize::from(fed_id)) {
+            Some(fed) => fed.label(),
+            None => fed_id.to_string(),
+        }
+    }
+
+    pub fn payload_inspectors(&self) -> &Vec<Arc<dyn PayloadInspector>> {
+        &self.payload_inspectors
+    }
+
+    /**
+     * Register an inspector that observes every message forwarded between
+     * federates. See PayloadInspector.
+     */
+    pub fn add_payload_inspector(&mut self, inspector: Arc<dyn PayloadInspector>) {
+        self.payload_inspectors.push(inspector);
+    }
+
+    pub(crate) fn set_fault_injector(&mut self, fault_injector: FaultInjector) {
+        self.fault_injector = Some(Mutex::new(fault_injector));
+    }
+
+    /**
+     * Return the fault to inject into a message of the given type and tag to
+     * the given federate, if fault injection is enabled.
+     */
+    pub(crate) fn inject_fault(
+        &self,
+        msg_type: MsgType,
+        fed_id: u16,
+        tag: &Tag,
+        start_time: Instant,
+    ) -> Option<Fault> {
+        self.fault_injector
+            .as_ref()?
+            .lock()
+            .unwrap()
+            .decide(msg_type, fed_id, tag, start_time)
+    }
+
+    pub(crate) fn set_max_control_rate(&mut self, max_control_rate: u32) {
+        self.max_control_rate = max_control_rate;
+    }
+
+    pub(crate) fn set_excess_control_policy(&mut self, excess_control_policy: ExcessControlPolicy) {
+        self.excess_control_policy = excess_control_policy;
+    }
+
+    pub(crate) fn grant_write_policy(&self, fed_id: u16) -> GrantWritePolicy {
+        self.grant_write_policies
+            .get(&fed_id)
+            .copied()
+            .unwrap_or(self.default_grant_write_policy)
+    }
+
+    pub(crate) fn grant_write_policies(&self) -> &HashMap<u16, GrantWritePolicy> {
+        &self.grant_write_policies
+    }
+
+    pub(crate) fn set_grant_write_policy(&mut self, fed_id: u16, policy: GrantWritePolicy) {
+        self.grant_write_policies.insert(fed_id, policy);
+    }
+
+    pub(crate) fn set_default_grant_write_policy(&mut self, policy: GrantWritePolicy) {
+        self.default_grant_write_policy = policy;
+    }
+
+    pub(crate) fn egress_rate(&self, fed_id: u16) -> Option<EgressRate> {
+        self.egress_rates
+            .get(&fed_id)
+            .copied()
+            .or(self.default_egress_rate)
+    }
+
+    pub(crate) fn egress_rates(&self) -> &HashMap<u16, EgressRate> {
+        &self.egress_rates
+    }
+
+    pub(crate) fn set_egress_rate(&mut self, fed_id: u16, rate: EgressRate) {
+        self.egress_rates.insert(fed_id, rate);
+    }
+
+    pub(crate) fn set_default_egress_rate(&mut self, rate: EgressRate) {
+        self.default_egress_rate = Some(rate);
+    }
+
+    pub(crate) fn set_excess_egress_policy(&mut self, excess_egress_policy: ExcessEgressPolicy) {
+        self.excess_egress_policy = excess_egress_policy;
+    }
+
+    /**
+     * Account for a data message of the given size in bytes, header included,
+     * to be relayed to the given federate. Return how long to wait before
+     * writing it, or None if it is to be dropped because the federate exceeds
+     * its egress rate.
+     */
+    pub(crate) fn shape_egress(&self, fed_id: u16, bytes: usize) -> Option<Duration> {
+        let rate = match self.egress_rate(fed_id) {
+            Some(rate) => rate,
+            None => return Some(Duration::ZERO),
+        };
+        let policy = self.excess_egress_policy;
+        let fed = &self.enclaves[usize::from(fed_id)];
+        let label = fed.label();
+        let mut shaper = fed.egress_shaper();
+        let shaper = shaper.get_or_insert_with(|| EgressShaper::new(rate));
+        match policy {
+            ExcessEgressPolicy::Queue => Some(shaper.reserve(bytes)),
+            ExcessEgressPolicy::Drop => {
+                if shaper.admit(bytes) {
+                    return Some(Duration::ZERO);
+                }
+                if shaper.flag() {
+                    warn!(
+                        "RTI: WARNING: Messages to federate {} exceed its egress rate of {} bytes per second. Dropping them.",
+                        label,
+                        rate.bytes_per_second()
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    /**
+     * Account for a NET or LTC message with the given tag that has just
+     * arrived from the given federate. Return false if the message is held
+     * back because the federate exceeds the rate of control messages, in which
+     * case it is handled later by the thread serving the federate.
+     */
+    pub(crate) fn admit_control_message(&self, fed_id: u16, msg_type: MsgType, tag: &Tag) -> bool {
+        let max_control_rate = self.federate_max_control_rate(fed_id);
+        if max_control_rate == 0 {
+            return true;
+        }
+        let policy = self.excess_control_policy;
+        let fed = &self.enclaves[usize::from(fed_id)];
+        let label = fed.label();
+        let mut limiter = fed.control_rate_limiter();
+        let limiter = limiter.get_or_insert_with(|| ControlRateLimiter::new(max_control_rate));
+        if limiter.admit() {
+            return true;
+        }
+        match policy {
+            ExcessControlPolicy::Coalesce => {
+                limiter.coalesce(msg_type, tag.clone());
+                false
+            }
+            ExcessControlPolicy::Flag => {
+                if limiter.flag() {
+                    warn!(
+                        "RTI: WARNING: Federate {} exceeds the rate of {} NET and LTC messages per second.",
+                        label, max_control_rate
+                    );
+                }
+                true
+            }
+        }
+    }
+
+    pub(crate) fn lock_stats(&self) -> &Option<Mutex<LockStats>> {
+        &self.lock_stats
+    }
+
+    pub(crate) fn wait_stats(&self) -> MutexGuard<'_, WaitStats> {
+        self.wait_stats.lock().unwrap()
+    }
+
+    /**
+     * Start collecting statistics of the contention on the lock of this state.
+     */
+    pub(crate) fn enable_lock_stats(&mut self) {
+        self.lock_stats = Some(Mutex::new(LockStats::new()));
+    }
+
+    /**
+     * Record an acquisition of the lock of this state. See lock_rti.
+     */
+    pub(crate) fn record_lock_wait(
+        &self,
+        location: &'static Location<'static>,
+        wait: Option<Duration>,
+    ) {
+        if let Some(lock_stats) = &self.lock_stats {
+            lock_stats.lock().unwrap().record(location, wait);
+        }
+    }
+
+    pub(crate) fn tracing_enabled(&self) -> bool {
+        self.tracing_enabled
+    }
+
+    pub(crate) fn set_tracing_enabled(&mut self, tracing_enabled: bool) {
+        self.tracing_enabled = tracing_enabled;
+    }
+
+    pub(crate) fn trace_file(&self) -> &str {
+        &self.trace_file
+    }
+
+    pub(crate) fn set_trace_file(&mut self, trace_file: String) {
+        self.trace_file = trace_file;
+    }
+
+    pub(crate) fn set_tracer(&mut self, tracer: Arc<Tracer>) {
+        self.tracer = Some(tracer);
+    }
+
+    /**
+     * Set the header of the trace, if any, once the start time is known.
+     */
+    pub(crate) fn set_trace_start_time(&self, start_time: Instant) {
+        if let Some(tracer) = &self.tracer {
+            let names = self.enclaves.iter().map(|fed| fed.name().clone()).collect();
+            tracer.set_header(TraceHeader::new(start_time, names));
+        }
+    }
+
+    /**
+     * Record in the trace, if any, a message that the RTI sends to the given
+     * federate, with its tag if it has one.
+     */
+    pub(crate) fn tracepoint_rti_to_federate(
+        &self,
+        event: TraceEvent,
+        fed_id: u16,
+        tag: Option<&Tag>,
+    ) {
+        if let Some(tracer) = &self.tracer {
+            tracer.tracepoint(event, -1, i32::from(fed_id), tag);
+        }
+    }
+
+    /**
+     * Record in the trace, if any, a message that the RTI receives from the
+     * given federate, with its tag if it has one.
+     */
+    pub(crate) fn tracepoint_rti_from_federate(
+        &self,
+        event: TraceEvent,
+        fed_id: u16,
+        tag: Option<&Tag>,
+    ) {
+        if let Some(tracer) = &self.tracer {
+            tracer.tracepoint(event, i32::from(fed_id), -1, tag);
+        }
+    }
+
+    pub(crate) fn history_length(&self) -> usize {
+        self.history_length
+    }
+
+    pub(crate) fn set_history_length(&mut self, history_length: usize) {
+        self.history_length = history_length;
+    }
+
+    pub(crate) fn in_transit_budget(&self) -> &Option<Arc<MemoryBudget>> {
+        &self.in_transit_budget
+    }
+
+    pub(crate) fn set_in_transit_budget(&mut self, in_transit_budget: Option<Arc<MemoryBudget>>) {
+        self.in_transit_budget = in_transit_budget;
+    }
+
+    pub(crate) fn trace_budget(&self) -> &Option<Arc<MemoryBudget>> {
+        &self.trace_budget
+    }
+
+    pub(crate) fn set_trace_budget(&mut self, trace_budget: Option<Arc<MemoryBudget>>) {
+        self.trace_budget = trace_budget;
+    }
+
+    pub(crate) fn set_history_budget(&mut self, history_budget: Option<Arc<MemoryBudget>>) {
+        self.history_budget = history_budget;
+    }
+
+    /**
+     * Return the memory budgets specified by the user.
+     */
+    pub(crate) fn memory_budgets(&self) -> Vec<Arc<MemoryBudget>> {
+        [
+            &self.in_transit_budget,
+            &self.trace_budget,
+            &self.history_budget,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+    }
+
+    /**
+     * Charge one history entry to the history budget, if any, evicting the
+     * oldest entry of the longest history while it is exhausted. Return false
+     * if the entry does not fit even in an empty history.
+     */
+    fn reserve_history_entry(&self) -> bool {
+        let Some(budget) = &self.history_budget else {
+            return true;
+        };
+        while !budget.try_reserve(HISTORY_ENTRY_SIZE) {
+            let mut longest: Option<(usize, usize)> = None;
+            for (index, fed) in self.enclaves.iter().enumerate() {
+                let length = fed.history().entries().len();
+                if length > longest.map_or(0, |(_, longest_length)| longest_length) {
+                    longest = Some((index, length));
+                }
+            }
+            let Some((index, _)) = longest else {
+                // The entry is dropped.
+                budget.record_eviction(1);
+                return false;
+            };
+            self.enclaves[index].history().evict_oldest();
+            budget.release(HISTORY_ENTRY_SIZE);
+            budget.record_eviction(1);
+        }
+        true
+    }
+
+    pub(crate) fn set_event_stream(&mut self, event_stream: EventStream) {
+        self.event_stream = Some(event_stream);
+    }
+
+    /**
+     * Read the physical time of the events in the event stream, if any, from
+     * the given clock.
+     */
+    pub(crate) fn set_event_clock(&mut self, clock: Arc<PhysicalClock>) {
+        if let Some(event_stream) = self.event_stream.as_mut() {
+            event_stream.set_clock(clock);
+        }
+    }
+
+    /**
+     * Return true if the RTI tolerates the given condition, which has been
+     * reported already. In strict mode, the RTI does not, and shuts down
+     * because of it instead.
+     */
+    pub(crate) fn tolerate(&self, condition: &str) -> bool {
+        if !self.strict {
+            return true;
+        }
+        error!("RTI: Strict mode: {}. Aborting.", condition);
+        self.shutdown
+            .request(&format!("{} in strict mode", condition));
+        false
+    }
+
+    /**
+     * Write a scheduling event to the event stream, if any, and add it to the
+     * history of the federate it concerns. See event_stream.rs for the meaning
+     * of the fields. The stream is closed if writing fails, e.g., because the
+     * analyzer has exited, so that the federation is not affected.
+     */
+    pub(crate) fn record_event(
+        &self,
+        event: &'static str,
+        fed_id: Option<u16>,
+        tag: Option<(&Tag, Instant)>,
+        reason: Option<&str>,
+    ) {
+        if let Some(fed_id) = fed_id {
+            let history_length = self.history_length;
+            let physical_time = self.physical_clock().now();
+            if history_length > 0
+                && usize::from(fed_id) < self.enclaves.len()
+                && self.reserve_history_entry()
+            {
+                let dropped = self.enclaves[usize::from(fed_id)].history().record(
+                    event,
+                    tag.map(|(tag, _)| tag),
+                    physical_time,
+                    history_length,
+                );
+                if let Some(budget) = &self.history_budget {
+                    budget.release(dropped * HISTORY_ENTRY_SIZE);
+                }
+            }
+        }
+        let Some(event_stream) = &self.event_stream else {
+            return;
+        };
+        let name = fed_id
+            .and_then(|fed_id| self.enclaves.get(usize::from(fed_id)))
+            .and_then(|fed| fed.name().as_deref());
+        let federate = fed_id.map(|fed_id| (fed_id, name));
+        if let Err(e) = event_stream.write_event(event, federate, tag, reason) {
+            warn!(
+                "RTI: WARNING: Failed to write to the event stream: {}. Closing it.",
+                e
+            );
+            event_stream.close();
+        }
+    }
+
+    pub(crate) fn set_clock_source(&mut self, clock_source: ClockSource) {
+        self.clock_source = clock_source;
+    }
+
+    /**
+     * Read physical time from the given virtual clock instead of the clock
+     * source. Unless the clock source is monotonic, steps and smears of the
+     * virtual clock are detected as those of the source would be. See
+     * VirtualClock.
+     */
+    pub fn set_virtual_clock(&mut self, virtual_clock: VirtualClock) {
+        self.virtual_clock = Some(virtual_clock);
+    }
+
+    pub(crate) fn set_time_resolution(&mut self, time_resolution: TimeResolution) {
+        self.time_resolution = time_resolution;
+    }
+
+    pub(crate) fn set_time_format(&mut self, time_format: TimeFormat) {
+        self.time_format = time_format;
+    }
+
+    pub(crate) fn set_log_timestamps(&mut self, log_timestamps: bool) {
+        self.log_timestamps = log_timestamps;
+    }
+
+    pub(crate) fn set_microstep_warning_threshold(&mut self, microstep_warning_threshold: u32) {
+        self.microstep_warning_threshold = microstep_warning_threshold;
+    }
+
+    pub(crate) fn set_breakpoint(&mut self, breakpoint: Option<Tag>) {
+        self.breakpoint = breakpoint;
+    }
+
+    /**
+     * Return true if a payload of the given size exceeds the configured limit.
+     */
+    pub(crate) fn exceeds_max_message_size(&self, size: usize) -> bool {
+        self.max_message_size > 0 && size > self.max_message_size
+    }
+
+    pub(crate) fn set_wire_dump_federates(&mut self, wire_dump_federates: Vec<String>) {
+        self.wire_dump_federates = wire_dump_federates;
+    }
+
+    pub(crate) fn set_wire_dump_dir(&mut self, wire_dump_dir: String) {
+        self.wire_dump_dir = wire_dump_dir;
+    }
+
+    pub(crate) fn set_decentralized_federates(&mut self, decentralized_federates: Vec<u16>) {
+        self.decentralized_federates = decentralized_federates;
+    }
+
+    pub(crate) fn set_transient_federates(&mut self, transient_federates: Vec<u16>) {
+        self.transient_federates = transient_federates;
+    }
+}
+
+impl Default for FederationRTI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/**
+ * The number of CPUs available to the RTI, which is the default number of I/O
+ * and scheduling threads.
+ */
+pub fn number_of_cpus() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
 }
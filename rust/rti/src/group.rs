@@ -0,0 +1,185 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Named groups of federates, such as sensors and compute nodes, whose
+ * policies override the ones of the RTI for their members.
+ *
+ * Federates are assigned to groups with --group or with the group attribute of
+ * the federation metadata, and each federate is in at most one group. The
+ * policies of a group are given with --group_policy. A policy that a group
+ * does not set is the one of the RTI.
+ */
+use std::fmt;
+
+use crate::tag::TimeResolution;
+use crate::trace::LogLevel;
+use crate::FailurePolicy;
+
+/**
+ * Policies of a group that override the ones of the RTI.
+ */
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct GroupPolicy {
+    // Like --max_control_rate, where 0 means no limit.
+    max_control_rate: Option<u32>,
+    // Like --lookahead, in nanoseconds.
+    lookahead: Option<i64>,
+    // Log level of the messages exchanged with the members and their grants.
+    log_level: Option<LogLevel>,
+    // Like --on_failure.
+    failure_policy: Option<FailurePolicy>,
+}
+
+impl GroupPolicy {
+    /**
+     * Parse a comma-separated list of max_control_rate=<n>, lookahead=<n> in
+     * units of the given time resolution, log_level=<level>, and
+     * on_failure=abort|degrade. Return a description of the first invalid
+     * entry, if any.
+     */
+    pub fn parse(text: &str, time_resolution: TimeResolution) -> Result<GroupPolicy, String> {
+        let mut policy = GroupPolicy::default();
+        for entry in text.split(',') {
+            let invalid = || format!("invalid policy '{}'", entry.trim());
+            let (key, value) = entry.trim().split_once('=').ok_or_else(invalid)?;
+            match key {
+                "max_control_rate" => {
+                    policy.max_control_rate = Some(value.parse::<u32>().map_err(|_| invalid())?)
+                }
+                "lookahead" => {
+                    let lookahead = value
+                        .parse::<i64>()
+                        .ok()
+                        .filter(|lookahead| *lookahead > 0)
+                        .ok_or_else(invalid)?;
+                    policy.lookahead = Some(
+                        time_resolution
+                            .to_nanoseconds(lookahead)
+                            .ok_or_else(|| format!("lookahead {} is too large", lookahead))?,
+                    );
+                }
+                "log_level" => {
+                    policy.log_level = Some(LogLevel::from_name(value).ok_or_else(invalid)?)
+                }
+                "on_failure" => {
+                    policy.failure_policy = Some(match value {
+                        "abort" => FailurePolicy::Abort,
+                        "degrade" => FailurePolicy::Degrade,
+                        _ => return Err(invalid()),
+                    })
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn max_control_rate(&self) -> Option<u32> {
+        self.max_control_rate
+    }
+
+    pub fn lookahead(&self) -> Option<i64> {
+        self.lookahead
+    }
+
+    pub fn log_level(&self) -> Option<LogLevel> {
+        self.log_level
+    }
+
+    pub fn failure_policy(&self) -> Option<FailurePolicy> {
+        self.failure_policy
+    }
+
+    /**
+     * Override the policies of this one with the ones that the given policy
+     * sets.
+     */
+    pub fn merge(&mut self, other: &GroupPolicy) {
+        self.max_control_rate = other.max_control_rate.or(self.max_control_rate);
+        self.lookahead = other.lookahead.or(self.lookahead);
+        self.log_level = other.log_level.or(self.log_level);
+        self.failure_policy = other.failure_policy.or(self.failure_policy);
+    }
+}
+
+impl fmt::Display for GroupPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries = Vec::new();
+        if let Some(max_control_rate) = self.max_control_rate {
+            entries.push(format!("max_control_rate={}", max_control_rate));
+        }
+        if let Some(lookahead) = self.lookahead {
+            entries.push(format!("lookahead={} ns", lookahead));
+        }
+        if let Some(log_level) = self.log_level {
+            entries.push(format!("log_level={:?}", log_level).to_lowercase());
+        }
+        if let Some(failure_policy) = self.failure_policy {
+            entries.push(format!("on_failure={:?}", failure_policy).to_lowercase());
+        }
+        if entries.is_empty() {
+            write!(f, "the policies of the RTI")
+        } else {
+            write!(f, "{}", entries.join(", "))
+        }
+    }
+}
+
+/**
+ * A named group of federates and its policies.
+ */
+#[derive(Clone, Debug)]
+pub struct FederateGroup {
+    name: String,
+    members: Vec<u16>,
+    policy: GroupPolicy,
+}
+
+impl FederateGroup {
+    pub fn new(name: &str) -> FederateGroup {
+        FederateGroup {
+            name: String::from(name),
+            members: Vec::new(),
+            policy: GroupPolicy::default(),
+        }
+    }
+
+    /**
+     * Return true if the given name can name a group on the command line and
+     * in the federation metadata.
+     */
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /**
+     * Return the IDs of the members in increasing order.
+     */
+    pub fn members(&self) -> &[u16] {
+        &self.members
+    }
+
+    pub fn policy(&self) -> &GroupPolicy {
+        &self.policy
+    }
+
+    pub fn policy_mut(&mut self) -> &mut GroupPolicy {
+        &mut self.policy
+    }
+
+    pub fn add_member(&mut self, fed_id: u16) {
+        if let Err(index) = self.members.binary_search(&fed_id) {
+            self.members.insert(index, fed_id);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Bounded in-memory history of the recent scheduling events of each
+ * federate, which the admin console shows even if tracing is off, and
+ * statistics on the provisional grants sent to it.
+ */
+use std::collections::VecDeque;
+use std::mem;
+
+use crate::enclave::Enclave;
+use crate::tag::{self, Instant, Tag};
+
+/**
+ * One scheduling event, with the same names as in the event stream: "net" or
+ * "ltc" for a message received from the federate, "tag" or "ptag" for a grant
+ * sent to it, and so on.
+ */
+pub struct HistoryEntry {
+    event: &'static str,
+    tag: Option<Tag>,
+    physical_time: Instant,
+}
+
+impl HistoryEntry {
+    /**
+     * Describe the entry with its tag relative to the start time and the
+     * physical time elapsed since the start time.
+     */
+    pub fn describe(&self, start_time: Instant) -> String {
+        let mut description = String::from(self.event);
+        if let Some(tag) = &self.tag {
+            description.push(' ');
+            description.push_str(&Enclave::relative_tag(tag, start_time));
+        }
+        description.push_str(&format!(
+            " at physical time {}",
+            tag::format_time(self.physical_time, start_time)
+        ));
+        description
+    }
+}
+
+/**
+ * Estimated size of one entry, which is charged to the history budget.
+ */
+pub const HISTORY_ENTRY_SIZE: usize = mem::size_of::<HistoryEntry>();
+
+/**
+ * The last events of one federate, oldest first.
+ */
+pub struct SchedulingHistory {
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl SchedulingHistory {
+    pub fn new() -> SchedulingHistory {
+        SchedulingHistory {
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &VecDeque<HistoryEntry> {
+        &self.entries
+    }
+
+    /**
+     * Append an event, dropping the oldest ones so that at most the given
+     * number of events is kept. Return the number of events dropped.
+     */
+    pub fn record(
+        &mut self,
+        event: &'static str,
+        tag: Option<&Tag>,
+        physical_time: Instant,
+        length: usize,
+    ) -> usize {
+        if length == 0 {
+            return 0;
+        }
+        let mut dropped = 0;
+        while self.entries.len() >= length {
+            self.entries.pop_front();
+            dropped += 1;
+        }
+        self.entries.push_back(HistoryEntry {
+            event,
+            tag: tag.cloned(),
+            physical_time,
+        });
+        dropped
+    }
+
+    /**
+     * Drop the oldest event, if any, and return true if there was one.
+     */
+    pub fn evict_oldest(&mut self) -> bool {
+        self.entries.pop_front().is_some()
+    }
+}
+
+/**
+ * Outcomes of the PTAGs sent to one federate, to evaluate whether provisional
+ * grants help the federation: a PTAG is converted if the next grant to the
+ * federate is a TAG of the same tag, and wasted if the next grant is of a
+ * later tag, so that the federate never completes the provisionally granted
+ * tag with a TAG for it. The last PTAG is pending until the next grant.
+ * The TAGs sent are counted as well.
+ */
+#[derive(Default)]
+pub struct ProvisionalGrantStats {
+    tags_sent: u64,
+    sent: u64,
+    converted: u64,
+    wasted: u64,
+    // PTAGs that were pending when the federate disconnected.
+    unresolved: u64,
+    pending: Option<Tag>,
+}
+
+impl ProvisionalGrantStats {
+    pub fn new() -> ProvisionalGrantStats {
+        ProvisionalGrantStats::default()
+    }
+
+    #[cfg(feature = "run_db")]
+    pub fn tags_sent(&self) -> u64 {
+        self.tags_sent
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    #[cfg(feature = "run_db")]
+    pub fn wasted(&self) -> u64 {
+        self.wasted
+    }
+
+    /**
+     * Return the number of PTAGs that are pending or were pending when the
+     * federate disconnected.
+     */
+    pub fn unresolved(&self) -> u64 {
+        self.unresolved + u64::from(self.pending.is_some())
+    }
+
+    /**
+     * Record a PTAG sent to the federate, which supersedes a pending one.
+     */
+    pub fn record_ptag(&mut self, tag: &Tag) {
+        if self.pending.is_some() {
+            self.wasted += 1;
+        }
+        self.sent += 1;
+        self.pending = Some(tag.clone());
+    }
+
+    /**
+     * Record a TAG sent to the federate, which converts or supersedes a
+     * pending PTAG.
+     */
+    pub fn record_tag(&mut self, tag: &Tag) {
+        self.tags_sent += 1;
+        match self.pending.take() {
+            Some(pending) if pending == *tag => self.converted += 1,
+            Some(_) => self.wasted += 1,
+            None => {}
+        }
+    }
+
+    /**
+     * Record that the federate has disconnected, which leaves a pending PTAG
+     * unresolved.
+     */
+    pub fn record_disconnection(&mut self) {
+        if self.pending.take().is_some() {
+            self.unresolved += 1;
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} PTAG(s) sent, {} converted to a TAG of the same tag, {} wasted, {} unresolved",
+            self.sent,
+            self.converted,
+            self.wasted,
+            self.unresolved()
+        )
+    }
+}
@@ -0,0 +1,105 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Extension point for applications that embed the RTI to observe the
+ * payloads of the messages that it forwards between federates.
+ */
+use crate::tag::Tag;
+
+/**
+ * A tagged message that the RTI forwards from one federate to another.
+ */
+pub struct ForwardedMessage {
+    source: u16,
+    destination: u16,
+    port: u16,
+    tag: Tag,
+    length: usize,
+}
+
+impl ForwardedMessage {
+    pub fn new(
+        source: u16,
+        destination: u16,
+        port: u16,
+        tag: Tag,
+        length: usize,
+    ) -> ForwardedMessage {
+        ForwardedMessage {
+            source,
+            destination,
+            port,
+            tag,
+            length,
+        }
+    }
+
+    pub fn source(&self) -> u16 {
+        self.source
+    }
+
+    pub fn destination(&self) -> u16 {
+        self.destination
+    }
+
+    /**
+     * The ID of the input port of the destination federate.
+     */
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /**
+     * The intended tag of the message, in absolute time.
+     */
+    pub fn tag(&self) -> Tag {
+        self.tag.clone()
+    }
+
+    /**
+     * The length of the payload in bytes.
+     */
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+/**
+ * An observer of the messages forwarded by the RTI, registered with
+ * FederationRTI::add_payload_inspector. The RTI forwards payloads in chunks
+ * without copying them, and an inspector sees the chunks in place, so an
+ * inspector must not block. Inspectors are called from the threads that
+ * handle the source federates, possibly concurrently.
+ */
+pub trait PayloadInspector: Send + Sync {
+    /**
+     * Return true to be shown the payload of the given message. By default,
+     * payloads are not inspected and only message_forwarded() is called.
+     */
+    fn wants_payload(&self, _message: &ForwardedMessage) -> bool {
+        false
+    }
+
+    /**
+     * Inspect a chunk of the payload of the given message, starting at the
+     * given offset in the payload, before it is forwarded. Called in order
+     * for every chunk of a message whose payload the inspector wants.
+     */
+    fn inspect_chunk(&self, _message: &ForwardedMessage, _offset: usize, _chunk: &[u8]) {}
+
+    /**
+     * Called once the whole message has been forwarded to its destination.
+     */
+    fn message_forwarded(&self, _message: &ForwardedMessage) {}
+
+    /**
+     * Return a line that describes the metrics collected by the inspector,
+     * which is printed in the run summary of the RTI, or None.
+     */
+    fn summary(&self) -> Option<String> {
+        None
+    }
+}
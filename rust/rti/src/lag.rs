@@ -0,0 +1,175 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Monitoring of the lag of logical time behind physical time, enabled
+ * with --max_lag, for realtime federations.
+ *
+ * The lag is the amount by which the physical time of the RTI is ahead of the
+ * earliest tag granted to the connected federates. Once it exceeds the
+ * maximum, the RTI warns with the lagging federate and the slowest federate
+ * upstream of it, i.e., the one with the earliest LTC, which is most likely
+ * what holds it back. The warning is repeated only after the lag has
+ * recovered.
+ */
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::PhysicalClock;
+use crate::constants::LAG_CHECK_PERIOD_MS;
+use crate::federate::federate_label;
+use crate::lock_stats::lock_rti;
+use crate::tag::{self, Instant, StartTime, Tag};
+use crate::trace::{info, warn};
+use crate::FedState;
+use crate::FederationRTI;
+
+/**
+ * Spawn a thread that checks the lag every LAG_CHECK_PERIOD_MS milliseconds
+ * until all federates have exited.
+ */
+pub fn start_lag_thread(
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    start_time: Arc<Mutex<StartTime>>,
+    clock: Arc<PhysicalClock>,
+    max_lag: i64,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut exceeded = false;
+        loop {
+            clock.sleep(Duration::from_millis(LAG_CHECK_PERIOD_MS));
+            let start_time_value;
+            {
+                let locked_start_time = start_time.lock().unwrap();
+                start_time_value = locked_start_time.start_time();
+            }
+            let mut locked_rti = lock_rti(&_f_rti);
+            if locked_rti.all_federates_exited() {
+                break;
+            }
+            let now = clock.now();
+            if start_time_value == tag::NEVER || now < start_time_value {
+                continue;
+            }
+            let Some((fed_id, granted)) = earliest_grant(&mut locked_rti, start_time_value) else {
+                continue;
+            };
+            let lag = now - granted.time();
+            locked_rti.record_lag(lag);
+            if lag <= max_lag {
+                if exceeded {
+                    info!(
+                        "RTI: ({},{}) The lag of logical time behind physical time is back within {} ns.",
+                        tag::format_time(granted.time(), start_time_value),
+                        granted.microstep(),
+                        max_lag
+                    );
+                    exceeded = false;
+                }
+                continue;
+            }
+            if exceeded {
+                continue;
+            }
+            exceeded = true;
+            locked_rti.count_lag_warning();
+            let slowest = slowest_upstream(&mut locked_rti, fed_id);
+            let culprit = if slowest == fed_id {
+                String::from("No federate upstream of it is slower")
+            } else {
+                let completed = locked_rti.enclaves()[usize::from(slowest)].e().completed();
+                format!(
+                    "The slowest federate upstream of it is federate {}, which has completed {}",
+                    federate_label(slowest),
+                    describe_tag(&completed, start_time_value)
+                )
+            };
+            let reason = format!(
+                "physical time is {} ns ahead of the tag granted to federate {}, beyond the maximum lag of {} ns. {}.",
+                lag,
+                federate_label(fed_id),
+                max_lag,
+                culprit
+            );
+            warn!(
+                "RTI: WARNING: ({},{}) Logical time lags behind: {}",
+                tag::format_time(granted.time(), start_time_value),
+                granted.microstep(),
+                reason
+            );
+            locked_rti.record_event(
+                "lag",
+                Some(fed_id),
+                Some((&granted, start_time_value)),
+                Some(&reason),
+            );
+        }
+    })
+}
+
+/**
+ * Return the connected federate that has been granted the earliest tag, with
+ * that tag. A federate that has not been granted a tag yet counts as granted
+ * the start tag. Federates with decentralized coordination are not granted
+ * tags and are skipped.
+ */
+fn earliest_grant(locked_rti: &mut FederationRTI, start_time: Instant) -> Option<(u16, Tag)> {
+    let start_tag = Tag::new(start_time, 0);
+    locked_rti
+        .enclaves()
+        .iter()
+        .map(|fed| fed.e())
+        .filter(|e| e.state() != FedState::NotConnected && !e.decentralized())
+        .map(|e| (e.id(), e.last_granted().max(start_tag.clone())))
+        .min_by(|(_, a), (_, b)| a.cmp(b))
+}
+
+/**
+ * Return the connected federate with the earliest LTC among the given
+ * federate and the federates upstream of it, directly or transitively. Of
+ * federates with the same LTC, the one furthest upstream is returned.
+ */
+fn slowest_upstream(locked_rti: &mut FederationRTI, fed_id: u16) -> u16 {
+    let enclaves = locked_rti.enclaves();
+    let mut visited = vec![false; enclaves.len()];
+    let mut queue = VecDeque::from([fed_id]);
+    visited[usize::from(fed_id)] = true;
+    let mut slowest = (fed_id, enclaves[usize::from(fed_id)].e().completed());
+    while let Some(id) = queue.pop_front() {
+        for &upstream_id in enclaves[usize::from(id)].e().upstream() {
+            let Ok(upstream_id) = u16::try_from(upstream_id) else {
+                continue;
+            };
+            let index = usize::from(upstream_id);
+            if index >= enclaves.len() || visited[index] {
+                continue;
+            }
+            visited[index] = true;
+            queue.push_back(upstream_id);
+            let e = enclaves[index].e();
+            if e.state() == FedState::NotConnected || e.decentralized() {
+                continue;
+            }
+            if e.completed() <= slowest.1 {
+                slowest = (upstream_id, e.completed());
+            }
+        }
+    }
+    slowest.0
+}
+
+fn describe_tag(tag: &Tag, start_time: Instant) -> String {
+    if *tag == Tag::never_tag() {
+        String::from("no tag")
+    } else {
+        format!(
+            "the tag ({},{})",
+            tag::format_time(tag.time(), start_time),
+            tag.microstep()
+        )
+    }
+}
@@ -0,0 +1,459 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Binary trace files in the LF trace format (.lft).
+ *
+ * The header has the layout that the C runtime writes and that trace_to_csv
+ * and the other LF trace tools read:
+ *
+ * - the start time (8 bytes, little endian);
+ * - the number of entries in the object table (4 bytes);
+ * - for each entry, a pointer (8 bytes), a trigger (8 bytes), and a
+ *   null-terminated description.
+ *
+ * The metadata of the RTI is stored in the object table so that the tools
+ * still accept the file. The first entry describes the RTI and carries the
+ * format version in its trigger, and each following entry describes the
+ * federate whose ID is its pointer with the federate's name. A file without
+ * the RTI entry, such as one written by the C RTI, is read as version 0
+ * without federate names.
+ *
+ * The header is followed by blocks of records, each of which is the number of
+ * records in the block (4 bytes) followed by the records. A record has the
+ * layout of trace_record_t of the C runtime on 64-bit platforms, including its
+ * padding (see TraceRecord).
+ */
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::tag::{Instant, Microstep, Tag};
+
+/**
+ * Version of the trace format written by this RTI. Readers accept files of
+ * this or an earlier version.
+ */
+pub const LFT_FORMAT_VERSION: u64 = 1;
+
+/**
+ * Description of the object table entry that identifies an RTI trace.
+ */
+const RTI_DESCRIPTION: &str = "RTI";
+
+/**
+ * Pointer of the object table entry that identifies an RTI trace. No federate
+ * entry uses it because federate IDs fit in 16 bits.
+ */
+const RTI_POINTER: u64 = u64::MAX;
+
+/**
+ * Metadata at the beginning of a binary trace file.
+ */
+#[derive(PartialEq, Debug)]
+pub struct TraceHeader {
+    version: u64,
+    start_time: Instant,
+    federate_names: Vec<Option<String>>,
+}
+
+impl TraceHeader {
+    /**
+     * Create the header of a trace in the current format version for the
+     * federates with the given names, indexed by federate ID.
+     */
+    pub fn new(start_time: Instant, federate_names: Vec<Option<String>>) -> TraceHeader {
+        TraceHeader {
+            version: LFT_FORMAT_VERSION,
+            start_time,
+            federate_names,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn start_time(&self) -> Instant {
+        self.start_time
+    }
+
+    pub fn number_of_federates(&self) -> usize {
+        self.federate_names.len()
+    }
+
+    pub fn federate_names(&self) -> &Vec<Option<String>> {
+        &self.federate_names
+    }
+
+    /**
+     * Write the header to the given trace file.
+     */
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let table_size = i32::try_from(self.federate_names.len() + 1)
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "too many federates"))?;
+        writer.write_all(&self.start_time.to_le_bytes())?;
+        writer.write_all(&table_size.to_le_bytes())?;
+        Self::write_entry(writer, RTI_POINTER, self.version, RTI_DESCRIPTION)?;
+        for (fed_id, name) in self.federate_names.iter().enumerate() {
+            let description = match name {
+                Some(name) => name.clone(),
+                None => format!("federate {}", fed_id),
+            };
+            Self::write_entry(writer, fed_id as u64, 0, &description)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Read a header from the given trace file. Fail if the file was written in
+     * a later format version than this RTI supports.
+     */
+    pub fn read(reader: &mut impl Read) -> io::Result<TraceHeader> {
+        let mut start_time = [0u8; 8];
+        reader.read_exact(&mut start_time)?;
+        let mut table_size = [0u8; 4];
+        reader.read_exact(&mut table_size)?;
+        let table_size = i32::from_le_bytes(table_size);
+        if table_size < 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid object table size {}", table_size),
+            ));
+        }
+        let mut entries = Vec::new();
+        for _ in 0..table_size {
+            entries.push(Self::read_entry(reader)?);
+        }
+
+        let mut header = TraceHeader {
+            version: 0,
+            start_time: Instant::from_le_bytes(start_time),
+            federate_names: Vec::new(),
+        };
+        match entries.first() {
+            Some((RTI_POINTER, version, description)) if description == RTI_DESCRIPTION => {
+                if *version > LFT_FORMAT_VERSION {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "trace format version {} is newer than the supported version {}",
+                            version, LFT_FORMAT_VERSION
+                        ),
+                    ));
+                }
+                header.version = *version;
+            }
+            // Not written by this RTI, so the table holds no federate names.
+            _ => return Ok(header),
+        }
+        for (fed_id, (pointer, _, description)) in entries.into_iter().skip(1).enumerate() {
+            let name = if pointer == fed_id as u64 && description != format!("federate {}", fed_id)
+            {
+                Some(description)
+            } else {
+                None
+            };
+            header.federate_names.push(name);
+        }
+        Ok(header)
+    }
+
+    fn write_entry(
+        writer: &mut impl Write,
+        pointer: u64,
+        trigger: u64,
+        description: &str,
+    ) -> io::Result<()> {
+        writer.write_all(&pointer.to_le_bytes())?;
+        writer.write_all(&trigger.to_le_bytes())?;
+        writer.write_all(description.as_bytes())?;
+        writer.write_all(&[0])
+    }
+
+    fn read_entry(reader: &mut impl Read) -> io::Result<(u64, u64, String)> {
+        let mut pointer = [0u8; 8];
+        reader.read_exact(&mut pointer)?;
+        let mut trigger = [0u8; 8];
+        reader.read_exact(&mut trigger)?;
+        let mut description = Vec::new();
+        loop {
+            let mut byte = [0u8; 1];
+            reader.read_exact(&mut byte)?;
+            if byte[0] == 0 {
+                break;
+            }
+            description.push(byte[0]);
+        }
+        Ok((
+            u64::from_le_bytes(pointer),
+            u64::from_le_bytes(trigger),
+            String::from_utf8_lossy(&description).into_owned(),
+        ))
+    }
+}
+
+/**
+ * Types of the events in a trace that concern the interactions of federates,
+ * with the values of trace_event_t in trace.h of the C runtime. The events of
+ * the RTI are recorded from its point of view, e.g., SendTag for a TAG that
+ * the RTI sends to a federate.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TraceEvent {
+    SendAck,
+    SendFailed,
+    SendTimestamp,
+    SendNet,
+    SendLtc,
+    SendStopReq,
+    SendStopReqRep,
+    SendStopGrn,
+    SendFedId,
+    SendPtag,
+    SendTag,
+    SendReject,
+    SendResign,
+    SendPortAbs,
+    SendCloseRq,
+    SendTaggedMsg,
+    SendP2pTaggedMsg,
+    SendMsg,
+    SendP2pMsg,
+    SendAdrAd,
+    SendAdrQr,
+    ReceiveAck,
+    ReceiveFailed,
+    ReceiveTimestamp,
+    ReceiveNet,
+    ReceiveLtc,
+    ReceiveStopReq,
+    ReceiveStopReqRep,
+    ReceiveStopGrn,
+    ReceiveFedId,
+    ReceivePtag,
+    ReceiveTag,
+    ReceiveReject,
+    ReceiveResign,
+    ReceivePortAbs,
+    ReceiveCloseRq,
+    ReceiveTaggedMsg,
+    ReceiveP2pTaggedMsg,
+    ReceiveMsg,
+    ReceiveP2pMsg,
+    ReceiveAdrAd,
+    ReceiveAdrQr,
+    ReceiveUnidentified,
+}
+
+/**
+ * The events in the order of trace_event_t, starting with send_ACK.
+ */
+const TRACE_EVENTS: [TraceEvent; 43] = [
+    TraceEvent::SendAck,
+    TraceEvent::SendFailed,
+    TraceEvent::SendTimestamp,
+    TraceEvent::SendNet,
+    TraceEvent::SendLtc,
+    TraceEvent::SendStopReq,
+    TraceEvent::SendStopReqRep,
+    TraceEvent::SendStopGrn,
+    TraceEvent::SendFedId,
+    TraceEvent::SendPtag,
+    TraceEvent::SendTag,
+    TraceEvent::SendReject,
+    TraceEvent::SendResign,
+    TraceEvent::SendPortAbs,
+    TraceEvent::SendCloseRq,
+    TraceEvent::SendTaggedMsg,
+    TraceEvent::SendP2pTaggedMsg,
+    TraceEvent::SendMsg,
+    TraceEvent::SendP2pMsg,
+    TraceEvent::SendAdrAd,
+    TraceEvent::SendAdrQr,
+    TraceEvent::ReceiveAck,
+    TraceEvent::ReceiveFailed,
+    TraceEvent::ReceiveTimestamp,
+    TraceEvent::ReceiveNet,
+    TraceEvent::ReceiveLtc,
+    TraceEvent::ReceiveStopReq,
+    TraceEvent::ReceiveStopReqRep,
+    TraceEvent::ReceiveStopGrn,
+    TraceEvent::ReceiveFedId,
+    TraceEvent::ReceivePtag,
+    TraceEvent::ReceiveTag,
+    TraceEvent::ReceiveReject,
+    TraceEvent::ReceiveResign,
+    TraceEvent::ReceivePortAbs,
+    TraceEvent::ReceiveCloseRq,
+    TraceEvent::ReceiveTaggedMsg,
+    TraceEvent::ReceiveP2pTaggedMsg,
+    TraceEvent::ReceiveMsg,
+    TraceEvent::ReceiveP2pMsg,
+    TraceEvent::ReceiveAdrAd,
+    TraceEvent::ReceiveAdrQr,
+    TraceEvent::ReceiveUnidentified,
+];
+
+/**
+ * Value of send_ACK in trace_event_t, which follows the events of reactions,
+ * workers, and the scheduler.
+ */
+const FIRST_FEDERATED_EVENT: i32 = 11;
+
+impl TraceEvent {
+    pub fn to_value(&self) -> i32 {
+        let index = TRACE_EVENTS.iter().position(|event| event == self).unwrap();
+        FIRST_FEDERATED_EVENT + index as i32
+    }
+
+    /**
+     * Return the event with the given value of trace_event_t, or None if it
+     * is not an event of the interactions of federates.
+     */
+    pub fn from_value(value: i32) -> Option<TraceEvent> {
+        let index = usize::try_from(value - FIRST_FEDERATED_EVENT).ok()?;
+        TRACE_EVENTS.get(index).copied()
+    }
+}
+
+/**
+ * Length of a record, which is the size of trace_record_t of the C runtime on
+ * 64-bit platforms.
+ */
+pub const TRACE_RECORD_LENGTH: usize = 64;
+
+/**
+ * One event in a trace. The RTI records the ID of the federate that sends a
+ * message to it as the source and the ID of the federate to which it sends a
+ * message as the destination, and -1 for itself.
+ */
+#[derive(PartialEq, Clone)]
+pub struct TraceRecord {
+    event: TraceEvent,
+    src_id: i32,
+    dst_id: i32,
+    tag: Tag,
+    physical_time: Instant,
+}
+
+impl TraceRecord {
+    pub fn new(
+        event: TraceEvent,
+        src_id: i32,
+        dst_id: i32,
+        tag: Tag,
+        physical_time: Instant,
+    ) -> TraceRecord {
+        TraceRecord {
+            event,
+            src_id,
+            dst_id,
+            tag,
+            physical_time,
+        }
+    }
+
+    pub fn event(&self) -> TraceEvent {
+        self.event
+    }
+
+    pub fn src_id(&self) -> i32 {
+        self.src_id
+    }
+
+    pub fn dst_id(&self) -> i32 {
+        self.dst_id
+    }
+
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    pub fn physical_time(&self) -> Instant {
+        self.physical_time
+    }
+
+    /**
+     * Write a block of records to the given trace file.
+     */
+    pub fn write_block(writer: &mut impl Write, records: &[TraceRecord]) -> io::Result<()> {
+        let count = i32::try_from(records.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "too many records"))?;
+        let mut buffer = Vec::with_capacity(4 + records.len() * TRACE_RECORD_LENGTH);
+        buffer.extend_from_slice(&count.to_le_bytes());
+        for record in records {
+            record.encode(&mut buffer);
+        }
+        writer.write_all(&buffer)
+    }
+
+    /**
+     * Read the next block of records from the given trace file, after its
+     * header. Return None at the end of the file.
+     */
+    pub fn read_block(reader: &mut impl Read) -> io::Result<Option<Vec<TraceRecord>>> {
+        let mut count = [0u8; 4];
+        match reader.read_exact(&mut count) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let count = i32::from_le_bytes(count);
+        if count < 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid number of records {}", count),
+            ));
+        }
+        let mut records = Vec::new();
+        for _ in 0..count {
+            let mut buffer = [0u8; TRACE_RECORD_LENGTH];
+            reader.read_exact(&mut buffer)?;
+            records.push(Self::decode(&buffer)?);
+        }
+        Ok(Some(records))
+    }
+
+    /**
+     * Append the record with the layout of trace_record_t: the event type
+     * (4 bytes) and padding (4), the pointer (8), the source and destination
+     * IDs (4 each), the logical time (8), the microstep (4) and padding (4),
+     * the physical time (8), the trigger (8), and the extra delay (8). The
+     * pointer, the trigger, and the extra delay are not used by the RTI.
+     */
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.event.to_value().to_le_bytes());
+        buffer.extend_from_slice(&[0; 4]);
+        buffer.extend_from_slice(&0u64.to_le_bytes());
+        buffer.extend_from_slice(&self.src_id.to_le_bytes());
+        buffer.extend_from_slice(&self.dst_id.to_le_bytes());
+        buffer.extend_from_slice(&self.tag.time().to_le_bytes());
+        buffer.extend_from_slice(&self.tag.microstep().to_le_bytes());
+        buffer.extend_from_slice(&[0; 4]);
+        buffer.extend_from_slice(&self.physical_time.to_le_bytes());
+        buffer.extend_from_slice(&0u64.to_le_bytes());
+        buffer.extend_from_slice(&0i64.to_le_bytes());
+    }
+
+    fn decode(buffer: &[u8; TRACE_RECORD_LENGTH]) -> io::Result<TraceRecord> {
+        let i32_at =
+            |offset: usize| i32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap());
+        let i64_at =
+            |offset: usize| i64::from_le_bytes(buffer[offset..offset + 8].try_into().unwrap());
+        let event = TraceEvent::from_value(i32_at(0)).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported event type {}", i32_at(0)),
+            )
+        })?;
+        let microstep = Microstep::from_le_bytes(buffer[32..36].try_into().unwrap());
+        Ok(TraceRecord {
+            event,
+            src_id: i32_at(16),
+            dst_id: i32_at(20),
+            tag: Tag::new(i64_at(24), microstep),
+            physical_time: i64_at(40),
+        })
+    }
+}
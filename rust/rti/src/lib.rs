@@ -6,27 +6,126 @@
  * License in [BSD 2-clause](..)
  * @brief ..
  */
+/**
+ * Print a line to the sink selected with --log_sink or --log_file, by default
+ * the standard output. This replaces the println! of the standard library in the whole
+ * crate so that all output of the RTI goes to the same sink.
+ */
+macro_rules! println {
+    () => {
+        $crate::log_sink::log_line(None, format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::log_sink::log_line(None, format_args!($($arg)*))
+    };
+}
+
+mod admin;
+mod affinity;
+mod args;
+#[cfg(feature = "auth")]
+mod auth;
+mod builder;
+mod clock;
+mod clock_sync;
+mod connection;
 mod constants;
+mod egress;
 mod enclave;
+mod encryption;
+mod error;
+mod event_stream;
+mod fault;
 mod federate;
+mod federation_metadata;
 mod federation_rti;
+mod group;
+mod history;
+mod inspector;
+mod lag;
+mod lft;
+mod lock_stats;
+mod log_sink;
+mod memory;
 mod message_record {
     pub mod message_record;
     pub mod rti_pqueue_support;
 }
+mod multiplex;
 mod net_common;
 mod net_util;
+mod notifier;
+mod outgoing;
+mod parent;
+mod partition;
+mod progress;
+mod rate_limit;
+mod resumption;
+#[cfg(feature = "run_db")]
+mod run_db;
 mod server;
+mod shutdown;
+mod silence;
+mod snapshot;
+mod status;
 mod tag;
+mod tls;
+mod topology;
+mod trace;
+mod tracer;
+mod transport;
+mod visited;
+mod wait_stats;
+mod wire_dump;
 
-use std::error::Error;
+use std::sync::Arc;
 
-use crate::constants::*;
 use crate::enclave::*;
 use crate::federate::*;
-use crate::federation_rti::*;
+use crate::memory::MemoryBudget;
+use crate::message_record::message_record::InTransitMessageRecordQueue;
+use crate::partition::Partitions;
+
+// The public API follows semantic versioning: the items re-exported here with
+// their public methods, the public functions below, and the enums defined in
+// this file only change incompatibly with the major version, or the minor
+// version before 1.0. An item to be removed or changed is first marked
+// #[deprecated] with its replacement for at least one minor release. The
+// modules are private, the methods that only the RTI itself calls are
+// pub(crate), and the items marked #[doc(hidden)] are exposed for the tests
+// of this crate only and may change in any release. tests/api.rs pins the
+// stable API.
+pub use args::process_args;
+pub use builder::RtiBuilder;
+pub use clock::{PhysicalClock, VirtualClock};
+#[cfg(feature = "encryption")]
+pub use encryption::decrypt;
+pub use encryption::OutputKey;
+pub use error::RtiError;
+pub use federation_rti::FederationRTI;
+pub use group::{FederateGroup, GroupPolicy};
+pub use inspector::{ForwardedMessage, PayloadInspector};
+pub use lft::{TraceEvent, TraceHeader, TraceRecord, LFT_FORMAT_VERSION};
+#[cfg(feature = "run_db")]
+pub use run_db::compare_runs;
+pub use server::Server;
+pub use shutdown::{install_signal_handlers, ShutdownHandle};
+pub use snapshot::{
+    install_snapshot_signal_handler, FederateSnapshot, Snapshot, SnapshotTrigger,
+    SNAPSHOT_SCHEMA_VERSION,
+};
+pub use tag::{Instant, Interval, Microstep, Tag};
+pub use topology::Topology;
+pub use trace::{log_message, LogLevel};
+pub use transport::Transport;
 
-use server::Server;
+#[doc(hidden)]
+pub use connection::{Connection, ConnectionState};
+#[doc(hidden)]
+pub use transport::MockStream;
+
+#[doc(hidden)]
+pub use net_util::{NetUtil, ReadError, ReadErrorKind};
 
 #[derive(PartialEq, PartialOrd, Clone)]
 pub enum ClockSyncStat {
@@ -45,167 +144,65 @@ impl ClockSyncStat {
     }
 }
 
-pub fn process_args(rti: &mut FederationRTI, argv: &[String]) -> Result<(), &'static str> {
-    let mut idx = 1;
-    let argc = argv.len();
-    while idx < argc {
-        let arg = argv[idx].as_str();
-        // println!("arg = {}", arg); // TODO: Remove this debugging code
-        if arg == "-i" || arg == "--id" {
-            if argc < idx + 2 {
-                println!("--id needs a string argument.");
-                usage(argc, argv);
-                return Err("Fail to handle id option");
-            }
-            idx += 1;
-            // println!("idx = {}", idx); // TODO: Remove this debugging code
-            println!("RTI: Federation ID: {}", arg);
-            rti.set_federation_id(argv[idx].clone());
-        } else if arg == "-n" || arg == "--number_of_federates" {
-            if argc < idx + 2 {
-                println!("--number_of_federates needs an integer argument.");
-                usage(argc, argv);
-                return Err("Fail to handle number_of_federates option");
-            }
-            idx += 1;
-            let num_federates: i64;
-            match argv[idx].parse::<i64>() {
-                Ok(parsed_value) => {
-                    if parsed_value == 0 || parsed_value == i64::MAX || parsed_value == i64::MIN {
-                        println!("--number_of_federates needs a valid positive integer argument.");
-                        usage(argc, argv);
-                        return Err("Fail to handle number_of_federates option");
-                    }
-                    num_federates = parsed_value;
-                }
-                Err(_e) => {
-                    return Err("Fail to parse a string to i64");
-                }
-            };
-            rti.set_number_of_enclaves(num_federates.try_into().unwrap()); // FIXME: panic if the converted value doesn't fit
-            println!("RTI: Number of federates: {}", rti.number_of_enclaves());
-        } else if arg == "-p" || arg == "--port" {
-            if argc < idx + 2 {
-                println!(
-                    "--port needs a short unsigned integer argument ( > 0 and < {}).",
-                    u16::MAX
-                );
-                usage(argc, argv);
-                return Err("Fail to handle port option");
-            }
-            idx += 1;
-            let rti_port: u16;
-            match argv[idx].parse::<u16>() {
-                Ok(parsed_value) => {
-                    if parsed_value <= 0 || parsed_value >= u16::MAX {
-                        println!(
-                            "--port needs a short unsigned integer argument ( > 0 and < {}).",
-                            u16::MAX
-                        );
-                        usage(argc, argv);
-                        return Err("Fail to handle number_of_federates option");
-                    }
-                    rti_port = parsed_value;
-                }
-                Err(_e) => {
-                    return Err("Fail to parse a string to u16");
-                }
-            }
-            rti.set_port(rti_port.try_into().unwrap());
-        } else if arg == "-c" || arg == "--clock_sync" {
-            if argc < idx + 2 {
-                println!("--clock-sync needs off|init|on.");
-                usage(argc, argv);
-                return Err("Fail to handle clock_sync option");
-            }
-            idx += 1;
-            // TODO: idx += process_clock_sync_args();
-        } else if arg == " " {
-            // Tolerate spaces
-            continue;
-        } else {
-            println!("Unrecognized command-line argument: {}", arg);
-            usage(argc, argv);
-            return Err("Invalid argument");
-        }
-        idx += 1;
-    }
-    if rti.number_of_enclaves() == 0 {
-        println!("--number_of_federates needs a valid positive integer argument.");
-        usage(argc, argv);
-        return Err("Invalid number of enclaves");
-    }
-    Ok(())
+/**
+ * What the RTI does when a federate fails, i.e., when it sends a
+ * MsgType::Failed message or its connection closes without resigning.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum FailurePolicy {
+    /// Stop the whole federation as soon as possible.
+    Abort,
+    /// Treat the failed federate as resigned and let the others continue.
+    Degrade,
 }
 
-fn usage(argc: usize, argv: &[String]) {
-    println!("\nCommand-line arguments: ");
-    println!("  -i, --id <n>");
-    println!("   The ID of the federation that this RTI will control.");
-    println!("  -n, --number_of_federates <n>");
-    println!("   The number of federates in the federation that this RTI will control.");
-    println!("  -p, --port <n>");
-    println!("   The port number to use for the RTI. Must be larger than 0 and smaller than {}. Default is {}.", u16::MAX, STARTING_PORT);
-    println!("  -c, --clock_sync [off|init|on] [period <n>] [exchanges-per-interval <n>]");
-    println!("   The status of clock synchronization for this federate.");
-    println!("       - off: Clock synchronization is off.");
-    println!("       - init (default): Clock synchronization is done only during startup.");
-    println!(
-        "       - on: Clock synchronization is done both at startup and during the execution."
-    );
-    println!("   Relevant parameters that can be set: ");
-    println!("       - period <n>(in nanoseconds): Controls how often a clock synchronization attempt is made");
-    println!("          (period in nanoseconds, default is 5 msec). Only applies to 'on'.");
-    println!("       - exchanges-per-interval <n>: Controls the number of messages that are exchanged for each");
-    println!("          clock sync attempt (default is 10). Applies to 'init' and 'on'.");
-
-    println!("Command given:");
-    let mut idx = 0;
-    while idx < argc {
-        println!("{} ", argv[idx]);
-        idx += 1;
-    }
+/**
+ * What the RTI does when a federate sends a NET earlier than the start time,
+ * which federates should never do.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum EarlyNetPolicy {
+    /// Warn and use the start tag as the NET instead.
+    Clamp,
+    /// Reject the federate and handle it as a failed federate.
+    Reject,
 }
 
 pub fn initialize_federates(rti: &mut FederationRTI) {
     let mut i: u16 = 0;
     while i32::from(i) < rti.number_of_enclaves() {
         let mut federate = Federate::new();
-        initialize_federate(&mut federate, i);
+        initialize_federate(&mut federate, i, rti.in_transit_budget());
+        if rti.decentralized_federates().contains(&i) {
+            federate.enclave().set_decentralized(true);
+        }
+        if rti.transient_federates().contains(&i) {
+            federate.enclave().set_transient(true);
+        }
         let enclaves: &mut Vec<Federate> = rti.enclaves();
         enclaves.push(federate);
         i += 1;
     }
+    let number_of_federates = rti.enclaves().len();
+    rti.set_partitions(Partitions::new(number_of_federates));
 }
 
-fn initialize_federate(fed: &mut Federate, id: u16) {
+fn initialize_federate(fed: &mut Federate, id: u16, in_transit_budget: &Option<Arc<MemoryBudget>>) {
     let enclave = fed.enclave();
     enclave.initialize_enclave(id);
-    // TODO: fed.set_in_transit_message_tags();
+    fed.set_in_transit_message_tags(InTransitMessageRecordQueue::with_budget(
+        in_transit_budget.clone(),
+    ));
     // TODO: fed.set_server_ip_addr();
 }
 
-pub fn start_rti_server(_f_rti: &mut FederationRTI) -> Result<Server, Box<dyn Error>> {
+pub fn start_rti_server(_f_rti: &mut FederationRTI) -> Result<Server, RtiError> {
     // TODO: _lf_initialize_clock();
     Ok(Server::create_server(
         _f_rti.user_specified_port().to_string(),
     ))
 }
 
-/**
- * Process command-line arguments related to clock synchronization. Will return
- * the last read position of argv if all related arguments are parsed or an
- * invalid argument is read.
- *
- * @param argc: Number of arguments in the list
- * @param argv: The list of arguments as a string
- * @return Current position (head) of argv;
- */
-// TODO: implement this function
-// fn process_clock_sync_args(_argc: i32, _argv: &[String]) -> i32 {
-//     0
-// }
-
 /**
  * Initialize the _RTI instance.
  */
@@ -0,0 +1,124 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Statistics of the contention on the lock of the RTI state, by the
+ * call site that acquires it.
+ */
+use std::collections::HashMap;
+use std::panic::Location;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use std::time::{Duration, Instant};
+
+use crate::FederationRTI;
+
+/**
+ * Number of call sites listed in the report, by decreasing total wait.
+ */
+const REPORTED_CALL_SITES: usize = 10;
+
+#[derive(Default)]
+struct CallSiteStats {
+    acquisitions: u64,
+    contended: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+#[derive(Default)]
+pub struct LockStats {
+    call_sites: HashMap<&'static Location<'static>, CallSiteStats>,
+}
+
+impl LockStats {
+    pub fn new() -> LockStats {
+        LockStats::default()
+    }
+
+    /**
+     * Record an acquisition of the lock at the given call site, which waited
+     * for the given time if the lock was held by another thread.
+     */
+    pub fn record(&mut self, location: &'static Location<'static>, wait: Option<Duration>) {
+        let stats = self.call_sites.entry(location).or_default();
+        stats.acquisitions += 1;
+        if let Some(wait) = wait {
+            stats.contended += 1;
+            stats.total_wait += wait;
+            stats.max_wait = stats.max_wait.max(wait);
+        }
+    }
+
+    /**
+     * Return one line for each of the call sites that waited the longest in
+     * total.
+     */
+    pub fn report(&self) -> Vec<String> {
+        let mut call_sites: Vec<_> = self
+            .call_sites
+            .iter()
+            .filter(|(_, stats)| stats.contended > 0)
+            .collect();
+        call_sites.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total_wait));
+        call_sites
+            .into_iter()
+            .take(REPORTED_CALL_SITES)
+            .map(|(location, stats)| {
+                format!(
+                    "{}:{}: {} acquisitions, {} contended, {} us waited in total, {} us at most",
+                    location.file(),
+                    location.line(),
+                    stats.acquisitions,
+                    stats.contended,
+                    stats.total_wait.as_micros(),
+                    stats.max_wait.as_micros()
+                )
+            })
+            .collect()
+    }
+}
+
+/**
+ * Acquire the lock of the RTI state exclusively and, if lock statistics are
+ * enabled, record how long the caller waited for it.
+ */
+#[track_caller]
+pub fn lock_rti(rti: &RwLock<FederationRTI>) -> RwLockWriteGuard<'_, FederationRTI> {
+    let location = Location::caller();
+    let (locked_rti, wait) = match rti.try_write() {
+        Ok(locked_rti) => (locked_rti, None),
+        Err(TryLockError::WouldBlock) => {
+            let start = Instant::now();
+            let locked_rti = rti.write().unwrap();
+            (locked_rti, Some(start.elapsed()))
+        }
+        Err(TryLockError::Poisoned(_)) => (rti.write().unwrap(), None),
+    };
+    locked_rti.record_lock_wait(location, wait);
+    locked_rti
+}
+
+/**
+ * Acquire the lock of the RTI state shared with the other threads that only
+ * read the state guarded by it, as the threads deciding grants do, and record
+ * the wait like lock_rti. The state that such threads change is guarded by
+ * locks of its own, e.g., the tags of each federate by the lock of the
+ * federate.
+ */
+#[track_caller]
+pub fn read_rti(rti: &RwLock<FederationRTI>) -> RwLockReadGuard<'_, FederationRTI> {
+    let location = Location::caller();
+    let (locked_rti, wait) = match rti.try_read() {
+        Ok(locked_rti) => (locked_rti, None),
+        Err(TryLockError::WouldBlock) => {
+            let start = Instant::now();
+            let locked_rti = rti.read().unwrap();
+            (locked_rti, Some(start.elapsed()))
+        }
+        Err(TryLockError::Poisoned(_)) => (rti.read().unwrap(), None),
+    };
+    locked_rti.record_lock_wait(location, wait);
+    locked_rti
+}
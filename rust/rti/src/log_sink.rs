@@ -0,0 +1,302 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Destination of the output of the RTI: the standard output, a file,
+ * the local syslog daemon, or the systemd journal.
+ *
+ * Every line that the RTI prints goes through log_line(), which println! is
+ * redefined to call in lib.rs and which the leveled macros of trace.rs call,
+ * so that RTIs managed by system administrators integrate with the standard
+ * log aggregation without wrapper scripts. Lines are sent to syslog in the format of RFC 3164 without a timestamp, which the
+ * daemon adds, and to journald with its native protocol. If a line cannot be
+ * sent, e.g., because the daemon has been restarted or does not keep up, it
+ * is printed to the standard output instead.
+ */
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, LineWriter, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::encryption::{self, OutputKey};
+use crate::tag;
+use crate::trace::{info, warn};
+
+/**
+ * Identifier of the RTI in syslog and in the journal.
+ */
+const IDENTIFIER: &str = "rti";
+
+/**
+ * The syslog facility of daemons (LOG_DAEMON).
+ */
+const FACILITY_DAEMON: u8 = 3;
+
+/**
+ * Where the RTI prints.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum LogSink {
+    Stdout,
+    Syslog,
+    Journald,
+}
+
+impl LogSink {
+    pub fn from_name(name: &str) -> Option<LogSink> {
+        match name {
+            "stdout" => Some(LogSink::Stdout),
+            "syslog" => Some(LogSink::Syslog),
+            "journald" => Some(LogSink::Journald),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogSink::Stdout => "stdout",
+            LogSink::Syslog => "syslog",
+            LogSink::Journald => "journald",
+        }
+    }
+
+    /**
+     * Return the path of the socket on which the daemon receives lines, or
+     * None for the standard output.
+     */
+    pub fn default_socket_path(self) -> Option<&'static str> {
+        match self {
+            LogSink::Stdout => None,
+            LogSink::Syslog => Some("/dev/log"),
+            LogSink::Journald => Some("/run/systemd/journal/socket"),
+        }
+    }
+
+    /**
+     * Return true if the sink can be used on this platform.
+     */
+    pub fn is_available(self) -> bool {
+        self == LogSink::Stdout || cfg!(unix)
+    }
+}
+
+/**
+ * Severity of a line, with the numbers of syslog, which journald shares.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Priority {
+    Error = 3,
+    Warning = 4,
+    Info = 6,
+    Debug = 7,
+}
+
+impl Priority {
+    /**
+     * The severity of a line that the RTI prints. The RTI marks warnings and
+     * errors in the text, as in "RTI: WARNING: ...".
+     */
+    fn of_line(line: &str) -> Priority {
+        if line.contains("ERROR") {
+            Priority::Error
+        } else if line.contains("WARNING") {
+            Priority::Warning
+        } else {
+            Priority::Info
+        }
+    }
+}
+
+#[cfg(unix)]
+struct Daemon {
+    sink: LogSink,
+    socket: UnixDatagram,
+}
+
+#[cfg(not(unix))]
+struct Daemon {
+    sink: LogSink,
+}
+
+static DAEMON: Mutex<Option<Daemon>> = Mutex::new(None);
+
+static FILE: Mutex<Option<LineWriter<Box<dyn Write + Send>>>> = Mutex::new(None);
+
+static TIMESTAMPS: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Send the following lines to the given sink for the whole process, through
+ * the socket at the given path or the default one of the sink. If the socket
+ * cannot be reached, warn and keep printing to the standard output.
+ */
+pub fn configure(sink: LogSink, socket_path: Option<&str>) {
+    let Some(path) = socket_path.or(sink.default_socket_path()) else {
+        *DAEMON.lock().unwrap() = None;
+        return;
+    };
+    match connect(sink, path) {
+        Ok(daemon) => {
+            info!("RTI: Logging to {} through {}.", sink.name(), path);
+            *DAEMON.lock().unwrap() = Some(daemon);
+        }
+        Err(e) => warn!(
+            "RTI: WARNING: Failed to connect to {} through {}: {}. Logging to the standard output.",
+            sink.name(),
+            path,
+            e
+        ),
+    }
+}
+
+/**
+ * Append the following lines to the file at the given path for the whole
+ * process instead of printing them to the standard output. If a key is given,
+ * the file is truncated and encrypted under it instead. If the file cannot be
+ * opened, warn and keep printing to the standard output.
+ */
+pub fn configure_file(path: &str, key: Option<&OutputKey>) {
+    let file = match key {
+        Some(key) => encryption::create(path, Some(key)),
+        None => OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|file| encryption::wrap(file, None)),
+    };
+    match file {
+        Ok(file) => {
+            *FILE.lock().unwrap() = Some(LineWriter::new(file));
+            info!("RTI: Logging to {}.", path);
+        }
+        Err(e) => warn!(
+            "RTI: WARNING: Failed to open the log file {}: {}. Logging to the standard output.",
+            path, e
+        ),
+    }
+}
+
+/**
+ * Close the log file, if any, so that an encrypted one is complete, and print
+ * the following lines to the standard output.
+ */
+pub fn close_file() {
+    FILE.lock().unwrap().take();
+}
+
+#[cfg(unix)]
+fn connect(sink: LogSink, path: &str) -> io::Result<Daemon> {
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    // A daemon that does not keep up must not block the RTI.
+    socket.set_nonblocking(true)?;
+    Ok(Daemon { sink, socket })
+}
+
+#[cfg(not(unix))]
+fn connect(_sink: LogSink, _path: &str) -> io::Result<Daemon> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/**
+ * Start the following lines with the current time in the printed time format
+ * (see tag::TimeFormat) for the whole process, so that they line up with the
+ * output of other tools printing times in the same format.
+ */
+pub fn set_timestamps(timestamps: bool) {
+    TIMESTAMPS.store(timestamps, Ordering::Relaxed);
+}
+
+/**
+ * Print a line to the configured sink, with the given priority or, if None,
+ * the one that the text of the line indicates.
+ */
+pub fn log_line(priority: Option<Priority>, args: fmt::Arguments) {
+    if TIMESTAMPS.load(Ordering::Relaxed) {
+        // Stamp every line of the text, which may span several.
+        let timestamp = tag::log_timestamp();
+        let stamped: Vec<String> = args
+            .to_string()
+            .split('\n')
+            .map(|line| format!("{} {}", timestamp, line))
+            .collect();
+        write_line(priority, format_args!("{}", stamped.join("\n")));
+    } else {
+        write_line(priority, args);
+    }
+}
+
+fn write_line(priority: Option<Priority>, args: fmt::Arguments) {
+    if let Some(daemon) = DAEMON.lock().unwrap().as_ref() {
+        let line = args.to_string();
+        let priority = priority.unwrap_or_else(|| Priority::of_line(&line));
+        let datagram = match daemon.sink {
+            LogSink::Journald => journald_datagram(priority, &line),
+            _ => syslog_datagram(priority, &line),
+        };
+        if send(daemon, &datagram).is_ok() {
+            return;
+        }
+    }
+    if let Some(file) = FILE.lock().unwrap().as_mut() {
+        if writeln!(file, "{}", args).is_ok() {
+            return;
+        }
+    }
+    std::println!("{}", args);
+}
+
+#[cfg(unix)]
+fn send(daemon: &Daemon, datagram: &[u8]) -> io::Result<()> {
+    daemon.socket.send(datagram).map(|_| ())
+}
+
+#[cfg(not(unix))]
+fn send(_daemon: &Daemon, _datagram: &[u8]) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/**
+ * Format a line as "<PRI>rti[PID]: line", where PRI combines the facility and
+ * the severity.
+ */
+fn syslog_datagram(priority: Priority, line: &str) -> Vec<u8> {
+    format!(
+        "<{}>{}[{}]: {}",
+        FACILITY_DAEMON * 8 + priority as u8,
+        IDENTIFIER,
+        std::process::id(),
+        line
+    )
+    .into_bytes()
+}
+
+/**
+ * Format a line as the fields of a journal entry, one "NAME=value" per line.
+ * A value with a newline is encoded as the name, a newline, its length in 8
+ * bytes (little endian), and the value.
+ */
+fn journald_datagram(priority: Priority, line: &str) -> Vec<u8> {
+    let mut datagram = Vec::new();
+    for (name, value) in [
+        ("PRIORITY", (priority as u8).to_string()),
+        ("SYSLOG_IDENTIFIER", IDENTIFIER.to_string()),
+        ("SYSLOG_PID", std::process::id().to_string()),
+        ("MESSAGE", line.to_string()),
+    ] {
+        datagram.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            datagram.push(b'\n');
+            datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            datagram.push(b'=');
+        }
+        datagram.extend_from_slice(value.as_bytes());
+        datagram.push(b'\n');
+    }
+    datagram
+}
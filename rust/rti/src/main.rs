@@ -6,21 +6,57 @@
  * License in [BSD 2-clause](..)
  * @brief ..
  */
+use std::collections::BTreeMap;
 use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
 use std::process;
 
-fn main() {
-    let mut _f_rti = rti::initialize_rti();
+use rti::LogLevel;
+
+/**
+ * Print a line to the log sink of the RTI at the given level, like the
+ * macros of the same names in the library.
+ */
+macro_rules! error {
+    ($($arg:tt)*) => {
+        rti::log_message(LogLevel::Error, format_args!($($arg)*))
+    };
+}
 
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        rti::log_message(LogLevel::Warn, format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        rti::log_message(LogLevel::Info, format_args!($($arg)*))
+    };
+}
+
+fn main() {
     let args: Vec<String> = env::args().collect();
     // dbg!(args);
+    if args.get(1).map(String::as_str) == Some("compare") {
+        compare(&args);
+    }
+    if args.get(1).map(String::as_str) == Some("validate_snapshot") {
+        validate_snapshot(&args);
+    }
+    if args.get(1).map(String::as_str) == Some("trace_summary") {
+        trace_summary(&args);
+    }
+
+    let mut _f_rti = rti::initialize_rti();
 
     rti::process_args(&mut _f_rti, &args).unwrap_or_else(|err| {
-        println!("Problem parsing arguments: {err}");
+        error!("Problem parsing arguments: {err}");
         process::exit(1);
     });
 
-    println!(
+    info!(
         "Starting RTI for {} federates in federation ID {}.",
         _f_rti.number_of_enclaves(),
         _f_rti.federation_id()
@@ -29,8 +65,224 @@ fn main() {
 
     rti::initialize_federates(&mut _f_rti);
 
+    let shutdown = _f_rti.shutdown_handle();
+    if let Err(e) = rti::install_signal_handlers(shutdown.clone()) {
+        warn!("RTI: WARNING: Failed to handle SIGINT and SIGTERM: {}.", e);
+    }
+    if let Err(e) = rti::install_snapshot_signal_handler(_f_rti.snapshot_trigger()) {
+        warn!("RTI: WARNING: Failed to handle SIGUSR1: {}.", e);
+    }
+
     let server = rti::start_rti_server(&mut _f_rti);
-    server
+    let failed_federates = server
         .expect("Failed to wait for federates")
         .wait_for_federates(_f_rti);
+    if let Some(reason) = shutdown.reason() {
+        error!("RTI: Shut down because of {}.", reason);
+        process::exit(1);
+    }
+    if !failed_federates.is_empty() {
+        error!(
+            "RTI: {} federate(s) reported an error or exited abnormally: {:?}.",
+            failed_federates.len(),
+            failed_federates
+        );
+        process::exit(1);
+    }
+}
+
+/**
+ * Validate a snapshot of the state of a federation, as returned by the
+ * status server or written to --snapshot_file: check that it follows a schema
+ * version that this RTI reads, print a summary of it, and exit. Restart a
+ * federation from it with --restore_snapshot.
+ * Usage: rti validate_snapshot <file>
+ */
+fn validate_snapshot(args: &[String]) -> ! {
+    if args.len() != 3 {
+        error!("Usage: {} validate_snapshot <file>", args[0]);
+        process::exit(1);
+    }
+    let text = fs::read_to_string(&args[2]).unwrap_or_else(|e| {
+        error!("Failed to read {}: {}.", args[2], e);
+        process::exit(1);
+    });
+    match rti::Snapshot::parse(&text) {
+        Ok(snapshot) => {
+            let connected = snapshot
+                .federates()
+                .iter()
+                .filter(|fed| fed.state() != "not_connected")
+                .count();
+            println!(
+                "Snapshot of federation {} in schema version {} (current: {}): {} federate(s), {} connected, start time {}{}.",
+                snapshot.federation_id(),
+                snapshot.schema_version(),
+                rti::SNAPSHOT_SCHEMA_VERSION,
+                snapshot.federates().len(),
+                connected,
+                snapshot
+                    .start_time()
+                    .map_or_else(|| String::from("not decided"), |time| time.to_string()),
+                if snapshot.stop_in_progress() {
+                    ", stopping"
+                } else {
+                    ""
+                }
+            );
+            process::exit(0);
+        }
+        Err(e) => {
+            error!("Invalid snapshot {}: {}.", args[2], e);
+            process::exit(1);
+        }
+    }
+}
+
+/**
+ * Print a summary of a binary trace written with --tracing: its header, the
+ * number of records of each event, and, for each federate, the number of
+ * records about it and the last TAG that it was sent, and exit.
+ * Usage: rti trace_summary <file>
+ */
+fn trace_summary(args: &[String]) -> ! {
+    if args.len() != 3 {
+        error!("Usage: {} trace_summary <file>", args[0]);
+        process::exit(1);
+    }
+    let file = File::open(&args[2]).unwrap_or_else(|e| {
+        error!("Failed to read {}: {}.", args[2], e);
+        process::exit(1);
+    });
+    let mut reader = BufReader::new(file);
+    let header = rti::TraceHeader::read(&mut reader).unwrap_or_else(|e| {
+        error!("Invalid trace {}: {}.", args[2], e);
+        process::exit(1);
+    });
+    let start_time = header.start_time();
+    let relative = |tag: &rti::Tag| {
+        if tag.time() == rti::Tag::never_tag().time() {
+            String::from("NEVER")
+        } else if tag.time() == rti::Tag::forever_tag().time() {
+            String::from("FOREVER")
+        } else {
+            format!("({}, {})", tag.time() - start_time, tag.microstep())
+        }
+    };
+    let mut event_counts: BTreeMap<i32, (rti::TraceEvent, usize)> = BTreeMap::new();
+    let mut federate_counts = vec![0usize; header.number_of_federates()];
+    let mut last_granted: Vec<Option<rti::Tag>> = vec![None; header.number_of_federates()];
+    let mut physical_times = None;
+    let mut records = 0;
+    loop {
+        let block = match rti::TraceRecord::read_block(&mut reader) {
+            Ok(Some(block)) => block,
+            Ok(None) => break,
+            Err(e) => {
+                error!(
+                    "Invalid trace {} after {} records: {}.",
+                    args[2], records, e
+                );
+                process::exit(1);
+            }
+        };
+        for record in block {
+            records += 1;
+            event_counts
+                .entry(record.event().to_value())
+                .or_insert((record.event(), 0))
+                .1 += 1;
+            let (first, last) = physical_times.unwrap_or((i64::MAX, i64::MIN));
+            physical_times = Some((
+                first.min(record.physical_time()),
+                last.max(record.physical_time()),
+            ));
+            // The RTI records itself as -1.
+            for id in [record.src_id(), record.dst_id()] {
+                if let Some(count) = usize::try_from(id)
+                    .ok()
+                    .and_then(|id| federate_counts.get_mut(id))
+                {
+                    *count += 1;
+                }
+            }
+            if record.event() == rti::TraceEvent::SendTag {
+                if let Some(last) = usize::try_from(record.dst_id())
+                    .ok()
+                    .and_then(|id| last_granted.get_mut(id))
+                {
+                    *last = Some(record.tag().clone());
+                }
+            }
+        }
+    }
+    println!(
+        "Trace in format version {} (current: {}) with start time {} and {} federate(s).",
+        header.version(),
+        rti::LFT_FORMAT_VERSION,
+        start_time,
+        header.number_of_federates()
+    );
+    match physical_times {
+        Some((first, last)) => println!(
+            "{} record(s) over {} ns of physical time.",
+            records,
+            last - first
+        ),
+        None => println!("No records."),
+    }
+    for (event, count) in event_counts.values() {
+        println!("  {:?}: {}", event, count);
+    }
+    for (id, name) in header.federate_names().iter().enumerate() {
+        println!(
+            "Federate {}{}: {} record(s), last TAG {}.",
+            id,
+            name.as_ref()
+                .map_or_else(String::new, |name| format!(" ({})", name)),
+            federate_counts[id],
+            last_granted[id]
+                .as_ref()
+                .map_or_else(|| String::from("none"), relative)
+        );
+    }
+    process::exit(0);
+}
+
+/**
+ * Print the comparison of two runs recorded with --run_db and exit.
+ * Usage: rti compare <file> [<run ID> <run ID>]
+ */
+#[cfg(feature = "run_db")]
+fn compare(args: &[String]) -> ! {
+    let runs = match args.len() {
+        3 => None,
+        5 => match (args[3].parse::<i64>(), args[4].parse::<i64>()) {
+            (Ok(first), Ok(second)) => Some((first, second)),
+            _ => {
+                error!("Run IDs must be integers.");
+                process::exit(1);
+            }
+        },
+        _ => {
+            error!("Usage: {} compare <file> [<run ID> <run ID>]", args[0]);
+            process::exit(1);
+        }
+    };
+    match rti::compare_runs(&args[2], runs) {
+        Ok(report) => {
+            println!("{}", report);
+            process::exit(0);
+        }
+        Err(e) => {
+            error!("Failed to compare the runs: {}.", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "run_db"))]
+fn compare(_args: &[String]) -> ! {
+    error!("compare requires the RTI to be built with the run_db feature (cargo build --features run_db).");
+    process::exit(1);
 }
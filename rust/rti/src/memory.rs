@@ -0,0 +1,107 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Memory budgets for the bookkeeping of the RTI that grows with the
+ * traffic of the federates: the records of in-transit messages, the trace
+ * buffer, and the scheduling histories.
+ *
+ * A budget is shared by all the structures of its kind, e.g., by the
+ * in-transit records of all federates, so that the worst-case memory use of a
+ * long-running RTI does not depend on how bursty the federates are. Sizes are
+ * estimated from the sizes of the records, without the overhead of the
+ * allocator. What happens when a budget is exhausted depends on the kind:
+ *
+ * - In-transit records: A new record is folded into a summary of the
+ *   evicted records of the destination federate, which conservatively bounds
+ *   its next event by the earliest of them until it completes the latest.
+ * - Trace records: New records are dropped until the buffer has been written.
+ * - History entries: The oldest entry of the longest history is evicted.
+ */
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/**
+ * A limit on the bytes held by the bookkeeping of one kind, with the
+ * accounting of the bytes held, of their peak, and of the evicted records.
+ */
+pub struct MemoryBudget {
+    name: &'static str,
+    limit: usize,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    evicted: AtomicU64,
+}
+
+impl MemoryBudget {
+    pub fn new(name: &'static str, limit: usize) -> MemoryBudget {
+        MemoryBudget {
+            name,
+            limit,
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            evicted: AtomicU64::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::SeqCst)
+    }
+
+    /**
+     * Account for the given number of bytes and return true if they fit in
+     * the budget. Otherwise, account for nothing and return false.
+     */
+    pub fn try_reserve(&self, bytes: usize) -> bool {
+        let reserved = self
+            .used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(bytes).filter(|total| *total <= self.limit)
+            });
+        match reserved {
+            Ok(used) => {
+                self.peak.fetch_max(used + bytes, Ordering::SeqCst);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /**
+     * Return the given number of bytes, which must have been reserved, to the
+     * budget.
+     */
+    pub fn release(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+    }
+
+    /**
+     * Count records evicted, or dropped, to stay within the budget.
+     */
+    pub fn record_eviction(&self, records: u64) {
+        self.evicted.fetch_add(records, Ordering::SeqCst);
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} bytes of {} used, peak {}, {} record(s) evicted",
+            self.used(),
+            self.limit,
+            self.peak(),
+            self.evicted()
+        )
+    }
+}
@@ -10,10 +10,20 @@
  * @copyright (c) 2023, The University of California at Berkeley.
  * License in [BSD 2-clause](..)
  */
+use std::mem;
+use std::sync::Arc;
+
 use priority_queue::PriorityQueue;
 
+use crate::memory::MemoryBudget;
 use crate::message_record::rti_pqueue_support::InTransitMessageRecord;
 use crate::tag::{Instant, Tag};
+use crate::trace::{debug, error};
+
+/**
+ * Estimated size of one in-transit record, which is charged to the budget.
+ */
+pub const IN_TRANSIT_RECORD_SIZE: usize = mem::size_of::<Tag>() + mem::size_of::<usize>();
 
 /**
  * @brief Queue to keep a record of in-transit messages.
@@ -22,13 +32,42 @@ use crate::tag::{Instant, Tag};
 pub struct InTransitMessageRecordQueue {
     main_queue: PriorityQueue<Tag, usize>,     // The primary queue.
     transfer_queue: PriorityQueue<Tag, usize>, // Queue used for housekeeping.
+    budget: Option<Arc<MemoryBudget>>, // Budget shared by the queues of all federates, or None.
+    charged: usize,                    // Number of records charged to the budget.
+    evicted: Option<(Tag, Tag)>,       // Earliest and latest tags of the records not kept
+                                       // because the budget was exhausted, or None.
 }
 
 impl InTransitMessageRecordQueue {
     pub fn new() -> InTransitMessageRecordQueue {
+        InTransitMessageRecordQueue::with_budget(None)
+    }
+
+    pub fn with_budget(budget: Option<Arc<MemoryBudget>>) -> InTransitMessageRecordQueue {
         InTransitMessageRecordQueue {
             main_queue: PriorityQueue::with_capacity(10),
             transfer_queue: PriorityQueue::with_capacity(10),
+            budget,
+            charged: 0,
+            evicted: None,
+        }
+    }
+
+    pub fn budget(&self) -> &Option<Arc<MemoryBudget>> {
+        &self.budget
+    }
+
+    /**
+     * Return to the budget the records charged to it that the queue no longer
+     * holds because they were removed or replaced by a record of the same tag.
+     */
+    fn settle(&mut self) {
+        let records = self.main_queue.len() + self.transfer_queue.len();
+        if records < self.charged {
+            if let Some(budget) = &self.budget {
+                budget.release((self.charged - records) * IN_TRANSIT_RECORD_SIZE);
+            }
+            self.charged = records;
         }
     }
 
@@ -41,20 +80,44 @@ impl InTransitMessageRecordQueue {
     }
 }
 
+impl Drop for InTransitMessageRecordQueue {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            budget.release(self.charged * IN_TRANSIT_RECORD_SIZE);
+        }
+    }
+}
+
 pub struct MessageRecord {}
 
 impl MessageRecord {
     /**
      * @brief Add a record of the in-transit message.
      *
+     * If the budget of the queue is exhausted, the record is folded into the
+     * summary of the evicted records instead.
+     *
      * @param queue The queue to add to.
      * @param tag The tag of the in-transit message.
      * @return 0 on success.
      */
     pub fn add_in_transit_message_record(queue: &mut InTransitMessageRecordQueue, tag: Tag) {
+        if let Some(budget) = &queue.budget {
+            if !budget.try_reserve(IN_TRANSIT_RECORD_SIZE) {
+                budget.record_eviction(1);
+                queue.evicted = match queue.evicted.take() {
+                    Some((earliest, latest)) => Some((earliest.min(tag.clone()), latest.max(tag))),
+                    None => Some((tag.clone(), tag)),
+                };
+                return;
+            }
+            queue.charged += 1;
+        }
         let main_queue = queue.main_queue();
         let in_transit_record = InTransitMessageRecord::new(tag, 0);
         main_queue.push(in_transit_record.tag(), in_transit_record.pos());
+        // A record of the same tag replaces the previous one.
+        queue.settle();
     }
 
     /**
@@ -82,8 +145,8 @@ impl MessageRecord {
                     {
                         // Now compare the tags. The message record queue is ordered according to the `time` field, so we need to check
                         // all records with that `time` and find those that have smaller or equal full tags.
-                        if Tag::lf_tag_compare(&head_tag, &tag) <= 0 {
-                            println!(
+                        if head_tag <= tag {
+                            debug!(
                                 "RTI: Removed a message with tag ({}, {}) from the list of in-transit messages.",
                                 head_tag.time() - start_time,
                                 head_tag.microstep()
@@ -93,7 +156,7 @@ impl MessageRecord {
                             match main_queue.pop() {
                                 Some(..) => {}
                                 None => {
-                                    println!("Failed to pop an item from a main queue.");
+                                    error!("Failed to pop an item from a main queue.");
                                 }
                             }
                         } else {
@@ -103,7 +166,7 @@ impl MessageRecord {
                                     temp_queue.push(head.0, head.1);
                                 }
                                 None => {
-                                    println!("Failed to pop an item from a main queue.");
+                                    error!("Failed to pop an item from a main queue.");
                                     return;
                                 }
                             }
@@ -111,7 +174,7 @@ impl MessageRecord {
                     }
                 }
                 None => {
-                    println!("Failed to peek an item from a main queue.")
+                    error!("Failed to peek an item from a main queue.")
                 }
             }
         }
@@ -121,6 +184,15 @@ impl MessageRecord {
         for node in &temp_queue {
             transfer_queue.push(node.0.clone(), *node.1);
         }
+        // The evicted messages have all been processed once the latest of them has.
+        if queue
+            .evicted
+            .as_ref()
+            .is_some_and(|(_, latest)| *latest <= tag)
+        {
+            queue.evicted = None;
+        }
+        queue.settle();
     }
 
     /**
@@ -142,15 +214,15 @@ impl MessageRecord {
                 Some(head_of_in_transit_messages) => {
                     // The message record queue is ordered according to the `time` field, so we need to check
                     // all records with the minimum `time` and find those that have the smallest tag.
-                    let mut head_tag = head_of_in_transit_messages.0.clone();
-                    if Tag::lf_tag_compare(&mut head_tag, &mut minimum_tag) <= 0 {
+                    let head_tag = head_of_in_transit_messages.0.clone();
+                    if head_tag <= minimum_tag {
                         minimum_tag = head_tag.clone();
                     } else if head_tag.time() > minimum_tag.time() {
                         break;
                     }
                 }
                 None => {
-                    println!("Failed to peek an item from a main queue.")
+                    error!("Failed to peek an item from a main queue.")
                 }
             }
 
@@ -160,7 +232,7 @@ impl MessageRecord {
                     temp_queue.push(head.0, head.1);
                 }
                 None => {
-                    println!("Failed to pop an item from a main queue.");
+                    error!("Failed to pop an item from a main queue.");
                 }
             }
         }
@@ -169,14 +241,14 @@ impl MessageRecord {
             match main_queue.peek() {
                 Some(head_of_in_transit_messages) => {
                     let head_tag = head_of_in_transit_messages.0.clone();
-                    println!(
+                    debug!(
                         "RTI: Minimum tag of all in-transit messages: ({},{})",
                         head_tag.time() - start_time,
                         head_tag.microstep()
                     );
                 }
                 None => {
-                    println!("Failed to peek an item from a main queue.")
+                    error!("Failed to peek an item from a main queue.")
                 }
             }
         }
@@ -185,7 +257,11 @@ impl MessageRecord {
         for node in &temp_queue {
             transfer_queue.push(node.0.clone(), *node.1);
         }
+        queue.settle();
 
-        minimum_tag
+        match &queue.evicted {
+            Some((earliest, _)) => minimum_tag.min(earliest.clone()),
+            None => minimum_tag,
+        }
     }
 }
@@ -0,0 +1,304 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Connections shared by several federates running in the same process.
+ * See MULTIPLEX_FRAME_HEADER_LENGTH.
+ *
+ * A thread reads the frames from the shared connection and appends their
+ * bytes to the channel of their federate, from which the RTI reads as from a
+ * connection of its own. Writes to a channel are sent as single frames, so
+ * that the threads writing to different federates do not interleave them.
+ */
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::constants::MULTIPLEX_READ_BUFFER_SIZE;
+use crate::net_common::MULTIPLEX_FRAME_HEADER_LENGTH;
+use crate::transport::FederateStream;
+
+/**
+ * Bytes received for a federate that have not been read yet.
+ */
+struct Inbox {
+    bytes: VecDeque<u8>,
+    // Indicates that the RTI or the process of the federate has closed the
+    // channel.
+    closed: bool,
+    // Shared by the clones of the stream, like the read timeout of a socket.
+    read_timeout: Option<Duration>,
+}
+
+/**
+ * Connection of one federate within a shared connection.
+ */
+struct Channel {
+    inbox: Mutex<Inbox>,
+    arrived: Condvar,
+}
+
+impl Channel {
+    fn new() -> Channel {
+        Channel {
+            inbox: Mutex::new(Inbox {
+                bytes: VecDeque::new(),
+                closed: false,
+                read_timeout: None,
+            }),
+            arrived: Condvar::new(),
+        }
+    }
+
+    fn push(&self, bytes: &[u8]) {
+        let mut inbox = self.inbox.lock().unwrap();
+        // The bytes that arrive after the RTI closed the channel are discarded.
+        if !inbox.closed {
+            inbox.bytes.extend(bytes);
+            self.arrived.notify_all();
+        }
+    }
+
+    /**
+     * Close the channel and return true if it was open.
+     */
+    fn close(&self) -> bool {
+        let mut inbox = self.inbox.lock().unwrap();
+        let was_open = !inbox.closed;
+        inbox.closed = true;
+        self.arrived.notify_all();
+        was_open
+    }
+}
+
+/**
+ * State of a shared connection, referenced by the streams of its channels.
+ */
+struct Shared {
+    // Clone of the connection to which frames are written.
+    socket: FederateStream,
+    channels: Mutex<HashMap<u16, Arc<Channel>>>,
+    // Held while a frame is written.
+    write_lock: Mutex<()>,
+}
+
+impl Shared {
+    fn write_frame(&self, fed_id: u16, bytes: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(MULTIPLEX_FRAME_HEADER_LENGTH + bytes.len());
+        frame.extend_from_slice(&fed_id.to_le_bytes());
+        frame.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        frame.extend_from_slice(bytes);
+        let _write_guard = self.write_lock.lock().unwrap();
+        (&self.socket).write_all(&frame)
+    }
+}
+
+/**
+ * Reader of the frames of a shared connection.
+ */
+pub struct Multiplexer {
+    socket: FederateStream,
+    shared: Arc<Shared>,
+}
+
+impl Multiplexer {
+    /**
+     * Take over the given connection, whose MsgType::Multiplex byte has been
+     * read.
+     */
+    pub fn new(socket: FederateStream) -> io::Result<Multiplexer> {
+        let shared = Arc::new(Shared {
+            socket: socket.try_clone()?,
+            channels: Mutex::new(HashMap::new()),
+            write_lock: Mutex::new(()),
+        });
+        Ok(Multiplexer { socket, shared })
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+
+    /**
+     * Read frames until the shared connection is closed, and call the given
+     * function with the stream of each federate that starts a connection.
+     * Then close the connections of all federates. Return the number of
+     * connections that have been started, or an error if the shared
+     * connection failed.
+     */
+    pub fn run(self, mut on_channel: impl FnMut(MultiplexedStream)) -> io::Result<usize> {
+        let mut started = 0;
+        let result = self.read_frames(&mut |stream| {
+            started += 1;
+            on_channel(stream)
+        });
+        for (_, channel) in self.shared.channels.lock().unwrap().drain() {
+            channel.close();
+        }
+        match result {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(started),
+            Err(e) => Err(e),
+            Ok(()) => Ok(started),
+        }
+    }
+
+    fn read_frames(&self, on_channel: &mut impl FnMut(MultiplexedStream)) -> io::Result<()> {
+        let mut socket = &self.socket;
+        let mut header = [0u8; MULTIPLEX_FRAME_HEADER_LENGTH];
+        let mut chunk = [0u8; MULTIPLEX_READ_BUFFER_SIZE];
+        loop {
+            socket.read_exact(&mut header)?;
+            let fed_id = u16::from_le_bytes(header[..2].try_into().unwrap());
+            let mut remaining = u32::from_le_bytes(header[2..].try_into().unwrap()) as usize;
+            if remaining == 0 {
+                // The process has closed the connection of the federate.
+                if let Some(channel) = self.shared.channels.lock().unwrap().remove(&fed_id) {
+                    channel.close();
+                }
+                continue;
+            }
+            let channel = {
+                let mut channels = self.shared.channels.lock().unwrap();
+                match channels.get(&fed_id) {
+                    Some(channel) => channel.clone(),
+                    None => {
+                        let channel = Arc::new(Channel::new());
+                        channels.insert(fed_id, channel.clone());
+                        drop(channels);
+                        on_channel(MultiplexedStream {
+                            fed_id,
+                            channel: channel.clone(),
+                            shared: self.shared.clone(),
+                        });
+                        channel
+                    }
+                }
+            };
+            while remaining > 0 {
+                let size = remaining.min(chunk.len());
+                socket.read_exact(&mut chunk[..size])?;
+                channel.push(&chunk[..size]);
+                remaining -= size;
+            }
+        }
+    }
+}
+
+/**
+ * Connection of one federate within a shared connection. Like a TcpStream, it
+ * can be cloned. The read timeout applies to this federate only, but the write
+ * timeout and the other options of the socket apply to the shared connection.
+ */
+pub struct MultiplexedStream {
+    fed_id: u16,
+    channel: Arc<Channel>,
+    shared: Arc<Shared>,
+}
+
+impl MultiplexedStream {
+    pub fn try_clone(&self) -> io::Result<MultiplexedStream> {
+        Ok(MultiplexedStream {
+            fed_id: self.fed_id,
+            channel: self.channel.clone(),
+            shared: self.shared.clone(),
+        })
+    }
+
+    /**
+     * Return the federate ID in the frames of this connection.
+     */
+    pub fn fed_id(&self) -> u16 {
+        self.fed_id
+    }
+
+    pub fn socket(&self) -> &TcpStream {
+        self.shared.socket.socket()
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        // A zero timeout is invalid, as for a socket.
+        if timeout == Some(Duration::ZERO) {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+        self.channel.inbox.lock().unwrap().read_timeout = timeout;
+        Ok(())
+    }
+
+    pub fn peek(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.read_inbox(buffer, false)
+    }
+
+    /**
+     * Close the connection of the federate, but not the shared connection.
+     */
+    pub fn shutdown(&self, _how: Shutdown) -> io::Result<()> {
+        if self.channel.close() {
+            self.shared.write_frame(self.fed_id, &[])
+        } else {
+            Ok(())
+        }
+    }
+
+    /**
+     * Read received bytes into the given buffer, waiting for them for at most
+     * the read timeout if there are none, and remove them from the stream if
+     * consume is true.
+     */
+    fn read_inbox(&self, buffer: &mut [u8], consume: bool) -> io::Result<usize> {
+        let mut inbox = self.channel.inbox.lock().unwrap();
+        let deadline = inbox.read_timeout.map(|timeout| Instant::now() + timeout);
+        while inbox.bytes.is_empty() && !inbox.closed && !buffer.is_empty() {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(io::Error::from(io::ErrorKind::WouldBlock));
+                    }
+                    inbox = self
+                        .channel
+                        .arrived
+                        .wait_timeout(inbox, deadline - now)
+                        .unwrap()
+                        .0;
+                }
+                None => inbox = self.channel.arrived.wait(inbox).unwrap(),
+            }
+        }
+        let size = buffer.len().min(inbox.bytes.len());
+        for (dst, src) in buffer.iter_mut().zip(inbox.bytes.iter()) {
+            *dst = *src;
+        }
+        if consume {
+            inbox.bytes.drain(..size);
+        }
+        Ok(size)
+    }
+}
+
+impl Read for &MultiplexedStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.read_inbox(buffer, true)
+    }
+}
+
+impl Write for &MultiplexedStream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        if self.channel.inbox.lock().unwrap().closed {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+        }
+        // An empty frame would close the connection of the federate.
+        if !buffer.is_empty() {
+            self.shared.write_frame(self.fed_id, buffer)?;
+        }
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Frames are written to the socket as a whole.
+        Ok(())
+    }
+}
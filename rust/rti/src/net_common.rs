@@ -13,13 +13,6 @@
  * used by scheduling enclaves.
  */
 use crate::tag::{Instant, Microstep};
-/**
- * Size of the buffer used for messages sent between federates.
- * This is used by both the federates and the rti, so message lengths
- * should generally match.
- */
-pub const FED_COM_BUFFER_SIZE: usize = 256;
-
 /**
  * Delay the start of all federates by this amount.
  * FIXME: More.
@@ -85,6 +78,330 @@ pub const MSG_TYPE_STOP_GRANTED_LENGTH: usize =
 
 pub const MSG_TYPE_NEIGHBOR_STRUCTURE_HEADER_SIZE: i32 = 9;
 
+/**
+ * Federate ID with which a federate asks the RTI to assign it an ID in a
+ * MSG_TYPE_FedIds message. Accepted only if the RTI runs with --dynamic_ids.
+ * The RTI then replies with a MSG_TYPE_Ack followed by the assigned ID
+ * (2 bytes, little endian) instead of a MSG_TYPE_Ack alone.
+ */
+pub const ASSIGN_FEDERATE_ID: u16 = u16::MAX;
+
+/**
+ * Length of the header of a MSG_TYPE_FederateName message. A federate may send
+ * this optional message after the MSG_TYPE_Ack of its MSG_TYPE_FedIds message
+ * and before its MSG_TYPE_NeighborStructure message to present a
+ * human-readable name, which the RTI then uses in its output and admin console.
+ *
+ * The next byte is the length of the name.
+ * The following bytes are the name in UTF-8 without a terminating null.
+ *
+ * The name must be unique in the federation, must not contain whitespace, and
+ * must not be a number or "all" so that the admin console can tell it from
+ * federate IDs. Otherwise, the RTI rejects the federate.
+ */
+pub const MSG_TYPE_FEDERATE_NAME_HEADER_LENGTH: usize = 1 + std::mem::size_of::<u8>();
+
+/**
+ * Length of the header of a MSG_TYPE_ControlChannel message that opens a
+ * control connection. A federate that wants its grants not to queue behind
+ * the large messages that the RTI relays to it sends this message without
+ * payload after the MSG_TYPE_Ack of its MSG_TYPE_FedIds message and before its
+ * MSG_TYPE_NeighborStructure message. It then opens a second connection to
+ * the RTI and sends on it a MSG_TYPE_ControlChannel message with the same
+ * payload as its MSG_TYPE_FedIds message, which the RTI acknowledges with a
+ * MSG_TYPE_Ack. The federate must complete its handshake on the first
+ * connection without waiting for the acknowledgement, because the RTI may
+ * only handle the control connection afterwards, but must receive the
+ * acknowledgement before it sends its MSG_TYPE_Timestamp message.
+ *
+ * The next 2 bytes are the federate ID.
+ * The next byte is the length of the federation ID.
+ * The following bytes are the federation ID.
+ *
+ * The RTI then sends MSG_TYPE_TagAdvanceGrant and
+ * MSG_TYPE_PropositionalTagAdvanceGrant messages to the federate only on the
+ * control connection, each followed by the number of MSG_TYPE_TaggedMessage,
+ * MSG_TYPE_P2pTaggedMessage, and MSG_TYPE_PortAbsent messages (8 bytes, little
+ * endian) that the RTI had started to relay to the federate on the first
+ * connection. The federate must have received that many messages on the first
+ * connection before it acts on the grant. The RTI sends nothing else on the
+ * control connection and does not read from it.
+ */
+pub const MSG_TYPE_CONTROL_CHANNEL_HEADER_LENGTH: usize =
+    1 + std::mem::size_of::<u16>() + std::mem::size_of::<u8>();
+
+/**
+ * Length of the header of a MSG_TYPE_FederateMetadata message. A federate may
+ * send this optional message after the MSG_TYPE_Ack of its MSG_TYPE_FedIds
+ * message and before its MSG_TYPE_NeighborStructure message to describe
+ * itself, which helps to debug federations of federates written in different
+ * languages. The RTI shows the metadata in its output, the topology file, and
+ * the admin console.
+ *
+ * The next byte is the length of the runtime, e.g., "C/0.8.0".
+ * The following bytes are the runtime in UTF-8 without a terminating null.
+ * The next byte is the length of the host name.
+ * The following bytes are the host name in UTF-8 without a terminating null.
+ * The next 4 bytes are the process ID (little endian).
+ *
+ * The runtime and the host name must not contain whitespace. Otherwise, the
+ * RTI rejects the federate.
+ */
+pub const MSG_TYPE_FEDERATE_METADATA_HEADER_LENGTH: usize = 1 + std::mem::size_of::<u8>();
+
+/**
+ * Length of a MSG_TYPE_DownstreamNextEventTag (DNET) message, which the RTI
+ * sends to a federate so that it can skip NET messages that no downstream
+ * federate needs, as the C RTI does unless DNET messages are disabled with
+ * --disable_dnet. The RTI sends DNET messages only to federates that have no
+ * upstream federates and use centralized coordination, because the grants of
+ * the others depend on their NETs.
+ *
+ * The next 8 bytes are the time of the DNET (little endian).
+ * The next 4 bytes are the microstep of the DNET (little endian).
+ *
+ * The federate need not send a MSG_TYPE_NextEventTag message with a tag that
+ * is earlier than or equal to the last DNET, because none of its downstream
+ * federates can be granted its next event tag before the federate completes
+ * that tag anyway. It must still send its MSG_TYPE_LogicalTagComplete
+ * messages. If a later DNET is earlier than the tag of a NET that it has
+ * skipped and not superseded, the federate must send that NET then.
+ */
+pub const MSG_TYPE_DOWNSTREAM_NEXT_EVENT_TAG_LENGTH: usize =
+    1 + std::mem::size_of::<i64>() + std::mem::size_of::<u32>();
+
+/**
+ * Length of a MSG_TYPE_StartTag message, which the RTI sends to a transient
+ * federate (see --transient) right after the MSG_TYPE_Timestamp message with
+ * the start time of the federation.
+ *
+ * The next 8 bytes are the time of the effective start tag of the federate
+ * (little endian).
+ * The next 4 bytes are its microstep (little endian).
+ *
+ * A transient federate that joins before the federation starts gets the
+ * start tag of the federation. One that joins or rejoins later gets a tag
+ * after every tag that its downstream federates may already have been
+ * granted, adjusted by the delays on the way, so that none of its messages
+ * can arrive in their past. The RTI drops the messages sent to the federate
+ * with a tag earlier than its effective start tag, as it drops the messages
+ * sent to it while it is absent.
+ */
+pub const MSG_TYPE_START_TAG_LENGTH: usize =
+    1 + std::mem::size_of::<i64>() + std::mem::size_of::<u32>();
+
+/**
+ * Length of a MSG_TYPE_Capabilities message, with which a federate and the RTI
+ * negotiate optional features, so that a feature can be added without
+ * breaking the federates that do not know it. A federate may send this
+ * message after the MSG_TYPE_Ack of its MSG_TYPE_FedIds message and before its
+ * MSG_TYPE_NeighborStructure message. The RTI replies with a
+ * MSG_TYPE_Capabilities message with the capabilities that it enables for the
+ * federate, which are those of the offered ones that it supports. The
+ * federate may complete its handshake without waiting for the reply, but must
+ * not rely on a capability before it has received the reply.
+ *
+ * The next 4 bytes are the bitmask of capabilities (little endian), made of
+ * the CAPABILITY_* bits. Bits that the RTI does not know are ignored and not
+ * enabled.
+ *
+ * A federate that does not send this message has DEFAULT_CAPABILITIES, to
+ * which a MSG_TYPE_ControlChannel message without payload before its
+ * MSG_TYPE_NeighborStructure message adds CAPABILITY_CONTROL_CHANNEL as
+ * before.
+ */
+pub const MSG_TYPE_CAPABILITIES_LENGTH: usize = 1 + std::mem::size_of::<u32>();
+
+/**
+ * Capability of compressing the payload of tagged messages. Reserved: this
+ * RTI does not support it and never enables it.
+ */
+pub const CAPABILITY_COMPRESSION: u32 = 1 << 0;
+
+/**
+ * Capability of receiving grants on a control connection. See
+ * MSG_TYPE_CONTROL_CHANNEL_HEADER_LENGTH. A federate that is enabled this
+ * capability must open the control connection.
+ */
+pub const CAPABILITY_CONTROL_CHANNEL: u32 = 1 << 2;
+
+/**
+ * Capability of handling the MSG_TYPE_ClockSyncCodedProbe messages that
+ * follow the MSG_TYPE_ClockSyncT4 messages of runtime clock synchronization.
+ * See MSG_TYPE_CLOCK_SYNC_TIME_LENGTH. The RTI does not send coded probes to
+ * a federate that negotiates its capabilities without this one.
+ */
+pub const CAPABILITY_CODED_PROBES: u32 = 1 << 3;
+
+/**
+ * Capability of resuming a session after a lost connection. See
+ * MSG_TYPE_RESUME_LENGTH. Enabled only with --resume_window and not together
+ * with CAPABILITY_CONTROL_CHANNEL, as the control connection cannot be
+ * resumed.
+ */
+pub const CAPABILITY_RESUMPTION: u32 = 1 << 4;
+
+/**
+ * Capability of running in fast mode, i.e., without waiting for physical
+ * time. Enabled only with --fast, so that the reply tells a federate that
+ * offers it that the whole federation runs in fast mode, and the federate
+ * runs in fast mode as well. A federate that does not offer it is not told
+ * and must be started in fast mode on its own.
+ */
+pub const CAPABILITY_FAST_MODE: u32 = 1 << 5;
+
+/**
+ * Capabilities of a federate that does not send a MSG_TYPE_Capabilities
+ * message.
+ */
+pub const DEFAULT_CAPABILITIES: u32 = CAPABILITY_CODED_PROBES;
+
+/**
+ * Return the names of the given capabilities.
+ */
+pub fn capability_names(capabilities: u32) -> Vec<&'static str> {
+    [
+        (CAPABILITY_COMPRESSION, "compression"),
+        (CAPABILITY_CONTROL_CHANNEL, "control_channel"),
+        (CAPABILITY_CODED_PROBES, "coded_probes"),
+        (CAPABILITY_RESUMPTION, "resumption"),
+        (CAPABILITY_FAST_MODE, "fast_mode"),
+    ]
+    .iter()
+    .filter(|(capability, _)| capabilities & capability != 0)
+    .map(|(_, name)| *name)
+    .collect()
+}
+
+/**
+ * Length of the resumption tokens of federates.
+ */
+pub const RESUME_TOKEN_LENGTH: usize = 16;
+
+/**
+ * Length of a MSG_TYPE_ResumeToken message, which the RTI sends to a federate
+ * right after the MSG_TYPE_Capabilities message that enables
+ * CAPABILITY_RESUMPTION for it.
+ *
+ * The next RESUME_TOKEN_LENGTH bytes are a random token, with which the
+ * federate can resume its session once, as described at
+ * MSG_TYPE_RESUME_LENGTH.
+ */
+pub const MSG_TYPE_RESUME_TOKEN_LENGTH: usize = 1 + RESUME_TOKEN_LENGTH;
+
+/**
+ * Length of a MSG_TYPE_Resume message, with which a federate that has been
+ * enabled CAPABILITY_RESUMPTION and has lost its connection to the RTI starts
+ * a new connection instead of a MSG_TYPE_FedIds message, or a
+ * MSG_TYPE_FedNonce message if the RTI authenticates federates.
+ *
+ * The next 2 bytes are the federate ID (little endian).
+ * The next RESUME_TOKEN_LENGTH bytes are the last token that the RTI has
+ * issued to the federate.
+ *
+ * The RTI holds the state of a federate whose connection is lost for the
+ * window given with --resume_window before it handles the federate as failed,
+ * so that the federates downstream of it wait for it instead of proceeding
+ * without it. If the token is valid and the window has not expired, the RTI
+ * replies with a MSG_TYPE_Ack followed by a new token, which replaces the
+ * used one, and re-attaches the federate to its state without the rest of the
+ * handshake: the federate resumes executing right away. The RTI then sends it
+ * its last grant again. Messages that the RTI relays to the federate while
+ * its connection is lost are dropped, and so may be messages in flight on the
+ * lost connection, as the protocol has no acknowledgements. The RTI refuses
+ * to resume a session once the stop protocol is in progress, as the federate
+ * may have missed its messages. Otherwise, the RTI replies with a
+ * MSG_TYPE_Reject with ErrType::ResumptionRefused.
+ */
+pub const MSG_TYPE_RESUME_LENGTH: usize = 1 + std::mem::size_of::<u16>() + RESUME_TOKEN_LENGTH;
+
+/**
+ * Length of the header of a frame on a connection that several federates
+ * running in the same process share to save sockets. The process opens the
+ * connection with a single MSG_TYPE_Multiplex byte. Each frame that follows,
+ * in both directions, carries bytes of the connection of one federate:
+ *
+ * The first 2 bytes are the federate ID (little endian).
+ * The next 4 bytes are the number of bytes that follow (little endian).
+ *
+ * The bytes of a federate form a connection of its own, over which it
+ * performs the usual handshake and executes, starting with its first frame.
+ * A frame without bytes closes the connection of the federate. The RTI
+ * discards the frames of a federate whose connection it has closed until the
+ * process closes it as well, after which the federate ID may start a new
+ * connection, e.g., when a transient federate joins again. A control
+ * connection has to be opened separately. Closing the shared connection
+ * closes the connections of all of its federates.
+ */
+pub const MULTIPLEX_FRAME_HEADER_LENGTH: usize =
+    std::mem::size_of::<u16>() + std::mem::size_of::<u32>();
+
+/**
+ * Length of the MSG_TYPE_ClockSyncT1, MSG_TYPE_ClockSyncT4, and
+ * MSG_TYPE_ClockSyncCodedProbe messages, which the RTI sends to a federate for
+ * clock synchronization.
+ *
+ * The next 8 bytes are the physical time of the RTI (little endian) when it
+ * sends the message.
+ *
+ * The RTI starts an exchange with a MSG_TYPE_ClockSyncT1 message, to which the
+ * federate replies with a MSG_TYPE_ClockSyncT3 message, to which the RTI
+ * replies with a MSG_TYPE_ClockSyncT4 message. During the handshake, after the
+ * MSG_TYPE_UdpPort message, the RTI performs a number of exchanges over TCP if
+ * the federate has given a UDP port. During the execution, if clock
+ * synchronization is on, the RTI periodically performs one exchange over UDP,
+ * from the UDP socket bound to the same port as its TCP socket to the port
+ * given by the federate, and follows each MSG_TYPE_ClockSyncT4 message with a
+ * MSG_TYPE_ClockSyncCodedProbe message so that the federate can discard
+ * exchanges delayed by the network.
+ */
+pub const MSG_TYPE_CLOCK_SYNC_TIME_LENGTH: usize = 1 + std::mem::size_of::<i64>();
+
+/**
+ * Length of a MSG_TYPE_ClockSyncT3 message, with which a federate replies to a
+ * MSG_TYPE_ClockSyncT1 message.
+ *
+ * The next 4 bytes are the federate ID (little endian).
+ */
+pub const MSG_TYPE_CLOCK_SYNC_T3_LENGTH: usize = 1 + std::mem::size_of::<i32>();
+
+/**
+ * Length of the nonces exchanged for authentication.
+ */
+pub const NONCE_LENGTH: usize = 8;
+
+/**
+ * Length of an HMAC-SHA256 tag.
+ */
+pub const SHA256_HMAC_LENGTH: usize = 32;
+
+/**
+ * Length of a MSG_TYPE_FedNonce message, with which a federate starts its
+ * connection if the RTI authenticates federates.
+ *
+ * The next 2 bytes are the federate ID (little endian), and the next
+ * NONCE_LENGTH bytes are a random nonce of the federate.
+ *
+ * The RTI replies with a MSG_TYPE_RtiResponse message, which carries a random
+ * nonce of the RTI and the HMAC-SHA256 tag of the message type
+ * MSG_TYPE_RtiResponse, the federate ID, and the nonce of the federate. The
+ * federate replies with a MSG_TYPE_FedResponse message, which carries the
+ * HMAC-SHA256 tag of the message type MSG_TYPE_FedResponse and the nonce of
+ * the RTI. The key of both tags is the federation ID. Only then may the
+ * federate send its MSG_TYPE_FedIds message.
+ */
+pub const MSG_TYPE_FED_NONCE_LENGTH: usize = 1 + std::mem::size_of::<u16>() + NONCE_LENGTH;
+
+/**
+ * Length of a MSG_TYPE_RtiResponse message. See MSG_TYPE_FED_NONCE_LENGTH.
+ */
+pub const MSG_TYPE_RTI_RESPONSE_LENGTH: usize = 1 + NONCE_LENGTH + SHA256_HMAC_LENGTH;
+
+/**
+ * Length of a MSG_TYPE_FedResponse message. See MSG_TYPE_FED_NONCE_LENGTH.
+ */
+pub const MSG_TYPE_FED_RESPONSE_LENGTH: usize = 1 + SHA256_HMAC_LENGTH;
+
 #[derive(Debug)]
 pub enum MsgType {
     Reject,
@@ -102,8 +419,25 @@ pub enum MsgType {
     AddressQuery,
     P2pSendingFedId,
     P2pTaggedMessage,
+    ClockSyncT1,
+    ClockSyncT3,
+    ClockSyncT4,
+    ClockSyncCodedProbe,
     PortAbsent,
     NeighborStructure,
+    Failed,
+    DownstreamNextEventTag,
+    ControlChannel,
+    FederateMetadata,
+    StartTag,
+    Capabilities,
+    Resume,
+    ResumeToken,
+    Multiplex,
+    FederateName,
+    FedNonce,
+    RtiResponse,
+    FedResponse,
     Ignore,
     UdpPort,
     Ack,
@@ -127,8 +461,25 @@ impl MsgType {
             MsgType::AddressQuery => 13,
             MsgType::P2pSendingFedId => 15,
             MsgType::P2pTaggedMessage => 17,
+            MsgType::ClockSyncT1 => 19,
+            MsgType::ClockSyncT3 => 20,
+            MsgType::ClockSyncT4 => 21,
+            MsgType::ClockSyncCodedProbe => 22,
             MsgType::PortAbsent => 23,
             MsgType::NeighborStructure => 24,
+            MsgType::Failed => 25,
+            MsgType::DownstreamNextEventTag => 26,
+            MsgType::ControlChannel => 27,
+            MsgType::FederateMetadata => 28,
+            MsgType::StartTag => 30,
+            MsgType::Capabilities => 31,
+            MsgType::Resume => 32,
+            MsgType::ResumeToken => 33,
+            MsgType::Multiplex => 34,
+            MsgType::FederateName => 35,
+            MsgType::FedNonce => 100,
+            MsgType::RtiResponse => 101,
+            MsgType::FedResponse => 102,
             MsgType::Ignore => 250,
             MsgType::UdpPort => 254,
             MsgType::Ack => 255,
@@ -141,6 +492,7 @@ impl MsgType {
             4 => MsgType::Resign,
             5 => MsgType::TaggedMessage,
             6 => MsgType::NextEventTag,
+            7 => MsgType::TagAdvanceGrant,
             8 => MsgType::PropositionalTagAdvanceGrant,
             9 => MsgType::LogicalTagComplete,
             10 => MsgType::StopRequest,
@@ -148,6 +500,12 @@ impl MsgType {
             12 => MsgType::StopGranted,
             13 => MsgType::AddressQuery,
             23 => MsgType::PortAbsent,
+            25 => MsgType::Failed,
+            26 => MsgType::DownstreamNextEventTag,
+            27 => MsgType::ControlChannel,
+            28 => MsgType::FederateMetadata,
+            31 => MsgType::Capabilities,
+            35 => MsgType::FederateName,
             _ => MsgType::Ignore,
         }
     }
@@ -166,6 +524,15 @@ pub enum ErrType {
     FederateIdOutOfRange,
     UnexpectedMessage,
     WrongServer,
+    HmacDoesNotMatch,
+    RtiNotExecutedWithAuth,
+    ProtocolViolation,
+    MessageTooLarge,
+    FederateNameInUse,
+    ResumptionRefused,
+    /// The federation is draining before it stops (see the drain command of
+    /// the admin console) and admits no transient federate.
+    FederationDraining,
 }
 
 impl ErrType {
@@ -176,6 +543,13 @@ impl ErrType {
             ErrType::FederateIdOutOfRange => 3,
             ErrType::UnexpectedMessage => 4,
             ErrType::WrongServer => 5,
+            ErrType::HmacDoesNotMatch => 6,
+            ErrType::RtiNotExecutedWithAuth => 7,
+            ErrType::ProtocolViolation => 8,
+            ErrType::MessageTooLarge => 9,
+            ErrType::FederateNameInUse => 10,
+            ErrType::ResumptionRefused => 11,
+            ErrType::FederationDraining => 12,
         }
     }
 }
@@ -6,87 +6,236 @@
  * License in [BSD 2-clause](..)
  * @brief ..
  */
-use std::io::{Read, Write};
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
 use std::mem;
 use std::net::TcpStream;
+use std::time::Duration;
 
+use socket2::{SockRef, TcpKeepalive};
+
+use crate::federate::federate_label;
 use crate::tag::Tag;
+use crate::trace::{error, warn};
+use crate::transport::FederateStream;
+
+#[cfg(feature = "async")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/**
+ * Classification of a failed read from a socket.
+ */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ReadErrorKind {
+    /// The socket is non-blocking and no data is available.
+    WouldBlock,
+    /// The connection was closed (or reset) before any byte was read.
+    Closed,
+    /// The data cannot be interpreted, e.g., a message cut short by EOF.
+    Corrupt,
+    /// The read timeout of the socket expired.
+    Timeout,
+}
+
+#[derive(Debug)]
+pub struct ReadError {
+    kind: ReadErrorKind,
+    bytes_read: usize,
+}
+
+impl ReadError {
+    pub fn new(kind: ReadErrorKind, bytes_read: usize) -> ReadError {
+        ReadError { kind, bytes_read }
+    }
+
+    pub fn kind(&self) -> ReadErrorKind {
+        self.kind
+    }
+
+    /**
+     * The number of bytes consumed from the socket before the error occurred.
+     */
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self.kind {
+            ReadErrorKind::WouldBlock => "no data available",
+            ReadErrorKind::Closed => "connection closed",
+            ReadErrorKind::Corrupt => "corrupt or truncated message",
+            ReadErrorKind::Timeout => "read timed out",
+        };
+        write!(f, "{} after {} byte(s)", description, self.bytes_read())
+    }
+}
+
+/**
+ * Size of an encoded tag: an i64 time followed by a u32 microstep.
+ */
+const TAG_SIZE: usize = mem::size_of::<i64>() + mem::size_of::<u32>();
 
 pub struct NetUtil {}
 
 impl NetUtil {
-    pub fn read_from_stream_errexit(
-        stream: &mut TcpStream,
-        buffer: &mut Vec<u8>,
+    /**
+     * Read exactly `buffer.len()` bytes from the stream into the buffer.
+     * Return false after reporting the error if the read fails, so that the
+     * caller can close the connection.
+     */
+    pub fn read_from_stream<R: Read>(
+        stream: &mut R,
+        buffer: &mut [u8],
         fed_id: u16,
         err_msg: &str,
-    ) {
-        while match stream.read(buffer) {
-            Ok(..) => false,
-            Err(_) => {
-                println!("RTI failed to read {} from federate {}.", err_msg, fed_id);
-                // TODO: Implement similarly with rti_lib.c
-                std::process::exit(1);
-            }
-        } {}
-        // print!("  [[[ PACKET from {} ]]] = ", fed_id);
-        // for x in buffer {
-        //     print!("{:02X?} ", x);
-        // }
-        // println!("\n");
+    ) -> bool {
+        if let Err(e) = Self::read_from_socket(stream, buffer) {
+            error!(
+                "RTI failed to read {} from federate {}: {}.",
+                err_msg,
+                federate_label(fed_id),
+                e
+            );
+            return false;
+        }
+        true
     }
 
-    pub fn read_from_stream(stream: &mut TcpStream, buffer: &mut Vec<u8>, fed_id: u16) -> usize {
+    /**
+     * Read exactly `buffer.len()` bytes from the stream into the buffer.
+     * Return the number of bytes read, or a ReadError classifying the failure
+     * and recording how many bytes were consumed before it occurred.
+     */
+    pub fn read_from_socket<R: Read>(
+        stream: &mut R,
+        buffer: &mut [u8],
+    ) -> Result<usize, ReadError> {
         let mut bytes_read = 0;
-        while match stream.read(buffer) {
-            Ok(msg_size) => {
-                bytes_read = msg_size;
-                false
+        while bytes_read < buffer.len() {
+            Self::account_for_read(stream.read(&mut buffer[bytes_read..]), &mut bytes_read)?;
+        }
+        Ok(bytes_read)
+    }
+
+    /**
+     * Add the result of one read call to `bytes_read`, or classify the
+     * failure. Shared by the sync and async read helpers.
+     */
+    fn account_for_read(
+        result: io::Result<usize>,
+        bytes_read: &mut usize,
+    ) -> Result<(), ReadError> {
+        match result {
+            Ok(0) => {
+                // A message cut short by EOF cannot be interpreted.
+                let kind = if *bytes_read == 0 {
+                    ReadErrorKind::Closed
+                } else {
+                    ReadErrorKind::Corrupt
+                };
+                Err(ReadError::new(kind, *bytes_read))
             }
-            Err(_) => {
-                println!("ERROR reading from the stream of federate {}.", fed_id);
-                // TODO: Implement similarly with rti_lib.c
-                false
+            Ok(size) => {
+                *bytes_read += size;
+                Ok(())
             }
-        } {}
-        // print!("  [[[ BUFFER from {} ]]] = ", fed_id);
-        // for x in buffer {
-        //     print!("{:02X?} ", x);
-        // }
-        // println!("\n");
-        bytes_read
+            Err(e) => {
+                let kind = match e.kind() {
+                    ErrorKind::Interrupted => return Ok(()),
+                    ErrorKind::WouldBlock => ReadErrorKind::WouldBlock,
+                    ErrorKind::TimedOut => ReadErrorKind::Timeout,
+                    ErrorKind::InvalidData => ReadErrorKind::Corrupt,
+                    _ => ReadErrorKind::Closed,
+                };
+                Err(ReadError::new(kind, *bytes_read))
+            }
+        }
+    }
+
+    /**
+     * Read a little-endian i64, e.g., a timestamp.
+     */
+    pub fn read_from_socket_i64<R: Read>(stream: &mut R) -> Result<i64, ReadError> {
+        let mut buffer = [0 as u8; mem::size_of::<i64>()];
+        Self::read_from_socket(stream, &mut buffer)?;
+        Ok(i64::from_le_bytes(buffer))
+    }
+
+    /**
+     * Read a tag encoded as a little-endian i64 time followed by a
+     * little-endian u32 microstep.
+     */
+    pub fn read_from_socket_tag<R: Read>(stream: &mut R) -> Result<Tag, ReadError> {
+        let mut buffer = [0 as u8; TAG_SIZE];
+        Self::read_from_socket(stream, &mut buffer)?;
+        Ok(Self::extract_tag(&buffer))
     }
 
-    pub fn write_to_stream_errexit(
-        mut stream: &TcpStream,
-        buffer: &Vec<u8>,
+    /**
+     * Write the whole buffer, blocking while the federate's receive buffer is
+     * full, and exit if the write fails.
+     */
+    pub fn write_to_stream_errexit<W: Write>(
+        mut stream: W,
+        buffer: &[u8],
         fed_id: u16,
         err_msg: &str,
     ) {
-        match stream.write(&buffer) {
+        match stream.write_all(buffer) {
             Ok(..) => {}
             Err(_e) => {
-                println!("RTI failed to write {} to federate {}.", err_msg, fed_id);
+                error!(
+                    "RTI failed to write {} to federate {}.",
+                    err_msg,
+                    federate_label(fed_id)
+                );
                 // TODO: Implement similarly with rti_lib.c
                 std::process::exit(1);
             }
         }
     }
 
-    pub fn write_to_stream(mut stream: &TcpStream, buffer: &Vec<u8>, fed_id: u16) -> usize {
+    pub fn write_to_stream(mut stream: &FederateStream, buffer: &Vec<u8>, fed_id: u16) -> usize {
         let mut bytes_written = 0;
         match stream.write(&buffer) {
             Ok(bytes_size) => {
                 bytes_written = bytes_size;
             }
             Err(_e) => {
-                println!("ERROR writing to the stream of federate {}.", fed_id);
+                error!(
+                    "ERROR writing to the stream of federate {}.",
+                    federate_label(fed_id)
+                );
                 // TODO: Implement similarly with rti_lib.c
             }
         }
         bytes_written
     }
 
+    /**
+     * Enable TCP keepalive on the stream, so that a read from it fails once
+     * the connection has been idle for the given number of seconds and
+     * KEEPALIVE_PROBES probes sent that many seconds apart are not answered.
+     * Where the interval and the number of probes cannot be set, the ones of
+     * the operating system are used.
+     */
+    pub fn set_keepalive(stream: &TcpStream, idle_s: u64) -> io::Result<()> {
+        let idle = Duration::from_secs(idle_s);
+        let keepalive = TcpKeepalive::new().with_time(idle);
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_vendor = "apple"
+        ))]
+        let keepalive = keepalive
+            .with_interval(idle)
+            .with_retries(crate::constants::KEEPALIVE_PROBES);
+        SockRef::from(stream).set_tcp_keepalive(&keepalive)
+    }
+
     /**
      * Write the specified data as a sequence of bytes starting
      * at the specified address. This encodes the data in little-endian
@@ -149,9 +298,10 @@ impl NetUtil {
                 .unwrap(),
         );
         if local_length_signed < 0 {
-            println!(
+            warn!(
                 "Received an invalid message length ({}) from federate {}.",
-                local_length_signed, *federate_id
+                local_length_signed,
+                federate_label(*federate_id)
             );
             // FIXME: Replace return to exit.
             return;
@@ -175,3 +325,60 @@ impl NetUtil {
         Tag::new(time, microstep)
     }
 }
+
+/**
+ * Async variants of the socket helpers for transports built on tokio. They
+ * share the error classification and the encoding with the sync helpers, so
+ * only the way bytes are moved differs between the two paths.
+ */
+#[cfg(feature = "async")]
+impl NetUtil {
+    /**
+     * Read exactly `buffer.len()` bytes, like NetUtil::read_from_socket.
+     */
+    pub async fn read_from_socket_async<R: AsyncRead + Unpin>(
+        stream: &mut R,
+        buffer: &mut [u8],
+    ) -> Result<usize, ReadError> {
+        let mut bytes_read = 0;
+        while bytes_read < buffer.len() {
+            Self::account_for_read(
+                stream.read(&mut buffer[bytes_read..]).await,
+                &mut bytes_read,
+            )?;
+        }
+        Ok(bytes_read)
+    }
+
+    /**
+     * Read a little-endian i64, like NetUtil::read_from_socket_i64.
+     */
+    pub async fn read_from_socket_i64_async<R: AsyncRead + Unpin>(
+        stream: &mut R,
+    ) -> Result<i64, ReadError> {
+        let mut buffer = [0 as u8; mem::size_of::<i64>()];
+        Self::read_from_socket_async(stream, &mut buffer).await?;
+        Ok(i64::from_le_bytes(buffer))
+    }
+
+    /**
+     * Read a tag, like NetUtil::read_from_socket_tag.
+     */
+    pub async fn read_from_socket_tag_async<R: AsyncRead + Unpin>(
+        stream: &mut R,
+    ) -> Result<Tag, ReadError> {
+        let mut buffer = [0 as u8; TAG_SIZE];
+        Self::read_from_socket_async(stream, &mut buffer).await?;
+        Ok(Self::extract_tag(&buffer))
+    }
+
+    /**
+     * Write the whole buffer to the stream.
+     */
+    pub async fn write_to_socket_async<W: AsyncWrite + Unpin>(
+        stream: &mut W,
+        buffer: &[u8],
+    ) -> io::Result<()> {
+        stream.write_all(buffer).await
+    }
+}
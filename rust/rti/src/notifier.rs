@@ -0,0 +1,540 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Queues of grants that have been decided and the thread that writes
+ * them to the federates.
+ */
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Write};
+use std::mem;
+use std::net::Shutdown;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::fault::Fault;
+use crate::federate::federate_label;
+use crate::lock_stats::{lock_rti, read_rti};
+use crate::outgoing::{OutgoingGuard, OutgoingLock};
+use crate::resumption;
+use crate::tag::{Tag, FOREVER};
+use crate::trace::error;
+use crate::transport::FederateStream;
+use crate::wire_dump::{Direction, WireDump};
+use crate::FedState;
+use crate::FederationRTI;
+
+/**
+ * Position of a grant in the queue of its federate: its tag, then a PTAG
+ * before a TAG for the same tag.
+ */
+type GrantKey = (i64, u32, bool);
+
+/**
+ * What the notifier does when the socket of a federate does not take a grant
+ * because its send buffer is full, e.g., because the federate stopped reading.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum GrantWritePolicy {
+    /// Wait until the socket takes the grant. Meanwhile, no grant is written
+    /// to any other federate.
+    Block,
+    /// Write the grants from a thread of the federate, which lets up to the
+    /// given number of grants wait while it is blocked. The federate is
+    /// disconnected if more are waiting.
+    Buffer(usize),
+    /// Wait at most the given number of milliseconds for the socket, and then
+    /// disconnect the federate.
+    Timeout(u64),
+    /// Write the grants from a thread of the federate, which, while it is
+    /// blocked, keeps waiting only the grants that no later grant supersedes:
+    /// the latest TAG and the latest PTAG after it.
+    Supersede,
+}
+
+/**
+ * A TAG or PTAG that has been decided and encoded, and is waiting to be
+ * written to the federate.
+ */
+pub struct QueuedGrant {
+    buffer: Vec<u8>,
+    fault: Option<Fault>,
+    stream: FederateStream,
+    outgoing_lock: Arc<OutgoingLock>,
+    wire_dump: Option<Arc<WireDump>>,
+}
+
+impl QueuedGrant {
+    pub fn new(
+        buffer: Vec<u8>,
+        fault: Option<Fault>,
+        stream: FederateStream,
+        outgoing_lock: Arc<OutgoingLock>,
+        wire_dump: Option<Arc<WireDump>>,
+    ) -> QueuedGrant {
+        QueuedGrant {
+            buffer,
+            fault,
+            stream,
+            outgoing_lock,
+            wire_dump,
+        }
+    }
+}
+
+/**
+ * Grants of a federate in tag order with their positions in its queue.
+ */
+type KeyedGrants = Vec<(GrantKey, QueuedGrant)>;
+
+/**
+ * Stream to which a batch of grants is written, with its outgoing lock and
+ * wire dump.
+ */
+type BatchTarget = (FederateStream, Arc<OutgoingLock>, Option<Arc<WireDump>>);
+
+#[derive(Default)]
+struct GrantQueues {
+    queues: BTreeMap<u16, BTreeMap<GrantKey, QueuedGrant>>,
+    // Key of the last TAG written to each federate.
+    last_written: BTreeMap<u16, GrantKey>,
+    stopped: bool,
+}
+
+impl GrantQueues {
+    /**
+     * Take the grants that may be written now, in tag order per federate. A
+     * grant that is not later than a TAG already written to the federate is
+     * dropped. If `pacing` gives a maximum lead and the physical time, a grant
+     * whose tag is further ahead of the physical time is held back together
+     * with the later grants of its federate. Return the grants with the
+     * earliest physical time at which a held grant may be written.
+     */
+    fn take_due(&mut self, pacing: Option<(i64, i64)>) -> (Vec<(u16, KeyedGrants)>, Option<i64>) {
+        let mut batches = Vec::new();
+        let mut next_due: Option<i64> = None;
+        for (fed_id, queue) in self.queues.iter_mut() {
+            let mut grants = Vec::new();
+            while let Some(entry) = queue.first_entry() {
+                let key = *entry.key();
+                if let Some((max_lead, now)) = pacing {
+                    let due = key.0.saturating_sub(max_lead);
+                    if key.0 != FOREVER && due > now {
+                        next_due = Some(next_due.map_or(due, |next_due| next_due.min(due)));
+                        break;
+                    }
+                }
+                let grant = entry.remove();
+                let last_written = self.last_written.get(fed_id);
+                if last_written.is_some_and(|last| (key.0, key.1) <= (last.0, last.1)) {
+                    continue;
+                }
+                if key.2 {
+                    self.last_written.insert(*fed_id, key);
+                }
+                grants.push((key, grant));
+            }
+            if !grants.is_empty() {
+                batches.push((*fed_id, grants));
+            }
+        }
+        self.queues.retain(|_, queue| !queue.is_empty());
+        (batches, next_due)
+    }
+}
+
+#[derive(Default)]
+struct PendingGrants {
+    grants: KeyedGrants,
+    // Whether the writer thread is writing grants that it has taken.
+    writing: bool,
+    // Whether the federate has been disconnected while the writer thread was
+    // writing, so that its failure is not reported again.
+    disconnected: bool,
+    stopped: bool,
+}
+
+/**
+ * Grants of a federate with the Buffer or Supersede policy, which are written
+ * by a thread of the federate so that the notifier thread never waits for its
+ * socket.
+ */
+#[derive(Default)]
+struct FederateWriter {
+    pending: Mutex<PendingGrants>,
+    queued: Condvar,
+}
+
+/**
+ * Writer of a federate with the handle of its thread.
+ */
+type WriterThread = (Arc<FederateWriter>, thread::JoinHandle<()>);
+
+impl FederateWriter {
+    /**
+     * Let grants taken from the queue of the federate wait for its writer
+     * thread according to the policy. Return false if the Buffer policy
+     * disconnects the federate, in which case the grants are dropped.
+     */
+    fn hand_over(&self, grants: KeyedGrants, policy: GrantWritePolicy) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.disconnected {
+            return true;
+        }
+        pending.grants.extend(grants);
+        match policy {
+            GrantWritePolicy::Buffer(max_grants)
+                if pending.writing && pending.grants.len() > max_grants =>
+            {
+                // Disconnecting the federate releases the blocked writer thread.
+                pending.grants.clear();
+                pending.disconnected = true;
+                return false;
+            }
+            GrantWritePolicy::Supersede => {
+                // A TAG supersedes all earlier grants, and a PTAG the earlier
+                // PTAGs.
+                if let Some(last_tag) = pending.grants.iter().rposition(|(key, _)| key.2) {
+                    pending.grants.drain(..last_tag);
+                }
+                let first_ptag = usize::from(pending.grants.first().is_some_and(|(key, _)| key.2));
+                if pending.grants.len() > first_ptag + 1 {
+                    let last_ptag = pending.grants.len() - 1;
+                    pending.grants.drain(first_ptag..last_ptag);
+                }
+            }
+            _ => {}
+        }
+        self.queued.notify_one();
+        true
+    }
+
+    /**
+     * Make the writer thread exit once it has written all waiting grants.
+     */
+    fn stop(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.stopped = true;
+        self.queued.notify_one();
+    }
+
+    fn start_writer_thread(
+        writer: Arc<FederateWriter>,
+        fed_id: u16,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            let grants = {
+                let mut pending = writer.pending.lock().unwrap();
+                while pending.grants.is_empty() && !pending.stopped {
+                    pending = writer.queued.wait(pending).unwrap();
+                }
+                if pending.grants.is_empty() {
+                    return;
+                }
+                pending.writing = true;
+                mem::take(&mut pending.grants)
+            };
+            let written = GrantNotifier::write_grants(fed_id, grants, None);
+            let mut pending = writer.pending.lock().unwrap();
+            pending.writing = false;
+            if mem::take(&mut pending.disconnected) {
+                pending.grants.clear();
+            } else if let Err(e) = written {
+                drop(pending);
+                GrantNotifier::handle_write_failure(&_f_rti, fed_id, GrantWritePolicy::Block, &e);
+            }
+        })
+    }
+}
+
+/**
+ * Per-federate queues of grants, ordered by tag. The scheduling threads decide
+ * grants while holding the lock of the RTI state and only queue them here; the
+ * notifier thread writes them to the sockets without holding that lock.
+ */
+#[derive(Default)]
+pub struct GrantNotifier {
+    queues: Mutex<GrantQueues>,
+    queued: Condvar,
+}
+
+impl GrantNotifier {
+    pub fn new() -> GrantNotifier {
+        GrantNotifier::default()
+    }
+
+    /**
+     * Queue a grant for the federate. Grants are decided in tag order, so a
+     * grant replaces a queued one only if it sends the same message.
+     */
+    pub fn enqueue(&self, fed_id: u16, tag: &Tag, provisional: bool, grant: QueuedGrant) {
+        let mut queues = self.queues.lock().unwrap();
+        queues
+            .queues
+            .entry(fed_id)
+            .or_default()
+            .insert((tag.time(), tag.microstep(), !provisional), grant);
+        self.queued.notify_one();
+    }
+
+    /**
+     * Forget the last TAG written to the federate, so that the next grant to
+     * it is written even if it is not later, e.g., to send a grant lost with a
+     * previous connection again.
+     */
+    pub fn forget_written(&self, fed_id: u16) {
+        self.queues.lock().unwrap().last_written.remove(&fed_id);
+    }
+
+    /**
+     * Make the notifier thread exit once it has written all queued grants.
+     */
+    pub fn stop(&self) {
+        let mut queues = self.queues.lock().unwrap();
+        queues.stopped = true;
+        self.queued.notify_one();
+    }
+
+    /**
+     * Start the thread that writes the queued grants. The grants queued for a
+     * federate at once are written in tag order with a single write unless a
+     * fault is injected into one of them. A grant that is not later than a TAG
+     * already written to the federate is dropped, so the federate never sees
+     * its grants go backwards. With a maximum lead (see
+     * FederationRTI::max_lead), grants are held back until they are no further
+     * ahead of physical time, except once the notifier is stopped. The grants
+     * of federates with the Buffer or Supersede policy (see
+     * FederationRTI::grant_write_policy) are handed over to a thread of the
+     * federate instead.
+     */
+    pub fn start_notifier_thread(
+        notifier: Arc<GrantNotifier>,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let (pacing, policies) = {
+                let locked_rti = read_rti(&_f_rti);
+                let pacing = locked_rti
+                    .max_lead()
+                    .map(|max_lead| (max_lead, locked_rti.physical_clock()));
+                let number_of_federates = locked_rti.number_of_enclaves() as u16;
+                let policies: Vec<GrantWritePolicy> = (0..number_of_federates)
+                    .map(|fed_id| locked_rti.grant_write_policy(fed_id))
+                    .collect();
+                (pacing, policies)
+            };
+            let mut writers: BTreeMap<u16, WriterThread> = BTreeMap::new();
+            loop {
+                let batches = {
+                    let mut queues = notifier.queues.lock().unwrap();
+                    loop {
+                        let now = match &pacing {
+                            Some((max_lead, clock)) if !queues.stopped => {
+                                Some((*max_lead, clock.now()))
+                            }
+                            _ => None,
+                        };
+                        let (batches, next_due) = queues.take_due(now);
+                        if !batches.is_empty() {
+                            break Some(batches);
+                        }
+                        if queues.queues.is_empty() && queues.stopped {
+                            break None;
+                        }
+                        queues = match (next_due, now) {
+                            (Some(next_due), Some((_, now))) => {
+                                let timeout = Duration::from_nanos((next_due - now) as u64);
+                                let timeout = match &pacing {
+                                    Some((_, clock)) => clock.poll_timeout(timeout),
+                                    None => timeout,
+                                };
+                                notifier.queued.wait_timeout(queues, timeout).unwrap().0
+                            }
+                            _ => notifier.queued.wait(queues).unwrap(),
+                        };
+                    }
+                };
+                let Some(batches) = batches else {
+                    break;
+                };
+                for (fed_id, grants) in batches {
+                    let policy = policies
+                        .get(usize::from(fed_id))
+                        .copied()
+                        .unwrap_or(GrantWritePolicy::Block);
+                    let timeout = match policy {
+                        GrantWritePolicy::Block => None,
+                        GrantWritePolicy::Timeout(ms) => Some(Duration::from_millis(ms)),
+                        GrantWritePolicy::Buffer(_) | GrantWritePolicy::Supersede => {
+                            let (writer, _) = writers.entry(fed_id).or_insert_with(|| {
+                                let writer = Arc::new(FederateWriter::default());
+                                let handle = FederateWriter::start_writer_thread(
+                                    writer.clone(),
+                                    fed_id,
+                                    _f_rti.clone(),
+                                );
+                                (writer, handle)
+                            });
+                            if !writer.hand_over(grants, policy) {
+                                error!(
+                                    "RTI disconnected federate {} because too many grants were waiting to be written to it.",
+                                    federate_label(fed_id)
+                                );
+                                Self::disconnect(&_f_rti, fed_id);
+                            }
+                            continue;
+                        }
+                    };
+                    if let Err(e) = Self::write_grants(fed_id, grants, timeout) {
+                        Self::handle_write_failure(&_f_rti, fed_id, policy, &e);
+                    }
+                }
+            }
+            for (writer, _) in writers.values() {
+                writer.stop();
+            }
+            for (_, (_, handle)) in writers {
+                handle.join().unwrap();
+            }
+        })
+    }
+
+    fn handle_write_failure(
+        _f_rti: &Arc<RwLock<FederationRTI>>,
+        fed_id: u16,
+        policy: GrantWritePolicy,
+        e: &io::Error,
+    ) {
+        if let (GrantWritePolicy::Timeout(ms), true) = (policy, is_timeout(e)) {
+            error!(
+                "RTI disconnected federate {} because it did not take a grant within {} ms.",
+                federate_label(fed_id),
+                ms
+            );
+            Self::disconnect(_f_rti, fed_id);
+            return;
+        }
+        let mut locked_rti = lock_rti(_f_rti);
+        if resumption::suspend_federate(&mut locked_rti, fed_id, &e.to_string()) {
+            // The last grant is sent again when the federate resumes.
+            return;
+        }
+        error!(
+            "RTI failed to send tag advance grant to federate {}.",
+            federate_label(fed_id)
+        );
+        // FIXME: We need better error handling, but don't stop other execution here.
+        locked_rti.enclaves()[usize::from(fed_id)]
+            .enclave()
+            .set_state(FedState::NotConnected);
+    }
+
+    /**
+     * Disconnect a federate that does not take its grants. Its connections are
+     * shut down, so that the thread serving it handles it as a failed
+     * federate.
+     */
+    fn disconnect(_f_rti: &Arc<RwLock<FederationRTI>>, fed_id: u16) {
+        let mut locked_rti = lock_rti(_f_rti);
+        let fed = &mut locked_rti.enclaves()[usize::from(fed_id)];
+        for stream in [fed.stream(), fed.control_stream()].into_iter().flatten() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        fed.enclave().set_state(FedState::NotConnected);
+    }
+
+    /**
+     * Write grants to a federate, waiting at most the given time for its
+     * socket to take each write.
+     */
+    fn write_grants(fed_id: u16, grants: KeyedGrants, timeout: Option<Duration>) -> io::Result<()> {
+        let mut batch: Vec<u8> = Vec::new();
+        let mut target: Option<BatchTarget> = None;
+        for (_, mut grant) in grants {
+            let Some(fault) = grant.fault else {
+                batch.extend_from_slice(&grant.buffer);
+                target = Some((grant.stream, grant.outgoing_lock, grant.wire_dump));
+                continue;
+            };
+            // Write the batch first to keep the grants in order.
+            Self::write_batch(&mut batch, target.take(), timeout)?;
+            let (_outgoing_guard, timeout) = lock_outgoing(&grant.outgoing_lock, timeout)?;
+            let send_now = fault.apply(
+                &mut grant.buffer,
+                fed_id,
+                &grant.stream,
+                grant.outgoing_lock.clone(),
+            );
+            if send_now {
+                write_with_timeout(&mut grant.stream, &grant.buffer, timeout)?;
+                if let Some(wire_dump) = &grant.wire_dump {
+                    wire_dump.record(Direction::Sent, &grant.buffer);
+                }
+            }
+        }
+        Self::write_batch(&mut batch, target, timeout)
+    }
+
+    fn write_batch(
+        batch: &mut Vec<u8>,
+        target: Option<BatchTarget>,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        let Some((mut stream, outgoing_lock, wire_dump)) = target else {
+            return Ok(());
+        };
+        let written = lock_outgoing(&outgoing_lock, timeout)
+            .and_then(|(_outgoing_guard, timeout)| write_with_timeout(&mut stream, batch, timeout));
+        if let (Ok(()), Some(wire_dump)) = (&written, &wire_dump) {
+            wire_dump.record(Direction::Sent, batch);
+        }
+        batch.clear();
+        written
+    }
+}
+
+/**
+ * Acquire the outgoing lock of a federate, waiting at most the given time,
+ * because a relayed message holds it while the federate does not read. Return
+ * the guard with the time left of the timeout.
+ */
+fn lock_outgoing(
+    outgoing_lock: &Arc<OutgoingLock>,
+    timeout: Option<Duration>,
+) -> io::Result<(OutgoingGuard, Option<Duration>)> {
+    let turn = outgoing_lock.reserve();
+    let Some(timeout) = timeout else {
+        return Ok((turn.wait(), None));
+    };
+    match turn.wait_timeout(timeout) {
+        Some((guard, left)) if !left.is_zero() => Ok((guard, Some(left))),
+        _ => Err(io::Error::from(ErrorKind::TimedOut)),
+    }
+}
+
+/**
+ * Write a buffer to a stream while holding its outgoing lock, so that the
+ * write timeout, which applies to the whole socket, is only set for this
+ * write.
+ */
+fn write_with_timeout(
+    stream: &mut FederateStream,
+    buffer: &[u8],
+    timeout: Option<Duration>,
+) -> io::Result<()> {
+    if timeout.is_none() {
+        return stream.write_all(buffer);
+    }
+    stream.set_write_timeout(timeout)?;
+    let written = stream.write_all(buffer);
+    let _ = stream.set_write_timeout(None);
+    written
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
@@ -0,0 +1,263 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Lock that orders the writes to a connection of a federate by the
+ * time at which the writers reserved their turn.
+ */
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fault::Fault;
+use crate::net_util::NetUtil;
+use crate::transport::FederateStream;
+use crate::wire_dump::{Direction, WireDump};
+use crate::Federate;
+
+#[derive(Default)]
+struct Turns {
+    // Ticket of the next turn to be reserved.
+    next: u64,
+    // Ticket of the turn that may write now.
+    serving: u64,
+    // Tickets of turns given up before they came, which are skipped.
+    abandoned: BTreeSet<u64>,
+}
+
+/**
+ * Lock held while writing a message to a connection of a federate, so that a
+ * message streamed in chunks is not interleaved with other messages. Writers
+ * are served in the order in which they reserved their turn. Reserving a turn
+ * never waits, so a writer reserves it while holding the lock of the RTI
+ * state, which fixes the order of its message relative to the decisions made
+ * under that lock, and waits for the turn only after releasing that lock.
+ * Thus no thread holding the lock of the RTI state waits for a message that is
+ * being relayed to a federate that is not reading.
+ */
+#[derive(Default)]
+pub struct OutgoingLock {
+    turns: Mutex<Turns>,
+    served: Condvar,
+}
+
+impl OutgoingLock {
+    pub fn new() -> Arc<OutgoingLock> {
+        Arc::new(OutgoingLock::default())
+    }
+
+    /**
+     * Reserve the next turn to write, after all turns reserved so far.
+     */
+    pub fn reserve(self: &Arc<Self>) -> OutgoingTurn {
+        let mut turns = self.turns.lock().unwrap();
+        let ticket = turns.next;
+        turns.next += 1;
+        OutgoingTurn {
+            lock: Some(self.clone()),
+            ticket,
+        }
+    }
+
+    /**
+     * Reserve the next turn and wait for it.
+     */
+    pub fn lock(self: &Arc<Self>) -> OutgoingGuard {
+        self.reserve().wait()
+    }
+
+    /**
+     * Return the guard if no other writer holds or waits for the lock.
+     */
+    pub fn try_lock(self: &Arc<Self>) -> Option<OutgoingGuard> {
+        let mut turns = self.turns.lock().unwrap();
+        if turns.next != turns.serving {
+            return None;
+        }
+        turns.next += 1;
+        Some(OutgoingGuard { lock: self.clone() })
+    }
+
+    /**
+     * Pass the lock on to the next turn that has not been given up.
+     */
+    fn advance(turns: &mut Turns) {
+        turns.serving += 1;
+        while turns.abandoned.remove(&turns.serving) {
+            turns.serving += 1;
+        }
+    }
+}
+
+/**
+ * A reserved turn to write. Dropping it without waiting gives it up.
+ */
+pub struct OutgoingTurn {
+    lock: Option<Arc<OutgoingLock>>,
+    ticket: u64,
+}
+
+impl OutgoingTurn {
+    /**
+     * Return true if the turn has come, so that wait returns at once.
+     */
+    pub fn is_ready(&self) -> bool {
+        let lock = self.lock.as_ref().unwrap();
+        lock.turns.lock().unwrap().serving == self.ticket
+    }
+
+    /**
+     * Wait until the turn has come.
+     */
+    pub fn wait(mut self) -> OutgoingGuard {
+        let lock = self.lock.take().unwrap();
+        let mut turns = lock.turns.lock().unwrap();
+        while turns.serving != self.ticket {
+            turns = lock.served.wait(turns).unwrap();
+        }
+        drop(turns);
+        OutgoingGuard { lock }
+    }
+
+    /**
+     * Write the message to the stream in this turn: at once if the turn has
+     * come, and otherwise from a thread that waits for it, so that a caller
+     * holding the lock of the RTI state does not wait for a message that is
+     * being relayed to the federate. A deferred write that fails is dropped,
+     * since the thread serving the federate handles the closed connection.
+     */
+    pub fn write(
+        self,
+        stream: &FederateStream,
+        buffer: &[u8],
+        wire_dump: &Option<Arc<WireDump>>,
+    ) -> io::Result<()> {
+        if self.is_ready() {
+            let _outgoing_guard = self.wait();
+            (&*stream).write_all(buffer)?;
+            if let Some(wire_dump) = wire_dump {
+                wire_dump.record(Direction::Sent, buffer);
+            }
+            return Ok(());
+        }
+        let mut stream = stream.try_clone()?;
+        let buffer = buffer.to_vec();
+        let wire_dump = wire_dump.clone();
+        thread::spawn(move || {
+            let _outgoing_guard = self.wait();
+            if stream.write_all(&buffer).is_ok() {
+                if let Some(wire_dump) = wire_dump {
+                    wire_dump.record(Direction::Sent, &buffer);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /**
+     * Wait at most the given time for the turn to come. Return the guard with
+     * the time left, or None if the turn did not come in time, in which case
+     * it is given up.
+     */
+    pub fn wait_timeout(mut self, timeout: Duration) -> Option<(OutgoingGuard, Duration)> {
+        let deadline = Instant::now() + timeout;
+        let lock = self.lock.take().unwrap();
+        let mut turns = lock.turns.lock().unwrap();
+        while turns.serving != self.ticket {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                turns.abandoned.insert(self.ticket);
+                return None;
+            }
+            turns = lock.served.wait_timeout(turns, left).unwrap().0;
+        }
+        drop(turns);
+        let left = deadline.saturating_duration_since(Instant::now());
+        Some((OutgoingGuard { lock }, left))
+    }
+}
+
+impl Drop for OutgoingTurn {
+    fn drop(&mut self) {
+        let Some(lock) = self.lock.take() else {
+            return;
+        };
+        let mut turns = lock.turns.lock().unwrap();
+        if turns.serving == self.ticket {
+            OutgoingLock::advance(&mut turns);
+            lock.served.notify_all();
+        } else {
+            turns.abandoned.insert(self.ticket);
+        }
+    }
+}
+
+/**
+ * The turn of a writer that has come. Dropping it passes the lock on.
+ */
+pub struct OutgoingGuard {
+    lock: Arc<OutgoingLock>,
+}
+
+impl Drop for OutgoingGuard {
+    fn drop(&mut self) {
+        let mut turns = self.lock.turns.lock().unwrap();
+        OutgoingLock::advance(&mut turns);
+        self.lock.served.notify_all();
+    }
+}
+
+/**
+ * A message to a federate whose turn to be written is reserved while holding
+ * the lock of the RTI state, with what is needed to write it after releasing
+ * that lock.
+ */
+pub struct ReservedWrite {
+    fed_id: u16,
+    stream: FederateStream,
+    outgoing_lock: Arc<OutgoingLock>,
+    outgoing_turn: OutgoingTurn,
+    wire_dump: Option<Arc<WireDump>>,
+}
+
+impl ReservedWrite {
+    /**
+     * Reserve the turn to write to the stream of the federate, or return None
+     * if the federate has no stream.
+     */
+    pub fn reserve(fed: &Federate) -> Option<ReservedWrite> {
+        let stream = fed.stream().as_ref()?.try_clone().ok()?;
+        let outgoing_lock = fed.outgoing_lock();
+        let outgoing_turn = outgoing_lock.reserve();
+        Some(ReservedWrite {
+            fed_id: fed.e().id(),
+            stream,
+            outgoing_lock,
+            outgoing_turn,
+            wire_dump: fed.wire_dump().clone(),
+        })
+    }
+
+    /**
+     * Wait for the turn and write the message, injecting the given fault, if
+     * any. Exit if the write fails, like NetUtil::write_to_stream_errexit.
+     * Return true if the message was written now.
+     */
+    pub fn write(self, mut buffer: Vec<u8>, fault: Option<Fault>, err_msg: &str) -> bool {
+        let _outgoing_guard = self.outgoing_turn.wait();
+        if !fault.is_none_or(|fault| {
+            fault.apply(&mut buffer, self.fed_id, &self.stream, self.outgoing_lock)
+        }) {
+            return false;
+        }
+        NetUtil::write_to_stream_errexit(&self.stream, &buffer, self.fed_id, err_msg);
+        if let Some(wire_dump) = &self.wire_dump {
+            wire_dump.record(Direction::Sent, &buffer);
+        }
+        true
+    }
+}
@@ -0,0 +1,340 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Experimental hierarchical coordination, in which the RTI of a
+ * partition of a federation connects to a parent RTI as one of its federates.
+ *
+ * Toward the parent RTI, the partition is a single federate whose neighbors
+ * are the other partitions given with --parent_upstream and
+ * --parent_downstream. The RTI proposes the latest start time proposed by its
+ * federates and starts them at the start time decided by the parent RTI. It
+ * sends the parent RTI the earliest NET and the earliest LTC of its connected
+ * federates, and resigns once all of them have exited. Its federates are not
+ * granted beyond the latest tag granted by the parent RTI.
+ *
+ * Tagged messages between partitions are not forwarded, and stop requests are
+ * not propagated across partitions: a stop request of the parent RTI is
+ * accepted at the requested tag, but the federates of the partition are not
+ * asked to stop.
+ */
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+
+use crate::enclave::Enclave;
+use crate::lock_stats::lock_rti;
+use crate::net_common::*;
+use crate::net_util::NetUtil;
+use crate::tag::{self, Instant, Interval, StartTime, Tag};
+use crate::trace::{debug, error, info, warn};
+use crate::FedState;
+use crate::FederationRTI;
+
+/**
+ * What the RTI has reported to the parent RTI so far.
+ */
+struct Reported {
+    next_event: Tag,
+    completed: Tag,
+    resigned: bool,
+}
+
+/**
+ * Connection of the RTI to its parent RTI.
+ */
+#[derive(Clone)]
+pub struct ParentLink {
+    stream: Arc<Mutex<TcpStream>>,
+    reported: Arc<Mutex<Reported>>,
+    listener: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
+}
+
+impl ParentLink {
+    /**
+     * Connect to the parent RTI at the given address as the federate with the
+     * given ID, and perform the handshake up to the proposal of the start
+     * time.
+     */
+    pub fn connect(
+        address: &str,
+        fed_id: u16,
+        federation_id: &str,
+        upstream: &[(u16, Interval)],
+        downstream: &[u16],
+    ) -> io::Result<ParentLink> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        let mut buffer = vec![MsgType::FedIds.to_byte()];
+        buffer.extend_from_slice(&fed_id.to_le_bytes());
+        buffer.push(federation_id.len() as u8);
+        buffer.extend_from_slice(federation_id.as_bytes());
+        stream.write_all(&buffer)?;
+        let mut response = [0u8; 1];
+        stream.read_exact(&mut response)?;
+        if response[0] != MsgType::Ack.to_byte() {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("the parent RTI rejected federate ID {}", fed_id),
+            ));
+        }
+
+        let mut buffer = vec![MsgType::NeighborStructure.to_byte()];
+        buffer.extend_from_slice(&(upstream.len() as i32).to_le_bytes());
+        buffer.extend_from_slice(&(downstream.len() as i32).to_le_bytes());
+        for (id, delay) in upstream {
+            buffer.extend_from_slice(&id.to_le_bytes());
+            buffer.extend_from_slice(&delay.unwrap_or(tag::NEVER).to_le_bytes());
+        }
+        for id in downstream {
+            buffer.extend_from_slice(&id.to_le_bytes());
+        }
+        stream.write_all(&buffer)?;
+
+        // The partition does not synchronize its clock with the parent RTI.
+        let mut buffer = vec![MsgType::UdpPort.to_byte()];
+        buffer.extend_from_slice(&u16::MAX.to_le_bytes());
+        stream.write_all(&buffer)?;
+
+        info!(
+            "RTI: Connected to the parent RTI at {} as federate {}.",
+            address, fed_id
+        );
+        Ok(ParentLink {
+            stream: Arc::new(Mutex::new(stream)),
+            reported: Arc::new(Mutex::new(Reported {
+                next_event: Tag::never_tag(),
+                completed: Tag::never_tag(),
+                resigned: false,
+            })),
+            listener: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /**
+     * Propose the given start time to the parent RTI and return the start
+     * time that it decides.
+     */
+    pub fn exchange_start_time(&self, proposed_start_time: Instant) -> io::Result<Instant> {
+        let mut stream = self.stream.lock().unwrap().try_clone()?;
+        let mut buffer = vec![MsgType::Timestamp.to_byte()];
+        buffer.extend_from_slice(&proposed_start_time.to_le_bytes());
+        stream.write_all(&buffer)?;
+        let mut reply = [0u8; MSG_TYPE_TIMESTAMP_LENGTH];
+        stream.read_exact(&mut reply)?;
+        if reply[0] != MsgType::Timestamp.to_byte() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the parent RTI sent message type {} instead of the start time",
+                    reply[0]
+                ),
+            ));
+        }
+        Ok(i64::from_le_bytes(reply[1..].try_into().unwrap()))
+    }
+
+    /**
+     * Spawn a thread that handles the messages of the parent RTI once the
+     * start time is known.
+     */
+    pub fn start_listener_thread(
+        &self,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<StartTime>>,
+    ) -> io::Result<()> {
+        let mut stream = self.stream.lock().unwrap().try_clone()?;
+        let link = self.clone();
+        let handle = thread::spawn(move || loop {
+            let mut message_type = [0u8; 1];
+            if stream.read_exact(&mut message_type).is_err() {
+                link.handle_disconnection(&_f_rti, "the parent RTI closed the connection");
+                return;
+            }
+            let handled = match MsgType::to_msg_type(message_type[0]) {
+                MsgType::TagAdvanceGrant | MsgType::PropositionalTagAdvanceGrant => {
+                    match NetUtil::read_from_socket_tag(&mut stream) {
+                        Ok(granted) => {
+                            let provisional =
+                                message_type[0] == MsgType::PropositionalTagAdvanceGrant.to_byte();
+                            link.handle_grant(&_f_rti, &start_time, granted, provisional);
+                            true
+                        }
+                        Err(_) => false,
+                    }
+                }
+                MsgType::StopRequest => match NetUtil::read_from_socket_tag(&mut stream) {
+                    Ok(stop_tag) => {
+                        warn!(
+                            "RTI: The stop request of the parent RTI at ({},{}) is not propagated to the federates of the partition.",
+                            tag::format_time(stop_tag.time(), start_time.lock().unwrap().start_time()),
+                            stop_tag.microstep()
+                        );
+                        link.send_tag(MsgType::StopRequestReply, &stop_tag).is_ok()
+                    }
+                    Err(_) => false,
+                },
+                MsgType::StopGranted => NetUtil::read_from_socket_tag(&mut stream).is_ok(),
+                MsgType::Failed => {
+                    link.handle_disconnection(&_f_rti, "the parent RTI failed");
+                    return;
+                }
+                _ => {
+                    error!(
+                        "RTI: Received from the parent RTI an unsupported message type {}.",
+                        message_type[0]
+                    );
+                    false
+                }
+            };
+            if !handled {
+                link.handle_disconnection(&_f_rti, "a broken connection to the parent RTI");
+                return;
+            }
+        });
+        *self.listener.lock().unwrap() = Some(handle);
+        Ok(())
+    }
+
+    /**
+     * Let the federates advance up to the tag granted by the parent RTI. A
+     * PTAG only allows the tags before the granted one.
+     */
+    fn handle_grant(
+        &self,
+        _f_rti: &Arc<RwLock<FederationRTI>>,
+        start_time: &Arc<Mutex<StartTime>>,
+        granted: Tag,
+        provisional: bool,
+    ) {
+        let start_time_value;
+        {
+            let locked_start_time = start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let limit = if !provisional {
+            granted.clone()
+        } else if granted.microstep() > 0 {
+            Tag::new(granted.time(), granted.microstep() - 1)
+        } else {
+            Tag::new(granted.time().saturating_sub(1), u32::MAX)
+        };
+        debug!(
+            "RTI received from the parent RTI the {} ({},{}).",
+            if provisional { "PTAG" } else { "TAG" },
+            tag::format_time(granted.time(), start_time_value),
+            granted.microstep()
+        );
+        let mut locked_rti = lock_rti(_f_rti);
+        if locked_rti
+            .parent_grant()
+            .as_ref()
+            .is_some_and(|parent_grant| limit <= *parent_grant)
+        {
+            return;
+        }
+        locked_rti.set_parent_grant(Some(limit));
+        Enclave::grant_all(&locked_rti, start_time_value);
+    }
+
+    /**
+     * Shut the RTI down after losing the parent RTI, unless the partition has
+     * resigned already.
+     */
+    fn handle_disconnection(&self, _f_rti: &Arc<RwLock<FederationRTI>>, reason: &str) {
+        if self.reported.lock().unwrap().resigned {
+            return;
+        }
+        error!("RTI: Shutting down because of {}.", reason);
+        let shutdown = lock_rti(_f_rti).shutdown_handle();
+        shutdown.request(reason);
+    }
+
+    /**
+     * Send the parent RTI the earliest NET and LTC of the connected federates
+     * if they have advanced, or resign once all federates have exited.
+     */
+    pub fn report(&self, locked_rti: &mut FederationRTI) {
+        let mut next_event = Tag::forever_tag();
+        let mut completed = Tag::forever_tag();
+        let mut connected = false;
+        for fed in locked_rti.enclaves().iter() {
+            let e = fed.e();
+            if e.state() == FedState::NotConnected || e.decentralized() {
+                continue;
+            }
+            connected = true;
+            next_event = next_event.min(e.next_event());
+            completed = completed.min(e.completed());
+        }
+        let mut reported = self.reported.lock().unwrap();
+        if reported.resigned {
+            return;
+        }
+        if !connected {
+            // Federates that have not connected yet have no stream.
+            if locked_rti
+                .enclaves()
+                .iter()
+                .all(|fed| fed.stream().is_some())
+            {
+                reported.resigned = true;
+                info!("RTI: All federates of the partition have exited. Resigning from the parent RTI.");
+                let _ = self.send(&[MsgType::Resign.to_byte()]);
+            }
+            return;
+        }
+        if next_event != Tag::never_tag()
+            && next_event > reported.next_event
+            && self.send_tag(MsgType::NextEventTag, &next_event).is_ok()
+        {
+            reported.next_event = next_event;
+        }
+        if completed != Tag::never_tag()
+            && completed > reported.completed
+            && self
+                .send_tag(MsgType::LogicalTagComplete, &completed)
+                .is_ok()
+        {
+            reported.completed = completed;
+        }
+    }
+
+    /**
+     * Close the connection and wait for the thread that handles the messages
+     * of the parent RTI.
+     */
+    pub fn stop(&self) {
+        // NOTE: Ignore errors because the parent RTI may have closed the
+        // connection already.
+        let _ = self.stream.lock().unwrap().shutdown(Shutdown::Both);
+        if let Some(handle) = self.listener.lock().unwrap().take() {
+            handle.join().unwrap();
+        }
+    }
+
+    fn send_tag(&self, message_type: MsgType, tag: &Tag) -> io::Result<()> {
+        let mut buffer = vec![0u8; 1 + mem::size_of::<Instant>() + mem::size_of::<u32>()];
+        buffer[0] = message_type.to_byte();
+        NetUtil::encode_int64(tag.time(), &mut buffer, 1);
+        NetUtil::encode_int32(
+            tag.microstep() as i32,
+            &mut buffer,
+            1 + mem::size_of::<Instant>(),
+        );
+        self.send(&buffer)
+    }
+
+    fn send(&self, buffer: &[u8]) -> io::Result<()> {
+        let result = self.stream.lock().unwrap().write_all(buffer);
+        if let Err(e) = &result {
+            error!("RTI failed to write to the parent RTI: {}.", e);
+        }
+        result
+    }
+}
@@ -0,0 +1,259 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Partition of the federates among the scheduling threads by the weakly
+ * connected components of the federation, so that unrelated pipelines
+ * coordinated by the same RTI are served by different threads.
+ *
+ * The grants of the federates of different components are decided under
+ * different locks. The components are known before the federates connect
+ * only from the federation metadata (see --federation_metadata). Without it,
+ * they are learned from the neighbor structures of the federates as they
+ * connect, and a component is spread over several threads only while enough
+ * are left for the components that may still show up.
+ */
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::enclave::{GrantInputs, TagAdvanceGrant};
+
+/**
+ * The weakly connected components of the federation as far as the neighbor
+ * structures of the federates that have connected tell, each with its own
+ * grant lock and grant decisions. Components are only ever merged, which
+ * requires the exclusive lock of the RTI state, so no grant lock is held
+ * while they are.
+ */
+pub struct Partitions {
+    /**
+     * The smallest federate ID of the component of each federate, by which
+     * the state of the component is indexed.
+     */
+    representative: Vec<u16>,
+
+    /**
+     * Held while deciding and sending the grants of the federates of the
+     * component.
+     */
+    grant_locks: Vec<Mutex<()>>,
+
+    /**
+     * The last grant decision made for each federate of the component, with
+     * the inputs from which it was made. Only accessed under the grant lock
+     * of the component or the exclusive lock of the RTI state.
+     */
+    grant_decisions: Vec<Mutex<HashMap<u16, (GrantInputs, TagAdvanceGrant)>>>,
+}
+
+impl Partitions {
+    /**
+     * Create the partitions of the given number of federates, each of which
+     * is a component of its own until it is merged with its neighbors.
+     */
+    pub fn new(number_of_federates: usize) -> Partitions {
+        Partitions {
+            representative: (0..number_of_federates as u16).collect(),
+            grant_locks: (0..number_of_federates).map(|_| Mutex::new(())).collect(),
+            grant_decisions: (0..number_of_federates)
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+        }
+    }
+
+    /**
+     * Merge the components of the given federate and of its neighbors,
+     * ignoring IDs of federates that do not exist.
+     */
+    pub fn merge(&mut self, fed_id: u16, neighbors: &[u16]) {
+        for neighbor in neighbors {
+            let (Some(first), Some(second)) = (
+                self.representative.get(usize::from(fed_id)).copied(),
+                self.representative.get(usize::from(*neighbor)).copied(),
+            ) else {
+                continue;
+            };
+            if first == second {
+                continue;
+            }
+            let (kept, merged) = (first.min(second), first.max(second));
+            for representative in self.representative.iter_mut() {
+                if *representative == merged {
+                    *representative = kept;
+                }
+            }
+            let decisions =
+                std::mem::take(self.grant_decisions[usize::from(merged)].get_mut().unwrap());
+            self.grant_decisions[usize::from(kept)]
+                .get_mut()
+                .unwrap()
+                .extend(decisions);
+        }
+    }
+
+    /**
+     * Return the smallest federate ID of the component of the given federate.
+     */
+    pub fn representative(&self, fed_id: u16) -> u16 {
+        self.representative[usize::from(fed_id)]
+    }
+
+    /**
+     * Acquire the grant lock of the component of the given federate.
+     */
+    pub fn lock_grants(&self, fed_id: u16) -> MutexGuard<'_, ()> {
+        self.grant_locks[usize::from(self.representative[usize::from(fed_id)])]
+            .lock()
+            .unwrap()
+    }
+
+    /**
+     * Return the grant decisions of the component of the given federate.
+     */
+    pub fn grant_decisions(
+        &self,
+        fed_id: u16,
+    ) -> MutexGuard<'_, HashMap<u16, (GrantInputs, TagAdvanceGrant)>> {
+        self.grant_decisions[usize::from(self.representative[usize::from(fed_id)])]
+            .lock()
+            .unwrap()
+    }
+}
+
+/**
+ * The scheduling thread of each federate, assigned as the federates connect
+ * when the components are not known in advance.
+ */
+pub struct SchedulingThreads {
+    thread_of: Vec<Option<usize>>,
+
+    /**
+     * The number of federates served by each thread.
+     */
+    loads: Vec<usize>,
+}
+
+impl SchedulingThreads {
+    /**
+     * Use the given assignment, e.g., from assign_scheduling_threads.
+     */
+    pub fn assigned(thread_of: Vec<usize>, number_of_threads: usize) -> SchedulingThreads {
+        let mut loads = vec![0; number_of_threads.max(1)];
+        for thread in &thread_of {
+            loads[*thread] += 1;
+        }
+        SchedulingThreads {
+            thread_of: thread_of.into_iter().map(Some).collect(),
+            loads,
+        }
+    }
+
+    /**
+     * Assign the threads as the federates connect.
+     */
+    pub fn unassigned(number_of_federates: usize, number_of_threads: usize) -> SchedulingThreads {
+        SchedulingThreads {
+            thread_of: vec![None; number_of_federates],
+            loads: vec![0; number_of_threads.max(1)],
+        }
+    }
+
+    /**
+     * Return the scheduling thread of the given federate, which has declared
+     * its neighbors. A federate that has not been assigned one is served by
+     * the thread that serves the fewest federates among the threads of its
+     * component, if it has any, or else among all threads. A component that
+     * has threads is also given an idle thread, however, if more are idle
+     * than federates that may turn out to be independent of the components
+     * known so far.
+     */
+    pub fn thread_of(&mut self, fed_id: u16, partitions: &Partitions) -> usize {
+        if let Some(thread) = self.thread_of[usize::from(fed_id)] {
+            return thread;
+        }
+        let representative = partitions.representative(fed_id);
+        let known: Vec<u16> = (0..self.thread_of.len() as u16)
+            .filter(|id| self.thread_of[usize::from(*id)].is_some())
+            .map(|id| partitions.representative(id))
+            .collect();
+        let unknown = (0..self.thread_of.len() as u16)
+            .map(|id| partitions.representative(id))
+            .filter(|other| *other != representative && !known.contains(other))
+            .count();
+        let own_threads: Vec<usize> = (0..self.thread_of.len() as u16)
+            .filter(|id| partitions.representative(*id) == representative)
+            .filter_map(|id| self.thread_of[usize::from(id)])
+            .collect();
+        let idle_threads = self.loads.iter().filter(|load| **load == 0).count();
+        let candidates: Vec<usize> = if own_threads.is_empty() || idle_threads > unknown {
+            (0..self.loads.len()).collect()
+        } else {
+            own_threads
+        };
+        let thread = candidates
+            .into_iter()
+            .min_by_key(|thread| self.loads[*thread])
+            .unwrap();
+        self.thread_of[usize::from(fed_id)] = Some(thread);
+        self.loads[thread] += 1;
+        thread
+    }
+}
+
+/**
+ * Return the index of the scheduling thread of each federate, by federate ID,
+ * given the weakly connected components of the federation. If there are at
+ * least as many components as threads, each component is served by a single
+ * thread, and the components are spread so that the threads serve about as
+ * many federates. Otherwise, each component is served by its own threads, of
+ * which the larger components get more, but no more than one per federate.
+ */
+pub fn assign_scheduling_threads(components: &[Vec<u16>], number_of_threads: usize) -> Vec<usize> {
+    let number_of_threads = number_of_threads.max(1);
+    let number_of_federates = components.iter().map(Vec::len).sum();
+    let mut thread_of = vec![0; number_of_federates];
+    let mut by_size: Vec<&Vec<u16>> = components.iter().collect();
+    by_size.sort_by_key(|component| std::cmp::Reverse(component.len()));
+    if components.len() >= number_of_threads {
+        let mut loads = vec![0; number_of_threads];
+        for component in by_size {
+            let (thread, load) = loads
+                .iter_mut()
+                .enumerate()
+                .min_by_key(|(_, load)| **load)
+                .unwrap();
+            *load += component.len();
+            for id in component {
+                thread_of[usize::from(*id)] = thread;
+            }
+        }
+        return thread_of;
+    }
+    let mut shares = vec![1; by_size.len()];
+    for _ in by_size.len()..number_of_threads {
+        // Give the next thread to the component with the most federates per
+        // thread, if any has more federates than threads.
+        let neediest = (0..by_size.len())
+            .filter(|index| shares[*index] < by_size[*index].len())
+            .max_by(|a, b| {
+                (by_size[*a].len() * shares[*b])
+                    .cmp(&(by_size[*b].len() * shares[*a]))
+                    // Prefer the larger component on a tie.
+                    .then(b.cmp(a))
+            });
+        match neediest {
+            Some(index) => shares[index] += 1,
+            None => break,
+        }
+    }
+    let mut first_thread = 0;
+    for (component, share) in by_size.into_iter().zip(shares) {
+        for (position, id) in component.iter().enumerate() {
+            thread_of[usize::from(*id)] = first_thread + position % share;
+        }
+        first_thread += share;
+    }
+    thread_of
+}
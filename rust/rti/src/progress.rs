@@ -0,0 +1,153 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Periodic reporting of the federation's progress toward a known stop tag.
+ */
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time;
+
+use crate::clock::PhysicalClock;
+use crate::lock_stats::read_rti;
+use crate::tag;
+use crate::tag::{Instant, StartTime, Tag};
+use crate::trace::info;
+use crate::FedState;
+use crate::FederationRTI;
+
+/**
+ * Keeps the previous sample of the minimum granted tag so that the rate of
+ * logical time advance (and hence the ETA) reflects recent progress rather
+ * than the average over the whole run.
+ */
+pub struct ProgressReporter {
+    clock: Arc<PhysicalClock>,
+    last_sample: Option<(Instant, Instant)>,
+}
+
+impl ProgressReporter {
+    pub fn new(clock: Arc<PhysicalClock>) -> ProgressReporter {
+        ProgressReporter {
+            clock,
+            last_sample: None,
+        }
+    }
+
+    /**
+     * Spawn a thread that reports the progress every `period_ms` milliseconds
+     * until all federates have exited.
+     */
+    pub fn start_progress_thread(
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<StartTime>>,
+        clock: Arc<PhysicalClock>,
+        period_ms: u64,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let mut reporter = ProgressReporter::new(clock);
+            loop {
+                reporter.clock.sleep(time::Duration::from_millis(period_ms));
+                let start_time_value;
+                {
+                    let locked_start_time = start_time.lock().unwrap();
+                    start_time_value = locked_start_time.start_time();
+                }
+                let min_granted;
+                let stop_tag;
+                {
+                    let locked_rti = read_rti(&_f_rti);
+                    if locked_rti.all_federates_exited() {
+                        break;
+                    }
+                    stop_tag = Self::known_stop_tag(&locked_rti, start_time_value);
+                    min_granted = Self::minimum_granted_tag(locked_rti.federates());
+                }
+                if let (Some(stop_tag), Some(min_granted)) = (stop_tag, min_granted) {
+                    reporter.report(&min_granted, &stop_tag, start_time_value);
+                }
+            }
+        })
+    }
+
+    /**
+     * Return the stop tag of the federation if it is known, either because a
+     * stop has been requested by a federate or because the user specified the
+     * expected stop time on the command line.
+     */
+    fn known_stop_tag(_f_rti: &FederationRTI, start_time: Instant) -> Option<Tag> {
+        let max_stop_tag = _f_rti.max_stop_tag();
+        if max_stop_tag != Tag::never_tag() {
+            return Some(max_stop_tag);
+        }
+        if start_time == i64::MIN {
+            // The start time has not been decided yet.
+            return None;
+        }
+        _f_rti
+            .stop_time()
+            .map(|stop_time| Tag::new(start_time.saturating_add(stop_time), 0))
+    }
+
+    /**
+     * Return the minimum tag granted to the federates that are still connected,
+     * or None if no such federate has been granted a tag yet.
+     */
+    fn minimum_granted_tag(enclaves: &[crate::Federate]) -> Option<Tag> {
+        let mut result: Option<Tag> = None;
+        for fed in enclaves {
+            let e = fed.e();
+            if e.state() == FedState::NotConnected {
+                continue;
+            }
+            let last_granted = e.last_granted();
+            if last_granted == Tag::never_tag() {
+                return None;
+            }
+            match &result {
+                Some(min) if last_granted >= *min => {}
+                _ => result = Some(last_granted),
+            }
+        }
+        result
+    }
+
+    /**
+     * Print the percent progress of `min_granted` toward `stop_tag` and an ETA
+     * estimated from the rate of advance since the previous report.
+     */
+    pub fn report(&mut self, min_granted: &Tag, stop_tag: &Tag, start_time: Instant) {
+        let total = stop_tag.time() - start_time;
+        let elapsed = min_granted.time() - start_time;
+        let percent = if total <= 0 {
+            100.0
+        } else {
+            (elapsed as f64 / total as f64 * 100.0).clamp(0.0, 100.0)
+        };
+
+        let now = self.clock.now();
+        let mut eta = String::from("unknown");
+        if let Some((last_wall, last_logical)) = self.last_sample {
+            let wall_delta = (now - last_wall) as f64 / 1e9;
+            let logical_delta = (min_granted.time() - last_logical) as f64;
+            if wall_delta > 0.0 && logical_delta > 0.0 {
+                let rate = logical_delta / wall_delta;
+                let remaining = (stop_tag.time() - min_granted.time()).max(0) as f64;
+                eta = format!("{:.1} s", remaining / rate);
+            }
+        }
+        self.last_sample = Some((now, min_granted.time()));
+
+        info!(
+            "RTI: Progress {:.1}% toward stop tag ({},{}). Minimum granted tag is ({},{}). ETA: {}.",
+            percent,
+            tag::format_time(stop_tag.time(), start_time),
+            stop_tag.microstep(),
+            tag::format_time(min_granted.time(), start_time),
+            min_granted.microstep(),
+            eta
+        );
+    }
+}
@@ -0,0 +1,166 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Limit on the rate of the NET and LTC messages of each federate, which
+ * protects the RTI from a federate that floods it with control messages.
+ *
+ * Each federate has a bucket of tokens that is refilled at the configured rate
+ * and holds at most one second worth of tokens. A NET or LTC message consumes
+ * one token. A message that arrives when the bucket is empty is in excess.
+ */
+use std::time::{Duration, Instant};
+
+use crate::net_common::MsgType;
+use crate::tag::Tag;
+
+/**
+ * What to do with a NET or LTC message in excess of the rate.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ExcessControlPolicy {
+    /// Hold the message back, keeping only the latest NET and LTC, and handle
+    /// them once the rate allows it. Because tags only increase, the latest
+    /// NET and LTC supersede the earlier ones.
+    Coalesce,
+    /// Handle the message anyway, but warn about the federate once.
+    Flag,
+}
+
+pub struct ControlRateLimiter {
+    rate: f64, // Messages per second.
+    tokens: f64,
+    last_refill: Instant,
+    pending_next_event_tag: Option<Tag>,
+    pending_logical_tag_complete: Option<Tag>,
+    excess_messages: u64,
+    coalesced_messages: u64,
+    flagged: bool,
+}
+
+impl ControlRateLimiter {
+    pub fn new(messages_per_second: u32) -> ControlRateLimiter {
+        let rate = f64::from(messages_per_second.max(1));
+        ControlRateLimiter {
+            rate,
+            tokens: rate,
+            last_refill: Instant::now(),
+            pending_next_event_tag: None,
+            pending_logical_tag_complete: None,
+            excess_messages: 0,
+            coalesced_messages: 0,
+            flagged: false,
+        }
+    }
+
+    /**
+     * Number of messages that arrived in excess of the rate.
+     */
+    pub fn excess_messages(&self) -> u64 {
+        self.excess_messages
+    }
+
+    /**
+     * Number of messages superseded by a later message of the same type
+     * before they were handled.
+     */
+    pub fn coalesced_messages(&self) -> u64 {
+        self.coalesced_messages
+    }
+
+    pub fn flagged(&self) -> bool {
+        self.flagged
+    }
+
+    /**
+     * Consume a token for a message that has just arrived. Return false if the
+     * message is in excess, either because no token is left or because
+     * earlier messages are still held back, which must be handled first.
+     */
+    pub fn admit(&mut self) -> bool {
+        self.refill();
+        if !self.has_pending() && self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return true;
+        }
+        self.excess_messages += 1;
+        false
+    }
+
+    /**
+     * Flag the federate. Return true if it was not flagged yet.
+     */
+    pub fn flag(&mut self) -> bool {
+        !std::mem::replace(&mut self.flagged, true)
+    }
+
+    /**
+     * Hold back a NET or LTC message in excess, replacing the held back
+     * message of the same type.
+     */
+    pub fn coalesce(&mut self, msg_type: MsgType, tag: Tag) {
+        let pending = if matches!(msg_type, MsgType::NextEventTag) {
+            &mut self.pending_next_event_tag
+        } else {
+            &mut self.pending_logical_tag_complete
+        };
+        if pending.replace(tag).is_some() {
+            self.coalesced_messages += 1;
+        }
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending_next_event_tag.is_some() || self.pending_logical_tag_complete.is_some()
+    }
+
+    /**
+     * Return the time until the held back messages can be handled, or None if
+     * no message is held back.
+     */
+    pub fn time_until_pending_allowed(&mut self) -> Option<Duration> {
+        if !self.has_pending() {
+            return None;
+        }
+        self.refill();
+        let missing = (1.0 - self.tokens).max(0.0);
+        Some(Duration::from_secs_f64(missing / self.rate))
+    }
+
+    /**
+     * If the rate allows it, consume a token and return the held back
+     * messages in the order in which they have to be handled. A NET is sent
+     * before an LTC of the same or a later tag, and after an LTC of an earlier
+     * tag, as a federate does.
+     */
+    pub fn take_pending(&mut self) -> Vec<(MsgType, Tag)> {
+        if !self.has_pending() {
+            return Vec::new();
+        }
+        self.refill();
+        if self.tokens < 1.0 {
+            return Vec::new();
+        }
+        self.tokens -= 1.0;
+        let next_event_tag = self
+            .pending_next_event_tag
+            .take()
+            .map(|tag| (MsgType::NextEventTag, tag));
+        let logical_tag_complete = self
+            .pending_logical_tag_complete
+            .take()
+            .map(|tag| (MsgType::LogicalTagComplete, tag));
+        match (next_event_tag, logical_tag_complete) {
+            (Some(net), Some(ltc)) if net.1 > ltc.1 => vec![ltc, net],
+            (net, ltc) => net.into_iter().chain(ltc).collect(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+}
@@ -0,0 +1,152 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Resumption of the sessions of federates after a lost connection,
+ * enabled with --resume_window.
+ *
+ * A federate that has been enabled CAPABILITY_RESUMPTION receives a random
+ * token at join. If its connection is lost, the RTI suspends it instead of
+ * handling it as failed: its scheduling state is kept, so the federates
+ * downstream of it wait for it. If it connects again with the token within
+ * the window, it is re-attached to its state. Otherwise, it is handled as
+ * failed once the window expires. See MSG_TYPE_RESUME_LENGTH for the protocol.
+ */
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::PhysicalClock;
+use crate::constants::RESUMPTION_CHECK_PERIOD_MS;
+use crate::federate::federate_label;
+use crate::lock_stats::lock_rti;
+use crate::net_common::RESUME_TOKEN_LENGTH;
+use crate::server::{Server, StopGranted};
+use crate::tag::StartTime;
+#[cfg(feature = "auth")]
+use crate::trace::error;
+use crate::trace::warn;
+use crate::FedState;
+use crate::FederationRTI;
+
+/**
+ * Return a new random resumption token, or None if the operating system
+ * provides no randomness or the RTI is built without the auth feature.
+ */
+pub fn new_token() -> Option<[u8; RESUME_TOKEN_LENGTH]> {
+    #[cfg(feature = "auth")]
+    {
+        let mut token = [0u8; RESUME_TOKEN_LENGTH];
+        match getrandom::getrandom(&mut token) {
+            Ok(()) => Some(token),
+            Err(e) => {
+                error!("RTI: Failed to generate a resumption token: {}.", e);
+                None
+            }
+        }
+    }
+    #[cfg(not(feature = "auth"))]
+    None
+}
+
+/**
+ * Return true if the given tokens are equal. The comparison takes constant
+ * time so that a token cannot be guessed byte by byte.
+ */
+pub fn tokens_match(expected: &[u8; RESUME_TOKEN_LENGTH], presented: &[u8]) -> bool {
+    presented.len() == RESUME_TOKEN_LENGTH
+        && expected
+            .iter()
+            .zip(presented)
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/**
+ * Suspend the given federate, whose connection has been lost because of the
+ * given cause, if it can resume its session: resumption is enabled, the RTI
+ * has issued a token to the federate, and neither a shutdown nor the stop
+ * protocol is in progress. Return true if the federate is suspended, in which
+ * case it is not to be handled as failed.
+ */
+pub fn suspend_federate(locked_rti: &mut FederationRTI, fed_id: u16, cause: &str) -> bool {
+    let window_ms = locked_rti.resume_window_ms();
+    if window_ms == 0 || locked_rti.shutdown_handle().requested() || locked_rti.stop_in_progress() {
+        return false;
+    }
+    let now = locked_rti.physical_clock().now();
+    let fed = &mut locked_rti.enclaves()[usize::from(fed_id)];
+    if fed.resume_token().is_none() || fed.e().state() == FedState::NotConnected {
+        return false;
+    }
+    if fed.suspended() {
+        return true;
+    }
+    fed.set_suspended_at(Some(now));
+    warn!(
+        "RTI: WARNING: Lost the connection to federate {} ({}). Holding its state for {} ms for it to resume its session.",
+        federate_label(fed_id),
+        cause,
+        window_ms
+    );
+    locked_rti.record_event("suspended", Some(fed_id), None, Some(cause));
+    true
+}
+
+/**
+ * Spawn a thread that handles the federates that have been suspended for
+ * longer than `window_ms` milliseconds as failed every
+ * RESUMPTION_CHECK_PERIOD_MS milliseconds, until all federates have exited.
+ */
+pub fn start_resumption_thread(
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    start_time: Arc<Mutex<StartTime>>,
+    stop_granted: Arc<Mutex<StopGranted>>,
+    clock: Arc<PhysicalClock>,
+    window_ms: u64,
+) -> thread::JoinHandle<()> {
+    let window_ns = i64::try_from(window_ms)
+        .ok()
+        .and_then(|window_ms| window_ms.checked_mul(1_000_000))
+        .unwrap_or(i64::MAX);
+    thread::spawn(move || loop {
+        clock.sleep(Duration::from_millis(RESUMPTION_CHECK_PERIOD_MS));
+        let expired: Vec<u16>;
+        {
+            let mut locked_rti = lock_rti(&_f_rti);
+            if locked_rti.all_federates_exited() {
+                break;
+            }
+            let now = clock.now();
+            expired = locked_rti
+                .enclaves()
+                .iter_mut()
+                .filter(|fed| {
+                    fed.e().state() != FedState::NotConnected
+                        && fed.suspended_at().is_some_and(|suspended_at| {
+                            now.saturating_sub(suspended_at) >= window_ns
+                        })
+                })
+                .map(|fed| {
+                    // The token can no longer be used.
+                    fed.set_resume_token(None);
+                    fed.e().id()
+                })
+                .collect();
+        }
+        for fed_id in expired {
+            Server::handle_federate_failed(
+                fed_id,
+                format!(
+                    "the connection was lost and not resumed within {} ms",
+                    window_ms
+                ),
+                _f_rti.clone(),
+                start_time.clone(),
+                stop_granted.clone(),
+            );
+        }
+    })
+}
@@ -0,0 +1,408 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Database of the summaries of past runs, enabled with --run_db, and
+ * the comparison of two runs with the compare subcommand.
+ *
+ * Each run appends a row to the runs table of a SQLite file, and a row per
+ * federate to the federates table, so that the impact of a change to the
+ * federation or to the options of the RTI can be quantified across runs.
+ */
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::RtiError;
+use crate::FederationRTI;
+
+/**
+ * Version of the schema, stored as the user_version of the database.
+ */
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        recorded_at INTEGER NOT NULL,
+        federation_id TEXT NOT NULL,
+        arguments TEXT NOT NULL,
+        number_of_federates INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        tags_sent INTEGER NOT NULL,
+        ptags_sent INTEGER NOT NULL,
+        ptags_wasted INTEGER NOT NULL,
+        relayed_messages INTEGER NOT NULL,
+        max_lag_ns INTEGER,
+        lag_warnings INTEGER NOT NULL,
+        failed_federates INTEGER NOT NULL,
+        shutdown_reason TEXT
+    );
+    CREATE TABLE IF NOT EXISTS federates (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        federate_id INTEGER NOT NULL,
+        name TEXT,
+        tags_sent INTEGER NOT NULL,
+        ptags_sent INTEGER NOT NULL,
+        relayed_messages INTEGER NOT NULL,
+        failure TEXT,
+        PRIMARY KEY (run_id, federate_id)
+    );
+";
+
+/**
+ * Names of the statistics of a run that are compared, in the order of the
+ * columns of the runs table.
+ */
+const RUN_STATISTICS: [&str; 8] = [
+    "duration_ms",
+    "tags_sent",
+    "ptags_sent",
+    "ptags_wasted",
+    "relayed_messages",
+    "max_lag_ns",
+    "lag_warnings",
+    "failed_federates",
+];
+
+const FEDERATE_STATISTICS: [&str; 3] = ["tags_sent", "ptags_sent", "relayed_messages"];
+
+struct FederateSummary {
+    id: u16,
+    name: Option<String>,
+    tags_sent: u64,
+    ptags_sent: u64,
+    ptags_wasted: u64,
+    relayed_messages: u64,
+    failure: Option<String>,
+}
+
+/**
+ * Summary of a run, collected once all federates have exited.
+ */
+pub struct RunSummary {
+    federation_id: String,
+    arguments: String,
+    duration: Duration,
+    max_lag_ns: Option<i64>,
+    lag_warnings: u64,
+    shutdown_reason: Option<String>,
+    federates: Vec<FederateSummary>,
+}
+
+impl RunSummary {
+    /**
+     * Collect the summary of a run that took the given duration and was shut
+     * down for the given reason, if any.
+     */
+    pub fn collect(
+        locked_rti: &mut FederationRTI,
+        duration: Duration,
+        shutdown_reason: Option<String>,
+    ) -> RunSummary {
+        let federates = locked_rti
+            .enclaves()
+            .iter_mut()
+            .map(|fed| FederateSummary {
+                id: fed.e().id(),
+                name: fed.name().clone(),
+                tags_sent: fed.provisional_grants().tags_sent(),
+                ptags_sent: fed.provisional_grants().sent(),
+                ptags_wasted: fed.provisional_grants().wasted(),
+                relayed_messages: fed.relayed_messages(),
+                failure: fed.failure_reason().clone(),
+            })
+            .collect();
+        RunSummary {
+            federation_id: locked_rti.federation_id(),
+            arguments: locked_rti.arguments().join(" "),
+            duration,
+            max_lag_ns: locked_rti.max_lag_observed(),
+            lag_warnings: locked_rti.lag_warnings(),
+            shutdown_reason,
+            federates,
+        }
+    }
+}
+
+/**
+ * Open the database in the given file, creating it if needed.
+ */
+fn open(path: &str) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < SCHEMA_VERSION {
+        connection.execute_batch(SCHEMA)?;
+        connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    }
+    Ok(connection)
+}
+
+/**
+ * Append the given summary to the database in the given file. Return the ID
+ * of the run.
+ */
+pub fn record_run(path: &str, summary: &RunSummary) -> rusqlite::Result<i64> {
+    let mut connection = open(path)?;
+    let transaction = connection.transaction()?;
+    let recorded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let sum = |statistic: fn(&FederateSummary) -> u64| -> i64 {
+        to_sql(summary.federates.iter().map(statistic).sum())
+    };
+    transaction.execute(
+        "INSERT INTO runs (recorded_at, federation_id, arguments, number_of_federates,
+             duration_ms, tags_sent, ptags_sent, ptags_wasted, relayed_messages, max_lag_ns,
+             lag_warnings, failed_federates, shutdown_reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            to_sql(recorded_at),
+            summary.federation_id,
+            summary.arguments,
+            summary.federates.len(),
+            to_sql(u64::try_from(summary.duration.as_millis()).unwrap_or(u64::MAX)),
+            sum(|fed| fed.tags_sent),
+            sum(|fed| fed.ptags_sent),
+            sum(|fed| fed.ptags_wasted),
+            sum(|fed| fed.relayed_messages),
+            summary.max_lag_ns,
+            to_sql(summary.lag_warnings),
+            summary
+                .federates
+                .iter()
+                .filter(|fed| fed.failure.is_some())
+                .count(),
+            summary.shutdown_reason,
+        ],
+    )?;
+    let run_id = transaction.last_insert_rowid();
+    for fed in &summary.federates {
+        transaction.execute(
+            "INSERT INTO federates (run_id, federate_id, name, tags_sent, ptags_sent,
+                 relayed_messages, failure)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                run_id,
+                fed.id,
+                fed.name,
+                to_sql(fed.tags_sent),
+                to_sql(fed.ptags_sent),
+                to_sql(fed.relayed_messages),
+                fed.failure,
+            ],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(run_id)
+}
+
+/**
+ * SQLite integers are signed.
+ */
+fn to_sql(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/**
+ * Return a report of the differences between two runs recorded in the
+ * database in the given file, the first one first. Without runs, the last two
+ * runs are compared.
+ */
+pub fn compare_runs(path: &str, runs: Option<(i64, i64)>) -> Result<String, RtiError> {
+    let database_error = |e: rusqlite::Error| RtiError::RunDatabase(e.to_string());
+    let connection = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(database_error)?;
+    let (first, second) = match runs {
+        Some(runs) => runs,
+        None => match last_two_runs(&connection).map_err(database_error)? {
+            Some(runs) => runs,
+            None => {
+                return Err(RtiError::RunDatabase(format!(
+                    "{} has fewer than two runs",
+                    path
+                )))
+            }
+        },
+    };
+    for run_id in [first, second] {
+        let recorded = connection
+            .query_row("SELECT 1 FROM runs WHERE id = ?1", [run_id], |_| Ok(()))
+            .optional()
+            .map_err(database_error)?;
+        if recorded.is_none() {
+            return Err(RtiError::RunDatabase(format!(
+                "{} has no run {}",
+                path, run_id
+            )));
+        }
+    }
+    compare(&connection, first, second).map_err(database_error)
+}
+
+/**
+ * Return the IDs of the last two runs, the earlier one first, or None if
+ * fewer runs have been recorded.
+ */
+fn last_two_runs(connection: &Connection) -> rusqlite::Result<Option<(i64, i64)>> {
+    let mut statement = connection.prepare("SELECT id FROM runs ORDER BY id DESC LIMIT 2")?;
+    let ids = statement
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    Ok(match ids[..] {
+        [last, previous] => Some((previous, last)),
+        _ => None,
+    })
+}
+
+fn compare(connection: &Connection, first: i64, second: i64) -> rusqlite::Result<String> {
+    let mut report = Vec::new();
+    let describe_run = |id: i64| -> rusqlite::Result<String> {
+        connection.query_row(
+            "SELECT recorded_at, federation_id, arguments FROM runs WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(format!(
+                    "run {} of federation {} recorded at {} with arguments '{}'",
+                    id,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(2)?
+                ))
+            },
+        )
+    };
+    report.push(format!("From {}", describe_run(first)?));
+    report.push(format!("to {}:", describe_run(second)?));
+    for statistic in RUN_STATISTICS {
+        let query = format!("SELECT {} FROM runs WHERE id = ?1", statistic);
+        let value = |id: i64| -> rusqlite::Result<Option<i64>> {
+            connection.query_row(&query, [id], |row| row.get(0))
+        };
+        report.push(format!(
+            "\t{}: {}",
+            statistic,
+            describe_change(value(first)?, value(second)?)
+        ));
+    }
+    let shutdown_reason = |id: i64| -> rusqlite::Result<Option<String>> {
+        connection.query_row(
+            "SELECT shutdown_reason FROM runs WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+    };
+    let (before, after) = (shutdown_reason(first)?, shutdown_reason(second)?);
+    if before != after {
+        report.push(format!(
+            "\tshutdown_reason: {} -> {}",
+            before.as_deref().unwrap_or("none"),
+            after.as_deref().unwrap_or("none")
+        ));
+    }
+    let mut statement = connection.prepare(
+        "SELECT DISTINCT federate_id FROM federates WHERE run_id IN (?1, ?2) ORDER BY federate_id",
+    )?;
+    let federate_ids = statement
+        .query_map([first, second], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    for federate_id in federate_ids {
+        let before = recorded_federate(connection, first, federate_id)?;
+        let after = recorded_federate(connection, second, federate_id)?;
+        let name = after
+            .as_ref()
+            .or(before.as_ref())
+            .and_then(|fed| fed.name.clone());
+        report.push(match name {
+            Some(name) => format!("\tFederate {} ({}):", federate_id, name),
+            None => format!("\tFederate {}:", federate_id),
+        });
+        for (index, statistic) in FEDERATE_STATISTICS.iter().enumerate() {
+            report.push(format!(
+                "\t\t{}: {}",
+                statistic,
+                describe_change(
+                    before.as_ref().map(|fed| fed.statistics[index]),
+                    after.as_ref().map(|fed| fed.statistics[index])
+                )
+            ));
+        }
+        let failed_before = before.and_then(|fed| fed.failure);
+        let failed_after = after.and_then(|fed| fed.failure);
+        if failed_before != failed_after {
+            report.push(format!(
+                "\t\tfailure: {} -> {}",
+                failed_before.as_deref().unwrap_or("none"),
+                failed_after.as_deref().unwrap_or("none")
+            ));
+        }
+    }
+    Ok(report.join("\n"))
+}
+
+/**
+ * A row of the federates table, with the statistics in the order of
+ * FEDERATE_STATISTICS.
+ */
+struct RecordedFederate {
+    name: Option<String>,
+    statistics: [i64; FEDERATE_STATISTICS.len()],
+    failure: Option<String>,
+}
+
+/**
+ * Return the row of the given federate in the given run, or None if the run
+ * has no such federate.
+ */
+fn recorded_federate(
+    connection: &Connection,
+    run_id: i64,
+    federate_id: i64,
+) -> rusqlite::Result<Option<RecordedFederate>> {
+    connection
+        .query_row(
+            "SELECT name, tags_sent, ptags_sent, relayed_messages, failure
+             FROM federates WHERE run_id = ?1 AND federate_id = ?2",
+            [run_id, federate_id],
+            |row| {
+                Ok(RecordedFederate {
+                    name: row.get(0)?,
+                    statistics: [row.get(1)?, row.get(2)?, row.get(3)?],
+                    failure: row.get(4)?,
+                })
+            },
+        )
+        .optional()
+}
+
+/**
+ * Describe the change of a statistic from one run to the next, e.g.,
+ * "1200 -> 900 (-300, -25.0%)".
+ */
+fn describe_change(before: Option<i64>, after: Option<i64>) -> String {
+    match (before, after) {
+        (None, None) => String::from("none (no change)"),
+        (Some(before), Some(after)) if before == after => format!("{} (no change)", after),
+        (Some(before), Some(after)) => {
+            let difference = after.saturating_sub(before);
+            if before == 0 {
+                format!("{} -> {} ({:+})", before, after, difference)
+            } else {
+                format!(
+                    "{} -> {} ({:+}, {:+.1}%)",
+                    before,
+                    after,
+                    difference,
+                    difference as f64 * 100.0 / before as f64
+                )
+            }
+        }
+        (before, after) => format!(
+            "{} -> {}",
+            before.map_or(String::from("none"), |before| before.to_string()),
+            after.map_or(String::from("none"), |after| after.to_string())
+        ),
+    }
+}
@@ -6,26 +6,67 @@
  * License in [BSD 2-clause](..)
  * @brief ..
  */
-use std::io::Write;
+use std::fs;
+use std::io::{ErrorKind, Write};
 use std::mem;
 use std::net::{Shutdown, TcpListener, TcpStream};
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
+use crate::admin::AdminConsole;
+use crate::affinity::ThreadPlacement;
+#[cfg(feature = "auth")]
+use crate::auth;
+use crate::clock::PhysicalClock;
+use crate::clock_sync;
+use crate::connection::{Connection, ConnectionState};
+use crate::constants::*;
+use crate::egress::EgressShaper;
+use crate::federate::{self, federate_label};
+use crate::inspector::{ForwardedMessage, PayloadInspector};
+use crate::lag;
+use crate::lft::TraceEvent;
+use crate::lock_stats::{lock_rti, read_rti};
+use crate::log_sink;
 use crate::message_record::message_record::MessageRecord;
+use crate::multiplex::Multiplexer;
 use crate::net_common;
 use crate::net_common::*;
 use crate::net_util::*;
+use crate::notifier::GrantNotifier;
+use crate::outgoing::ReservedWrite;
+use crate::parent::ParentLink;
+use crate::partition::{self, SchedulingThreads};
+use crate::progress::ProgressReporter;
+use crate::resumption;
+#[cfg(feature = "run_db")]
+use crate::run_db::{self, RunSummary};
+use crate::silence;
+use crate::snapshot;
+use crate::status::StatusServer;
 use crate::tag;
 use crate::tag::*;
+use crate::tls::TlsConfig;
+use crate::topology::Topology;
+use crate::trace;
+use crate::trace::{debug, debug_for, error, info, trace, warn};
+use crate::tracer::Tracer;
+use crate::transport::{DeadlineStream, FederateStream, Transport};
+use crate::wait_stats;
+use crate::wire_dump::DumpStream;
 use crate::ClockSyncStat;
+use crate::EarlyNetPolicy;
 use crate::Enclave;
+use crate::FailurePolicy;
 use crate::FedState;
 use crate::Federate;
 use crate::FederationRTI;
 
-struct StopGranted {
+pub(crate) struct StopGranted {
     _lf_rti_stop_granted_already_sent_to_federates: bool,
 }
 
@@ -49,42 +90,403 @@ impl StopGranted {
     }
 }
 
+/**
+ * Progress of the handshakes, which the RTI waits for before it stops
+ * accepting connections.
+ */
+#[derive(Default)]
+struct HandshakeProgress {
+    /// Number of federates that have completed the handshake.
+    federates: usize,
+    /// Number of those federates that are not transient.
+    persistent_federates: usize,
+    /// Number of those federates that have announced a control connection.
+    control_channels_announced: usize,
+    /// Number of control connections that have been opened or rejected.
+    control_channels_received: usize,
+}
+
+/**
+ * What the threads that complete the handshakes of new connections need to
+ * hand the federates over to their scheduling threads.
+ */
+#[derive(Clone)]
+struct Handover {
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    progress: Arc<(Mutex<HandshakeProgress>, Condvar)>,
+    federate_senders: Arc<Vec<Sender<(Connection, FederateStream)>>>,
+    scheduling_threads: Arc<Mutex<SchedulingThreads>>,
+    handshake_timeout_ms: u64,
+}
+
+impl Handover {
+    /**
+     * Return the index of the scheduling thread of the federate, which has
+     * declared its neighbors, so that its weakly connected component is
+     * known.
+     */
+    fn scheduling_thread(&self, fed_id: u16) -> usize {
+        let locked_rti = read_rti(&self._f_rti);
+        self.scheduling_threads
+            .lock()
+            .unwrap()
+            .thread_of(fed_id, locked_rti.partitions())
+    }
+
+    /**
+     * Return the time by which a handshake that starts now has to complete,
+     * if any.
+     */
+    fn handshake_deadline(&self) -> Option<std::time::Instant> {
+        (self.handshake_timeout_ms > 0)
+            .then(|| std::time::Instant::now() + Duration::from_millis(self.handshake_timeout_ms))
+    }
+}
+
+/**
+ * Result of waiting for a message from a federate.
+ */
+#[derive(PartialEq)]
+enum PollOutcome {
+    /// No message arrived in time.
+    Idle,
+    /// A message has been handled.
+    Handled,
+    /// The connection has been closed or the federate is disconnected.
+    Closed,
+}
+
 pub struct Server {
     port: String,
 }
 
 impl Server {
-    pub fn create_server(port: String) -> Server {
+    pub(crate) fn create_server(port: String) -> Server {
         // TODO: handle TCP and UDP cases
         Server { port }
     }
 
-    pub fn wait_for_federates(&mut self, _f_rti: FederationRTI) {
+    /**
+     * Accept connections from the federates and handle their messages until all
+     * of them have exited. Return the IDs of the federates that have failed.
+     */
+    pub fn wait_for_federates(&mut self, mut _f_rti: FederationRTI) -> Vec<u16> {
+        let run_started = std::time::Instant::now();
+        trace::configure(_f_rti.log_level(), _f_rti.trace_lines_per_second());
+        trace::configure_federates(_f_rti.federate_log_levels());
+        tag::set_printed_resolution(_f_rti.time_resolution());
+        tag::set_printed_time_format(_f_rti.time_format());
+        tag::set_printed_start_time(tag::NEVER);
+        log_sink::set_timestamps(_f_rti.log_timestamps());
+        log_sink::configure(_f_rti.log_sink(), _f_rti.log_socket().as_deref());
+        if let Some(path) = _f_rti.log_file() {
+            log_sink::configure_file(path, _f_rti.output_key().as_ref());
+        }
         let mut address = String::from("0.0.0.0:");
         address.push_str(self.port.as_str());
-        let socket = TcpListener::bind(address).unwrap();
+        let socket = Self::bind_listener(&address);
+        let udp_socket = if _f_rti.clock_sync_global_status() >= ClockSyncStat::ClockSyncOn {
+            let port = socket.local_addr().unwrap().port();
+            clock_sync::bind_udp_socket(port).inspect(|_| _f_rti.set_final_port_udp(port))
+        } else {
+            None
+        };
         // accept connections and process them, spawning a new thread for each one
-        println!("Server listening on port {}", self.port);
+        info!("Server listening on port {}", self.port);
         let start_time = Arc::new(Mutex::new(StartTime::new()));
-        let received_start_times = Arc::new((Mutex::new(false), Condvar::new()));
         let sent_start_time = Arc::new((Mutex::new(false), Condvar::new()));
         let stop_granted = Arc::new(Mutex::new(StopGranted::new()));
+        let progress_period_ms = _f_rti.progress_period_ms();
+        // Logical time is not expected to follow physical time in fast mode.
+        let max_lag = _f_rti.max_lag().filter(|_| !_f_rti.fast_mode());
+        let silent_grace_ms = _f_rti.silent_grace_ms();
+        let resume_window_ms = _f_rti.resume_window_ms();
+        let admin_port = _f_rti.admin_port();
+        let status_port = _f_rti.status_port();
+        let clock_sync_period_ns = _f_rti.clock_sync_period_ns();
+        let clock = _f_rti.physical_clock();
+        _f_rti.set_event_clock(clock.clone());
+        let tracer = if _f_rti.tracing_enabled() {
+            Tracer::start(
+                _f_rti.trace_file(),
+                clock.clone(),
+                _f_rti.output_key().as_ref(),
+                _f_rti.trace_budget().clone(),
+            )
+        } else {
+            None
+        };
+        if let Some((tracer, _)) = &tracer {
+            _f_rti.set_tracer(tracer.clone());
+        }
+        let shutdown = _f_rti.shutdown_handle();
+        let arc_rti = Arc::new(RwLock::new(_f_rti));
+        let shutdown_handle = {
+            let shutdown = shutdown.clone();
+            let arc_rti = arc_rti.clone();
+            thread::spawn(move || {
+                if let Some(reason) = shutdown.wait() {
+                    Self::shut_down(&arc_rti, &reason);
+                }
+            })
+        };
+        // Start the admin console before federates connect so that operators
+        // can pause the federation before it starts.
+        let admin_handle = admin_port.map(|admin_port| {
+            let listener = TcpListener::bind(("127.0.0.1", admin_port)).unwrap();
+            info!("RTI: Admin console listening on port {}.", admin_port);
+            AdminConsole::new(
+                arc_rti.clone(),
+                start_time.clone(),
+                sent_start_time.clone(),
+                stop_granted.clone(),
+            )
+            .start_admin_thread(listener)
+        });
+        let status_handle = status_port.map(|status_port| {
+            let listener = TcpListener::bind(("127.0.0.1", status_port)).unwrap();
+            info!("RTI: Status server listening on port {}.", status_port);
+            StatusServer::new(arc_rti.clone(), start_time.clone()).start_status_thread(listener)
+        });
+        let parent_link = Self::connect_to_parent(&arc_rti);
+        let grant_notifier = lock_rti(&arc_rti).grant_notifier();
+        let notifier_handle =
+            GrantNotifier::start_notifier_thread(grant_notifier.clone(), arc_rti.clone());
         let handles = self.connect_to_federates(
             socket,
-            _f_rti,
-            start_time,
-            received_start_times,
-            sent_start_time,
-            stop_granted,
+            arc_rti.clone(),
+            start_time.clone(),
+            sent_start_time.clone(),
+            stop_granted.clone(),
         );
 
-        println!("RTI: All expected federates have connected. Starting execution.");
+        if !shutdown.requested() {
+            info!("RTI: All expected federates have connected. Starting execution.");
+        }
+
+        // Runtime clock synchronization is only needed if a federate does it.
+        let clock_sync_handle = match udp_socket {
+            Some(udp_socket)
+                if lock_rti(&arc_rti)
+                    .enclaves()
+                    .iter()
+                    .any(|fed| fed.clock_synchronization_enabled()) =>
+            {
+                Some(clock_sync::start_clock_sync_thread(
+                    udp_socket,
+                    arc_rti.clone(),
+                    start_time.clone(),
+                    sent_start_time.clone(),
+                    clock.clone(),
+                    clock_sync_period_ns,
+                ))
+            }
+            _ => None,
+        };
+
+        let silence_handle = if silent_grace_ms > 0 {
+            Some(silence::start_silence_thread(
+                arc_rti.clone(),
+                start_time.clone(),
+                clock.clone(),
+                silent_grace_ms,
+            ))
+        } else {
+            None
+        };
+        let resumption_handle = if resume_window_ms > 0 {
+            Some(resumption::start_resumption_thread(
+                arc_rti.clone(),
+                start_time.clone(),
+                stop_granted,
+                clock.clone(),
+                resume_window_ms,
+            ))
+        } else {
+            None
+        };
+        let lag_handle = max_lag.map(|max_lag| {
+            lag::start_lag_thread(arc_rti.clone(), start_time.clone(), clock.clone(), max_lag)
+        });
+        let (snapshot_trigger, snapshot_file, checkpoint_period_ms) = {
+            let locked_rti = read_rti(&arc_rti);
+            (
+                locked_rti.snapshot_trigger(),
+                locked_rti.snapshot_file().clone(),
+                locked_rti.checkpoint_period_ms(),
+            )
+        };
+        let snapshot_handle = snapshot_file.map(|path| {
+            snapshot::start_snapshot_thread(
+                arc_rti.clone(),
+                start_time.clone(),
+                snapshot_trigger.clone(),
+                path,
+                checkpoint_period_ms,
+            )
+        });
+        let progress_handle = if progress_period_ms > 0 {
+            Some(ProgressReporter::start_progress_thread(
+                arc_rti.clone(),
+                start_time,
+                clock.clone(),
+                progress_period_ms,
+            ))
+        } else {
+            None
+        };
 
         for handle in handles {
             handle.join().unwrap();
         }
+        if let Some(parent_link) = parent_link {
+            parent_link.stop();
+        }
+        shutdown.finish();
+        shutdown_handle.join().unwrap();
+        grant_notifier.stop();
+        notifier_handle.join().unwrap();
+        if let Some((tracer, tracer_handle)) = tracer {
+            tracer.stop();
+            tracer_handle.join().unwrap();
+        }
 
-        // TODO: _f_rti.set_all_federates_exited(true);
+        let mut failed_federates = Vec::new();
+        {
+            let mut locked_rti = lock_rti(&arc_rti);
+            locked_rti.set_all_federates_exited(true);
+
+            info!("RTI: Run summary:");
+            info!("\tThe run took {} ms.", run_started.elapsed().as_millis());
+            if let Some(reason) = shutdown.reason() {
+                info!("\tThe RTI was shut down because of {}.", reason);
+            }
+            for fed in locked_rti.enclaves().iter() {
+                match fed.failure_reason() {
+                    Some(reason) => {
+                        info!(
+                            "\tFederate {} failed: {}.",
+                            federate_label(fed.e().id()),
+                            reason
+                        );
+                        failed_federates.push(fed.e().id());
+                    }
+                    None if fed.stream().is_none() => info!(
+                        "\tFederate {} never connected.",
+                        federate_label(fed.e().id())
+                    ),
+                    None => info!(
+                        "\tFederate {} exited normally.",
+                        federate_label(fed.e().id())
+                    ),
+                }
+            }
+            for fed in locked_rti.enclaves().iter_mut() {
+                let fed_id = fed.e().id();
+                if fed.provisional_grants().sent() > 0 {
+                    info!(
+                        "\tFederate {}: {}.",
+                        federate_label(fed_id),
+                        fed.provisional_grants().describe()
+                    );
+                }
+                if let Some(description) = fed
+                    .egress_shaper()
+                    .as_ref()
+                    .and_then(EgressShaper::describe)
+                {
+                    info!("\tFederate {}: {}.", federate_label(fed_id), description);
+                }
+                if let Some(limiter) = fed.control_rate_limiter().as_ref() {
+                    if limiter.flagged() {
+                        info!(
+                            "\tFederate {} was flagged for sending {} NET and LTC message(s) in excess of the rate.",
+                            federate_label(fed_id),
+                            limiter.excess_messages()
+                        );
+                    } else if limiter.excess_messages() > 0 {
+                        info!(
+                            "\tFederate {} sent {} NET and LTC message(s) in excess of the rate, of which {} were superseded by later ones.",
+                            federate_label(fed_id),
+                            limiter.excess_messages(),
+                            limiter.coalesced_messages()
+                        );
+                    }
+                }
+            }
+            if let Some(description) = locked_rti.wait_stats().describe_start_time_barrier() {
+                info!("\tStart time barrier: {}.", description);
+            }
+            let report = locked_rti.wait_stats().report();
+            if !report.is_empty() {
+                info!("\tWaits for the start time to be sent, by what was held back:");
+                for line in report {
+                    info!("\t\t{}", line);
+                }
+            }
+            if let Some(lock_stats) = locked_rti.lock_stats() {
+                let report = lock_stats.lock().unwrap().report();
+                if report.is_empty() {
+                    info!("\tNo contention on the lock of the RTI state.");
+                } else {
+                    info!("\tContention on the lock of the RTI state by call site:");
+                    for line in report {
+                        info!("\t\t{}", line);
+                    }
+                }
+            }
+            for inspector in locked_rti.payload_inspectors() {
+                if let Some(summary) = inspector.summary() {
+                    info!("\t{}", summary);
+                }
+            }
+            for budget in locked_rti.memory_budgets() {
+                info!(
+                    "\tMemory budget of the {}: {}.",
+                    budget.name(),
+                    budget.describe()
+                );
+            }
+            #[cfg(feature = "run_db")]
+            if let Some(path) = locked_rti.run_db().clone() {
+                let summary =
+                    RunSummary::collect(&mut locked_rti, run_started.elapsed(), shutdown.reason());
+                match run_db::record_run(&path, &summary) {
+                    Ok(run_id) => info!("\tRecorded as run {} in {}.", run_id, path),
+                    Err(e) => warn!("RTI: Failed to record the run in {}: {}.", path, e),
+                }
+            }
+        }
+        // The periodic threads may sleep on a virtual clock that is no longer
+        // advanced.
+        clock.release();
+        if let Some(progress_handle) = progress_handle {
+            progress_handle.join().unwrap();
+        }
+        snapshot_trigger.finish();
+        if let Some(snapshot_handle) = snapshot_handle {
+            snapshot_handle.join().unwrap();
+        }
+        if let Some(lag_handle) = lag_handle {
+            lag_handle.join().unwrap();
+        }
+        if let Some(silence_handle) = silence_handle {
+            silence_handle.join().unwrap();
+        }
+        if let Some(resumption_handle) = resumption_handle {
+            resumption_handle.join().unwrap();
+        }
+        if let Some(clock_sync_handle) = clock_sync_handle {
+            clock_sync_handle.join().unwrap();
+        }
+        if let Some(admin_handle) = admin_handle {
+            Self::wake_up_listener(admin_port.unwrap());
+            admin_handle.join().unwrap();
+        }
+        if let Some(status_handle) = status_handle {
+            Self::wake_up_listener(status_port.unwrap());
+            status_handle.join().unwrap();
+        }
 
         // The socket server will not continue to accept connections after all the federates
         // have joined.
@@ -102,446 +504,2018 @@ impl Server {
         // the OS is preventing another program from accidentally receiving
         // duplicated packets intended for this program.
         // TODO: close(socket_descriptor);
+
+        log_sink::close_file();
+        failed_federates
     }
 
     fn connect_to_federates(
         &mut self,
         socket: TcpListener,
-        mut _f_rti: FederationRTI,
+        arc_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
-        received_start_times: Arc<(Mutex<bool>, Condvar)>,
         sent_start_time: Arc<(Mutex<bool>, Condvar)>,
         stop_granted: Arc<Mutex<StopGranted>>,
     ) -> Vec<JoinHandle<()>> {
         // TODO: Error-handling of unwrap()
-        let number_of_enclaves: usize = _f_rti.number_of_enclaves().try_into().unwrap();
-        let arc_rti = Arc::new(Mutex::new(_f_rti));
-        let mut handle_list: Vec<JoinHandle<()>> = vec![];
-        for _i in 0..number_of_enclaves {
-            let cloned_rti = Arc::clone(&arc_rti);
-            // Wait for an incoming connection request.
-            // The following blocks until a federate connects.
-            for stream in socket.incoming() {
-                match stream {
-                    Ok(mut stream) => {
-                        println!("\nNew connection: {}", stream.peer_addr().unwrap());
-
-                        // The first message from the federate should contain its ID and the federation ID.
-                        let fed_id =
-                            self.receive_and_check_fed_id_message(&mut stream, cloned_rti.clone());
-                        // TODO: Error-handling of fed_id.try_into().unwrap()
-                        if fed_id >= 0
-                            && self.receive_connection_information(
-                                fed_id.try_into().unwrap(),
-                                &mut stream,
-                                cloned_rti.clone(),
-                            )
-                            && self.receive_udp_message_and_set_up_clock_sync(
-                                fed_id.try_into().unwrap(),
-                                &mut stream,
-                                cloned_rti.clone(),
-                            )
-                        {
-                            // Create a thread to communicate with the federate.
-                            // This has to be done after clock synchronization is finished
-                            // or that thread may end up attempting to handle incoming clock
-                            // synchronization messages.
-                            let cloned_start_time = Arc::clone(&start_time);
-                            let cloned_received_start_times = Arc::clone(&received_start_times);
-                            let cloned_sent_start_time = Arc::clone(&sent_start_time);
-                            let cloned_stop_granted = Arc::clone(&stop_granted);
-                            let _handle = thread::spawn(move || {
-                                // This closure is the implementation of federate_thread_TCP in rti_lib.c
-                                {
-                                    let mut locked_rti = cloned_rti.lock().unwrap();
-                                    // FIXME: Handle "as usize" properly.
-                                    let fed: &mut Federate =
-                                        &mut locked_rti.enclaves()[fed_id as usize];
-                                    fed.set_stream(stream.try_clone().unwrap());
-                                }
-
-                                // Buffer for incoming messages.
-                                // This does not constrain the message size because messages
-                                // are forwarded piece by piece.
-                                let mut buffer = vec![0 as u8; 1];
-
-                                // Listen for messages from the federate.
-                                loop {
-                                    {
-                                        let mut locked_rti = cloned_rti.lock().unwrap();
-                                        let enclaves = locked_rti.enclaves();
-                                        // FIXME: Replace "as usize" properly.
-                                        let fed: &mut Federate = &mut enclaves[fed_id as usize];
-                                        let enclave = fed.enclave();
-                                        if enclave.state() == FedState::NotConnected {
-                                            break;
-                                        }
-                                    }
-                                    // Read no more than one byte to get the message type.
-                                    // FIXME: Handle unwrap properly.
-                                    let bytes_read = NetUtil::read_from_stream(
-                                        &mut stream,
-                                        &mut buffer,
-                                        fed_id.try_into().unwrap(),
-                                    );
-                                    if bytes_read < 1 {
-                                        // Socket is closed
-                                        println!("RTI: Socket to federate {} is closed. Exiting the thread.",
-                                            fed_id);
-                                        let mut locked_rti = cloned_rti.lock().unwrap();
-                                        let enclaves = locked_rti.enclaves();
-                                        // FIXME: Replace "as usize" properly.
-                                        let fed: &mut Federate = &mut enclaves[fed_id as usize];
-                                        fed.enclave().set_state(FedState::NotConnected);
-                                        // FIXME: We need better error handling here, but do not stop execution here.
-                                        break;
-                                    }
-                                    println!(
-                                        "RTI: Received message type {} from federate {}.",
-                                        buffer[0], fed_id
-                                    );
-                                    match MsgType::to_msg_type(buffer[0]) {
-                                        MsgType::Timestamp => Self::handle_timestamp(
-                                            // &buffer,
-                                            fed_id.try_into().unwrap(),
-                                            &mut stream,
-                                            cloned_rti.clone(),
-                                            cloned_start_time.clone(),
-                                            cloned_received_start_times.clone(),
-                                            cloned_sent_start_time.clone(),
-                                        ),
-                                        MsgType::Resign => {
-                                            Self::handle_federate_resign(
-                                                fed_id.try_into().unwrap(),
-                                                cloned_rti.clone(),
-                                                cloned_start_time.clone(),
-                                                cloned_sent_start_time.clone(),
-                                            );
-                                            return;
-                                        }
-                                        MsgType::TaggedMessage => Self::handle_timed_message(
-                                            buffer[0],
-                                            fed_id.try_into().unwrap(),
-                                            &mut stream,
-                                            cloned_rti.clone(),
-                                            cloned_start_time.clone(),
-                                            cloned_sent_start_time.clone(),
-                                        ),
-                                        MsgType::NextEventTag => Self::handle_next_event_tag(
-                                            fed_id.try_into().unwrap(),
-                                            &mut stream,
-                                            cloned_rti.clone(),
-                                            cloned_start_time.clone(),
-                                            cloned_sent_start_time.clone(),
-                                        ),
-                                        MsgType::LogicalTagComplete => {
-                                            Self::handle_logical_tag_complete(
-                                                fed_id.try_into().unwrap(),
-                                                &mut stream,
-                                                cloned_rti.clone(),
-                                                cloned_start_time.clone(),
-                                                cloned_sent_start_time.clone(),
-                                            )
-                                        }
-                                        // FIXME: Reviewed until here.
-                                        // Need to also look at
-                                        // notify_advance_grant_if_safe()
-                                        // and notify_downstream_advance_grant_if_safe()
-                                        MsgType::StopRequest => Self::handle_stop_request_message(
-                                            fed_id.try_into().unwrap(),
-                                            &mut stream,
-                                            cloned_rti.clone(),
-                                            cloned_start_time.clone(),
-                                            cloned_stop_granted.clone(),
-                                        ),
-                                        MsgType::StopRequestReply => {
-                                            Self::handle_stop_request_reply(
-                                                fed_id.try_into().unwrap(),
-                                                &mut stream,
-                                                cloned_rti.clone(),
-                                                cloned_start_time.clone(),
-                                                cloned_stop_granted.clone(),
-                                            )
-                                        }
-                                        MsgType::PortAbsent => Self::handle_port_absent_message(
-                                            &buffer,
-                                            fed_id.try_into().unwrap(),
-                                            &mut stream,
-                                            cloned_rti.clone(),
-                                            cloned_start_time.clone(),
-                                            cloned_sent_start_time.clone(),
-                                        ),
-                                        _ => {
-                                            let mut locked_rti = cloned_rti.lock().unwrap();
-                                            let fed: &mut Federate =
-                                                &mut locked_rti.enclaves()[fed_id as usize];
-                                            println!("RTI received from federate {} an unrecognized TCP message type: {}.", fed.enclave().id(), buffer[0]);
-                                        }
-                                    }
-                                }
-                            });
-                            // TODO: Need to set handle to federate.thread_id?
-                            handle_list.push(_handle);
-                        }
-                        break;
-                    }
-                    Err(e) => {
-                        println!("RTI failed to accept the socket. {}.", e);
-                        /* connection failed */
-                        // FIXME: This should not exit on error, but rather just reject the connection.
-                        std::process::exit(1);
+        let number_of_enclaves: usize;
+        let number_of_persistent_federates: usize;
+        let number_of_io_threads;
+        let number_of_scheduling_threads;
+        let io_cpus;
+        let scheduling_cpus;
+        let realtime_priority;
+        let components;
+        {
+            let locked_rti = lock_rti(&arc_rti);
+            number_of_enclaves = locked_rti.number_of_enclaves().try_into().unwrap();
+            number_of_persistent_federates = locked_rti
+                .number_of_persistent_federates()
+                .try_into()
+                .unwrap();
+            // More threads than federates would never be used.
+            number_of_io_threads = locked_rti.io_threads().min(number_of_enclaves);
+            number_of_scheduling_threads = locked_rti.scheduling_threads().min(number_of_enclaves);
+            io_cpus = locked_rti.io_cpus().clone();
+            scheduling_cpus = locked_rti.scheduling_cpus().clone();
+            realtime_priority = locked_rti.realtime_priority();
+            components = locked_rti
+                .federation_metadata()
+                .as_ref()
+                .map(|metadata| metadata.components());
+        }
+        info!(
+            "RTI: Using {} I/O thread(s) and {} scheduling thread(s).",
+            number_of_io_threads, number_of_scheduling_threads
+        );
+
+        // Create the threads that handle the messages of the federates once
+        // they have connected. The federates of different weakly connected
+        // components are served by different threads where possible. Unless
+        // the components are known in advance, they are learned as the
+        // federates connect. See Handover::scheduling_thread.
+        let scheduling_threads = Arc::new(Mutex::new(match &components {
+            Some(components) => {
+                let scheduling_thread_of =
+                    partition::assign_scheduling_threads(components, number_of_scheduling_threads);
+                if components.len() > 1 {
+                    for component in components {
+                        let mut threads: Vec<usize> = component
+                            .iter()
+                            .map(|id| scheduling_thread_of[usize::from(*id)])
+                            .collect();
+                        threads.sort_unstable();
+                        threads.dedup();
+                        info!(
+                            "RTI: Federates {:?}, which are independent of the others, are served by scheduling thread(s) {:?}.",
+                            component, threads
+                        );
                     }
                 }
+                SchedulingThreads::assigned(scheduling_thread_of, number_of_scheduling_threads)
             }
+            None => SchedulingThreads::unassigned(number_of_enclaves, number_of_scheduling_threads),
+        }));
+        let mut federate_senders = vec![];
+        let mut handle_list = vec![];
+        for i in 0..number_of_scheduling_threads {
+            let (federate_sender, federate_receiver) =
+                mpsc::channel::<(Connection, FederateStream)>();
+            federate_senders.push(federate_sender);
+            handle_list.push(Self::start_scheduling_thread(
+                ThreadPlacement::new(
+                    format!("scheduling thread {}", i),
+                    &scheduling_cpus,
+                    i,
+                    realtime_priority,
+                ),
+                federate_receiver,
+                arc_rti.clone(),
+                start_time.clone(),
+                sent_start_time.clone(),
+                stop_granted.clone(),
+            ));
         }
-        // All federates have connected.
-        println!("All federates have connected to RTI.");
+        let federate_senders = Arc::new(federate_senders);
 
-        let cloned_rti = Arc::clone(&arc_rti);
-        let mut locked_rti = cloned_rti.lock().unwrap();
-        let clock_sync_global_status = locked_rti.clock_sync_global_status();
-        if clock_sync_global_status >= ClockSyncStat::ClockSyncOn {
-            // Create the thread that performs periodic PTP clock synchronization sessions
-            // over the UDP channel, but only if the UDP channel is open and at least one
-            // federate is performing runtime clock synchronization.
-            let mut clock_sync_enabled = false;
-            for i in 0..locked_rti.number_of_enclaves() {
-                if locked_rti.enclaves()[i as usize].clock_synchronization_enabled() {
-                    clock_sync_enabled = true;
-                    break;
+        // The accept thread only enqueues new sockets. The queue is bounded so
+        // that the accept thread waits while all I/O threads are busy.
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(number_of_io_threads);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let port = socket.local_addr().unwrap().port();
+        lock_rti(&arc_rti).set_listening_port(Some(port));
+        let all_connected = Arc::new(AtomicBool::new(false));
+        let accept_handle =
+            Self::start_accept_thread(socket, sender, all_connected.clone(), arc_rti.clone());
+
+        let progress: Arc<(Mutex<HandshakeProgress>, Condvar)> =
+            Arc::new((Mutex::new(HandshakeProgress::default()), Condvar::new()));
+        let handshake_timeout_ms = lock_rti(&arc_rti).handshake_timeout_ms();
+        let tls_config = lock_rti(&arc_rti).tls_config().clone();
+        let handover = Handover {
+            _f_rti: arc_rti.clone(),
+            progress: progress.clone(),
+            federate_senders: federate_senders.clone(),
+            scheduling_threads: scheduling_threads.clone(),
+            handshake_timeout_ms,
+        };
+        // The federates on connections shared by several federates may start
+        // their handshakes until the RTI stops accepting connections.
+        let shared_handover = Arc::new(Mutex::new(Some(handover.clone())));
+        let mut io_handles = vec![];
+        for i in 0..number_of_io_threads {
+            let receiver = receiver.clone();
+            let handover = handover.clone();
+            let shared_handover = shared_handover.clone();
+            let tls_config = tls_config.clone();
+            let placement =
+                ThreadPlacement::new(format!("I/O thread {}", i), &io_cpus, i, realtime_priority);
+            io_handles.push(thread::spawn(move || {
+                placement.apply();
+                loop {
+                    let next_stream = receiver.lock().unwrap().recv();
+                    let Ok(socket) = next_stream else {
+                        // The accept thread has exited.
+                        break;
+                    };
+                    // A client that does not complete the handshake in time,
+                    // such as a port scanner, is closed to free the thread.
+                    let deadline = handover.handshake_deadline();
+                    let Some(stream) = Self::accept_stream(socket, tls_config.as_ref(), deadline)
+                    else {
+                        continue;
+                    };
+                    Self::complete_handshake(stream, deadline, &handover, &shared_handover);
+                }
+            }));
+        }
+        drop(handover);
+
+        let shutdown = lock_rti(&arc_rti).shutdown_handle();
+        {
+            // Keep accepting connections until every federate that is not
+            // transient has completed its handshake and every federate has
+            // opened the control connection it announced, or the RTI shuts
+            // down.
+            let (lock, condvar) = &*progress;
+            let notified_progress = progress.clone();
+            shutdown.on_request(Box::new(move || {
+                let (lock, condvar) = &*notified_progress;
+                let _progress = lock.lock().unwrap();
+                condvar.notify_all();
+            }));
+            let connect_timeout_s = lock_rti(&arc_rti).connect_timeout_s();
+            let deadline = (connect_timeout_s > 0)
+                .then(|| std::time::Instant::now() + Duration::from_secs(connect_timeout_s));
+            let mut progress = lock.lock().unwrap();
+            while (progress.persistent_federates < number_of_persistent_federates
+                || progress.control_channels_received < progress.control_channels_announced)
+                && !shutdown.requested()
+            {
+                match deadline {
+                    Some(deadline) => {
+                        let now = std::time::Instant::now();
+                        if now >= deadline {
+                            drop(progress);
+                            shutdown.request(&format!(
+                                "the timeout of {} s for the federates to connect",
+                                connect_timeout_s
+                            ));
+                            break;
+                        }
+                        progress = condvar.wait_timeout(progress, deadline - now).unwrap().0;
+                    }
+                    None => progress = condvar.wait(progress).unwrap(),
                 }
             }
-            if locked_rti.final_port_udp() != u16::MAX && clock_sync_enabled {
-                println!("\tNEED to create clock_synchronization_thread thread..");
-                // TODO: Implement the following.
-                // lf_thread_create(&_f_rti->clock_thread, clock_synchronization_thread, NULL);
+        }
+        let stop_accepting = {
+            let arc_rti = arc_rti.clone();
+            move || {
+                all_connected.store(true, Ordering::SeqCst);
+                Self::wake_up_listener(port);
+                accept_handle.join().unwrap();
+                lock_rti(&arc_rti).set_listening_port(None);
+                for io_handle in io_handles {
+                    io_handle.join().unwrap();
+                }
+                shared_handover.lock().unwrap().take();
+                // No more federates will be handed over, so the scheduling
+                // threads can exit once their federates have exited.
+                drop(federate_senders);
+            }
+        };
+        if number_of_persistent_federates < number_of_enclaves
+            || lock_rti(&arc_rti).resume_window_ms() > 0
+        {
+            // Transient federates may join and rejoin, and federates may
+            // resume their sessions, until the other federates have exited.
+            let cloned_rti = Arc::clone(&arc_rti);
+            handle_list.push(thread::spawn(move || {
+                while !Self::persistent_federates_exited(&cloned_rti) {
+                    thread::sleep(Duration::from_millis(PERSISTENT_EXIT_POLL_PERIOD_MS));
+                }
+                stop_accepting();
+            }));
+            info!("All federates other than the transient ones have connected to RTI.");
+        } else {
+            stop_accepting();
+            if !shutdown.requested() {
+                // All federates have connected.
+                info!("All federates have connected to RTI.");
             }
         }
 
+        let cloned_rti = Arc::clone(&arc_rti);
+        let mut locked_rti = lock_rti(&cloned_rti);
+        if !locked_rti.assigned_federate_ids().is_empty() {
+            info!(
+                "RTI: Federate IDs assigned in the order in which the federates joined: {:?}.",
+                locked_rti.assigned_federate_ids()
+            );
+        }
+        Self::set_up_topology(&mut locked_rti);
         handle_list
     }
 
-    fn receive_and_check_fed_id_message(
-        &mut self,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
-    ) -> i32 {
-        // Buffer for message ID, federate ID, and federation ID length.
-        let length = 1 + mem::size_of::<u16>() + 1;
-        let mut first_buffer = vec![0 as u8; length];
-        // Initialize to an invalid value.
-        let fed_id;
-        let cloned_rti = Arc::clone(&_f_rti);
-        // Read bytes from the socket. We need 4 bytes.
-        // FIXME: This should not exit with error but rather should just reject the connection.
-        NetUtil::read_from_stream_errexit(stream, &mut first_buffer, 0, "");
-
-        // First byte received is the message type.
-        if first_buffer[0] != MsgType::FedIds.to_byte() {
-            if first_buffer[0] == MsgType::P2pSendingFedId.to_byte()
-                || first_buffer[0] == MsgType::P2pTaggedMessage.to_byte()
-            {
-                // The federate is trying to connect to a peer, not to the RTI.
-                // It has connected to the RTI instead.
-                // FIXME: This should not happen, but apparently has been observed.
-                // It should not happen because the peers get the port and IP address
-                // of the peer they want to connect to from the RTI.
-                // If the connection is a peer-to-peer connection between two
-                // federates, reject the connection with the WrongServer error.
-                Self::send_reject(stream, ErrType::WrongServer.to_byte());
-            } else {
-                Self::send_reject(stream, ErrType::UnexpectedMessage.to_byte());
+    /**
+     * Return true if all federates that are not transient have resigned or
+     * failed.
+     */
+    fn persistent_federates_exited(_f_rti: &Arc<RwLock<FederationRTI>>) -> bool {
+        lock_rti(_f_rti)
+            .enclaves()
+            .iter()
+            .all(|fed| fed.e().transient() || fed.e().state() == FedState::NotConnected)
+    }
+
+    /**
+     * Analyze the neighbor structures reported by the federates and report
+     * the problems in them unless this has been done already. The last
+     * federate may propose its start time before the accept loop notices that
+     * all federates have connected, so this is also done before the start
+     * time is sent, so that the problems are reported before execution.
+     */
+    fn set_up_topology(locked_rti: &mut FederationRTI) {
+        if locked_rti.topology().is_some() {
+            return;
+        }
+        let topology = Self::load_or_compute_topology(locked_rti);
+        for warning in topology.warnings() {
+            warn!("WARNING: {}", warning);
+            locked_rti.tolerate(&format!(
+                "inconsistent neighbor structures: {}",
+                warning.trim_end_matches('.')
+            ));
+        }
+        locked_rti.set_topology(topology);
+    }
+
+    /**
+     * Return the topology stored in the topology file if it describes the
+     * neighbor structures reported by the federates, and otherwise compute the
+     * topology and write it to the topology file, if any. The file is also
+     * rewritten if only the metadata of the federates changed.
+     */
+    fn load_or_compute_topology(locked_rti: &mut FederationRTI) -> Topology {
+        let federation_id = locked_rti.federation_id();
+        let topology_file = locked_rti.topology_file().clone();
+        if let Some(path) = &topology_file {
+            match fs::read_to_string(path).map(|text| Topology::parse(&text)) {
+                Ok(Ok(mut topology))
+                    if topology.describes(&federation_id, locked_rti.enclaves()) =>
+                {
+                    info!("RTI: Loaded the topology of the federation from {}.", path);
+                    if topology.update_metadata(locked_rti.enclaves()) {
+                        Self::write_topology(path, &topology);
+                    }
+                    return topology;
+                }
+                Ok(Ok(_)) => info!(
+                    "RTI: The topology in {} does not match the federates. Replacing it.",
+                    path
+                ),
+                Ok(Err(e)) => warn!(
+                    "RTI: WARNING: Invalid topology in {}: {}. Replacing it.",
+                    path, e
+                ),
+                // The file is written at the first run.
+                Err(_) => {}
             }
-            println!(
-                "RTI expected a MsgType::FedIds message. Got {} (see net_common.h).",
-                first_buffer[0]
-            );
-            return -1;
-        } else {
-            // Received federate ID.
-            // FIXME: Change from_le_bytes properly.
-            let u16_size = mem::size_of::<u16>();
-            fed_id = u16::from_le_bytes(first_buffer[1..(1 + u16_size)].try_into().unwrap());
-            println!("RTI received federate ID: {}.", fed_id);
+        }
+        let time_resolution = locked_rti.time_resolution();
+        let topology = Topology::compute(&federation_id, locked_rti.enclaves(), time_resolution);
+        if let Some(path) = &topology_file {
+            Self::write_topology(path, &topology);
+        }
+        topology
+    }
 
-            // Read the federation ID.  First read the length, which is one byte.
-            // FIXME: Change from_le_bytes properly.
-            let federation_id_length = u8::from_le_bytes(
-                first_buffer[(1 + u16_size)..(1 + u16_size + 1)]
-                    .try_into()
-                    .unwrap(),
-            );
-            let mut federation_id_buffer = vec![0 as u8; federation_id_length.into()];
-            NetUtil::read_from_stream_errexit(
-                stream,
-                &mut federation_id_buffer,
-                fed_id,
-                "federation id",
+    fn write_topology(path: &str, topology: &Topology) {
+        match fs::write(path, topology.to_text()) {
+            Ok(()) => info!("RTI: Wrote the topology of the federation to {}.", path),
+            Err(e) => warn!(
+                "RTI: WARNING: Failed to write the topology to {}: {}.",
+                path, e
+            ),
+        }
+    }
+
+    /**
+     * Perform the handshake of the federate on a new connection before the
+     * given deadline, if any, and hand the federate over to its scheduling
+     * thread. A connection shared by several federates is handed over to a
+     * thread that reads its frames instead.
+     */
+    fn complete_handshake(
+        mut stream: FederateStream,
+        deadline: Option<std::time::Instant>,
+        handover: &Handover,
+        shared_handover: &Arc<Mutex<Option<Handover>>>,
+    ) {
+        let cloned_rti = &handover._f_rti;
+        let connection = if let Some(deadline) = deadline {
+            let connection = Self::dumped_handshake(
+                &mut DeadlineStream::new(&mut stream, deadline),
+                cloned_rti.clone(),
             );
-            let federation_id_received;
-            match String::from_utf8(federation_id_buffer) {
-                Ok(federation_id) => {
-                    federation_id_received = federation_id;
+            // NOTE: Ignore errors because the socket may be closed.
+            let _ = stream.set_read_timeout(None);
+            connection
+        } else {
+            Self::dumped_handshake(&mut stream, cloned_rti.clone())
+        };
+        let (lock, condvar) = &*handover.progress;
+        match (connection.state(), connection.fed_id()) {
+            (ConnectionState::AwaitingTimestamp, Some(fed_id)) => {
+                let announced = connection.control_channel_announced();
+                let transient = lock_rti(cloned_rti).enclaves()[usize::from(fed_id)]
+                    .e()
+                    .transient();
+                // Hand the federate over to its scheduling thread.
+                // This has to be done after clock synchronization is finished
+                // or that thread may end up attempting to handle incoming clock
+                // synchronization messages.
+                let index = handover.scheduling_thread(fed_id);
+                handover.federate_senders[index]
+                    .send((connection, stream))
+                    .unwrap();
+                let mut progress = lock.lock().unwrap();
+                progress.federates += 1;
+                if !transient {
+                    progress.persistent_federates += 1;
                 }
-                Err(e) => {
-                    println!(
-                        "Failed to convert a message buffer to a federation id ({})",
-                        e
-                    );
-                    return -1;
+                if announced {
+                    progress.control_channels_announced += 1;
                 }
+                condvar.notify_all();
             }
-
-            println!("RTI received federation ID: {}.", federation_id_received);
-            let number_of_enclaves;
-            let federation_id;
-            {
-                let locked_rti = cloned_rti.lock().unwrap();
-                number_of_enclaves = locked_rti.number_of_enclaves();
-                federation_id = locked_rti.federation_id();
+            (ConnectionState::Running, Some(fed_id)) => {
+                // The federate has resumed its session, so it has
+                // already been counted.
+                let index = handover.scheduling_thread(fed_id);
+                handover.federate_senders[index]
+                    .send((connection, stream))
+                    .unwrap();
             }
-            // Compare the received federation ID to mine.
-            if federation_id_received != federation_id {
-                // Federation IDs do not match. Send back a MSG_TYPE_Reject message.
-                println!(
-                    "WARNING: Federate from another federation {} attempted to connect to RTI in federation {}.",
-                    federation_id_received, federation_id
+            (ConnectionState::ControlChannel, Some(fed_id)) => {
+                Self::open_control_channel(fed_id, &mut stream, cloned_rti.clone());
+                lock.lock().unwrap().control_channels_received += 1;
+                condvar.notify_all();
+            }
+            (ConnectionState::Multiplexed, _) => {
+                Self::start_demultiplexer_thread(stream, shared_handover.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /**
+     * Spawn a thread that reads the frames of a connection shared by several
+     * federates and completes the handshake of each federate on it in a
+     * thread of its own, so that the federates do not wait for each other.
+     * The thread exits when the process of the federates closes the
+     * connection.
+     */
+    fn start_demultiplexer_thread(
+        stream: FederateStream,
+        shared_handover: Arc<Mutex<Option<Handover>>>,
+    ) {
+        let multiplexer = match Multiplexer::new(stream) {
+            Ok(multiplexer) => multiplexer,
+            Err(e) => {
+                warn!(
+                    "RTI: WARNING: Failed to take over a connection shared by federates: {}.",
+                    e
                 );
-                Self::send_reject(stream, ErrType::FederationIdDoesNotMatch.to_byte());
-                std::process::exit(1);
-            } else {
-                if i32::from(fed_id) >= number_of_enclaves {
-                    // Federate ID is out of range.
-                    println!(
-                        "RTI received federate ID {}, which is out of range.",
-                        fed_id
+                return;
+            }
+        };
+        let address = multiplexer
+            .peer_addr()
+            .map_or_else(|_| String::from("an unknown address"), |a| a.to_string());
+        info!("RTI: Federates share the connection from {}.", address);
+        thread::spawn(move || {
+            let result = multiplexer.run(|channel| {
+                let Some(handover) = shared_handover.lock().unwrap().clone() else {
+                    warn!(
+                        "RTI: WARNING: Closing the connection of federate {} shared with other federates because the RTI no longer accepts connections.",
+                        federate_label(channel.fed_id())
                     );
-                    Self::send_reject(stream, ErrType::FederateIdOutOfRange.to_byte());
-                    std::process::exit(1);
-                } else {
-                    let mut locked_rti = cloned_rti.lock().unwrap();
-                    let idx: usize = fed_id.into();
-                    let federate: &mut Federate = &mut locked_rti.enclaves()[idx];
-                    let enclave = federate.enclave();
-                    if enclave.state() != FedState::NotConnected {
-                        println!("RTI received duplicate federate ID: {}.", fed_id);
-                        Self::send_reject(stream, ErrType::FederateIdInUse.to_byte());
-                        std::process::exit(1);
+                    // NOTE: Ignore errors because the process may be gone.
+                    let _ = channel.shutdown(Shutdown::Both);
+                    return;
+                };
+                let deadline = handover.handshake_deadline();
+                let shared_handover = shared_handover.clone();
+                thread::spawn(move || {
+                    Self::complete_handshake(
+                        FederateStream::Multiplexed(channel),
+                        deadline,
+                        &handover,
+                        &shared_handover,
+                    )
+                });
+            });
+            match result {
+                Ok(started) => info!(
+                    "RTI: The connection from {} shared by {} federate connection(s) has been closed.",
+                    address, started
+                ),
+                Err(e) => warn!(
+                    "RTI: WARNING: The connection from {} shared by federates failed: {}.",
+                    address, e
+                ),
+            }
+        });
+    }
+
+    /**
+     * Spawn the thread that accepts connections and enqueues them for the
+     * handshake workers until all federates have connected. The thread blocks
+     * in accept(), so once no more federates are expected, it has to be woken
+     * up with wake_up_listener().
+     *
+     * If the listening socket fails, or an operator asks for it, the thread
+     * closes it and binds a new one to the same port. Connections that have
+     * already been accepted are not affected, but connections waiting to be
+     * accepted by the closed socket are refused and have to be retried by
+     * their federates.
+     */
+    fn start_accept_thread(
+        mut socket: TcpListener,
+        sender: SyncSender<TcpStream>,
+        all_connected: Arc<AtomicBool>,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> JoinHandle<()> {
+        let keepalive_s = lock_rti(&_f_rti).keepalive_s();
+        thread::spawn(move || {
+            while !all_connected.load(Ordering::SeqCst) {
+                let result = socket.accept();
+                if all_connected.load(Ordering::SeqCst) {
+                    break;
+                }
+                let relisten_requested = {
+                    let mut locked_rti = lock_rti(&_f_rti);
+                    let relisten_requested = locked_rti.relisten_requested();
+                    locked_rti.set_relisten_requested(false);
+                    relisten_requested
+                };
+                if relisten_requested {
+                    // NOTE: The connection that woke up the thread is dropped.
+                    info!("RTI: Reopening the listening socket as requested.");
+                    socket = Self::rebind_listener(socket);
+                    continue;
+                }
+                match result {
+                    Ok((stream, address)) => {
+                        debug!("\nNew connection: {}", address);
+                        if keepalive_s > 0 {
+                            if let Err(e) = NetUtil::set_keepalive(&stream, keepalive_s) {
+                                warn!(
+                                    "RTI: WARNING: Failed to enable keepalive on the connection from {}: {}.",
+                                    address, e
+                                );
+                            }
+                        }
+                        if sender.send(stream).is_err() {
+                            break;
+                        }
+                    }
+                    // The connection failed before it was accepted, which does
+                    // not affect the listening socket.
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            ErrorKind::ConnectionAborted
+                                | ErrorKind::ConnectionReset
+                                | ErrorKind::Interrupted
+                                | ErrorKind::TimedOut
+                                | ErrorKind::WouldBlock
+                        ) =>
+                    {
+                        error!("RTI failed to accept a connection: {}.", e);
+                    }
+                    Err(e) => {
+                        error!(
+                            "RTI failed to accept a connection: {}. Reopening the listening socket.",
+                            e
+                        );
+                        socket = Self::rebind_listener(socket);
                     }
                 }
             }
-            println!(
-                "Federation ID matches! \"{}(received)\" <-> \"{}(_f_rti)\"",
-                federation_id_received, federation_id
-            );
-
-            // TODO: Assign the address information for federate.
+        })
+    }
 
-            // Set the federate's state as pending
-            // because it is waiting for the start time to be
-            // sent by the RTI before beginning its execution.
-            {
-                let mut locked_rti = cloned_rti.lock().unwrap();
-                let idx: usize = fed_id.into();
-                let federate: &mut Federate = &mut locked_rti.enclaves()[idx];
-                let enclave: &mut Enclave = federate.enclave();
-                enclave.set_state(FedState::Pending);
+    /**
+     * Return the stream to a newly connected federate: its socket, or, if
+     * federates connect over TLS, the socket after the TLS handshake, which
+     * has to complete before the given deadline. Return None if the TLS
+     * handshake fails.
+     */
+    fn accept_stream(
+        socket: TcpStream,
+        tls_config: Option<&TlsConfig>,
+        deadline: Option<std::time::Instant>,
+    ) -> Option<FederateStream> {
+        let Some(tls_config) = tls_config else {
+            return Some(FederateStream::Tcp(socket));
+        };
+        let address = socket.peer_addr();
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            // NOTE: Ignore errors because the handshake below reports a closed
+            // socket.
+            let _ = socket.set_read_timeout(Some(remaining.max(Duration::from_millis(1))));
+        }
+        match tls_config.accept(socket) {
+            Ok(stream) => {
+                let _ = stream.set_read_timeout(None);
+                Some(stream)
+            }
+            Err(e) => {
+                warn!(
+                    "RTI: Closing the connection from {} because its TLS handshake failed: {}.",
+                    address.map_or_else(|_| String::from("an unknown address"), |a| a.to_string()),
+                    e
+                );
+                None
             }
-            println!("RTI responding with MsgType::Ack to federate {}.", fed_id);
-            // Send an MsgType::Ack message.
-            let ack_message: Vec<u8> = vec![MsgType::Ack.to_byte()];
-            NetUtil::write_to_stream_errexit(stream, &ack_message, fed_id, "MsgType::Ack message");
         }
+    }
 
-        fed_id.into()
+    /**
+     * Bind a listening socket to the given address, retrying while the
+     * address is not available. Exit if it does not become available.
+     */
+    fn bind_listener(address: &str) -> TcpListener {
+        let mut attempts = 0;
+        loop {
+            match TcpListener::bind(address) {
+                Ok(socket) => return socket,
+                Err(e) if attempts + 1 < LISTENER_BIND_ATTEMPTS => {
+                    attempts += 1;
+                    error!(
+                        "RTI failed to bind a listening socket to {}: {}. Retrying.",
+                        address, e
+                    );
+                    thread::sleep(Duration::from_millis(LISTENER_BIND_RETRY_PERIOD_MS));
+                }
+                Err(e) => {
+                    error!(
+                        "RTI failed to bind a listening socket to {}: {}. Giving up.",
+                        address, e
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 
-    fn send_reject(stream: &mut TcpStream, error_code: u8) {
-        println!("RTI sending MsgType::Reject.");
+    /**
+     * Close the given listening socket and bind a new one to the same port.
+     */
+    fn rebind_listener(socket: TcpListener) -> TcpListener {
+        let port = socket.local_addr().unwrap().port();
+        drop(socket);
+        let socket = Self::bind_listener(&format!("0.0.0.0:{}", port));
+        info!("RTI: Listening again on port {}.", port);
+        socket
+    }
+
+    /**
+     * Connect to the listener on the given port of the loopback interface so
+     * that a thread blocked in accept() on it returns and sees that it has to
+     * exit.
+     */
+    pub(crate) fn wake_up_listener(port: u16) {
+        // NOTE: Ignore errors because the thread may have exited already.
+        let _ = TcpStream::connect(("127.0.0.1", port));
+    }
+
+    /**
+     * Spawn a thread that handles the messages of the federates handed over to
+     * it through the given receiver. While the thread serves several
+     * federates, it polls their sockets in turn, so a message from one
+     * federate waits while a message from another one is being handled. The
+     * thread exits when all of its federates have exited and no more federates
+     * will be handed over.
+     */
+    fn start_scheduling_thread(
+        placement: ThreadPlacement,
+        receiver: Receiver<(Connection, FederateStream)>,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            placement.apply();
+            // This closure is the implementation of federate_thread_TCP in rti_lib.c
+            let mut federates: Vec<(Connection, FederateStream)> = Vec::new();
+            let mut more_federates = true;
+            // Time that a pass over the federates may wait for a message,
+            // which grows while they are idle so that an idle thread rarely
+            // wakes up.
+            let mut idle_timeout = Duration::from_millis(FEDERATE_POLL_TIMEOUT_MS);
+            loop {
+                let next_federate = if federates.is_empty() {
+                    match receiver.recv() {
+                        Ok(federate) => Some(federate),
+                        Err(_) => break,
+                    }
+                } else {
+                    match receiver.try_recv() {
+                        Ok(federate) => Some(federate),
+                        Err(TryRecvError::Empty) => None,
+                        Err(TryRecvError::Disconnected) => {
+                            more_federates = false;
+                            None
+                        }
+                    }
+                };
+                if let Some((connection, stream)) = next_federate {
+                    // A transient federate that joins again, or a federate that
+                    // resumes its session, replaces its previous connection if
+                    // it has not been closed yet.
+                    federates.retain(|(previous, _)| previous.fed_id() != connection.fed_id());
+                    let fed_id = connection.fed_id().unwrap();
+                    if connection.state() == ConnectionState::Running
+                        && !Self::resume_federate(fed_id, &stream, &_f_rti, &start_time)
+                    {
+                        continue;
+                    }
+                    let mut locked_rti = lock_rti(&_f_rti);
+                    let idx: usize = fed_id.into();
+                    let shutdown_requested = locked_rti.shutdown_handle().requested();
+                    let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
+                    fed.set_stream(stream.try_clone().unwrap());
+                    if shutdown_requested {
+                        // The federate completed its handshake while the RTI
+                        // was shutting down.
+                        Self::notify_shutdown(fed);
+                    }
+                    federates.push((connection, stream));
+                }
+
+                // A thread that serves a single federate and expects no other
+                // one can block on its socket. Otherwise, the wait of a pass is
+                // shared among the federates.
+                let poll_timeout = if more_federates || federates.len() > 1 {
+                    let number_of_federates = federates.len().max(1) as u32;
+                    Some(
+                        (idle_timeout / number_of_federates)
+                            .max(Duration::from_millis(FEDERATE_POLL_TIMEOUT_MS)),
+                    )
+                } else {
+                    None
+                };
+                // Under load, first serve the federates whose next message may
+                // let grants advance, so that it is not stuck behind NET
+                // updates and forwarded payloads. The sort is stable, so
+                // federates with messages of the same priority are served in
+                // turn.
+                if federates.len() > 1 {
+                    federates.sort_by_cached_key(|(_, stream)| Self::next_message_priority(stream));
+                }
+                let mut handled = false;
+                federates.retain_mut(|(connection, stream)| {
+                    let outcome = Self::poll_federate(
+                        connection,
+                        stream,
+                        poll_timeout,
+                        _f_rti.clone(),
+                        start_time.clone(),
+                        sent_start_time.clone(),
+                        stop_granted.clone(),
+                    );
+                    handled |= outcome != PollOutcome::Idle;
+                    outcome != PollOutcome::Closed
+                });
+                idle_timeout = if handled {
+                    Duration::from_millis(FEDERATE_POLL_TIMEOUT_MS)
+                } else {
+                    (idle_timeout * 2).min(Duration::from_millis(FEDERATE_POLL_MAX_TIMEOUT_MS))
+                };
+            }
+        })
+    }
+
+    /**
+     * Re-attach a federate that has resumed its session to the given
+     * connection and send it its last grant again, which may have been lost
+     * with the previous connection. Return false and close the connection if
+     * the resumption window has expired meanwhile.
+     */
+    fn resume_federate(
+        fed_id: u16,
+        stream: &FederateStream,
+        _f_rti: &Arc<RwLock<FederationRTI>>,
+        start_time: &Arc<Mutex<tag::StartTime>>,
+    ) -> bool {
+        let start_time_value = start_time.lock().unwrap().start_time();
+        let mut locked_rti = lock_rti(_f_rti);
+        let now = locked_rti.physical_clock().now();
+        let fed: &mut Federate = &mut locked_rti.enclaves()[usize::from(fed_id)];
+        if fed.e().state() == FedState::NotConnected {
+            warn!(
+                "RTI: Federate {} resumed its session after it was handled as failed. Closing the connection.",
+                federate_label(fed_id)
+            );
+            // NOTE: Ignore errors because the federate may be gone already.
+            let _ = stream.shutdown(Shutdown::Both);
+            return false;
+        }
+        if let Some(previous) = fed.stream() {
+            let _ = previous.shutdown(Shutdown::Both);
+        }
+        // Setting the stream and clearing the suspension under the same lock
+        // makes the other threads write to the new connection from now on.
+        fed.set_stream(stream.try_clone().unwrap());
+        let suspended_ms = fed.suspended_at().map_or(0, |suspended_at| {
+            now.saturating_sub(suspended_at) / 1_000_000
+        });
+        fed.set_suspended_at(None);
+        let dropped = fed.dropped_while_suspended();
+        fed.reset_dropped_while_suspended();
+        let description = format!(
+            "suspended for {} ms, {} message(s) dropped meanwhile",
+            suspended_ms, dropped
+        );
+        info!(
+            "RTI: Federate {} resumed its session ({}).",
+            federate_label(fed_id),
+            description
+        );
+        locked_rti.record_event("resumed", Some(fed_id), None, Some(&description));
+        Enclave::resend_last_grant_locked(&locked_rti, fed_id, start_time_value);
+        true
+    }
+
+    /**
+     * Return the priority of the next message of the federate, without
+     * waiting for it, lower first: messages that complete a tag, stop, or
+     * disconnect the federate, then NET and other control messages, then
+     * forwarded payloads, and last no message at all.
+     */
+    fn next_message_priority(stream: &FederateStream) -> u8 {
+        let mut buffer = [0u8; 1];
+        // A read timeout, unlike a non-blocking socket, does not affect the
+        // threads writing to the federate.
+        let _ = stream.set_read_timeout(Some(Duration::from_micros(1)));
+        let result = stream.peek(&mut buffer);
+        let _ = stream.set_read_timeout(None);
+        match result {
+            // The connection has been closed.
+            Ok(0) => 0,
+            Ok(_) => match MsgType::to_msg_type(buffer[0]) {
+                MsgType::LogicalTagComplete
+                | MsgType::StopRequest
+                | MsgType::StopRequestReply
+                | MsgType::Resign
+                | MsgType::Failed => 0,
+                MsgType::TaggedMessage | MsgType::P2pTaggedMessage => 2,
+                _ => 1,
+            },
+            Err(e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                3
+            }
+            // The error is reported when reading the message.
+            Err(_) => 0,
+        }
+    }
+
+    /**
+     * Wait for a message from the federate on the given connection for at most
+     * the given time (forever if None) and dispatch it.
+     */
+    fn poll_federate(
+        connection: &mut Connection,
+        stream: &mut FederateStream,
+        poll_timeout: Option<Duration>,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> PollOutcome {
+        let fed_id = connection.fed_id().unwrap();
+        let wire_dump;
+        {
+            let mut locked_rti = lock_rti(&_f_rti);
+            let enclaves = locked_rti.enclaves();
+            // FIXME: Replace "as usize" properly.
+            let fed: &mut Federate = &mut enclaves[fed_id as usize];
+            wire_dump = fed.wire_dump().clone();
+            let enclave = fed.enclave();
+            if enclave.state() == FedState::NotConnected {
+                return PollOutcome::Closed;
+            }
+        }
+        Self::process_pending_control_messages(fed_id, _f_rti.clone(), start_time.clone());
+        let mut poll_timeout = poll_timeout;
+        {
+            let locked_rti = read_rti(&_f_rti);
+            let idx: usize = fed_id.into();
+            let mut limiter = locked_rti.federates()[idx].control_rate_limiter();
+            if let Some(limiter) = limiter.as_mut() {
+                // Wake up in time to handle the held back messages, even if the
+                // federate sends nothing else. A zero timeout is invalid.
+                if let Some(wait) = limiter.time_until_pending_allowed() {
+                    let wait = wait.max(Duration::from_millis(1));
+                    poll_timeout = Some(poll_timeout.map_or(wait, |timeout| timeout.min(wait)));
+                }
+            }
+        }
+        // Read no more than one byte to get the message type.
+        let mut buffer = vec![0 as u8; 1];
+        // NOTE: Ignore errors because the read below reports a closed socket.
+        let _ = stream.set_read_timeout(poll_timeout);
+        let result = NetUtil::read_from_socket(
+            &mut DumpStream::new(&mut *stream, wire_dump.clone()),
+            &mut buffer,
+        );
+        let _ = stream.set_read_timeout(None);
+        match result {
+            Ok(_) => {
+                Self::dispatch(
+                    connection,
+                    buffer[0],
+                    &mut DumpStream::new(stream, wire_dump),
+                    _f_rti,
+                    start_time,
+                    sent_start_time,
+                    stop_granted,
+                );
+                if connection.state() == ConnectionState::Closed {
+                    PollOutcome::Closed
+                } else {
+                    PollOutcome::Handled
+                }
+            }
+            Err(e)
+                if e.kind() == ReadErrorKind::WouldBlock || e.kind() == ReadErrorKind::Timeout =>
+            {
+                PollOutcome::Idle
+            }
+            Err(e) => {
+                Self::handle_read_error(fed_id, e, false, _f_rti, start_time, stop_granted);
+                PollOutcome::Closed
+            }
+        }
+    }
+
+    /**
+     * Handle a message of the given type from the given federate. Return
+     * false if the federate has resigned or failed.
+     */
+    fn handle_federate_message(
+        fed_id: u16,
+        message_type: u8,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> bool {
+        let buffer = vec![message_type];
+        debug_for!(
+            fed_id,
+            "RTI: Received message type {} from federate {}.",
+            buffer[0],
+            federate_label(fed_id)
+        );
+        let result = match MsgType::to_msg_type(buffer[0]) {
+            MsgType::Timestamp => Self::handle_timestamp(
+                // &buffer,
+                fed_id,
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                sent_start_time.clone(),
+            ),
+            MsgType::Resign => {
+                Self::handle_federate_resign(fed_id, _f_rti.clone(), start_time.clone());
+                return false;
+            }
+            MsgType::Failed => {
+                Self::handle_federate_failed(
+                    fed_id,
+                    String::from("the federate reported an error"),
+                    _f_rti.clone(),
+                    start_time.clone(),
+                    stop_granted.clone(),
+                );
+                return false;
+            }
+            MsgType::TaggedMessage => Self::handle_timed_message(
+                buffer[0],
+                fed_id,
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                sent_start_time.clone(),
+                stop_granted.clone(),
+            ),
+            MsgType::NextEventTag => Self::handle_next_event_tag(
+                fed_id,
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                stop_granted.clone(),
+            ),
+            MsgType::LogicalTagComplete => Self::handle_logical_tag_complete(
+                fed_id,
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                stop_granted.clone(),
+            ),
+            // FIXME: Reviewed until here.
+            // Need to also look at
+            // notify_advance_grant_if_safe()
+            // and notify_downstream_advance_grant_if_safe()
+            MsgType::StopRequest => Self::handle_stop_request_message(
+                fed_id,
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                stop_granted.clone(),
+            ),
+            MsgType::StopRequestReply => Self::handle_stop_request_reply(
+                fed_id,
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                stop_granted.clone(),
+            ),
+            MsgType::PortAbsent => Self::handle_port_absent_message(
+                &buffer,
+                fed_id,
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                sent_start_time.clone(),
+            ),
+            _ => {
+                let mut locked_rti = lock_rti(&_f_rti);
+                let fed: &mut Federate = &mut locked_rti.enclaves()[fed_id as usize];
+                warn!(
+                    "RTI received from federate {} an unrecognized TCP message type: {}.",
+                    federate_label(fed.enclave().id()),
+                    buffer[0]
+                );
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            Self::handle_read_error(fed_id, e, true, _f_rti, start_time, stop_granted);
+            return false;
+        }
+        Self::report_to_parent(&_f_rti);
+        true
+    }
+
+    /**
+     * Connect to the parent RTI if one is given. The RTI shuts down if the
+     * connection fails.
+     */
+    fn connect_to_parent(_f_rti: &Arc<RwLock<FederationRTI>>) -> Option<ParentLink> {
+        let mut locked_rti = lock_rti(_f_rti);
+        let address = locked_rti.parent_address().clone()?;
+        match ParentLink::connect(
+            &address,
+            locked_rti.parent_id(),
+            &locked_rti.federation_id(),
+            locked_rti.parent_upstream(),
+            locked_rti.parent_downstream(),
+        ) {
+            Ok(parent_link) => {
+                // Nothing is granted before the parent RTI grants it.
+                locked_rti.set_parent_grant(Some(Tag::never_tag()));
+                locked_rti.set_parent_link(parent_link.clone());
+                Some(parent_link)
+            }
+            Err(e) => {
+                error!(
+                    "RTI: Failed to connect to the parent RTI at {}: {}.",
+                    address, e
+                );
+                locked_rti
+                    .shutdown_handle()
+                    .request("the failure to connect to the parent RTI");
+                None
+            }
+        }
+    }
+
+    /**
+     * Send the parent RTI, if any, the earliest NET and LTC of the federates.
+     */
+    fn report_to_parent(_f_rti: &Arc<RwLock<FederationRTI>>) {
+        let mut locked_rti = lock_rti(_f_rti);
+        if let Some(parent_link) = locked_rti.parent_link().clone() {
+            parent_link.report(&mut locked_rti);
+        }
+    }
+
+    /**
+     * Perform the handshake with a newly connected federate: receive its
+     * federate and federation IDs, its neighbor structure, and its UDP port.
+     * Return the connection to the federate, which is in
+     * ConnectionState::AwaitingTimestamp if the federate was accepted and in
+     * ConnectionState::Closed if it was rejected.
+     */
+    /**
+     * Perform the handshake, keeping the exchanged bytes in memory if wire
+     * dumps are enabled, and write them to the dump of the federate once it is
+     * known whether it is selected.
+     */
+    fn dumped_handshake(
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> Connection {
+        if !lock_rti(&_f_rti).wire_dump_enabled() {
+            return Self::handshake(stream, _f_rti);
+        }
+        let mut dump_stream = DumpStream::recording(stream);
+        let connection = Self::handshake(&mut dump_stream, _f_rti.clone());
+        let dump = connection
+            .fed_id()
+            .and_then(|fed_id| lock_rti(&_f_rti).open_wire_dump(fed_id));
+        dump_stream.finish(dump.as_deref());
+        connection
+    }
+
+    #[doc(hidden)]
+    pub fn handshake(
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> Connection {
+        // No message accepted during the handshake uses the start time or the
+        // state of the stop protocol.
+        let start_time = Arc::new(Mutex::new(StartTime::new()));
+        let sent_start_time = Arc::new((Mutex::new(false), Condvar::new()));
+        let stop_granted = Arc::new(Mutex::new(StopGranted::new()));
+        let mut connection = Connection::new();
+        if lock_rti(&_f_rti).authentication_enabled() {
+            connection.set_state(ConnectionState::AwaitingAuthentication);
+        }
+        let mut buffer: Vec<u8> = vec![0; 1];
+        while connection.state().is_handshaking() {
+            if !NetUtil::read_from_stream(
+                stream,
+                &mut buffer,
+                connection.fed_id().unwrap_or(0),
+                "message type",
+            ) {
+                connection.set_state(ConnectionState::Closed);
+                break;
+            }
+            Self::dispatch(
+                &mut connection,
+                buffer[0],
+                stream,
+                _f_rti.clone(),
+                start_time.clone(),
+                sent_start_time.clone(),
+                stop_granted.clone(),
+            );
+        }
+        if let (ConnectionState::Closed, Some(fed_id)) = (connection.state(), connection.fed_id()) {
+            // Free the federate ID so that the federate can connect again.
+            let mut locked_rti = lock_rti(&_f_rti);
+            let enclave = locked_rti.enclaves()[usize::from(fed_id)].enclave();
+            if enclave.state() == FedState::Pending {
+                enclave.set_state(FedState::NotConnected);
+            }
+        }
+        connection
+    }
+
+    /**
+     * Handle a message of the given type from the federate on the given
+     * connection and advance the state of the connection. This is the single
+     * entry point for all messages from federates, so that a message that is
+     * out of order is rejected the same way at every step of the protocol.
+     */
+    fn dispatch(
+        connection: &mut Connection,
+        message_type: u8,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) {
+        if connection.state() == ConnectionState::Running {
+            let locked_rti = lock_rti(&_f_rti);
+            if locked_rti.stop_in_progress() {
+                connection.set_state(ConnectionState::Stopping);
+            }
+        }
+        let Some(next_state) = connection.state().next(message_type) else {
+            Self::handle_unexpected_message(
+                connection,
+                message_type,
+                stream,
+                _f_rti,
+                start_time,
+                stop_granted,
+            );
+            return;
+        };
+        if let (false, Some(fed_id)) = (
+            connection.record_received(message_type),
+            connection.fed_id(),
+        ) {
+            let duplicate = format!(
+                "duplicate message type {} from federate {}",
+                message_type,
+                federate_label(fed_id)
+            );
+            warn!("RTI: WARNING: Received a {}.", duplicate);
+            lock_rti(&_f_rti).tolerate(&duplicate);
+        }
+        let handled = match (connection.state(), connection.fed_id()) {
+            (ConnectionState::AwaitingAuthentication | ConnectionState::AwaitingFedIds, _)
+                if message_type == MsgType::Resume.to_byte() =>
+            {
+                match Self::receive_resume_message(stream, _f_rti) {
+                    Some(fed_id) => {
+                        connection.set_fed_id(fed_id);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            // The frames that follow carry the messages of the federates.
+            (ConnectionState::AwaitingAuthentication | ConnectionState::AwaitingFedIds, _)
+                if message_type == MsgType::Multiplex.to_byte() =>
+            {
+                true
+            }
+            #[cfg(feature = "auth")]
+            (ConnectionState::AwaitingAuthentication, _) => {
+                Self::authenticate_federate(stream, _f_rti)
+            }
+            (ConnectionState::AwaitingFedIds, _)
+                if message_type == MsgType::ControlChannel.to_byte() =>
+            {
+                match Self::receive_control_channel_message(stream, _f_rti) {
+                    Some(fed_id) => {
+                        connection.set_fed_id(fed_id);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            (ConnectionState::AwaitingFedIds, _) => {
+                let fed_id = Self::receive_and_check_fed_id_message(stream, _f_rti);
+                // TODO: Error-handling of fed_id.try_into().unwrap()
+                if fed_id >= 0 {
+                    connection.set_fed_id(fed_id.try_into().unwrap());
+                }
+                fed_id >= 0
+            }
+            (ConnectionState::AwaitingNeighbors, Some(fed_id))
+                if message_type == MsgType::FederateName.to_byte() =>
+            {
+                Self::receive_federate_name(fed_id, stream, _f_rti)
+            }
+            (ConnectionState::AwaitingNeighbors, Some(fed_id))
+                if message_type == MsgType::FederateMetadata.to_byte() =>
+            {
+                Self::receive_federate_metadata(fed_id, stream, _f_rti)
+            }
+            (ConnectionState::AwaitingNeighbors, Some(fed_id))
+                if message_type == MsgType::Capabilities.to_byte() =>
+            {
+                Self::negotiate_capabilities(fed_id, connection, stream, _f_rti)
+            }
+            (ConnectionState::AwaitingNeighbors, Some(fed_id))
+                if message_type == MsgType::ControlChannel.to_byte() =>
+            {
+                info!(
+                    "RTI: Federate {} will open a control connection.",
+                    federate_label(fed_id)
+                );
+                connection.set_control_channel_announced(true);
+                let mut locked_rti = lock_rti(&_f_rti);
+                let fed = &mut locked_rti.enclaves()[usize::from(fed_id)];
+                fed.set_capabilities(fed.capabilities() | CAPABILITY_CONTROL_CHANNEL);
+                // The control connection cannot be resumed.
+                fed.set_resume_token(None);
+                true
+            }
+            (ConnectionState::AwaitingNeighbors, Some(fed_id)) => {
+                Self::receive_connection_information(fed_id, stream, _f_rti)
+            }
+            (ConnectionState::AwaitingUdpPort, Some(fed_id)) => {
+                Self::receive_udp_message_and_set_up_clock_sync(fed_id, stream, _f_rti)
+            }
+            (_, Some(fed_id)) => Self::handle_federate_message(
+                fed_id,
+                message_type,
+                stream,
+                _f_rti,
+                start_time,
+                sent_start_time,
+                stop_granted,
+            ),
+            (_, None) => false,
+        };
+        connection.set_state(if handled {
+            next_state
+        } else {
+            ConnectionState::Closed
+        });
+    }
+
+    /**
+     * Reject a message that is out of order in the state of the given
+     * connection and close the connection. A federate that has completed the
+     * handshake is then handled as a failed federate.
+     */
+    fn handle_unexpected_message(
+        connection: &mut Connection,
+        message_type: u8,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) {
+        let state = connection.state();
+        warn!(
+            "RTI received an unexpected message type {} (see net_common.h) in state {:?}. Rejecting federate.",
+            message_type, state
+        );
+        lock_rti(&_f_rti).tolerate(&format!(
+            "unexpected message type {} in state {:?}",
+            message_type, state
+        ));
+        if state == ConnectionState::AwaitingFedIds
+            && (message_type == MsgType::P2pSendingFedId.to_byte()
+                || message_type == MsgType::P2pTaggedMessage.to_byte())
+        {
+            // The federate is trying to connect to a peer, not to the RTI.
+            // It has connected to the RTI instead.
+            // FIXME: This should not happen, but apparently has been observed.
+            // It should not happen because the peers get the port and IP address
+            // of the peer they want to connect to from the RTI.
+            // If the connection is a peer-to-peer connection between two
+            // federates, reject the connection with the WrongServer error.
+            Self::send_reject(stream, ErrType::WrongServer.to_byte());
+        } else if state == ConnectionState::AwaitingFedIds
+            && message_type == MsgType::FedNonce.to_byte()
+        {
+            // The federate expects to authenticate itself, but this RTI does
+            // not authenticate federates.
+            Self::send_reject(stream, ErrType::RtiNotExecutedWithAuth.to_byte());
+        } else {
+            Self::send_reject(stream, ErrType::UnexpectedMessage.to_byte());
+        }
+        connection.set_state(ConnectionState::Closed);
+        if let Some(fed_id) = connection.fed_id() {
+            if !state.is_handshaking() {
+                Self::handle_federate_failed(
+                    fed_id,
+                    format!(
+                        "unexpected message type {} in state {:?}",
+                        message_type, state
+                    ),
+                    _f_rti,
+                    start_time,
+                    stop_granted,
+                );
+            }
+        }
+    }
+
+    /**
+     * Authenticate a federate that has sent a MsgType::FedNonce message, as
+     * described at MSG_TYPE_FED_NONCE_LENGTH, with the federation ID as the
+     * key. Reject the federate if its response does not prove that it knows
+     * the key.
+     */
+    #[cfg(feature = "auth")]
+    fn authenticate_federate(
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> bool {
+        // The federate ID and the nonce of the federate.
+        let mut fed_nonce = vec![0u8; MSG_TYPE_FED_NONCE_LENGTH - 1];
+        if !NetUtil::read_from_stream(stream, &mut fed_nonce, 0, "MsgType::FedNonce message") {
+            return false;
+        }
+        let fed_id = u16::from_le_bytes(fed_nonce[0..2].try_into().unwrap());
+        let key = lock_rti(&_f_rti).federation_id();
+        let Some(rti_nonce) = auth::nonce() else {
+            let _ = stream.shutdown();
+            return false;
+        };
+        let mut response = Vec::with_capacity(MSG_TYPE_RTI_RESPONSE_LENGTH);
+        response.push(MsgType::RtiResponse.to_byte());
+        response.extend_from_slice(&rti_nonce);
+        response.extend_from_slice(&auth::hmac_tag(
+            key.as_bytes(),
+            &[&[MsgType::RtiResponse.to_byte()], &fed_nonce],
+        ));
+        if stream.write_all(&response).is_err() {
+            error!(
+                "RTI failed to send MsgType::RtiResponse to federate {}.",
+                federate_label(fed_id)
+            );
+            return false;
+        }
+        let mut fed_response = vec![0u8; MSG_TYPE_FED_RESPONSE_LENGTH];
+        if !NetUtil::read_from_stream(
+            stream,
+            &mut fed_response,
+            fed_id,
+            "MsgType::FedResponse message",
+        ) {
+            return false;
+        }
+        if fed_response[0] != MsgType::FedResponse.to_byte() {
+            warn!(
+                "RTI: Unexpected message {} from federate {} during authentication. Rejecting federate.",
+                fed_response[0],
+                federate_label(fed_id)
+            );
+            Self::send_reject(stream, ErrType::UnexpectedMessage.to_byte());
+            return false;
+        }
+        if !auth::verify_hmac_tag(
+            key.as_bytes(),
+            &[&[MsgType::FedResponse.to_byte()], &rti_nonce],
+            &fed_response[1..],
+        ) {
+            warn!(
+                "RTI: WARNING: HMAC authentication of federate {} failed. Rejecting federate.",
+                federate_label(fed_id)
+            );
+            Self::send_reject(stream, ErrType::HmacDoesNotMatch.to_byte());
+            return false;
+        }
+        info!("RTI: Federate {} authenticated.", federate_label(fed_id));
+        true
+    }
+
+    fn receive_and_check_fed_id_message(
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> i32 {
+        // Buffer for federate ID and federation ID length.
+        let length = mem::size_of::<u16>() + 1;
+        let mut first_buffer = vec![0 as u8; length];
+        // Initialize to an invalid value.
+        let mut fed_id;
+        let cloned_rti = Arc::clone(&_f_rti);
+        // Read bytes from the socket. We need 3 bytes.
+        if !NetUtil::read_from_stream(stream, &mut first_buffer, 0, "") {
+            return -1;
+        }
+
+        {
+            // Received federate ID.
+            // FIXME: Change from_le_bytes properly.
+            let u16_size = mem::size_of::<u16>();
+            fed_id = u16::from_le_bytes(first_buffer[0..u16_size].try_into().unwrap());
+            debug!("RTI received federate ID: {}.", fed_id);
+
+            // Read the federation ID.  First read the length, which is one byte.
+            // FIXME: Change from_le_bytes properly.
+            let federation_id_length =
+                u8::from_le_bytes(first_buffer[u16_size..(u16_size + 1)].try_into().unwrap());
+            let mut federation_id_buffer = vec![0 as u8; federation_id_length.into()];
+            if !NetUtil::read_from_stream(
+                stream,
+                &mut federation_id_buffer,
+                fed_id,
+                "federation id",
+            ) {
+                return -1;
+            }
+            let federation_id_received;
+            match String::from_utf8(federation_id_buffer) {
+                Ok(federation_id) => {
+                    federation_id_received = federation_id;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to convert a message buffer to a federation id ({})",
+                        e
+                    );
+                    return -1;
+                }
+            }
+
+            debug!("RTI received federation ID: {}.", federation_id_received);
+            let number_of_enclaves;
+            let federation_id;
+            let dynamic_ids;
+            {
+                let locked_rti = lock_rti(&cloned_rti);
+                number_of_enclaves = locked_rti.number_of_enclaves();
+                federation_id = locked_rti.federation_id();
+                dynamic_ids = locked_rti.dynamic_ids();
+            }
+            let assign_id = dynamic_ids && fed_id == ASSIGN_FEDERATE_ID;
+            // Compare the received federation ID to mine.
+            if federation_id_received != federation_id {
+                // Federation IDs do not match. Send back a MSG_TYPE_Reject message.
+                warn!(
+                    "WARNING: Federate from another federation {} attempted to connect to RTI in federation {}.",
+                    federation_id_received, federation_id
+                );
+                Self::send_reject(stream, ErrType::FederationIdDoesNotMatch.to_byte());
+                std::process::exit(1);
+            } else if assign_id {
+                let assigned;
+                {
+                    let mut locked_rti = lock_rti(&cloned_rti);
+                    assigned = locked_rti.assign_federate_id();
+                }
+                match assigned {
+                    Some(assigned_id) => {
+                        info!(
+                            "RTI assigned federate ID {} to a joining federate.",
+                            assigned_id
+                        );
+                        fed_id = assigned_id;
+                    }
+                    None => {
+                        // Keep running because the federates that joined are not affected.
+                        warn!("RTI has no federate ID left to assign to a joining federate.");
+                        Self::send_reject(stream, ErrType::FederateIdOutOfRange.to_byte());
+                        return -1;
+                    }
+                }
+            } else {
+                if i32::from(fed_id) >= number_of_enclaves {
+                    // Federate ID is out of range.
+                    warn!(
+                        "RTI received federate ID {}, which is out of range.",
+                        fed_id
+                    );
+                    Self::send_reject(stream, ErrType::FederateIdOutOfRange.to_byte());
+                    std::process::exit(1);
+                } else {
+                    let mut locked_rti = lock_rti(&cloned_rti);
+                    let draining = locked_rti.draining();
+                    let idx: usize = fed_id.into();
+                    let federate: &mut Federate = &mut locked_rti.enclaves()[idx];
+                    let enclave = federate.enclave();
+                    if enclave.state() != FedState::NotConnected {
+                        error!("RTI received duplicate federate ID: {}.", fed_id);
+                        Self::send_reject(stream, ErrType::FederateIdInUse.to_byte());
+                        std::process::exit(1);
+                    }
+                    if enclave.transient() && draining {
+                        warn!(
+                            "RTI: The federation is draining. Rejecting transient federate {}.",
+                            federate_label(fed_id)
+                        );
+                        Self::send_reject(stream, ErrType::FederationDraining.to_byte());
+                        return -1;
+                    }
+                    if enclave.transient() {
+                        if federate.start_time_proposed() {
+                            info!(
+                                "RTI: Transient federate {} is joining again.",
+                                federate_label(fed_id)
+                            );
+                        }
+                        federate.reset_for_rejoin();
+                    } else if federate.start_time_proposed() {
+                        // Keep running because the other federates are not affected.
+                        warn!(
+                            "RTI: Federate {} has already taken part in the execution and is not transient. Rejecting it.",
+                            federate_label(fed_id)
+                        );
+                        Self::send_reject(stream, ErrType::FederateIdInUse.to_byte());
+                        return -1;
+                    }
+                }
+            }
+            debug!(
+                "Federation ID matches! \"{}(received)\" <-> \"{}(_f_rti)\"",
+                federation_id_received, federation_id
+            );
+
+            // TODO: Assign the address information for federate.
+
+            // Set the federate's state as pending
+            // because it is waiting for the start time to be
+            // sent by the RTI before beginning its execution.
+            {
+                let mut locked_rti = lock_rti(&cloned_rti);
+                let idx: usize = fed_id.into();
+                let federate: &mut Federate = &mut locked_rti.enclaves()[idx];
+                let enclave: &mut Enclave = federate.enclave();
+                enclave.set_state(FedState::Pending);
+                locked_rti.tracepoint_rti_from_federate(TraceEvent::ReceiveFedId, fed_id, None);
+                locked_rti.tracepoint_rti_to_federate(TraceEvent::SendAck, fed_id, None);
+            }
+            debug!(
+                "RTI responding with MsgType::Ack to federate {}.",
+                federate_label(fed_id)
+            );
+            // Send an MsgType::Ack message, followed by the ID if the RTI assigned it.
+            let mut ack_message: Vec<u8> = vec![MsgType::Ack.to_byte()];
+            if assign_id {
+                ack_message.extend_from_slice(&fed_id.to_le_bytes());
+            }
+            NetUtil::write_to_stream_errexit(stream, &ack_message, fed_id, "MsgType::Ack message");
+        }
+
+        fed_id.into()
+    }
+
+    /**
+     * Handle a MsgType::Resume message, with which a federate resumes its
+     * session on a new connection. If the token is valid, reply with a
+     * MsgType::Ack followed by a new token and return the federate ID. The
+     * federate stays suspended until its scheduling thread re-attaches it to
+     * the new connection. Otherwise, reject the connection and return None.
+     */
+    fn receive_resume_message(
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> Option<u16> {
+        // The message type has already been read.
+        let mut buffer = vec![0u8; MSG_TYPE_RESUME_LENGTH - 1];
+        if !NetUtil::read_from_stream(stream, &mut buffer, 0, "MsgType::Resume message") {
+            return None;
+        }
+        let fed_id = u16::from_le_bytes([buffer[0], buffer[1]]);
+        let presented = &buffer[mem::size_of::<u16>()..];
+        let mut locked_rti = lock_rti(&_f_rti);
+        let refusal = if i32::from(fed_id) >= locked_rti.number_of_enclaves() {
+            Some("its federate ID is out of range")
+        } else if locked_rti.shutdown_handle().requested() || locked_rti.stop_in_progress() {
+            Some("the federation is stopping")
+        } else {
+            let fed = &locked_rti.enclaves()[usize::from(fed_id)];
+            match fed.resume_token() {
+                _ if fed.e().state() == FedState::NotConnected => Some("it is no longer connected"),
+                Some(token) if resumption::tokens_match(token, presented) => None,
+                _ => Some("its token is not valid"),
+            }
+        };
+        if let Some(refusal) = refusal {
+            warn!(
+                "RTI: Refusing to resume the session of federate {} because {}.",
+                fed_id, refusal
+            );
+            Self::send_reject(stream, ErrType::ResumptionRefused.to_byte());
+            return None;
+        }
+        let Some(token) = resumption::new_token() else {
+            Self::send_reject(stream, ErrType::ResumptionRefused.to_byte());
+            return None;
+        };
+        let now = locked_rti.physical_clock().now();
+        let fed = &mut locked_rti.enclaves()[usize::from(fed_id)];
+        fed.set_resume_token(Some(token));
+        // The RTI may not have noticed that the previous connection is lost.
+        // Messages to the federate are dropped until it is re-attached rather
+        // than written to that connection.
+        if !fed.suspended() {
+            fed.set_suspended_at(Some(now));
+        }
+        let mut reply = vec![MsgType::Ack.to_byte()];
+        reply.extend_from_slice(&token);
+        NetUtil::write_to_stream_errexit(stream, &reply, fed_id, "MsgType::Ack message");
+        Some(fed_id)
+    }
+
+    /**
+     * Read the federate and federation IDs of a MsgType::ControlChannel
+     * message that opens a control connection. Return the federate ID, or
+     * None after rejecting the connection if the IDs are not valid.
+     */
+    fn receive_control_channel_message(
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> Option<u16> {
+        // The message type has already been read.
+        let mut header = vec![0u8; MSG_TYPE_CONTROL_CHANNEL_HEADER_LENGTH - 1];
+        if !NetUtil::read_from_stream(
+            stream,
+            &mut header,
+            0,
+            "MsgType::ControlChannel message header",
+        ) {
+            return None;
+        }
+        let fed_id = u16::from_le_bytes([header[0], header[1]]);
+        let mut federation_id_buffer = vec![0u8; usize::from(header[2])];
+        if !NetUtil::read_from_stream(
+            stream,
+            &mut federation_id_buffer,
+            fed_id,
+            "MsgType::ControlChannel federation id",
+        ) {
+            return None;
+        }
+        let locked_rti = lock_rti(&_f_rti);
+        if federation_id_buffer != locked_rti.federation_id().as_bytes() {
+            warn!("RTI: Rejecting a control connection from another federation.");
+            Self::send_reject(stream, ErrType::FederationIdDoesNotMatch.to_byte());
+            return None;
+        }
+        if i32::from(fed_id) >= locked_rti.number_of_enclaves() {
+            warn!(
+                "RTI: Rejecting a control connection for federate ID {}, which is out of range.",
+                fed_id
+            );
+            Self::send_reject(stream, ErrType::FederateIdOutOfRange.to_byte());
+            return None;
+        }
+        Some(fed_id)
+    }
+
+    /**
+     * Make the given connection the control connection of the given federate,
+     * on which its grants are sent from now on, and acknowledge it. Reject the
+     * connection if the federate has not joined or already has one.
+     */
+    fn open_control_channel(
+        fed_id: u16,
+        stream: &mut FederateStream,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) {
+        let mut locked_rti = lock_rti(&_f_rti);
+        let idx: usize = fed_id.into();
+        let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
+        let control_stream = match stream.try_clone() {
+            Ok(control_stream)
+                if fed.e().state() != FedState::NotConnected && fed.control_stream().is_none() =>
+            {
+                control_stream
+            }
+            _ => {
+                warn!(
+                    "RTI: Rejecting a control connection for federate {}, which has not joined or already has one.",
+                    federate_label(fed_id)
+                );
+                Self::send_reject(stream, ErrType::FederateIdInUse.to_byte());
+                return;
+            }
+        };
+        fed.set_control_stream(control_stream);
+        info!(
+            "RTI: Federate {} opened a control connection. Sending grants on it.",
+            federate_label(fed_id)
+        );
+        NetUtil::write_to_stream_errexit(
+            stream,
+            &[MsgType::Ack.to_byte()],
+            fed_id,
+            "MsgType::Ack message",
+        );
+        fed.record_sent(&[MsgType::Ack.to_byte()]);
+    }
+
+    fn send_reject(stream: &mut impl Transport, error_code: u8) {
+        Self::send_error(stream, error_code);
+        // Close the socket.
+        stream.shutdown().expect("shutdown call failed");
+    }
+
+    /**
+     * Send a MsgType::Reject message that rejects only the last message from
+     * the federate and keep the connection open.
+     */
+    fn send_error(stream: &mut impl Transport, error_code: u8) {
+        debug!("RTI sending MsgType::Reject.");
         let mut response = vec![0 as u8; 2];
         response[0] = MsgType::Reject.to_byte();
         response[1] = error_code;
         // NOTE: Ignore errors on this response.
-        match stream.write(&response) {
+        match stream.write_all(&response) {
             Ok(..) => {}
             Err(_e) => {
-                println!("RTI failed to write MsgType::Reject message on the stream.");
+                error!("RTI failed to write MsgType::Reject message on the stream.");
                 // TODO: Handle errexit
                 std::process::exit(1);
             }
         }
-        // Close the socket.
-        stream
-            .shutdown(Shutdown::Both)
-            .expect("shutdown call failed");
+    }
+
+    /**
+     * Read the name that the federate presents in a MsgType::FederateName
+     * message and register it. Reject the federate if the name is not valid
+     * or another federate has already presented it.
+     */
+    fn receive_federate_name(
+        fed_id: u16,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> bool {
+        // The message type has already been read.
+        let mut length = vec![0u8; MSG_TYPE_FEDERATE_NAME_HEADER_LENGTH - 1];
+        if !NetUtil::read_from_stream(
+            stream,
+            &mut length,
+            fed_id,
+            "MsgType::FederateName message header",
+        ) {
+            return false;
+        }
+        let mut name_buffer = vec![0u8; usize::from(length[0])];
+        if !NetUtil::read_from_stream(
+            stream,
+            &mut name_buffer,
+            fed_id,
+            "MsgType::FederateName message body",
+        ) {
+            return false;
+        }
+        let name = match String::from_utf8(name_buffer) {
+            Ok(name)
+                if !name.is_empty()
+                    && name != "all"
+                    && name.parse::<u16>().is_err()
+                    && !name.contains(char::is_whitespace) =>
+            {
+                name
+            }
+            _ => {
+                warn!(
+                    "RTI: Federate {} presented an invalid name. Names must be non-empty UTF-8 without whitespace and must not be a number or \"all\". Rejecting federate.",
+                    federate_label(fed_id)
+                );
+                Self::send_reject(stream, ErrType::ProtocolViolation.to_byte());
+                return false;
+            }
+        };
+
+        let mut locked_rti = lock_rti(&_f_rti);
+        if let Some(other_id) = locked_rti.federate_id_by_name(&name) {
+            warn!(
+                "RTI: Federate {} presented the name {}, which federate {} already has. Rejecting federate.",
+                fed_id, name, other_id
+            );
+            Self::send_reject(stream, ErrType::FederateNameInUse.to_byte());
+            return false;
+        }
+        info!("RTI: Federate {} is named {}.", fed_id, name);
+        federate::register_federate_label(fed_id, &name);
+        let idx: usize = fed_id.into();
+        locked_rti.enclaves()[idx].set_name(name);
+        true
+    }
+
+    /**
+     * Read the metadata that the federate presents in a
+     * MsgType::FederateMetadata message and store it. Reject the federate if
+     * the metadata is not valid.
+     */
+    fn receive_federate_metadata(
+        fed_id: u16,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> bool {
+        // The message type has already been read.
+        let mut read_text = |description: &str| -> Option<Vec<u8>> {
+            let mut length = vec![0u8; MSG_TYPE_FEDERATE_METADATA_HEADER_LENGTH - 1];
+            if !NetUtil::read_from_stream(stream, &mut length, fed_id, description) {
+                return None;
+            }
+            let mut text_buffer = vec![0u8; usize::from(length[0])];
+            if !NetUtil::read_from_stream(stream, &mut text_buffer, fed_id, description) {
+                return None;
+            }
+            Some(text_buffer)
+        };
+        let Some(runtime) = read_text("MsgType::FederateMetadata runtime") else {
+            return false;
+        };
+        let Some(host_name) = read_text("MsgType::FederateMetadata host name") else {
+            return false;
+        };
+        let mut process_id = vec![0u8; mem::size_of::<u32>()];
+        if !NetUtil::read_from_stream(
+            stream,
+            &mut process_id,
+            fed_id,
+            "MsgType::FederateMetadata process ID",
+        ) {
+            return false;
+        }
+        let valid = |text: &str| !text.is_empty() && !text.contains(char::is_whitespace);
+        let runtime = String::from_utf8(runtime).ok().filter(|text| valid(text));
+        let host_name = String::from_utf8(host_name).ok().filter(|text| valid(text));
+        let (Some(runtime), Some(host_name)) = (runtime, host_name) else {
+            warn!(
+                "RTI: Federate {} presented invalid metadata. The runtime and host name must be non-empty UTF-8 without whitespace. Rejecting federate.",
+                federate_label(fed_id)
+            );
+            Self::send_reject(stream, ErrType::ProtocolViolation.to_byte());
+            return false;
+        };
+        let metadata = federate::FederateMetadata::new(
+            runtime,
+            host_name,
+            u32::from_le_bytes(process_id.try_into().unwrap()),
+        );
+        info!(
+            "RTI: Federate {} runs on {} as process {} of {}.",
+            federate_label(fed_id),
+            metadata.host_name(),
+            metadata.process_id(),
+            metadata.runtime()
+        );
+        let mut locked_rti = lock_rti(&_f_rti);
+        locked_rti.enclaves()[usize::from(fed_id)].set_metadata(metadata);
+        true
+    }
+
+    /**
+     * Read the capabilities that the federate offers in a
+     * MsgType::Capabilities message, enable those that the RTI supports, and
+     * reply with them.
+     */
+    fn negotiate_capabilities(
+        fed_id: u16,
+        connection: &mut Connection,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> bool {
+        // The message type has already been read.
+        let mut buffer = vec![0u8; MSG_TYPE_CAPABILITIES_LENGTH - 1];
+        if !NetUtil::read_from_stream(stream, &mut buffer, fed_id, "MsgType::Capabilities message")
+        {
+            return false;
+        }
+        let offered = u32::from_le_bytes(buffer.try_into().unwrap());
+        let enabled;
+        {
+            let mut locked_rti = lock_rti(&_f_rti);
+            let mut supported = locked_rti.supported_capabilities();
+            if offered & supported & CAPABILITY_CONTROL_CHANNEL != 0 {
+                // The control connection cannot be resumed.
+                supported &= !CAPABILITY_RESUMPTION;
+            }
+            enabled = offered & supported;
+            locked_rti.enclaves()[usize::from(fed_id)].set_capabilities(enabled);
+        }
+        let describe = |capabilities: u32| {
+            let names = capability_names(capabilities);
+            if names.is_empty() {
+                String::from("none")
+            } else {
+                names.join(",")
+            }
+        };
+        info!(
+            "RTI: Federate {} offered the capabilities {}, of which {} are enabled.",
+            federate_label(fed_id),
+            describe(offered),
+            describe(enabled)
+        );
+        if enabled & CAPABILITY_CONTROL_CHANNEL != 0 {
+            connection.set_control_channel_announced(true);
+        }
+        let mut reply = vec![MsgType::Capabilities.to_byte()];
+        reply.extend_from_slice(&enabled.to_le_bytes());
+        NetUtil::write_to_stream_errexit(
+            &mut *stream,
+            &reply,
+            fed_id,
+            "MsgType::Capabilities message",
+        );
+        if enabled & CAPABILITY_RESUMPTION != 0 {
+            let Some(token) = resumption::new_token() else {
+                Self::send_reject(stream, ErrType::UnexpectedMessage.to_byte());
+                return false;
+            };
+            lock_rti(&_f_rti).enclaves()[usize::from(fed_id)].set_resume_token(Some(token));
+            let mut message = Vec::with_capacity(MSG_TYPE_RESUME_TOKEN_LENGTH);
+            message.push(MsgType::ResumeToken.to_byte());
+            message.extend_from_slice(&token);
+            NetUtil::write_to_stream_errexit(
+                stream,
+                &message,
+                fed_id,
+                "MsgType::ResumeToken message",
+            );
+        }
+        true
     }
 
     fn receive_connection_information(
-        &mut self,
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
     ) -> bool {
-        println!(
+        debug!(
             "RTI waiting for MsgType::NeighborStructure from federate {}.",
-            fed_id
+            federate_label(fed_id)
         );
         let cloned_rti = Arc::clone(&_f_rti);
-        let mut locked_rti = cloned_rti.lock().unwrap();
-        let mut connection_info_header =
-            vec![0 as u8; MSG_TYPE_NEIGHBOR_STRUCTURE_HEADER_SIZE.try_into().unwrap()];
-        NetUtil::read_from_stream_errexit(
+        let mut locked_rti = lock_rti(&cloned_rti);
+        // The message type has already been read.
+        let mut connection_info_header = vec![
+            0 as u8;
+            (MSG_TYPE_NEIGHBOR_STRUCTURE_HEADER_SIZE - 1)
+                .try_into()
+                .unwrap()
+        ];
+        if !NetUtil::read_from_stream(
             stream,
             &mut connection_info_header,
             fed_id,
             "MsgType::NeighborStructure message header",
-        );
+        ) {
+            return false;
+        }
 
-        if connection_info_header[0] != MsgType::NeighborStructure.to_byte() {
-            println!("RTI was expecting a MsgType::NeighborStructure message from federate {}. Got {} instead. Rejecting federate.", fed_id, connection_info_header[0]);
-            Self::send_reject(stream, ErrType::UnexpectedMessage.to_byte());
+        let num_upstream = usize::from(connection_info_header[0]);
+        let num_downstream = usize::from(connection_info_header[mem::size_of::<i32>()]);
+        let connections_info_body_size = ((mem::size_of::<u16>() + mem::size_of::<i64>())
+            * num_upstream)
+            + (mem::size_of::<u16>() * num_downstream);
+        if locked_rti.exceeds_max_message_size(connections_info_body_size) {
+            warn!(
+                "RTI: MsgType::NeighborStructure message of {} bytes from federate {} exceeds the maximum message size of {} bytes. Rejecting federate.",
+                connections_info_body_size,
+                federate_label(fed_id),
+                locked_rti.max_message_size()
+            );
+            Self::send_reject(stream, ErrType::MessageTooLarge.to_byte());
             return false;
-        } else {
+        }
+
+        // A neighbor that is not in the federation will never connect. It is
+        // left out of the neighbor structure and reported by validate_topology.
+        let number_of_enclaves = locked_rti.enclaves().len();
+        let mut unknown_neighbors = Vec::new();
+        {
             let idx: usize = fed_id.into();
             let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
             let enclave: &mut Enclave = fed.enclave();
-            enclave.set_num_upstream(connection_info_header[1].into());
-            enclave.set_num_downstream(connection_info_header[1 + mem::size_of::<i32>()].into());
-            println!(
+            enclave.set_num_upstream(connection_info_header[0].into());
+            enclave.set_num_downstream(connection_info_header[mem::size_of::<i32>()].into());
+            debug!(
                 "RTI got {} upstreams and {} downstreams from federate {}.",
                 enclave.num_upstream(),
                 enclave.num_downstream(),
-                fed_id
+                federate_label(fed_id)
             );
 
-            let num_upstream = enclave.num_upstream() as usize;
-            let num_downstream = enclave.num_downstream() as usize;
-            let connections_info_body_size = ((mem::size_of::<u16>() + mem::size_of::<i64>())
-                * num_upstream)
-                + (mem::size_of::<u16>() * num_downstream);
             let mut connection_info_body = vec![0 as u8; connections_info_body_size];
-            NetUtil::read_from_stream_errexit(
+            if !NetUtil::read_from_stream(
                 stream,
                 &mut connection_info_body,
                 fed_id,
                 "MsgType::NeighborStructure message body",
-            );
+            ) {
+                return false;
+            }
 
             // Keep track of where we are in the buffer
             let mut message_head: usize = 0;
             // First, read the info about upstream federates
-            for i in 0..num_upstream {
+            let mut i = 0;
+            for _ in 0..num_upstream {
                 // FIXME: Change from_le_bytes properly.
                 let upstream_id = u16::from_le_bytes(
                     connection_info_body[message_head..(message_head + mem::size_of::<u16>())]
                         .try_into()
                         .unwrap(),
                 );
-                enclave.set_upstream_id_at(upstream_id, i);
                 message_head += mem::size_of::<u16>();
-                println!(
+                trace!(
                     "upstream_id: {}, message_head: {}",
-                    upstream_id, message_head
+                    upstream_id,
+                    message_head
                 );
                 // FIXME: Change from_le_bytes properly.
                 let upstream_delay = i64::from_le_bytes(
@@ -549,225 +2523,822 @@ impl Server {
                         .try_into()
                         .unwrap(),
                 );
-                enclave.set_upstream_delay_at(Some(upstream_delay), i);
                 message_head += mem::size_of::<i64>();
-                println!(
+                trace!(
                     "[{}] upstream_delay: {}, message_head: {}",
-                    i, upstream_delay, message_head
+                    i,
+                    upstream_delay,
+                    message_head
                 );
+                if usize::from(upstream_id) >= number_of_enclaves {
+                    unknown_neighbors.push((upstream_id, true));
+                    continue;
+                }
+                enclave.set_upstream_id_at(upstream_id, i);
+                enclave.set_upstream_delay_at(Some(upstream_delay), i);
+                i += 1;
             }
+            enclave.set_num_upstream(i as i32);
 
             // Next, read the info about downstream federates
-            for i in 0..num_downstream {
+            let mut i = 0;
+            for _ in 0..num_downstream {
                 // FIXME: Change from_le_bytes properly.
                 let downstream_id = u16::from_le_bytes(
                     connection_info_body[message_head..(message_head + mem::size_of::<u16>())]
                         .try_into()
                         .unwrap(),
                 );
-                enclave.set_downstream_id_at(downstream_id, i);
                 message_head += mem::size_of::<u16>();
-                println!(
+                trace!(
                     "downstream_id: {}, message_head: {}",
-                    downstream_id, message_head
+                    downstream_id,
+                    message_head
+                );
+                if usize::from(downstream_id) >= number_of_enclaves {
+                    unknown_neighbors.push((downstream_id, false));
+                    continue;
+                }
+                enclave.set_downstream_id_at(downstream_id, i);
+                i += 1;
+            }
+            enclave.set_num_downstream(i as i32);
+            for (neighbor_id, is_upstream) in unknown_neighbors {
+                fed.add_unknown_neighbor(neighbor_id, is_upstream);
+            }
+        }
+
+        locked_rti.merge_partitions(fed_id);
+        let diff = match locked_rti.federation_metadata().clone() {
+            Some(metadata) => metadata.diff_federate(&locked_rti.enclaves()[usize::from(fed_id)]),
+            None => Vec::new(),
+        };
+        if !diff.is_empty() {
+            error!(
+                "RTI: Federate {} does not match the federation metadata, which suggests that it runs a mismatched binary:\n{}\nRejecting federate and shutting down.",
+                federate_label(fed_id),
+                diff.join("\n")
+            );
+            Self::send_reject(stream, ErrType::ProtocolViolation.to_byte());
+            let shutdown = locked_rti.shutdown_handle();
+            drop(locked_rti);
+            shutdown.request("a federate that does not match the federation metadata");
+            return false;
+        }
+        true
+    }
+
+    fn receive_udp_message_and_set_up_clock_sync(
+        fed_id: u16,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+    ) -> bool {
+        // Read the MsgType::UdpPort message from the federate regardless of the status of
+        // clock synchronization. This message will tell the RTI whether the federate
+        // is doing clock synchronization, and if it is, what port to use for UDP.
+        debug!(
+            "RTI waiting for MsgType::UdpPort from federate {}.",
+            federate_label(fed_id)
+        );
+        let cloned_rti = Arc::clone(&_f_rti);
+        // The message type has already been read.
+        let mut response = vec![0 as u8; mem::size_of::<u16>()];
+        if !NetUtil::read_from_stream(stream, &mut response, fed_id, "MsgType::UdpPort message") {
+            return false;
+        }
+        {
+            let clock_sync_global_status;
+            let fast_mode;
+            let clock;
+            let exchanges;
+            {
+                let locked_rti = read_rti(&cloned_rti);
+                clock_sync_global_status = locked_rti.clock_sync_global_status();
+                fast_mode = locked_rti.fast_mode();
+                clock = locked_rti.physical_clock();
+                exchanges = locked_rti.clock_sync_exchanges_per_interval();
+            }
+
+            if clock_sync_global_status == ClockSyncStat::ClockSyncOff && fast_mode {
+                info!(
+                    "RTI: Federate {} will not be clock synchronized because the federation runs in fast mode.",
+                    federate_label(fed_id)
+                );
+            }
+
+            if clock_sync_global_status >= ClockSyncStat::ClockSyncInit {
+                // If no initial clock sync, no need perform initial clock sync.
+                // FIXME: Change from_le_bytes properly.
+                let federate_udp_port_number =
+                    u16::from_le_bytes(response[0..2].try_into().unwrap());
+
+                debug!(
+                    "RTI got MsgType::UdpPort {} from federate {}.",
+                    federate_udp_port_number,
+                    federate_label(fed_id)
+                );
+                // A port number of UINT16_MAX means initial clock sync should not be performed.
+                if federate_udp_port_number != u16::MAX {
+                    for _ in 0..exchanges {
+                        if !Self::exchange_clock_sync_messages(fed_id, stream, &clock) {
+                            return false;
+                        }
+                    }
+                    debug!(
+                        "RTI finished initial clock synchronization with federate {}.",
+                        federate_label(fed_id)
+                    );
+                }
+                if clock_sync_global_status >= ClockSyncStat::ClockSyncOn {
+                    // If no runtime clock sync, no need to set up the UDP port.
+                    let mut locked_rti = lock_rti(&cloned_rti);
+                    let fed: &mut Federate = &mut locked_rti.enclaves()[usize::from(fed_id)];
+                    if federate_udp_port_number > 0 && federate_udp_port_number != u16::MAX {
+                        // The UDP socket of the federate is at the address of its TCP connection.
+                        fed.set_udp_port(federate_udp_port_number);
+                    } else {
+                        fed.set_clock_synchronization_enabled(false);
+                    }
+                } else {
+                    // Disable clock sync after initial round.
+                    let mut locked_rti = lock_rti(&cloned_rti);
+                    let idx: usize = fed_id.into();
+                    let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
+                    fed.set_clock_synchronization_enabled(false);
+                }
+            } else {
+                // No clock synchronization at all.
+                // Clock synchronization is universally disabled via the clock-sync command-line parameter
+                // (-c off was passed to the RTI).
+                // Note that the federates are still going to send a MSG_TYPE_UdpPort message but with a payload (port) of -1.
+                let mut locked_rti = lock_rti(&cloned_rti);
+                let idx: usize = fed_id.into();
+                let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
+                fed.set_clock_synchronization_enabled(false);
+            }
+        }
+        true
+    }
+
+    /**
+     * Perform one initial clock synchronization exchange with a federate over
+     * its TCP connection: send a MsgType::ClockSyncT1 message, read the
+     * MsgType::ClockSyncT3 reply, and answer with a MsgType::ClockSyncT4
+     * message. Reject the federate if it replies with another message.
+     */
+    fn exchange_clock_sync_messages(
+        fed_id: u16,
+        stream: &mut impl Transport,
+        clock: &PhysicalClock,
+    ) -> bool {
+        let t1 = clock_sync::clock_sync_message(MsgType::ClockSyncT1, clock);
+        if stream.write_all(&t1).is_err() {
+            error!(
+                "RTI failed to send MsgType::ClockSyncT1 to federate {}.",
+                federate_label(fed_id)
+            );
+            return false;
+        }
+        let mut t3 = vec![0u8; MSG_TYPE_CLOCK_SYNC_T3_LENGTH];
+        if !NetUtil::read_from_stream(stream, &mut t3, fed_id, "MsgType::ClockSyncT3 message") {
+            return false;
+        }
+        if t3[0] != MsgType::ClockSyncT3.to_byte() {
+            warn!(
+                "RTI: Unexpected message {} from federate {} during clock synchronization. Rejecting federate.",
+                t3[0],
+                federate_label(fed_id)
+            );
+            Self::send_reject(stream, ErrType::UnexpectedMessage.to_byte());
+            return false;
+        }
+        let t4 = clock_sync::clock_sync_message(MsgType::ClockSyncT4, clock);
+        if stream.write_all(&t4).is_err() {
+            error!(
+                "RTI failed to send MsgType::ClockSyncT4 to federate {}.",
+                federate_label(fed_id)
+            );
+            return false;
+        }
+        true
+    }
+
+    /**
+     * Handle a proposed start time from a federate. The federate that proposes
+     * last sends the start time to all federates so that no thread has to wait
+     * for the federates it does not serve.
+     */
+    fn handle_timestamp(
+        fed_id: u16,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+    ) -> Result<(), ReadError> {
+        let timestamp = NetUtil::read_from_socket_i64(stream)?;
+        debug_for!(
+            fed_id,
+            "RTI received timestamp message with time: {} from federate {}.",
+            timestamp,
+            federate_label(fed_id)
+        );
+
+        {
+            let mut locked_rti = lock_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(
+                TraceEvent::ReceiveTimestamp,
+                fed_id,
+                Some(&Tag::new(timestamp, 0)),
+            );
+            locked_rti.wait_stats().record_start_time_proposal(fed_id);
+            let fed = &mut locked_rti.enclaves()[usize::from(fed_id)];
+            fed.set_start_time_proposed(true);
+            if fed.e().transient() {
+                drop(locked_rti);
+                return Self::handle_transient_timestamp(
+                    fed_id,
+                    timestamp,
+                    _f_rti,
+                    start_time,
+                    sent_start_time,
+                );
+            }
+            let number_of_persistent_federates = locked_rti.number_of_persistent_federates();
+            let max_start_time = locked_rti.max_start_time();
+            let num_feds_proposed_start = locked_rti.num_feds_proposed_start() + 1;
+            locked_rti.set_num_feds_proposed_start(num_feds_proposed_start);
+            if timestamp > max_start_time {
+                locked_rti.set_max_start_time(timestamp);
+            }
+            if num_feds_proposed_start < number_of_persistent_federates {
+                // Some federates have not yet proposed a start time.
+                return Ok(());
+            }
+            if locked_rti.start_held() {
+                let now = locked_rti.physical_clock().now();
+                locked_rti.set_held_since(Some(now));
+                info!("RTI: All federates have proposed a start time. Holding it until it is released with the start command of the admin console.");
+                return Ok(());
+            }
+        }
+
+        Self::send_start_time(_f_rti, start_time, sent_start_time);
+        Ok(())
+    }
+
+    /**
+     * Decide the start time, once all federates have proposed one, and send it
+     * to them.
+     */
+    pub(crate) fn send_start_time(
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+    ) {
+        // All federates have proposed a start time. With a parent RTI, the
+        // start time is the one decided by the parent RTI for all partitions.
+        let parent_link = lock_rti(&_f_rti).parent_link().clone();
+        let parent_start_time = match &parent_link {
+            Some(parent_link) => {
+                let max_start_time = lock_rti(&_f_rti).max_start_time();
+                match parent_link.exchange_start_time(max_start_time) {
+                    Ok(parent_start_time) => Some(parent_start_time),
+                    Err(e) => {
+                        error!(
+                            "RTI: Failed to receive the start time from the parent RTI: {}.",
+                            e
+                        );
+                        lock_rti(&_f_rti)
+                            .shutdown_handle()
+                            .request("a broken connection to the parent RTI");
+                        return;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // Send back to the federates the maximum time plus an offset on a Timestamp
+        // message.
+        let mut start_time_buffer = vec![0 as u8; MSG_TYPE_TIMESTAMP_LENGTH];
+        start_time_buffer[0] = MsgType::Timestamp.to_byte();
+        let mut locked_start_time = start_time.lock().unwrap();
+        let mut locked_rti = lock_rti(&_f_rti);
+        Self::set_up_topology(&mut locked_rti);
+        if locked_rti.shutdown_handle().requested() {
+            // The federates are notified of the shutdown instead.
+            return;
+        }
+        // A restarted federation keeps the start time of the snapshot, so that
+        // the tags of the federates line up with those before the snapshot.
+        let restored_start_time = locked_rti
+            .restored_snapshot()
+            .as_ref()
+            .and_then(|snapshot| snapshot.start_time());
+        // Add an offset to this start time to get everyone starting together.
+        locked_start_time.set_start_time(
+            parent_start_time
+                .or(restored_start_time)
+                .unwrap_or_else(|| locked_rti.max_start_time() + net_common::DELAY_START),
+        );
+        // TODO: Consider swap_bytes_if_big_endian_int64()
+        NetUtil::encode_int64(locked_start_time.start_time(), &mut start_time_buffer, 1);
+        let start_time_value = locked_start_time.start_time();
+        // Isolated federates can advance to the stop tag (or forever) right away.
+        locked_rti.set_trace_start_time(start_time_value);
+        tag::set_printed_start_time(start_time_value);
+        let unconstrained_tag = locked_rti.unconstrained_tag(start_time_value);
+        let mut isolated_federates = Vec::new();
+        let mut started_federates = Vec::new();
+
+        if let Some(snapshot) = locked_rti.restored_snapshot().clone() {
+            snapshot.restore(&locked_rti);
+        }
+
+        let start_tag = Tag::new(start_time_value, 0);
+        // Federates start executing at the start time, which may be in the past.
+        let started_at = locked_rti.physical_clock().now().max(start_time_value);
+        for fed in locked_rti.enclaves().iter_mut() {
+            // A federate that has already failed does not need the start time,
+            // and a transient federate that has not proposed one yet gets it
+            // when it does.
+            if fed.e().state() == FedState::NotConnected || !fed.start_time_proposed() {
+                continue;
+            }
+            let id = fed.e().id();
+            let stream = fed.stream().as_ref().unwrap();
+            let bytes_written = NetUtil::write_to_stream(stream, &start_time_buffer, id);
+            fed.record_sent(&start_time_buffer[..bytes_written]);
+            if bytes_written < MSG_TYPE_TIMESTAMP_LENGTH {
+                error!(
+                    "Failed to send the starting time to federate {}.",
+                    federate_label(id)
+                );
+            }
+            if fed.e().transient() {
+                Self::send_start_tag(fed, &start_tag);
+            }
+            fed.enclave().set_start_tag(start_tag.clone());
+            fed.set_started_at(started_at);
+
+            // Update state for the federate to indicate that the MSG_TYPE_Timestamp
+            // message has been sent. That MSG_TYPE_Timestamp message grants time advance to
+            // the federate to the start time.
+            fed.enclave().set_state(FedState::Granted);
+            info!(
+                "RTI sent start time {} to federate {}.",
+                locked_start_time.start_time(),
+                federate_label(id)
+            );
+            started_federates.push(id);
+            if fed.e().num_upstream() == 0
+                && fed.e().num_downstream() == 0
+                && !fed.e().decentralized()
+            {
+                isolated_federates.push(id);
+            }
+        }
+        for id in started_federates {
+            locked_rti.tracepoint_rti_to_federate(
+                TraceEvent::SendTimestamp,
+                id,
+                Some(&Tag::new(start_time_value, 0)),
+            );
+        }
+        locked_rti.wait_stats().record_start_time_sent();
+        {
+            let (lock, condvar) = &*sent_start_time;
+            let mut notified = lock.lock().unwrap();
+            *notified = true;
+            condvar.notify_all();
+        }
+        drop(locked_rti);
+        drop(locked_start_time);
+
+        // A federate without upstream and downstream federates is never
+        // constrained by others, so grant it the whole execution at once rather
+        // than exchanging NET and TAG messages for the entire run.
+        let locked_rti = read_rti(&_f_rti);
+        for id in isolated_federates {
+            info!(
+                "RTI: Federate {} has no upstream or downstream federates.",
+                federate_label(id)
+            );
+            let _grants = locked_rti.lock_grants(id);
+            Enclave::notify_tag_advance_grant(
+                &locked_rti,
+                id,
+                unconstrained_tag.clone(),
+                "the federate has no upstream or downstream federates",
+                start_time_value,
+            );
+        }
+        drop(locked_rti);
+        if let Some(parent_link) = parent_link {
+            if let Err(e) = parent_link.start_listener_thread(_f_rti.clone(), start_time) {
+                error!("RTI: Failed to listen to the parent RTI: {}.", e);
+                lock_rti(&_f_rti)
+                    .shutdown_handle()
+                    .request("a broken connection to the parent RTI");
+            }
+        }
+    }
+
+    /**
+     * Handle the proposed start time of a transient federate. If the start
+     * time has not been sent yet, the federate gets it with the other
+     * federates. Otherwise, the federate joins the running federation at the
+     * start tag returned by effective_start_tag().
+     */
+    fn handle_transient_timestamp(
+        fed_id: u16,
+        timestamp: Instant,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+    ) -> Result<(), ReadError> {
+        // Hold the same locks as the federate that sends the start time, so
+        // that the federate is sent the start time exactly once.
+        let locked_start_time = start_time.lock().unwrap();
+        let mut locked_rti = lock_rti(&_f_rti);
+        let idx: usize = fed_id.into();
+        if !*sent_start_time.0.lock().unwrap() {
+            if timestamp > locked_rti.max_start_time() {
+                locked_rti.set_max_start_time(timestamp);
+            }
+            return Ok(());
+        }
+        if locked_rti.enclaves()[idx].e().start_tag().time() != NEVER {
+            // The federate was sent the start time with the other federates.
+            return Ok(());
+        }
+        let start_time_value = locked_start_time.start_time();
+        let start_tag =
+            Self::effective_start_tag(&mut locked_rti, fed_id, timestamp, start_time_value);
+
+        let mut start_time_buffer = vec![0u8; MSG_TYPE_TIMESTAMP_LENGTH];
+        start_time_buffer[0] = MsgType::Timestamp.to_byte();
+        NetUtil::encode_int64(start_time_value, &mut start_time_buffer, 1);
+        let unconstrained_tag = locked_rti.unconstrained_tag(start_time_value);
+        let started_at = locked_rti.physical_clock().now().max(start_time_value);
+        let fed = &mut locked_rti.enclaves()[idx];
+        fed.set_started_at(started_at);
+        {
+            // Messages relayed to the federate must not overtake the start time.
+            let mut buffer = start_time_buffer;
+            buffer.extend_from_slice(&Self::start_tag_buffer(&start_tag));
+            let stream = fed.stream().as_ref().unwrap();
+            let outgoing_turn = fed.outgoing_lock().reserve();
+            if let Err(e) = outgoing_turn.write(stream, &buffer, fed.wire_dump()) {
+                error!(
+                    "Failed to send the starting time to federate {}: {}.",
+                    federate_label(fed_id),
+                    e
+                );
+            }
+        }
+        // The federate has no event before its start tag, which bounds the
+        // grants of its downstream federates until it sends a NET.
+        let enclave = fed.enclave();
+        enclave.set_start_tag(start_tag.clone());
+        enclave.set_next_event(start_tag.clone());
+        enclave.set_state(FedState::Granted);
+        let isolated = enclave.num_upstream() == 0
+            && enclave.num_downstream() == 0
+            && !enclave.decentralized();
+        locked_rti.tracepoint_rti_to_federate(
+            TraceEvent::SendTimestamp,
+            fed_id,
+            Some(&Tag::new(start_time_value, 0)),
+        );
+        locked_rti.record_event(
+            "joined",
+            Some(fed_id),
+            Some((&start_tag, start_time_value)),
+            None,
+        );
+        info!(
+            "RTI sent start time {} and start tag {} to transient federate {}.",
+            start_time_value,
+            Enclave::relative_tag(&start_tag, start_time_value),
+            federate_label(fed_id)
+        );
+        drop(locked_start_time);
+        if isolated {
+            Enclave::notify_tag_advance_grant(
+                &locked_rti,
+                fed_id,
+                unconstrained_tag,
+                "the federate has no upstream or downstream federates",
+                start_time_value,
+            );
+        } else {
+            // Grants to the federate are not sent before its start time, so
+            // send the one that its upstream federates allow already.
+            let number_of_enclaves = locked_rti.number_of_enclaves();
+            Enclave::notify_advance_grant_if_safe(
+                &locked_rti,
+                fed_id,
+                number_of_enclaves,
+                start_time_value,
+            );
+        }
+        Ok(())
+    }
+
+    /**
+     * Return the tag at which a transient federate that joins the running
+     * federation starts. It is the tag of its proposed start time, unless a
+     * message that it sends at that tag could arrive at a downstream federate
+     * at or before a tag that has already been granted to it. Then it is the
+     * earliest tag from which messages arrive after the grants of all
+     * downstream federates.
+     */
+    fn effective_start_tag(
+        locked_rti: &mut FederationRTI,
+        fed_id: u16,
+        timestamp: Instant,
+        start_time: Instant,
+    ) -> Tag {
+        let mut start_tag = Tag::new(timestamp.max(start_time), 0);
+        let number_of_enclaves = locked_rti.number_of_enclaves() as u16;
+        // The delays are collected first because enclaves() borrows the RTI
+        // mutably.
+        let delays: Vec<(u16, i64)> = match locked_rti.topology() {
+            Some(topology) => (0..number_of_enclaves)
+                .filter(|&downstream_id| downstream_id != fed_id)
+                .filter_map(|downstream_id| {
+                    topology
+                        .min_delay(fed_id, downstream_id)
+                        .map(|delay| (downstream_id, delay))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        for (downstream_id, delay) in delays {
+            let e = locked_rti.enclaves()[usize::from(downstream_id)].e();
+            if e.absent() || e.decentralized() {
+                continue;
+            }
+            let mut granted = e.last_granted();
+            if e.last_provisionally_granted() > granted {
+                granted = e.last_provisionally_granted();
+            }
+            if granted.time() == NEVER || granted.time() == FOREVER {
+                continue;
+            }
+            // A message sent at tag t arrives at (t + delay, 0), or one
+            // microstep after t if the delay is 0.
+            let earliest = if delay == 0 {
+                granted.checked_next_microstep()
+            } else {
+                granted
+                    .checked_sub(delay - 1)
+                    .map(|tag| Tag::new(tag.time(), 0))
+            };
+            let Ok(earliest) = earliest else {
+                // No message can arrive at a tag that is not representable.
+                continue;
+            };
+            if earliest > start_tag {
+                info!(
+                    "RTI: Transient federate {} starts after the grant {} of federate {}.",
+                    federate_label(fed_id),
+                    Enclave::relative_tag(&granted, start_time),
+                    federate_label(downstream_id)
                 );
+                start_tag = earliest;
             }
         }
-        true
+        start_tag
     }
 
-    fn receive_udp_message_and_set_up_clock_sync(
-        &mut self,
-        fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
-    ) -> bool {
-        // Read the MsgType::UdpPort message from the federate regardless of the status of
-        // clock synchronization. This message will tell the RTI whether the federate
-        // is doing clock synchronization, and if it is, what port to use for UDP.
-        println!("RTI waiting for MsgType::UdpPort from federate {}.", fed_id);
-        let cloned_rti = Arc::clone(&_f_rti);
-        let mut response = vec![0 as u8; 1 + mem::size_of::<u16>()];
-        NetUtil::read_from_stream_errexit(
-            stream,
-            &mut response,
-            fed_id,
-            "MsgType::UdpPort message",
-        );
-        if response[0] != MsgType::UdpPort.to_byte() {
-            println!("RTI was expecting a MsgType::UdpPort message from federate {}. Got {} instead. Rejecting federate.", fed_id, response[0]);
-            Self::send_reject(stream, ErrType::UnexpectedMessage.to_byte());
-            return false;
-        } else {
-            let clock_sync_global_status;
-            {
-                let locked_rti = cloned_rti.lock().unwrap();
-                clock_sync_global_status = locked_rti.clock_sync_global_status();
-            }
-
-            if clock_sync_global_status >= ClockSyncStat::ClockSyncInit {
-                // If no initial clock sync, no need perform initial clock sync.
-                // FIXME: Change from_le_bytes properly.
-                let federate_udp_port_number =
-                    u16::from_le_bytes(response[1..3].try_into().unwrap());
-
-                println!(
-                    "RTI got MsgType::UdpPort {} from federate {}.",
-                    federate_udp_port_number, fed_id
-                );
-                // A port number of UINT16_MAX means initial clock sync should not be performed.
-                if federate_udp_port_number != u16::MAX {
-                    // TODO: Implement this if body
-                    println!(
-                        "RTI finished initial clock synchronization with federate {}.",
-                        fed_id
-                    );
-                }
-                if clock_sync_global_status >= ClockSyncStat::ClockSyncOn {
-                    // If no runtime clock sync, no need to set up the UDP port.
-                    if federate_udp_port_number > 0 {
-                        // Initialize the UDP_addr field of the federate struct
-                        // TODO: Handle below assignments
-                        // fed.UDP_addr.sin_family = AF_INET;
-                        // fed.UDP_addr.sin_port = htons(federate_udp_port_number);
-                        // fed.UDP_addr.sin_addr = fed->server_ip_addr;
-                    }
-                } else {
-                    // Disable clock sync after initial round.
-                    let mut locked_rti = cloned_rti.lock().unwrap();
-                    let idx: usize = fed_id.into();
-                    let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-                    fed.set_clock_synchronization_enabled(false);
-                }
-            } else {
-                // No clock synchronization at all.
-                // Clock synchronization is universally disabled via the clock-sync command-line parameter
-                // (-c off was passed to the RTI).
-                // Note that the federates are still going to send a MSG_TYPE_UdpPort message but with a payload (port) of -1.
-                let mut locked_rti = cloned_rti.lock().unwrap();
-                let idx: usize = fed_id.into();
-                let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-                fed.set_clock_synchronization_enabled(false);
-            }
+    /**
+     * Send a MsgType::StartTag message with the given tag to a transient
+     * federate. See MSG_TYPE_START_TAG_LENGTH.
+     */
+    fn send_start_tag(fed: &Federate, start_tag: &Tag) {
+        let buffer = Self::start_tag_buffer(start_tag);
+        let fed_id = fed.e().id();
+        let bytes_written =
+            NetUtil::write_to_stream(fed.stream().as_ref().unwrap(), &buffer, fed_id);
+        fed.record_sent(&buffer[..bytes_written]);
+        if bytes_written < MSG_TYPE_START_TAG_LENGTH {
+            error!(
+                "Failed to send the start tag to federate {}.",
+                federate_label(fed_id)
+            );
         }
-        true
     }
 
-    fn handle_timestamp(
+    fn start_tag_buffer(start_tag: &Tag) -> Vec<u8> {
+        let mut buffer = vec![0u8; MSG_TYPE_START_TAG_LENGTH];
+        buffer[0] = MsgType::StartTag.to_byte();
+        NetUtil::encode_int64(start_tag.time(), &mut buffer, 1);
+        NetUtil::encode_int32(
+            start_tag.microstep() as i32,
+            &mut buffer,
+            1 + mem::size_of::<i64>(),
+        );
+        buffer
+    }
+
+    /**
+     * Handle a failed read from the socket of the given federate. A connection
+     * closed between two messages means that the federate exited without
+     * resigning, while any other error leaves the stream at an unknown position.
+     * In both cases, the federate is handled as a failed federate, unless it
+     * can resume its session (see resumption::suspend_federate).
+     */
+    fn handle_read_error(
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        error: ReadError,
+        mid_message: bool,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
-        received_start_times: Arc<(Mutex<bool>, Condvar)>,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
+        stop_granted: Arc<Mutex<StopGranted>>,
     ) {
-        let mut buffer = vec![0 as u8; mem::size_of::<i64>()];
-        let bytes_read = NetUtil::read_from_stream(stream, &mut buffer, fed_id);
-        if bytes_read < mem::size_of::<i64>() {
-            println!("ERROR reading timestamp from federate {}.", fed_id);
-        }
-
-        // FIXME: Check whether swap_bytes_if_big_endian_int64() is implemented correctly
-        let timestamp = i64::from_le_bytes(buffer.try_into().unwrap());
-        println!("RTI received timestamp message with time: {} .", timestamp);
-
-        let mut num_feds_proposed_start;
-        let number_of_enclaves;
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            number_of_enclaves = locked_rti.number_of_enclaves();
-            let max_start_time = locked_rti.max_start_time();
-            num_feds_proposed_start = locked_rti.num_feds_proposed_start();
-            num_feds_proposed_start += 1;
-            locked_rti.set_num_feds_proposed_start(num_feds_proposed_start);
-            if timestamp > max_start_time {
-                locked_rti.set_max_start_time(timestamp);
+            let mut locked_rti = lock_rti(&_f_rti);
+            let idx: usize = fed_id.into();
+            if locked_rti.shutdown_handle().requested()
+                && locked_rti.enclaves()[idx].e().state() == FedState::NotConnected
+            {
+                // The RTI closed the connection because it is shutting down.
+                return;
+            }
+            // A connection lost between two messages may be resumed.
+            if !mid_message
+                && resumption::suspend_federate(&mut locked_rti, fed_id, &error.to_string())
+            {
+                return;
             }
         }
-        if num_feds_proposed_start == number_of_enclaves {
-            // All federates have proposed a start time.
-            let received_start_times_notifier = Arc::clone(&received_start_times);
-            let (lock, condvar) = &*received_start_times_notifier;
-            let mut notified = lock.lock().unwrap();
-            *notified = true;
-            condvar.notify_all();
+        let reason = if !mid_message && error.kind() == ReadErrorKind::Closed {
+            info!(
+                "RTI: Socket to federate {} is closed. Exiting the thread.",
+                federate_label(fed_id)
+            );
+            String::from("the connection was closed without resigning")
         } else {
-            // Some federates have not yet proposed a start time.
-            // wait for a notification.
-            while num_feds_proposed_start < number_of_enclaves {
-                // FIXME: Should have a timeout here?
-                let (lock, condvar) = &*received_start_times;
-                let mut notified = lock.lock().unwrap();
-                while !*notified {
-                    notified = condvar.wait(notified).unwrap();
-                }
-                {
-                    let locked_rti = _f_rti.lock().unwrap();
-                    num_feds_proposed_start = locked_rti.num_feds_proposed_start();
+            format!("failed to read a message ({})", error)
+        };
+        Self::handle_federate_failed(fed_id, reason, _f_rti, start_time, stop_granted);
+    }
+
+    /**
+     * Shut down the RTI before the federates have exited: send every connected
+     * federate a MsgType::Failed message, so that it knows that the RTI is
+     * gone rather than waiting for a grant, and close its connections. The
+     * threads serving the federates then see that the federates are no longer
+     * connected and exit.
+     */
+    fn shut_down(_f_rti: &Arc<RwLock<FederationRTI>>, reason: &str) {
+        warn!(
+            "RTI: Shutting down because of {}. Notifying the connected federates.",
+            reason
+        );
+        let mut notified_federates = Vec::new();
+        {
+            let mut locked_rti = lock_rti(_f_rti);
+            locked_rti.record_event("shutdown", None, None, Some(reason));
+            for fed in locked_rti.enclaves().iter_mut() {
+                if fed.e().state() != FedState::NotConnected && fed.stream().is_some() {
+                    Self::notify_shutdown(fed);
+                    notified_federates.push(fed.e().id());
                 }
             }
+            for fed_id in notified_federates {
+                locked_rti.tracepoint_rti_to_federate(TraceEvent::SendFailed, fed_id, None);
+            }
         }
+    }
 
-        // Send back to the federate the maximum time plus an offset on a Timestamp
-        // message.
-        let mut start_time_buffer = vec![0 as u8; MSG_TYPE_TIMESTAMP_LENGTH];
-        start_time_buffer[0] = MsgType::Timestamp.to_byte();
-        // Add an offset to this start time to get everyone starting together.
-        let max_start_time;
+    /**
+     * Send the given federate a MsgType::Failed message on behalf of the RTI,
+     * close its connections, and mark it as no longer connected. The message
+     * is dropped if another thread is writing to the federate or if the
+     * federate does not take it in time, since the closed connection tells
+     * the federate as much.
+     */
+    fn notify_shutdown(fed: &mut Federate) {
+        let outgoing_lock = fed.outgoing_lock();
+        if let (Some(_outgoing), Some(stream)) = (outgoing_lock.try_lock(), fed.stream().as_ref()) {
+            // NOTE: Ignore errors because the federate may be gone already.
+            let _ =
+                stream.set_write_timeout(Some(Duration::from_millis(SHUTDOWN_WRITE_TIMEOUT_MS)));
+            let buffer = [MsgType::Failed.to_byte()];
+            if let Ok(bytes_written) = (&*stream).write(&buffer) {
+                fed.record_sent(&buffer[..bytes_written]);
+            }
+        }
+        for stream in [fed.stream(), fed.control_stream()].into_iter().flatten() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        fed.enclave().set_state(FedState::NotConnected);
+    }
+
+    fn handle_federate_resign(
+        fed_id: u16,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+    ) {
+        // Nothing more to do. Close the socket and exit.
         {
-            let locked_rti = _f_rti.lock().unwrap();
-            max_start_time = locked_rti.max_start_time();
+            let locked_rti = read_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(TraceEvent::ReceiveResign, fed_id, None);
+            locked_rti.record_event("resigned", Some(fed_id), None, None);
         }
-        let mut locked_start_time = start_time.lock().unwrap();
-        locked_start_time.set_start_time(max_start_time + net_common::DELAY_START);
-        // TODO: Consider swap_bytes_if_big_endian_int64()
-        NetUtil::encode_int64(locked_start_time.start_time(), &mut start_time_buffer, 1);
+        Self::disconnect_federate(fed_id, _f_rti, start_time);
+        info!("Federate {} has resigned.", federate_label(fed_id));
+    }
 
+    /**
+     * Handle a federate that has failed, either because it sent a
+     * MsgType::Failed message or because its connection was closed without
+     * resigning. The failure is recorded so that it appears in the run summary
+     * and in the exit code of the RTI. Downstream federates are treated as if
+     * the failed federate had resigned. If the failure policy is
+     * FailurePolicy::Abort, the RTI also requests all other federates to stop
+     * on behalf of the failed federate.
+     */
+    pub(crate) fn handle_federate_failed(
+        fed_id: u16,
+        reason: String,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) {
+        error!(
+            "RTI: Federate {} has failed ({}) and has exited.",
+            federate_label(fed_id),
+            reason
+        );
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             let idx: usize = fed_id.into();
+            locked_rti.tracepoint_rti_from_federate(TraceEvent::ReceiveFailed, fed_id, None);
+            locked_rti.record_event("failed", Some(fed_id), None, Some(&reason));
             let my_fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            let stream = my_fed.stream().as_ref().unwrap();
-            let bytes_written = NetUtil::write_to_stream(stream, &start_time_buffer, fed_id);
-            if bytes_written < MSG_TYPE_TIMESTAMP_LENGTH {
-                println!("Failed to send the starting time to federate {}.", fed_id);
-            }
+            my_fed.set_failure_reason(reason);
+        }
+        Self::disconnect_federate(fed_id, _f_rti.clone(), start_time.clone());
 
-            // Update state for the federate to indicate that the MSG_TYPE_Timestamp
-            // message has been sent. That MSG_TYPE_Timestamp message grants time advance to
-            // the federate to the start time.
-            my_fed.enclave().set_state(FedState::Granted);
-            let sent_start_time_notifier = Arc::clone(&sent_start_time);
-            let (lock, condvar) = &*sent_start_time_notifier;
-            let mut notified = lock.lock().unwrap();
-            *notified = true;
-            condvar.notify_all();
-            println!(
-                "RTI sent start time {} to federate {}.",
-                locked_start_time.start_time(),
-                my_fed.enclave().id()
-            );
+        let failure_policy;
+        let proposed_stop_tag;
+        {
+            let mut locked_rti = lock_rti(&_f_rti);
+            failure_policy = locked_rti.federate_failure_policy(fed_id);
+            let idx: usize = fed_id.into();
+            let my_fed: &mut Federate = &mut locked_rti.enclaves()[idx];
+            proposed_stop_tag = my_fed.e().completed();
+        }
+        if failure_policy != FailurePolicy::Abort {
+            return;
+        }
+        let start_time_value;
+        {
+            let locked_start_time = start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
         }
+        // The other federates will reply with a stop tag no earlier than their
+        // current tag, so the tag completed by the failed federate (or the
+        // start tag if it has not completed any tag) is a safe proposal.
+        let proposed_stop_tag = if proposed_stop_tag == Tag::never_tag() {
+            Tag::new(start_time_value, 0)
+        } else {
+            proposed_stop_tag
+        };
+        error!(
+            "RTI: Aborting the federation because federate {} has failed.",
+            federate_label(fed_id)
+        );
+        Self::handle_stop_request(
+            Some(fed_id),
+            proposed_stop_tag,
+            _f_rti,
+            start_time_value,
+            stop_granted,
+        );
     }
 
-    fn handle_federate_resign(
+    fn disconnect_federate(
         fed_id: u16,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
     ) {
-        // Nothing more to do. Close the socket and exit.
-
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             let idx: usize = fed_id.into();
             let my_fed: &mut Federate = &mut locked_rti.enclaves()[idx];
             my_fed.enclave().set_state(FedState::NotConnected);
+            my_fed.provisional_grants().record_disconnection();
+            locked_rti.remove_from_topology(fed_id);
         }
 
         // Indicate that there will no further events from this federate.
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             let idx: usize = fed_id.into();
             let my_fed: &mut Federate = &mut locked_rti.enclaves()[idx];
             my_fed.enclave().set_next_event(Tag::forever_tag());
@@ -778,52 +3349,47 @@ impl Server {
         // Here, we just signal the other side that no further writes to the socket are
         // forthcoming, which should result in the other end getting a zero-length reception.
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             let idx: usize = fed_id.into();
             let my_fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            my_fed
-                .stream()
-                .as_ref()
-                .unwrap()
-                .shutdown(Shutdown::Both)
-                .unwrap();
-
-            println!("Federate {} has resigned.", fed_id);
+            if let Some(stream) = my_fed.stream().as_ref() {
+                // NOTE: Ignore errors because the other end may have already closed the socket.
+                let _ = stream.shutdown(Shutdown::Both);
+            }
         }
 
         // Check downstream federates to see whether they should now be granted a TAG.
-        // To handle cycles, need to create a boolean array to keep
-        // track of which upstream federates have been visited.
-        let number_of_enclaves;
-        {
-            let locked_rti = _f_rti.lock().unwrap();
-            number_of_enclaves = locked_rti.number_of_enclaves();
-        }
         let start_time_value;
         {
             let locked_start_time = start_time.lock().unwrap();
             start_time_value = locked_start_time.start_time();
         }
-        // FIXME: Handle unwrap properly.
-        let mut visited = vec![false as bool; number_of_enclaves.try_into().unwrap()]; // Initializes to 0.
+        let mut locked_rti = lock_rti(&_f_rti);
+        let number_of_enclaves = locked_rti.number_of_enclaves();
         Enclave::notify_downstream_advance_grant_if_safe(
-            _f_rti.clone(),
+            &locked_rti,
             fed_id,
             number_of_enclaves,
             start_time_value,
-            &mut visited,
-            sent_start_time,
         );
+        // The federate no longer holds back the lookahead window.
+        if locked_rti.has_lookahead() {
+            Enclave::grant_all(&locked_rti, start_time_value);
+        }
+        if let Some(parent_link) = locked_rti.parent_link().clone() {
+            parent_link.report(&mut locked_rti);
+        }
     }
 
     fn handle_timed_message(
         message_type: u8,
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
         sent_start_time: Arc<(Mutex<bool>, Condvar)>,
-    ) {
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> Result<(), ReadError> {
         let header_size = 1
             + mem::size_of::<u16>()
             + mem::size_of::<u16>()
@@ -832,12 +3398,7 @@ impl Server {
             + mem::size_of::<u32>();
         // Read the header, minus the first byte which has already been read.
         let mut header_buffer = vec![0 as u8; (header_size - 1).try_into().unwrap()];
-        NetUtil::read_from_stream_errexit(
-            stream,
-            &mut header_buffer,
-            fed_id,
-            "the timed message header",
-        );
+        NetUtil::read_from_socket(stream, &mut header_buffer)?;
         // Extract the header information. of the sender
         let mut reactor_port_id: u16 = 0;
         let mut federate_id: u16 = 0;
@@ -852,24 +3413,60 @@ impl Server {
             &mut intended_tag,
         );
 
-        // FIXME: Handle "as i32" properly.
-        let total_bytes_to_read = length + header_size as i32;
-        let mut bytes_to_read = length;
+        let length = match usize::try_from(length) {
+            Ok(length) => length,
+            Err(_) => {
+                warn!(
+                    "RTI received a message with negative length {} from federate {}.",
+                    length,
+                    federate_label(fed_id)
+                );
+                return Err(ReadError::new(ReadErrorKind::Corrupt, header_size));
+            }
+        };
 
-        if FED_COM_BUFFER_SIZE < header_size + 1 {
-            println!(
-                "Buffer size ({}) is not large enough to read the header plus one byte.",
-                FED_COM_BUFFER_SIZE
+        // The destination indexes the federates below, so a message to a
+        // federate that is not in the federation is a violation.
+        let number_of_enclaves = read_rti(&_f_rti).number_of_enclaves();
+        if i32::from(federate_id) >= number_of_enclaves {
+            // Consume the payload so that the next message can be read.
+            Self::discard_payload(stream, length)?;
+            Self::handle_protocol_violation(
+                fed_id,
+                format!(
+                    "message for federate {}, which is not in the federation of {} federates",
+                    federate_id, number_of_enclaves
+                ),
+                stream,
+                _f_rti,
+                start_time,
+                stop_granted,
             );
-            // FIXME: Change return to exit.
-            return;
+            return Ok(());
         }
 
-        // Cut up the payload in chunks.
-        // FIXME: Handle unwrap properly.
-        let size_diff = (FED_COM_BUFFER_SIZE - header_size).try_into().unwrap();
-        if bytes_to_read > size_diff {
-            bytes_to_read = size_diff
+        let too_large;
+        let max_message_size;
+        {
+            let locked_rti = read_rti(&_f_rti);
+            too_large = locked_rti.exceeds_max_message_size(length);
+            max_message_size = locked_rti.max_message_size();
+        }
+        if too_large {
+            warn!(
+                "RTI: Message of {} bytes from federate {} for federate {} exceeds the maximum message size of {} bytes. Dropping message.",
+                length, federate_label(fed_id), federate_label(federate_id), max_message_size
+            );
+            // Consume the payload so that the next message can be read.
+            Self::discard_payload(stream, length)?;
+            let outgoing_turn = {
+                let locked_rti = read_rti(&_f_rti);
+                let idx: usize = fed_id.into();
+                locked_rti.federates()[idx].outgoing_lock().reserve()
+            };
+            let _outgoing_guard = outgoing_turn.wait();
+            Self::send_error(stream, ErrType::MessageTooLarge.to_byte());
+            return Ok(());
         }
 
         let start_time_value;
@@ -877,312 +3474,848 @@ impl Server {
             let locked_start_time = start_time.lock().unwrap();
             start_time_value = locked_start_time.start_time();
         }
-        println!("RTI received message from federate {} for federate {} port {} with intended tag ({}, {}). Forwarding.",
-                fed_id, federate_id, reactor_port_id,
-                intended_tag.time() - start_time_value, intended_tag.microstep());
-
-        let mut message_buffer = vec![0 as u8; bytes_to_read.try_into().unwrap()];
-        NetUtil::read_from_stream_errexit(stream, &mut message_buffer, fed_id, "timed message");
-        // FIXME: Handle "as i32" properly.
-        let bytes_read = bytes_to_read + header_size as i32;
-        // Following only works for string messages.
-        // println!("Message received by RTI: {}.", buffer + header_size);
+        debug!("RTI received message from federate {} for federate {} port {} with intended tag ({}, {}). Forwarding.",
+                federate_label(fed_id), federate_label(federate_id), reactor_port_id,
+                tag::format_time(intended_tag.time(), start_time_value), intended_tag.microstep());
 
         let completed;
+        let egress_delay;
         {
-            // Need to acquire the mutex lock to ensure that the thread handling
-            // messages coming from the socket connected to the destination does not
-            // issue a TAG before this message has been forwarded.
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = read_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(
+                TraceEvent::ReceiveTaggedMsg,
+                fed_id,
+                Some(&intended_tag),
+            );
 
             // If the destination federate is no longer connected, issue a warning
             // and return.
             let idx: usize = federate_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            let enclave = fed.enclave();
-            if enclave.state() == FedState::NotConnected {
-                println!(
+            let fed: &Federate = &locked_rti.federates()[idx];
+            let enclave = fed.e();
+            if enclave.absent() {
+                warn!(
                     "RTI: Destination federate {} is no longer connected. Dropping message.",
-                    federate_id
+                    federate_label(federate_id)
                 );
-                println!("Fed status: next_event ({}, {}), completed ({}, {}), last_granted ({}, {}), last_provisionally_granted ({}, {}).",
-                        enclave.next_event().time() - start_time_value,
+                debug!("Fed status: next_event ({}, {}), completed ({}, {}), last_granted ({}, {}), last_provisionally_granted ({}, {}).",
+                        tag::format_time(enclave.next_event().time(), start_time_value),
                         enclave.next_event().microstep(),
-                        enclave.completed().time() - start_time_value,
+                        tag::format_time(enclave.completed().time(), start_time_value),
                         enclave.completed().microstep(),
-                        enclave.last_granted().time() - start_time_value,
+                        tag::format_time(enclave.last_granted().time(), start_time_value),
                         enclave.last_granted().microstep(),
-                        enclave.last_provisionally_granted().time() - start_time_value,
+                        tag::format_time(enclave.last_provisionally_granted().time(), start_time_value),
                         enclave.last_provisionally_granted().microstep()
                 );
-                return;
+                drop(locked_rti);
+                // Consume the payload so that the next message can be read.
+                return Self::discard_payload(stream, length);
+            }
+            if enclave.transient() && intended_tag < enclave.start_tag() {
+                warn!(
+                    "RTI: Destination federate {} started at {}, after the intended tag. Dropping message.",
+                    federate_label(federate_id),
+                    Enclave::relative_tag(&enclave.start_tag(), start_time_value)
+                );
+                drop(locked_rti);
+                return Self::discard_payload(stream, length);
             }
 
             completed = enclave.completed();
+            egress_delay = match locked_rti.shape_egress(federate_id, header_size + length) {
+                Some(egress_delay) => egress_delay,
+                None => {
+                    drop(locked_rti);
+                    return Self::discard_payload(stream, length);
+                }
+            };
         }
 
-        println!(
+        debug_for!(
+            federate_id,
             "RTI forwarding message to port {} of federate {} of length {}.",
-            reactor_port_id, federate_id, length
+            reactor_port_id,
+            federate_label(federate_id),
+            length
         );
 
         // Record this in-transit message in federate's in-transit message queue.
-        if Tag::lf_tag_compare(&completed, &intended_tag) < 0 {
-            // Add a record of this message to the list of in-transit messages to this federate.
-            let mut locked_rti = _f_rti.lock().unwrap();
+        if completed < intended_tag {
+            // Add a record of this message to the list of in-transit messages
+            // to this federate. Hold the grant lock so that the destination is
+            // not issued a TAG before this message has been recorded.
+            let locked_rti = read_rti(&_f_rti);
+            let _grants = locked_rti.lock_grants(federate_id);
             let idx: usize = federate_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
+            let fed: &Federate = &locked_rti.federates()[idx];
             MessageRecord::add_in_transit_message_record(
-                fed.in_transit_message_tags(),
+                &mut fed.in_transit_message_tags(),
                 intended_tag.clone(),
             );
-            println!(
+            debug!(
                 "RTI: Adding a message with tag ({}, {}) to the list of in-transit messages for federate {}.",
-                intended_tag.time() - start_time_value,
+                tag::format_time(intended_tag.time(), start_time_value),
                 intended_tag.microstep(),
-                federate_id
+                federate_label(federate_id)
             );
         } else {
-            println!(
+            warn!(
                 "RTI: Federate {} has already completed tag ({}, {}), but there is an in-transit message with tag ({}, {}) from federate {}. This is going to cause an STP violation under centralized coordination.",
-                federate_id,
-                completed.time() - start_time_value,
+                federate_label(federate_id),
+                tag::format_time(completed.time(), start_time_value),
                 completed.microstep(),
-                intended_tag.time() - start_time_value,
+                tag::format_time(intended_tag.time(), start_time_value),
                 intended_tag.microstep(),
-                fed_id
+                federate_label(fed_id)
             );
             // FIXME: Drop the federate?
         }
 
         // Need to make sure that the destination federate's thread has already
-        // sent the starting MsgType::Timestamp message.
+        // sent the starting MsgType::Timestamp message. The start time is sent
+        // under the RTI lock, so wait for it without holding the lock.
+        Self::wait_for_start_time(
+            &_f_rti,
+            federate_id,
+            &sent_start_time,
+            wait_stats::START_TIME_WAIT_TAGGED_MESSAGE,
+        );
+        let destination_stream;
+        let destination_dump;
+        let outgoing_turn;
+        let inspectors;
+        let egress_queue;
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = read_rti(&_f_rti);
+            let grants = locked_rti.lock_grants(federate_id);
             let idx: usize = federate_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            while fed.enclave().state() == FedState::Pending {
-                // Need to wait here.
-                let (lock, condvar) = &*sent_start_time;
-                let mut notified = lock.lock().unwrap();
-                while !*notified {
-                    notified = condvar.wait(notified).unwrap();
-                }
+            let fed: &Federate = &locked_rti.federates()[idx];
+            // The destination may have resigned or been disconnected since the
+            // check above.
+            let absent = fed.e().absent();
+            let suspended = !absent && fed.suspended();
+            if suspended {
+                fed.count_dropped_while_suspended();
+                warn!(
+                    "RTI: Destination federate {} has lost its connection and not resumed its session yet. Dropped message.",
+                    federate_label(federate_id)
+                );
+            }
+            if absent || suspended {
+                drop(grants);
+                drop(locked_rti);
+                return Self::discard_payload(stream, length);
             }
 
             // FIXME: Handle unwrap properly.
-            let destination_stream = fed.stream().as_ref().unwrap();
-            let mut result_buffer = vec![0 as u8; 1];
-            result_buffer[0] = message_type;
-            result_buffer = vec![result_buffer.clone(), header_buffer, message_buffer].concat();
-            NetUtil::write_to_stream_errexit(
-                destination_stream,
-                &result_buffer,
+            destination_stream = fed.stream().as_ref().unwrap().try_clone();
+            destination_dump = fed.wire_dump().clone();
+            // Reserve the turn to write before releasing the grant lock so that
+            // no TAG to the destination can overtake this message, but wait for
+            // it only after releasing the locks.
+            outgoing_turn = fed.outgoing_lock().reserve();
+            if destination_stream.is_ok() {
+                // A TAG sent on the control connection tells the federate to
+                // wait for this message.
+                fed.count_relayed_message();
+            }
+            inspectors = locked_rti.payload_inspectors().clone();
+            egress_queue = fed.egress_queue();
+            locked_rti.tracepoint_rti_to_federate(
+                TraceEvent::SendTaggedMsg,
                 federate_id,
-                "message",
+                Some(&intended_tag),
             );
         }
+        let mut destination_stream = match destination_stream {
+            Ok(destination_stream) => destination_stream,
+            Err(e) => {
+                error!(
+                    "RTI failed to access the stream of federate {}: {}. Dropping message.",
+                    federate_label(federate_id),
+                    e
+                );
+                drop(outgoing_turn);
+                return Self::discard_payload(stream, length);
+            }
+        };
+        let message = ForwardedMessage::new(
+            fed_id,
+            federate_id,
+            reactor_port_id,
+            intended_tag.clone(),
+            length,
+        );
+        let mut result_buffer = vec![message_type];
+        result_buffer.extend_from_slice(&header_buffer);
 
-        // The message length may be longer than the buffer,
-        // in which case we have to handle it in chunks.
-        let mut total_bytes_read = bytes_read;
-        while total_bytes_read < total_bytes_to_read {
-            println!("Forwarding message in chunks.");
-            bytes_to_read = total_bytes_to_read - total_bytes_read;
-            // FIXME: Handle "as i32" properly.
-            let fed_com_buffer_size = FED_COM_BUFFER_SIZE as i32;
-            if bytes_to_read > fed_com_buffer_size {
-                bytes_to_read = fed_com_buffer_size;
+        if !egress_delay.is_zero() {
+            // Read the whole message now and leave it to the queue of the
+            // destination, so that this thread goes on reading the messages of
+            // the federates it serves. The turn to write, reserved above, keeps
+            // the later grants to the destination from overtaking the message.
+            debug!(
+                "RTI: Delaying a message to federate {} by {} us to keep within its egress rate.",
+                federate_label(federate_id),
+                egress_delay.as_micros()
+            );
+            let due = std::time::Instant::now() + egress_delay;
+            result_buffer.resize(result_buffer.len() + length, 0);
+            let payload_start = result_buffer.len() - length;
+            let mut bytes_read = 0;
+            while bytes_read < length {
+                let chunk_size = (length - bytes_read).min(FORWARD_CHUNK_SIZE);
+                let chunk = &mut result_buffer
+                    [payload_start + bytes_read..payload_start + bytes_read + chunk_size];
+                if let Err(e) = NetUtil::read_from_socket(stream, chunk) {
+                    error!(
+                        "RTI failed to read the message from federate {} for federate {}: {}. Dropping message.",
+                        federate_label(fed_id), federate_label(federate_id), e
+                    );
+                    return Err(e);
+                }
+                for inspector in inspectors
+                    .iter()
+                    .filter(|inspector| inspector.wants_payload(&message))
+                {
+                    inspector.inspect_chunk(&message, bytes_read, chunk);
+                }
+                bytes_read += chunk_size;
             }
-            // FIXME: Handle unwrap properly.
-            let mut forward_buffer = vec![0 as u8; bytes_to_read.try_into().unwrap()];
-            NetUtil::read_from_stream_errexit(
-                stream,
-                &mut forward_buffer,
-                fed_id,
-                "message chunks",
+            let _f_rti = _f_rti.clone();
+            egress_queue.push(
+                due,
+                Box::new(move || {
+                    let outgoing_guard = outgoing_turn.wait();
+                    let written = DumpStream::new(&mut destination_stream, destination_dump)
+                        .write_all(&result_buffer)
+                        .is_ok();
+                    drop(outgoing_guard);
+                    Self::finish_relay(&_f_rti, written, &message, &inspectors, start_time_value);
+                }),
             );
-            total_bytes_read += bytes_to_read;
+            return Ok(());
+        }
 
-            // FIXME: a mutex needs to be held for this so that other threads
-            // do not write to destination_socket and cause interleaving. However,
-            // holding the rti_mutex might be very expensive. Instead, each outgoing
-            // socket should probably have its own mutex.
-            {
-                let mut locked_rti = _f_rti.lock().unwrap();
-                let idx: usize = federate_id.into();
-                let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-                // FIXME: Handle unwrap properly.
-                let destination_stream = fed.stream().as_ref().unwrap();
-                NetUtil::write_to_stream_errexit(
-                    destination_stream,
-                    &forward_buffer,
-                    federate_id,
-                    "message chunks",
+        let outgoing_guard = outgoing_turn.wait();
+        let mut destination_stream = DumpStream::new(&mut destination_stream, destination_dump);
+
+        // Stream the payload to the destination without holding the RTI lock, so
+        // that a large payload neither has to fit in memory nor stalls the
+        // federation. The outgoing lock keeps other messages from being
+        // interleaved with its chunks. Writes block while the destination is not
+        // keeping up, which in turn stops reading from the source federate. If
+        // the notifier disconnects the destination meanwhile (see
+        // GrantWritePolicy), the rest of the payload is read and dropped.
+        let mut written = destination_stream.write_all(&result_buffer).is_ok();
+        if length > FORWARD_CHUNK_SIZE {
+            debug!("Forwarding message in chunks.");
+        }
+        let payload_inspectors: Vec<&Arc<dyn PayloadInspector>> = inspectors
+            .iter()
+            .filter(|inspector| inspector.wants_payload(&message))
+            .collect();
+        let mut forward_buffer = vec![0 as u8; length.min(FORWARD_CHUNK_SIZE)];
+        let mut bytes_forwarded = 0;
+        while bytes_forwarded < length {
+            let chunk_size = (length - bytes_forwarded).min(FORWARD_CHUNK_SIZE);
+            if let Err(e) = NetUtil::read_from_socket(stream, &mut forward_buffer[..chunk_size]) {
+                // The destination has received a truncated message that it
+                // cannot interpret, so close its connection, too.
+                error!(
+                    "RTI failed to read the message from federate {} for federate {}: {}. Closing the connection to federate {}.",
+                    federate_label(fed_id), federate_label(federate_id), e, federate_label(federate_id)
+                );
+                // NOTE: Ignore errors because the other end may have already closed the socket.
+                let _ = destination_stream.shutdown();
+                return Err(e);
+            }
+            for inspector in &payload_inspectors {
+                inspector.inspect_chunk(&message, bytes_forwarded, &forward_buffer[..chunk_size]);
+            }
+            written = written
+                && destination_stream
+                    .write_all(&forward_buffer[..chunk_size])
+                    .is_ok();
+            bytes_forwarded += chunk_size;
+        }
+        drop(outgoing_guard);
+        Self::finish_relay(&_f_rti, written, &message, &inspectors, start_time_value);
+        Ok(())
+    }
+
+    /**
+     * Once a relayed message has been written to its destination, notify the
+     * inspectors and update the next event tag of the destination. If it could
+     * not be written, suspend or drop the destination instead.
+     */
+    fn finish_relay(
+        _f_rti: &Arc<RwLock<FederationRTI>>,
+        written: bool,
+        message: &ForwardedMessage,
+        inspectors: &[Arc<dyn PayloadInspector>],
+        start_time_value: Instant,
+    ) {
+        let federate_id = message.destination();
+        if !written {
+            let mut locked_rti = lock_rti(_f_rti);
+            let idx: usize = federate_id.into();
+            if resumption::suspend_federate(
+                &mut locked_rti,
+                federate_id,
+                "failed to write a message",
+            ) {
+                locked_rti.enclaves()[idx].count_dropped_while_suspended();
+                warn!(
+                    "RTI: Destination federate {} has lost its connection. Dropped message.",
+                    federate_label(federate_id)
+                );
+                return;
+            }
+            if locked_rti.enclaves()[idx].e().state() != FedState::NotConnected {
+                error!(
+                    "RTI failed to write message to federate {}.",
+                    federate_label(federate_id)
                 );
+                // TODO: Implement similarly with rti_lib.c
+                std::process::exit(1);
             }
+            warn!(
+                "RTI: Destination federate {} has been disconnected. Dropped message.",
+                federate_label(federate_id)
+            );
+            return;
+        }
+        for inspector in inspectors {
+            inspector.message_forwarded(message);
         }
 
+        let locked_rti = read_rti(_f_rti);
+        let _grants = locked_rti.lock_grants(federate_id);
         Self::update_federate_next_event_tag_locked(
-            _f_rti,
+            &locked_rti,
             federate_id,
-            intended_tag,
+            message.tag(),
             start_time_value,
-            sent_start_time,
         );
     }
 
+    /**
+     * Wait until the start time has been sent if the given federate has not
+     * been sent it yet, recording the wait under the given name. Must be
+     * called without holding the RTI lock.
+     */
+    fn wait_for_start_time(
+        _f_rti: &Arc<RwLock<FederationRTI>>,
+        fed_id: u16,
+        sent_start_time: &(Mutex<bool>, Condvar),
+        name: &'static str,
+    ) {
+        let pending = read_rti(_f_rti).federates()[usize::from(fed_id)]
+            .e()
+            .state()
+            == FedState::Pending;
+        if !pending {
+            return;
+        }
+        if let Some(waited) = wait_stats::wait_until_set(sent_start_time) {
+            read_rti(_f_rti).wait_stats().record(name, fed_id, waited);
+        }
+    }
+
+    /**
+     * Read and drop a message payload of the given length, one chunk at a time.
+     */
+    fn discard_payload(stream: &mut impl Transport, length: usize) -> Result<(), ReadError> {
+        let mut discard_buffer = vec![0 as u8; length.min(FORWARD_CHUNK_SIZE)];
+        let mut bytes_discarded = 0;
+        while bytes_discarded < length {
+            let chunk_size = (length - bytes_discarded).min(FORWARD_CHUNK_SIZE);
+            NetUtil::read_from_socket(stream, &mut discard_buffer[..chunk_size])?;
+            bytes_discarded += chunk_size;
+        }
+        Ok(())
+    }
+
     fn update_federate_next_event_tag_locked(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        locked_rti: &FederationRTI,
         fed_id: u16,
         mut next_event_tag: Tag,
         start_time: Instant,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
     ) {
-        let min_in_transit_tag;
-        {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            let idx: usize = fed_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            min_in_transit_tag = MessageRecord::get_minimum_in_transit_message_tag(
-                fed.in_transit_message_tags(),
-                start_time,
-            );
-        }
-        if Tag::lf_tag_compare(&min_in_transit_tag, &next_event_tag) < 0 {
+        let idx: usize = fed_id.into();
+        let fed: &Federate = &locked_rti.federates()[idx];
+        let min_in_transit_tag = MessageRecord::get_minimum_in_transit_message_tag(
+            &mut fed.in_transit_message_tags(),
+            start_time,
+        );
+        if min_in_transit_tag < next_event_tag {
             next_event_tag = min_in_transit_tag.clone();
         }
         Enclave::update_enclave_next_event_tag_locked(
-            _f_rti,
+            locked_rti,
             fed_id,
             next_event_tag,
             start_time,
-            sent_start_time,
         );
     }
 
     fn handle_next_event_tag(
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
-    ) {
-        let mut header_buffer = vec![0 as u8; mem::size_of::<i64>() + mem::size_of::<u32>()];
-        NetUtil::read_from_stream_errexit(
-            stream,
-            &mut header_buffer,
-            fed_id,
-            "the content of the next event tag",
-        );
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> Result<(), ReadError> {
+        let mut intended_tag = NetUtil::read_from_socket_tag(stream)?;
 
         // Acquire a mutex lock to ensure that this state does not change while a
         // message is in transport or being used to determine a TAG.
         let enclave_id;
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = read_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(
+                TraceEvent::ReceiveNet,
+                fed_id,
+                Some(&intended_tag),
+            );
             let idx: usize = fed_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            enclave_id = fed.enclave().id();
+            enclave_id = locked_rti.federates()[idx].e().id();
         }
-        let intended_tag = NetUtil::extract_tag(
-            header_buffer[0..(mem::size_of::<i64>() + mem::size_of::<u32>())]
-                .try_into()
-                .unwrap(),
-        );
         let start_time_value;
         {
             let locked_start_time = start_time.lock().unwrap();
             start_time_value = locked_start_time.start_time();
         }
-        println!(
-            "RTI received from federate {} the Next Event Tag (NET) ({},{})",
+        debug_for!(
             enclave_id,
-            intended_tag.time() - start_time_value,
+            "RTI received from federate {} the Next Event Tag (NET) ({},{})",
+            federate_label(enclave_id),
+            tag::format_time(intended_tag.time(), start_time_value),
             intended_tag.microstep()
         );
+        if intended_tag.time() < start_time_value {
+            let early_net_policy;
+            {
+                let locked_rti = read_rti(&_f_rti);
+                early_net_policy = locked_rti.early_net_policy();
+            }
+            warn!(
+                "RTI: WARNING: Federate {} sent the NET {}, which is earlier than the start time.",
+                federate_label(fed_id),
+                Enclave::relative_tag(&intended_tag, start_time_value)
+            );
+            match early_net_policy {
+                EarlyNetPolicy::Clamp => {
+                    read_rti(&_f_rti).tolerate(&format!(
+                        "NET {} of federate {} earlier than the start time",
+                        Enclave::relative_tag(&intended_tag, start_time_value),
+                        federate_label(fed_id)
+                    ));
+                    info!(
+                        "RTI: Using the start tag as the NET of federate {}.",
+                        federate_label(fed_id)
+                    );
+                    intended_tag = Tag::new(start_time_value, 0);
+                }
+                EarlyNetPolicy::Reject => {
+                    warn!("RTI: Rejecting federate {}.", federate_label(fed_id));
+                    Self::send_reject(stream, ErrType::ProtocolViolation.to_byte());
+                    Self::handle_federate_failed(
+                        fed_id,
+                        format!(
+                            "NET {} earlier than the start time",
+                            Enclave::relative_tag(&intended_tag, start_time_value)
+                        ),
+                        _f_rti,
+                        start_time,
+                        stop_granted,
+                    );
+                    return Ok(());
+                }
+            }
+        }
+        let violation;
+        {
+            let locked_rti = read_rti(&_f_rti);
+            let time_resolution = locked_rti.time_resolution();
+            let idx: usize = fed_id.into();
+            let fed: &Federate = &locked_rti.federates()[idx];
+            violation = Self::check_next_event_tag(
+                fed.e(),
+                &intended_tag,
+                start_time_value,
+                time_resolution,
+            );
+        }
+        if let Some(violation) = violation {
+            Self::handle_protocol_violation(
+                fed_id,
+                violation,
+                stream,
+                _f_rti,
+                start_time,
+                stop_granted,
+            );
+            return Ok(());
+        }
+        // Share the RTI lock with the threads of the other federates, and
+        // decide the grants of its component one at a time under its grant lock.
+        let locked_rti = read_rti(&_f_rti);
+        let _grants = locked_rti.lock_grants(fed_id);
+        locked_rti.record_event(
+            "net",
+            Some(fed_id),
+            Some((&intended_tag, start_time_value)),
+            None,
+        );
+        let fed = &locked_rti.federates()[usize::from(fed_id)];
+        fed.set_net_received(true);
+        if fed.presumed_idle() {
+            fed.set_presumed_idle(false);
+            warn!(
+                "RTI: WARNING: Federate {} sent its first NET {} after it was presumed to have a NET of FOREVER. Grants to the federates downstream of it may have been premature.",
+                federate_label(fed_id),
+                Enclave::relative_tag(&intended_tag, start_time_value)
+            );
+        }
+        if !locked_rti.admit_control_message(fed_id, MsgType::NextEventTag, &intended_tag) {
+            return Ok(());
+        }
         Self::update_federate_next_event_tag_locked(
-            _f_rti,
+            &locked_rti,
             fed_id,
             intended_tag,
             start_time_value,
-            sent_start_time,
         );
+        Ok(())
     }
 
     fn handle_logical_tag_complete(
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
-        sent_start_time: Arc<(Mutex<bool>, Condvar)>,
-    ) {
-        let mut header_buffer = vec![0 as u8; mem::size_of::<i64>() + mem::size_of::<u32>()];
-        NetUtil::read_from_stream_errexit(
-            stream,
-            &mut header_buffer,
-            fed_id,
-            "the content of the logical tag complete",
-        );
-        let completed = NetUtil::extract_tag(
-            header_buffer[0..(mem::size_of::<i64>() + mem::size_of::<u32>())]
-                .try_into()
-                .unwrap(),
-        );
-        let number_of_enclaves;
-        {
-            let locked_rti = _f_rti.lock().unwrap();
-            number_of_enclaves = locked_rti.number_of_enclaves();
-        }
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> Result<(), ReadError> {
+        let completed = NetUtil::read_from_socket_tag(stream)?;
         let start_time_value;
         {
             let locked_start_time = start_time.lock().unwrap();
             start_time_value = locked_start_time.start_time();
         }
+        let violation;
+        {
+            let locked_rti = read_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(
+                TraceEvent::ReceiveLtc,
+                fed_id,
+                Some(&completed),
+            );
+            let time_resolution = locked_rti.time_resolution();
+            let idx: usize = fed_id.into();
+            let fed: &Federate = &locked_rti.federates()[idx];
+            violation = Self::check_logical_tag_complete(
+                fed.e(),
+                &completed,
+                start_time_value,
+                time_resolution,
+            );
+        }
+        if let Some(violation) = violation {
+            Self::handle_protocol_violation(
+                fed_id,
+                violation,
+                stream,
+                _f_rti,
+                start_time,
+                stop_granted,
+            );
+            return Ok(());
+        }
+        let locked_rti = read_rti(&_f_rti);
+        let grants = locked_rti.lock_grants(fed_id);
+        locked_rti.record_event(
+            "ltc",
+            Some(fed_id),
+            Some((&completed, start_time_value)),
+            None,
+        );
+        if !locked_rti.admit_control_message(fed_id, MsgType::LogicalTagComplete, &completed) {
+            return Ok(());
+        }
+        let window_advanced =
+            Self::process_logical_tag_complete(&locked_rti, fed_id, completed, start_time_value);
+        drop(grants);
+        if window_advanced {
+            Enclave::grant_all(&locked_rti, start_time_value);
+        }
+        Ok(())
+    }
+
+    /**
+     * Handle the LTC of the federate under the grant lock of its component.
+     * Return true if it advanced the lookahead window, which holds back the
+     * grants of all components, so that the caller sends every federate the
+     * grant that it now allows once it has released the grant lock.
+     */
+    fn process_logical_tag_complete(
+        locked_rti: &FederationRTI,
+        fed_id: u16,
+        completed: Tag,
+        start_time_value: Instant,
+    ) -> bool {
+        let number_of_enclaves = locked_rti.number_of_enclaves();
+        let window_start = locked_rti
+            .has_lookahead()
+            .then(|| locked_rti.lookahead_window_start(start_time_value))
+            .flatten();
         Enclave::logical_tag_complete(
-            _f_rti.clone(),
+            locked_rti,
             fed_id,
             number_of_enclaves,
             start_time_value,
-            sent_start_time,
             completed.clone(),
         );
+        // The grants held back by the lookahead windows may be sent once the
+        // earliest LTC advances.
+        let window_advanced = locked_rti.has_lookahead()
+            && locked_rti.lookahead_window_start(start_time_value) > window_start;
 
         // See if we can remove any of the recorded in-transit messages for this.
+        let idx: usize = fed_id.into();
+        let fed: &Federate = &locked_rti.federates()[idx];
+        MessageRecord::clean_in_transit_message_record_up_to_tag(
+            &mut fed.in_transit_message_tags(),
+            completed,
+            start_time_value,
+        );
+        window_advanced
+    }
+
+    /**
+     * Handle the NET and LTC messages of the federate that have been held back
+     * because it exceeded the rate of control messages, if the rate now allows
+     * it. Only the thread serving the federate calls this, so that the held
+     * back messages are handled before the later messages of the federate.
+     */
+    fn process_pending_control_messages(
+        fed_id: u16,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+    ) {
+        let pending;
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = read_rti(&_f_rti);
             let idx: usize = fed_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            let in_transit_message_tags = fed.in_transit_message_tags();
-            MessageRecord::clean_in_transit_message_record_up_to_tag(
-                in_transit_message_tags,
-                completed,
-                start_time_value,
-            );
+            pending = match locked_rti.federates()[idx].control_rate_limiter().as_mut() {
+                Some(limiter) => limiter.take_pending(),
+                None => return,
+            };
+        }
+        if pending.is_empty() {
+            return;
+        }
+        let start_time_value;
+        {
+            let locked_start_time = start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let locked_rti = read_rti(&_f_rti);
+        let grants = locked_rti.lock_grants(fed_id);
+        let mut window_advanced = false;
+        for (msg_type, tag) in pending {
+            if matches!(msg_type, MsgType::NextEventTag) {
+                Self::update_federate_next_event_tag_locked(
+                    &locked_rti,
+                    fed_id,
+                    tag,
+                    start_time_value,
+                );
+            } else {
+                window_advanced |=
+                    Self::process_logical_tag_complete(&locked_rti, fed_id, tag, start_time_value);
+            }
+        }
+        drop(grants);
+        if window_advanced {
+            Enclave::grant_all(&locked_rti, start_time_value);
         }
     }
 
-    fn handle_stop_request_message(
+    /**
+     * Check the tag of a NET message against the state recorded for the
+     * federate. Return a description of the violation if the tag is earlier
+     * than the tag the federate has already completed, or if it is not a
+     * multiple of the time resolution. NETs earlier than the start time are
+     * handled according to the EarlyNetPolicy before this check.
+     */
+    fn check_next_event_tag(
+        e: &Enclave,
+        tag: &Tag,
+        start_time: Instant,
+        time_resolution: TimeResolution,
+    ) -> Option<String> {
+        if let Some(violation) = Self::check_resolution("NET", tag, start_time, time_resolution) {
+            return Some(violation);
+        }
+        if *tag < e.completed() {
+            return Some(format!(
+                "NET {} is earlier than the completed tag {}",
+                Enclave::relative_tag(tag, start_time),
+                Enclave::relative_tag(&e.completed(), start_time)
+            ));
+        }
+        None
+    }
+
+    /**
+     * Check the tag of an LTC message against the state recorded for the
+     * federate. Return a description of the violation if the tag is earlier
+     * than the start time or than the previously completed tag, if it is not
+     * a multiple of the time resolution, or if a federate with upstream
+     * federates completes a tag it was not granted.
+     */
+    fn check_logical_tag_complete(
+        e: &Enclave,
+        tag: &Tag,
+        start_time: Instant,
+        time_resolution: TimeResolution,
+    ) -> Option<String> {
+        if tag.time() < start_time {
+            return Some(format!(
+                "LTC {} is earlier than the start time",
+                Enclave::relative_tag(tag, start_time)
+            ));
+        }
+        if let Some(violation) = Self::check_resolution("LTC", tag, start_time, time_resolution) {
+            return Some(violation);
+        }
+        if *tag < e.completed() {
+            return Some(format!(
+                "LTC {} is earlier than the previously completed tag {}",
+                Enclave::relative_tag(tag, start_time),
+                Enclave::relative_tag(&e.completed(), start_time)
+            ));
+        }
+        // Federates without upstream federates and federates using decentralized
+        // coordination do not wait for grants.
+        if e.num_upstream() > 0 && !e.decentralized() {
+            let mut granted = e.last_granted();
+            if e.last_provisionally_granted() > granted {
+                granted = e.last_provisionally_granted();
+            }
+            if *tag > granted {
+                return Some(format!(
+                    "LTC {} is beyond the granted tag {}",
+                    Enclave::relative_tag(tag, start_time),
+                    Enclave::relative_tag(&granted, start_time)
+                ));
+            }
+        }
+        None
+    }
+
+    /**
+     * Return a description of the violation if the time of the tag, relative
+     * to the start time, is not a multiple of the time resolution. FOREVER is
+     * always allowed.
+     */
+    fn check_resolution(
+        message: &str,
+        tag: &Tag,
+        start_time: Instant,
+        time_resolution: TimeResolution,
+    ) -> Option<String> {
+        if tag.time() == tag::FOREVER {
+            return None;
+        }
+        let Ok(relative) = tag.checked_sub(start_time) else {
+            return Some(format!(
+                "{} {} is out of range",
+                message,
+                tag.elapsed(start_time)
+            ));
+        };
+        if time_resolution.is_aligned(relative.time()) {
+            return None;
+        }
+        Some(format!(
+            "{} ({} ns,{}) is not a multiple of the time resolution of 1 {}",
+            message,
+            relative.time(),
+            relative.microstep(),
+            time_resolution.unit()
+        ))
+    }
+
+    /**
+     * Report a protocol violation by the given federate. The offending message
+     * is ignored so that it does not corrupt the scheduling state. If
+     * quarantine is enabled, the federate is also rejected with
+     * ErrType::ProtocolViolation and handled as a failed federate.
+     */
+    fn handle_protocol_violation(
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        violation: String,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
         stop_granted: Arc<Mutex<StopGranted>>,
     ) {
-        println!("RTI handling stop_request from federate {}.", fed_id);
-
-        let mut header_buffer = vec![0 as u8; MSG_TYPE_STOP_REQUEST_LENGTH - 1];
-        NetUtil::read_from_stream_errexit(
-            stream,
-            &mut header_buffer,
+        error!(
+            "RTI: Protocol violation by federate {}: {}. Ignoring the message.",
+            federate_label(fed_id),
+            violation
+        );
+        let quarantine_enabled;
+        {
+            let locked_rti = lock_rti(&_f_rti);
+            quarantine_enabled = locked_rti.quarantine_enabled();
+            locked_rti.tolerate(&format!(
+                "protocol violation by federate {}: {}",
+                federate_label(fed_id),
+                violation
+            ));
+        }
+        if !quarantine_enabled {
+            return;
+        }
+        info!("RTI: Disconnecting federate {}.", federate_label(fed_id));
+        Self::send_reject(stream, ErrType::ProtocolViolation.to_byte());
+        Self::handle_federate_failed(
             fed_id,
-            "the MsgType::StopRequest payload",
+            format!("protocol violation: {}", violation),
+            _f_rti,
+            start_time,
+            stop_granted,
+        );
+    }
+
+    fn handle_stop_request_message(
+        fed_id: u16,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<tag::StartTime>>,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> Result<(), ReadError> {
+        info!(
+            "RTI handling stop_request from federate {}.",
+            federate_label(fed_id)
         );
 
+        let proposed_stop_tag = NetUtil::read_from_socket_tag(stream)?;
+
         // Acquire a mutex lock to ensure that this state does change while a
         // message is in transport or being used to determine a TAG.
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(
+                TraceEvent::ReceiveStopReq,
+                fed_id,
+                Some(&proposed_stop_tag),
+            );
             let idx: usize = fed_id.into();
             let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
 
@@ -1190,48 +4323,94 @@ impl Server {
             // from this federate
             if fed.requested_stop() {
                 // Ignore this request
-                return;
+                return Ok(());
             }
         }
 
-        // Extract the proposed stop tag for the federate
-        let proposed_stop_tag = NetUtil::extract_tag(
-            header_buffer[0..(mem::size_of::<i64>() + mem::size_of::<u32>())]
-                .try_into()
-                .unwrap(),
-        );
-
         // Update the maximum stop tag received from federates
         let start_time_value;
         {
             let locked_start_time = start_time.lock().unwrap();
             start_time_value = locked_start_time.start_time();
         }
+        info!(
+            "RTI received from federate {} a MsgType::StopRequest message with tag ({},{}).",
+            federate_label(fed_id),
+            tag::format_time(proposed_stop_tag.time(), start_time_value),
+            proposed_stop_tag.microstep()
+        );
+
+        Self::handle_stop_request(
+            Some(fed_id),
+            proposed_stop_tag,
+            _f_rti,
+            start_time_value,
+            stop_granted,
+        );
+        Ok(())
+    }
+
+    /**
+     * Stop admitting transient federates and request all federates to stop at
+     * the given tag on behalf of the RTI. The federates past the tag stop as
+     * soon as they can instead. Either way, they complete the messages and
+     * grants up to the stop tag on which they agree. Return false if the stop
+     * protocol is in progress already.
+     */
+    pub(crate) fn drain(
+        drain_tag: Tag,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time_value: Instant,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) -> bool {
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
-            if Tag::lf_tag_compare(&proposed_stop_tag, &locked_rti.max_stop_tag()) > 0 {
-                locked_rti.set_max_stop_tag(proposed_stop_tag.clone());
+            let mut locked_rti = lock_rti(&_f_rti);
+            if locked_rti.stop_in_progress() {
+                return false;
             }
+            locked_rti.set_draining(true);
+            locked_rti.record_event("drain", None, Some((&drain_tag, start_time_value)), None);
         }
-
-        println!(
-            "RTI received from federate {} a MsgType::StopRequest message with tag ({},{}).",
-            fed_id,
-            proposed_stop_tag.time() - start_time_value,
-            proposed_stop_tag.microstep()
+        info!(
+            "RTI: Draining the federation until {}. No transient federate is admitted anymore.",
+            Enclave::relative_tag(&drain_tag, start_time_value)
         );
+        Self::handle_stop_request(None, drain_tag, _f_rti, start_time_value, stop_granted);
+        true
+    }
+
+    /**
+     * Handle a stop request proposing the given stop tag on behalf of the
+     * given federate, or of the RTI if None, forwarding it to all other
+     * federates that have not requested to stop yet.
+     */
+    fn handle_stop_request(
+        requester: Option<u16>,
+        proposed_stop_tag: Tag,
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time_value: Instant,
+        stop_granted: Arc<Mutex<StopGranted>>,
+    ) {
+        {
+            let mut locked_rti = lock_rti(&_f_rti);
+            if proposed_stop_tag > locked_rti.max_stop_tag() {
+                locked_rti.set_max_stop_tag(proposed_stop_tag.clone());
+            }
+        }
 
         // If this federate has not already asked
         // for a stop, add it to the tally.
-        Self::mark_federate_requesting_stop(
-            fed_id,
-            _f_rti.clone(),
-            stop_granted.clone(),
-            start_time_value,
-        );
+        if let Some(fed_id) = requester {
+            Self::mark_federate_requesting_stop(
+                fed_id,
+                _f_rti.clone(),
+                stop_granted.clone(),
+                start_time_value,
+            );
+        }
 
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
             if locked_rti.num_enclaves_handling_stop() == locked_rti.number_of_enclaves() {
                 // We now have information about the stop time of all
                 // federates. This is extremely unlikely, but it can occur
@@ -1243,7 +4422,7 @@ impl Server {
         // also issued a stop request.
         let mut stop_request_buffer = vec![0 as u8; MSG_TYPE_STOP_REQUEST_LENGTH];
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
             Self::encode_stop_request(
                 &mut stop_request_buffer,
                 locked_rti.max_stop_tag().time(),
@@ -1254,7 +4433,7 @@ impl Server {
         // Iterate over federates and send each the MSG_TYPE_StopRequest message
         // if we do not have a stop_time already for them. Do not do this more than once.
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             if locked_rti.stop_in_progress() {
                 return;
             }
@@ -1262,38 +4441,59 @@ impl Server {
         }
         let number_of_enclaves;
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
             number_of_enclaves = locked_rti.number_of_enclaves();
         }
         for i in 0..number_of_enclaves {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             // FIXME: Handle usize properly.
             let f: &mut Federate = &mut locked_rti.enclaves()[i as usize];
-            if f.e().id() != fed_id && f.requested_stop() == false {
-                if f.e().state() == FedState::NotConnected {
+            if Some(f.e().id()) != requester && f.requested_stop() == false {
+                // A suspended federate can no longer resume its session once
+                // the stop protocol is in progress.
+                if f.e().absent() || f.suspended() {
+                    let f_id = f.e().id();
+                    drop(locked_rti);
                     Self::mark_federate_requesting_stop(
-                        f.e().id(),
+                        f_id,
                         _f_rti.clone(),
                         stop_granted.clone(),
                         start_time_value,
                     );
                     continue;
                 }
+                let f_id = f.e().id();
+                let max_stop_tag = locked_rti.max_stop_tag();
+                let fault = locked_rti.inject_fault(
+                    MsgType::StopRequest,
+                    f_id,
+                    &max_stop_tag,
+                    start_time_value,
+                );
+                let f: &Federate = &locked_rti.enclaves()[i as usize];
                 // FIXME: Handle unwrap properly.
-                let stream = f.stream().as_ref().unwrap();
-                NetUtil::write_to_stream_errexit(
-                    stream,
-                    &stop_request_buffer,
-                    f.e().id(),
+                let reserved = ReservedWrite::reserve(f).unwrap();
+                // Write without holding the RTI lock, as a message relayed to
+                // the federate may be holding its outgoing lock.
+                drop(locked_rti);
+                if reserved.write(
+                    stop_request_buffer.clone(),
+                    fault,
                     "MsgType::StopRequest message",
-                );
+                ) {
+                    lock_rti(&_f_rti).tracepoint_rti_to_federate(
+                        TraceEvent::SendStopReq,
+                        f_id,
+                        Some(&max_stop_tag),
+                    );
+                }
             }
         }
         {
-            let locked_rti = _f_rti.lock().unwrap();
-            println!(
+            let locked_rti = lock_rti(&_f_rti);
+            info!(
                 "RTI forwarded to federates MsgType::StopRequest with tag ({}, {}).",
-                locked_rti.max_stop_tag().time() - start_time_value,
+                tag::format_time(locked_rti.max_stop_tag().time(), start_time_value),
                 locked_rti.max_stop_tag().microstep()
             );
         }
@@ -1301,17 +4501,17 @@ impl Server {
 
     fn mark_federate_requesting_stop(
         fed_id: u16,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         stop_granted: Arc<Mutex<StopGranted>>,
         start_time_value: Instant,
     ) {
         let mut num_enclaves_handling_stop;
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
             num_enclaves_handling_stop = locked_rti.num_enclaves_handling_stop();
         }
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             let idx: usize = fed_id.into();
             let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
             if !fed.requested_stop() {
@@ -1321,7 +4521,7 @@ impl Server {
             }
         }
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let mut locked_rti = lock_rti(&_f_rti);
             let idx: usize = fed_id.into();
             let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
             if !fed.requested_stop() {
@@ -1332,7 +4532,7 @@ impl Server {
         }
         let number_of_enclaves;
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
             num_enclaves_handling_stop = locked_rti.num_enclaves_handling_stop();
             number_of_enclaves = locked_rti.number_of_enclaves();
         }
@@ -1356,7 +4556,7 @@ impl Server {
      * This function assumes the caller holds the _RTI.rti_mutex lock.
      */
     fn _lf_rti_broadcast_stop_time_to_federates_locked(
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         stop_granted: Arc<Mutex<StopGranted>>,
         start_time_value: Instant,
     ) {
@@ -1369,7 +4569,7 @@ impl Server {
         // Reply with a stop granted to all federates
         let mut outgoing_buffer = vec![0 as u8; MSG_TYPE_STOP_GRANTED_LENGTH];
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
             Self::encode_stop_granted(
                 &mut outgoing_buffer,
                 locked_rti.max_stop_tag().time(),
@@ -1379,7 +4579,7 @@ impl Server {
 
         let number_of_enclaves;
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
             number_of_enclaves = locked_rti.number_of_enclaves();
         }
         // Iterate over federates and send each the message.
@@ -1387,46 +4587,71 @@ impl Server {
             let next_event;
             let max_stop_tag;
             {
-                let mut locked_rti = _f_rti.lock().unwrap();
+                let mut locked_rti = lock_rti(&_f_rti);
                 max_stop_tag = locked_rti.max_stop_tag();
                 // FIXME: Handle usize properly.
                 let fed: &Federate = &locked_rti.enclaves()[i as usize];
                 next_event = fed.e().next_event();
-                if fed.e().state() == FedState::NotConnected {
+                if fed.e().absent() {
                     continue;
                 }
             }
             {
-                let mut locked_rti = _f_rti.lock().unwrap();
+                let mut locked_rti = lock_rti(&_f_rti);
                 // FIXME: Handle usize properly.
                 let fed: &mut Federate = &mut locked_rti.enclaves()[i as usize];
-                if Tag::lf_tag_compare(&next_event, &max_stop_tag) >= 0 {
+                if next_event >= max_stop_tag {
                     // Need the next_event to be no greater than the stop tag.
                     fed.enclave().set_next_event(max_stop_tag);
                 }
             }
             {
-                let mut locked_rti = _f_rti.lock().unwrap();
+                let mut locked_rti = lock_rti(&_f_rti);
+                let max_stop_tag = locked_rti.max_stop_tag();
+                let fault = locked_rti.inject_fault(
+                    MsgType::StopGranted,
+                    i as u16,
+                    &max_stop_tag,
+                    start_time_value,
+                );
                 // FIXME: Handle usize properly.
                 let fed: &mut Federate = &mut locked_rti.enclaves()[i as usize];
+                if fed.suspended() {
+                    continue;
+                }
                 // FIXME: Handle unwrap properly.
-                let stream = fed.stream().as_ref().unwrap();
-                NetUtil::write_to_stream_errexit(
-                    stream,
-                    &outgoing_buffer,
-                    fed.e().id(),
+                let reserved = ReservedWrite::reserve(fed).unwrap();
+                // Write without holding the RTI lock, as a message relayed to
+                // the federate may be holding its outgoing lock.
+                drop(locked_rti);
+                if reserved.write(
+                    outgoing_buffer.clone(),
+                    fault,
                     "MsgType::StopGranted message",
-                );
+                ) {
+                    lock_rti(&_f_rti).tracepoint_rti_to_federate(
+                        TraceEvent::SendStopGrn,
+                        i as u16,
+                        Some(&max_stop_tag),
+                    );
+                }
             }
         }
 
         {
-            let locked_rti = _f_rti.lock().unwrap();
-            println!(
+            let locked_rti = read_rti(&_f_rti);
+            info!(
                 "RTI sent to federates MsgType::StopGranted with tag ({}, {}).",
-                locked_rti.max_stop_tag().time() - start_time_value,
+                tag::format_time(locked_rti.max_stop_tag().time(), start_time_value),
                 locked_rti.max_stop_tag().microstep()
             );
+            let max_stop_tag = locked_rti.max_stop_tag();
+            locked_rti.record_event(
+                "stop_granted",
+                None,
+                Some((&max_stop_tag, start_time_value)),
+                None,
+            );
         }
         {
             let mut _stop_granted = stop_granted.lock().unwrap();
@@ -1458,34 +4683,24 @@ impl Server {
 
     fn handle_stop_request_reply(
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
         stop_granted: Arc<Mutex<StopGranted>>,
-    ) {
+    ) -> Result<(), ReadError> {
         let mut header_buffer = vec![0 as u8; MSG_TYPE_STOP_REQUEST_REPLY_LENGTH - 1];
-        NetUtil::read_from_stream_errexit(
-            stream,
-            &mut header_buffer,
-            fed_id,
-            "the reply to MsgType::StopRequest message",
-        );
-
-        let federate_stop_tag = NetUtil::extract_tag(
-            header_buffer[0..(MSG_TYPE_STOP_REQUEST_REPLY_LENGTH - 1)]
-                .try_into()
-                .unwrap(),
-        );
+        NetUtil::read_from_socket(stream, &mut header_buffer)?;
+        let federate_stop_tag = NetUtil::extract_tag(&header_buffer);
 
         let start_time_value;
         {
             let locked_start_time = start_time.lock().unwrap();
             start_time_value = locked_start_time.start_time();
         }
-        println!(
+        info!(
             "RTI received from federate {} STOP reply tag ({}, {}).",
-            fed_id,
-            federate_stop_tag.time() - start_time_value,
+            federate_label(fed_id),
+            tag::format_time(federate_stop_tag.time(), start_time_value),
             federate_stop_tag.microstep()
         );
 
@@ -1493,11 +4708,16 @@ impl Server {
         // If the federate has not requested stop before, count the reply
         let max_stop_tag;
         {
-            let locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = lock_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(
+                TraceEvent::ReceiveStopReqRep,
+                fed_id,
+                Some(&federate_stop_tag),
+            );
             max_stop_tag = locked_rti.max_stop_tag();
         }
-        if Tag::lf_tag_compare(&federate_stop_tag, &max_stop_tag) > 0 {
-            let mut locked_rti = _f_rti.lock().unwrap();
+        if federate_stop_tag > max_stop_tag {
+            let mut locked_rti = lock_rti(&_f_rti);
             locked_rti.set_max_stop_tag(federate_stop_tag);
         }
         Self::mark_federate_requesting_stop(
@@ -1506,26 +4726,22 @@ impl Server {
             stop_granted.clone(),
             start_time_value,
         );
+        Ok(())
     }
 
     fn handle_port_absent_message(
         buffer: &Vec<u8>,
         fed_id: u16,
-        stream: &mut TcpStream,
-        _f_rti: Arc<Mutex<FederationRTI>>,
+        stream: &mut impl Transport,
+        _f_rti: Arc<RwLock<FederationRTI>>,
         start_time: Arc<Mutex<tag::StartTime>>,
         sent_start_time: Arc<(Mutex<bool>, Condvar)>,
-    ) {
+    ) -> Result<(), ReadError> {
         let message_size =
             mem::size_of::<u16>() * 2 + mem::size_of::<i64>() + mem::size_of::<u32>();
 
         let mut header_buffer = vec![0 as u8; message_size];
-        NetUtil::read_from_stream_errexit(
-            stream,
-            &mut header_buffer,
-            fed_id,
-            "port absent message",
-        );
+        NetUtil::read_from_socket(stream, &mut header_buffer)?;
 
         let u16_size = mem::size_of::<u16>();
         // FIXME: Change from_le_bytes properly.
@@ -1536,79 +4752,95 @@ impl Server {
                 .unwrap(),
         );
 
-        // TODO: Will be used when tracing_enabled
-        // let start_idx = u16_size * 2;
-        // let tag = NetUtil::extract_tag(
-        //     header_buffer[start_idx..(start_idx + mem::size_of::<i64>() + mem::size_of::<u32>())]
-        //         .try_into()
-        //         .unwrap(),
-        // );
+        let start_idx = u16_size * 2;
+        let tag = NetUtil::extract_tag(
+            header_buffer[start_idx..(start_idx + mem::size_of::<i64>() + mem::size_of::<u32>())]
+                .try_into()
+                .unwrap(),
+        );
 
         let start_time_value;
         {
             let locked_start_time = start_time.lock().unwrap();
             start_time_value = locked_start_time.start_time();
         }
-        // Need to acquire the mutex lock to ensure that the thread handling
-        // messages coming from the socket connected to the destination does not
-        // issue a TAG before this message has been forwarded.
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            let locked_rti = read_rti(&_f_rti);
+            locked_rti.tracepoint_rti_from_federate(TraceEvent::ReceivePortAbs, fed_id, Some(&tag));
 
             // If the destination federate is no longer connected, issue a warning
             // and return.
             let idx: usize = federate_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            let enclave = fed.enclave();
-            if enclave.state() == FedState::NotConnected {
-                println!(
+            let enclave = locked_rti.federates()[idx].e();
+            if enclave.absent() {
+                warn!(
                     "RTI: Destination federate {} is no longer connected. Dropping message.",
-                    federate_id
+                    federate_label(federate_id)
                 );
-                println!("Fed status: next_event ({}, {}), completed ({}, {}), last_granted ({}, {}), last_provisionally_granted ({}, {}).",
-                        enclave.next_event().time() - start_time_value,
+                debug!("Fed status: next_event ({}, {}), completed ({}, {}), last_granted ({}, {}), last_provisionally_granted ({}, {}).",
+                        tag::format_time(enclave.next_event().time(), start_time_value),
                         enclave.next_event().microstep(),
-                        enclave.completed().time() - start_time_value,
+                        tag::format_time(enclave.completed().time(), start_time_value),
                         enclave.completed().microstep(),
-                        enclave.last_granted().time() - start_time_value,
+                        tag::format_time(enclave.last_granted().time(), start_time_value),
                         enclave.last_granted().microstep(),
-                        enclave.last_provisionally_granted().time() - start_time_value,
+                        tag::format_time(enclave.last_provisionally_granted().time(), start_time_value),
                         enclave.last_provisionally_granted().microstep()
                 );
-                return;
+                return Ok(());
+            }
+            if enclave.transient() && tag < enclave.start_tag() {
+                warn!(
+                    "RTI: Destination federate {} started at {}, after the tag of the port absent message. Dropping message.",
+                    federate_label(federate_id),
+                    Enclave::relative_tag(&enclave.start_tag(), start_time_value)
+                );
+                return Ok(());
             }
         }
-        println!(
+        debug_for!(
+            federate_id,
             "RTI forwarding port absent message for port {} to federate {}.",
-            reactor_port_id, federate_id
+            reactor_port_id,
+            federate_label(federate_id)
         );
 
         // Need to make sure that the destination federate's thread has already
-        // sent the starting MsgType::Timestamp message.
+        // sent the starting MsgType::Timestamp message. See handle_timed_message.
+        Self::wait_for_start_time(
+            &_f_rti,
+            federate_id,
+            &sent_start_time,
+            wait_stats::START_TIME_WAIT_PORT_ABSENT,
+        );
+        let reserved;
         {
-            let mut locked_rti = _f_rti.lock().unwrap();
+            // Hold the grant lock to ensure that the destination is not issued a
+            // TAG before this message has been forwarded.
+            let locked_rti = read_rti(&_f_rti);
+            let _grants = locked_rti.lock_grants(federate_id);
             let idx: usize = federate_id.into();
-            let fed: &mut Federate = &mut locked_rti.enclaves()[idx];
-            while fed.enclave().state() == FedState::Pending {
-                // Need to wait here.
-                let (lock, condvar) = &*sent_start_time;
-                let mut notified = lock.lock().unwrap();
-                while !*notified {
-                    notified = condvar.wait(notified).unwrap();
-                }
+            let fed: &Federate = &locked_rti.federates()[idx];
+            if fed.e().absent() {
+                // The destination has resigned or has been disconnected since
+                // the check above.
+                return Ok(());
             }
-
-            // Forward the message.
-            let destination_stream = fed.stream().as_ref().unwrap();
-            let mut result_buffer = vec![0 as u8];
-            result_buffer[0] = buffer[0];
-            result_buffer = vec![result_buffer.clone(), header_buffer].concat();
-            NetUtil::write_to_stream_errexit(
-                destination_stream,
-                &result_buffer,
-                federate_id,
-                "message",
-            );
+            if fed.suspended() {
+                fed.count_dropped_while_suspended();
+                return Ok(());
+            }
+            fed.count_relayed_message();
+            // FIXME: Handle unwrap properly.
+            reserved = ReservedWrite::reserve(fed).unwrap();
+            locked_rti.tracepoint_rti_to_federate(TraceEvent::SendPortAbs, federate_id, Some(&tag));
         }
+
+        // Forward the message without holding the RTI lock, as a message
+        // relayed to the destination may be holding its outgoing lock.
+        let mut result_buffer = vec![buffer[0]];
+        result_buffer.extend_from_slice(&header_buffer);
+        reserved.write(result_buffer, None, "message");
+        Ok(())
     }
 }
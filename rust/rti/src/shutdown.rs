@@ -0,0 +1,154 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Shutdown of the RTI before the federates have exited, e.g., when the
+ * operator presses Ctrl-C. The connected federates are sent a
+ * MsgType::Failed message and their connections are closed, so that they do
+ * not wait forever for the RTI, and the RTI returns from wait_for_federates().
+ */
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Default)]
+struct ShutdownState {
+    reason: Option<String>,
+    finished: bool,
+    callbacks: Vec<Box<dyn FnOnce() + Send>>,
+}
+
+/**
+ * Handle through which a shutdown of the RTI is requested, e.g., by a signal
+ * handler or by an application that embeds the RTI.
+ */
+#[derive(Clone, Default)]
+pub struct ShutdownHandle {
+    state: Arc<(Mutex<ShutdownState>, Condvar)>,
+}
+
+impl ShutdownHandle {
+    pub fn new() -> ShutdownHandle {
+        ShutdownHandle::default()
+    }
+
+    /**
+     * Request the RTI to shut down for the given reason. Only the first
+     * request has an effect.
+     */
+    pub fn request(&self, reason: &str) {
+        let callbacks = {
+            let (lock, condvar) = &*self.state;
+            let mut state = lock.lock().unwrap();
+            if state.reason.is_some() {
+                return;
+            }
+            state.reason = Some(reason.to_string());
+            condvar.notify_all();
+            std::mem::take(&mut state.callbacks)
+        };
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    pub fn requested(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /**
+     * Return the reason of the shutdown, if it has been requested.
+     */
+    pub fn reason(&self) -> Option<String> {
+        self.state.0.lock().unwrap().reason.clone()
+    }
+
+    /**
+     * Run the given callback when a shutdown is requested, or right away if it
+     * has been requested already.
+     */
+    pub(crate) fn on_request(&self, callback: Box<dyn FnOnce() + Send>) {
+        {
+            let mut state = self.state.0.lock().unwrap();
+            if state.reason.is_none() {
+                state.callbacks.push(callback);
+                return;
+            }
+        }
+        callback();
+    }
+
+    /**
+     * Wait until a shutdown is requested or the RTI has finished, and return
+     * the reason of the shutdown in the former case.
+     */
+    pub(crate) fn wait(&self) -> Option<String> {
+        let (lock, condvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        while state.reason.is_none() && !state.finished {
+            state = condvar.wait(state).unwrap();
+        }
+        state.reason.clone()
+    }
+
+    /**
+     * Record that all federates have exited, so that wait() returns.
+     */
+    pub(crate) fn finish(&self) {
+        let (lock, condvar) = &*self.state;
+        lock.lock().unwrap().finished = true;
+        condvar.notify_all();
+    }
+}
+
+/**
+ * Request a shutdown through the given handle when the process receives
+ * SIGINT or SIGTERM. A second signal exits the process right away, in case
+ * the shutdown itself hangs. The handlers are process-wide, so this is called
+ * by the RTI binary rather than by the library.
+ */
+#[cfg(unix)]
+pub fn install_signal_handlers(handle: ShutdownHandle) -> io::Result<()> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the signal handlers are already installed",
+        ));
+    }
+    let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            INSTALLED.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+    };
+    thread::spawn(move || {
+        let mut received = 0;
+        for signum in signals.forever() {
+            received += 1;
+            if received > 1 {
+                std::process::exit(128 + signum);
+            }
+            let name = if signum == SIGINT {
+                "SIGINT"
+            } else {
+                "SIGTERM"
+            };
+            handle.request(name);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn install_signal_handlers(_handle: ShutdownHandle) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
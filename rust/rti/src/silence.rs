@@ -0,0 +1,101 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Handling of silent federates, enabled with --silent_grace.
+ *
+ * A federate is silent if it has started executing but sent no NET, which is
+ * common when it deadlocks internally before its first tag. Until it sends a
+ * NET, its start tag bounds the grants of the federates downstream of it.
+ * Once it has been silent for the grace period, the RTI presumes that its NET
+ * is FOREVER, or the earliest tag of the messages in transit to it, so that
+ * the others can proceed. A NET that it sends later replaces the presumption.
+ */
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::PhysicalClock;
+use crate::constants::SILENCE_CHECK_PERIOD_MS;
+use crate::enclave::Enclave;
+use crate::federate::federate_label;
+use crate::lock_stats::read_rti;
+use crate::message_record::message_record::MessageRecord;
+use crate::tag::{self, StartTime};
+use crate::trace::warn;
+use crate::FederationRTI;
+
+/**
+ * Spawn a thread that looks for federates that have been silent for longer
+ * than `grace_ms` milliseconds every SILENCE_CHECK_PERIOD_MS milliseconds,
+ * until all federates have exited.
+ */
+pub fn start_silence_thread(
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    start_time: Arc<Mutex<StartTime>>,
+    clock: Arc<PhysicalClock>,
+    grace_ms: u64,
+) -> thread::JoinHandle<()> {
+    let grace_ns = i64::try_from(grace_ms)
+        .ok()
+        .and_then(|grace_ms| grace_ms.checked_mul(1_000_000))
+        .unwrap_or(i64::MAX);
+    thread::spawn(move || loop {
+        clock.sleep(Duration::from_millis(SILENCE_CHECK_PERIOD_MS));
+        let start_time_value;
+        {
+            let locked_start_time = start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let locked_rti = read_rti(&_f_rti);
+        if locked_rti.all_federates_exited() {
+            break;
+        }
+        if start_time_value == tag::NEVER {
+            continue;
+        }
+        let now = clock.now();
+        let expired: Vec<u16> = locked_rti
+            .federates()
+            .iter()
+            .filter(|fed| {
+                fed.silent()
+                    && !fed.presumed_idle()
+                    && fed.e().num_downstream() > 0
+                    && fed
+                        .started_at()
+                        .is_some_and(|started_at| now.saturating_sub(started_at) >= grace_ns)
+            })
+            .map(|fed| fed.e().id())
+            .collect();
+        for fed_id in expired {
+            let _grants = locked_rti.lock_grants(fed_id);
+            let fed = &locked_rti.federates()[usize::from(fed_id)];
+            fed.set_presumed_idle(true);
+            // Messages in transit to the federate are still to be processed.
+            let presumed = MessageRecord::get_minimum_in_transit_message_tag(
+                &mut fed.in_transit_message_tags(),
+                start_time_value,
+            );
+            let reason = format!(
+                "sent no NET within {} ms of starting, so its NET is presumed to be {}",
+                grace_ms,
+                Enclave::relative_tag(&presumed, start_time_value)
+            );
+            warn!(
+                "RTI: WARNING: Federate {} {}.",
+                federate_label(fed_id),
+                reason
+            );
+            locked_rti.record_event("silent", Some(fed_id), None, Some(&reason));
+            Enclave::presume_enclave_next_event_tag_locked(
+                &locked_rti,
+                fed_id,
+                presumed,
+                start_time_value,
+            );
+        }
+    })
+}
@@ -0,0 +1,784 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Snapshots of the state of the federation in JSON, which the status
+ * server and the "snapshot" command of the admin console export and which
+ * tools read back with Snapshot::parse. With --snapshot_file, the RTI also
+ * writes one to a file on SIGUSR1, every --checkpoint_period, and when the
+ * federation ends. With --restore_snapshot, it restarts a federation from
+ * one: the federates get the start time of the snapshot, so that their tags
+ * line up with those before it was taken, and the completed tag (LTC) of
+ * each federate is restored until it reports a later one.
+ *
+ * A snapshot is one JSON object with the following fields:
+ *
+ * - "schema_version": the version of this schema, SNAPSHOT_SCHEMA_VERSION;
+ * - "federation_id": the ID of the federation;
+ * - "start_time": the start time in nanoseconds since the epoch of the clock
+ *   source, or null if it has not been decided;
+ * - "stop_in_progress": whether the stop protocol is in progress;
+ * - "federates": one object per federate, in the order of their IDs, with its
+ *   "id" and "name" (null if not presented), its "state" ("not_connected",
+ *   "granted", or "pending"), its "next_event" (NET), "completed" (LTC),
+ *   "last_granted" (TAG), and "last_provisionally_granted" (PTAG) tags, its
+ *   "upstream" federates with the "delay" of their connection in nanoseconds
+ *   (null for no delay), its "downstream" federates, the IDs of the federates
+ *   of the zero-delay cycle that it is part of, or null ("zero_delay_cycle"),
+ *   the names of the capabilities enabled for it ("capabilities"), whether its
+ *   connection is lost while the RTI holds its state for it to resume its
+ *   session ("suspended"), whether it has started but sent no NET yet
+ *   ("silent"), in which case its "next_event" may be one presumed after
+ *   --silent_grace, and whether it is transient ("transient").
+ *
+ * Tags are rendered as in the event stream. Readers ignore the fields that
+ * they do not know, so a field may be added without a new version, but a
+ * change of the meaning of a field or a required new field needs one.
+ * Version 1 is the unversioned format of earlier releases, without
+ * "schema_version", "stop_in_progress", and "transient", which are read as
+ * false.
+ */
+use std::fs;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::event_stream::{json_string, json_tag};
+use crate::lock_stats::read_rti;
+use crate::net_common::capability_names;
+use crate::tag::{self, Instant, StartTime, Tag, NO_DELAY};
+use crate::topology;
+use crate::trace::{info, warn};
+use crate::FedState;
+use crate::FederationRTI;
+
+/**
+ * Version of the snapshot schema written by this RTI. Snapshot::parse accepts
+ * snapshots of this or an earlier version.
+ */
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 2;
+
+/**
+ * Names of the states of a federate in a snapshot.
+ */
+const STATE_NAMES: [&str; 3] = ["not_connected", "granted", "pending"];
+
+/**
+ * State of one federate in a snapshot. Tags are relative to the start time.
+ */
+#[derive(PartialEq, Clone, Debug)]
+pub struct FederateSnapshot {
+    id: u16,
+    name: Option<String>,
+    state: String,
+    next_event: Tag,
+    completed: Tag,
+    last_granted: Tag,
+    last_provisionally_granted: Tag,
+    upstream: Vec<(u16, Option<i64>)>,
+    downstream: Vec<u16>,
+    zero_delay_cycle: Option<Vec<u16>>,
+    capabilities: Vec<String>,
+    suspended: bool,
+    silent: bool,
+    transient: bool,
+}
+
+impl FederateSnapshot {
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /**
+     * Return "not_connected", "granted", or "pending".
+     */
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn next_event(&self) -> &Tag {
+        &self.next_event
+    }
+
+    pub fn completed(&self) -> &Tag {
+        &self.completed
+    }
+
+    pub fn last_granted(&self) -> &Tag {
+        &self.last_granted
+    }
+
+    pub fn last_provisionally_granted(&self) -> &Tag {
+        &self.last_provisionally_granted
+    }
+
+    /**
+     * Return the upstream federates with the delays of their connections, or
+     * None for connections without delay.
+     */
+    pub fn upstream(&self) -> &[(u16, Option<i64>)] {
+        &self.upstream
+    }
+
+    pub fn downstream(&self) -> &[u16] {
+        &self.downstream
+    }
+
+    pub fn zero_delay_cycle(&self) -> Option<&[u16]> {
+        self.zero_delay_cycle.as_deref()
+    }
+
+    pub fn capabilities(&self) -> &[String] {
+        &self.capabilities
+    }
+
+    pub fn suspended(&self) -> bool {
+        self.suspended
+    }
+
+    pub fn silent(&self) -> bool {
+        self.silent
+    }
+
+    pub fn transient(&self) -> bool {
+        self.transient
+    }
+
+    fn to_json(&self) -> String {
+        let upstream: Vec<String> = self
+            .upstream
+            .iter()
+            .map(|(id, delay)| match delay {
+                Some(delay) => format!("{{\"id\":{},\"delay\":{}}}", id, delay),
+                None => format!("{{\"id\":{},\"delay\":null}}", id),
+            })
+            .collect();
+        let cycle = match &self.zero_delay_cycle {
+            Some(cycle) => format!("[{}]", join_ids(cycle)),
+            None => String::from("null"),
+        };
+        let capabilities: Vec<String> = self
+            .capabilities
+            .iter()
+            .map(|name| json_string(name))
+            .collect();
+        format!(
+            "{{\"id\":{},\"name\":{},\"state\":\"{}\",\"next_event\":{},\"completed\":{},\"last_granted\":{},\"last_provisionally_granted\":{},\"upstream\":[{}],\"downstream\":[{}],\"zero_delay_cycle\":{},\"capabilities\":[{}],\"suspended\":{},\"silent\":{},\"transient\":{}}}",
+            self.id,
+            match &self.name {
+                Some(name) => json_string(name),
+                None => String::from("null"),
+            },
+            self.state,
+            json_tag(&self.next_event, 0),
+            json_tag(&self.completed, 0),
+            json_tag(&self.last_granted, 0),
+            json_tag(&self.last_provisionally_granted, 0),
+            upstream.join(","),
+            join_ids(&self.downstream),
+            cycle,
+            capabilities.join(","),
+            self.suspended,
+            self.silent,
+            self.transient
+        )
+    }
+}
+
+/**
+ * State of the federation at one point in time.
+ */
+#[derive(PartialEq, Clone, Debug)]
+pub struct Snapshot {
+    schema_version: u32,
+    federation_id: String,
+    start_time: Option<Instant>,
+    stop_in_progress: bool,
+    federates: Vec<FederateSnapshot>,
+}
+
+impl Snapshot {
+    /**
+     * Take a snapshot of the federation, whose start time is NEVER if it has
+     * not been decided.
+     */
+    pub(crate) fn capture(locked_rti: &FederationRTI, start_time: Instant) -> Snapshot {
+        let number_of_enclaves = locked_rti.federates().len();
+        let cycles: Vec<Option<Vec<u16>>> = (0..number_of_enclaves as u16)
+            .map(|fed_id| match locked_rti.topology() {
+                Some(topology) => topology.zero_delay_cycle(fed_id).cloned(),
+                None => topology::zero_delay_cycle(locked_rti.federates(), fed_id),
+            })
+            .collect();
+        let relative = |tag: Tag| {
+            if tag.time() == tag::NEVER || tag.time() == tag::FOREVER {
+                tag
+            } else {
+                Tag::new(tag.time().saturating_sub(start_time), tag.microstep())
+            }
+        };
+        let federates = locked_rti
+            .federates()
+            .iter()
+            .zip(cycles)
+            .map(|(fed, zero_delay_cycle)| {
+                let e = fed.e();
+                let state = match e.state() {
+                    FedState::NotConnected => STATE_NAMES[0],
+                    FedState::Granted => STATE_NAMES[1],
+                    FedState::Pending => STATE_NAMES[2],
+                };
+                FederateSnapshot {
+                    id: e.id(),
+                    name: fed.name().clone(),
+                    state: String::from(state),
+                    next_event: relative(e.next_event()),
+                    completed: relative(e.completed()),
+                    last_granted: relative(e.last_granted()),
+                    last_provisionally_granted: relative(e.last_provisionally_granted()),
+                    upstream: e
+                        .upstream()
+                        .iter()
+                        .zip(e.upstream_delay())
+                        .map(|(id, delay)| {
+                            let delay = delay.filter(|delay| *delay != NO_DELAY.unwrap());
+                            (*id as u16, delay)
+                        })
+                        .collect(),
+                    downstream: e.downstream().iter().map(|id| *id as u16).collect(),
+                    zero_delay_cycle,
+                    capabilities: capability_names(fed.capabilities())
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect(),
+                    suspended: fed.suspended() && e.state() != FedState::NotConnected,
+                    silent: fed.silent(),
+                    transient: e.transient(),
+                }
+            })
+            .collect();
+        Snapshot {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            federation_id: locked_rti.federation_id(),
+            start_time: (start_time != tag::NEVER).then_some(start_time),
+            stop_in_progress: locked_rti.stop_in_progress(),
+            federates,
+        }
+    }
+
+    /**
+     * Return the version of the schema in which the snapshot was written. A
+     * snapshot of an earlier version has been upgraded to the current one.
+     */
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    pub fn federation_id(&self) -> &str {
+        &self.federation_id
+    }
+
+    /**
+     * Return the start time, or None if it had not been decided.
+     */
+    pub fn start_time(&self) -> Option<Instant> {
+        self.start_time
+    }
+
+    pub fn stop_in_progress(&self) -> bool {
+        self.stop_in_progress
+    }
+
+    pub fn federates(&self) -> &[FederateSnapshot] {
+        &self.federates
+    }
+
+    /**
+     * Return the snapshot as a JSON object in the current schema version.
+     */
+    pub fn to_json(&self) -> String {
+        let federates: Vec<String> = self.federates.iter().map(|fed| fed.to_json()).collect();
+        format!(
+            "{{\"schema_version\":{},\"federation_id\":{},\"start_time\":{},\"stop_in_progress\":{},\"federates\":[{}]}}",
+            SNAPSHOT_SCHEMA_VERSION,
+            json_string(&self.federation_id),
+            match self.start_time {
+                Some(start_time) => start_time.to_string(),
+                None => String::from("null"),
+            },
+            self.stop_in_progress,
+            federates.join(",")
+        )
+    }
+
+    /**
+     * Read a snapshot in the current or an earlier schema version. The error
+     * tells where the text does not follow the schema, e.g.,
+     * "federates[1].next_event: expected [time, microstep], "NEVER", or
+     * "FOREVER"".
+     */
+    pub fn parse(text: &str) -> Result<Snapshot, String> {
+        let value = parse_json(text)?;
+        let fields = Fields::of(&value, "")?;
+        let schema_version = match fields.get("schema_version") {
+            None => 1,
+            Some(value) => {
+                let version: u32 = integer(value, &fields.path("schema_version"))?;
+                if version == 0 {
+                    return Err(String::from("schema_version: versions start at 1"));
+                }
+                if version > SNAPSHOT_SCHEMA_VERSION {
+                    return Err(format!(
+                        "schema_version: version {} is newer than version {} that this RTI reads; upgrade the RTI",
+                        version, SNAPSHOT_SCHEMA_VERSION
+                    ));
+                }
+                version
+            }
+        };
+        // The fields added in later versions.
+        let since_version_2 = |fields: &Fields, name: &str| -> Result<bool, String> {
+            if schema_version < 2 {
+                Ok(false)
+            } else {
+                boolean(fields.required(name)?, &fields.path(name))
+            }
+        };
+        let federation_id = string(
+            fields.required("federation_id")?,
+            &fields.path("federation_id"),
+        )?;
+        let start_time = match fields.required("start_time")? {
+            Value::Null => None,
+            value => Some(integer(value, &fields.path("start_time"))?),
+        };
+        let stop_in_progress = since_version_2(&fields, "stop_in_progress")?;
+        let entries = array(fields.required("federates")?, "federates")?;
+        let mut federates = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let fields = Fields::of(entry, &format!("federates[{}]", index))?;
+            let id: u16 = integer(fields.required("id")?, &fields.path("id"))?;
+            if usize::from(id) != index {
+                return Err(format!(
+                    "{}: expected {} because federates are listed in the order of their IDs, found {}",
+                    fields.path("id"),
+                    index,
+                    id
+                ));
+            }
+            let name = match fields.required("name")? {
+                Value::Null => None,
+                value => Some(string(value, &fields.path("name"))?),
+            };
+            let state = string(fields.required("state")?, &fields.path("state"))?;
+            if !STATE_NAMES.contains(&state.as_str()) {
+                return Err(format!(
+                    "{}: expected \"not_connected\", \"granted\", or \"pending\", found \"{}\"",
+                    fields.path("state"),
+                    state
+                ));
+            }
+            let tag_field = |name: &str| parse_tag(fields.required(name)?, &fields.path(name));
+            let mut upstream = Vec::new();
+            for (i, value) in array(fields.required("upstream")?, &fields.path("upstream"))?
+                .iter()
+                .enumerate()
+            {
+                let upstream_fields =
+                    Fields::of(value, &format!("{}[{}]", fields.path("upstream"), i))?;
+                let upstream_id =
+                    integer(upstream_fields.required("id")?, &upstream_fields.path("id"))?;
+                let delay = match upstream_fields.required("delay")? {
+                    Value::Null => None,
+                    value => Some(integer(value, &upstream_fields.path("delay"))?),
+                };
+                upstream.push((upstream_id, delay));
+            }
+            let downstream = id_list(fields.required("downstream")?, &fields.path("downstream"))?;
+            let zero_delay_cycle = match fields.required("zero_delay_cycle")? {
+                Value::Null => None,
+                value => Some(id_list(value, &fields.path("zero_delay_cycle"))?),
+            };
+            let mut capabilities = Vec::new();
+            for (i, value) in array(
+                fields.required("capabilities")?,
+                &fields.path("capabilities"),
+            )?
+            .iter()
+            .enumerate()
+            {
+                capabilities.push(string(
+                    value,
+                    &format!("{}[{}]", fields.path("capabilities"), i),
+                )?);
+            }
+            federates.push(FederateSnapshot {
+                id,
+                name,
+                state,
+                next_event: tag_field("next_event")?,
+                completed: tag_field("completed")?,
+                last_granted: tag_field("last_granted")?,
+                last_provisionally_granted: tag_field("last_provisionally_granted")?,
+                upstream,
+                downstream,
+                zero_delay_cycle,
+                capabilities,
+                suspended: boolean(fields.required("suspended")?, &fields.path("suspended"))?,
+                silent: boolean(fields.required("silent")?, &fields.path("silent"))?,
+                transient: since_version_2(&fields, "transient")?,
+            });
+        }
+        // Every federate that is referred to has to be listed.
+        for (index, fed) in federates.iter().enumerate() {
+            let referred = fed
+                .upstream
+                .iter()
+                .map(|(id, _)| ("upstream", *id))
+                .chain(fed.downstream.iter().map(|id| ("downstream", *id)))
+                .chain(
+                    fed.zero_delay_cycle
+                        .iter()
+                        .flatten()
+                        .map(|id| ("zero_delay_cycle", *id)),
+                );
+            for (field, id) in referred {
+                if usize::from(id) >= federates.len() {
+                    return Err(format!(
+                        "federates[{}].{}: no federate has the ID {}",
+                        index, field, id
+                    ));
+                }
+            }
+        }
+        Ok(Snapshot {
+            schema_version,
+            federation_id,
+            start_time,
+            stop_in_progress,
+            federates,
+        })
+    }
+
+    /**
+     * Check that the snapshot can be restored in a federation with the given
+     * ID and number of federates, and return why not otherwise.
+     */
+    pub(crate) fn check_restorable(
+        &self,
+        federation_id: &str,
+        number_of_federates: usize,
+    ) -> Result<(), String> {
+        if self.federation_id != federation_id {
+            return Err(format!(
+                "it is of federation {}, not {}",
+                self.federation_id, federation_id
+            ));
+        }
+        if self.federates.len() != number_of_federates {
+            return Err(format!(
+                "it has {} federates, not {}",
+                self.federates.len(),
+                number_of_federates
+            ));
+        }
+        if self.start_time.is_none() {
+            return Err(String::from(
+                "it was taken before the start time was decided",
+            ));
+        }
+        if self.stop_in_progress {
+            return Err(String::from(
+                "it was taken while the federation was stopping",
+            ));
+        }
+        Ok(())
+    }
+
+    /**
+     * Restore the completed tag (LTC) of each federate, so that the RTI does
+     * not hold back the federates downstream of one that has not yet reported
+     * its progress since the snapshot was taken. The RTI calls this once it
+     * has sent the start time of the snapshot to the federates.
+     */
+    pub(crate) fn restore(&self, locked_rti: &FederationRTI) {
+        let Some(start_time) = self.start_time else {
+            return;
+        };
+        for (fed, restored) in locked_rti.federates().iter().zip(&self.federates) {
+            let completed = &restored.completed;
+            let completed = if completed.time() == tag::NEVER || completed.time() == tag::FOREVER {
+                completed.clone()
+            } else {
+                Tag::new(
+                    completed.time().saturating_add(start_time),
+                    completed.microstep(),
+                )
+            };
+            if completed > fed.e().completed() {
+                fed.e().set_completed(completed);
+            }
+        }
+    }
+}
+
+/**
+ * Trigger of the snapshots that the RTI writes to --snapshot_file, e.g., by a
+ * SIGUSR1 handler or by an application that embeds the RTI.
+ */
+#[derive(Clone, Default)]
+pub struct SnapshotTrigger {
+    state: Arc<(Mutex<TriggerState>, Condvar)>,
+}
+
+#[derive(Default)]
+struct TriggerState {
+    requested: bool,
+    finished: bool,
+}
+
+impl SnapshotTrigger {
+    pub fn new() -> SnapshotTrigger {
+        SnapshotTrigger::default()
+    }
+
+    /**
+     * Request the RTI to write a snapshot. Requests made while the previous
+     * one is being written result in one more snapshot.
+     */
+    pub fn request(&self) {
+        let (lock, condvar) = &*self.state;
+        lock.lock().unwrap().requested = true;
+        condvar.notify_all();
+    }
+
+    /**
+     * Wait until a snapshot is requested or the given period has passed, and
+     * return false if the RTI has finished instead.
+     */
+    fn wait(&self, period: Option<Duration>) -> bool {
+        let (lock, condvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        let deadline = period.map(|period| std::time::Instant::now() + period);
+        while !state.requested && !state.finished {
+            match deadline {
+                Some(deadline) => {
+                    let now = std::time::Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    state = condvar.wait_timeout(state, deadline - now).unwrap().0;
+                }
+                None => state = condvar.wait(state).unwrap(),
+            }
+        }
+        state.requested = false;
+        !state.finished
+    }
+
+    /**
+     * Record that all federates have exited, so that the snapshot thread
+     * writes the last snapshot and returns.
+     */
+    pub(crate) fn finish(&self) {
+        let (lock, condvar) = &*self.state;
+        lock.lock().unwrap().finished = true;
+        condvar.notify_all();
+    }
+}
+
+/**
+ * Spawn a thread that writes a snapshot to the file at `path` whenever one is
+ * requested through the trigger, every `checkpoint_period_ms` milliseconds if
+ * it is not 0, and once more when all federates have exited.
+ */
+pub(crate) fn start_snapshot_thread(
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    start_time: Arc<Mutex<StartTime>>,
+    trigger: SnapshotTrigger,
+    path: String,
+    checkpoint_period_ms: u64,
+) -> thread::JoinHandle<()> {
+    let period = (checkpoint_period_ms > 0).then(|| Duration::from_millis(checkpoint_period_ms));
+    thread::spawn(move || loop {
+        let running = trigger.wait(period);
+        let start_time_value = start_time.lock().unwrap().start_time();
+        let text = Snapshot::capture(&read_rti(&_f_rti), start_time_value).to_json();
+        match write_snapshot(&path, &text) {
+            Ok(()) => info!("RTI: Wrote a snapshot of the federation to {}.", path),
+            Err(e) => warn!("RTI: Failed to write a snapshot to {}: {}.", path, e),
+        }
+        if !running {
+            break;
+        }
+    })
+}
+
+/**
+ * Replace the file at `path` with the snapshot, so that readers never see a
+ * partially written one.
+ */
+fn write_snapshot(path: &str, text: &str) -> io::Result<()> {
+    let partial = format!("{}.partial", path);
+    fs::write(&partial, text)?;
+    fs::rename(&partial, path)
+}
+
+/**
+ * Request a snapshot through the given trigger when the process receives
+ * SIGUSR1. The handler is process-wide, so this is called by the RTI binary
+ * rather than by the library.
+ */
+#[cfg(unix)]
+pub fn install_snapshot_signal_handler(trigger: SnapshotTrigger) -> io::Result<()> {
+    use signal_hook::consts::SIGUSR1;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGUSR1])?;
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            trigger.request();
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn install_snapshot_signal_handler(_trigger: SnapshotTrigger) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+fn join_ids(ids: &[u16]) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    ids.join(",")
+}
+
+/**
+ * The fields of a JSON object, with the path of the object in the snapshot
+ * for the errors.
+ */
+struct Fields<'a> {
+    path: String,
+    fields: &'a Map<String, Value>,
+}
+
+impl<'a> Fields<'a> {
+    fn of(value: &'a Value, path: &str) -> Result<Fields<'a>, String> {
+        match value {
+            Value::Object(fields) => Ok(Fields {
+                path: path.to_string(),
+                fields,
+            }),
+            _ if path.is_empty() => Err(String::from("expected a JSON object")),
+            _ => Err(format!("{}: expected an object", path)),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&'a Value> {
+        self.fields.get(name)
+    }
+
+    fn required(&self, name: &str) -> Result<&'a Value, String> {
+        self.get(name).ok_or_else(|| {
+            if self.path.is_empty() {
+                format!("missing field \"{}\"", name)
+            } else {
+                format!("{}: missing field \"{}\"", self.path, name)
+            }
+        })
+    }
+
+    fn path(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.path, name)
+        }
+    }
+}
+
+/**
+ * Parse the JSON text of a snapshot. The error tells the line and the column,
+ * e.g., "line 19, column 41: expected `,` or `}`". The parser limits the
+ * nesting of arrays and objects, so a deeply nested text is rejected rather
+ * than exhausting the stack.
+ */
+fn parse_json(text: &str) -> Result<Value, String> {
+    serde_json::from_str(text).map_err(|error| {
+        let message = error.to_string();
+        let location = format!(" at line {} column {}", error.line(), error.column());
+        format!(
+            "line {}, column {}: {}",
+            error.line(),
+            error.column(),
+            message.strip_suffix(&location).unwrap_or(&message)
+        )
+    })
+}
+
+fn integer<T: TryFrom<i64>>(value: &Value, path: &str) -> Result<T, String> {
+    value
+        .as_i64()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| {
+            format!(
+                "{}: expected an integer of type {}",
+                path,
+                std::any::type_name::<T>()
+            )
+        })
+}
+
+fn boolean(value: &Value, path: &str) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("{}: expected true or false", path))
+}
+
+fn string(value: &Value, path: &str) -> Result<String, String> {
+    value
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| format!("{}: expected a string", path))
+}
+
+fn array<'a>(value: &'a Value, path: &str) -> Result<&'a [Value], String> {
+    value
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| format!("{}: expected an array", path))
+}
+
+fn id_list(value: &Value, path: &str) -> Result<Vec<u16>, String> {
+    array(value, path)?
+        .iter()
+        .enumerate()
+        .map(|(i, value)| integer(value, &format!("{}[{}]", path, i)))
+        .collect()
+}
+
+fn parse_tag(value: &Value, path: &str) -> Result<Tag, String> {
+    match value {
+        Value::String(text) if text == "NEVER" => Ok(Tag::new(tag::NEVER, 0)),
+        Value::String(text) if text == "FOREVER" => {
+            Ok(Tag::new(tag::FOREVER, tag::FOREVER_MICROSTEP))
+        }
+        Value::Array(values) if values.len() == 2 => Ok(Tag::new(
+            integer(&values[0], &format!("{}[0]", path))?,
+            integer(&values[1], &format!("{}[1]", path))?,
+        )),
+        _ => Err(format!(
+            "{}: expected [time, microstep], \"NEVER\", or \"FOREVER\"",
+            path
+        )),
+    }
+}
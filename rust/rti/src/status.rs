@@ -0,0 +1,150 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief HTTP server that reports the live state of the federation in JSON,
+ * e.g., to find out why a federation is stuck.
+ *
+ * "GET /status" (or "GET /") is answered with a snapshot of the state of the
+ * federation (see snapshot.rs).
+ *
+ * Each connection is answered once and closed. If the RTI has tokens for the
+ * admin console, requests must carry either of them in an "Authorization:
+ * Bearer <token>" header and are otherwise answered with "401 Unauthorized".
+ */
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::admin;
+use crate::constants::STATUS_READ_TIMEOUT_MS;
+use crate::lock_stats::read_rti;
+use crate::snapshot::Snapshot;
+use crate::tag::StartTime;
+use crate::trace::{debug, error};
+use crate::FederationRTI;
+
+pub struct StatusServer {
+    _f_rti: Arc<RwLock<FederationRTI>>,
+    start_time: Arc<Mutex<StartTime>>,
+}
+
+impl StatusServer {
+    pub fn new(
+        _f_rti: Arc<RwLock<FederationRTI>>,
+        start_time: Arc<Mutex<StartTime>>,
+    ) -> StatusServer {
+        StatusServer { _f_rti, start_time }
+    }
+
+    /**
+     * Spawn a thread that accepts connections on the given listener until all
+     * federates have exited. Each connection is served by its own thread so
+     * that a slow client does not block others.
+     */
+    pub fn start_status_thread(self, listener: TcpListener) -> thread::JoinHandle<()> {
+        let server = Arc::new(self);
+        thread::spawn(move || {
+            // NOTE: The RTI wakes up the thread with a connection once all
+            // federates have exited.
+            loop {
+                let result = listener.accept();
+                {
+                    let locked_rti = read_rti(&server._f_rti);
+                    if locked_rti.all_federates_exited() {
+                        break;
+                    }
+                }
+                match result {
+                    Ok((stream, address)) => {
+                        debug!("RTI: Status request from {}.", address);
+                        let server = server.clone();
+                        thread::spawn(move || server.serve(stream));
+                    }
+                    Err(e) => {
+                        error!("RTI: Status server failed to accept a connection: {}.", e);
+                    }
+                }
+            }
+        })
+    }
+
+    fn serve(&self, stream: TcpStream) {
+        let _ = stream.set_read_timeout(Some(Duration::from_millis(STATUS_READ_TIMEOUT_MS)));
+        let mut writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(e) => {
+                error!("RTI: Status server failed to set up a connection: {}.", e);
+                return;
+            }
+        };
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+        // Skip the headers other than the token.
+        let mut token = None;
+        loop {
+            let mut header = String::new();
+            match reader.read_line(&mut header) {
+                Ok(0) | Err(_) => break,
+                Ok(_) if header.trim().is_empty() => break,
+                Ok(_) => {
+                    if let Some((name, value)) = header.split_once(':') {
+                        let value = value.trim();
+                        if name.trim().eq_ignore_ascii_case("authorization") {
+                            token = value.strip_prefix("Bearer ").map(str::to_string);
+                        }
+                    }
+                }
+            }
+        }
+        // Every role may read the status.
+        let authorized = admin::authorize(&read_rti(&self._f_rti), token.as_deref()).is_some();
+        let words: Vec<&str> = request_line.split_whitespace().collect();
+        let (status, body) = match words.as_slice() {
+            _ if !authorized => (
+                "401 Unauthorized",
+                String::from("{\"error\":\"unauthorized\"}"),
+            ),
+            ["GET", "/" | "/status", ..] => ("200 OK", self.status()),
+            ["GET", ..] => ("404 Not Found", String::from("{\"error\":\"not found\"}")),
+            _ => (
+                "405 Method Not Allowed",
+                String::from("{\"error\":\"method not allowed\"}"),
+            ),
+        };
+        let challenge = if authorized {
+            ""
+        } else {
+            "WWW-Authenticate: Bearer\r\n"
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            challenge,
+            body.len(),
+            body
+        );
+        // NOTE: Ignore errors because the client may have gone away.
+        let _ = writer.write_all(response.as_bytes());
+    }
+
+    /**
+     * Return the state of the federation as a JSON object.
+     */
+    pub fn status(&self) -> String {
+        let start_time_value;
+        {
+            let locked_start_time = self.start_time.lock().unwrap();
+            start_time_value = locked_start_time.start_time();
+        }
+        let locked_rti = read_rti(&self._f_rti);
+        Snapshot::capture(&locked_rti, start_time_value).to_json()
+    }
+}
@@ -1,181 +1,235 @@
 /**
  * @file
- * @author Edward A. Lee (eal@berkeley.edu)
- * @author Soroush Bateni (soroush@utdallas.edu)
- * @author Erling Jellum (erling.r.jellum@ntnu.no)
- * @author Chadlia Jerad (chadlia.jerad@ensi-uma.tn)
  * @author Chanhee Lee (chanheel@asu.edu)
  * @author Hokeun Kim (hokeun@asu.edu)
- * @copyright (c) 2020-2023, The University of California at Berkeley
+ * @copyright (c) 2023, Arizona State University
  * License in [BSD 2-clause](..)
- * @brief Declarations for runtime infrastructure (RTI) for distributed Lingua Franca programs.
- * This file extends enclave.h with RTI features that are specific to federations and are not
- * used by scheduling enclaves.
+ * @brief Tags and logical time used by the RTI.
+ * The implementation lives in the standalone lf_tag crate so that other
+ * Rust tooling for Lingua Franca can share it.
  */
+pub use lf_tag::*;
 
-////////////////  Type definitions
+use std::sync::atomic::{AtomicI64, AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /**
- * Time instant. Both physical and logical times are represented
- * using this typedef.
+ * Resolution of logical time in a federation. Times are always encoded in
+ * nanoseconds on the wire, but a federation with a coarser resolution only
+ * uses multiples of it, and the RTI prints times and parses the times given
+ * by users in its unit.
  */
-pub type Instant = i64;
-
-/**
- * Interval of time.
- */
-pub type Interval = std::option::Option<i64>;
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TimeResolution {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+}
 
-/**
- * Microstep instant.
- */
-pub type Microstep = u32;
+impl TimeResolution {
+    pub fn from_unit(unit: &str) -> Option<TimeResolution> {
+        match unit {
+            "ns" => Some(TimeResolution::Nanoseconds),
+            "us" => Some(TimeResolution::Microseconds),
+            "ms" => Some(TimeResolution::Milliseconds),
+            _ => None,
+        }
+    }
 
-const NEVER: i64 = i64::MIN;
+    pub fn unit(self) -> &'static str {
+        match self {
+            TimeResolution::Nanoseconds => "ns",
+            TimeResolution::Microseconds => "us",
+            TimeResolution::Milliseconds => "ms",
+        }
+    }
 
-pub struct StartTime {
-    start_time: Instant,
-}
+    /**
+     * The number of nanoseconds in one unit of this resolution.
+     */
+    pub fn nanoseconds(self) -> i64 {
+        match self {
+            TimeResolution::Nanoseconds => nsec(1),
+            TimeResolution::Microseconds => usec(1),
+            TimeResolution::Milliseconds => msec(1),
+        }
+    }
 
-impl StartTime {
-    pub fn new() -> StartTime {
-        StartTime { start_time: NEVER }
+    /**
+     * Return true if the given number of nanoseconds is a whole number of
+     * units of this resolution.
+     */
+    pub fn is_aligned(self, nanoseconds: i64) -> bool {
+        nanoseconds % self.nanoseconds() == 0
     }
 
-    pub fn start_time(&self) -> Instant {
-        self.start_time
+    /**
+     * Convert nanoseconds to units of this resolution, rounding toward zero.
+     */
+    pub fn to_units(self, nanoseconds: i64) -> i64 {
+        nanoseconds / self.nanoseconds()
     }
 
-    pub fn set_start_time(&mut self, start_time: Instant) {
-        self.start_time = start_time;
+    /**
+     * Convert units of this resolution to nanoseconds, or return None if the
+     * result does not fit in an instant.
+     */
+    pub fn to_nanoseconds(self, units: i64) -> Option<i64> {
+        units.checked_mul(self.nanoseconds())
     }
 }
 
 /**
- * A tag is a time, microstep pair.
+ * How the RTI prints times, such as the times of tags in its output and in
+ * the responses of the admin console, and the timestamps of log lines.
  */
-#[derive(Hash, Eq, PartialEq, Clone)]
-pub struct Tag {
-    time: Instant,
-    microstep: Microstep,
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TimeFormat {
+    /// The time elapsed since the start time, in units of the time resolution.
+    Elapsed,
+    /// The date and time in UTC in the format of RFC 3339, with nanoseconds,
+    /// taking times as nanoseconds since the Unix epoch.
+    Absolute,
+    /// The nanoseconds since the epoch of the clock, as encoded on the wire.
+    Raw,
 }
 
-////////////////  Functions
-
-impl Tag {
-    pub fn new(time: Instant, microstep: Microstep) -> Tag {
-        Tag { time, microstep }
-    }
-
-    pub fn never_tag() -> Tag {
-        Tag {
-            time: i64::MIN,
-            microstep: 0,
+impl TimeFormat {
+    pub fn from_name(name: &str) -> Option<TimeFormat> {
+        match name {
+            "elapsed" => Some(TimeFormat::Elapsed),
+            "absolute" => Some(TimeFormat::Absolute),
+            "raw" => Some(TimeFormat::Raw),
+            _ => None,
         }
     }
 
-    pub fn forever_tag() -> Tag {
-        Tag {
-            time: i64::MAX,
-            microstep: u32::MAX,
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeFormat::Elapsed => "elapsed",
+            TimeFormat::Absolute => "absolute",
+            TimeFormat::Raw => "raw",
         }
     }
+}
 
-    pub fn time(&self) -> Instant {
-        self.time.clone()
-    }
+/**
+ * Resolution, in nanoseconds, used to print times for the whole process.
+ */
+static PRINTED_RESOLUTION_NS: AtomicI64 = AtomicI64::new(1);
 
-    pub fn microstep(&self) -> Microstep {
-        self.microstep.clone()
-    }
+/**
+ * Set the resolution in which times are printed for the whole process.
+ */
+pub fn set_printed_resolution(resolution: TimeResolution) {
+    PRINTED_RESOLUTION_NS.store(resolution.nanoseconds(), Ordering::Relaxed);
+}
 
-    pub fn set_time(&mut self, time: i64) {
-        self.time = time;
-    }
+/**
+ * Format used to print times for the whole process.
+ */
+static PRINTED_TIME_FORMAT: AtomicU8 = AtomicU8::new(TimeFormat::Elapsed as u8);
 
-    pub fn set_microstep(&mut self, microstep: u32) {
-        self.microstep = microstep;
-    }
+/**
+ * Start time of the federation for the timestamps of log lines, or NEVER
+ * until it is known.
+ */
+static PRINTED_START_TIME: AtomicI64 = AtomicI64::new(NEVER);
 
-    pub fn lf_tag_compare(tag1: &Tag, tag2: &Tag) -> i32 {
-        let tag1_time = tag1.time();
-        let tag2_time = tag2.time();
-        let tag1_microstep = tag1.microstep();
-        let tag2_microstep = tag2.microstep();
-        if tag1_time < tag2_time {
-            // println!("{} < {}", tag1_time, tag2_time);
-            -1
-        } else if tag1_time > tag2_time {
-            // println!("{} > {}", tag1_time, tag2_time);
-            1
-        } else if tag1_microstep < tag2_microstep {
-            // println!("{} and microstep < {}", tag1_microstep, tag2_microstep);
-            -1
-        } else if tag1_microstep > tag2_microstep {
-            1
-        } else {
-            0
-        }
+/**
+ * Set the format in which times are printed for the whole process.
+ */
+pub fn set_printed_time_format(time_format: TimeFormat) {
+    PRINTED_TIME_FORMAT.store(time_format as u8, Ordering::Relaxed);
+}
+
+pub fn printed_time_format() -> TimeFormat {
+    match PRINTED_TIME_FORMAT.load(Ordering::Relaxed) {
+        format if format == TimeFormat::Absolute as u8 => TimeFormat::Absolute,
+        format if format == TimeFormat::Raw as u8 => TimeFormat::Raw,
+        _ => TimeFormat::Elapsed,
     }
+}
 
-    pub fn lf_delay_tag(tag: &Tag, interval: Interval) -> Tag {
-        if tag.time() == i64::MIN || interval < Some(0) {
-            // println!(
-            //     "tag.time() == i64::MIN || interval < Some(0),  (interval, time) = ({:?},{})",
-            //     interval,
-            //     tag.time()
-            // );
-            return tag.clone();
-        }
-        let mut result = tag.clone();
-        if interval == Some(0) {
-            // Note that unsigned variables will wrap on overflow.
-            // This is probably the only reasonable thing to do with overflowing
-            // microsteps.
-            result.set_microstep(result.microstep() + 1);
-            // println!(
-            //     "interval == 0,  (time, microstep) = ({},{})",
-            //     result.time(),
-            //     result.microstep()
-            // );
-        } else {
-            // Note that overflow in C is undefined for signed variables.
-            if i64::MAX - interval.unwrap() < result.time() {
-                result.set_time(i64::MAX);
-                // println!(
-                //     "i64::MAX - interval.unwrap() < result.time()  (time, microstep) = ({},{})",
-                //     result.time(),
-                //     result.microstep()
-                // );
-            } else {
-                // FIXME: Handle unwrap() properly.
-                result.set_time(result.time() + interval.unwrap());
-                println!("result.set_time(result.time() + interval.unwrap()),  (time, microstep) = ({},{})", result.time(), result.microstep());
-            }
-            result.set_microstep(0);
-        }
+/**
+ * Set the start time from which the timestamps of log lines are counted in
+ * the elapsed format.
+ */
+pub fn set_printed_start_time(start_time: Instant) {
+    PRINTED_START_TIME.store(start_time, Ordering::Relaxed);
+}
 
-        result
-    }
+/**
+ * The logical time elapsed from the start time to the given time in units of
+ * the printed resolution.
+ */
+pub fn elapsed(time: Instant, start_time: Instant) -> i64 {
+    time.saturating_sub(start_time) / PRINTED_RESOLUTION_NS.load(Ordering::Relaxed)
+}
 
-    pub fn lf_delay_strict(tag: &Tag, interval: Interval) -> Tag {
-        let mut result = Self::lf_delay_tag(tag, interval);
-        if interval != Some(0)
-            && interval != Some(i64::MIN)
-            && interval != Some(i64::MAX)
-            && result.time() != i64::MIN
-            && result.time() != i64::MAX
-        {
-            // println!("interval={:?}, result time={}", interval, result.time());
-            result.set_time(result.time() - 1);
-            result.set_microstep(u32::MAX);
-        }
+/**
+ * Format the given time, given the start time, in the printed time format,
+ * for human-readable output.
+ */
+pub fn format_time(time: Instant, start_time: Instant) -> String {
+    match printed_time_format() {
+        TimeFormat::Elapsed => elapsed(time, start_time).to_string(),
+        TimeFormat::Absolute => format_utc(time),
+        TimeFormat::Raw => time.to_string(),
+    }
+}
 
-        // println!(
-        //     "(time, microstep) = ({},{})",
-        //     result.time(),
-        //     result.microstep()
-        // );
-        result
+/**
+ * Format the current wall-clock time in the printed time format, counting
+ * from the start time in the elapsed format, for the timestamps of log lines.
+ * Before the start time is known, elapsed timestamps are "-".
+ */
+pub fn log_timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| {
+            i64::try_from(since_epoch.as_nanos()).unwrap_or(FOREVER)
+        });
+    let start_time = PRINTED_START_TIME.load(Ordering::Relaxed);
+    if printed_time_format() == TimeFormat::Elapsed && start_time == NEVER {
+        return String::from("-");
     }
+    format_time(now, start_time)
+}
+
+/**
+ * Format nanoseconds since the Unix epoch as a date and time in UTC, such as
+ * 2023-05-01T12:00:00.000000000Z.
+ */
+fn format_utc(time: Instant) -> String {
+    let seconds = time.div_euclid(1_000_000_000);
+    let nanoseconds = time.rem_euclid(1_000_000_000);
+    let days = seconds.div_euclid(86_400);
+    let second_of_day = seconds.rem_euclid(86_400);
+    // Convert the days since 1970-01-01 to a date in the proleptic Gregorian
+    // calendar, counting years from March so that leap days come last.
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3_600,
+        second_of_day % 3_600 / 60,
+        second_of_day % 60,
+        nanoseconds
+    )
 }
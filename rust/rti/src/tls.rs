@@ -0,0 +1,285 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Optional TLS encryption of the connections of federates, enabled with
+ * --tls_cert and --tls_key.
+ *
+ * The RTI performs the TLS handshake on a new connection before the handshake
+ * of the federate, within the handshake timeout. Like a TcpStream, a TlsStream
+ * can be cloned: the clones share the TLS session, which is only locked while
+ * records are encrypted or decrypted, so that the threads that write to a
+ * federate do not wait for its scheduling thread blocked in a read.
+ */
+use std::io;
+use std::net::TcpStream;
+
+use crate::transport::FederateStream;
+
+#[cfg(feature = "tls")]
+use std::collections::VecDeque;
+#[cfg(feature = "tls")]
+use std::fs::File;
+#[cfg(feature = "tls")]
+use std::io::{BufReader, Read, Write};
+#[cfg(feature = "tls")]
+use std::net::Shutdown;
+#[cfg(feature = "tls")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "tls")]
+use rustls::crypto::ring;
+#[cfg(feature = "tls")]
+use rustls::{ServerConfig, ServerConnection};
+
+#[cfg(feature = "tls")]
+use crate::constants::TLS_READ_BUFFER_SIZE;
+
+/**
+ * Certificate chain and private key with which the RTI accepts connections
+ * over TLS.
+ */
+#[derive(Clone)]
+pub struct TlsConfig {
+    #[cfg(feature = "tls")]
+    config: Arc<ServerConfig>,
+}
+
+impl TlsConfig {
+    /**
+     * Load the certificate chain and the private key of the RTI from the PEM
+     * files at the given paths.
+     */
+    #[cfg(feature = "tls")]
+    pub fn load(cert_path: &str, key_path: &str) -> Result<TlsConfig, String> {
+        let open = |path: &str| {
+            File::open(path)
+                .map(BufReader::new)
+                .map_err(|e| format!("{}: {}", path, e))
+        };
+        let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{}: {}", cert_path, e))?;
+        if certs.is_empty() {
+            return Err(format!("{}: no certificate found", cert_path));
+        }
+        let key = rustls_pemfile::private_key(&mut open(key_path)?)
+            .map_err(|e| format!("{}: {}", key_path, e))?
+            .ok_or_else(|| format!("{}: no private key found", key_path))?;
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| e.to_string())?;
+        Ok(TlsConfig {
+            config: Arc::new(config),
+        })
+    }
+
+    #[cfg(not(feature = "tls"))]
+    pub fn load(_cert_path: &str, _key_path: &str) -> Result<TlsConfig, String> {
+        Err(String::from(
+            "the RTI has been built without the tls feature",
+        ))
+    }
+
+    /**
+     * Perform the TLS handshake on the given connection, within the read
+     * timeout of the socket, and return the encrypted stream.
+     */
+    pub fn accept(&self, socket: TcpStream) -> io::Result<FederateStream> {
+        #[cfg(feature = "tls")]
+        {
+            TlsStream::accept(socket, self.config.clone()).map(FederateStream::Tls)
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            drop(socket);
+            Err(io::Error::from(io::ErrorKind::Unsupported))
+        }
+    }
+}
+
+/**
+ * State of a TLS session shared by the clones of a TlsStream.
+ */
+#[cfg(feature = "tls")]
+struct Session {
+    connection: ServerConnection,
+    // Decrypted bytes that have not been read yet.
+    plaintext: VecDeque<u8>,
+    // Indicates that the federate has closed the connection.
+    closed: bool,
+}
+
+#[cfg(feature = "tls")]
+impl Session {
+    /**
+     * Move the decrypted bytes out of the connection.
+     */
+    fn take_plaintext(&mut self) -> io::Result<()> {
+        let mut chunk = [0u8; TLS_READ_BUFFER_SIZE];
+        loop {
+            match self.connection.reader().read(&mut chunk) {
+                // The federate has sent a close_notify alert.
+                Ok(0) => {
+                    self.closed = true;
+                    return Ok(());
+                }
+                Ok(size) => self.plaintext.extend(&chunk[..size]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /**
+     * Write the pending TLS records to the socket.
+     */
+    fn write_records(&mut self, mut socket: &TcpStream) -> io::Result<()> {
+        while self.connection.wants_write() {
+            self.connection.write_tls(&mut socket)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Connection to a federate encrypted with TLS.
+ */
+#[cfg(feature = "tls")]
+pub struct TlsStream {
+    socket: TcpStream,
+    session: Arc<Mutex<Session>>,
+    // Held while reading, so that the records read by one thread are
+    // decrypted before those read by another.
+    read_lock: Arc<Mutex<()>>,
+}
+
+#[cfg(feature = "tls")]
+impl TlsStream {
+    fn accept(mut socket: TcpStream, config: Arc<ServerConfig>) -> io::Result<TlsStream> {
+        let mut connection = ServerConnection::new(config).map_err(io::Error::other)?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket)?;
+        }
+        let mut session = Session {
+            connection,
+            plaintext: VecDeque::new(),
+            closed: false,
+        };
+        // The federate may have sent its first message together with the end
+        // of the TLS handshake.
+        session.take_plaintext()?;
+        session.write_records(&socket)?;
+        Ok(TlsStream {
+            socket,
+            session: Arc::new(Mutex::new(session)),
+            read_lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    pub fn try_clone(&self) -> io::Result<TlsStream> {
+        Ok(TlsStream {
+            socket: self.socket.try_clone()?,
+            session: self.session.clone(),
+            read_lock: self.read_lock.clone(),
+        })
+    }
+
+    pub fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+
+    pub fn peek(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.read_plaintext(buffer, false)
+    }
+
+    /**
+     * Send a close_notify alert to the federate and shut down the socket.
+     */
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        {
+            let mut session = self.session.lock().unwrap();
+            session.connection.send_close_notify();
+            // NOTE: Ignore errors because the federate may have closed the
+            // connection already.
+            let _ = session.write_records(&self.socket);
+        }
+        self.socket.shutdown(how)
+    }
+
+    /**
+     * Read decrypted bytes into the given buffer, waiting for records from
+     * the socket if there are none, and remove them from the stream if
+     * consume is true.
+     */
+    fn read_plaintext(&self, buffer: &mut [u8], consume: bool) -> io::Result<usize> {
+        let _read_guard = self.read_lock.lock().unwrap();
+        let mut records = [0u8; TLS_READ_BUFFER_SIZE];
+        loop {
+            {
+                let mut session = self.session.lock().unwrap();
+                if !session.plaintext.is_empty() || buffer.is_empty() {
+                    let size = buffer.len().min(session.plaintext.len());
+                    for (dst, src) in buffer.iter_mut().zip(session.plaintext.iter()) {
+                        *dst = *src;
+                    }
+                    if consume {
+                        session.plaintext.drain(..size);
+                    }
+                    return Ok(size);
+                }
+                if session.closed {
+                    return Ok(0);
+                }
+            }
+            // Wait for records without holding the session, which the threads
+            // writing to the federate need.
+            let size = (&self.socket).read(&mut records)?;
+            let mut session = self.session.lock().unwrap();
+            if size == 0 {
+                session.closed = true;
+                continue;
+            }
+            let mut unprocessed = &records[..size];
+            while !unprocessed.is_empty() {
+                session.connection.read_tls(&mut unprocessed)?;
+                if let Err(e) = session.connection.process_new_packets() {
+                    // NOTE: Ignore errors on the alert that tells the
+                    // federate about the error.
+                    let _ = session.write_records(&self.socket);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+                session.take_plaintext()?;
+            }
+            // Answer, e.g., key updates.
+            session.write_records(&self.socket)?;
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Read for &TlsStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.read_plaintext(buffer, true)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Write for &TlsStream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let mut session = self.session.lock().unwrap();
+        let size = session.connection.writer().write(buffer)?;
+        session.write_records(&self.socket)?;
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Records are written to the socket as soon as they are encrypted.
+        Ok(())
+    }
+}
@@ -0,0 +1,722 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Validation of the connection topology reported by the federates.
+ */
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::federate::FederateMetadata;
+use crate::tag::{Interval, TimeResolution, NO_DELAY};
+use crate::FedState;
+use crate::Federate;
+
+/**
+ * Check the neighbor structures reported by the federates for problems that
+ * usually indicate a bug in the code generator or in the configuration of the
+ * federation, and return a description of each problem. Neighbors that are not
+ * in the federation are reported, with the subgraph that lists them if it is
+ * disconnected from the rest. Federates without any neighbors and independent
+ * subgraphs are not reported because they are legitimate in loosely coupled
+ * federations. Connections that only one end lists, neighbors listed more than
+ * once, and delays that are not multiples of the time resolution are also
+ * reported, except connections that only a transient federate that is not
+ * connected could list.
+ */
+pub fn validate_topology(enclaves: &[Federate], time_resolution: TimeResolution) -> Vec<String> {
+    let number_of_enclaves = enclaves.len();
+    let mut warnings = Vec::new();
+    // Undirected adjacency lists of the connections between federates.
+    let mut neighbors: Vec<Vec<usize>> = vec![Vec::new(); number_of_enclaves];
+    for fed in enclaves {
+        let e = fed.e();
+        let id = usize::from(e.id());
+        for &(neighbor_id, is_upstream) in fed.unknown_neighbors() {
+            warnings.push(format!(
+                "Federate {} lists federate {} as {}, which will never connect because the federation has {} federates.",
+                fed.label(),
+                neighbor_id,
+                if is_upstream { "upstream" } else { "downstream" },
+                number_of_enclaves
+            ));
+        }
+        for (is_upstream, neighbor_ids) in [(true, e.upstream()), (false, e.downstream())] {
+            let (direction, opposite) = if is_upstream {
+                ("upstream", "downstream")
+            } else {
+                ("downstream", "upstream")
+            };
+            for &neighbor_id in neighbor_ids {
+                let Some(neighbor) = usize::try_from(neighbor_id)
+                    .ok()
+                    .filter(|neighbor| *neighbor < number_of_enclaves)
+                else {
+                    continue;
+                };
+                let neighbor_e = enclaves[neighbor].e();
+                let reverse_ids = if is_upstream {
+                    neighbor_e.downstream()
+                } else {
+                    neighbor_e.upstream()
+                };
+                let unreported =
+                    neighbor_e.transient() && neighbor_e.state() == FedState::NotConnected;
+                if !reverse_ids.contains(&i32::from(e.id())) && !unreported {
+                    warnings.push(format!(
+                        "Federate {} lists federate {} as {}, but federate {} does not list federate {} as {}.",
+                        fed.label(),
+                        enclaves[neighbor].label(),
+                        direction,
+                        enclaves[neighbor].label(),
+                        fed.label(),
+                        opposite
+                    ));
+                }
+                neighbors[id].push(neighbor);
+                neighbors[neighbor].push(id);
+            }
+        }
+    }
+
+    let label = |id: i32| match usize::try_from(id) {
+        Ok(id) if id < number_of_enclaves => enclaves[id].label(),
+        _ => id.to_string(),
+    };
+    let describe_delay = |delay: Interval| match delay {
+        _ if delay == NO_DELAY => String::from("no delay"),
+        Some(delay_ns) => format!("a delay of {} ns", delay_ns),
+        None => String::from("an unknown delay"),
+    };
+
+    // The code generator lists each neighbor once, with the minimum delay of
+    // the connections from an upstream federate, so a neighbor listed twice
+    // indicates a bug, especially if the delays disagree.
+    for fed in enclaves {
+        let e = fed.e();
+        let mut listed: Vec<(i32, Interval)> = Vec::new();
+        for (&upstream_id, &delay) in e.upstream().iter().zip(e.upstream_delay()) {
+            match listed.iter().find(|(id, _)| *id == upstream_id) {
+                Some(&(_, first_delay)) if first_delay != delay => warnings.push(format!(
+                    "Federate {} lists federate {} as upstream with {} and with {}, which disagree.",
+                    fed.label(),
+                    label(upstream_id),
+                    describe_delay(first_delay),
+                    describe_delay(delay)
+                )),
+                Some(_) => warnings.push(format!(
+                    "Federate {} lists federate {} as upstream more than once.",
+                    fed.label(),
+                    label(upstream_id)
+                )),
+                None => listed.push((upstream_id, delay)),
+            }
+        }
+        for (idx, &downstream_id) in e.downstream().iter().enumerate() {
+            if e.downstream()[..idx].contains(&downstream_id) {
+                warnings.push(format!(
+                    "Federate {} lists federate {} as downstream more than once.",
+                    fed.label(),
+                    label(downstream_id)
+                ));
+            }
+        }
+    }
+
+    for fed in enclaves {
+        let e = fed.e();
+        for (&upstream_id, &delay) in e.upstream().iter().zip(e.upstream_delay()) {
+            match delay {
+                Some(delay_ns) if delay != NO_DELAY && !time_resolution.is_aligned(delay_ns) => {
+                    warnings.push(format!(
+                        "The connection from federate {} to federate {} has a delay of {} ns, which is not a multiple of the time resolution of 1 {}.",
+                        label(upstream_id),
+                        fed.label(),
+                        delay_ns,
+                        time_resolution.unit()
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Find the connected subgraphs with a depth-first search.
+    let mut visited = vec![false; number_of_enclaves];
+    let mut subgraphs = Vec::new();
+    for start in 0..number_of_enclaves {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut subgraph = Vec::new();
+        let mut stack = vec![start];
+        while let Some(id) = stack.pop() {
+            subgraph.push(id);
+            for &neighbor in &neighbors[id] {
+                if !visited[neighbor] {
+                    visited[neighbor] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        subgraph.sort();
+        subgraphs.push(subgraph);
+    }
+    // Independent subgraphs are legitimate, and the RTI schedules them
+    // separately (see partition.rs), but one that lists a federate that will
+    // never connect was meant to be connected to the rest.
+    if subgraphs.len() > 1 {
+        for subgraph in &subgraphs {
+            let Some(&(neighbor_id, _)) = subgraph
+                .iter()
+                .find_map(|&id| enclaves[id].unknown_neighbors().first())
+            else {
+                continue;
+            };
+            let labels: Vec<String> = subgraph.iter().map(|&id| enclaves[id].label()).collect();
+            warnings.push(format!(
+                "The subgraph of federates [{}] is disconnected from the rest of the federation and lists federate {}, which will never connect.",
+                labels.join(", "),
+                neighbor_id
+            ));
+        }
+    }
+    warnings
+}
+
+/**
+ * Return a shortest cycle through the given federate along connections without
+ * an "after" delay, or with a zero delay, which only advance the microstep.
+ * The cycle starts and ends with the given federate. Return None if there is no
+ * such cycle.
+ */
+pub fn zero_delay_cycle(enclaves: &[Federate], fed_id: u16) -> Option<Vec<u16>> {
+    let departed = vec![false; enclaves.len()];
+    shortest_zero_delay_cycle(&Topology::federates(enclaves), &departed, fed_id)
+}
+
+/**
+ * Return a shortest cycle through the given federate along connections without
+ * delay, as zero_delay_cycle() does, that does not pass through the federates
+ * that have departed.
+ */
+fn shortest_zero_delay_cycle(
+    federates: &[FederateTopology],
+    departed: &[bool],
+    fed_id: u16,
+) -> Option<Vec<u16>> {
+    let number_of_enclaves = federates.len();
+    // Downstream adjacency lists of the connections that do not advance time.
+    let mut downstream: Vec<Vec<usize>> = vec![Vec::new(); number_of_enclaves];
+    for (id, fed) in federates.iter().enumerate() {
+        if departed[id] {
+            continue;
+        }
+        for &(upstream_id, delay) in &fed.upstream {
+            let advances_time = matches!(delay, Some(d) if d > 0);
+            match usize::try_from(upstream_id) {
+                Ok(upstream)
+                    if upstream < number_of_enclaves && !departed[upstream] && !advances_time =>
+                {
+                    downstream[upstream].push(id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Find the shortest path back to the federate with a breadth-first search.
+    let start = usize::from(fed_id);
+    if start >= number_of_enclaves {
+        return None;
+    }
+    let mut parent: Vec<Option<usize>> = vec![None; number_of_enclaves];
+    let mut queue = VecDeque::from([start]);
+    while let Some(id) = queue.pop_front() {
+        for &next in &downstream[id] {
+            if next == start {
+                let mut cycle = Vec::new();
+                let mut current = id;
+                while current != start {
+                    cycle.push(current as u16);
+                    current = parent[current].unwrap();
+                }
+                cycle.push(fed_id);
+                cycle.reverse();
+                cycle.push(fed_id);
+                return Some(cycle);
+            }
+            if parent[next].is_none() {
+                parent[next] = Some(id);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/**
+ * The neighbor structure that one federate reported.
+ */
+#[derive(PartialEq, Clone, Debug)]
+struct FederateTopology {
+    name: Option<String>,
+    upstream: Vec<(i32, Interval)>,
+    downstream: Vec<i32>,
+}
+
+/**
+ * The validated topology of a federation: the neighbor structures reported by
+ * the federates, the problems found in them, the shortest cycle of connections
+ * without delay through each federate, and the minimum delay along the
+ * connections from each federate to each other one.
+ *
+ * The topology can be written to a file, one item per line, and loaded by a
+ * later run of the same federation, which then skips the analysis if the
+ * federates report the same neighbor structures. The lines are
+ * "federation <ID>", then
+ * "federate <ID> name=<name or -> upstream=<ID>:<delay>,... downstream=<ID>,...",
+ * where the delay is in nanoseconds or "none" for a connection without an
+ * "after" delay, then "warning <text>", "cycle <ID> <ID> ... <ID>", and
+ * "min_delay <from ID> <to ID> <nanoseconds>", and
+ * "metadata <ID> runtime=<runtime> host=<host name> pid=<process ID>" for each
+ * federate that presented metadata. Lines starting with '#' are ignored.
+ *
+ * The metadata is informative only: a topology is reused even if the metadata
+ * of the federates changed, e.g., because they run as other processes.
+ *
+ * Once a federate has departed, i.e., resigned or failed, the cycles, minimum
+ * delays, and downstream closures are recomputed without its connections, so
+ * that they do not go through a federate that no longer forwards messages.
+ * The file always describes the federation as it started.
+ */
+#[derive(PartialEq, Debug)]
+pub struct Topology {
+    federation_id: String,
+    federates: Vec<FederateTopology>,
+    warnings: Vec<String>,
+    zero_delay_cycles: Vec<Option<Vec<u16>>>,
+    min_delays: Vec<Vec<Option<i64>>>,
+    // Not written to the file, as it follows from the neighbor structures.
+    downstream_closures: Vec<Arc<[u16]>>,
+    metadata: Vec<Option<FederateMetadata>>,
+    // Federates that have left the federation for good, whose connections no
+    // longer count.
+    departed: Vec<bool>,
+}
+
+impl Topology {
+    /**
+     * Analyze the neighbor structures reported by the federates.
+     */
+    pub(crate) fn compute(
+        federation_id: &str,
+        enclaves: &[Federate],
+        time_resolution: TimeResolution,
+    ) -> Topology {
+        let federates = Self::federates(enclaves);
+        let departed = vec![false; federates.len()];
+        Topology {
+            federation_id: federation_id.to_string(),
+            downstream_closures: Self::downstream_closures(&federates, &departed),
+            zero_delay_cycles: Self::zero_delay_cycles(&federates, &departed),
+            min_delays: Self::min_delays(&federates, &departed),
+            federates,
+            warnings: validate_topology(enclaves, time_resolution),
+            metadata: Self::metadata(enclaves),
+            departed,
+        }
+    }
+
+    /**
+     * Record that the given federate has left the federation for good and
+     * recompute the cycles, minimum delays, and downstream closures without
+     * its connections. Return false if it had departed already.
+     */
+    pub fn remove_federate(&mut self, fed_id: u16) -> bool {
+        match self.departed.get_mut(usize::from(fed_id)) {
+            Some(departed) if !*departed => *departed = true,
+            _ => return false,
+        }
+        self.zero_delay_cycles = Self::zero_delay_cycles(&self.federates, &self.departed);
+        self.min_delays = Self::min_delays(&self.federates, &self.departed);
+        self.downstream_closures = Self::downstream_closures(&self.federates, &self.departed);
+        true
+    }
+
+    fn zero_delay_cycles(
+        federates: &[FederateTopology],
+        departed: &[bool],
+    ) -> Vec<Option<Vec<u16>>> {
+        (0..federates.len())
+            .map(|id| shortest_zero_delay_cycle(federates, departed, id as u16))
+            .collect()
+    }
+
+    /**
+     * Return the minimum delays between the federates that have not departed
+     * with the Floyd-Warshall algorithm, where a connection without an "after"
+     * delay only advances the microstep.
+     */
+    fn min_delays(federates: &[FederateTopology], departed: &[bool]) -> Vec<Vec<Option<i64>>> {
+        let number_of_enclaves = federates.len();
+        let mut min_delays: Vec<Vec<Option<i64>>> =
+            vec![vec![None; number_of_enclaves]; number_of_enclaves];
+        for (id, row) in min_delays.iter_mut().enumerate() {
+            row[id] = Some(0);
+        }
+        for (to, fed) in federates.iter().enumerate() {
+            if departed[to] {
+                continue;
+            }
+            for &(upstream_id, delay) in &fed.upstream {
+                let Ok(from) = usize::try_from(upstream_id) else {
+                    continue;
+                };
+                if from >= number_of_enclaves || departed[from] {
+                    continue;
+                }
+                let delay = match delay {
+                    Some(delay_ns) if delay != NO_DELAY => delay_ns.max(0),
+                    _ => 0,
+                };
+                if min_delays[from][to].is_none_or(|known| delay < known) {
+                    min_delays[from][to] = Some(delay);
+                }
+            }
+        }
+        for via in 0..number_of_enclaves {
+            let via_row = min_delays[via].clone();
+            for row in min_delays.iter_mut() {
+                let Some(first) = row[via] else {
+                    continue;
+                };
+                for (known, second) in row.iter_mut().zip(&via_row) {
+                    if let Some(second) = second {
+                        let delay = first.saturating_add(*second);
+                        if known.is_none_or(|known| delay < known) {
+                            *known = Some(delay);
+                        }
+                    }
+                }
+            }
+        }
+        min_delays
+    }
+
+    /**
+     * Return, for each federate, the federates downstream of it, directly or
+     * transitively, in the order in which a depth-first traversal of the
+     * downstream connections first reaches them. The federate itself is not
+     * included, even if it is on a cycle. The traversal does not continue
+     * through federates that have departed, but the closure of a departed
+     * federate still holds the federates downstream of it, which are notified
+     * when it departs.
+     */
+    fn downstream_closures(federates: &[FederateTopology], departed: &[bool]) -> Vec<Arc<[u16]>> {
+        fn visit(
+            federates: &[FederateTopology],
+            departed: &[bool],
+            id: usize,
+            visited: &mut [bool],
+            closure: &mut Vec<u16>,
+        ) {
+            visited[id] = true;
+            for &downstream_id in &federates[id].downstream {
+                let Ok(downstream_id) = usize::try_from(downstream_id) else {
+                    continue;
+                };
+                if downstream_id >= federates.len()
+                    || visited[downstream_id]
+                    || departed[downstream_id]
+                {
+                    continue;
+                }
+                closure.push(downstream_id as u16);
+                visit(federates, departed, downstream_id, visited, closure);
+            }
+        }
+        (0..federates.len())
+            .map(|id| {
+                let mut visited = vec![false; federates.len()];
+                let mut closure = Vec::new();
+                visit(federates, departed, id, &mut visited, &mut closure);
+                Arc::from(closure)
+            })
+            .collect()
+    }
+
+    fn federates(enclaves: &[Federate]) -> Vec<FederateTopology> {
+        enclaves
+            .iter()
+            .map(|fed| FederateTopology {
+                name: fed.name().clone(),
+                upstream: fed
+                    .e()
+                    .upstream()
+                    .iter()
+                    .copied()
+                    .zip(fed.e().upstream_delay().iter().copied())
+                    .collect(),
+                downstream: fed.e().downstream().clone(),
+            })
+            .collect()
+    }
+
+    fn metadata(enclaves: &[Federate]) -> Vec<Option<FederateMetadata>> {
+        enclaves.iter().map(|fed| fed.metadata().clone()).collect()
+    }
+
+    /**
+     * Replace the metadata with the one presented by the given federates.
+     * Return true if it changed.
+     */
+    pub(crate) fn update_metadata(&mut self, enclaves: &[Federate]) -> bool {
+        let metadata = Self::metadata(enclaves);
+        let changed = self.metadata != metadata;
+        self.metadata = metadata;
+        changed
+    }
+
+    /**
+     * Return true if the topology was computed from the same neighbor
+     * structures as the ones reported by the given federates.
+     */
+    pub(crate) fn describes(&self, federation_id: &str, enclaves: &[Federate]) -> bool {
+        self.federation_id == federation_id && self.federates == Self::federates(enclaves)
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /**
+     * Return the federates downstream of the given one, directly or
+     * transitively, in the order in which to notify them of a change.
+     */
+    pub fn downstream_closure(&self, fed_id: u16) -> Option<Arc<[u16]>> {
+        self.downstream_closures.get(usize::from(fed_id)).cloned()
+    }
+
+    /**
+     * Return the shortest cycle of connections without delay through the
+     * given federate, as zero_delay_cycle() does.
+     */
+    pub fn zero_delay_cycle(&self, fed_id: u16) -> Option<&Vec<u16>> {
+        self.zero_delay_cycles
+            .get(usize::from(fed_id))
+            .and_then(|cycle| cycle.as_ref())
+    }
+
+    /**
+     * Return the minimum delay in nanoseconds along the connections from one
+     * federate to another, or None if there is no path between them.
+     */
+    pub fn min_delay(&self, from: u16, to: u16) -> Option<i64> {
+        self.min_delays
+            .get(usize::from(from))
+            .and_then(|row| row.get(usize::from(to)).copied().flatten())
+    }
+
+    /**
+     * Return the topology in the format of a topology file.
+     */
+    pub fn to_text(&self) -> String {
+        let delay = |delay: Interval| match delay {
+            _ if delay == NO_DELAY => String::from("none"),
+            Some(delay_ns) => delay_ns.to_string(),
+            None => String::from("unknown"),
+        };
+        let list = |ids: Vec<String>| {
+            if ids.is_empty() {
+                String::from("-")
+            } else {
+                ids.join(",")
+            }
+        };
+        let mut text = String::from("# Topology of the federation, written by the RTI.\n");
+        text.push_str(&format!("federation {}\n", self.federation_id));
+        for (id, fed) in self.federates.iter().enumerate() {
+            text.push_str(&format!(
+                "federate {} name={} upstream={} downstream={}\n",
+                id,
+                fed.name.as_deref().unwrap_or("-"),
+                list(
+                    fed.upstream
+                        .iter()
+                        .map(|&(upstream_id, upstream_delay)| {
+                            format!("{}:{}", upstream_id, delay(upstream_delay))
+                        })
+                        .collect()
+                ),
+                list(fed.downstream.iter().map(|id| id.to_string()).collect())
+            ));
+        }
+        for warning in &self.warnings {
+            text.push_str(&format!("warning {}\n", warning));
+        }
+        for cycle in self.zero_delay_cycles.iter().flatten() {
+            let ids: Vec<String> = cycle.iter().map(|id| id.to_string()).collect();
+            text.push_str(&format!("cycle {}\n", ids.join(" ")));
+        }
+        for (from, row) in self.min_delays.iter().enumerate() {
+            for (to, min_delay) in row.iter().enumerate() {
+                if let (Some(min_delay), true) = (min_delay, from != to) {
+                    text.push_str(&format!("min_delay {} {} {}\n", from, to, min_delay));
+                }
+            }
+        }
+        for (id, metadata) in self.metadata.iter().enumerate() {
+            if let Some(metadata) = metadata {
+                text.push_str(&format!("metadata {} {}\n", id, metadata.to_text()));
+            }
+        }
+        text
+    }
+
+    /**
+     * Parse a topology file. Return a description of the first invalid line,
+     * if any.
+     */
+    pub fn parse(text: &str) -> Result<Topology, String> {
+        let mut topology = Topology {
+            federation_id: String::new(),
+            federates: Vec::new(),
+            warnings: Vec::new(),
+            zero_delay_cycles: Vec::new(),
+            min_delays: Vec::new(),
+            downstream_closures: Vec::new(),
+            metadata: Vec::new(),
+            departed: Vec::new(),
+        };
+        let mut metadata = Vec::new();
+        let mut cycles = Vec::new();
+        let mut min_delays = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let invalid = || format!("line {}: invalid line '{}'", number + 1, line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match keyword {
+                "federation" => topology.federation_id = rest.to_string(),
+                "federate" => {
+                    let fed = Self::parse_federate(rest).ok_or_else(invalid)?;
+                    if fed.0 != topology.federates.len() {
+                        return Err(invalid());
+                    }
+                    topology.federates.push(fed.1);
+                }
+                "warning" => topology.warnings.push(rest.to_string()),
+                "cycle" => {
+                    let cycle: Vec<u16> = rest
+                        .split_whitespace()
+                        .map(|id| id.parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| invalid())?;
+                    if cycle.is_empty() {
+                        return Err(invalid());
+                    }
+                    cycles.push(cycle);
+                }
+                "min_delay" => {
+                    let values: Vec<&str> = rest.split_whitespace().collect();
+                    let [from, to, min_delay] = values.as_slice() else {
+                        return Err(invalid());
+                    };
+                    min_delays.push((
+                        from.parse::<usize>().map_err(|_| invalid())?,
+                        to.parse::<usize>().map_err(|_| invalid())?,
+                        min_delay.parse::<i64>().map_err(|_| invalid())?,
+                    ));
+                }
+                "metadata" => {
+                    let (id, rest) = rest.split_once(' ').ok_or_else(invalid)?;
+                    metadata.push((
+                        id.parse::<usize>().map_err(|_| invalid())?,
+                        FederateMetadata::parse(rest).ok_or_else(invalid)?,
+                    ));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        let number_of_enclaves = topology.federates.len();
+        topology.zero_delay_cycles = vec![None; number_of_enclaves];
+        for cycle in cycles {
+            match topology.zero_delay_cycles.get_mut(usize::from(cycle[0])) {
+                Some(slot) => *slot = Some(cycle),
+                None => return Err(format!("cycle through unknown federate {}", cycle[0])),
+            }
+        }
+        topology.min_delays = vec![vec![None; number_of_enclaves]; number_of_enclaves];
+        for (id, row) in topology.min_delays.iter_mut().enumerate() {
+            row[id] = Some(0);
+        }
+        for (from, to, min_delay) in min_delays {
+            if from >= number_of_enclaves || to >= number_of_enclaves {
+                return Err(format!(
+                    "minimum delay between unknown federates {} and {}",
+                    from, to
+                ));
+            }
+            topology.min_delays[from][to] = Some(min_delay);
+        }
+        topology.metadata = vec![None; number_of_enclaves];
+        for (id, fed_metadata) in metadata {
+            match topology.metadata.get_mut(id) {
+                Some(slot) => *slot = Some(fed_metadata),
+                None => return Err(format!("metadata of unknown federate {}", id)),
+            }
+        }
+        topology.departed = vec![false; number_of_enclaves];
+        topology.downstream_closures =
+            Self::downstream_closures(&topology.federates, &topology.departed);
+        Ok(topology)
+    }
+
+    /**
+     * Parse the rest of a "federate" line into the ID and the neighbor
+     * structure of the federate.
+     */
+    fn parse_federate(rest: &str) -> Option<(usize, FederateTopology)> {
+        let words: Vec<&str> = rest.split_whitespace().collect();
+        let [id, name, upstream, downstream] = words.as_slice() else {
+            return None;
+        };
+        let list = |value: &str| -> Vec<String> {
+            if value == "-" {
+                Vec::new()
+            } else {
+                value.split(',').map(String::from).collect()
+            }
+        };
+        let name = name.strip_prefix("name=")?;
+        let upstream = list(upstream.strip_prefix("upstream=")?)
+            .iter()
+            .map(|item| {
+                let (upstream_id, delay) = item.split_once(':')?;
+                let delay = match delay {
+                    "none" => NO_DELAY,
+                    "unknown" => None,
+                    delay => Some(delay.parse().ok()?),
+                };
+                Some((upstream_id.parse().ok()?, delay))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let downstream = list(downstream.strip_prefix("downstream=")?)
+            .iter()
+            .map(|id| id.parse().ok())
+            .collect::<Option<Vec<_>>>()?;
+        Some((
+            id.parse().ok()?,
+            FederateTopology {
+                name: (name != "-").then(|| name.to_string()),
+                upstream,
+                downstream,
+            },
+        ))
+    }
+}
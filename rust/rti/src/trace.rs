@@ -0,0 +1,275 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Leveled output of the RTI, with rate-limited trace output for paths
+ * whose output grows with the number of pairs of federates.
+ *
+ * The error!, warn!, info!, debug!, and trace! macros print a line like
+ * println! if the log level is at least theirs and do not evaluate their
+ * arguments otherwise, so that the messages exchanged with federates and the
+ * grants cost nothing in large federations unless they are asked for. The
+ * debug_for! macro prints them at the log level of the group of the federate,
+ * if it has one.
+ */
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::log_sink::{log_line, Priority};
+
+/**
+ * How much the RTI prints.
+ */
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+pub enum LogLevel {
+    /// Print only the failures of the RTI and of federates.
+    Error,
+    /// Also print the warnings, e.g., about rejected federates and dropped
+    /// messages.
+    Warn,
+    /// Also print the connections and resignations of federates and the other
+    /// events of the federation.
+    Info,
+    /// Also print the messages exchanged with federates and the grants.
+    Debug,
+    /// Also print the intermediate steps of the grant computations, such as
+    /// the next event tag of every upstream federate of every federate.
+    Trace,
+}
+
+impl LogLevel {
+    pub fn from_name(name: &str) -> Option<LogLevel> {
+        match name {
+            "error" => Some(LogLevel::Error),
+            "warn" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+
+    /**
+     * The priority of the lines of this level in syslog and in the journal.
+     */
+    pub(crate) fn priority(self) -> Priority {
+        match self {
+            LogLevel::Error => Priority::Error,
+            LogLevel::Warn => Priority::Warning,
+            LogLevel::Info => Priority::Info,
+            LogLevel::Debug | LogLevel::Trace => Priority::Debug,
+        }
+    }
+}
+
+/**
+ * Default maximum number of trace lines printed per second.
+ */
+pub const DEFAULT_TRACE_LINES_PER_SECOND: u64 = 100;
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static TRACE_LINES_PER_SECOND: AtomicU64 = AtomicU64::new(DEFAULT_TRACE_LINES_PER_SECOND);
+
+/**
+ * Log levels of the federates whose group sets one, by federate ID, which
+ * replace the log level for the lines about them printed with debug_for!.
+ */
+static FEDERATE_LOG_LEVELS: RwLock<Option<HashMap<u16, LogLevel>>> = RwLock::new(None);
+
+/**
+ * Whether any federate has a log level in FEDERATE_LOG_LEVELS, so that the
+ * lines about federates do not take its lock in the common case of none.
+ */
+static FEDERATE_LOG_LEVELS_SET: AtomicBool = AtomicBool::new(false);
+
+/**
+ * The start of the current one-second window, the number of lines printed in
+ * it, and the number of lines suppressed in it.
+ */
+static TRACE_WINDOW: Mutex<Option<(Instant, u64, u64)>> = Mutex::new(None);
+
+/**
+ * Set the log level and the maximum number of trace lines per second
+ * (0 means no limit) for the whole process.
+ */
+pub fn configure(log_level: LogLevel, trace_lines_per_second: u64) {
+    LOG_LEVEL.store(log_level as u8, Ordering::Relaxed);
+    TRACE_LINES_PER_SECOND.store(trace_lines_per_second, Ordering::Relaxed);
+}
+
+/**
+ * Set the log levels of federates for the whole process, see
+ * FEDERATE_LOG_LEVELS.
+ */
+pub fn configure_federates(log_levels: HashMap<u16, LogLevel>) {
+    let mut federate_log_levels = FEDERATE_LOG_LEVELS.write().unwrap();
+    FEDERATE_LOG_LEVELS_SET.store(!log_levels.is_empty(), Ordering::Release);
+    *federate_log_levels = (!log_levels.is_empty()).then_some(log_levels);
+}
+
+/**
+ * Return true if lines of the given level are printed.
+ */
+pub fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+/**
+ * Return true if lines of the given level about the given federate are
+ * printed, at the log level of the federate if it has one.
+ */
+pub fn enabled_for(level: LogLevel, fed_id: u16) -> bool {
+    if !FEDERATE_LOG_LEVELS_SET.load(Ordering::Acquire) {
+        return enabled(level);
+    }
+    let federate_level = FEDERATE_LOG_LEVELS
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|log_levels| log_levels.get(&fed_id).copied());
+    match federate_level {
+        Some(federate_level) => level <= federate_level,
+        None => enabled(level),
+    }
+}
+
+/**
+ * Return true if a trace line may be printed now. Lines beyond the rate limit
+ * are counted, and their number is reported when the next window starts, so
+ * that enabling tracing does not slow down large federations dramatically.
+ */
+pub fn trace_allowed() -> bool {
+    if !enabled(LogLevel::Trace) {
+        return false;
+    }
+    let limit = TRACE_LINES_PER_SECOND.load(Ordering::Relaxed);
+    if limit == 0 {
+        return true;
+    }
+    let now = Instant::now();
+    let mut window = TRACE_WINDOW.lock().unwrap();
+    match window.as_mut() {
+        Some((start, printed, suppressed))
+            if now.duration_since(*start) < Duration::from_secs(1) =>
+        {
+            if *printed < limit {
+                *printed += 1;
+                true
+            } else {
+                *suppressed += 1;
+                false
+            }
+        }
+        _ => {
+            if let Some((_, _, suppressed)) = *window {
+                if suppressed > 0 {
+                    log_line(
+                        Some(Priority::Debug),
+                        format_args!("RTI: Suppressed {} trace lines.", suppressed),
+                    );
+                }
+            }
+            *window = Some((now, 1, 0));
+            true
+        }
+    }
+}
+
+/**
+ * Print a line with the priority of the given level to the log sink if the
+ * log level is at least that level, as the macros below do. This is for the
+ * RTI binary and the applications that embed the RTI, which cannot use the
+ * macros.
+ */
+pub fn log_message(level: LogLevel, args: fmt::Arguments) {
+    if enabled(level) {
+        log_line(Some(level.priority()), args);
+    }
+}
+
+/**
+ * Print a line like println! if the log level is LogLevel::Trace and the rate
+ * limit has not been reached. The arguments are not evaluated otherwise.
+ */
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if $crate::trace::trace_allowed() {
+            $crate::log_sink::log_line(
+                Some($crate::log_sink::Priority::Debug),
+                format_args!($($arg)*),
+            );
+        }
+    };
+}
+
+/**
+ * Print a line like println! with the priority of the given level if the log
+ * level is at least that level. The arguments are not evaluated otherwise.
+ */
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::trace::enabled($level) {
+            $crate::log_sink::log_line(Some($level.priority()), format_args!($($arg)*));
+        }
+    };
+}
+
+/**
+ * Print a failure of the RTI or of a federate.
+ */
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::trace::log!($crate::trace::LogLevel::Error, $($arg)*)
+    };
+}
+
+/**
+ * Print a warning.
+ */
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::trace::log!($crate::trace::LogLevel::Warn, $($arg)*)
+    };
+}
+
+/**
+ * Print an event of the federation.
+ */
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::trace::log!($crate::trace::LogLevel::Info, $($arg)*)
+    };
+}
+
+/**
+ * Print a message exchanged with a federate or a grant.
+ */
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::trace::log!($crate::trace::LogLevel::Debug, $($arg)*)
+    };
+}
+
+/**
+ * Print a message exchanged with the given federate or a grant to it, at the
+ * log level of the federate (see enabled_for()).
+ */
+macro_rules! debug_for {
+    ($fed_id:expr, $($arg:tt)*) => {
+        if $crate::trace::enabled_for($crate::trace::LogLevel::Debug, $fed_id) {
+            $crate::log_sink::log_line(
+                Some($crate::trace::LogLevel::Debug.priority()),
+                format_args!($($arg)*),
+            );
+        }
+    };
+}
+
+// The macro is named warning because warn would be ambiguous with the builtin
+// attribute here.
+pub(crate) use {debug, debug_for, error, info, log, trace, warning as warn};
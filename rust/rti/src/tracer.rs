@@ -0,0 +1,202 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Recording of the interactions with federates in a binary trace file
+ * (see lft.rs), which the LF trace tools such as trace_to_csv and
+ * trace_to_chrome can analyze as traces of the C RTI.
+ */
+use std::io::{BufWriter, Write};
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::clock::PhysicalClock;
+use crate::encryption::{self, OutputKey};
+use crate::lft::{TraceEvent, TraceHeader, TraceRecord};
+use crate::memory::MemoryBudget;
+use crate::tag::Tag;
+use crate::trace::{info, warn};
+
+/**
+ * Number of records after which the flush thread writes them, as
+ * TRACE_BUFFER_CAPACITY in the C runtime.
+ */
+const TRACE_BUFFER_CAPACITY: usize = 2048;
+
+/**
+ * Period after which the flush thread writes the records even if there are
+ * fewer, so that the trace of a long run is not lost if the RTI is killed.
+ */
+const FLUSH_PERIOD: Duration = Duration::from_secs(1);
+
+/**
+ * Size of one buffered record, which is charged to the trace budget.
+ */
+const TRACE_RECORD_SIZE: usize = mem::size_of::<TraceRecord>();
+
+/**
+ * Records that have not been written yet.
+ */
+#[derive(Default)]
+struct TraceBuffer {
+    records: Vec<TraceRecord>,
+    // The header, which is known once the start time is, until it is written.
+    header: Option<TraceHeader>,
+    header_written: bool,
+    stopped: bool,
+}
+
+/**
+ * Collects trace records while the lock of the RTI state is held and hands
+ * them to a flush thread, which writes them to the trace file.
+ */
+pub struct Tracer {
+    buffer: Mutex<TraceBuffer>,
+    filled: Condvar,
+    clock: Arc<PhysicalClock>,
+    // Budget of the buffered records, beyond which records are dropped, or None.
+    budget: Option<Arc<MemoryBudget>>,
+}
+
+impl Tracer {
+    /**
+     * Create the trace file at the given path, encrypted under the given key
+     * if any, and start the thread that writes to it. Records that do not fit
+     * in the given budget until the thread has written the buffer are dropped.
+     * Return None if the file cannot be created.
+     */
+    pub fn start(
+        path: &str,
+        clock: Arc<PhysicalClock>,
+        key: Option<&OutputKey>,
+        budget: Option<Arc<MemoryBudget>>,
+    ) -> Option<(Arc<Tracer>, thread::JoinHandle<()>)> {
+        let file = match encryption::create(path, key) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!(
+                    "RTI: WARNING: Failed to create the trace file {}: {}. Tracing is disabled.",
+                    path, e
+                );
+                return None;
+            }
+        };
+        info!("RTI: Tracing to {}.", path);
+        let tracer = Arc::new(Tracer {
+            buffer: Mutex::new(TraceBuffer::default()),
+            filled: Condvar::new(),
+            clock,
+            budget,
+        });
+        let handle =
+            Self::start_flush_thread(tracer.clone(), BufWriter::new(file), path.to_string());
+        Some((tracer, handle))
+    }
+
+    /**
+     * Record an event with the given source and destination IDs (-1 for the
+     * RTI) at the current physical time. An event without a tag is recorded
+     * with NEVER, as in the C RTI.
+     */
+    pub fn tracepoint(&self, event: TraceEvent, src_id: i32, dst_id: i32, tag: Option<&Tag>) {
+        let tag = tag.cloned().unwrap_or_else(Tag::never_tag);
+        let record = TraceRecord::new(event, src_id, dst_id, tag, self.clock.now());
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.stopped {
+            return;
+        }
+        if let Some(budget) = &self.budget {
+            if !budget.try_reserve(TRACE_RECORD_SIZE) {
+                budget.record_eviction(1);
+                self.filled.notify_one();
+                return;
+            }
+        }
+        buffer.records.push(record);
+        if buffer.records.len() >= TRACE_BUFFER_CAPACITY {
+            self.filled.notify_one();
+        }
+    }
+
+    /**
+     * Set the header of the trace once the start time is known. Records are
+     * kept until then because the header comes first in the file.
+     */
+    pub fn set_header(&self, header: TraceHeader) {
+        let mut buffer = self.buffer.lock().unwrap();
+        if !buffer.header_written && buffer.header.is_none() {
+            buffer.header = Some(header);
+        }
+    }
+
+    /**
+     * Make the flush thread write the remaining records and exit. Later events
+     * are not recorded. If the start time has never been known, the header has
+     * a start time of 0.
+     */
+    pub fn stop(&self) {
+        let mut buffer = self.buffer.lock().unwrap();
+        if !buffer.header_written && buffer.header.is_none() {
+            buffer.header = Some(TraceHeader::new(0, Vec::new()));
+        }
+        buffer.stopped = true;
+        self.filled.notify_one();
+    }
+
+    fn start_flush_thread(
+        tracer: Arc<Tracer>,
+        mut writer: BufWriter<Box<dyn Write + Send>>,
+        path: String,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || loop {
+            let (header, records, stopped) = {
+                let mut buffer = tracer.buffer.lock().unwrap();
+                let header_known = buffer.header_written || buffer.header.is_some();
+                if (buffer.records.len() < TRACE_BUFFER_CAPACITY || !header_known)
+                    && !buffer.stopped
+                {
+                    buffer = tracer.filled.wait_timeout(buffer, FLUSH_PERIOD).unwrap().0;
+                }
+                if !buffer.header_written && buffer.header.is_none() {
+                    continue;
+                }
+                let header = buffer.header.take();
+                buffer.header_written = true;
+                if let Some(budget) = &tracer.budget {
+                    budget.release(buffer.records.len() * TRACE_RECORD_SIZE);
+                }
+                (header, mem::take(&mut buffer.records), buffer.stopped)
+            };
+            let mut written = match header {
+                Some(header) => header.write(&mut writer),
+                None => Ok(()),
+            };
+            if written.is_ok() && !records.is_empty() {
+                written = TraceRecord::write_block(&mut writer, &records);
+            }
+            if written.is_ok() {
+                written = writer.flush();
+            }
+            if let Err(e) = written {
+                warn!(
+                    "RTI: WARNING: Failed to write to the trace file {}: {}. Tracing is disabled.",
+                    path, e
+                );
+                let mut buffer = tracer.buffer.lock().unwrap();
+                buffer.stopped = true;
+                if let Some(budget) = &tracer.budget {
+                    budget.release(buffer.records.len() * TRACE_RECORD_SIZE);
+                }
+                buffer.records.clear();
+                return;
+            }
+            if stopped {
+                return;
+            }
+        })
+    }
+}
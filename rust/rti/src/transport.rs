@@ -0,0 +1,285 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Byte streams that connect the RTI to federates.
+ */
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use crate::multiplex::MultiplexedStream;
+#[cfg(feature = "tls")]
+use crate::tls::TlsStream;
+
+/**
+ * A byte stream from which the RTI reads the messages of one federate and to
+ * which it writes the replies. Handlers take this trait rather than TcpStream
+ * so that they can be exercised with a MockStream.
+ */
+pub trait Transport: Read + Write {
+    /**
+     * Signal the other end that no further reads or writes will happen.
+     */
+    fn shutdown(&mut self) -> io::Result<()>;
+}
+
+impl Transport for TcpStream {
+    fn shutdown(&mut self) -> io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+/**
+ * Connection of the RTI to a federate, over plain TCP or, with --tls_cert and
+ * --tls_key, over TLS, possibly shared with other federates in the same
+ * process. Like a TcpStream, it can be cloned, so that other threads write to
+ * the federate while its scheduling thread reads from it.
+ */
+pub enum FederateStream {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+    Multiplexed(MultiplexedStream),
+}
+
+impl FederateStream {
+    pub fn try_clone(&self) -> io::Result<FederateStream> {
+        match self {
+            FederateStream::Tcp(stream) => stream.try_clone().map(FederateStream::Tcp),
+            #[cfg(feature = "tls")]
+            FederateStream::Tls(stream) => stream.try_clone().map(FederateStream::Tls),
+            FederateStream::Multiplexed(stream) => {
+                stream.try_clone().map(FederateStream::Multiplexed)
+            }
+        }
+    }
+
+    /**
+     * Return the TCP socket of the connection, e.g., to set socket options.
+     */
+    pub fn socket(&self) -> &TcpStream {
+        match self {
+            FederateStream::Tcp(stream) => stream,
+            #[cfg(feature = "tls")]
+            FederateStream::Tls(stream) => stream.socket(),
+            FederateStream::Multiplexed(stream) => stream.socket(),
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket().peer_addr()
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            FederateStream::Multiplexed(stream) => stream.set_read_timeout(timeout),
+            _ => self.socket().set_read_timeout(timeout),
+        }
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket().set_write_timeout(timeout)
+    }
+
+    /**
+     * Read bytes without removing them from the stream, as TcpStream::peek.
+     * Over TLS, these are the decrypted bytes.
+     */
+    pub fn peek(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            FederateStream::Tcp(stream) => stream.peek(buffer),
+            #[cfg(feature = "tls")]
+            FederateStream::Tls(stream) => stream.peek(buffer),
+            FederateStream::Multiplexed(stream) => stream.peek(buffer),
+        }
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            FederateStream::Tcp(stream) => stream.shutdown(how),
+            #[cfg(feature = "tls")]
+            FederateStream::Tls(stream) => stream.shutdown(how),
+            FederateStream::Multiplexed(stream) => stream.shutdown(how),
+        }
+    }
+}
+
+impl Read for &FederateStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            FederateStream::Tcp(stream) => (&*stream).read(buffer),
+            #[cfg(feature = "tls")]
+            FederateStream::Tls(stream) => (&*stream).read(buffer),
+            FederateStream::Multiplexed(stream) => (&*stream).read(buffer),
+        }
+    }
+}
+
+impl Write for &FederateStream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        match self {
+            FederateStream::Tcp(stream) => (&*stream).write(buffer),
+            #[cfg(feature = "tls")]
+            FederateStream::Tls(stream) => (&*stream).write(buffer),
+            FederateStream::Multiplexed(stream) => (&*stream).write(buffer),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            FederateStream::Tcp(stream) => (&*stream).flush(),
+            #[cfg(feature = "tls")]
+            FederateStream::Tls(stream) => (&*stream).flush(),
+            FederateStream::Multiplexed(stream) => (&*stream).flush(),
+        }
+    }
+}
+
+impl Read for FederateStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        (&*self).read(buffer)
+    }
+}
+
+impl Write for FederateStream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        (&*self).write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self).flush()
+    }
+}
+
+impl Transport for FederateStream {
+    fn shutdown(&mut self) -> io::Result<()> {
+        FederateStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+/**
+ * A stream to a federate whose reads fail with ErrorKind::TimedOut once a
+ * deadline has passed, however the bytes are spread over the reads. The read
+ * timeout of the stream is changed by every read, so it has to be reset
+ * afterwards.
+ */
+pub struct DeadlineStream<'a> {
+    stream: &'a mut FederateStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream<'_> {
+    pub fn new(stream: &mut FederateStream, deadline: Instant) -> DeadlineStream<'_> {
+        DeadlineStream { stream, deadline }
+    }
+}
+
+impl Read for DeadlineStream<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::from(io::ErrorKind::TimedOut));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buffer)
+    }
+}
+
+impl Write for DeadlineStream<'_> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.stream.write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Transport for DeadlineStream<'_> {
+    fn shutdown(&mut self) -> io::Result<()> {
+        FederateStream::shutdown(self.stream, Shutdown::Both)
+    }
+}
+
+/**
+ * An in-memory stream that returns scripted bytes to reads and records all
+ * writes. Reads return end-of-file once the scripted bytes are consumed, as a
+ * federate that closed its socket would.
+ */
+pub struct MockStream {
+    reads: VecDeque<u8>,
+    written: Vec<u8>,
+    shut_down: bool,
+}
+
+impl MockStream {
+    pub fn new() -> MockStream {
+        MockStream {
+            reads: VecDeque::new(),
+            written: Vec::new(),
+            shut_down: false,
+        }
+    }
+
+    /**
+     * Append bytes to be returned by subsequent reads.
+     */
+    pub fn push_read(&mut self, bytes: &[u8]) {
+        self.reads.extend(bytes);
+    }
+
+    /**
+     * All bytes written to this stream so far.
+     */
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+}
+
+impl Default for MockStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.shut_down {
+            return Ok(0);
+        }
+        let size = buffer.len().min(self.reads.len());
+        for (dst, src) in buffer.iter_mut().zip(self.reads.drain(..size)) {
+            *dst = src;
+        }
+        Ok(size)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        if self.shut_down {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+        }
+        self.written.extend_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for MockStream {
+    fn shutdown(&mut self) -> io::Result<()> {
+        self.shut_down = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Reusable sets of visited federates for traversals of the federation
+ * graph.
+ */
+use std::cell::RefCell;
+use std::mem;
+
+/**
+ * Visit marks, cleared in constant time by stamping each visited federate
+ * with the generation of the current traversal.
+ */
+#[derive(Default)]
+struct Stamps {
+    stamps: Vec<u32>,
+    generation: u32,
+}
+
+thread_local! {
+    // Stamps not in use by a traversal on this thread. Traversals nest (e.g.,
+    // a downstream traversal computes grants, which traverse upstream), so
+    // each one takes its own.
+    static FREE_STAMPS: RefCell<Vec<Stamps>> = const { RefCell::new(Vec::new()) };
+}
+
+/**
+ * Set of visited federates for one traversal. Its memory is taken from, and
+ * returned to, a pool of the current thread, so traversals do not allocate
+ * once the pool has warmed up.
+ */
+pub struct VisitedSet {
+    stamps: Stamps,
+}
+
+impl VisitedSet {
+    /**
+     * Return an empty set for a federation of the given number of federates.
+     */
+    pub fn new(len: usize) -> VisitedSet {
+        let mut stamps = FREE_STAMPS
+            .with(|free| free.borrow_mut().pop())
+            .unwrap_or_default();
+        if stamps.stamps.len() < len {
+            stamps.stamps.resize(len, 0);
+        }
+        stamps.generation = stamps.generation.wrapping_add(1);
+        if stamps.generation == 0 {
+            // Stamps of earlier traversals could match again after wrapping.
+            stamps.stamps.fill(0);
+            stamps.generation = 1;
+        }
+        VisitedSet { stamps }
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.stamps.stamps[id] == self.stamps.generation
+    }
+
+    pub fn insert(&mut self, id: usize) {
+        self.stamps.stamps[id] = self.stamps.generation;
+    }
+}
+
+impl Drop for VisitedSet {
+    fn drop(&mut self) {
+        let stamps = mem::take(&mut self.stamps);
+        // The pool is gone if the thread is exiting.
+        let _ = FREE_STAMPS.try_with(|free| free.borrow_mut().push(stamps));
+    }
+}
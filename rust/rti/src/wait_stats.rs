@@ -0,0 +1,130 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Statistics of the waits of the RTI threads on condition variables,
+ * such as the wait for the start time to be sent, by what is held back and
+ * for which federate.
+ *
+ * A stall at startup usually means that one federate proposes its start time
+ * long after the others, so the times at which the federates propose theirs
+ * are recorded as well, and the report names the last one.
+ */
+use std::collections::BTreeMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::federate::federate_label;
+
+/**
+ * Names of the waits for the start time to be sent, by what is held back.
+ */
+pub const START_TIME_WAIT_TAGGED_MESSAGE: &str = "a tagged message";
+pub const START_TIME_WAIT_PORT_ABSENT: &str = "a port absent message";
+
+#[derive(Default)]
+struct WaitSiteStats {
+    waits: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+#[derive(Default)]
+pub struct WaitStats {
+    sites: BTreeMap<(&'static str, u16), WaitSiteStats>,
+    start_time_proposals: Vec<(u16, Instant)>,
+    start_time_sent: Option<Instant>,
+}
+
+impl WaitStats {
+    pub fn new() -> WaitStats {
+        WaitStats::default()
+    }
+
+    /**
+     * Record a wait of the given duration before sending what the given name
+     * describes to the given federate.
+     */
+    pub fn record(&mut self, name: &'static str, fed_id: u16, wait: Duration) {
+        let stats = self.sites.entry((name, fed_id)).or_default();
+        stats.waits += 1;
+        stats.total_wait += wait;
+        stats.max_wait = stats.max_wait.max(wait);
+    }
+
+    /**
+     * Record that the given federate has proposed its start time now.
+     */
+    pub fn record_start_time_proposal(&mut self, fed_id: u16) {
+        self.start_time_proposals.push((fed_id, Instant::now()));
+    }
+
+    /**
+     * Record that the start time has been sent to the federates now.
+     */
+    pub fn record_start_time_sent(&mut self) {
+        self.start_time_sent.get_or_insert_with(Instant::now);
+    }
+
+    /**
+     * Return a description of when the federates proposed their start times,
+     * naming the last one, or None if the start time has not been sent.
+     */
+    pub fn describe_start_time_barrier(&self) -> Option<String> {
+        let sent = self.start_time_sent?;
+        let (first_id, first) = self.start_time_proposals.first()?;
+        let (last_id, last) = self
+            .start_time_proposals
+            .iter()
+            .rev()
+            .find(|(_, proposed)| *proposed <= sent)?;
+        Some(format!(
+            "federate {} proposed its start time last, {} ms after federate {} proposed one first, and the start time was sent {} ms after the first proposal",
+            federate_label(*last_id),
+            last.duration_since(*first).as_millis(),
+            federate_label(*first_id),
+            sent.duration_since(*first).as_millis()
+        ))
+    }
+
+    /**
+     * Return one line for each wait site, by decreasing total wait.
+     */
+    pub fn report(&self) -> Vec<String> {
+        let mut sites: Vec<_> = self.sites.iter().collect();
+        sites.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total_wait));
+        sites
+            .into_iter()
+            .map(|((name, fed_id), stats)| {
+                format!(
+                    "{} to federate {}: {} wait(s) for the start time, {} us in total, {} us at most",
+                    name,
+                    federate_label(*fed_id),
+                    stats.waits,
+                    stats.total_wait.as_micros(),
+                    stats.max_wait.as_micros()
+                )
+            })
+            .collect()
+    }
+}
+
+/**
+ * Wait until the flag guarded by the given mutex is set and the condition
+ * variable notified. Return how long the caller waited, or None if the flag
+ * was set already.
+ */
+pub fn wait_until_set(flag: &(Mutex<bool>, Condvar)) -> Option<Duration> {
+    let (lock, condvar) = flag;
+    let mut notified = lock.lock().unwrap();
+    if *notified {
+        return None;
+    }
+    let start = Instant::now();
+    while !*notified {
+        notified = condvar.wait(notified).unwrap();
+    }
+    Some(start.elapsed())
+}
@@ -0,0 +1,196 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Dumps of the bytes exchanged with selected federates, for debugging
+ * the interoperability with federates written in other languages.
+ */
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::encryption::{self, OutputKey};
+use crate::trace::info;
+use crate::transport::Transport;
+
+/**
+ * Direction of the bytes in a dump, as seen from the RTI.
+ */
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Direction {
+    Received,
+    Sent,
+}
+
+/**
+ * Bytes read from or written to a federate, with the time at which they were
+ * read or written.
+ */
+struct Record {
+    time: SystemTime,
+    direction: Direction,
+    bytes: Vec<u8>,
+}
+
+impl Record {
+    fn new(direction: Direction, bytes: &[u8]) -> Record {
+        Record {
+            time: SystemTime::now(),
+            direction,
+            bytes: bytes.to_vec(),
+        }
+    }
+}
+
+/**
+ * File to which all bytes exchanged with one federate are written, one read or
+ * write per line: "<seconds>.<microseconds> <RX|TX> <length> <bytes in hex>",
+ * where the time is since the UNIX epoch, as in pcap files.
+ */
+pub struct WireDump {
+    file: Mutex<Box<dyn Write + Send>>,
+}
+
+impl WireDump {
+    /**
+     * Create the dump file of the federate with the given ID and label in the
+     * given directory, encrypted under the given key if any.
+     */
+    pub fn create(
+        directory: &str,
+        fed_id: u16,
+        label: &str,
+        key: Option<&OutputKey>,
+    ) -> io::Result<WireDump> {
+        let path = Path::new(directory).join(format!("federate_{}.wiredump", fed_id));
+        let mut file = encryption::create(&path.to_string_lossy(), key)?;
+        writeln!(
+            file,
+            "# Bytes exchanged with federate {}: <time> <RX|TX> <length> <bytes in hex>",
+            label
+        )?;
+        info!(
+            "RTI: Dumping the bytes exchanged with federate {} to {}.",
+            label,
+            path.display()
+        );
+        Ok(WireDump {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, direction: Direction, bytes: &[u8]) {
+        self.write_record(&Record::new(direction, bytes));
+    }
+
+    fn write_record(&self, record: &Record) {
+        if record.bytes.is_empty() {
+            return;
+        }
+        let since_epoch = record.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let hex: String = record
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let line = format!(
+            "{}.{:06} {} {} {}\n",
+            since_epoch.as_secs(),
+            since_epoch.subsec_micros(),
+            match record.direction {
+                Direction::Received => "RX",
+                Direction::Sent => "TX",
+            },
+            record.bytes.len(),
+            hex
+        );
+        // NOTE: Ignore errors because the dump is only a debugging aid. The
+        // flush makes an encrypted dump readable up to this record.
+        let mut file = self.file.lock().unwrap();
+        let _ = file.write_all(line.as_bytes()).and_then(|_| file.flush());
+    }
+}
+
+/**
+ * A transport that records the bytes read from and written to the wrapped
+ * one, either into a dump or, until the federate on the other end is known,
+ * in memory.
+ */
+pub struct DumpStream<'a, T: Transport> {
+    stream: &'a mut T,
+    dump: Option<Arc<WireDump>>,
+    // Records kept until the federate is known, or None if not recording.
+    pending: Option<Vec<Record>>,
+}
+
+impl<'a, T: Transport> DumpStream<'a, T> {
+    /**
+     * Wrap a stream whose bytes are written to the given dump, if any.
+     */
+    pub fn new(stream: &'a mut T, dump: Option<Arc<WireDump>>) -> DumpStream<'a, T> {
+        DumpStream {
+            stream,
+            dump,
+            pending: None,
+        }
+    }
+
+    /**
+     * Wrap a stream whose bytes are kept in memory until finish() is called.
+     */
+    pub fn recording(stream: &'a mut T) -> DumpStream<'a, T> {
+        DumpStream {
+            stream,
+            dump: None,
+            pending: Some(Vec::new()),
+        }
+    }
+
+    /**
+     * Write the bytes kept in memory to the given dump, or discard them.
+     */
+    pub fn finish(self, dump: Option<&WireDump>) {
+        if let (Some(dump), Some(pending)) = (dump, &self.pending) {
+            for record in pending {
+                dump.write_record(record);
+            }
+        }
+    }
+
+    fn record(&mut self, direction: Direction, bytes: &[u8]) {
+        if let Some(pending) = &mut self.pending {
+            pending.push(Record::new(direction, bytes));
+        } else if let Some(dump) = &self.dump {
+            dump.record(direction, bytes);
+        }
+    }
+}
+
+impl<T: Transport> Read for DumpStream<'_, T> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.stream.read(buffer)?;
+        self.record(Direction::Received, &buffer[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+impl<T: Transport> Write for DumpStream<'_, T> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let bytes_written = self.stream.write(buffer)?;
+        self.record(Direction::Sent, &buffer[..bytes_written]);
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<T: Transport> Transport for DumpStream<'_, T> {
+    fn shutdown(&mut self) -> io::Result<()> {
+        self.stream.shutdown()
+    }
+}
@@ -0,0 +1,141 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests that pin the stable public API of the RTI. A change that fails
+ * to compile here breaks the tools that embed the RTI and needs a deprecation
+ * first, as described in lib.rs.
+ */
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rti::{
+    FederateGroup, FederationRTI, ForwardedMessage, Instant, Interval, LogLevel, Microstep,
+    PayloadInspector, PhysicalClock, RtiBuilder, RtiError, Server, ShutdownHandle, Snapshot,
+    SnapshotTrigger, Tag, Topology, Transport, VirtualClock,
+};
+
+/// The signatures of the functions that embedders call, which must not change.
+#[test]
+fn entry_points_keep_their_signatures() {
+    let _: fn() -> FederationRTI = rti::initialize_rti;
+    let _: fn(&mut FederationRTI, &[String]) -> Result<(), RtiError> = rti::process_args;
+    let _: fn(&mut FederationRTI) = rti::initialize_federates;
+    let _: fn(&mut FederationRTI) -> Result<Server, RtiError> = rti::start_rti_server;
+    let _: fn(&mut Server, FederationRTI) -> Vec<u16> = Server::wait_for_federates;
+    let _: fn(ShutdownHandle) -> io::Result<()> = rti::install_signal_handlers;
+    let _: fn(SnapshotTrigger) -> io::Result<()> = rti::install_snapshot_signal_handler;
+    let _: fn(&str) -> Result<Topology, String> = Topology::parse;
+    let _: fn(&str) -> Result<Snapshot, String> = Snapshot::parse;
+    let _: fn(&FederationRTI) -> i32 = FederationRTI::number_of_enclaves;
+    let _: fn(&FederationRTI) -> String = FederationRTI::federation_id;
+    let _: fn(&FederationRTI) -> u16 = FederationRTI::user_specified_port;
+    let _: fn(&FederationRTI) -> &Vec<FederateGroup> = FederationRTI::groups;
+    let _: fn(&FederationRTI, u16) -> Option<&FederateGroup> = FederationRTI::group_of;
+    let _: fn(&FederationRTI) -> ShutdownHandle = FederationRTI::shutdown_handle;
+    let _: fn(&FederationRTI) -> SnapshotTrigger = FederationRTI::snapshot_trigger;
+    let _: fn(&SnapshotTrigger) = SnapshotTrigger::request;
+    let _: fn(&mut FederationRTI, Arc<dyn PayloadInspector>) = FederationRTI::add_payload_inspector;
+    let _: fn(&mut FederationRTI, VirtualClock) = FederationRTI::set_virtual_clock;
+    let _: fn(&VirtualClock, i64) = VirtualClock::step;
+    let _: fn(&PhysicalClock) -> Vec<i64> = PhysicalClock::detected_steps;
+    let _: fn(&PhysicalClock) -> Option<i64> = PhysicalClock::detected_smear_ppm;
+    #[cfg(feature = "run_db")]
+    let _: fn(&str, Option<(i64, i64)>) -> Result<String, RtiError> = rti::compare_runs;
+    let _: fn(Instant, Microstep) -> Tag = Tag::new;
+    let _: Interval = Some(0);
+    let _: LogLevel = LogLevel::Info;
+    let _: fn(LogLevel, std::fmt::Arguments) = rti::log_message;
+}
+
+/// An inspector as an embedder would write it, relying on the default methods.
+struct CountingInspector {
+    forwarded: AtomicUsize,
+}
+
+impl PayloadInspector for CountingInspector {
+    fn message_forwarded(&self, _message: &ForwardedMessage) {
+        self.forwarded.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// A transport as an embedder would write it.
+struct NullTransport;
+
+impl Read for NullTransport {
+    fn read(&mut self, _buffer: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for NullTransport {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for NullTransport {
+    fn shutdown(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn hooks_and_transports_can_be_implemented_outside_the_crate() {
+    let inspector = CountingInspector {
+        forwarded: AtomicUsize::new(0),
+    };
+    let message = ForwardedMessage::new(0, 1, 0, Tag::new(0, 0), 8);
+    assert!(!inspector.wants_payload(&message));
+    inspector.message_forwarded(&message);
+    assert_eq!(inspector.summary(), None);
+    assert_eq!(inspector.forwarded.load(Ordering::SeqCst), 1);
+
+    let mut transport = NullTransport;
+    transport.write_all(&[1, 2, 3]).unwrap();
+    Transport::shutdown(&mut transport).unwrap();
+}
+
+#[test]
+fn builder_builds_an_rti_from_options_and_hooks() {
+    let inspector: Arc<dyn PayloadInspector> = Arc::new(CountingInspector {
+        forwarded: AtomicUsize::new(0),
+    });
+    let clock = VirtualClock::new(5);
+    let rti = RtiBuilder::new()
+        .args(["-n", "3"])
+        .arg("-i")
+        .arg(String::from("api"))
+        .payload_inspector(inspector)
+        .virtual_clock(clock.clone())
+        .build()
+        .unwrap();
+    assert_eq!(rti.number_of_enclaves(), 3);
+    assert_eq!(rti.federation_id(), "api");
+    assert_eq!(rti.payload_inspectors().len(), 1);
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(rti.physical_clock().now(), 1_000_000_005);
+}
+
+#[test]
+fn builder_returns_invalid_arguments_as_an_error() {
+    let Err(error) = RtiBuilder::new().arg("--no_such_option").build() else {
+        panic!("an invalid option was accepted");
+    };
+    // RtiError is non-exhaustive, so matches on it need a wildcard arm.
+    match &error {
+        RtiError::InvalidArguments(_) => {}
+        _ => panic!("unexpected error {}", error),
+    }
+    assert!(error.to_string().starts_with("invalid arguments: "));
+    assert!(error.source().is_none());
+}
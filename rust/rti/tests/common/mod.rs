@@ -0,0 +1,903 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief In-process harness that runs the RTI together with scripted federates
+ * and records a global log of the scheduling messages they exchange.
+ */
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, UdpSocket};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub mod scenario;
+
+pub const FEDERATION_ID: &str = "harness";
+
+/// Delay encoding used on the wire for a connection without an "after" delay.
+pub const NO_DELAY: i64 = i64::MIN;
+
+const MSG_TYPE_REJECT: u8 = 0;
+const MSG_TYPE_TIMESTAMP: u8 = 2;
+const MSG_TYPE_RESIGN: u8 = 4;
+const MSG_TYPE_TAGGED_MESSAGE: u8 = 5;
+const MSG_TYPE_NEXT_EVENT_TAG: u8 = 6;
+const MSG_TYPE_TAG_ADVANCE_GRANT: u8 = 7;
+const MSG_TYPE_PROVISIONAL_TAG_ADVANCE_GRANT: u8 = 8;
+const MSG_TYPE_LOGICAL_TAG_COMPLETE: u8 = 9;
+const MSG_TYPE_STOP_REQUEST: u8 = 10;
+const MSG_TYPE_STOP_REQUEST_REPLY: u8 = 11;
+const MSG_TYPE_STOP_GRANTED: u8 = 12;
+const MSG_TYPE_CLOCK_SYNC_T1: u8 = 19;
+const MSG_TYPE_CLOCK_SYNC_T3: u8 = 20;
+const MSG_TYPE_CLOCK_SYNC_T4: u8 = 21;
+const MSG_TYPE_CLOCK_SYNC_CODED_PROBE: u8 = 22;
+const MSG_TYPE_NEIGHBOR_STRUCTURE: u8 = 24;
+const MSG_TYPE_FAILED: u8 = 25;
+const MSG_TYPE_DOWNSTREAM_NEXT_EVENT_TAG: u8 = 26;
+const MSG_TYPE_CONTROL_CHANNEL: u8 = 27;
+const MSG_TYPE_FEDERATE_METADATA: u8 = 28;
+const MSG_TYPE_START_TAG: u8 = 30;
+const MSG_TYPE_CAPABILITIES: u8 = 31;
+const MSG_TYPE_RESUME: u8 = 32;
+const MSG_TYPE_RESUME_TOKEN: u8 = 33;
+const MSG_TYPE_FED_IDS: u8 = 1;
+const MSG_TYPE_UDP_PORT: u8 = 254;
+const MSG_TYPE_ACK: u8 = 255;
+const CAPABILITY_RESUMPTION: u32 = 1 << 4;
+const RESUME_TOKEN_LENGTH: usize = 16;
+
+/// A tag relative to the start time of the federation.
+pub type RelTag = (i64, u32);
+
+/// FOREVER, which is not shifted by the start time.
+pub const FOREVER_TAG: RelTag = (i64::MAX, u32::MAX);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+pub enum Kind {
+    Net,
+    Ltc,
+    Tag,
+    Ptag,
+    StopRequest,
+    StopRequestReply,
+    StopGranted,
+    Resign,
+    /// A MsgType::Reject message, recorded with the error code as the time.
+    Reject,
+    /// A MsgType::TaggedMessage, whose payload is checked against the one
+    /// sent by Step::SendMessage.
+    Message,
+    /// A MsgType::DownstreamNextEventTag message.
+    Dnet,
+    /// The MsgType::StartTag message sent to a transient federate.
+    StartTag,
+    /// A MsgType::Failed message sent by the RTI when it shuts down.
+    Failed,
+}
+
+/// One entry of the global log. `Sent` entries are recorded before the bytes
+/// are written, and `Received` entries after they are read, so the log order
+/// is consistent with causality.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Sent(u16, Kind, RelTag),
+    Received(u16, Kind, RelTag),
+    /// A command sent to the admin console by the given federate's script.
+    Admin(u16, &'static str),
+    /// A runtime clock synchronization round completed by the given federate.
+    ClockSync(u16),
+}
+
+/// One step of a scripted federate.
+#[derive(Clone, Debug)]
+pub enum Step {
+    Send(Kind, RelTag),
+    /// Send a tagged message with a payload of the given length to the given
+    /// federate.
+    SendMessage(u16, RelTag, usize),
+    /// Read messages from the RTI until one of the given kind and tag arrives.
+    Expect(Kind, RelTag),
+    /// Reply to a stop request with the given tag once one has been received.
+    ReplyToStopRequest(RelTag),
+    Sleep(u64),
+    Resign,
+    /// Report a failure to the RTI and close the connection.
+    Fail,
+    /// Send a command to the admin console of the RTI and check that the
+    /// response starts with the given text.
+    Admin(&'static str, &'static str),
+    /// Like Admin, but authenticate with the given token first.
+    AdminWithToken(&'static str, &'static str, &'static str),
+    /// Like Admin, but repeat the command until the response starts with the
+    /// given text, for state that the RTI updates after the federate's
+    /// messages are sent.
+    AdminUntil(&'static str, &'static str),
+    /// Check that the physical time is not earlier than the given time
+    /// relative to the start time.
+    NotBefore(i64),
+    /// Drop the connection to the RTI and resume the session on a new one
+    /// with the resumption token, which requires the resumption capability.
+    Reconnect,
+}
+
+pub struct ScriptedFederate {
+    pub id: u16,
+    pub upstream: Vec<(u16, i64)>,
+    pub downstream: Vec<u16>,
+    pub steps: Vec<Step>,
+    /// Open a control connection, on which the RTI sends the grants.
+    pub control_channel: bool,
+    /// Time in milliseconds to wait before connecting to the RTI.
+    pub join_delay_ms: u64,
+    /// Runtime, host name, and process ID presented at the handshake.
+    pub metadata: Option<(&'static str, &'static str, u32)>,
+    /// Do clock synchronization with the given number of exchanges at startup.
+    pub clock_sync_exchanges: Option<usize>,
+    /// Propose the current physical time as the start time, as real federates
+    /// do, instead of 0.
+    pub physical_start: bool,
+    /// Offer the first bitmask of capabilities and check that the RTI
+    /// enables the second one.
+    pub capabilities: Option<(u32, u32)>,
+    /// Receive a start tag after the start time, and start the script without
+    /// waiting for the other federates. The RTI must be given --transient
+    /// with the ID. A later entry with the same ID joins again.
+    pub transient: bool,
+}
+
+impl ScriptedFederate {
+    pub fn new(id: u16) -> ScriptedFederate {
+        ScriptedFederate {
+            id,
+            upstream: Vec::new(),
+            downstream: Vec::new(),
+            steps: Vec::new(),
+            control_channel: false,
+            join_delay_ms: 0,
+            metadata: None,
+            clock_sync_exchanges: None,
+            physical_start: false,
+            capabilities: None,
+            transient: false,
+        }
+    }
+}
+
+/// Connect federate `from` to federate `to` with the given delay.
+pub fn connect(feds: &mut [ScriptedFederate], from: u16, to: u16, delay: i64) {
+    feds[from as usize].downstream.push(to);
+    feds[to as usize].upstream.push((from, delay));
+}
+
+pub struct Log {
+    events: Vec<Event>,
+    failed_federates: Vec<u16>,
+}
+
+impl Log {
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// IDs of the federates that the RTI reported as failed.
+    pub fn failed_federates(&self) -> &[u16] {
+        &self.failed_federates
+    }
+
+    pub fn position(&self, event: &Event) -> usize {
+        self.events
+            .iter()
+            .position(|e| e == event)
+            .unwrap_or_else(|| panic!("{:?} not found in {:#?}", event, self.events))
+    }
+
+    pub fn contains(&self, event: &Event) -> bool {
+        self.events.contains(event)
+    }
+
+    pub fn assert_before(&self, first: &Event, second: &Event) {
+        assert!(
+            self.position(first) < self.position(second),
+            "expected {:?} before {:?} in {:#?}",
+            first,
+            second,
+            self.events
+        );
+    }
+
+    /// Grants (TAG and PTAG) received by the given federate, in order.
+    pub fn grants_to(&self, fed_id: u16) -> Vec<(Kind, RelTag)> {
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Received(id, kind, tag)
+                    if *id == fed_id && (*kind == Kind::Tag || *kind == Kind::Ptag) =>
+                {
+                    Some((*kind, *tag))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Run the RTI with the given federates and extra command-line arguments
+/// until every federate has resigned or failed, and return the global log.
+pub fn run_federation(federates: Vec<ScriptedFederate>, extra_args: &[&str]) -> Log {
+    run_federation_with(federates, extra_args, |_| {})
+}
+
+/// Like run_federation, but let the given function configure the RTI after
+/// the arguments have been processed, as an application embedding it would.
+pub fn run_federation_with(
+    federates: Vec<ScriptedFederate>,
+    extra_args: &[&str],
+    configure: impl FnOnce(&mut rti::FederationRTI) + Send + 'static,
+) -> Log {
+    run_federation_through(federates, extra_args, configure, |port| port)
+}
+
+/// Like run_federation_with, but connect the federates to the port that the
+/// given function returns for the port of the RTI, e.g., that of a relay.
+pub fn run_federation_through(
+    federates: Vec<ScriptedFederate>,
+    extra_args: &[&str],
+    configure: impl FnOnce(&mut rti::FederationRTI) + Send + 'static,
+    relay: impl FnOnce(u16) -> u16,
+) -> Log {
+    let port = free_port();
+    // A transient federate that joins again has several entries.
+    let mut ids: Vec<u16> = federates.iter().map(|fed| fed.id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let number_of_federates = ids.len();
+    let mut args: Vec<String> = vec![
+        "rti",
+        "-i",
+        FEDERATION_ID,
+        "-n",
+        &number_of_federates.to_string(),
+        "-p",
+        &port.to_string(),
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    args.extend(extra_args.iter().map(|s| s.to_string()));
+    let uses_admin_console = federates.iter().any(|fed| {
+        fed.steps.iter().any(|step| {
+            matches!(
+                step,
+                Step::Admin(..) | Step::AdminWithToken(..) | Step::AdminUntil(..)
+            )
+        })
+    });
+    let admin_port = if uses_admin_console {
+        let admin_port = free_port();
+        args.extend(["--admin_port".to_string(), admin_port.to_string()]);
+        Some(admin_port)
+    } else {
+        None
+    };
+
+    let rti_thread = thread::spawn(move || {
+        let mut _f_rti = rti::initialize_rti();
+        rti::process_args(&mut _f_rti, &args).unwrap();
+        configure(&mut _f_rti);
+        rti::initialize_federates(&mut _f_rti);
+        rti::start_rti_server(&mut _f_rti)
+            .unwrap()
+            .wait_for_federates(_f_rti)
+    });
+
+    let mut log = run_scripts(federates, relay(port), admin_port);
+    log.failed_federates = rti_thread.join().unwrap();
+    log
+}
+
+/// Run the federates against an RTI executable, such as the one of the C
+/// reference implementation, that takes the federation ID, the number of
+/// federates, and the port as the "-i", "-n", and "-p" options. The RTI is
+/// killed if it has not exited shortly after the federates, and the failed
+/// federates are not known.
+pub fn run_federation_on(executable: &str, federates: Vec<ScriptedFederate>) -> Log {
+    let port = free_port();
+    let mut rti_process = Command::new(executable)
+        .args(["-i", FEDERATION_ID, "-n"])
+        .arg(federates.len().to_string())
+        .arg("-p")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap_or_else(|e| panic!("Failed to start the RTI {}: {}.", executable, e));
+    let log = run_scripts(federates, port, None);
+    for _ in 0..100 {
+        if rti_process.try_wait().unwrap().is_some() {
+            return log;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let _ = rti_process.kill();
+    let _ = rti_process.wait();
+    log
+}
+
+/// Run the federates against an RTI that has been started separately and
+/// listens on the given port.
+pub fn run_federates_on(port: u16, federates: Vec<ScriptedFederate>) -> Log {
+    run_scripts(federates, port, None)
+}
+
+/// Run the scripts of the federates against the RTI listening on the given
+/// port until every one of them has finished.
+fn run_scripts(federates: Vec<ScriptedFederate>, port: u16, admin_port: Option<u16>) -> Log {
+    let log = Arc::new(Mutex::new(Log {
+        events: Vec::new(),
+        failed_federates: Vec::new(),
+    }));
+    // Scripts start only after every federate that is not transient has
+    // received the start time.
+    let started = Arc::new(Barrier::new(
+        federates.iter().filter(|fed| !fed.transient).count(),
+    ));
+    let mut handles = Vec::new();
+    for fed in federates {
+        let log = log.clone();
+        let started = started.clone();
+        handles.push(thread::spawn(move || {
+            run_federate(fed, port, admin_port, log, started)
+        }));
+    }
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    Arc::try_unwrap(log).ok().unwrap().into_inner().unwrap()
+}
+
+fn connect_to_rti(port: u16) -> TcpStream {
+    for _ in 0..100 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)) {
+            stream
+                .set_read_timeout(Some(Duration::from_secs(10)))
+                .unwrap();
+            return stream;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    panic!("Failed to connect to the RTI on port {}.", port);
+}
+
+fn run_federate(
+    fed: ScriptedFederate,
+    port: u16,
+    admin_port: Option<u16>,
+    log: Arc<Mutex<Log>>,
+    started: Arc<Barrier>,
+) {
+    thread::sleep(Duration::from_millis(fed.join_delay_ms));
+    let mut stream = connect_to_rti(port);
+
+    // MsgType::FedIds
+    let mut buffer = vec![MSG_TYPE_FED_IDS];
+    buffer.extend_from_slice(&fed.id.to_le_bytes());
+    buffer.push(FEDERATION_ID.len() as u8);
+    buffer.extend_from_slice(FEDERATION_ID.as_bytes());
+    stream.write_all(&buffer).unwrap();
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack).unwrap();
+    assert_eq!(ack[0], MSG_TYPE_ACK, "federate {} was rejected", fed.id);
+
+    if let Some((runtime, host_name, process_id)) = fed.metadata {
+        // MsgType::FederateMetadata
+        let mut buffer = vec![MSG_TYPE_FEDERATE_METADATA];
+        buffer.push(runtime.len() as u8);
+        buffer.extend_from_slice(runtime.as_bytes());
+        buffer.push(host_name.len() as u8);
+        buffer.extend_from_slice(host_name.as_bytes());
+        buffer.extend_from_slice(&process_id.to_le_bytes());
+        stream.write_all(&buffer).unwrap();
+    }
+
+    if let Some((offered, enabled)) = fed.capabilities {
+        // MsgType::Capabilities
+        let mut buffer = vec![MSG_TYPE_CAPABILITIES];
+        buffer.extend_from_slice(&offered.to_le_bytes());
+        stream.write_all(&buffer).unwrap();
+        let mut reply = [0u8; 5];
+        stream.read_exact(&mut reply).unwrap();
+        assert_eq!(reply[0], MSG_TYPE_CAPABILITIES);
+        assert_eq!(
+            u32::from_le_bytes(reply[1..].try_into().unwrap()),
+            enabled,
+            "federate {} was enabled unexpected capabilities",
+            fed.id
+        );
+    }
+    let mut resume_token = None;
+    if fed
+        .capabilities
+        .is_some_and(|(_, enabled)| enabled & CAPABILITY_RESUMPTION != 0)
+    {
+        // MsgType::ResumeToken
+        let mut message = [0u8; 1 + RESUME_TOKEN_LENGTH];
+        stream.read_exact(&mut message).unwrap();
+        assert_eq!(message[0], MSG_TYPE_RESUME_TOKEN);
+        resume_token = Some(message[1..].to_vec());
+    }
+
+    let mut control_stream = if fed.control_channel {
+        Some(open_control_channel(&mut stream, fed.id, port))
+    } else {
+        None
+    };
+    // Number of tagged messages received so far.
+    let mut received_messages = 0;
+
+    // MsgType::NeighborStructure
+    let mut buffer = vec![MSG_TYPE_NEIGHBOR_STRUCTURE];
+    buffer.extend_from_slice(&(fed.upstream.len() as i32).to_le_bytes());
+    buffer.extend_from_slice(&(fed.downstream.len() as i32).to_le_bytes());
+    for (id, delay) in &fed.upstream {
+        buffer.extend_from_slice(&id.to_le_bytes());
+        buffer.extend_from_slice(&delay.to_le_bytes());
+    }
+    for id in &fed.downstream {
+        buffer.extend_from_slice(&id.to_le_bytes());
+    }
+    stream.write_all(&buffer).unwrap();
+
+    // MsgType::UdpPort, where u16::MAX means no clock synchronization.
+    let udp_socket = fed
+        .clock_sync_exchanges
+        .map(|_| UdpSocket::bind("127.0.0.1:0").unwrap());
+    let udp_port = udp_socket
+        .as_ref()
+        .map_or(u16::MAX, |socket| socket.local_addr().unwrap().port());
+    let mut buffer = vec![MSG_TYPE_UDP_PORT];
+    buffer.extend_from_slice(&udp_port.to_le_bytes());
+    stream.write_all(&buffer).unwrap();
+    for _ in 0..fed.clock_sync_exchanges.unwrap_or(0) {
+        let mut t1 = [0u8; 9];
+        stream.read_exact(&mut t1).unwrap();
+        assert_eq!(t1[0], MSG_TYPE_CLOCK_SYNC_T1);
+        stream.write_all(&clock_sync_t3(fed.id)).unwrap();
+        let mut t4 = [0u8; 9];
+        stream.read_exact(&mut t4).unwrap();
+        assert_eq!(t4[0], MSG_TYPE_CLOCK_SYNC_T4);
+    }
+    let _responder =
+        udp_socket.map(|socket| ClockSyncResponder::start(socket, fed.id, log.clone()));
+
+    if let Some(control_stream) = control_stream.as_mut() {
+        let mut ack = [0u8; 1];
+        control_stream.read_exact(&mut ack).unwrap();
+        assert_eq!(
+            ack[0], MSG_TYPE_ACK,
+            "the control connection of federate {} was rejected",
+            fed.id
+        );
+    }
+
+    // Propose a start time and receive the agreed one.
+    let proposed_start_time = if fed.physical_start {
+        physical_time()
+    } else {
+        0
+    };
+    let mut buffer = vec![MSG_TYPE_TIMESTAMP];
+    buffer.extend_from_slice(&proposed_start_time.to_le_bytes());
+    stream.write_all(&buffer).unwrap();
+    let mut reply = [0u8; 9];
+    stream.read_exact(&mut reply[..1]).unwrap();
+    if reply[0] == MSG_TYPE_FAILED {
+        // The RTI has shut down before sending the start time.
+        log.lock()
+            .unwrap()
+            .events
+            .push(Event::Received(fed.id, Kind::Failed, (0, 0)));
+        return;
+    }
+    stream.read_exact(&mut reply[1..]).unwrap();
+    assert_eq!(reply[0], MSG_TYPE_TIMESTAMP);
+    let start_time = i64::from_le_bytes(reply[1..9].try_into().unwrap());
+    if fed.transient {
+        let mut start_tag = [0u8; 13];
+        stream.read_exact(&mut start_tag).unwrap();
+        assert_eq!(start_tag[0], MSG_TYPE_START_TAG);
+        let time = i64::from_le_bytes(start_tag[1..9].try_into().unwrap());
+        let microstep = u32::from_le_bytes(start_tag[9..13].try_into().unwrap());
+        log.lock().unwrap().events.push(Event::Received(
+            fed.id,
+            Kind::StartTag,
+            (time - start_time, microstep),
+        ));
+    } else {
+        started.wait();
+    }
+
+    let mut pending_stop_request = false;
+    for step in fed.steps {
+        match step {
+            Step::Send(kind, tag) => {
+                log.lock()
+                    .unwrap()
+                    .events
+                    .push(Event::Sent(fed.id, kind, tag));
+                send_tagged(&mut stream, kind, tag, start_time);
+            }
+            Step::SendMessage(destination, tag, length) => {
+                log.lock()
+                    .unwrap()
+                    .events
+                    .push(Event::Sent(fed.id, Kind::Message, tag));
+                let mut buffer = vec![MSG_TYPE_TAGGED_MESSAGE];
+                // Port 0 of the destination federate.
+                buffer.extend_from_slice(&0u16.to_le_bytes());
+                buffer.extend_from_slice(&destination.to_le_bytes());
+                buffer.extend_from_slice(&(length as i32).to_le_bytes());
+                buffer.extend_from_slice(&(start_time + tag.0).to_le_bytes());
+                buffer.extend_from_slice(&tag.1.to_le_bytes());
+                buffer.extend((0..length).map(payload_byte));
+                stream.write_all(&buffer).unwrap();
+            }
+            Step::Expect(kind, tag) => loop {
+                let (received_kind, received_tag) = match control_stream.as_mut() {
+                    Some(control_stream) if matches!(kind, Kind::Tag | Kind::Ptag) => {
+                        receive_grant(
+                            control_stream,
+                            &mut stream,
+                            &mut received_messages,
+                            start_time,
+                            fed.id,
+                            &log,
+                        )
+                    }
+                    _ => receive(&mut stream, start_time, fed.id),
+                };
+                if received_kind == Kind::Message {
+                    received_messages += 1;
+                }
+                log.lock().unwrap().events.push(Event::Received(
+                    fed.id,
+                    received_kind,
+                    received_tag,
+                ));
+                if received_kind == Kind::StopRequest {
+                    pending_stop_request = true;
+                }
+                if received_kind == kind && received_tag == tag {
+                    break;
+                }
+            },
+            Step::ReplyToStopRequest(tag) => {
+                while !pending_stop_request {
+                    let (received_kind, received_tag) = receive(&mut stream, start_time, fed.id);
+                    log.lock().unwrap().events.push(Event::Received(
+                        fed.id,
+                        received_kind,
+                        received_tag,
+                    ));
+                    pending_stop_request = received_kind == Kind::StopRequest;
+                }
+                log.lock()
+                    .unwrap()
+                    .events
+                    .push(Event::Sent(fed.id, Kind::StopRequestReply, tag));
+                send_tagged(&mut stream, Kind::StopRequestReply, tag, start_time);
+            }
+            Step::Sleep(ms) => thread::sleep(Duration::from_millis(ms)),
+            Step::Resign => {
+                log.lock()
+                    .unwrap()
+                    .events
+                    .push(Event::Sent(fed.id, Kind::Resign, (0, 0)));
+                stream.write_all(&[MSG_TYPE_RESIGN]).unwrap();
+                // Keep the connection open until the RTI closes it, recording
+                // any grant that was already in flight.
+                while let Some((kind, tag)) = try_receive(&mut stream, start_time, fed.id) {
+                    log.lock()
+                        .unwrap()
+                        .events
+                        .push(Event::Received(fed.id, kind, tag));
+                }
+                return;
+            }
+            Step::Fail => {
+                stream.write_all(&[MSG_TYPE_FAILED]).unwrap();
+                return;
+            }
+            Step::Reconnect => {
+                let token = resume_token
+                    .take()
+                    .expect("the federate has no resumption token");
+                stream.shutdown(Shutdown::Both).unwrap();
+                stream = connect_to_rti(port);
+                // MsgType::Resume
+                let mut buffer = vec![MSG_TYPE_RESUME];
+                buffer.extend_from_slice(&fed.id.to_le_bytes());
+                buffer.extend_from_slice(&token);
+                stream.write_all(&buffer).unwrap();
+                let mut reply = [0u8; 1 + RESUME_TOKEN_LENGTH];
+                stream.read_exact(&mut reply).unwrap();
+                assert_eq!(
+                    reply[0], MSG_TYPE_ACK,
+                    "federate {} could not resume its session",
+                    fed.id
+                );
+                assert_ne!(&reply[1..], &token[..], "the token was not renewed");
+                resume_token = Some(reply[1..].to_vec());
+            }
+            Step::NotBefore(time) => {
+                let now = physical_time();
+                assert!(
+                    now >= start_time + time,
+                    "federate {} reached step NotBefore({}) at {}",
+                    fed.id,
+                    time,
+                    now - start_time
+                );
+            }
+            Step::Admin(command, expected_response) => {
+                log.lock()
+                    .unwrap()
+                    .events
+                    .push(Event::Admin(fed.id, command));
+                let mut admin = TcpStream::connect(("127.0.0.1", admin_port.unwrap())).unwrap();
+                writeln!(admin, "{}", command).unwrap();
+                let mut response = String::new();
+                BufReader::new(admin).read_line(&mut response).unwrap();
+                assert!(
+                    response.starts_with(expected_response),
+                    "admin console answered '{}' to '{}'",
+                    response.trim_end(),
+                    command
+                );
+            }
+            Step::AdminUntil(command, expected_response) => {
+                log.lock()
+                    .unwrap()
+                    .events
+                    .push(Event::Admin(fed.id, command));
+                let mut response = String::new();
+                for _ in 0..500 {
+                    let mut admin = TcpStream::connect(("127.0.0.1", admin_port.unwrap())).unwrap();
+                    writeln!(admin, "{}", command).unwrap();
+                    response.clear();
+                    BufReader::new(admin).read_line(&mut response).unwrap();
+                    if response.starts_with(expected_response) {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+                assert!(
+                    response.starts_with(expected_response),
+                    "admin console kept answering '{}' to '{}'",
+                    response.trim_end(),
+                    command
+                );
+            }
+            Step::AdminWithToken(token, command, expected_response) => {
+                log.lock()
+                    .unwrap()
+                    .events
+                    .push(Event::Admin(fed.id, command));
+                let mut admin = TcpStream::connect(("127.0.0.1", admin_port.unwrap())).unwrap();
+                writeln!(admin, "auth {}", token).unwrap();
+                writeln!(admin, "{}", command).unwrap();
+                let mut reader = BufReader::new(admin);
+                let mut response = String::new();
+                reader.read_line(&mut response).unwrap();
+                assert!(
+                    response.starts_with("OK authenticated"),
+                    "admin console answered '{}' to the token",
+                    response.trim_end()
+                );
+                response.clear();
+                reader.read_line(&mut response).unwrap();
+                assert!(
+                    response.starts_with(expected_response),
+                    "admin console answered '{}' to '{}'",
+                    response.trim_end(),
+                    command
+                );
+            }
+        }
+    }
+}
+
+/// The realtime clock in nanoseconds since the epoch.
+fn physical_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64
+}
+
+fn clock_sync_t3(fed_id: u16) -> Vec<u8> {
+    let mut buffer = vec![MSG_TYPE_CLOCK_SYNC_T3];
+    buffer.extend_from_slice(&i32::from(fed_id).to_le_bytes());
+    buffer
+}
+
+/// Thread of a federate that answers the runtime clock synchronization rounds
+/// of the RTI over UDP until it is dropped.
+struct ClockSyncResponder {
+    stopped: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl ClockSyncResponder {
+    fn start(socket: UdpSocket, fed_id: u16, log: Arc<Mutex<Log>>) -> ClockSyncResponder {
+        socket
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stopped_by_drop = stopped.clone();
+        let handle = thread::spawn(move || {
+            let mut awaiting_probe = false;
+            let mut buffer = [0u8; 9];
+            while !stopped.load(Ordering::SeqCst) {
+                let Ok((_, rti_address)) = socket.recv_from(&mut buffer) else {
+                    continue;
+                };
+                match buffer[0] {
+                    MSG_TYPE_CLOCK_SYNC_T1 => {
+                        socket.send_to(&clock_sync_t3(fed_id), rti_address).unwrap();
+                    }
+                    MSG_TYPE_CLOCK_SYNC_T4 => awaiting_probe = true,
+                    MSG_TYPE_CLOCK_SYNC_CODED_PROBE if awaiting_probe => {
+                        awaiting_probe = false;
+                        log.lock().unwrap().events.push(Event::ClockSync(fed_id));
+                    }
+                    other => panic!("unexpected clock sync message {}", other),
+                }
+            }
+        });
+        ClockSyncResponder {
+            stopped: stopped_by_drop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for ClockSyncResponder {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Announce a control connection on the given stream and open it. The RTI
+/// acknowledges it only once an I/O thread is free, so the acknowledgement is
+/// read after the handshake.
+fn open_control_channel(stream: &mut TcpStream, fed_id: u16, port: u16) -> TcpStream {
+    stream.write_all(&[MSG_TYPE_CONTROL_CHANNEL]).unwrap();
+    let mut control_stream = connect_to_rti(port);
+    let mut buffer = vec![MSG_TYPE_CONTROL_CHANNEL];
+    buffer.extend_from_slice(&fed_id.to_le_bytes());
+    buffer.push(FEDERATION_ID.len() as u8);
+    buffer.extend_from_slice(FEDERATION_ID.as_bytes());
+    control_stream.write_all(&buffer).unwrap();
+    control_stream
+}
+
+/// Receive a grant on the control stream, and then the tagged messages that
+/// the RTI relayed on the main stream before it, which are logged first.
+fn receive_grant(
+    control_stream: &mut TcpStream,
+    stream: &mut TcpStream,
+    received_messages: &mut u64,
+    start_time: i64,
+    fed_id: u16,
+    log: &Mutex<Log>,
+) -> (Kind, RelTag) {
+    let grant = receive(control_stream, start_time, fed_id);
+    let mut relayed_messages = [0u8; 8];
+    control_stream.read_exact(&mut relayed_messages).unwrap();
+    while *received_messages < u64::from_le_bytes(relayed_messages) {
+        let (kind, tag) = receive(stream, start_time, fed_id);
+        if kind == Kind::Message {
+            *received_messages += 1;
+        }
+        log.lock()
+            .unwrap()
+            .events
+            .push(Event::Received(fed_id, kind, tag));
+    }
+    grant
+}
+
+/// The byte at the given index of the payloads sent by scripted federates.
+pub fn payload_byte(index: usize) -> u8 {
+    (index % 251) as u8
+}
+
+fn send_tagged(stream: &mut TcpStream, kind: Kind, tag: RelTag, start_time: i64) {
+    let msg_type = match kind {
+        Kind::Net => MSG_TYPE_NEXT_EVENT_TAG,
+        Kind::Ltc => MSG_TYPE_LOGICAL_TAG_COMPLETE,
+        Kind::StopRequest => MSG_TYPE_STOP_REQUEST,
+        Kind::StopRequestReply => MSG_TYPE_STOP_REQUEST_REPLY,
+        _ => panic!("{:?} cannot be sent by a federate", kind),
+    };
+    let mut buffer = vec![msg_type];
+    buffer.extend_from_slice(&(start_time + tag.0).to_le_bytes());
+    buffer.extend_from_slice(&tag.1.to_le_bytes());
+    stream.write_all(&buffer).unwrap();
+}
+
+fn receive(stream: &mut TcpStream, start_time: i64, fed_id: u16) -> (Kind, RelTag) {
+    try_receive(stream, start_time, fed_id)
+        .unwrap_or_else(|| panic!("the RTI closed the connection to federate {}", fed_id))
+}
+
+fn try_receive(stream: &mut TcpStream, start_time: i64, fed_id: u16) -> Option<(Kind, RelTag)> {
+    let mut msg_type = [0u8; 1];
+    match stream.read(&mut msg_type) {
+        Ok(0) => return None,
+        Ok(_) => {}
+        Err(e) => panic!("federate {} failed to read from the RTI: {}", fed_id, e),
+    }
+    if msg_type[0] == MSG_TYPE_REJECT {
+        let mut error_code = [0u8; 1];
+        stream.read_exact(&mut error_code).unwrap();
+        return Some((Kind::Reject, (error_code[0] as i64, 0)));
+    }
+    if msg_type[0] == MSG_TYPE_FAILED {
+        return Some((Kind::Failed, (0, 0)));
+    }
+    let kind = match msg_type[0] {
+        MSG_TYPE_TAG_ADVANCE_GRANT => Kind::Tag,
+        MSG_TYPE_PROVISIONAL_TAG_ADVANCE_GRANT => Kind::Ptag,
+        MSG_TYPE_STOP_REQUEST => Kind::StopRequest,
+        MSG_TYPE_STOP_GRANTED => Kind::StopGranted,
+        MSG_TYPE_DOWNSTREAM_NEXT_EVENT_TAG => Kind::Dnet,
+        MSG_TYPE_TAGGED_MESSAGE => {
+            let mut header = [0u8; 20];
+            stream.read_exact(&mut header).unwrap();
+            let length = i32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+            let time = i64::from_le_bytes(header[8..16].try_into().unwrap());
+            let microstep = u32::from_le_bytes(header[16..20].try_into().unwrap());
+            let mut payload = vec![0u8; length];
+            stream.read_exact(&mut payload).unwrap();
+            for (index, byte) in payload.iter().enumerate() {
+                assert_eq!(
+                    *byte,
+                    payload_byte(index),
+                    "federate {} received a corrupt payload at byte {}",
+                    fed_id,
+                    index
+                );
+            }
+            return Some((Kind::Message, (time - start_time, microstep)));
+        }
+        other => panic!(
+            "federate {} received unexpected message type {}",
+            fed_id, other
+        ),
+    };
+    let mut payload = [0u8; 12];
+    stream.read_exact(&mut payload).unwrap();
+    let time = i64::from_le_bytes(payload[0..8].try_into().unwrap());
+    let microstep = u32::from_le_bytes(payload[8..12].try_into().unwrap());
+    if time == i64::MAX || time == i64::MIN {
+        return Some((kind, (time, microstep)));
+    }
+    Some((kind, (time - start_time, microstep)))
+}
@@ -0,0 +1,268 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Declarative scenarios for the scripted federates of the harness, so
+ * that a regression scenario is written as a RON file instead of a test
+ * function.
+ *
+ * A scenario is a `Scenario` struct with the fields below, all but
+ * `federates` optional. Tags are written as `At(<time>, <microstep>)`
+ * relative to the start time, or as `Forever`, and kinds of messages as the
+ * variants of Kind, e.g., `Net`, `Ptag`, or `StopGranted`.
+ *
+ * - `rti`: Extra arguments of the RTI, e.g., `["--step"]`.
+ * - `federates`: The federates, in the order of their IDs, except that a
+ *   transient federate may be listed again to join again. A federate is
+ *   `(id: <id>, steps: [...])` with the optional fields `control_channel`,
+ *   `transient`, and `physical_start` (false by default), `join_delay_ms`,
+ *   and `clock_sync_exchanges`. The steps are:
+ *   - `Send(Net|Ltc|StopRequest, <tag>)`;
+ *   - `Message(<destination>, <tag>, <length>)`: send a tagged message;
+ *   - `Expect(<kind>, <tag>)`: read until a message of the kind and tag
+ *     arrives;
+ *   - `ReplyToStopRequest(<tag>)`: reply to a stop request once it arrives;
+ *   - `Sleep(<ms>)`;
+ *   - `NotBefore(<time>)`: check that the physical time is not earlier than
+ *     the time after the start time;
+ *   - `Admin("<command>", "<response>")`: send a command to the admin console
+ *     and check the start of the response;
+ *   - `Resign` or `Fail`.
+ * - `connections`: `(from: <fed>, to: <fed>)` connections, without a delay
+ *   unless `delay` is given in nanoseconds. They refer to the federates by
+ *   their positions in `federates`.
+ * - `checks`: The checks, which run after the federation has finished:
+ *   - `Grants(<fed>, [(<kind>, <tag>), ...])`: check the first grants that
+ *     the federate received;
+ *   - `Happened(<event>)`, or `Before(<event>, <event>)` to also check the
+ *     order, where an event is `Sent|Received(<fed>, <kind>, <tag>)`;
+ *   - `NotReceived(<kind>)`: check that no federate received a message of
+ *     the kind;
+ *   - `Failed([<fed>, ...])`: check the federates reported as failed.
+ */
+use serde::Deserialize;
+
+use super::*;
+
+#[derive(Deserialize)]
+enum SpecTag {
+    At(i64, u32),
+    Forever,
+}
+
+impl SpecTag {
+    fn relative(&self) -> RelTag {
+        match *self {
+            SpecTag::At(time, microstep) => (time, microstep),
+            SpecTag::Forever => FOREVER_TAG,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum SpecStep {
+    Send(Kind, SpecTag),
+    Message(u16, SpecTag, usize),
+    Expect(Kind, SpecTag),
+    ReplyToStopRequest(SpecTag),
+    Sleep(u64),
+    NotBefore(i64),
+    Admin(String, String),
+    Resign,
+    Fail,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecFederate {
+    id: u16,
+    #[serde(default)]
+    control_channel: bool,
+    #[serde(default)]
+    transient: bool,
+    #[serde(default)]
+    physical_start: bool,
+    #[serde(default)]
+    join_delay_ms: u64,
+    #[serde(default)]
+    clock_sync_exchanges: Option<usize>,
+    #[serde(default)]
+    steps: Vec<SpecStep>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecConnection {
+    from: u16,
+    to: u16,
+    #[serde(default)]
+    delay: Option<i64>,
+}
+
+#[derive(Deserialize)]
+enum SpecEvent {
+    Sent(u16, Kind, SpecTag),
+    Received(u16, Kind, SpecTag),
+}
+
+impl SpecEvent {
+    fn event(&self) -> Event {
+        match self {
+            SpecEvent::Sent(fed_id, kind, tag) => Event::Sent(*fed_id, *kind, tag.relative()),
+            SpecEvent::Received(fed_id, kind, tag) => {
+                Event::Received(*fed_id, *kind, tag.relative())
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+enum Check {
+    Grants(u16, Vec<(Kind, SpecTag)>),
+    Happened(SpecEvent),
+    Before(SpecEvent, SpecEvent),
+    NotReceived(Kind),
+    Failed(Vec<u16>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Scenario", deny_unknown_fields)]
+struct Spec {
+    #[serde(default)]
+    rti: Vec<String>,
+    federates: Vec<SpecFederate>,
+    #[serde(default)]
+    connections: Vec<SpecConnection>,
+    #[serde(default)]
+    checks: Vec<Check>,
+}
+
+pub struct Scenario {
+    federates: Vec<ScriptedFederate>,
+    args: Vec<String>,
+    checks: Vec<Check>,
+}
+
+/// Keep the given text for the rest of the test, as the steps need.
+fn leak(text: String) -> &'static str {
+    Box::leak(text.into_boxed_str())
+}
+
+fn step(spec: SpecStep) -> Result<Step, String> {
+    Ok(match spec {
+        SpecStep::Send(kind @ (Kind::Net | Kind::Ltc | Kind::StopRequest), tag) => {
+            Step::Send(kind, tag.relative())
+        }
+        SpecStep::Send(..) => {
+            return Err(String::from("only Net, Ltc, and StopRequest can be sent"))
+        }
+        SpecStep::Message(destination, tag, length) => {
+            Step::SendMessage(destination, tag.relative(), length)
+        }
+        SpecStep::Expect(kind, tag) => Step::Expect(kind, tag.relative()),
+        SpecStep::ReplyToStopRequest(tag) => Step::ReplyToStopRequest(tag.relative()),
+        SpecStep::Sleep(ms) => Step::Sleep(ms),
+        SpecStep::NotBefore(time) => Step::NotBefore(time),
+        SpecStep::Admin(command, response) => Step::Admin(leak(command), leak(response)),
+        SpecStep::Resign => Step::Resign,
+        SpecStep::Fail => Step::Fail,
+    })
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Scenario, String> {
+        let spec: Spec = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(text)
+            .map_err(|e| e.to_string())?;
+        if spec.federates.is_empty() {
+            return Err(String::from("no federate"));
+        }
+        let mut federates = Vec::new();
+        for spec in spec.federates {
+            let mut fed = ScriptedFederate::new(spec.id);
+            fed.control_channel = spec.control_channel;
+            fed.transient = spec.transient;
+            fed.physical_start = spec.physical_start;
+            fed.join_delay_ms = spec.join_delay_ms;
+            fed.clock_sync_exchanges = spec.clock_sync_exchanges;
+            fed.steps = spec
+                .steps
+                .into_iter()
+                .map(step)
+                .collect::<Result<_, String>>()
+                .map_err(|e| format!("federate {}: {}", spec.id, e))?;
+            federates.push(fed);
+        }
+        for connection in spec.connections {
+            if usize::from(connection.from.max(connection.to)) >= federates.len() {
+                return Err(format!(
+                    "connection from {} to {} refers to a federate not in the scenario",
+                    connection.from, connection.to
+                ));
+            }
+            let delay = connection.delay.unwrap_or(NO_DELAY);
+            connect(&mut federates, connection.from, connection.to, delay);
+        }
+        Ok(Scenario {
+            federates,
+            args: spec.rti,
+            checks: spec.checks,
+        })
+    }
+
+    /// Read the scenario in the given file.
+    pub fn load(path: &str) -> Scenario {
+        let text = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", path, e));
+        Scenario::parse(&text).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    /// Run the federation and check the outcome.
+    pub fn run(self) -> Log {
+        let args: Vec<&str> = self.args.iter().map(String::as_str).collect();
+        let log = run_federation(self.federates, &args);
+        for check in &self.checks {
+            match check {
+                Check::Grants(fed_id, expected) => {
+                    let expected: Vec<(Kind, RelTag)> = expected
+                        .iter()
+                        .map(|(kind, tag)| (*kind, tag.relative()))
+                        .collect();
+                    let grants = log.grants_to(*fed_id);
+                    assert!(
+                        grants.starts_with(&expected),
+                        "federate {} received the grants {:?} instead of {:?}",
+                        fed_id,
+                        grants,
+                        expected
+                    );
+                }
+                Check::Happened(event) => {
+                    let event = event.event();
+                    assert!(
+                        log.contains(&event),
+                        "{:?} not found in {:#?}",
+                        event,
+                        log.events()
+                    )
+                }
+                Check::Before(first, second) => log.assert_before(&first.event(), &second.event()),
+                Check::NotReceived(kind) => {
+                    assert!(
+                        !log.events()
+                            .iter()
+                            .any(|event| matches!(event, Event::Received(_, k, _) if k == kind)),
+                        "a federate received {:?} in {:#?}",
+                        kind,
+                        log.events()
+                    );
+                }
+                Check::Failed(ids) => assert_eq!(log.failed_federates(), ids.as_slice()),
+            }
+        }
+        log
+    }
+}
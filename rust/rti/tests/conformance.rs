@@ -0,0 +1,223 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Conformance tests that run the same scripted federations against this
+ * RTI and, if the LF_C_RTI environment variable names its executable, against
+ * the RTI of the C reference implementation, and compare the grants.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::env;
+
+/**
+ * A scripted federation and the last TAG each federate is expected to receive.
+ */
+struct Scenario {
+    name: &'static str,
+    federates: fn() -> Vec<ScriptedFederate>,
+    final_tags: &'static [(u16, Option<RelTag>)],
+}
+
+fn pipeline() -> Vec<ScriptedFederate> {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    feds
+}
+
+fn diamond() -> Vec<ScriptedFederate> {
+    let mut feds: Vec<ScriptedFederate> = (0..4).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 0, 2, NO_DELAY);
+    connect(&mut feds, 1, 3, NO_DELAY);
+    connect(&mut feds, 2, 3, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    for id in 1..3 {
+        feds[id].steps = vec![
+            Step::Send(Kind::Net, (0, 0)),
+            Step::Expect(Kind::Tag, (0, 0)),
+            Step::Sleep(50 * id as u64),
+            Step::Send(Kind::Ltc, (0, 0)),
+            Step::Resign,
+        ];
+    }
+    feds[3].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    feds
+}
+
+fn cycle_with_delay() -> Vec<ScriptedFederate> {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 0, 10);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Send(Kind::Net, (20, 0)),
+        Step::Expect(Kind::Tag, (20, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Send(Kind::Net, (30, 0)),
+        Step::Sleep(300),
+        Step::Resign,
+    ];
+    feds
+}
+
+fn stop_request() -> Vec<ScriptedFederate> {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10);
+    feds[0].steps = vec![
+        Step::Send(Kind::StopRequest, (5, 0)),
+        Step::Expect(Kind::StopGranted, (7, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::ReplyToStopRequest((7, 0)),
+        Step::Expect(Kind::StopGranted, (7, 0)),
+        Step::Resign,
+    ];
+    feds
+}
+
+const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "pipeline",
+        federates: pipeline,
+        final_tags: &[(0, None), (1, Some((0, 0)))],
+    },
+    Scenario {
+        name: "diamond",
+        federates: diamond,
+        final_tags: &[
+            (0, None),
+            (1, Some((0, 0))),
+            (2, Some((0, 0))),
+            (3, Some((0, 0))),
+        ],
+    },
+    Scenario {
+        name: "cycle with delay",
+        federates: cycle_with_delay,
+        final_tags: &[(0, Some((20, 0))), (1, Some((0, 0)))],
+    },
+    Scenario {
+        name: "stop request",
+        federates: stop_request,
+        final_tags: &[(0, None), (1, None)],
+    },
+];
+
+/**
+ * Return the TAGs received by the federate before it resigned. PTAGs are left
+ * out because whether one precedes a TAG depends on when the LTCs of upstream
+ * federates arrive, and so are grants in flight when the federate resigns.
+ */
+fn tags_before_resigning(log: &Log, fed_id: u16) -> Vec<RelTag> {
+    log.events()
+        .iter()
+        .take_while(|event| **event != Event::Sent(fed_id, Kind::Resign, (0, 0)))
+        .filter_map(|event| match event {
+            Event::Received(id, Kind::Tag, tag) if *id == fed_id => Some(*tag),
+            _ => None,
+        })
+        .collect()
+}
+
+/**
+ * Return the last TAG received by the federate before it resigned, if any.
+ */
+fn final_tag(log: &Log, fed_id: u16) -> Option<RelTag> {
+    tags_before_resigning(log, fed_id).last().copied()
+}
+
+/**
+ * Describe how the grants in the two logs differ, one line per federate.
+ */
+fn divergences(scenario: &Scenario, log: &Log, reference: &Log) -> Vec<String> {
+    let mut divergences = Vec::new();
+    for (fed_id, _) in scenario.final_tags {
+        let tags = tags_before_resigning(log, *fed_id);
+        let reference_tags = tags_before_resigning(reference, *fed_id);
+        if tags != reference_tags {
+            divergences.push(format!(
+                "{}: federate {} was granted {:?} instead of {:?}",
+                scenario.name, fed_id, tags, reference_tags
+            ));
+        }
+        let tag = final_tag(log, *fed_id);
+        let reference_tag = final_tag(reference, *fed_id);
+        if tag != reference_tag {
+            divergences.push(format!(
+                "{}: federate {} ended at {:?} instead of {:?}",
+                scenario.name, fed_id, tag, reference_tag
+            ));
+        }
+    }
+    divergences
+}
+
+#[test]
+fn scenarios_reach_the_expected_final_tags() {
+    for scenario in SCENARIOS {
+        let log = run_federation((scenario.federates)(), &[]);
+        for (fed_id, expected) in scenario.final_tags {
+            assert_eq!(
+                final_tag(&log, *fed_id),
+                *expected,
+                "{}: final tag of federate {} in {:?}",
+                scenario.name,
+                fed_id,
+                log.events()
+            );
+        }
+    }
+}
+
+#[test]
+fn scenarios_match_the_c_rti() {
+    let Ok(c_rti) = env::var("LF_C_RTI") else {
+        println!("Set LF_C_RTI to the executable of the C RTI to compare with it.");
+        return;
+    };
+    let mut all_divergences = Vec::new();
+    for scenario in SCENARIOS {
+        let log = run_federation((scenario.federates)(), &[]);
+        let reference = run_federation_on(&c_rti, (scenario.federates)());
+        all_divergences.extend(divergences(scenario, &log, &reference));
+    }
+    assert!(
+        all_divergences.is_empty(),
+        "Divergences from the C RTI:\n{}",
+        all_divergences.join("\n")
+    );
+}
@@ -0,0 +1,94 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the protocol state machine of federate connections.
+ */
+use rti::ConnectionState;
+use rti::ConnectionState::*;
+
+const MSG_TYPE_FED_IDS: u8 = 1;
+const MSG_TYPE_TIMESTAMP: u8 = 2;
+const MSG_TYPE_RESIGN: u8 = 4;
+const MSG_TYPE_NEXT_EVENT_TAG: u8 = 6;
+const MSG_TYPE_LOGICAL_TAG_COMPLETE: u8 = 9;
+const MSG_TYPE_STOP_REQUEST: u8 = 10;
+const MSG_TYPE_STOP_REQUEST_REPLY: u8 = 11;
+const MSG_TYPE_NEIGHBOR_STRUCTURE: u8 = 24;
+const MSG_TYPE_FAILED: u8 = 25;
+const MSG_TYPE_UDP_PORT: u8 = 254;
+
+/**
+ * Feed the given message types to a new connection and return the state after
+ * each of them, stopping at the first out-of-order message.
+ */
+fn run(message_types: &[u8]) -> Vec<Option<ConnectionState>> {
+    let mut state = AwaitingFedIds;
+    let mut states = vec![];
+    for message_type in message_types {
+        let next = state.next(*message_type);
+        states.push(next);
+        match next {
+            Some(next) => state = next,
+            None => break,
+        }
+    }
+    states
+}
+
+#[test]
+fn connection_follows_the_protocol_to_resignation() {
+    assert_eq!(
+        run(&[
+            MSG_TYPE_FED_IDS,
+            MSG_TYPE_NEIGHBOR_STRUCTURE,
+            MSG_TYPE_UDP_PORT,
+            MSG_TYPE_TIMESTAMP,
+            MSG_TYPE_NEXT_EVENT_TAG,
+            MSG_TYPE_LOGICAL_TAG_COMPLETE,
+            MSG_TYPE_STOP_REQUEST,
+            MSG_TYPE_STOP_REQUEST_REPLY,
+            MSG_TYPE_RESIGN,
+        ]),
+        vec![
+            Some(AwaitingNeighbors),
+            Some(AwaitingUdpPort),
+            Some(AwaitingTimestamp),
+            Some(Running),
+            Some(Running),
+            Some(Running),
+            Some(Stopping),
+            Some(Stopping),
+            Some(Closed),
+        ]
+    );
+}
+
+#[test]
+fn handshake_messages_out_of_order_are_rejected() {
+    assert_eq!(
+        run(&[MSG_TYPE_FED_IDS, MSG_TYPE_UDP_PORT]),
+        vec![Some(AwaitingNeighbors), None]
+    );
+    assert_eq!(run(&[MSG_TYPE_TIMESTAMP]), vec![None]);
+    assert_eq!(run(&[MSG_TYPE_RESIGN]), vec![None]);
+}
+
+#[test]
+fn running_federates_cannot_repeat_the_handshake() {
+    let states = run(&[
+        MSG_TYPE_FED_IDS,
+        MSG_TYPE_NEIGHBOR_STRUCTURE,
+        MSG_TYPE_UDP_PORT,
+        MSG_TYPE_TIMESTAMP,
+        MSG_TYPE_FED_IDS,
+    ]);
+    assert_eq!(states.last(), Some(&None));
+    assert_eq!(Running.next(MSG_TYPE_TIMESTAMP), None);
+    assert_eq!(Running.next(MSG_TYPE_STOP_REQUEST_REPLY), None);
+    assert_eq!(AwaitingTimestamp.next(MSG_TYPE_NEXT_EVENT_TAG), None);
+    assert_eq!(AwaitingTimestamp.next(MSG_TYPE_FAILED), Some(Closed));
+    assert_eq!(Closed.next(MSG_TYPE_RESIGN), None);
+}
@@ -0,0 +1,2920 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Regression tests of the global ordering of grants for small federations.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use lf_tag::Tag;
+use rti::{ForwardedMessage, OutputKey, PayloadInspector, TraceEvent, TraceHeader, TraceRecord};
+
+#[test]
+fn pipeline_grants_follow_upstream_completion() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    log.assert_before(
+        &Event::Sent(0, Kind::Ltc, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+}
+
+#[test]
+fn diamond_grants_wait_for_both_branches() {
+    let mut feds: Vec<ScriptedFederate> = (0..4).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 0, 2, NO_DELAY);
+    connect(&mut feds, 1, 3, NO_DELAY);
+    connect(&mut feds, 2, 3, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    for id in 1..3 {
+        feds[id].steps = vec![
+            Step::Send(Kind::Net, (0, 0)),
+            Step::Expect(Kind::Tag, (0, 0)),
+            Step::Sleep(50 * id as u64),
+            Step::Send(Kind::Ltc, (0, 0)),
+            Step::Resign,
+        ];
+    }
+    feds[3].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    let tag_to_3 = Event::Received(3, Kind::Tag, (0, 0));
+    log.assert_before(&Event::Sent(1, Kind::Ltc, (0, 0)), &tag_to_3);
+    log.assert_before(&Event::Sent(2, Kind::Ltc, (0, 0)), &tag_to_3);
+}
+
+#[test]
+fn cycle_with_delay_advances_both_federates() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 0, 10);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Send(Kind::Net, (20, 0)),
+        Step::Expect(Kind::Tag, (20, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Send(Kind::Net, (30, 0)),
+        Step::Sleep(300),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    log.assert_before(
+        &Event::Sent(0, Kind::Ltc, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+    // Federate 1 cannot send anything earlier than (40, 0) to federate 0 once
+    // its next event is at (30, 0), so federate 0 may advance to (20, 0).
+    log.assert_before(
+        &Event::Sent(1, Kind::Net, (30, 0)),
+        &Event::Received(0, Kind::Tag, (20, 0)),
+    );
+}
+
+#[test]
+fn source_federate_receives_the_dnet_of_its_downstream_federate() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10_000_000);
+    feds[0].steps = vec![
+        // A message sent before 90 ms cannot arrive before the next event
+        // tag of federate 1, so the NETs up to then are not needed.
+        Step::Expect(Kind::Dnet, (89_999_999, u32::MAX)),
+        Step::Send(Kind::Net, (95_000_000, 0)),
+        Step::Send(Kind::Ltc, (95_000_000, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (100_000_000, 0)),
+        Step::Expect(Kind::Tag, (100_000_000, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    log.assert_before(
+        &Event::Received(0, Kind::Dnet, (89_999_999, u32::MAX)),
+        &Event::Received(1, Kind::Tag, (100_000_000, 0)),
+    );
+}
+
+#[test]
+fn dnet_is_not_sent_to_federates_with_upstream_federates() {
+    let mut feds = vec![
+        ScriptedFederate::new(0),
+        ScriptedFederate::new(1),
+        ScriptedFederate::new(2),
+    ];
+    connect(&mut feds, 0, 1, 10_000_000);
+    connect(&mut feds, 1, 2, 10_000_000);
+    feds[0].steps = vec![
+        Step::Expect(Kind::Dnet, (79_999_999, u32::MAX)),
+        Step::Send(Kind::Net, (200_000_000, 0)),
+        Step::Sleep(100),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (200_000_000, 0)),
+        Step::Sleep(200),
+        Step::Resign,
+    ];
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (100_000_000, 0)),
+        Step::Expect(Kind::Tag, (100_000_000, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    assert!(!log
+        .events()
+        .iter()
+        .any(|event| matches!(event, Event::Received(1 | 2, Kind::Dnet, _))));
+}
+
+#[test]
+fn dnet_is_not_sent_when_it_is_disabled() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10_000_000);
+    feds[0].steps = vec![
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (200_000_000, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (100_000_000, 0)),
+        Step::Expect(Kind::Tag, (100_000_000, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--disable_dnet"]);
+    assert!(!log
+        .events()
+        .iter()
+        .any(|event| matches!(event, Event::Received(_, Kind::Dnet, _))));
+}
+
+#[test]
+fn federate_is_enabled_only_the_capabilities_that_the_rti_supports() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10_000_000);
+    // Compression (bit 0) and unknown bits are not enabled. DNET messages
+    // are sent without being negotiated.
+    feds[0].capabilities = Some((0x8000_000b, 0b1000));
+    feds[0].steps = vec![
+        Step::Expect(Kind::Dnet, (89_999_999, u32::MAX)),
+        Step::Send(Kind::Net, (95_000_000, 0)),
+        Step::Send(Kind::Ltc, (95_000_000, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (100_000_000, 0)),
+        Step::Expect(Kind::Tag, (100_000_000, 0)),
+        Step::Resign,
+    ];
+    run_federation(feds, &[]);
+}
+
+#[test]
+fn federate_offering_fast_mode_is_told_whether_the_federation_runs_in_fast_mode() {
+    for (args, enabled) in [(&["--fast"][..], 0b10_0000), (&[][..], 0)] {
+        let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+        connect(&mut feds, 0, 1, NO_DELAY);
+        // Fast mode is bit 5. Federate 1 does not offer it.
+        feds[0].capabilities = Some((0b10_0000, enabled));
+        feds[0].steps = vec![Step::Send(Kind::Ltc, (0, 0)), Step::Resign];
+        feds[1].steps = vec![
+            Step::Send(Kind::Net, (0, 0)),
+            Step::Expect(Kind::Tag, (0, 0)),
+            Step::Resign,
+        ];
+        let log = run_federation(feds, args);
+        assert!(log.failed_federates().is_empty());
+    }
+}
+
+#[test]
+fn federate_resumes_its_session_after_a_lost_connection() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10_000_000);
+    // Resumption is bit 4.
+    feds[0].capabilities = Some((0b1_0000, 0b1_0000));
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (50_000_000, 0)),
+        Step::Sleep(100),
+        Step::Reconnect,
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (200_000_000, 0)),
+        Step::Sleep(100),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (100_000_000, 0)),
+        Step::Expect(Kind::Tag, (100_000_000, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--resume_window", "2000"]);
+    assert!(log.failed_federates().is_empty());
+    // The RTI held the NET of federate 0 while its connection was lost.
+    log.assert_before(
+        &Event::Sent(0, Kind::Net, (200_000_000, 0)),
+        &Event::Received(1, Kind::Tag, (100_000_000, 0)),
+    );
+}
+
+#[test]
+fn suspended_federate_fails_once_the_resumption_window_expires() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10_000_000);
+    feds[0].capabilities = Some((0b1_0000, 0b1_0000));
+    // The connection is closed without resigning after the last step.
+    feds[0].steps = vec![Step::Send(Kind::Net, (50_000_000, 0))];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (100_000_000, 0)),
+        // Once the window expires, the failed federate no longer holds back
+        // its downstream federates.
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--resume_window", "200"]);
+    assert_eq!(log.failed_federates(), &[0]);
+}
+
+#[test]
+fn transient_federate_joins_again_after_the_grants_of_its_downstream_federate() {
+    // The third entry is federate 0 joining again after it has resigned.
+    let mut feds = vec![
+        ScriptedFederate::new(0),
+        ScriptedFederate::new(1),
+        ScriptedFederate::new(0),
+    ];
+    feds[0].downstream = vec![1];
+    feds[2].downstream = vec![1];
+    feds[1].upstream = vec![(0, 10_000_000)];
+    feds[0].transient = true;
+    feds[0].steps = vec![Step::Resign];
+    feds[1].steps = vec![
+        Step::Sleep(100),
+        // Federate 0 is absent, so federate 1 is granted its next event, but
+        // not FOREVER.
+        Step::Send(Kind::Net, (200_000_000, 0)),
+        Step::Expect(Kind::Tag, (200_000_000, 0)),
+        Step::Sleep(250),
+        Step::Send(Kind::Net, (500_000_000, 0)),
+        Step::Expect(Kind::Tag, (500_000_000, 0)),
+        Step::Resign,
+    ];
+    feds[2].transient = true;
+    feds[2].join_delay_ms = 250;
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (300_000_000, 0)),
+        Step::Sleep(200),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--transient", "0"]);
+    assert!(log.contains(&Event::Received(0, Kind::StartTag, (0, 0))));
+    // A message sent at the start tag arrives 10 ms later, after the grant.
+    assert!(log.contains(&Event::Received(0, Kind::StartTag, (190_000_001, 0))));
+    assert!(!log.contains(&Event::Received(1, Kind::Tag, FOREVER_TAG)));
+    // While federate 0 is back, its NET holds back the grant of federate 1.
+    let rejoined_resign = log
+        .events()
+        .iter()
+        .rposition(|event| *event == Event::Sent(0, Kind::Resign, (0, 0)))
+        .unwrap();
+    assert!(rejoined_resign < log.position(&Event::Received(1, Kind::Tag, (500_000_000, 0))));
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn stop_request_is_granted_to_all_federates() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10);
+    feds[0].steps = vec![
+        Step::Send(Kind::StopRequest, (5, 0)),
+        Step::Expect(Kind::StopGranted, (7, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::ReplyToStopRequest((7, 0)),
+        Step::Expect(Kind::StopGranted, (7, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    log.assert_before(
+        &Event::Sent(1, Kind::StopRequestReply, (7, 0)),
+        &Event::Received(0, Kind::StopGranted, (7, 0)),
+    );
+    assert!(log.contains(&Event::Received(1, Kind::StopRequest, (5, 0))));
+}
+
+#[test]
+fn drain_stops_the_federation_no_earlier_than_the_given_tag() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10);
+    feds[0].steps = vec![
+        Step::Admin("drain 5 0", "OK draining until (5,0)"),
+        Step::Admin("drain", "ERROR the federation is stopping already"),
+        Step::ReplyToStopRequest((5, 0)),
+        Step::Expect(Kind::StopGranted, (7, 0)),
+        Step::Resign,
+    ];
+    // Federate 1 is past the drain tag.
+    feds[1].steps = vec![
+        Step::ReplyToStopRequest((7, 0)),
+        Step::Expect(Kind::StopGranted, (7, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    assert!(log.contains(&Event::Received(0, Kind::StopRequest, (5, 0))));
+    assert!(log.contains(&Event::Received(1, Kind::StopRequest, (5, 0))));
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn failure_degrades_to_resignation_by_default() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Fail];
+    feds[1].steps = vec![
+        // Like a resigned federate, the failed one no longer holds back its
+        // downstream federates.
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    assert!(!log.contains(&Event::Received(1, Kind::StopRequest, (0, 0))));
+    assert_eq!(log.failed_federates(), &[0]);
+}
+
+#[test]
+fn failure_aborts_the_federation_with_abort_policy() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Fail];
+    feds[1].steps = vec![
+        Step::ReplyToStopRequest((5, 0)),
+        Step::Expect(Kind::StopGranted, (5, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--on_failure", "abort"]);
+    assert!(log.contains(&Event::Received(1, Kind::StopRequest, (0, 0))));
+    assert_eq!(log.failed_federates(), &[0]);
+}
+
+#[test]
+fn group_policy_overrides_the_failure_policy_of_the_rti() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Fail];
+    feds[1].steps = vec![Step::Expect(Kind::Tag, FOREVER_TAG), Step::Resign];
+    // The failure of a sensor does not abort the federation.
+    let log = run_federation(
+        feds,
+        &[
+            "--on_failure",
+            "abort",
+            "--group",
+            "sensors=0",
+            "--group_policy",
+            "sensors:on_failure=degrade",
+        ],
+    );
+    assert!(!log.contains(&Event::Received(1, Kind::StopRequest, (0, 0))));
+    assert_eq!(log.grants_to(1), vec![(Kind::Tag, FOREVER_TAG)]);
+    assert_eq!(log.failed_federates(), &[0]);
+}
+
+#[test]
+fn group_policy_leaves_the_failure_policy_of_other_federates() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::ReplyToStopRequest((5, 0)),
+        Step::Expect(Kind::StopGranted, (5, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![Step::Fail];
+    // Federate 1 is not a sensor, so its failure aborts the federation.
+    let log = run_federation(
+        feds,
+        &[
+            "--on_failure",
+            "abort",
+            "--group",
+            "sensors=0",
+            "--group_policy",
+            "sensors:on_failure=degrade",
+        ],
+    );
+    assert!(log.contains(&Event::Received(0, Kind::StopRequest, (0, 0))));
+    assert_eq!(log.failed_federates(), &[1]);
+}
+
+#[test]
+fn ltc_beyond_granted_tag_is_ignored() {
+    let mut feds: Vec<ScriptedFederate> = (0..3).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 2, NO_DELAY);
+    feds[0].steps = vec![Step::Sleep(200), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Ltc, (10, 0)),
+        Step::Sleep(100),
+        Step::Resign,
+    ];
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (5, 0)),
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    // Had the LTC been accepted, federate 2 would have been granted (5,0).
+    assert_eq!(log.grants_to(2), vec![(Kind::Tag, FOREVER_TAG)]);
+    log.assert_before(
+        &Event::Sent(1, Kind::Resign, (0, 0)),
+        &Event::Received(2, Kind::Tag, FOREVER_TAG),
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn federate_violating_the_protocol_is_quarantined() {
+    let mut feds: Vec<ScriptedFederate> = (0..3).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 2, NO_DELAY);
+    feds[0].steps = vec![Step::Sleep(200), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Ltc, (10, 0)),
+        // ErrType::ProtocolViolation
+        Step::Expect(Kind::Reject, (8, 0)),
+    ];
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (5, 0)),
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--quarantine"]);
+    assert_eq!(log.grants_to(2), vec![(Kind::Tag, FOREVER_TAG)]);
+    assert_eq!(log.failed_federates(), &[1]);
+}
+
+#[test]
+fn message_to_a_federate_outside_the_federation_is_a_protocol_violation() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // The payload is discarded, and the messages after it are still read.
+    feds[0].steps = vec![
+        Step::SendMessage(7, (0, 0), 100),
+        Step::SendMessage(1, (0, 0), 10),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    log.assert_before(
+        &Event::Received(1, Kind::Message, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+    assert!(log.failed_federates().is_empty());
+
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::SendMessage(7, (0, 0), 100),
+        // ErrType::ProtocolViolation
+        Step::Expect(Kind::Reject, (8, 0)),
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--quarantine"]);
+    assert_eq!(log.grants_to(1), vec![(Kind::Tag, FOREVER_TAG)]);
+    assert_eq!(log.failed_federates(), &[0]);
+}
+
+#[test]
+fn net_earlier_than_the_start_time_is_clamped() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (-5, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    // A clamped NET is not a protocol violation.
+    let log = run_federation(feds, &["--quarantine"]);
+    log.assert_before(
+        &Event::Sent(0, Kind::Ltc, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn net_earlier_than_the_start_time_is_rejected() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Sleep(200), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (-5, 0)),
+        // ErrType::ProtocolViolation
+        Step::Expect(Kind::Reject, (8, 0)),
+    ];
+    let log = run_federation(feds, &["--early_net", "reject"]);
+    assert!(log.grants_to(1).is_empty());
+    assert_eq!(log.failed_federates(), &[1]);
+}
+
+/// Run the federates with --strict and return the log and the reason for
+/// which the RTI shut down, if it did.
+fn run_strict_federation(feds: Vec<ScriptedFederate>) -> (Log, Option<String>) {
+    let shutdown = Arc::new(Mutex::new(None));
+    let handle = shutdown.clone();
+    let log = run_federation_with(feds, &["--strict"], move |_f_rti| {
+        *handle.lock().unwrap() = Some(_f_rti.shutdown_handle());
+    });
+    let reason = shutdown
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|shutdown| shutdown.reason());
+    (log, reason)
+}
+
+#[test]
+fn strict_mode_shuts_down_on_a_net_earlier_than_the_start_time() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Expect(Kind::Failed, (0, 0))];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (-5, 0)),
+        Step::Expect(Kind::Failed, (0, 0)),
+    ];
+    let (log, reason) = run_strict_federation(feds);
+    let reason = reason.expect("the RTI did not shut down");
+    assert!(reason.starts_with("NET "), "{}", reason);
+    assert!(
+        reason.ends_with("of federate 1 earlier than the start time in strict mode"),
+        "{}",
+        reason
+    );
+    assert!(log.grants_to(1).is_empty());
+}
+
+#[test]
+fn strict_mode_shuts_down_on_inconsistent_neighbor_structures() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    // Federate 0 does not list federate 1 as downstream.
+    feds[1].upstream = vec![(0, NO_DELAY)];
+    for fed in &mut feds {
+        fed.steps = vec![Step::Expect(Kind::Failed, (0, 0))];
+    }
+    let (log, reason) = run_strict_federation(feds);
+    assert_eq!(
+        reason.as_deref(),
+        Some("inconsistent neighbor structures: Federate 1 lists federate 0 as upstream, but federate 0 does not list federate 1 as downstream in strict mode")
+    );
+    // The federation does not start.
+    assert!(log.contains(&Event::Received(0, Kind::Failed, (0, 0))));
+    assert!(log.contains(&Event::Received(1, Kind::Failed, (0, 0))));
+}
+
+#[test]
+fn strict_mode_shuts_down_on_a_duplicate_stop_request() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::StopRequest, (5, 0)),
+        Step::Send(Kind::StopRequest, (5, 0)),
+        Step::Expect(Kind::Failed, (0, 0)),
+    ];
+    feds[1].steps = vec![Step::Expect(Kind::Failed, (0, 0))];
+    let (_, reason) = run_strict_federation(feds);
+    let reason = reason.expect("the RTI did not shut down");
+    assert!(reason.starts_with("duplicate message type "), "{}", reason);
+    assert!(
+        reason.ends_with("from federate 0 in strict mode"),
+        "{}",
+        reason
+    );
+}
+
+#[test]
+fn single_scheduling_thread_serves_all_federates() {
+    let mut feds: Vec<ScriptedFederate> = (0..3).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 2, NO_DELAY);
+    for id in 0..2 {
+        feds[id].steps = vec![
+            Step::Send(Kind::Net, (0, 0)),
+            Step::Sleep(50),
+            Step::Send(Kind::Ltc, (0, 0)),
+            Step::Resign,
+        ];
+    }
+    feds[1].steps.insert(1, Step::Expect(Kind::Tag, (0, 0)));
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--io_threads", "1", "--scheduling_threads", "1"]);
+    log.assert_before(
+        &Event::Sent(0, Kind::Ltc, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+    log.assert_before(
+        &Event::Sent(1, Kind::Ltc, (0, 0)),
+        &Event::Received(2, Kind::Tag, (0, 0)),
+    );
+}
+
+#[test]
+fn pinned_threads_still_serve_all_federates() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    // If realtime scheduling is not permitted, the RTI only warns.
+    let log = run_federation(
+        feds,
+        &[
+            "--io_cpus",
+            "0",
+            "--scheduling_cpus",
+            "0-0,0",
+            "--realtime_priority",
+            "10",
+        ],
+    );
+    log.assert_before(
+        &Event::Sent(0, Kind::Ltc, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+}
+
+#[test]
+fn lock_stats_do_not_change_grants() {
+    let mut feds: Vec<ScriptedFederate> = (0..3).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 2, NO_DELAY);
+    connect(&mut feds, 1, 2, NO_DELAY);
+    for id in 0..2 {
+        feds[id].steps = vec![
+            Step::Send(Kind::Net, (0, 0)),
+            Step::Send(Kind::Ltc, (0, 0)),
+            Step::Resign,
+        ];
+    }
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--lock_stats"]);
+    let tag_to_2 = Event::Received(2, Kind::Tag, (0, 0));
+    log.assert_before(&Event::Sent(0, Kind::Ltc, (0, 0)), &tag_to_2);
+    log.assert_before(&Event::Sent(1, Kind::Ltc, (0, 0)), &tag_to_2);
+}
+
+/// A pipeline in which both federates repeat a NET, so that some grant
+/// decisions are made again while none of their inputs has changed.
+fn repeated_net_pipeline() -> Vec<ScriptedFederate> {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Send(Kind::Net, (20, 0)),
+        Step::Send(Kind::Net, (20, 0)),
+        Step::Sleep(150),
+        Step::Send(Kind::Ltc, (20, 0)),
+        Step::Send(Kind::Net, (50, 0)),
+        Step::Send(Kind::Net, (50, 0)),
+        Step::Sleep(50),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Sleep(50),
+        Step::Send(Kind::Net, (20, 0)),
+        Step::Expect(Kind::Tag, (20, 0)),
+        Step::Send(Kind::Net, (20, 0)),
+        Step::Send(Kind::Ltc, (20, 0)),
+        Step::Send(Kind::Net, (40, 0)),
+        Step::Send(Kind::Net, (40, 0)),
+        Step::Expect(Kind::Tag, (40, 0)),
+        Step::Resign,
+    ];
+    feds
+}
+
+#[test]
+fn reused_grant_decisions_are_the_recomputed_ones() {
+    let cached = run_federation(repeated_net_pipeline(), &[]);
+    let recomputed = run_federation(repeated_net_pipeline(), &["--no_grant_cache"]);
+    for fed_id in 0..2 {
+        assert_eq!(cached.grants_to(fed_id), recomputed.grants_to(fed_id));
+    }
+    // The LTC of (0,0) allows up to (9,MAX) before federate 1 sends a NET.
+    assert_eq!(
+        cached.grants_to(1),
+        vec![
+            (Kind::Tag, (9, u32::MAX)),
+            (Kind::Tag, (20, 0)),
+            (Kind::Tag, (40, 0))
+        ]
+    );
+    cached.assert_before(
+        &Event::Sent(0, Kind::Net, (50, 0)),
+        &Event::Received(1, Kind::Tag, (40, 0)),
+    );
+}
+
+/// The repeated NET pipeline with a federate downstream of it, whose NETs
+/// change none of the inputs of the grant decisions of the pipeline.
+fn repeated_net_pipeline_with_downstream_federate() -> Vec<ScriptedFederate> {
+    let mut feds = repeated_net_pipeline();
+    feds.push(ScriptedFederate::new(2));
+    connect(&mut feds, 1, 2, 10);
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (10, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (30, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (60, 0)),
+        Step::Sleep(100),
+        Step::Resign,
+    ];
+    feds
+}
+
+#[test]
+fn grant_decisions_reused_across_changes_downstream_are_the_recomputed_ones() {
+    let cached = run_federation(repeated_net_pipeline_with_downstream_federate(), &[]);
+    let recomputed = run_federation(
+        repeated_net_pipeline_with_downstream_federate(),
+        &["--no_grant_cache"],
+    );
+    for fed_id in 0..2 {
+        assert_eq!(cached.grants_to(fed_id), recomputed.grants_to(fed_id));
+    }
+}
+
+#[test]
+fn decentralized_upstream_does_not_constrain_grants() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Sleep(200), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (10, 0)),
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--decentralized", "0"]);
+    // Federate 1 is as unconstrained as if it had no upstream federate.
+    log.assert_before(
+        &Event::Received(1, Kind::Tag, FOREVER_TAG),
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+    );
+}
+
+#[test]
+fn isolated_federates_are_granted_forever_at_startup() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    for fed in feds.iter_mut() {
+        fed.steps = vec![Step::Expect(Kind::Tag, FOREVER_TAG), Step::Resign];
+    }
+    let log = run_federation(feds, &[]);
+    assert_eq!(log.grants_to(0), vec![(Kind::Tag, FOREVER_TAG)]);
+    assert_eq!(log.grants_to(1), vec![(Kind::Tag, FOREVER_TAG)]);
+}
+
+#[test]
+fn large_message_is_forwarded_intact_before_the_grant() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        // Much larger than the chunks in which the RTI forwards payloads.
+        Step::SendMessage(1, (0, 0), 4 * 1024 * 1024 + 17),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    log.assert_before(
+        &Event::Received(1, Kind::Message, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+}
+
+#[test]
+fn stalled_relay_does_not_stall_the_federation() {
+    let mut feds: Vec<ScriptedFederate> = (0..5).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 3, 4, NO_DELAY);
+    // Federate 1 does not read while the message, which is larger than the
+    // socket buffers, is relayed to it, so the relay keeps its turn to write
+    // to federate 1 until it does.
+    feds[0].steps = vec![Step::SendMessage(1, (0, 0), 48 * 1024 * 1024), Step::Resign];
+    feds[1].steps = vec![
+        Step::Sleep(5000),
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Message, (0, 0)),
+        Step::Resign,
+    ];
+    // The stop request is forwarded to federate 1, which has to wait for the
+    // relay without holding the lock of the RTI state.
+    feds[2].steps = vec![
+        Step::Sleep(2500),
+        Step::Send(Kind::StopRequest, (5, 0)),
+        Step::Resign,
+    ];
+    feds[3].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(3500),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[4].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    // Each federate is served by its own thread, so that only the lock of the
+    // RTI state could keep the LTC of federate 3 from being handled.
+    let log = run_federation(feds, &["--scheduling_threads", "5"]);
+    log.assert_before(
+        &Event::Received(4, Kind::Tag, (0, 0)),
+        &Event::Sent(1, Kind::Net, (0, 0)),
+    );
+    assert!(log.contains(&Event::Received(1, Kind::StopRequest, (5, 0))));
+}
+
+#[test]
+fn message_exceeding_the_maximum_size_is_rejected() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::SendMessage(1, (0, 0), 2048),
+        // ErrType::MessageTooLarge
+        Step::Expect(Kind::Reject, (9, 0)),
+        // The connection is still usable.
+        Step::SendMessage(1, (0, 0), 1024),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--max_message_size", "1024"]);
+    let received: Vec<&Event> = log
+        .events()
+        .iter()
+        .filter(|event| matches!(event, Event::Received(1, Kind::Message, _)))
+        .collect();
+    assert_eq!(received.len(), 1);
+    log.assert_before(
+        &Event::Received(0, Kind::Reject, (9, 0)),
+        &Event::Received(1, Kind::Message, (0, 0)),
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn messages_in_excess_of_the_egress_rate_are_delayed() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let start = std::time::Instant::now();
+    // The first message fits in the burst, and each of the other four waits
+    // for about 100 ms worth of bytes.
+    let log = run_federation(feds, &["--egress_rate", "1=10000:1100"]);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(400));
+    let received = log
+        .events()
+        .iter()
+        .filter(|event| matches!(event, Event::Received(1, Kind::Message, _)))
+        .count();
+    assert_eq!(received, 5);
+    log.assert_before(
+        &Event::Received(1, Kind::Message, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn delayed_message_does_not_hold_up_grants_to_other_federates_on_the_same_thread() {
+    let mut feds: Vec<ScriptedFederate> = (0..4).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 2, 3, NO_DELAY);
+    // The message to federate 1 waits for about 900 ms worth of bytes.
+    feds[0].steps = vec![
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::Sleep(1500),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![Step::Expect(Kind::Message, (0, 0)), Step::Resign];
+    feds[2].steps = vec![
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Sleep(1500),
+        Step::Resign,
+    ];
+    feds[3].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let start = std::time::Instant::now();
+    let log = run_federation(
+        feds,
+        &["--scheduling_threads", "1", "--egress_rate", "1=1000:100"],
+    );
+    assert!(start.elapsed() >= std::time::Duration::from_millis(800));
+    log.assert_before(
+        &Event::Received(3, Kind::Tag, (0, 0)),
+        &Event::Received(1, Kind::Message, (0, 0)),
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn ltc_is_handled_before_net_updates_that_arrived_with_it() {
+    let path = std::env::temp_dir().join(format!("rti-priority-{}.lft", std::process::id()));
+    let mut feds: Vec<ScriptedFederate> = (0..6).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // Relaying a message larger than the socket buffers to federate 1, which
+    // does not read it yet, keeps the only scheduling thread busy while the
+    // other federates send.
+    feds[0].steps = vec![Step::SendMessage(1, (0, 0), 16 << 20), Step::Resign];
+    feds[1].steps = vec![
+        Step::Sleep(1500),
+        Step::Expect(Kind::Message, (0, 0)),
+        Step::Resign,
+    ];
+    for fed in &mut feds[2..5] {
+        fed.steps = vec![
+            Step::Sleep(1000),
+            Step::Send(Kind::Net, (10, 0)),
+            Step::Sleep(1500),
+            Step::Resign,
+        ];
+    }
+    feds[5].steps = vec![
+        Step::Sleep(1000),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Sleep(1500),
+        Step::Resign,
+    ];
+    run_federation(
+        feds,
+        &[
+            "--scheduling_threads",
+            "1",
+            "--trace_file",
+            path.to_str().unwrap(),
+        ],
+    );
+    let mut file = std::fs::File::open(&path).unwrap();
+    let header = TraceHeader::read(&mut file).unwrap();
+    let mut records = Vec::new();
+    while let Some(block) = TraceRecord::read_block(&mut file).unwrap() {
+        records.extend(block);
+    }
+    std::fs::remove_file(&path).unwrap();
+    let position = |event: TraceEvent, src_id: i32| {
+        records
+            .iter()
+            .position(|record| record.event() == event && record.src_id() == src_id)
+            .unwrap_or_else(|| panic!("no {:?} record from {}", event, src_id))
+    };
+    let ltc = position(TraceEvent::ReceiveLtc, 5);
+    for fed_id in 2..5 {
+        assert!(ltc < position(TraceEvent::ReceiveNet, fed_id));
+    }
+    assert_eq!(header.number_of_federates(), 6);
+}
+
+#[test]
+fn messages_in_excess_of_the_egress_rate_are_dropped_under_the_drop_policy() {
+    let mut feds = vec![
+        ScriptedFederate::new(0),
+        ScriptedFederate::new(1),
+        ScriptedFederate::new(2),
+    ];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 0, 2, NO_DELAY);
+    feds[0].steps = vec![
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::SendMessage(1, (0, 0), 1000),
+        Step::SendMessage(1, (0, 0), 1000),
+        // Federate 2 has no limit.
+        Step::SendMessage(2, (0, 0), 1000),
+        Step::SendMessage(2, (0, 0), 1000),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    for fed in feds.iter_mut().skip(1) {
+        fed.steps = vec![
+            Step::Send(Kind::Net, (0, 0)),
+            Step::Expect(Kind::Tag, (0, 0)),
+            Step::Resign,
+        ];
+    }
+    let log = run_federation(
+        feds,
+        &["--egress_rate", "1=1000", "--on_excess_egress", "drop"],
+    );
+    let received = |fed_id| {
+        log.events()
+            .iter()
+            .filter(|event| matches!(event, Event::Received(id, Kind::Message, _) if *id == fed_id))
+            .count()
+    };
+    assert_eq!(received(1), 1);
+    assert_eq!(received(2), 2);
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn paused_federation_withholds_grants_until_resumed() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Admin("pause", "OK paused"),
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Sleep(200),
+        Step::Admin("resume", "OK resumed"),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        // Let the pause take effect first.
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    // The LTC received while paused allows the grant once resumed.
+    log.assert_before(
+        &Event::Admin(0, "resume"),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+    let resumed = log.position(&Event::Admin(0, "resume"));
+    assert!(!log.events()[..resumed]
+        .iter()
+        .any(|event| matches!(event, Event::Received(_, Kind::Tag | Kind::Ptag, _))));
+}
+
+#[test]
+fn step_mode_sends_each_grant_once_released() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Sleep(100),
+        Step::Admin(
+            "pending",
+            "OK 2 pending: #1 PTAG (0,0) to federate 1 because upstream federate 0 may send",
+        ),
+        Step::Admin("release", "OK released PTAG (0,0) to federate 1"),
+        Step::Sleep(100),
+        Step::Admin("step off", "OK step mode off, released"),
+        Step::Admin("pending", "OK no pending grants"),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--step"]);
+    assert_eq!(
+        log.grants_to(1)[..2],
+        [(Kind::Ptag, (0, 0)), (Kind::Tag, (0, 0))]
+    );
+    log.assert_before(
+        &Event::Admin(0, "release"),
+        &Event::Received(1, Kind::Ptag, (0, 0)),
+    );
+    log.assert_before(
+        &Event::Admin(0, "step off"),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+}
+
+#[test]
+fn history_shows_the_recent_events_of_a_federate() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Sleep(200),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        // Only the last event is kept.
+        Step::Admin("history 1", "OK federate 1 tag (0,0) at physical time "),
+        Step::Admin("history 0", "OK federate 0 ltc (0,0) at physical time "),
+        Step::Admin("history 2", "ERROR unknown federate '2'"),
+        Step::Admin("snapshot", "OK {\"schema_version\":2,"),
+        Step::Resign,
+    ];
+    run_federation(feds, &["--history_length", "1"]);
+}
+
+#[test]
+fn ptags_reports_provisional_grants_converted_to_tags() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Sleep(200),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Admin(
+            "ptags 1",
+            "OK federate 1 1 PTAG(s) sent, 1 converted to a TAG of the same tag, 0 wasted, 0 unresolved",
+        ),
+        Step::Admin("ptags all", "OK federate 0 "),
+        Step::Admin("ptags 2", "ERROR unknown federate '2'"),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    assert_eq!(
+        log.grants_to(1)[..2],
+        [(Kind::Ptag, (0, 0)), (Kind::Tag, (0, 0))]
+    );
+}
+
+#[test]
+fn exhausted_memory_budgets_evict_records_and_keep_grants_safe() {
+    let mut feds = vec![
+        ScriptedFederate::new(0),
+        ScriptedFederate::new(1),
+        ScriptedFederate::new(2),
+    ];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 2, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (100, 0)),
+        Step::SendMessage(1, (100, 0), 16),
+        Step::Send(Kind::Ltc, (100, 0)),
+        Step::Send(Kind::Net, (1_000_000, 0)),
+        Step::Sleep(800),
+        Step::Resign,
+    ];
+    // Federate 1 sends a NET later than the message in transit to it, whose
+    // tag holds back federate 2 until federate 1 has completed it although
+    // the budget leaves no room for its record.
+    feds[1].steps = vec![
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (1_000_000, 0)),
+        Step::Expect(Kind::Tag, (100, 0)),
+        Step::Sleep(200),
+        Step::Send(Kind::Ltc, (100, 0)),
+        Step::Send(Kind::Net, (1_000_000, 0)),
+        Step::Sleep(500),
+        Step::Resign,
+    ];
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (200, 0)),
+        Step::Expect(Kind::Tag, (200, 0)),
+        Step::Admin(
+            "memory",
+            "OK in-transit records 0 bytes of 0 used, peak 0, 1 record(s) evicted; \
+             scheduling histories 0 bytes of 0 used, peak 0, ",
+        ),
+        Step::Admin("history 1", "OK federate 1 no events"),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--memory_budget", "in_transit=0,history=0"]);
+    log.assert_before(
+        &Event::Sent(1, Kind::Ltc, (100, 0)),
+        &Event::Received(2, Kind::Tag, (200, 0)),
+    );
+}
+
+#[test]
+fn eimt_explanation_names_the_upstream_path() {
+    let mut feds = vec![
+        ScriptedFederate::new(0),
+        ScriptedFederate::new(1),
+        ScriptedFederate::new(2),
+    ];
+    connect(&mut feds, 0, 1, 10);
+    connect(&mut feds, 1, 2, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (100, 0)),
+        Step::Sleep(200),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (500, 0)),
+        Step::Sleep(200),
+        Step::Resign,
+    ];
+    feds[2].steps = vec![
+        // Let the NETs of the upstream federates arrive first.
+        Step::Sleep(100),
+        Step::Admin(
+            "eimt 2",
+            "OK EIMT of federate 2 is (110,0) from upstream federate 1; \
+             upstream federate 1 over a connection with delay none: \
+             earliest next event (110,0) is the next event of federate 0 via 1 (delay 10 ns)",
+        ),
+        Step::Admin("eimt all", "ERROR eimt needs a single federate"),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn breakpoint_holds_grants_until_continued() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Admin(
+            "break all 100 0",
+            "OK breakpoint at (100,0) for all federates",
+        ),
+        Step::Send(Kind::Net, (300, 0)),
+        Step::Sleep(200),
+        Step::Admin("breakpoints", "OK breakpoints: all (100,0)"),
+        Step::Admin("continue all", "OK continued all federates"),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        // Let the breakpoint take effect first.
+        Step::Sleep(100),
+        Step::Send(Kind::Net, (200, 0)),
+        Step::Expect(Kind::Tag, (200, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    // Federate 1 is granted up to the breakpoint instead of its NET.
+    assert_eq!(
+        log.grants_to(1)[..2],
+        [(Kind::Tag, (100, 0)), (Kind::Tag, (200, 0))]
+    );
+    log.assert_before(
+        &Event::Admin(0, "continue all"),
+        &Event::Received(1, Kind::Tag, (200, 0)),
+    );
+}
+
+#[test]
+fn tag_that_is_not_a_multiple_of_the_time_resolution_is_a_protocol_violation() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        // 1.5 ms with a resolution of 1 ms.
+        Step::Send(Kind::Net, (1_500_000, 0)),
+        // ErrType::ProtocolViolation
+        Step::Expect(Kind::Reject, (8, 0)),
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (2_000_000, 0)),
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--time_resolution", "ms", "--quarantine"]);
+    assert_eq!(log.grants_to(1), vec![(Kind::Tag, FOREVER_TAG)]);
+    assert_eq!(log.failed_federates(), &[0]);
+}
+
+#[test]
+fn event_stream_records_each_scheduling_decision() {
+    let path = std::env::temp_dir().join(format!("rti-events-{}.ndjson", std::process::id()));
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    run_federation(feds, &["--event_stream", path.to_str().unwrap()]);
+    let events = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<&str> = events.lines().collect();
+    assert!(lines
+        .iter()
+        .all(|line| line.starts_with("{\"physical_time\":") && line.ends_with('}')));
+    let position = |pattern: &str| {
+        lines
+            .iter()
+            .position(|line| line.contains(pattern))
+            .unwrap_or_else(|| panic!("no event {} in {}", pattern, events))
+    };
+    let ltc = position("\"event\":\"ltc\",\"federate\":0,\"tag\":[0,0]");
+    let tag = position("\"event\":\"tag\",\"federate\":1,\"tag\":[0,0],\"reason\":");
+    assert!(ltc < tag);
+    position("\"event\":\"net\",\"federate\":1,\"tag\":[0,0]");
+    position("\"event\":\"resigned\",\"federate\":0");
+}
+
+/// Parses the arguments of a two-federate federation with the given event
+/// stream target.
+fn process_event_stream_args(target: &str) -> Result<(), rti::RtiError> {
+    let mut rti = rti::initialize_rti();
+    let args: Vec<String> = [
+        "rti",
+        "-i",
+        FEDERATION_ID,
+        "-n",
+        "2",
+        "--event_stream",
+        target,
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    rti::process_args(&mut rti, &args)
+}
+
+#[test]
+fn event_stream_target_that_is_a_number_is_a_file_path() {
+    let name = format!("{}", 4_000_000_000u64 + u64::from(std::process::id()));
+    process_event_stream_args(&name).unwrap();
+    let created = std::path::Path::new(&name).is_file();
+    std::fs::remove_file(&name).unwrap();
+    assert!(created);
+}
+
+#[cfg(unix)]
+#[test]
+fn event_stream_rejects_a_file_descriptor_that_is_not_open() {
+    let error = process_event_stream_args("fd:1000000").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid arguments: Fail to open the event stream"
+    );
+    assert!(process_event_stream_args("fd:-1").is_err());
+    assert!(process_event_stream_args("fd:three").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn event_stream_writes_to_an_inherited_file_descriptor() {
+    use std::os::unix::io::AsRawFd;
+    let path = std::env::temp_dir().join(format!("rti-events-fd-{}.ndjson", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    let target = format!("fd:{}", file.as_raw_fd());
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Send(Kind::Net, (0, 0)), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    run_federation(feds, &["--event_stream", &target]);
+    drop(file);
+    let events = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(events.contains("\"event\":\"net\",\"federate\":1,\"tag\":[0,0]"));
+    assert!(events.contains("\"event\":\"resigned\",\"federate\":0"));
+}
+
+#[test]
+fn lag_beyond_max_lag_names_the_slowest_upstream_federate() {
+    let path = std::env::temp_dir().join(format!("rti-lag-{}.ndjson", std::process::id()));
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 1, 0, NO_DELAY);
+    for fed in feds.iter_mut() {
+        fed.physical_start = true;
+    }
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    // Federate 1 holds federate 0 at the start tag for longer than the
+    // maximum lag.
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(400),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    run_federation(
+        feds,
+        &[
+            "--max_lag",
+            "100000000",
+            "--event_stream",
+            path.to_str().unwrap(),
+        ],
+    );
+    let events = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lags: Vec<&str> = events
+        .lines()
+        .filter(|line| line.contains("\"event\":\"lag\""))
+        .collect();
+    assert_eq!(lags.len(), 1, "{}", events);
+    assert!(
+        lags[0].contains("\"federate\":0,\"tag\":[0,0]"),
+        "{}",
+        lags[0]
+    );
+    assert!(
+        lags[0].contains(
+            "The slowest federate upstream of it is federate 1, which has completed no tag."
+        ),
+        "{}",
+        lags[0]
+    );
+}
+
+/**
+ * Inspector that checks the payloads of the messages to one federate and
+ * counts the messages and the bytes that it has seen.
+ */
+struct CountingInspector {
+    destination: u16,
+    inspected_bytes: AtomicUsize,
+    forwarded: Mutex<Vec<(u16, u16, usize)>>,
+}
+
+impl PayloadInspector for CountingInspector {
+    fn wants_payload(&self, message: &ForwardedMessage) -> bool {
+        message.destination() == self.destination
+    }
+
+    fn inspect_chunk(&self, _message: &ForwardedMessage, offset: usize, chunk: &[u8]) {
+        for (i, byte) in chunk.iter().enumerate() {
+            assert_eq!(*byte, payload_byte(offset + i));
+        }
+        self.inspected_bytes
+            .fetch_add(chunk.len(), Ordering::Relaxed);
+    }
+
+    fn message_forwarded(&self, message: &ForwardedMessage) {
+        self.forwarded.lock().unwrap().push((
+            message.source(),
+            message.destination(),
+            message.length(),
+        ));
+    }
+}
+
+#[test]
+fn payload_inspectors_see_forwarded_messages() {
+    let length = 3 * 1024 * 1024 + 5;
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::SendMessage(1, (0, 0), length),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let inspecting = Arc::new(CountingInspector {
+        destination: 1,
+        inspected_bytes: AtomicUsize::new(0),
+        forwarded: Mutex::new(Vec::new()),
+    });
+    let observing = Arc::new(CountingInspector {
+        destination: 0,
+        inspected_bytes: AtomicUsize::new(0),
+        forwarded: Mutex::new(Vec::new()),
+    });
+    let inspectors = (inspecting.clone(), observing.clone());
+    run_federation_with(feds, &[], move |_f_rti| {
+        _f_rti.add_payload_inspector(inspectors.0);
+        _f_rti.add_payload_inspector(inspectors.1);
+    });
+    assert_eq!(inspecting.inspected_bytes.load(Ordering::Relaxed), length);
+    assert_eq!(*inspecting.forwarded.lock().unwrap(), vec![(0, 1, length)]);
+    // Inspectors that do not want the payload are still told about the message.
+    assert_eq!(observing.inspected_bytes.load(Ordering::Relaxed), 0);
+    assert_eq!(*observing.forwarded.lock().unwrap(), vec![(0, 1, length)]);
+}
+
+#[test]
+fn grants_on_the_control_connection_account_for_relayed_messages() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::SendMessage(1, (0, 0), 2 * 1024 * 1024),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].control_channel = true;
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &[]);
+    // The TAG overtakes the message, but tells federate 1 to receive it first.
+    log.assert_before(
+        &Event::Received(1, Kind::Message, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+}
+
+#[test]
+fn reopening_the_listener_keeps_connected_federates() {
+    let admin_port = free_port();
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Send(Kind::Ltc, (0, 0)), Step::Resign];
+    // Federate 1 joins through the reopened socket.
+    feds[1].join_delay_ms = 500;
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let operator = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        let mut admin = TcpStream::connect(("127.0.0.1", admin_port)).unwrap();
+        writeln!(admin, "relisten").unwrap();
+        let mut response = String::new();
+        BufReader::new(admin).read_line(&mut response).unwrap();
+        response
+    });
+    let log = run_federation(feds, &["--admin_port", &admin_port.to_string()]);
+    assert!(operator.join().unwrap().starts_with("OK"));
+    assert!(log.failed_federates().is_empty());
+    assert!(log.contains(&Event::Received(1, Kind::Tag, (0, 0))));
+}
+
+/// Send the given command to the admin console on the given port after the
+/// given time in milliseconds, and return the response.
+fn send_admin_command_after(
+    admin_port: u16,
+    delay_ms: u64,
+    command: &'static str,
+) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(delay_ms));
+        let mut admin = TcpStream::connect(("127.0.0.1", admin_port)).unwrap();
+        writeln!(admin, "{}", command).unwrap();
+        let mut response = String::new();
+        BufReader::new(admin).read_line(&mut response).unwrap();
+        response
+    })
+}
+
+#[test]
+fn held_start_time_is_sent_once_released() {
+    let admin_port = free_port();
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Send(Kind::Ltc, (0, 0)), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let start = std::time::Instant::now();
+    // Both federates have proposed a start time long before.
+    let operator = send_admin_command_after(admin_port, 300, "start");
+    let log = run_federation(feds, &["--hold", "--admin_port", &admin_port.to_string()]);
+    assert_eq!(operator.join().unwrap().trim_end(), "OK start time sent");
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert!(log.failed_federates().is_empty());
+    assert!(log.contains(&Event::Received(1, Kind::Tag, (0, 0))));
+}
+
+#[test]
+fn start_released_before_all_federates_have_proposed_is_not_held() {
+    let admin_port = free_port();
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    feds[1].join_delay_ms = 300;
+    for fed in feds.iter_mut() {
+        fed.steps = vec![Step::Expect(Kind::Tag, FOREVER_TAG), Step::Resign];
+    }
+    let operator = send_admin_command_after(admin_port, 100, "start");
+    let log = run_federation(feds, &["--hold", "--admin_port", &admin_port.to_string()]);
+    assert!(operator
+        .join()
+        .unwrap()
+        .starts_with("OK the start time is sent once all federates have proposed one"));
+    assert!(log.failed_federates().is_empty());
+    assert!(log.contains(&Event::Received(1, Kind::Tag, FOREVER_TAG)));
+}
+
+#[test]
+fn stalled_handshakes_are_closed_after_the_timeout() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Send(Kind::Ltc, (0, 0)), Step::Resign];
+    // Federate 0 connects while a stalled client holds its ID and the only
+    // I/O thread.
+    feds[0].join_delay_ms = 100;
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let log = run_federation_with(
+        feds,
+        &["--io_threads", "1", "--handshake_timeout", "300"],
+        move |_f_rti| {
+            let port = _f_rti.user_specified_port();
+            thread::spawn(move || {
+                // Claim the ID of federate 0 and send nothing else.
+                let mut stalled = loop {
+                    if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)) {
+                        break stream;
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                };
+                let mut buffer = vec![1u8];
+                buffer.extend_from_slice(&0u16.to_le_bytes());
+                buffer.push(FEDERATION_ID.len() as u8);
+                buffer.extend_from_slice(FEDERATION_ID.as_bytes());
+                stalled.write_all(&buffer).unwrap();
+                let mut ack = [0u8; 1];
+                stalled.read_exact(&mut ack).unwrap();
+                // The RTI closes the connection once the timeout expires.
+                sender.send(stalled.read(&mut ack).unwrap()).unwrap();
+            });
+        },
+    );
+    assert_eq!(receiver.recv().unwrap(), 0);
+    assert!(log.failed_federates().is_empty());
+    assert!(log.contains(&Event::Received(1, Kind::Tag, (0, 0))));
+}
+
+#[test]
+fn topology_file_is_written_and_reused() {
+    let path = std::env::temp_dir().join(format!("rti-topology-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let run = |delay: i64| {
+        let mut feds = vec![
+            ScriptedFederate::new(0),
+            ScriptedFederate::new(1),
+            ScriptedFederate::new(2),
+        ];
+        connect(&mut feds, 0, 1, delay);
+        connect(&mut feds, 1, 2, NO_DELAY);
+        for fed in &mut feds {
+            fed.steps = vec![Step::Resign];
+        }
+        run_federation(feds, &["--topology_file", path.to_str().unwrap()]);
+    };
+
+    run(10_000_000);
+    let text = std::fs::read_to_string(&path).unwrap();
+    let topology = rti::Topology::parse(&text).unwrap();
+    assert_eq!(topology.min_delay(0, 2), Some(10_000_000));
+    assert_eq!(topology.min_delay(1, 2), Some(0));
+    assert_eq!(topology.min_delay(2, 0), None);
+    assert_eq!(topology.downstream_closure(0).as_deref(), Some(&[1, 2][..]));
+    assert_eq!(topology.downstream_closure(2).as_deref(), Some(&[][..]));
+
+    // A run with the same neighbors loads the file instead of rewriting it.
+    std::fs::write(&path, format!("{}# Reused.\n", text)).unwrap();
+    run(10_000_000);
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("# Reused.\n"));
+
+    // A run with other delays replaces it.
+    run(20_000_000);
+    let topology = rti::Topology::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(topology.min_delay(0, 2), Some(20_000_000));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn departed_federates_are_removed_from_the_topology() {
+    let mut topology = rti::Topology::parse(
+        "federation harness\n\
+         federate 0 name=- upstream=- downstream=1,3\n\
+         federate 1 name=- upstream=0:10,2:none downstream=2\n\
+         federate 2 name=- upstream=1:none,3:none downstream=1\n\
+         federate 3 name=- upstream=0:50 downstream=2\n\
+         cycle 1 2 1\n\
+         cycle 2 1 2\n\
+         min_delay 0 1 10\n\
+         min_delay 0 2 10\n\
+         min_delay 0 3 50\n",
+    )
+    .unwrap();
+    assert_eq!(topology.min_delay(0, 2), Some(10));
+    assert_eq!(
+        topology.downstream_closure(0).as_deref(),
+        Some(&[1, 2, 3][..])
+    );
+
+    assert!(topology.remove_federate(1));
+    assert!(!topology.remove_federate(1));
+    // Messages from federate 0 now only reach federate 2 through federate 3.
+    assert_eq!(topology.min_delay(0, 1), None);
+    assert_eq!(topology.min_delay(0, 2), Some(50));
+    assert_eq!(topology.zero_delay_cycle(2), None);
+    assert_eq!(topology.downstream_closure(0).as_deref(), Some(&[3, 2][..]));
+    // Federates downstream of a departed federate are still notified.
+    assert_eq!(topology.downstream_closure(1).as_deref(), Some(&[2][..]));
+}
+
+#[test]
+fn inconsistent_neighbor_structures_are_reported() {
+    let path = std::env::temp_dir().join(format!("rti-inconsistent-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut feds = vec![
+        ScriptedFederate::new(0),
+        ScriptedFederate::new(1),
+        ScriptedFederate::new(2),
+    ];
+    feds[0].downstream = vec![1];
+    feds[1].upstream = vec![(0, 10_000_000), (0, 20_000_000)];
+    // Federate 0 does not list federate 2 as downstream.
+    feds[2].upstream = vec![(0, NO_DELAY)];
+    for fed in &mut feds {
+        fed.steps = vec![Step::Resign];
+    }
+    run_federation(feds, &["--topology_file", path.to_str().unwrap()]);
+
+    let topology = rti::Topology::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let warnings = topology.warnings();
+    assert!(
+        warnings.iter().any(|warning| warning
+            == "Federate 1 lists federate 0 as upstream with a delay of 10000000 ns and with a delay of 20000000 ns, which disagree."),
+        "{:?}",
+        warnings
+    );
+    assert!(
+        warnings.iter().any(|warning| warning
+            == "Federate 2 lists federate 0 as upstream, but federate 0 does not list federate 2 as downstream."),
+        "{:?}",
+        warnings
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unknown_neighbors_and_the_subgraph_that_lists_them_are_reported() {
+    let path = std::env::temp_dir().join(format!("rti-unknown-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut feds: Vec<ScriptedFederate> = (0..4).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 2, 3, NO_DELAY);
+    // Federate 3 expects a federate 5 upstream, e.g., from a stale configuration.
+    feds[3].upstream.push((5, NO_DELAY));
+    for fed in &mut feds {
+        fed.steps = vec![
+            Step::Send(Kind::Net, (10, 0)),
+            Step::Send(Kind::Ltc, (10, 0)),
+            Step::Resign,
+        ];
+    }
+    run_federation(feds, &["--topology_file", path.to_str().unwrap()]);
+
+    let topology = rti::Topology::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        topology.warnings(),
+        &[
+            String::from("Federate 3 lists federate 5 as upstream, which will never connect because the federation has 4 federates."),
+            String::from("The subgraph of federates [2, 3] is disconnected from the rest of the federation and lists federate 5, which will never connect."),
+        ]
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn independent_pipelines_are_not_reported() {
+    let path = std::env::temp_dir().join(format!("rti-pipelines-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut feds: Vec<ScriptedFederate> = (0..5).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 2, 3, NO_DELAY);
+    // Federate 4 has no neighbors at all.
+    for fed in &mut feds {
+        fed.steps = vec![Step::Resign];
+    }
+    run_federation(feds, &["--topology_file", path.to_str().unwrap()]);
+
+    let topology = rti::Topology::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert!(topology.warnings().is_empty(), "{:?}", topology.warnings());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn independent_pipelines_are_served_by_separate_scheduling_threads() {
+    let path = std::env::temp_dir().join(format!(
+        "rti-independent-pipelines-{}.txt",
+        std::process::id()
+    ));
+    std::fs::write(
+        &path,
+        format!(
+            "federation {}\nfederate 0\nfederate 1\nfederate 2\nfederate 3\nconnection 0 1 delay=10000000\nconnection 2 3 delay=10000000\n",
+            FEDERATION_ID
+        ),
+    )
+    .unwrap();
+    let mut feds: Vec<ScriptedFederate> = (0..4).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, 10_000_000);
+    connect(&mut feds, 2, 3, 10_000_000);
+    // The first pipeline is held back by federate 0 for a while.
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (50_000_000, 0)),
+        Step::Sleep(300),
+        Step::Resign,
+    ];
+    for id in [1, 3] {
+        feds[id].steps = vec![
+            Step::Send(Kind::Net, (100_000_000, 0)),
+            // Once its upstream federate has resigned.
+            Step::Expect(Kind::Tag, FOREVER_TAG),
+            Step::Resign,
+        ];
+    }
+    feds[2].steps = vec![Step::Resign];
+    let log = run_federation(
+        feds,
+        &[
+            "--federation_metadata",
+            path.to_str().unwrap(),
+            "--scheduling_threads",
+            "2",
+        ],
+    );
+    std::fs::remove_file(&path).unwrap();
+    log.assert_before(
+        &Event::Received(3, Kind::Tag, FOREVER_TAG),
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+    );
+    log.assert_before(
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+        &Event::Received(1, Kind::Tag, FOREVER_TAG),
+    );
+}
+
+#[test]
+fn independent_pipelines_progress_while_one_is_stalled_without_metadata() {
+    let mut feds: Vec<ScriptedFederate> = (0..4).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 2, 3, NO_DELAY);
+    // The first pipeline is stalled: federate 1 does not read while the
+    // message, which is larger than the socket buffers, is relayed to it, so
+    // the thread serving federate 0 is busy relaying it until it does.
+    feds[0].steps = vec![Step::SendMessage(1, (0, 0), 48 * 1024 * 1024), Step::Resign];
+    feds[1].steps = vec![
+        Step::Sleep(5000),
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Message, (0, 0)),
+        Step::Resign,
+    ];
+    feds[2].steps = vec![
+        Step::Sleep(2500),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[3].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    // Federate i would be served by thread i modulo 2, i.e., federates 0 and
+    // 2 by the same thread, if the pipelines were not learned from the
+    // neighbor structures of the federates.
+    let log = run_federation(feds, &["--scheduling_threads", "2"]);
+    log.assert_before(
+        &Event::Received(3, Kind::Tag, (0, 0)),
+        &Event::Sent(1, Kind::Net, (0, 0)),
+    );
+    assert!(log.contains(&Event::Received(1, Kind::Message, (0, 0))));
+}
+
+#[test]
+fn admin_console_names_the_federate_that_proposed_its_start_time_last() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[1].join_delay_ms = 300;
+    feds[0].steps = vec![
+        Step::Admin("waits", "OK federate 1 proposed its start time last, "),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![Step::Resign];
+    run_federation(feds, &[]);
+}
+
+#[test]
+fn federate_metadata_is_shown_in_the_topology_and_the_admin_console() {
+    let path = std::env::temp_dir().join(format!("rti-metadata-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].metadata = Some(("C/0.8.0", "node-a", 4242));
+    feds[0].steps = vec![
+        Step::Admin(
+            "info all",
+            "OK federate 0 runtime=C/0.8.0 host=node-a pid=4242; federate 1 no metadata",
+        ),
+        Step::Admin("info 1", "OK federate 1 no metadata"),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![Step::Resign];
+    run_federation(feds, &["--topology_file", path.to_str().unwrap()]);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("metadata 0 runtime=C/0.8.0 host=node-a pid=4242\n"));
+    assert!(!text.contains("metadata 1 "));
+    assert!(rti::Topology::parse(&text).is_ok());
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// A snapshot of the federation of the tests below, taken after federate 0
+/// completed (50, 0).
+fn snapshot_to_restore(start_time: i64, number_of_federates: u16) -> String {
+    let federates: Vec<String> = (0..number_of_federates)
+        .map(|id| {
+            let completed = if id == 0 { "[50, 0]" } else { "\"NEVER\"" };
+            format!(
+                "{{\"id\": {}, \"name\": null, \"state\": \"granted\", \"next_event\": [60, 0], \"completed\": {}, \"last_granted\": [50, 0], \"last_provisionally_granted\": \"NEVER\", \"upstream\": [], \"downstream\": [], \"zero_delay_cycle\": null, \"capabilities\": [], \"suspended\": false, \"silent\": false, \"transient\": false}}",
+                id, completed
+            )
+        })
+        .collect();
+    format!(
+        "{{\"schema_version\": 2, \"federation_id\": \"{}\", \"start_time\": {}, \"stop_in_progress\": false, \"federates\": [{}]}}",
+        FEDERATION_ID,
+        start_time,
+        federates.join(", ")
+    )
+}
+
+#[test]
+fn federation_restarts_from_a_snapshot_and_writes_one_when_it_ends() {
+    const START_TIME: i64 = 1_000_000_000_000;
+    let restored = std::env::temp_dir().join(format!("rti-restored-{}.json", std::process::id()));
+    let written = std::env::temp_dir().join(format!("rti-written-{}.json", std::process::id()));
+    std::fs::write(&restored, snapshot_to_restore(START_TIME, 2)).unwrap();
+    let _ = std::fs::remove_file(&written);
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // Federate 0 sends nothing, so federate 1 is granted up to its restored
+    // LTC before it resigns.
+    feds[0].steps = vec![Step::Sleep(300), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (40, 0)),
+        Step::Expect(Kind::Tag, (50, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(
+        feds,
+        &[
+            "--restore_snapshot",
+            restored.to_str().unwrap(),
+            "--snapshot_file",
+            written.to_str().unwrap(),
+        ],
+    );
+    log.assert_before(
+        &Event::Received(1, Kind::Tag, (50, 0)),
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+    );
+
+    let snapshot = rti::Snapshot::parse(&std::fs::read_to_string(&written).unwrap()).unwrap();
+    assert_eq!(snapshot.federation_id(), FEDERATION_ID);
+    assert_eq!(snapshot.start_time(), Some(START_TIME));
+    assert_eq!(snapshot.federates().len(), 2);
+    std::fs::remove_file(&restored).unwrap();
+    std::fs::remove_file(&written).unwrap();
+}
+
+#[test]
+fn snapshot_of_another_federation_is_not_restored() {
+    let path = std::env::temp_dir().join(format!("rti-mismatch-{}.json", std::process::id()));
+    std::fs::write(&path, snapshot_to_restore(1_000_000_000_000, 3)).unwrap();
+    let mut rti = rti::initialize_rti();
+    let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "2", "--restore_snapshot"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain([path.to_str().unwrap().to_string()])
+        .collect();
+    let error = rti::process_args(&mut rti, &args).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid arguments: Snapshot that cannot be restored"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn clock_sync_rounds_run_over_udp_when_on() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].clock_sync_exchanges = Some(3);
+    for fed in &mut feds {
+        fed.steps = vec![Step::Sleep(300), Step::Resign];
+    }
+    let log = run_federation(
+        feds,
+        &[
+            "-c",
+            "on",
+            "period",
+            "20000000",
+            "exchanges-per-interval",
+            "3",
+        ],
+    );
+    assert!(log.contains(&Event::ClockSync(0)));
+    // Federate 1 has not given a UDP port.
+    assert!(!log.contains(&Event::ClockSync(1)));
+}
+
+#[test]
+fn fast_mode_turns_off_clock_sync_max_lead_and_max_lag() {
+    const MS: i64 = 1_000_000;
+    let path = std::env::temp_dir().join(format!("rti-fast-{}.ndjson", std::process::id()));
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    for fed in feds.iter_mut() {
+        fed.physical_start = true;
+    }
+    // The harness fails the federate if the RTI starts an initial clock
+    // synchronization that it does not expect.
+    feds[0].clock_sync_exchanges = Some(0);
+    // Federate 0 holds federate 1 at the start tag for longer than the
+    // maximum lag, and then lets it advance far beyond the maximum lead.
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(300),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Send(Kind::Net, (20_000 * MS, 0)),
+        Step::Sleep(300),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Send(Kind::Net, (10_000 * MS, 0)),
+        Step::Expect(Kind::Tag, (10_000 * MS, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(
+        feds,
+        &[
+            "--fast",
+            "-c",
+            "on",
+            "period",
+            "20000000",
+            "exchanges-per-interval",
+            "3",
+            "--max_lead",
+            "100000000",
+            "--max_lag",
+            "100000000",
+            "--event_stream",
+            path.to_str().unwrap(),
+        ],
+    );
+    let events = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(log.failed_federates().is_empty());
+    assert!(!log.contains(&Event::ClockSync(0)));
+    // Without fast mode, the grant would be held for almost ten seconds.
+    log.assert_before(
+        &Event::Received(1, Kind::Tag, (10_000 * MS, 0)),
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+    );
+    assert!(!events.contains("\"event\":\"lag\""), "{}", events);
+}
+
+#[test]
+fn wire_dump_records_the_bytes_of_selected_federates() {
+    let dir = std::env::temp_dir().join(format!("rti-wire-dump-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(
+        feds,
+        &["--wire_dump", "1", "--wire_dump_dir", dir.to_str().unwrap()],
+    );
+    assert!(log.failed_federates().is_empty());
+    assert!(!dir.join("federate_0.wiredump").exists());
+    let dump = std::fs::read_to_string(dir.join("federate_1.wiredump")).unwrap();
+    let records: Vec<(&str, &str)> = dump
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            (fields[1], fields[3])
+        })
+        .collect();
+    // The handshake opens with MsgType::FedIds and is acknowledged.
+    assert_eq!(records[0], ("RX", "01"));
+    assert!(records.contains(&("TX", "ff")));
+    // The TAG is sent after the start time.
+    assert!(records
+        .iter()
+        .any(|(direction, bytes)| *direction == "TX" && bytes.starts_with("07")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn control_messages_in_excess_of_the_rate_are_coalesced() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // Only the first two messages are within the rate. The others are held
+    // back and handled as the latest NET and LTC, even though federate 0
+    // sends nothing else until it resigns.
+    feds[0].steps = (0..10)
+        .flat_map(|i| {
+            [
+                Step::Send(Kind::Net, (i * 10, 0)),
+                Step::Send(Kind::Ltc, (i * 10, 0)),
+            ]
+        })
+        .chain([Step::Sleep(1500), Step::Resign])
+        .collect();
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (90, 0)),
+        Step::Expect(Kind::Tag, (90, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--max_control_rate", "2"]);
+    log.assert_before(
+        &Event::Received(1, Kind::Tag, (90, 0)),
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+    );
+}
+
+#[test]
+fn fault_rules_delay_grants_to_the_selected_federate() {
+    let path = std::env::temp_dir().join(format!("rti-faults-{}.txt", std::process::id()));
+    std::fs::write(
+        &path,
+        "# Federate 0 is not affected.\ndelay TAG federate=1 ms=300\ndrop PTAG\n",
+    )
+    .unwrap();
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    for fed in feds.iter_mut() {
+        fed.steps = vec![Step::Expect(Kind::Tag, FOREVER_TAG), Step::Resign];
+    }
+    let log = run_federation(
+        feds,
+        &["--fault_rules", path.to_str().unwrap(), "--fault_seed", "7"],
+    );
+    std::fs::remove_file(&path).unwrap();
+    log.assert_before(
+        &Event::Received(0, Kind::Tag, FOREVER_TAG),
+        &Event::Received(1, Kind::Tag, FOREVER_TAG),
+    );
+}
+
+#[test]
+fn grants_are_held_until_they_are_within_the_max_lead() {
+    const MS: i64 = 1_000_000;
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    for fed in feds.iter_mut() {
+        fed.physical_start = true;
+    }
+    // Without --max_lead, federate 1 would be granted its NET as soon as the
+    // NET of federate 0 arrives. The margin allows for the clock of the RTI
+    // being only anchored to the realtime clock.
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (400 * MS, 0)),
+        Step::Sleep(500),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (300 * MS, 0)),
+        Step::Expect(Kind::Tag, (300 * MS, 0)),
+        Step::NotBefore(195 * MS),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--max_lead", "100000000"]);
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn grants_are_held_within_the_lookahead_of_the_earliest_completed_tag() {
+    const MS: i64 = 1_000_000;
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    // Without --lookahead, both federates would be granted FOREVER.
+    feds[0].steps = vec![
+        Step::Expect(Kind::Tag, (100 * MS, 0)),
+        Step::Send(Kind::Ltc, (100 * MS, 0)),
+        Step::Expect(Kind::Tag, (150 * MS, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Expect(Kind::Tag, (100 * MS, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (50 * MS, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(feds, &["--lookahead", "100000000"]);
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn group_lookahead_bounds_only_the_grants_to_its_members() {
+    const MS: i64 = 1_000_000;
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    feds[0].steps = vec![
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Send(Kind::Ltc, (100 * MS, 0)),
+        Step::Sleep(100),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Expect(Kind::Tag, (100 * MS, 0)),
+        Step::Send(Kind::Ltc, (100 * MS, 0)),
+        Step::Expect(Kind::Tag, (200 * MS, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(
+        feds,
+        &[
+            "--group",
+            "compute=1",
+            "--group_policy",
+            "compute:lookahead=100000000",
+        ],
+    );
+    // Federate 0 is not in the group, so it is granted FOREVER at once.
+    assert_eq!(log.grants_to(0), vec![(Kind::Tag, FOREVER_TAG)]);
+    assert_eq!(
+        log.grants_to(1),
+        vec![(Kind::Tag, (100 * MS, 0)), (Kind::Tag, (200 * MS, 0))]
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn trace_file_records_the_interactions_with_federates() {
+    let path = std::env::temp_dir().join(format!("rti-trace-{}.lft", std::process::id()));
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    run_federation(feds, &["--trace_file", path.to_str().unwrap()]);
+    let mut file = std::fs::File::open(&path).unwrap();
+    let header = TraceHeader::read(&mut file).unwrap();
+    let mut records = Vec::new();
+    while let Some(block) = TraceRecord::read_block(&mut file).unwrap() {
+        records.extend(block);
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(header.number_of_federates(), 2);
+    let start = Tag::new(header.start_time(), 0);
+    let position = |event: TraceEvent, src_id: i32, dst_id: i32, tag: Option<&Tag>| {
+        records
+            .iter()
+            .position(|record| {
+                record.event() == event
+                    && record.src_id() == src_id
+                    && record.dst_id() == dst_id
+                    && tag.is_none_or(|tag| record.tag() == tag)
+            })
+            .unwrap_or_else(|| panic!("no {:?} record from {} to {}", event, src_id, dst_id))
+    };
+    position(TraceEvent::ReceiveFedId, 0, -1, None);
+    position(TraceEvent::SendAck, -1, 1, None);
+    position(TraceEvent::SendTimestamp, -1, 0, None);
+    let net = position(TraceEvent::ReceiveNet, 1, -1, Some(&start));
+    let ltc = position(TraceEvent::ReceiveLtc, 0, -1, Some(&start));
+    let tag = position(TraceEvent::SendTag, -1, 1, Some(&start));
+    assert!(net < tag && ltc < tag);
+    position(TraceEvent::ReceiveResign, 0, -1, Some(&Tag::never_tag()));
+    position(TraceEvent::ReceiveResign, 1, -1, None);
+    assert!(records
+        .windows(2)
+        .all(|pair| pair[0].physical_time() <= pair[1].physical_time()));
+}
+
+#[test]
+fn trace_event_stream_and_wire_dumps_are_encrypted_under_the_output_key() {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let dir = std::env::temp_dir().join(format!("rti-encrypted-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let key_path = dir.join("key");
+    let key_text = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .mode(0o600)
+        .open(&key_path)
+        .unwrap()
+        .write_all(key_text.as_bytes())
+        .unwrap();
+    let trace_path = dir.join("trace.lft");
+    let events_path = dir.join("events.ndjson");
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(
+        feds,
+        &[
+            "--output_key_file",
+            key_path.to_str().unwrap(),
+            "--trace_file",
+            trace_path.to_str().unwrap(),
+            "--event_stream",
+            events_path.to_str().unwrap(),
+            "--wire_dump",
+            "1",
+            "--wire_dump_dir",
+            dir.to_str().unwrap(),
+        ],
+    );
+    assert!(log.failed_federates().is_empty());
+    let key = OutputKey::from_hex(key_text).unwrap();
+    let decrypt = |path: &std::path::Path| {
+        let encrypted = std::fs::read(path).unwrap();
+        assert!(encrypted.starts_with(b"LFRTIENC"));
+        let mut decrypted = Vec::new();
+        assert!(rti::decrypt(&key, encrypted.as_slice(), &mut decrypted).unwrap());
+        decrypted
+    };
+
+    let trace = decrypt(&trace_path);
+    let header = TraceHeader::read(&mut trace.as_slice()).unwrap();
+    assert_eq!(header.number_of_federates(), 2);
+    let events = String::from_utf8(decrypt(&events_path)).unwrap();
+    assert!(events.contains("\"event\":\"resigned\",\"federate\":0"));
+    let dump = String::from_utf8(decrypt(&dir.join("federate_1.wiredump"))).unwrap();
+    assert!(dump.starts_with("# Bytes exchanged with federate 1"));
+
+    // Another key or a modified file is detected.
+    let other_key = OutputKey::from_hex(&key_text.replace('0', "1")).unwrap();
+    let encrypted = std::fs::read(&events_path).unwrap();
+    let error = rti::decrypt(&other_key, encrypted.as_slice(), Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let mut modified = encrypted.clone();
+    *modified.last_mut().unwrap() ^= 1;
+    assert!(rti::decrypt(&key, modified.as_slice(), Vec::new()).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Federate 1 does not read while federate 0 sends it a message that is too
+/// large for the socket buffers, so the relay blocks and the grant decided for
+/// federate 1 once federate 2 sends a NET cannot be written. Federate 2 then
+/// expects another grant before federate 0 resigns. The end of the sleep of
+/// federate 1 is marked by a NET at (11, 0).
+fn federation_with_a_federate_that_does_not_read() -> Vec<ScriptedFederate> {
+    let mut feds = vec![
+        ScriptedFederate::new(0),
+        ScriptedFederate::new(1),
+        ScriptedFederate::new(2),
+    ];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 0, 2, NO_DELAY);
+    connect(&mut feds, 2, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (100, 0)),
+        Step::SendMessage(1, (50, 0), 4 << 20),
+        Step::Sleep(2500),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (10, 0)),
+        Step::Sleep(2500),
+        Step::Send(Kind::Net, (11, 0)),
+    ];
+    feds[2].steps = vec![
+        Step::Sleep(1000),
+        Step::Send(Kind::Net, (20, 0)),
+        Step::Expect(Kind::Tag, (20, 0)),
+        Step::Send(Kind::Ltc, (20, 0)),
+        Step::Send(Kind::Net, (30, 0)),
+        Step::Expect(Kind::Tag, (30, 0)),
+        Step::Resign,
+    ];
+    feds
+}
+
+#[test]
+fn federate_that_does_not_take_its_grants_within_the_timeout_is_disconnected() {
+    // The blocked relay occupies a thread, so the others need their own.
+    let log = run_federation(
+        federation_with_a_federate_that_does_not_read(),
+        &[
+            "--grant_write_policy",
+            "1=timeout:200",
+            "--io_threads",
+            "3",
+            "--scheduling_threads",
+            "3",
+        ],
+    );
+    log.assert_before(
+        &Event::Received(2, Kind::Tag, (30, 0)),
+        &Event::Sent(1, Kind::Net, (11, 0)),
+    );
+    assert_eq!(log.failed_federates(), [1]);
+}
+
+#[test]
+fn grants_to_other_federates_do_not_wait_for_a_federate_with_superseded_grants() {
+    let mut feds = federation_with_a_federate_that_does_not_read();
+    feds[1]
+        .steps
+        .extend([Step::Expect(Kind::Tag, (10, 0)), Step::Resign]);
+    let log = run_federation(
+        feds,
+        &[
+            "--grant_write_policy",
+            "block,1=supersede",
+            "--io_threads",
+            "3",
+            "--scheduling_threads",
+            "3",
+        ],
+    );
+    log.assert_before(
+        &Event::Received(2, Kind::Tag, (30, 0)),
+        &Event::Sent(1, Kind::Net, (11, 0)),
+    );
+    assert!(log.contains(&Event::Received(1, Kind::Message, (50, 0))));
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn grants_decided_in_a_burst_are_written_in_tag_order() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (1000, 0)),
+        Step::Sleep(1500),
+        Step::Resign,
+    ];
+    // Federate 1 sends its NETs without waiting for the grants, each of which
+    // is decided at once, so that several grants wait in its queue at a time.
+    feds[1].steps = vec![Step::Sleep(100)];
+    for time in 1..=20 {
+        feds[1].steps.push(Step::Send(Kind::Net, (time, 0)));
+    }
+    // A NET that goes backwards is not granted again, so that federate 1
+    // never sees its grants go backwards.
+    feds[1].steps.extend([
+        Step::Expect(Kind::Tag, (20, 0)),
+        Step::Send(Kind::Net, (10, 0)),
+        Step::Send(Kind::Net, (30, 0)),
+        Step::Expect(Kind::Tag, (30, 0)),
+        Step::Resign,
+    ]);
+    let log = run_federation(feds, &[]);
+    let grants = log.grants_to(1);
+    // A PTAG may precede the TAG for the same tag, but no grant is earlier
+    // than or the same as a previous one.
+    let keys: Vec<(i64, u32, bool)> = grants
+        .iter()
+        .map(|(kind, tag)| (tag.0, tag.1, *kind == Kind::Tag))
+        .collect();
+    assert!(
+        keys.windows(2).all(|pair| pair[0] < pair[1]),
+        "{:?}",
+        grants
+    );
+    assert_eq!(grants.last(), Some(&(Kind::Tag, (30, 0))));
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn shutdown_notifies_the_connected_federates_and_ends_the_run() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // Neither federate resigns, as if they waited for the RTI forever.
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (100, 0)),
+        Step::Expect(Kind::Failed, (0, 0)),
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (10, 0)),
+        Step::Expect(Kind::Failed, (0, 0)),
+    ];
+    let log = run_federation_with(feds, &["--keepalive", "1"], |_f_rti| {
+        let shutdown = _f_rti.shutdown_handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(500));
+            shutdown.request("a test");
+        });
+    });
+    assert!(log.contains(&Event::Received(0, Kind::Failed, (0, 0))));
+    assert!(log.contains(&Event::Received(1, Kind::Failed, (0, 0))));
+    // Federates closed by the shutdown are not reported as failed.
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn rti_shuts_down_if_the_federates_do_not_connect_within_the_timeout() {
+    let args: Vec<String> = [
+        "rti",
+        "-i",
+        FEDERATION_ID,
+        "-n",
+        "2",
+        "-p",
+        &free_port().to_string(),
+        "--timeout",
+        "1",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let mut _f_rti = rti::initialize_rti();
+    rti::process_args(&mut _f_rti, &args).unwrap();
+    let shutdown = _f_rti.shutdown_handle();
+    rti::initialize_federates(&mut _f_rti);
+    rti::start_rti_server(&mut _f_rti)
+        .unwrap()
+        .wait_for_federates(_f_rti);
+    assert_eq!(
+        shutdown.reason().as_deref(),
+        Some("the timeout of 1 s for the federates to connect")
+    );
+}
+
+/// Send an HTTP GET request for the given path to the status server on the
+/// given port and return the response.
+fn get_status(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn status_server_reports_the_state_of_each_federate() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 0, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (100, 0)),
+        Step::Sleep(1000),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (10, 0)),
+        Step::Sleep(1000),
+        Step::Resign,
+    ];
+    let status_port = free_port();
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let requester = {
+        let responses = responses.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(500));
+            let mut responses = responses.lock().unwrap();
+            responses.push(get_status(status_port, "/status"));
+            responses.push(get_status(status_port, "/unknown"));
+        })
+    };
+    run_federation(feds, &["--status_port", &status_port.to_string()]);
+    requester.join().unwrap();
+    let responses = responses.lock().unwrap();
+    let status = &responses[0];
+    assert!(status.starts_with("HTTP/1.1 200 OK\r\n"), "{}", status);
+    assert!(
+        status.contains("Content-Type: application/json"),
+        "{}",
+        status
+    );
+    assert!(
+        status.contains(&format!("\"federation_id\":\"{}\"", FEDERATION_ID)),
+        "{}",
+        status
+    );
+    assert!(
+        status.contains("{\"id\":0,\"name\":null,\"state\":\""),
+        "{}",
+        status
+    );
+    assert!(
+        status.contains("\"next_event\":[100,0],\"completed\":\"NEVER\""),
+        "{}",
+        status
+    );
+    assert!(
+        status.contains("\"upstream\":[{\"id\":1,\"delay\":null}],\"downstream\":[1],\"zero_delay_cycle\":[0,1,0]"),
+        "{}",
+        status
+    );
+    let body = &status[status.find("\r\n\r\n").unwrap() + 4..];
+    let snapshot = rti::Snapshot::parse(body).unwrap();
+    assert_eq!(snapshot.schema_version(), rti::SNAPSHOT_SCHEMA_VERSION);
+    assert_eq!(snapshot.federates()[1].next_event(), &Tag::new(10, 0));
+    assert!(
+        responses[1].starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        responses[1]
+    );
+}
+
+#[test]
+fn silent_federate_is_reported_and_presumed_idle_after_the_grace_period() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // Federate 0 never sends a NET, as if it had deadlocked.
+    feds[0].steps = vec![Step::Sleep(1500), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (1000, 0)),
+        Step::Expect(Kind::Tag, (1000, 0)),
+        Step::Resign,
+    ];
+    let status_port = free_port();
+    let requester = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        get_status(status_port, "/status")
+    });
+    let log = run_federation(
+        feds,
+        &[
+            "--status_port",
+            &status_port.to_string(),
+            "--silent_grace",
+            "500",
+        ],
+    );
+    let status = requester.join().unwrap();
+    let silent = status.find("\"silent\":true,").expect(&status);
+    let not_silent = status.find("\"silent\":false,").expect(&status);
+    assert!(silent < not_silent, "{}", status);
+    log.assert_before(
+        &Event::Received(1, Kind::Tag, (1000, 0)),
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+    );
+}
+
+#[test]
+fn partition_is_not_granted_beyond_the_grants_of_the_parent_rti() {
+    let parent_port = free_port();
+    let args: Vec<String> = [
+        "rti",
+        "-i",
+        FEDERATION_ID,
+        "-n",
+        "2",
+        "-p",
+        &parent_port.to_string(),
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let parent_rti = thread::spawn(move || {
+        let mut _f_rti = rti::initialize_rti();
+        rti::process_args(&mut _f_rti, &args).unwrap();
+        rti::initialize_federates(&mut _f_rti);
+        rti::start_rti_server(&mut _f_rti)
+            .unwrap()
+            .wait_for_federates(_f_rti)
+    });
+    // At the parent RTI, federate 1 is upstream of the partition, which is
+    // federate 0.
+    let mut parent_feds = vec![ScriptedFederate::new(1)];
+    parent_feds[0].downstream = vec![0];
+    parent_feds[0].steps = vec![
+        Step::Send(Kind::Net, (10, 0)),
+        Step::Sleep(500),
+        Step::Send(Kind::Ltc, (10, 0)),
+        Step::Send(Kind::Net, (100, 0)),
+        Step::Resign,
+    ];
+    let parent_scripts = thread::spawn(move || run_federates_on(parent_port, parent_feds));
+
+    // The federate of the partition has no neighbors in the partition, so it
+    // would be granted FOREVER right away without the parent RTI.
+    let mut feds = vec![ScriptedFederate::new(0)];
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (50, 0)),
+        Step::Expect(Kind::Tag, (50, 0)),
+        Step::Resign,
+    ];
+    let log = run_federation(
+        feds,
+        &[
+            "--parent_rti",
+            &format!("127.0.0.1:{}", parent_port),
+            "--parent_id",
+            "0",
+            "--parent_upstream",
+            "1",
+        ],
+    );
+    let parent_log = parent_scripts.join().unwrap();
+    assert!(parent_rti.join().unwrap().is_empty());
+    assert!(log.failed_federates().is_empty());
+
+    let grants = log.grants_to(0);
+    assert_eq!(grants.last(), Some(&(Kind::Tag, (50, 0))), "{:?}", grants);
+    assert!(
+        grants.iter().all(|(_, tag)| *tag <= (50, 0)),
+        "{:?}",
+        grants
+    );
+    assert!(parent_log.contains(&Event::Sent(1, Kind::Ltc, (10, 0))));
+}
+
+/// Send an HTTP GET request for /status with the given bearer token to the
+/// status server on the given port and return the response.
+fn get_status_with_token(port: u16, token: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(
+        stream,
+        "GET /status HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\n\r\n",
+        token
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn admin_tokens_separate_reading_from_control() {
+    let dir = std::env::temp_dir().join(format!("rti-admin-tokens-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let control_token_path = dir.join("control");
+    let read_token_path = dir.join("read");
+    std::fs::write(&control_token_path, "control-secret\n").unwrap();
+    std::fs::write(&read_token_path, "read-secret\n").unwrap();
+
+    let mut feds = vec![ScriptedFederate::new(0)];
+    feds[0].steps = vec![
+        Step::Admin("info 0", "ERROR authenticate first"),
+        Step::Admin("auth wrong-secret", "ERROR invalid token"),
+        Step::AdminWithToken("read-secret", "info 0", "OK federate 0 "),
+        Step::AdminWithToken(
+            "read-secret",
+            "pause",
+            "ERROR 'pause' needs the control role",
+        ),
+        Step::AdminWithToken("control-secret", "pause", "OK paused"),
+        Step::AdminWithToken("control-secret", "resume", "OK resumed"),
+        Step::Sleep(500),
+        Step::Resign,
+    ];
+    let status_port = free_port();
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let requester = {
+        let responses = responses.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            let mut responses = responses.lock().unwrap();
+            responses.push(get_status(status_port, "/status"));
+            responses.push(get_status_with_token(status_port, "wrong-secret"));
+            responses.push(get_status_with_token(status_port, "read-secret"));
+        })
+    };
+    run_federation(
+        feds,
+        &[
+            "--status_port",
+            &status_port.to_string(),
+            "--admin_token_file",
+            control_token_path.to_str().unwrap(),
+            "--admin_read_token_file",
+            read_token_path.to_str().unwrap(),
+        ],
+    );
+    requester.join().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let responses = responses.lock().unwrap();
+    assert!(
+        responses[0].starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+        "{}",
+        responses[0]
+    );
+    assert!(
+        responses[1].starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+        "{}",
+        responses[1]
+    );
+    assert!(
+        responses[2].starts_with("HTTP/1.1 200 OK\r\n"),
+        "{}",
+        responses[2]
+    );
+}
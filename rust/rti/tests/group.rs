@@ -0,0 +1,106 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of assigning federates to groups and of the policies of the
+ * groups given on the command line and in the federation metadata.
+ */
+use rti::{FederationRTI, GroupPolicy, LogLevel, RtiError};
+
+/// Process the given arguments for a federation of three federates.
+fn process_args(extra_args: &[&str]) -> Result<FederationRTI, RtiError> {
+    let args: Vec<String> = ["rti", "-i", "group", "-n", "3"]
+        .iter()
+        .chain(extra_args)
+        .map(|s| s.to_string())
+        .collect();
+    let mut _f_rti = rti::initialize_rti();
+    rti::process_args(&mut _f_rti, &args)?;
+    Ok(_f_rti)
+}
+
+#[test]
+fn federates_are_assigned_to_groups_from_the_arguments_and_the_metadata() {
+    let path = std::env::temp_dir().join(format!("rti-group-{}.txt", std::process::id()));
+    std::fs::write(
+        &path,
+        "federation group\nfederate 0 group=sensors name=camera\nfederate 1 name=lidar group=sensors\nfederate 2\n",
+    )
+    .unwrap();
+    let _f_rti = process_args(&[
+        "--federation_metadata",
+        path.to_str().unwrap(),
+        "--group",
+        "compute=2",
+        "--group_policy",
+        "sensors:max_control_rate=10,on_failure=degrade",
+        "--group_policy",
+        "sensors:max_control_rate=20",
+        "--group_policy",
+        "compute:lookahead=5,log_level=debug",
+        "--time_resolution",
+        "us",
+    ])
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let sensors = _f_rti.group_of(1).unwrap();
+    assert_eq!(sensors.name(), "sensors");
+    assert_eq!(sensors.members(), &[0, 1]);
+    // A later policy overrides an earlier one for the same group.
+    assert_eq!(sensors.policy().max_control_rate(), Some(20));
+    assert_eq!(sensors.policy().lookahead(), None);
+    assert_eq!(
+        sensors.policy().to_string(),
+        "max_control_rate=20, on_failure=degrade"
+    );
+    let compute = _f_rti.group_of(2).unwrap();
+    assert_eq!(compute.members(), &[2]);
+    // In the time resolution given after the policy.
+    assert_eq!(compute.policy().lookahead(), Some(5_000));
+    assert_eq!(compute.policy().log_level(), Some(LogLevel::Debug));
+    assert_eq!(_f_rti.groups().len(), 2);
+}
+
+#[test]
+fn invalid_groups_and_policies_are_rejected() {
+    // Federate 0 is in two groups.
+    assert!(process_args(&["--group", "sensors=0,1", "--group", "compute=0"]).is_err());
+    // There is no federate 3.
+    assert!(process_args(&["--group", "sensors=3"]).is_err());
+    assert!(process_args(&["--group", "sensors:0"]).is_err());
+    assert!(process_args(&["--group", "sensors="]).is_err());
+    assert!(process_args(&["--group", "a b=0"]).is_err());
+    // The policy of a group without federates.
+    assert!(process_args(&["--group_policy", "compute:max_control_rate=1"]).is_err());
+    assert!(process_args(&["--group", "sensors=0", "--group_policy", "sensors"]).is_err());
+
+    let parse = |text| GroupPolicy::parse(text, rti::initialize_rti().time_resolution());
+    assert_eq!(
+        parse("max_control_rate=-1").unwrap_err(),
+        "invalid policy 'max_control_rate=-1'"
+    );
+    assert_eq!(
+        parse("lookahead=0").unwrap_err(),
+        "invalid policy 'lookahead=0'"
+    );
+    assert_eq!(
+        parse("on_failure=retry").unwrap_err(),
+        "invalid policy 'on_failure=retry'"
+    );
+    assert_eq!(
+        parse("log_level=loud").unwrap_err(),
+        "invalid policy 'log_level=loud'"
+    );
+    assert_eq!(
+        parse("priority=1").unwrap_err(),
+        "invalid policy 'priority=1'"
+    );
+    assert_eq!(parse("").unwrap_err(), "invalid policy ''");
+    assert_eq!(
+        GroupPolicy::default().to_string(),
+        "the policies of the RTI"
+    );
+}
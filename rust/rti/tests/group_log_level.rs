@@ -0,0 +1,73 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Test of the log levels and rate limits of groups of federates, in its
+ * own process because the log levels are selected for the whole process.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+#[test]
+fn groups_have_their_own_log_levels_and_rate_limits() {
+    let path = std::env::temp_dir().join(format!("rti-group-log-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // A sensor that floods the RTI with control messages.
+    feds[0].steps = (0..10)
+        .flat_map(|i| {
+            [
+                Step::Send(Kind::Net, (i * 10, 0)),
+                Step::Send(Kind::Ltc, (i * 10, 0)),
+            ]
+        })
+        .chain([Step::Sleep(1500), Step::Resign])
+        .collect();
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (90, 0)),
+        Step::Expect(Kind::Tag, (90, 0)),
+        Step::Resign,
+    ];
+    run_federation(
+        feds,
+        &[
+            "--log_level",
+            "info",
+            "--log_file",
+            path.to_str().unwrap(),
+            "--group",
+            "sensors=0",
+            "--group",
+            "compute=1",
+            "--group_policy",
+            "sensors:max_control_rate=2,log_level=warn",
+            "--group_policy",
+            "compute:log_level=debug",
+        ],
+    );
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        text.contains("All federates have connected to RTI."),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("RTI received from federate 1 the Next Event Tag (NET)"),
+        "{}",
+        text
+    );
+    assert!(!text.contains("from federate 0 the"), "{}", text);
+    assert!(
+        text.lines()
+            .any(|line| line.contains("Federate 0 sent") && line.contains("in excess of the rate")),
+        "{}",
+        text
+    );
+    assert!(!text.contains("Federate 1 sent"), "{}", text);
+}
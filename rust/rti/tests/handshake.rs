@@ -0,0 +1,468 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the federate handshake over an in-memory stream.
+ */
+#[cfg(feature = "auth")]
+use std::io::{Read, Write};
+#[cfg(feature = "auth")]
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "auth")]
+use std::thread;
+
+#[cfg(feature = "auth")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "auth")]
+use sha2::Sha256;
+
+use rti::{Connection, ConnectionState, MockStream};
+
+const MSG_TYPE_REJECT: u8 = 0;
+const MSG_TYPE_FED_IDS: u8 = 1;
+const MSG_TYPE_P2P_SENDING_FED_ID: u8 = 15;
+const MSG_TYPE_CLOCK_SYNC_T1: u8 = 19;
+const MSG_TYPE_CLOCK_SYNC_T3: u8 = 20;
+const MSG_TYPE_CLOCK_SYNC_T4: u8 = 21;
+const MSG_TYPE_NEIGHBOR_STRUCTURE: u8 = 24;
+const MSG_TYPE_FEDERATE_NAME: u8 = 35;
+const MSG_TYPE_FED_NONCE: u8 = 100;
+const MSG_TYPE_RTI_RESPONSE: u8 = 101;
+const MSG_TYPE_FED_RESPONSE: u8 = 102;
+const MSG_TYPE_UDP_PORT: u8 = 254;
+const MSG_TYPE_ACK: u8 = 255;
+
+const FEDERATE_ID_OUT_OF_RANGE: u8 = 3;
+const UNEXPECTED_MESSAGE: u8 = 4;
+const WRONG_SERVER: u8 = 5;
+const HMAC_DOES_NOT_MATCH: u8 = 6;
+const RTI_NOT_EXECUTED_WITH_AUTH: u8 = 7;
+const PROTOCOL_VIOLATION: u8 = 8;
+const FEDERATE_NAME_IN_USE: u8 = 10;
+const FEDERATION_DRAINING: u8 = 12;
+
+const FEDERATION_ID: &str = "handshake";
+
+fn fed_ids_message(fed_id: u16) -> Vec<u8> {
+    let mut buffer = vec![MSG_TYPE_FED_IDS];
+    buffer.extend_from_slice(&fed_id.to_le_bytes());
+    buffer.push(FEDERATION_ID.len() as u8);
+    buffer.extend_from_slice(FEDERATION_ID.as_bytes());
+    buffer
+}
+
+fn neighbor_structure_message(upstream: &[(u16, i64)], downstream: &[u16]) -> Vec<u8> {
+    let mut buffer = vec![MSG_TYPE_NEIGHBOR_STRUCTURE];
+    buffer.extend_from_slice(&(upstream.len() as i32).to_le_bytes());
+    buffer.extend_from_slice(&(downstream.len() as i32).to_le_bytes());
+    for (id, delay) in upstream {
+        buffer.extend_from_slice(&id.to_le_bytes());
+        buffer.extend_from_slice(&delay.to_le_bytes());
+    }
+    for id in downstream {
+        buffer.extend_from_slice(&id.to_le_bytes());
+    }
+    buffer
+}
+
+fn federate_name_message(name: &str) -> Vec<u8> {
+    let mut buffer = vec![MSG_TYPE_FEDERATE_NAME];
+    buffer.push(name.len() as u8);
+    buffer.extend_from_slice(name.as_bytes());
+    buffer
+}
+
+fn udp_port_message() -> Vec<u8> {
+    udp_port_message_with_port(u16::MAX)
+}
+
+fn udp_port_message_with_port(port: u16) -> Vec<u8> {
+    let mut buffer = vec![MSG_TYPE_UDP_PORT];
+    buffer.extend_from_slice(&port.to_le_bytes());
+    buffer
+}
+
+fn clock_sync_t3_message(fed_id: u16) -> Vec<u8> {
+    let mut buffer = vec![MSG_TYPE_CLOCK_SYNC_T3];
+    buffer.extend_from_slice(&i32::from(fed_id).to_le_bytes());
+    buffer
+}
+
+fn fed_nonce_message(fed_id: u16) -> Vec<u8> {
+    let mut buffer = vec![MSG_TYPE_FED_NONCE];
+    buffer.extend_from_slice(&fed_id.to_le_bytes());
+    buffer.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    buffer
+}
+
+/**
+ * Return the HMAC-SHA256 tag of the concatenation of the given parts, keyed
+ * with the federation ID.
+ */
+#[cfg(feature = "auth")]
+fn hmac_tag(parts: &[&[u8]]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(FEDERATION_ID.as_bytes()).unwrap();
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn handshake(stream: &mut MockStream) -> Connection {
+    handshake_with_args(stream, &[])
+}
+
+fn handshake_with_args(stream: &mut MockStream, extra_args: &[&str]) -> Connection {
+    let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "2"]
+        .iter()
+        .chain(extra_args)
+        .map(|s| s.to_string())
+        .collect();
+    let mut _f_rti = rti::initialize_rti();
+    rti::process_args(&mut _f_rti, &args).unwrap();
+    rti::initialize_federates(&mut _f_rti);
+    rti::Server::handshake(stream, Arc::new(RwLock::new(_f_rti)))
+}
+
+#[test]
+fn handshake_accepts_a_federate() {
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(1));
+    stream.push_read(&neighbor_structure_message(&[(0, 10)], &[]));
+    stream.push_read(&udp_port_message());
+    let connection = handshake(&mut stream);
+    assert_eq!(connection.state(), ConnectionState::AwaitingTimestamp);
+    assert_eq!(connection.fed_id(), Some(1));
+    assert_eq!(stream.written(), &[MSG_TYPE_ACK]);
+    assert!(!stream.is_shut_down());
+}
+
+#[test]
+fn handshake_rejects_a_peer_connection() {
+    let mut stream = MockStream::new();
+    let mut message = fed_ids_message(1);
+    message[0] = MSG_TYPE_P2P_SENDING_FED_ID;
+    stream.push_read(&message);
+    let connection = handshake(&mut stream);
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(connection.fed_id(), None);
+    assert_eq!(stream.written(), &[MSG_TYPE_REJECT, WRONG_SERVER]);
+    assert!(stream.is_shut_down());
+}
+
+#[test]
+fn handshake_rejects_a_missing_neighbor_structure() {
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(0));
+    stream.push_read(&udp_port_message());
+    let connection = handshake(&mut stream);
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(connection.fed_id(), Some(0));
+    assert_eq!(
+        stream.written(),
+        &[MSG_TYPE_ACK, MSG_TYPE_REJECT, UNEXPECTED_MESSAGE]
+    );
+    assert!(stream.is_shut_down());
+}
+
+#[test]
+fn handshake_assigns_federate_ids_to_joining_federates() {
+    let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "2", "--dynamic_ids"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut _f_rti = rti::initialize_rti();
+    rti::process_args(&mut _f_rti, &args).unwrap();
+    rti::initialize_federates(&mut _f_rti);
+    let _f_rti = Arc::new(RwLock::new(_f_rti));
+
+    for expected_id in 0..2u16 {
+        let mut stream = MockStream::new();
+        stream.push_read(&fed_ids_message(u16::MAX));
+        stream.push_read(&neighbor_structure_message(&[], &[]));
+        stream.push_read(&udp_port_message());
+        let connection = rti::Server::handshake(&mut stream, _f_rti.clone());
+        assert_eq!(connection.state(), ConnectionState::AwaitingTimestamp);
+        assert_eq!(connection.fed_id(), Some(expected_id));
+        let mut expected_ack = vec![MSG_TYPE_ACK];
+        expected_ack.extend_from_slice(&expected_id.to_le_bytes());
+        assert_eq!(stream.written(), &expected_ack[..]);
+    }
+
+    // Every ID is taken.
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(u16::MAX));
+    let connection = rti::Server::handshake(&mut stream, _f_rti.clone());
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(
+        stream.written(),
+        &[MSG_TYPE_REJECT, FEDERATE_ID_OUT_OF_RANGE]
+    );
+    assert!(stream.is_shut_down());
+}
+
+#[test]
+fn handshake_rejects_a_federate_name_in_use() {
+    let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "2"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut _f_rti = rti::initialize_rti();
+    rti::process_args(&mut _f_rti, &args).unwrap();
+    rti::initialize_federates(&mut _f_rti);
+    let _f_rti = Arc::new(RwLock::new(_f_rti));
+
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(0));
+    stream.push_read(&federate_name_message("sensor"));
+    stream.push_read(&neighbor_structure_message(&[], &[1]));
+    stream.push_read(&udp_port_message());
+    let connection = rti::Server::handshake(&mut stream, _f_rti.clone());
+    assert_eq!(connection.state(), ConnectionState::AwaitingTimestamp);
+    assert_eq!(stream.written(), &[MSG_TYPE_ACK]);
+
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(1));
+    stream.push_read(&federate_name_message("sensor"));
+    let connection = rti::Server::handshake(&mut stream, _f_rti.clone());
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(
+        stream.written(),
+        &[MSG_TYPE_ACK, MSG_TYPE_REJECT, FEDERATE_NAME_IN_USE]
+    );
+    assert!(stream.is_shut_down());
+}
+
+#[test]
+fn handshake_rejects_a_transient_federate_while_draining() {
+    let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "2", "--transient", "1"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut _f_rti = rti::initialize_rti();
+    rti::process_args(&mut _f_rti, &args).unwrap();
+    rti::initialize_federates(&mut _f_rti);
+    _f_rti.set_draining(true);
+    let _f_rti = Arc::new(RwLock::new(_f_rti));
+
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(1));
+    let connection = rti::Server::handshake(&mut stream, _f_rti.clone());
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(stream.written(), &[MSG_TYPE_REJECT, FEDERATION_DRAINING]);
+    assert!(stream.is_shut_down());
+
+    // Federates that are not transient still join.
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(0));
+    stream.push_read(&neighbor_structure_message(&[], &[]));
+    stream.push_read(&udp_port_message());
+    let connection = rti::Server::handshake(&mut stream, _f_rti);
+    assert_eq!(connection.state(), ConnectionState::AwaitingTimestamp);
+}
+
+#[test]
+fn handshake_performs_the_initial_clock_synchronization() {
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(0));
+    stream.push_read(&neighbor_structure_message(&[], &[1]));
+    stream.push_read(&udp_port_message_with_port(15045));
+    stream.push_read(&clock_sync_t3_message(0));
+    stream.push_read(&clock_sync_t3_message(0));
+    let connection =
+        handshake_with_args(&mut stream, &["-c", "init", "exchanges-per-interval", "2"]);
+    assert_eq!(connection.state(), ConnectionState::AwaitingTimestamp);
+    // The Ack, then a T1 and a T4 message with the physical time per exchange.
+    let written = stream.written();
+    assert_eq!(written.len(), 1 + 4 * 9);
+    assert_eq!(written[0], MSG_TYPE_ACK);
+    let types: Vec<u8> = written[1..].chunks(9).map(|message| message[0]).collect();
+    assert_eq!(
+        types,
+        [
+            MSG_TYPE_CLOCK_SYNC_T1,
+            MSG_TYPE_CLOCK_SYNC_T4,
+            MSG_TYPE_CLOCK_SYNC_T1,
+            MSG_TYPE_CLOCK_SYNC_T4
+        ]
+    );
+}
+
+#[test]
+fn handshake_rejects_other_messages_during_clock_synchronization() {
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(0));
+    stream.push_read(&neighbor_structure_message(&[], &[1]));
+    stream.push_read(&udp_port_message_with_port(15045));
+    stream.push_read(&fed_ids_message(0));
+    let connection = handshake(&mut stream);
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert!(stream
+        .written()
+        .ends_with(&[MSG_TYPE_REJECT, UNEXPECTED_MESSAGE]));
+    assert!(stream.is_shut_down());
+}
+
+#[cfg(feature = "auth")]
+#[test]
+fn handshake_authenticates_a_federate() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let rti_thread = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "2", "-a"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut _f_rti = rti::initialize_rti();
+        rti::process_args(&mut _f_rti, &args).unwrap();
+        rti::initialize_federates(&mut _f_rti);
+        rti::Server::handshake(&mut stream, Arc::new(RwLock::new(_f_rti)))
+    });
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    let fed_nonce = fed_nonce_message(1);
+    stream.write_all(&fed_nonce).unwrap();
+    let mut response = [0u8; 1 + 8 + 32];
+    stream.read_exact(&mut response).unwrap();
+    assert_eq!(response[0], MSG_TYPE_RTI_RESPONSE);
+    // The RTI proves that it knows the key, too.
+    assert_eq!(
+        response[9..],
+        hmac_tag(&[&[MSG_TYPE_RTI_RESPONSE], &fed_nonce[1..]])[..]
+    );
+    let mut fed_response = vec![MSG_TYPE_FED_RESPONSE];
+    fed_response.extend(hmac_tag(&[&[MSG_TYPE_FED_RESPONSE], &response[1..9]]));
+    stream.write_all(&fed_response).unwrap();
+    stream.write_all(&fed_ids_message(1)).unwrap();
+    stream
+        .write_all(&neighbor_structure_message(&[(0, 10)], &[]))
+        .unwrap();
+    stream.write_all(&udp_port_message()).unwrap();
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack).unwrap();
+    assert_eq!(ack, [MSG_TYPE_ACK]);
+
+    let connection = rti_thread.join().unwrap();
+    assert_eq!(connection.state(), ConnectionState::AwaitingTimestamp);
+    assert_eq!(connection.fed_id(), Some(1));
+}
+
+#[cfg(feature = "auth")]
+#[test]
+fn handshake_rejects_a_federate_with_the_wrong_key() {
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_nonce_message(1));
+    let mut fed_response = vec![MSG_TYPE_FED_RESPONSE];
+    fed_response.extend_from_slice(&[0; 32]);
+    stream.push_read(&fed_response);
+    stream.push_read(&fed_ids_message(1));
+    let connection = handshake_with_args(&mut stream, &["-a"]);
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(connection.fed_id(), None);
+    let written = stream.written();
+    assert_eq!(written[0], MSG_TYPE_RTI_RESPONSE);
+    assert_eq!(
+        &written[1 + 8 + 32..],
+        &[MSG_TYPE_REJECT, HMAC_DOES_NOT_MATCH]
+    );
+    assert!(stream.is_shut_down());
+}
+
+#[cfg(feature = "auth")]
+#[test]
+fn handshake_rejects_an_unauthenticated_federate() {
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(1));
+    let connection = handshake_with_args(&mut stream, &["-a"]);
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(stream.written(), &[MSG_TYPE_REJECT, UNEXPECTED_MESSAGE]);
+    assert!(stream.is_shut_down());
+}
+
+#[test]
+fn handshake_rejects_authentication_if_it_is_not_enabled() {
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_nonce_message(1));
+    let connection = handshake(&mut stream);
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(
+        stream.written(),
+        &[MSG_TYPE_REJECT, RTI_NOT_EXECUTED_WITH_AUTH]
+    );
+    assert!(stream.is_shut_down());
+}
+
+/// Write a federation metadata file in which federate 0 sends to federate 1
+/// with a delay of 10 ns, and return its path.
+fn federation_metadata_file(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!(
+        "rti-federation-metadata-{}-{}.txt",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(
+        &path,
+        format!(
+            "# Generated by the code generator.\nfederation {}\nfederate 0 name=sensor\nfederate 1\nconnection 0 1 delay=10\n",
+            FEDERATION_ID
+        ),
+    )
+    .unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn handshake_checks_federates_against_the_federation_metadata() {
+    let path = federation_metadata_file("handshake");
+    let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "2"]
+        .iter()
+        .map(|s| s.to_string())
+        .chain(["--federation_metadata".to_string(), path.clone()])
+        .collect();
+    let mut _f_rti = rti::initialize_rti();
+    rti::process_args(&mut _f_rti, &args).unwrap();
+    rti::initialize_federates(&mut _f_rti);
+    let _f_rti = Arc::new(RwLock::new(_f_rti));
+
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(0));
+    stream.push_read(&federate_name_message("sensor"));
+    stream.push_read(&neighbor_structure_message(&[], &[1]));
+    stream.push_read(&udp_port_message());
+    let connection = rti::Server::handshake(&mut stream, _f_rti.clone());
+    assert_eq!(connection.state(), ConnectionState::AwaitingTimestamp);
+    assert_eq!(stream.written(), &[MSG_TYPE_ACK]);
+    assert!(!_f_rti.read().unwrap().shutdown_handle().requested());
+
+    // A federate generated from another version of the program, in which the
+    // connection has no delay.
+    let mut stream = MockStream::new();
+    stream.push_read(&fed_ids_message(1));
+    stream.push_read(&neighbor_structure_message(&[(0, i64::MIN)], &[]));
+    let connection = rti::Server::handshake(&mut stream, _f_rti.clone());
+    assert_eq!(connection.state(), ConnectionState::Closed);
+    assert_eq!(
+        stream.written(),
+        &[MSG_TYPE_ACK, MSG_TYPE_REJECT, PROTOCOL_VIOLATION]
+    );
+    assert!(stream.is_shut_down());
+    assert!(_f_rti.read().unwrap().shutdown_handle().requested());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn arguments_that_do_not_match_the_federation_metadata_are_rejected() {
+    let path = federation_metadata_file("arguments");
+    let args: Vec<String> = ["rti", "-i", FEDERATION_ID, "-n", "3"]
+        .iter()
+        .map(|s| s.to_string())
+        .chain(["--federation_metadata".to_string(), path.clone()])
+        .collect();
+    let mut _f_rti = rti::initialize_rti();
+    assert!(rti::process_args(&mut _f_rti, &args).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,57 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the options that detect unresponsive federates on their
+ * connections: TCP keepalive and read timeouts.
+ */
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use rti::{NetUtil, ReadErrorKind};
+use socket2::SockRef;
+
+/// Return both ends of a connection over the loopback interface.
+fn connection() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, server)
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn keepalive_probes_idle_connections_with_the_given_period() {
+    let (_client, server) = connection();
+    let socket = SockRef::from(&server);
+    assert!(!socket.keepalive().unwrap());
+    NetUtil::set_keepalive(&server, 7).unwrap();
+    assert!(socket.keepalive().unwrap());
+    assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(7));
+    assert_eq!(socket.keepalive_interval().unwrap(), Duration::from_secs(7));
+    assert_eq!(socket.keepalive_retries().unwrap(), 3);
+}
+
+#[test]
+fn read_from_a_silent_federate_times_out_without_consuming_bytes() {
+    let (_client, mut server) = connection();
+    server
+        .set_read_timeout(Some(Duration::from_millis(50)))
+        .unwrap();
+    let started = Instant::now();
+    let error = NetUtil::read_from_socket_i64(&mut server).unwrap_err();
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    // Depending on the platform, an expired read timeout is reported as
+    // either kind, both of which the RTI treats as a timeout.
+    assert!(
+        matches!(
+            error.kind(),
+            ReadErrorKind::WouldBlock | ReadErrorKind::Timeout
+        ),
+        "{}",
+        error
+    );
+    assert_eq!(error.bytes_read(), 0);
+}
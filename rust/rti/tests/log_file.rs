@@ -0,0 +1,72 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the log levels and the log file, in their own process
+ * because the level and the file are selected for the whole process.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::path::PathBuf;
+
+/// Run a federation whose neighbor structures the RTI warns about, with the
+/// given log level and a log file, and return the contents of the file.
+fn run_with_log_level(log_level: &str) -> String {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("rti-{}-{}.log", log_level, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    // Federate 0 does not list federate 1 as downstream.
+    feds[1].upstream = vec![(0, NO_DELAY)];
+    for fed in &mut feds {
+        fed.steps = vec![Step::Send(Kind::Net, (10, 0)), Step::Resign];
+    }
+    run_federation(
+        feds,
+        &[
+            "--log_level",
+            log_level,
+            "--log_file",
+            path.to_str().unwrap(),
+        ],
+    );
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    text
+}
+
+#[test]
+fn output_goes_to_the_log_file_up_to_the_log_level() {
+    let text = run_with_log_level("warn");
+    assert!(
+        text.contains("WARNING: Federate 1 lists federate 0 as upstream"),
+        "{}",
+        text
+    );
+    assert!(
+        !text.contains("All federates have connected to RTI."),
+        "{}",
+        text
+    );
+    assert!(!text.contains("Next Event Tag (NET)"), "{}", text);
+
+    let text = run_with_log_level("info");
+    assert!(
+        text.contains("All federates have connected to RTI."),
+        "{}",
+        text
+    );
+    assert!(!text.contains("Next Event Tag (NET)"), "{}", text);
+
+    let text = run_with_log_level("debug");
+    assert!(
+        text.contains("RTI received from federate 0 the Next Event Tag (NET)"),
+        "{}",
+        text
+    );
+}
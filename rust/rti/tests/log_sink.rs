@@ -0,0 +1,91 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the syslog and journald sinks, in their own process because
+ * the sink is selected for the whole process.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// Run a federation whose neighbor structures the RTI warns about, with the
+/// given sink bound to a socket of the test, and return the received lines.
+fn run_with_sink(sink: &str) -> Vec<String> {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("rti-{}-{}.sock", sink, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    // Receive while the federation runs so that no line is dropped.
+    let receiver = thread::spawn(move || {
+        let mut lines = Vec::new();
+        let mut buffer = vec![0u8; 65536];
+        while let Ok(length) = socket.recv(&mut buffer) {
+            lines.push(String::from_utf8_lossy(&buffer[..length]).into_owned());
+        }
+        lines
+    });
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    // Federate 0 does not list federate 1 as downstream.
+    feds[1].upstream = vec![(0, NO_DELAY)];
+    for fed in &mut feds {
+        fed.steps = vec![Step::Resign];
+    }
+    run_federation(
+        feds,
+        &["--log_sink", sink, "--log_socket", path.to_str().unwrap()],
+    );
+    let lines = receiver.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    lines
+}
+
+#[test]
+fn output_goes_to_syslog_and_journald_with_priorities() {
+    let pid = std::process::id();
+    let lines = run_with_sink("syslog");
+    // The daemon facility (3) with the info (6) and warning (4) severities.
+    assert!(
+        lines
+            .iter()
+            .any(|line| line == &format!("<30>rti[{}]: All federates have connected to RTI.", pid)),
+        "{:#?}",
+        lines
+    );
+    assert!(
+        lines.iter().any(|line| line.starts_with(&format!(
+            "<28>rti[{}]: WARNING: Federate 1 lists federate 0 as upstream",
+            pid
+        ))),
+        "{:#?}",
+        lines
+    );
+
+    let entries = run_with_sink("journald");
+    assert!(
+        entries.iter().any(|entry| entry
+            == &format!(
+                "PRIORITY=6\nSYSLOG_IDENTIFIER=rti\nSYSLOG_PID={}\nMESSAGE=All federates have connected to RTI.\n",
+                pid
+            )),
+        "{:#?}",
+        entries
+    );
+    assert!(
+        entries.iter().any(|entry| entry.starts_with("PRIORITY=4\n")
+            && entry.contains("MESSAGE=WARNING: Federate 1 lists federate 0 as upstream")),
+        "{:#?}",
+        entries
+    );
+}
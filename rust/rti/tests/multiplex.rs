@@ -0,0 +1,178 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of federates running in the same process that share one
+ * connection to the RTI.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const MSG_TYPE_MULTIPLEX: u8 = 34;
+const FRAME_HEADER_LENGTH: usize = 6;
+
+/// Writer of frames to the connection shared with the RTI.
+#[derive(Clone)]
+struct SharedConnection(Arc<Mutex<TcpStream>>);
+
+impl SharedConnection {
+    fn send(&self, fed_id: u16, bytes: &[u8]) {
+        let mut frame = fed_id.to_le_bytes().to_vec();
+        frame.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        frame.extend_from_slice(bytes);
+        // NOTE: Ignore errors because the RTI may have exited.
+        let _ = self.0.lock().unwrap().write_all(&frame);
+    }
+}
+
+fn connect_with_retry(port: u16) -> TcpStream {
+    for _ in 0..100 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)) {
+            return stream;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    panic!("Failed to connect to the RTI on port {}.", port);
+}
+
+/// Start a relay that, like a process running several federates, carries the
+/// connections of the given number of federates to the RTI on the given port
+/// over a single connection, and return the port on which the relay accepts
+/// them. The counter is incremented for each connection to the RTI.
+fn start_relay(rti_port: u16, number_of_federates: usize, connections: Arc<AtomicUsize>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let mut upstream = connect_with_retry(rti_port);
+        connections.fetch_add(1, Ordering::SeqCst);
+        upstream.write_all(&[MSG_TYPE_MULTIPLEX]).unwrap();
+        let shared = SharedConnection(Arc::new(Mutex::new(upstream.try_clone().unwrap())));
+        let federates: Arc<Mutex<HashMap<u16, TcpStream>>> = Arc::new(Mutex::new(HashMap::new()));
+
+        // Relay the frames from the RTI to the federates.
+        let mut reader = upstream.try_clone().unwrap();
+        let receivers = federates.clone();
+        thread::spawn(move || {
+            let mut header = [0u8; FRAME_HEADER_LENGTH];
+            while reader.read_exact(&mut header).is_ok() {
+                let fed_id = u16::from_le_bytes(header[..2].try_into().unwrap());
+                let length = u32::from_le_bytes(header[2..].try_into().unwrap()) as usize;
+                let mut bytes = vec![0u8; length];
+                if reader.read_exact(&mut bytes).is_err() {
+                    break;
+                }
+                let mut receivers = receivers.lock().unwrap();
+                if let Some(stream) = receivers.get_mut(&fed_id) {
+                    if length == 0 {
+                        let _ = stream.shutdown(Shutdown::Both);
+                    } else {
+                        let _ = stream.write_all(&bytes);
+                    }
+                }
+            }
+        });
+
+        // Relay the bytes from each federate to the RTI.
+        let mut handles = Vec::new();
+        for _ in 0..number_of_federates {
+            let (mut stream, _) = listener.accept().unwrap();
+            // The MsgType::FedIds message starts with the federate ID.
+            let mut first = [0u8; 3];
+            stream.read_exact(&mut first).unwrap();
+            let fed_id = u16::from_le_bytes(first[1..].try_into().unwrap());
+            federates
+                .lock()
+                .unwrap()
+                .insert(fed_id, stream.try_clone().unwrap());
+            shared.send(fed_id, &first);
+            let shared = shared.clone();
+            handles.push(thread::spawn(move || {
+                let mut chunk = [0u8; 4096];
+                while let Ok(size @ 1..) = stream.read(&mut chunk) {
+                    shared.send(fed_id, &chunk[..size]);
+                }
+                shared.send(fed_id, &[]);
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let _ = upstream.shutdown(Shutdown::Both);
+    });
+    port
+}
+
+/// Run the federates over a single connection to the RTI, and return the log
+/// and the number of connections to the RTI.
+fn run_multiplexed_federation(feds: Vec<ScriptedFederate>, extra_args: &[&str]) -> (Log, usize) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let number_of_federates = feds.len();
+    let counter = connections.clone();
+    let log = run_federation_through(
+        feds,
+        extra_args,
+        |_| {},
+        move |port| start_relay(port, number_of_federates, counter),
+    );
+    (log, connections.load(Ordering::SeqCst))
+}
+
+#[test]
+fn federates_share_one_connection() {
+    let mut feds: Vec<ScriptedFederate> = (0..3).map(ScriptedFederate::new).collect();
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 2, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        // Larger than a frame that the RTI reads at once.
+        Step::SendMessage(1, (0, 0), 100 * 1024 + 17),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[2].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    let (log, connections) = run_multiplexed_federation(feds, &[]);
+    assert_eq!(connections, 1);
+    log.assert_before(
+        &Event::Received(1, Kind::Message, (0, 0)),
+        &Event::Received(1, Kind::Tag, (0, 0)),
+    );
+    log.assert_before(
+        &Event::Sent(1, Kind::Ltc, (0, 0)),
+        &Event::Received(2, Kind::Tag, (0, 0)),
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+#[test]
+fn federate_that_fails_on_a_shared_connection_does_not_affect_the_others() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Fail];
+    feds[1].steps = vec![Step::Expect(Kind::Tag, FOREVER_TAG), Step::Resign];
+    let (log, connections) = run_multiplexed_federation(feds, &[]);
+    assert_eq!(connections, 1);
+    assert_eq!(log.failed_federates(), &[0]);
+}
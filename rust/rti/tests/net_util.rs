@@ -0,0 +1,147 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the classification of failed reads by the socket helpers,
+ * and of the number of bytes they report as read before each failure.
+ */
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read};
+
+use lf_tag::Tag;
+use rti::{MockStream, NetUtil, ReadErrorKind};
+
+/// A MockStream that returns its scripted bytes at most `chunk` bytes per
+/// read, then the scripted read errors in order, then end-of-file.
+struct ScriptedStream {
+    stream: MockStream,
+    chunk: usize,
+    errors: VecDeque<ErrorKind>,
+}
+
+impl ScriptedStream {
+    fn new(bytes: &[u8], chunk: usize, errors: &[ErrorKind]) -> ScriptedStream {
+        let mut stream = MockStream::new();
+        stream.push_read(bytes);
+        ScriptedStream {
+            stream,
+            chunk,
+            errors: errors.iter().copied().collect(),
+        }
+    }
+}
+
+impl Read for ScriptedStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let size = buffer.len().min(self.chunk);
+        match self.stream.read(&mut buffer[..size])? {
+            0 => match self.errors.pop_front() {
+                Some(kind) => Err(io::Error::from(kind)),
+                None => Ok(0),
+            },
+            read => Ok(read),
+        }
+    }
+}
+
+/// Encode the tag as the federates do: the time, then the microstep.
+fn encoded_tag(time: i64, microstep: u32) -> Vec<u8> {
+    let mut buffer = time.to_le_bytes().to_vec();
+    buffer.extend_from_slice(&microstep.to_le_bytes());
+    buffer
+}
+
+#[test]
+fn message_split_across_reads_is_read_whole() {
+    let mut stream = ScriptedStream::new(&encoded_tag(-42, 7), 1, &[]);
+    assert_eq!(
+        NetUtil::read_from_socket_tag(&mut stream).unwrap(),
+        Tag::new(-42, 7)
+    );
+
+    // An interrupted read is retried rather than reported, so the failure
+    // is that of the read after it.
+    let mut stream = ScriptedStream::new(b"abc", 2, &[ErrorKind::Interrupted, ErrorKind::TimedOut]);
+    let mut buffer = [0u8; 6];
+    let error = NetUtil::read_from_socket(&mut stream, &mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Timeout);
+    assert_eq!(error.bytes_read(), 3);
+    assert_eq!(&buffer[..3], b"abc");
+}
+
+#[test]
+fn no_data_on_a_non_blocking_socket_is_would_block() {
+    let mut stream = ScriptedStream::new(&[], 8, &[ErrorKind::WouldBlock]);
+    let error = NetUtil::read_from_socket_i64(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::WouldBlock);
+    assert_eq!(error.bytes_read(), 0);
+
+    // Part of the message has been consumed when the socket runs dry.
+    let mut stream = ScriptedStream::new(&[1, 2, 3], 2, &[ErrorKind::WouldBlock]);
+    let error = NetUtil::read_from_socket_i64(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::WouldBlock);
+    assert_eq!(error.bytes_read(), 3);
+    assert_eq!(error.to_string(), "no data available after 3 byte(s)");
+}
+
+#[test]
+fn end_of_file_or_reset_before_any_byte_is_closed() {
+    let mut stream = ScriptedStream::new(&[], 8, &[]);
+    let error = NetUtil::read_from_socket_tag(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Closed);
+    assert_eq!(error.bytes_read(), 0);
+    assert_eq!(error.to_string(), "connection closed after 0 byte(s)");
+
+    // Other errors, e.g., a reset, are treated as a closed connection.
+    let mut stream = ScriptedStream::new(&[9; 4], 8, &[ErrorKind::ConnectionReset]);
+    let error = NetUtil::read_from_socket_tag(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Closed);
+    assert_eq!(error.bytes_read(), 4);
+}
+
+#[test]
+fn message_cut_short_or_invalid_is_corrupt() {
+    let truncated = &encoded_tag(10, 1)[..5];
+    let mut stream = ScriptedStream::new(truncated, 2, &[]);
+    let error = NetUtil::read_from_socket_tag(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Corrupt);
+    assert_eq!(error.bytes_read(), 5);
+    assert_eq!(
+        error.to_string(),
+        "corrupt or truncated message after 5 byte(s)"
+    );
+
+    let mut stream = ScriptedStream::new(&[0; 6], 8, &[ErrorKind::InvalidData]);
+    let error = NetUtil::read_from_socket_i64(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Corrupt);
+    assert_eq!(error.bytes_read(), 6);
+}
+
+#[test]
+fn expired_read_timeout_is_timeout() {
+    let mut stream = ScriptedStream::new(&[], 8, &[ErrorKind::TimedOut]);
+    let error = NetUtil::read_from_socket_i64(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Timeout);
+    assert_eq!(error.bytes_read(), 0);
+
+    let mut stream = ScriptedStream::new(&encoded_tag(5, 0)[..7], 3, &[ErrorKind::TimedOut]);
+    let error = NetUtil::read_from_socket_tag(&mut stream).unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Timeout);
+    assert_eq!(error.bytes_read(), 7);
+    assert_eq!(error.to_string(), "read timed out after 7 byte(s)");
+}
+
+#[test]
+fn failed_read_reports_the_bytes_read_through_read_from_stream() {
+    let mut stream = ScriptedStream::new(&[1, 2], 1, &[ErrorKind::TimedOut]);
+    let mut buffer = [0u8; 4];
+    assert!(!NetUtil::read_from_stream(
+        &mut stream,
+        &mut buffer,
+        0,
+        "the message"
+    ));
+    assert_eq!(&buffer[..2], &[1, 2]);
+}
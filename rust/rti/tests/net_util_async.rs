@@ -0,0 +1,94 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the async socket helpers over in-memory tokio streams,
+ * checked against the sync helpers that read the same bytes.
+ */
+use lf_tag::Tag;
+use rti::{NetUtil, ReadErrorKind};
+use tokio::io::{duplex, AsyncWriteExt};
+
+/// Encode the tag as the federates do: the time, then the microstep.
+fn encoded_tag(time: i64, microstep: u32) -> Vec<u8> {
+    let mut buffer = time.to_le_bytes().to_vec();
+    buffer.extend_from_slice(&microstep.to_le_bytes());
+    buffer
+}
+
+#[tokio::test]
+async fn async_helpers_read_back_what_is_written() {
+    // A buffer smaller than the messages, so that they are split across reads.
+    let (mut writer, mut reader) = duplex(5);
+    let sent = encoded_tag(-42, 7);
+    let written = sent.clone();
+    let writing = tokio::spawn(async move {
+        NetUtil::write_to_socket_async(&mut writer, &1_700_000_000_000_000_000i64.to_le_bytes())
+            .await
+            .unwrap();
+        NetUtil::write_to_socket_async(&mut writer, &written)
+            .await
+            .unwrap();
+        NetUtil::write_to_socket_async(&mut writer, b"payload")
+            .await
+            .unwrap();
+    });
+    assert_eq!(
+        NetUtil::read_from_socket_i64_async(&mut reader)
+            .await
+            .unwrap(),
+        1_700_000_000_000_000_000
+    );
+    let tag = NetUtil::read_from_socket_tag_async(&mut reader)
+        .await
+        .unwrap();
+    assert_eq!(tag, Tag::new(-42, 7));
+    // The sync helper decodes the same bytes to the same tag.
+    assert_eq!(
+        NetUtil::read_from_socket_tag(&mut sent.as_slice()).unwrap(),
+        tag
+    );
+    let mut payload = [0u8; 7];
+    assert_eq!(
+        NetUtil::read_from_socket_async(&mut reader, &mut payload)
+            .await
+            .unwrap(),
+        7
+    );
+    assert_eq!(&payload, b"payload");
+    writing.await.unwrap();
+}
+
+#[tokio::test]
+async fn async_helpers_classify_failures_like_the_sync_helpers() {
+    // Closed before any byte is read.
+    let (writer, mut reader) = duplex(64);
+    drop(writer);
+    let error = NetUtil::read_from_socket_i64_async(&mut reader)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Closed);
+    assert_eq!(error.bytes_read(), 0);
+    let sync_error = NetUtil::read_from_socket_i64(&mut &[][..]).unwrap_err();
+    assert_eq!(sync_error.kind(), ReadErrorKind::Closed);
+
+    // Cut short in the middle of a tag.
+    let (mut writer, mut reader) = duplex(64);
+    let truncated = &encoded_tag(10, 1)[..5];
+    writer.write_all(truncated).await.unwrap();
+    drop(writer);
+    let error = NetUtil::read_from_socket_tag_async(&mut reader)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ReadErrorKind::Corrupt);
+    assert_eq!(error.bytes_read(), 5);
+    let sync_error = NetUtil::read_from_socket_tag(&mut &truncated[..]).unwrap_err();
+    assert_eq!(sync_error.kind(), ReadErrorKind::Corrupt);
+    assert_eq!(sync_error.bytes_read(), 5);
+    assert_eq!(
+        error.to_string(),
+        "corrupt or truncated message after 5 byte(s)"
+    );
+}
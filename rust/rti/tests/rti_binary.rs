@@ -0,0 +1,393 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the rti executable: its output and its exit status.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+/// Start the rti executable for the given number of federates with the given
+/// extra arguments, and return it with its port and a thread that returns its
+/// output once it exits. The given function is called with the process ID for
+/// each line of output.
+fn start_rti(
+    number_of_federates: usize,
+    extra_args: &[&str],
+    on_line: impl Fn(u32, &str) + Send + 'static,
+) -> (Child, u16, JoinHandle<String>) {
+    let port = free_port();
+    let mut rti_process = Command::new(env!("CARGO_BIN_EXE_rti"))
+        .args(["-i", FEDERATION_ID, "-n"])
+        .arg(number_of_federates.to_string())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(extra_args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let pid = rti_process.id();
+    let stdout = rti_process.stdout.take().unwrap();
+    let output = thread::spawn(move || {
+        let mut output = String::new();
+        for line in BufReader::new(stdout).lines() {
+            let line = line.unwrap();
+            on_line(pid, &line);
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output
+    });
+    (rti_process, port, output)
+}
+
+#[cfg(unix)]
+#[test]
+fn sigterm_notifies_the_federates_and_exits_with_an_error() {
+    let (sender, receiver) = mpsc::channel();
+    let (mut rti_process, port, output) = start_rti(2, &[], move |pid, line| {
+        if line.contains("All federates have connected to RTI.") {
+            sender.send(pid).unwrap();
+        }
+    });
+    let signaling = thread::spawn(move || {
+        let pid = receiver.recv().unwrap();
+        // SAFETY: kill() only sends a signal to the RTI process.
+        assert_eq!(unsafe { libc::kill(pid as i32, libc::SIGTERM) }, 0);
+    });
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // Neither federate resigns, as if they waited for the RTI forever.
+    for fed in &mut feds {
+        fed.steps = vec![
+            Step::Send(Kind::Net, (100, 0)),
+            Step::Expect(Kind::Failed, (0, 0)),
+        ];
+    }
+    let log = run_federates_on(port, feds);
+    signaling.join().unwrap();
+    let status = rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    assert!(log.contains(&Event::Received(0, Kind::Failed, (0, 0))));
+    assert!(log.contains(&Event::Received(1, Kind::Failed, (0, 0))));
+    assert!(
+        output.contains("RTI: Shut down because of SIGTERM."),
+        "{}",
+        output
+    );
+    assert_eq!(status.code(), Some(1));
+}
+
+#[cfg(unix)]
+#[test]
+fn sigusr1_writes_a_snapshot_to_the_snapshot_file() {
+    let path = std::env::temp_dir().join(format!("rti-sigusr1-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (sender, receiver) = mpsc::channel();
+    let (mut rti_process, port, output) = start_rti(
+        2,
+        &["--snapshot_file", path.to_str().unwrap()],
+        move |pid, line| {
+            if line.contains("All federates have connected to RTI.") {
+                sender.send((pid, libc::SIGUSR1)).unwrap();
+            } else if line.contains("Wrote a snapshot of the federation") {
+                // Stop the federation once the snapshot has been written.
+                let _ = sender.send((pid, libc::SIGTERM));
+            }
+        },
+    );
+    let signaling = thread::spawn(move || {
+        for (pid, signum) in receiver.iter().take(2) {
+            // SAFETY: kill() only sends a signal to the RTI process.
+            assert_eq!(unsafe { libc::kill(pid as i32, signum) }, 0);
+        }
+    });
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    for fed in &mut feds {
+        fed.steps = vec![Step::Expect(Kind::Failed, (0, 0))];
+    }
+    run_federates_on(port, feds);
+    signaling.join().unwrap();
+    rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    // Once on SIGUSR1 and once when the federation ends.
+    assert_eq!(
+        output.matches("Wrote a snapshot of the federation").count(),
+        2,
+        "{}",
+        output
+    );
+    let snapshot = rti::Snapshot::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(snapshot.federation_id(), FEDERATION_ID);
+    assert_eq!(snapshot.federates().len(), 2);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn trace_summary_reads_back_the_trace_of_a_run() {
+    let path = std::env::temp_dir().join(format!("rti-summary-{}.lft", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (mut rti_process, port, output) =
+        start_rti(1, &["--trace_file", path.to_str().unwrap()], |_, _| {});
+    let mut feds = vec![ScriptedFederate::new(0)];
+    feds[0].steps = vec![Step::Expect(Kind::Tag, FOREVER_TAG), Step::Resign];
+    run_federates_on(port, feds);
+    rti_process.wait().unwrap();
+    output.join().unwrap();
+
+    let summary = Command::new(env!("CARGO_BIN_EXE_rti"))
+        .arg("trace_summary")
+        .arg(&path)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(summary.stdout).unwrap();
+    assert!(summary.status.success(), "{}", text);
+    assert!(
+        text.starts_with("Trace in format version 1 (current: 1) with start time "),
+        "{}",
+        text
+    );
+    assert!(text.contains(" and 1 federate(s).\n"), "{}", text);
+    assert!(text.contains("\n  ReceiveResign: 1\n"), "{}", text);
+    assert!(text.contains("\n  SendTag: 1\n"), "{}", text);
+    assert!(text.contains("\nFederate 0: "), "{}", text);
+    assert!(text.ends_with(", last TAG FOREVER.\n"), "{}", text);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn trace_summary_rejects_a_file_that_is_not_a_trace() {
+    let path = std::env::temp_dir().join(format!("rti-not-a-trace-{}.lft", std::process::id()));
+    std::fs::write(&path, [1, 2, 3]).unwrap();
+    let summary = Command::new(env!("CARGO_BIN_EXE_rti"))
+        .arg("trace_summary")
+        .arg(&path)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(summary.stdout).unwrap();
+    assert!(text.starts_with("Invalid trace "), "{}", text);
+    assert_eq!(summary.status.code(), Some(1));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn failed_federate_is_in_the_run_summary_and_the_exit_status() {
+    let (mut rti_process, port, output) = start_rti(2, &[], |_, _| {});
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Fail];
+    feds[1].steps = vec![Step::Expect(Kind::Tag, FOREVER_TAG), Step::Resign];
+    run_federates_on(port, feds);
+    let status = rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    assert!(
+        output.contains("\tFederate 0 failed: the federate reported an error."),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("\tFederate 1 exited normally."),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("RTI: 1 federate(s) reported an error or exited abnormally: [0]."),
+        "{}",
+        output
+    );
+    assert_eq!(status.code(), Some(1));
+}
+
+#[test]
+fn federation_without_failures_exits_successfully() {
+    let (mut rti_process, port, output) = start_rti(1, &[], |_, _| {});
+    let mut feds = vec![ScriptedFederate::new(0)];
+    feds[0].steps = vec![Step::Resign];
+    run_federates_on(port, feds);
+    let status = rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    assert!(
+        output.contains("\tFederate 0 exited normally."),
+        "{}",
+        output
+    );
+    assert!(
+        output.starts_with(&format!("RTI: Federation ID: {}\n", FEDERATION_ID)),
+        "{}",
+        output
+    );
+    assert_eq!(status.code(), Some(0));
+}
+
+#[test]
+fn startup_lines_follow_the_log_level() {
+    let (mut rti_process, port, output) = start_rti(1, &["--log_level", "warn"], |_, _| {});
+    let mut feds = vec![ScriptedFederate::new(0)];
+    feds[0].steps = vec![Step::Resign];
+    run_federates_on(port, feds);
+    rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    assert!(!output.contains("Starting RTI for"), "{}", output);
+    assert!(!output.contains("Run summary"), "{}", output);
+}
+
+#[test]
+fn microstep_loop_is_reported_with_its_cycle() {
+    let (mut rti_process, port, output) =
+        start_rti(2, &["--microstep_warning_threshold", "3"], |_, _| {});
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 0, NO_DELAY);
+    // Each federate schedules an event at the next microstep of time 0 and
+    // sends a message to the other one at every tag, which never advances the
+    // time.
+    for fed in &mut feds {
+        for microstep in 0..5 {
+            fed.steps.extend([
+                Step::Send(Kind::Net, (0, microstep)),
+                Step::Expect(Kind::Ptag, (0, microstep)),
+                Step::Send(Kind::Ltc, (0, microstep)),
+            ]);
+        }
+        fed.steps.push(Step::Resign);
+    }
+    run_federates_on(port, feds);
+    rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    for (fed_id, cycle) in [(0, "0 -> 1 -> 0"), (1, "1 -> 0 -> 1")] {
+        let warning = format!(
+            "RTI: WARNING: Federate {} has been granted 4 consecutive tags at time 0 with increasing microsteps (now 3). The federation may be livelocked in a microstep loop involving the cycle of connections without delay through federates {}.",
+            fed_id, cycle
+        );
+        assert!(output.contains(&warning), "{}", output);
+    }
+    // The warning is printed once per streak.
+    assert_eq!(output.matches("microstep loop").count(), 2, "{}", output);
+}
+
+#[test]
+fn advancing_time_is_not_reported_as_a_microstep_loop() {
+    let (mut rti_process, port, output) =
+        start_rti(2, &["--microstep_warning_threshold", "3"], |_, _| {});
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    connect(&mut feds, 1, 0, NO_DELAY);
+    for fed in &mut feds {
+        for time in 0..5 {
+            fed.steps.extend([
+                Step::Send(Kind::Net, (time, 0)),
+                Step::Expect(Kind::Ptag, (time, 0)),
+                Step::Send(Kind::Ltc, (time, 0)),
+            ]);
+        }
+        fed.steps.push(Step::Resign);
+    }
+    run_federates_on(port, feds);
+    rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    assert!(!output.contains("microstep loop"), "{}", output);
+}
+
+#[test]
+fn progress_toward_the_stop_time_is_reported_with_an_eta() {
+    const MS: i64 = 1_000_000;
+    let (mut rti_process, port, output) = start_rti(
+        2,
+        &[
+            "--stop_time",
+            "1000000000",
+            "--progress_period",
+            "100",
+            "--lookahead",
+            "100000000",
+        ],
+        |_, _| {},
+    );
+    // With the lookahead, the federates are granted 100 ms of logical time
+    // more once both have completed the previous grant, about every 150 ms.
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    for fed in &mut feds {
+        for step in 1..=6 {
+            let time = step * 100 * MS;
+            fed.steps.extend([
+                Step::Expect(Kind::Tag, (time, 0)),
+                Step::Sleep(150),
+                Step::Send(Kind::Ltc, (time, 0)),
+            ]);
+        }
+        fed.steps.push(Step::Resign);
+    }
+    run_federates_on(port, feds);
+    rti_process.wait().unwrap();
+    let output = output.join().unwrap();
+    let reports: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("RTI: Progress "))
+        .collect();
+    // The first report has no previous one to estimate the rate from.
+    assert_eq!(
+        reports.first(),
+        Some(&"RTI: Progress 10.0% toward stop tag (1000000000,0). Minimum granted tag is (100000000,0). ETA: unknown."),
+        "{}",
+        output
+    );
+    let percents: Vec<f64> = reports
+        .iter()
+        .map(|report| {
+            report["RTI: Progress ".len()..]
+                .split('%')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap()
+        })
+        .collect();
+    assert!(
+        percents.windows(2).all(|pair| pair[0] <= pair[1]),
+        "{}",
+        output
+    );
+    // Once the minimum granted tag has advanced between two reports, the ETA
+    // is the remaining time at the rate of that advance.
+    assert!(
+        reports
+            .iter()
+            .any(|report| report.ends_with(" s.") && !report.contains("100.0%")),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn unrecognized_argument_prints_the_usage_of_the_c_rti() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rti"))
+        .args(["-n", "2", "--bogus"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let output = String::from_utf8(output.stdout).unwrap();
+    let usage_of_the_c_rti = "\nCommand-line arguments: \n\n  -i, --id <n>\n   The ID of the federation that this RTI will control.\n\n  -n, --number_of_federates <n>\n   The number of federates in the federation that this RTI will control.\n\n";
+    assert!(
+        output.contains("Unrecognized command-line argument: --bogus"),
+        "{}",
+        output
+    );
+    assert!(output.contains(usage_of_the_c_rti), "{}", output);
+    for line in [
+        "  -a, --auth Turn on HMAC authentication options.\n\n",
+        "  -t, --tracing Turn on tracing.\n\n",
+        "  -d, --disable_dnet Turn off the use of DNET signals.\n\n",
+    ] {
+        assert!(output.contains(line), "{}", output);
+    }
+}
@@ -0,0 +1,72 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the database of runs recorded with --run_db and of their
+ * comparison.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+use rti::RtiError;
+
+fn pipeline() -> Vec<ScriptedFederate> {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![Step::Send(Kind::Ltc, (0, 0)), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    feds
+}
+
+#[test]
+fn recorded_runs_are_compared() {
+    let path = std::env::temp_dir().join(format!("rti-runs-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let path_arg = path.to_str().unwrap();
+    let first = run_federation(pipeline(), &["--run_db", path_arg]);
+    assert!(first.failed_federates().is_empty());
+    let second = run_federation(pipeline(), &["--run_db", path_arg, "--fast"]);
+    assert!(second.failed_federates().is_empty());
+
+    // The last two runs by default.
+    let report = rti::compare_runs(path_arg, None).unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert!(lines[0].starts_with("From run 1 of federation harness"));
+    assert!(lines[1].starts_with("to run 2 of federation harness"));
+    assert!(lines[1].ends_with("--fast':"));
+    assert!(report.contains("\ttags_sent: "));
+    assert!(report.contains("\tfailed_federates: 0 (no change)"));
+    // The lag is only measured with --max_lag.
+    assert!(report.contains("\tmax_lag_ns: none (no change)"));
+    assert!(report.contains("\tFederate 1:\n\t\ttags_sent: "));
+
+    let report = rti::compare_runs(path_arg, Some((2, 1))).unwrap();
+    assert!(report.starts_with("From run 2"));
+    match rti::compare_runs(path_arg, Some((1, 3))) {
+        Err(RtiError::RunDatabase(reason)) => assert!(reason.ends_with("has no run 3")),
+        result => panic!("unexpected comparison with a missing run: {:?}", result),
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn comparing_needs_two_recorded_runs() {
+    let path = std::env::temp_dir().join(format!("rti-one-run-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let path_arg = path.to_str().unwrap();
+    run_federation(pipeline(), &["--run_db", path_arg]);
+    match rti::compare_runs(path_arg, None) {
+        Err(RtiError::RunDatabase(reason)) => {
+            assert!(reason.ends_with("has fewer than two runs"))
+        }
+        result => panic!("unexpected comparison of a single run: {:?}", result),
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,77 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Regression scenarios written as RON files in tests/scenarios. See
+ * tests/common/scenario.rs for the format.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::scenario::Scenario;
+
+fn scenario_path(name: &str) -> String {
+    format!(
+        "{}/tests/scenarios/{}.ron",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    )
+}
+
+fn run(name: &str) {
+    Scenario::load(&scenario_path(name)).run();
+}
+
+#[test]
+fn every_scenario_parses() {
+    let dir = format!("{}/tests/scenarios", env!("CARGO_MANIFEST_DIR"));
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        Scenario::load(path.to_str().unwrap());
+    }
+}
+
+#[test]
+fn errors_name_the_position() {
+    let error = Scenario::parse("Scenario(\n    federates: [(id: 0, steps: [Jump(1)])],\n)")
+        .err()
+        .unwrap();
+    assert!(error.starts_with("2:"), "{}", error);
+    assert!(error.contains("Jump"), "{}", error);
+    let error = Scenario::parse("Scenario(federates: [(id: 0)], connections: [(from: 0, to: 1)])")
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        "connection from 0 to 1 refers to a federate not in the scenario"
+    );
+    let error = Scenario::parse("Scenario(federates: [(id: 0, steps: [Send(Tag, At(0, 0))])])")
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        "federate 0: only Net, Ltc, and StopRequest can be sent"
+    );
+}
+
+#[test]
+fn ptag_converted_to_tag() {
+    run("ptag_converted_to_tag");
+}
+
+#[test]
+fn stop_request_granted() {
+    run("stop_request_granted");
+}
+
+#[test]
+fn failed_federate_releases_downstream() {
+    run("failed_federate_releases_downstream");
+}
+
+#[test]
+fn step_mode_releases_grants() {
+    run("step_mode_releases_grants");
+}
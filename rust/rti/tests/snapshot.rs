@@ -0,0 +1,132 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of reading snapshots of the state of a federation.
+ */
+use lf_tag::{Tag, FOREVER, FOREVER_MICROSTEP, NEVER};
+use rti::{Snapshot, SNAPSHOT_SCHEMA_VERSION};
+
+/// A snapshot of two federates in a zero-delay cycle in the current schema.
+const SNAPSHOT: &str = r#"{
+  "schema_version": 2,
+  "federation_id": "snapshot \"test\"",
+  "start_time": 1700000000000000000,
+  "stop_in_progress": true,
+  "federates": [
+    {"id": 0, "name": "sensor", "state": "granted",
+     "next_event": [100, 0], "completed": "NEVER", "last_granted": [50, 1],
+     "last_provisionally_granted": "NEVER",
+     "upstream": [{"id": 1, "delay": null}], "downstream": [1],
+     "zero_delay_cycle": [0, 1, 0], "capabilities": ["control_channel"],
+     "suspended": false, "silent": false, "transient": true,
+     "added_later": {"ignored": [1, 2]}},
+    {"id": 1, "name": null, "state": "not_connected",
+     "next_event": "FOREVER", "completed": "FOREVER", "last_granted": "FOREVER",
+     "last_provisionally_granted": "FOREVER",
+     "upstream": [{"id": 0, "delay": 2000}], "downstream": [0],
+     "zero_delay_cycle": null, "capabilities": [],
+     "suspended": false, "silent": true, "transient": false}
+  ]
+}"#;
+
+#[test]
+fn snapshot_is_read_and_written_back() {
+    let snapshot = Snapshot::parse(SNAPSHOT).unwrap();
+    assert_eq!(snapshot.schema_version(), SNAPSHOT_SCHEMA_VERSION);
+    assert_eq!(snapshot.federation_id(), "snapshot \"test\"");
+    assert_eq!(snapshot.start_time(), Some(1_700_000_000_000_000_000));
+    assert!(snapshot.stop_in_progress());
+    let federates = snapshot.federates();
+    assert_eq!(federates.len(), 2);
+    assert_eq!(federates[0].name(), Some("sensor"));
+    assert_eq!(federates[0].state(), "granted");
+    assert_eq!(federates[0].next_event(), &Tag::new(100, 0));
+    assert_eq!(federates[0].completed(), &Tag::new(NEVER, 0));
+    assert_eq!(federates[0].zero_delay_cycle(), Some(&[0, 1, 0][..]));
+    assert_eq!(
+        federates[0].capabilities(),
+        &[String::from("control_channel")]
+    );
+    assert!(federates[0].transient());
+    assert_eq!(federates[1].name(), None);
+    assert_eq!(
+        federates[1].last_granted(),
+        &Tag::new(FOREVER, FOREVER_MICROSTEP)
+    );
+    assert_eq!(federates[1].upstream(), &[(0, Some(2000))]);
+    assert!(federates[1].silent());
+
+    let written = snapshot.to_json();
+    assert!(written.starts_with("{\"schema_version\":2,"), "{}", written);
+    assert_eq!(Snapshot::parse(&written).unwrap(), snapshot);
+}
+
+#[test]
+fn unversioned_snapshot_is_read_as_version_1() {
+    // A snapshot as the status server of earlier releases returned it.
+    let text = r#"{"federation_id":"old","start_time":null,"federates":[{"id":0,"name":null,"state":"pending","next_event":[10,0],"completed":"NEVER","last_granted":"NEVER","last_provisionally_granted":"NEVER","upstream":[],"downstream":[],"zero_delay_cycle":null,"capabilities":[],"suspended":false,"silent":false}]}"#;
+    let snapshot = Snapshot::parse(text).unwrap();
+    assert_eq!(snapshot.schema_version(), 1);
+    assert_eq!(snapshot.start_time(), None);
+    assert!(!snapshot.stop_in_progress());
+    assert!(!snapshot.federates()[0].transient());
+    // It is written back in the current version.
+    let upgraded = Snapshot::parse(&snapshot.to_json()).unwrap();
+    assert_eq!(upgraded.schema_version(), SNAPSHOT_SCHEMA_VERSION);
+    assert_eq!(upgraded.federates(), snapshot.federates());
+}
+
+#[test]
+fn invalid_snapshots_are_rejected_with_the_location_of_the_problem() {
+    let replaced = |from: &str, to: &str| {
+        assert!(SNAPSHOT.contains(from), "{}", from);
+        Snapshot::parse(&SNAPSHOT.replacen(from, to, 1)).unwrap_err()
+    };
+    assert_eq!(
+        replaced("\"schema_version\": 2", "\"schema_version\": 3"),
+        "schema_version: version 3 is newer than version 2 that this RTI reads; upgrade the RTI"
+    );
+    assert_eq!(
+        replaced("\"next_event\": [100, 0]", "\"next_event\": 100"),
+        "federates[0].next_event: expected [time, microstep], \"NEVER\", or \"FOREVER\""
+    );
+    assert_eq!(
+        replaced("\"state\": \"granted\"", "\"state\": \"running\""),
+        "federates[0].state: expected \"not_connected\", \"granted\", or \"pending\", found \"running\""
+    );
+    assert_eq!(
+        replaced("\"delay\": 2000", "\"delay\": \"2 us\""),
+        "federates[1].upstream[0].delay: expected an integer of type i64"
+    );
+    assert_eq!(
+        replaced("\"downstream\": [0]", "\"downstream\": [7]"),
+        "federates[1].downstream: no federate has the ID 7"
+    );
+    assert_eq!(
+        replaced("{\"id\": 1, \"name\"", "{\"id\": 2, \"name\""),
+        "federates[1].id: expected 1 because federates are listed in the order of their IDs, found 2"
+    );
+    assert_eq!(
+        replaced(", \"transient\": false", ""),
+        "federates[1]: missing field \"transient\""
+    );
+    assert_eq!(
+        replaced("\"silent\": true,", "\"silent\": true"),
+        "line 19, column 41: expected `,` or `}`"
+    );
+    assert_eq!(Snapshot::parse("[]").unwrap_err(), "expected a JSON object");
+}
+
+#[test]
+fn malformed_json_is_rejected() {
+    let nested = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+    let error = Snapshot::parse(&nested).unwrap_err();
+    assert!(error.contains("recursion limit exceeded"), "{}", error);
+    let escaped = SNAPSHOT.replacen("snapshot \\\"test\\\"", "snapshot \\u+041", 1);
+    assert_ne!(escaped, SNAPSHOT);
+    let error = Snapshot::parse(&escaped).unwrap_err();
+    assert!(error.contains("invalid escape"), "{}", error);
+}
@@ -0,0 +1,98 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the formats of the times printed by the RTI, in their own
+ * process because the format is selected for the whole process.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::path::PathBuf;
+
+/// Run a pipeline whose federates propose 0 as their start time, so that the
+/// start time is 1 ns, with the given arguments, a log file, and debug
+/// output, and return the contents of the file. Federate 0 waits for the
+/// given response of the admin console to "history 0" once it sends its NET.
+fn run_with_time_format(name: &str, args: &[&str], history: &'static str) -> String {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("rti-time-{}-{}.log", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (10, 0)),
+        // Until the RTI has handled the NET.
+        Step::AdminUntil("history 0", history),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![Step::Send(Kind::Net, (10, 0)), Step::Resign];
+    let mut all_args = vec!["--log_level", "debug", "--log_file", path.to_str().unwrap()];
+    all_args.extend_from_slice(args);
+    run_federation(feds, &all_args);
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    text
+}
+
+/// Return true if the text is a date and time in UTC such as
+/// 2023-05-01T12:00:00.000000000Z.
+fn is_utc_time(text: &str) -> bool {
+    text.len() == 30
+        && text.char_indices().all(|(index, c)| match index {
+            4 | 7 => c == '-',
+            10 => c == 'T',
+            13 | 16 => c == ':',
+            19 => c == '.',
+            29 => c == 'Z',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+#[test]
+fn times_are_printed_in_the_time_format() {
+    let text = run_with_time_format("elapsed", &[], "OK federate 0 net (10,0) at physical time ");
+    assert!(text.contains("the Next Event Tag (NET) (10,0)"), "{}", text);
+
+    let text = run_with_time_format(
+        "raw",
+        &["--time_format", "raw"],
+        "OK federate 0 net (11,0) at physical time ",
+    );
+    assert!(text.contains("the Next Event Tag (NET) (11,0)"), "{}", text);
+
+    let text = run_with_time_format(
+        "absolute",
+        &["--time_format", "absolute", "--log_timestamps"],
+        "OK federate 0 net (1970-01-01T00:00:00.000000011Z,0) at physical time 20",
+    );
+    assert!(
+        text.contains("the Next Event Tag (NET) (1970-01-01T00:00:00.000000011Z,0)"),
+        "{}",
+        text
+    );
+    for line in text.lines() {
+        let timestamp = line.split(' ').next().unwrap();
+        assert!(is_utc_time(timestamp), "{}", line);
+        assert!(timestamp.starts_with("20"), "{}", line);
+    }
+
+    // Elapsed timestamps are counted from the start time once it is known.
+    let text = run_with_time_format(
+        "elapsed-timestamps",
+        &["--log_timestamps"],
+        "OK federate 0 net (10,0) at physical time ",
+    );
+    let first = text.lines().next().unwrap();
+    assert!(first.starts_with("- "), "{}", first);
+    let net = text
+        .lines()
+        .find(|line| line.contains("the Next Event Tag (NET)"))
+        .unwrap();
+    let timestamp = net.split(' ').next().unwrap();
+    assert!(timestamp.parse::<i64>().unwrap() > 0, "{}", net);
+}
@@ -0,0 +1,137 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of federates connecting to the RTI over TLS.
+ */
+#[allow(dead_code)]
+mod common;
+
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rustls::crypto::ring;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+use common::free_port;
+
+const MSG_TYPE_FED_IDS: u8 = 1;
+const MSG_TYPE_TIMESTAMP: u8 = 2;
+const MSG_TYPE_RESIGN: u8 = 4;
+const MSG_TYPE_TAG_ADVANCE_GRANT: u8 = 7;
+const MSG_TYPE_NEIGHBOR_STRUCTURE: u8 = 24;
+const MSG_TYPE_UDP_PORT: u8 = 254;
+const MSG_TYPE_ACK: u8 = 255;
+
+const FEDERATION_ID: &str = "tls";
+
+fn data_file(name: &str) -> String {
+    format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn connect(port: u16) -> TcpStream {
+    for _ in 0..100 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)) {
+            stream
+                .set_read_timeout(Some(Duration::from_secs(10)))
+                .unwrap();
+            return stream;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    panic!("Failed to connect to the RTI on port {}.", port);
+}
+
+fn connect_over_tls(port: u16) -> StreamOwned<ClientConnection, TcpStream> {
+    let mut roots = RootCertStore::empty();
+    let mut ca = BufReader::new(std::fs::File::open(data_file("tls-ca.pem")).unwrap());
+    for cert in rustls_pemfile::certs(&mut ca) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connection =
+        ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap())
+            .unwrap();
+    StreamOwned::new(connection, connect(port))
+}
+
+fn start_rti(port: u16) -> thread::JoinHandle<Vec<u16>> {
+    let args: Vec<String> = [
+        "rti",
+        "-i",
+        FEDERATION_ID,
+        "-n",
+        "1",
+        "-p",
+        &port.to_string(),
+        "--tls_cert",
+        &data_file("tls-cert.pem"),
+        "--tls_key",
+        &data_file("tls-key.pem"),
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    thread::spawn(move || {
+        let mut _f_rti = rti::initialize_rti();
+        rti::process_args(&mut _f_rti, &args).unwrap();
+        rti::initialize_federates(&mut _f_rti);
+        rti::start_rti_server(&mut _f_rti)
+            .unwrap()
+            .wait_for_federates(_f_rti)
+    })
+}
+
+#[test]
+fn federate_runs_over_tls_and_plain_connections_are_closed() {
+    let port = free_port();
+    let rti_thread = start_rti(port);
+
+    // A federate that does not speak TLS is closed without an answer.
+    let mut plain = connect(port);
+    let mut buffer = vec![MSG_TYPE_FED_IDS];
+    buffer.extend_from_slice(&0u16.to_le_bytes());
+    buffer.push(FEDERATION_ID.len() as u8);
+    buffer.extend_from_slice(FEDERATION_ID.as_bytes());
+    plain.write_all(&buffer).unwrap();
+    let mut reply = Vec::new();
+    let _ = plain.read_to_end(&mut reply);
+    assert!(!reply.contains(&MSG_TYPE_ACK));
+
+    let mut stream = connect_over_tls(port);
+    stream.write_all(&buffer).unwrap();
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack).unwrap();
+    assert_eq!(ack[0], MSG_TYPE_ACK);
+
+    let mut buffer = vec![MSG_TYPE_NEIGHBOR_STRUCTURE];
+    buffer.extend_from_slice(&0i32.to_le_bytes());
+    buffer.extend_from_slice(&0i32.to_le_bytes());
+    buffer.push(MSG_TYPE_UDP_PORT);
+    buffer.extend_from_slice(&u16::MAX.to_le_bytes());
+    buffer.push(MSG_TYPE_TIMESTAMP);
+    buffer.extend_from_slice(&0i64.to_le_bytes());
+    stream.write_all(&buffer).unwrap();
+    let mut start_time = [0u8; 9];
+    stream.read_exact(&mut start_time).unwrap();
+    assert_eq!(start_time[0], MSG_TYPE_TIMESTAMP);
+
+    // A federate without neighbors is granted right away.
+    let mut grant = [0u8; 13];
+    stream.read_exact(&mut grant).unwrap();
+    assert_eq!(grant[0], MSG_TYPE_TAG_ADVANCE_GRANT);
+
+    stream.write_all(&[MSG_TYPE_RESIGN]).unwrap();
+    let failed_federates = rti_thread.join().unwrap();
+    assert!(failed_federates.is_empty());
+}
@@ -0,0 +1,68 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of the header of binary trace files.
+ */
+use lf_tag::Tag;
+use rti::{TraceEvent, TraceHeader, TraceRecord, LFT_FORMAT_VERSION};
+
+#[test]
+fn trace_header_is_read_back() {
+    let header = TraceHeader::new(
+        1_700_000_000_000_000_000,
+        vec![Some(String::from("sensor")), None],
+    );
+    let mut buffer = Vec::new();
+    header.write(&mut buffer).unwrap();
+    let read = TraceHeader::read(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read, header);
+    assert_eq!(read.version(), LFT_FORMAT_VERSION);
+    assert_eq!(read.number_of_federates(), 2);
+}
+
+#[test]
+fn trace_header_without_rti_metadata_is_version_0() {
+    // A header as written by the C runtime, with an empty object table.
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&42i64.to_le_bytes());
+    buffer.extend_from_slice(&0i32.to_le_bytes());
+    let read = TraceHeader::read(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read.version(), 0);
+    assert_eq!(read.start_time(), 42);
+    assert_eq!(read.number_of_federates(), 0);
+}
+
+#[test]
+fn trace_header_of_a_later_version_is_rejected() {
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&0i64.to_le_bytes());
+    buffer.extend_from_slice(&1i32.to_le_bytes());
+    buffer.extend_from_slice(&u64::MAX.to_le_bytes());
+    buffer.extend_from_slice(&(LFT_FORMAT_VERSION + 1).to_le_bytes());
+    buffer.extend_from_slice(b"RTI\0");
+    assert!(TraceHeader::read(&mut buffer.as_slice()).is_err());
+}
+
+#[test]
+fn trace_records_are_read_back() {
+    let records = vec![
+        TraceRecord::new(TraceEvent::ReceiveNet, 1, -1, Tag::new(100, 2), 150),
+        TraceRecord::new(TraceEvent::SendTag, -1, 1, Tag::new(100, 2), 160),
+        TraceRecord::new(TraceEvent::ReceiveResign, 0, -1, Tag::never_tag(), 170),
+    ];
+    let mut buffer = Vec::new();
+    TraceRecord::write_block(&mut buffer, &records).unwrap();
+    // An i32 count followed by one trace_record_t of the C runtime per record.
+    assert_eq!(buffer.len(), 4 + 64 * records.len());
+    assert_eq!(
+        &buffer[4..8],
+        &TraceEvent::ReceiveNet.to_value().to_le_bytes()
+    );
+    let mut reader = buffer.as_slice();
+    let read = TraceRecord::read_block(&mut reader).unwrap().unwrap();
+    assert!(read == records);
+    assert!(TraceRecord::read_block(&mut reader).unwrap().is_none());
+}
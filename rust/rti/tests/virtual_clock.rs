@@ -0,0 +1,251 @@
+/**
+ * @file
+ * @author Chanhee Lee (chanheel@asu.edu)
+ * @author Hokeun Kim (hokeun@asu.edu)
+ * @copyright (c) 2023, Arizona State University
+ * License in [BSD 2-clause](..)
+ * @brief Tests of federations whose RTI reads a virtual clock that the test
+ * advances, so that hours of physical time pass in milliseconds, and of the
+ * detection of leap seconds that step or smear the clock source, whose
+ * readings the virtual clock stands in for.
+ */
+#[allow(dead_code)]
+mod common;
+
+use common::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rti::{PhysicalClock, VirtualClock};
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+/// Advance the clock by the given step every millisecond until the returned
+/// function is called, which returns the number of advances.
+fn keep_advancing(clock: VirtualClock, step: Duration) -> impl FnOnce() -> u64 {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stop = stopped.clone();
+    let handle = thread::spawn(move || {
+        let mut advances = 0;
+        while !stopped.load(Ordering::SeqCst) {
+            clock.advance(step);
+            advances += 1;
+            thread::sleep(Duration::from_millis(1));
+        }
+        advances
+    });
+    move || {
+        stop.store(true, Ordering::SeqCst);
+        handle.join().unwrap()
+    }
+}
+
+/// Run the federates with the given arguments on a virtual clock that starts
+/// at 0 and is advanced by a minute every millisecond, and return the log and
+/// the number of advances.
+fn run_on_virtual_clock(feds: Vec<ScriptedFederate>, extra_args: &[&str]) -> (Log, u64) {
+    let clock = VirtualClock::new(0);
+    let stop = keep_advancing(clock.clone(), Duration::from_secs(60));
+    let log = run_federation_with(feds, extra_args, move |rti| rti.set_virtual_clock(clock));
+    (log, stop())
+}
+
+#[test]
+fn virtual_clock_passes_only_when_advanced() {
+    let clock = VirtualClock::new(1_000);
+    let shared = clock.clone();
+    thread::sleep(Duration::from_millis(10));
+    assert_eq!(clock.now(), 1_000);
+    shared.advance(Duration::from_micros(2));
+    assert_eq!(clock.now(), 3_000);
+    assert_eq!(clock.sleeping_threads(), 0);
+}
+
+#[test]
+fn silent_federate_is_presumed_idle_after_an_hour_of_virtual_time() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    // Federate 0 never sends a NET, as if it had deadlocked.
+    feds[0].steps = vec![Step::Sleep(1500), Step::Resign];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (1000, 0)),
+        Step::Expect(Kind::Tag, (1000, 0)),
+        Step::Resign,
+    ];
+    let grace = HOUR_MS.to_string();
+    let (log, advances) = run_on_virtual_clock(feds, &["--silent_grace", &grace]);
+    assert!(advances >= 60, "{}", advances);
+    log.assert_before(
+        &Event::Received(1, Kind::Tag, (1000, 0)),
+        &Event::Sent(0, Kind::Resign, (0, 0)),
+    );
+}
+
+#[test]
+fn suspended_federate_fails_once_a_resumption_window_of_hours_expires() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, 10_000_000);
+    // Resumption is bit 4.
+    feds[0].capabilities = Some((0b1_0000, 0b1_0000));
+    // The connection is closed without resigning after the last step.
+    feds[0].steps = vec![Step::Send(Kind::Net, (50_000_000, 0))];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (100_000_000, 0)),
+        Step::Expect(Kind::Tag, FOREVER_TAG),
+        Step::Resign,
+    ];
+    let window = (6 * HOUR_MS).to_string();
+    let (log, advances) = run_on_virtual_clock(feds, &["--resume_window", &window]);
+    assert!(advances >= 6 * 60, "{}", advances);
+    assert_eq!(log.failed_federates(), &[0]);
+}
+
+#[test]
+fn federation_on_a_virtual_clock_that_is_not_advanced_still_exits() {
+    let mut feds = vec![ScriptedFederate::new(0), ScriptedFederate::new(1)];
+    connect(&mut feds, 0, 1, NO_DELAY);
+    feds[0].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Sleep(100),
+        Step::Send(Kind::Ltc, (0, 0)),
+        Step::Resign,
+    ];
+    feds[1].steps = vec![
+        Step::Send(Kind::Net, (0, 0)),
+        Step::Expect(Kind::Tag, (0, 0)),
+        Step::Resign,
+    ];
+    // The periodic threads sleep on the clock until the RTI releases them.
+    let clock = VirtualClock::new(0);
+    let log = run_federation_with(
+        feds,
+        &[
+            "--silent_grace",
+            "100",
+            "--resume_window",
+            "100",
+            "--progress_period",
+            "100",
+        ],
+        move |rti| rti.set_virtual_clock(clock),
+    );
+    assert!(log.failed_federates().is_empty());
+}
+
+/// Return the physical clock of an RTI that reads the virtual clock in place
+/// of the given clock source.
+fn physical_clock(clock: &VirtualClock, clock_source: &str) -> Arc<PhysicalClock> {
+    let mut rti = rti::initialize_rti();
+    let args: Vec<String> = [
+        "rti",
+        "-i",
+        FEDERATION_ID,
+        "-n",
+        "1",
+        "--clock_source",
+        clock_source,
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    rti::process_args(&mut rti, &args).unwrap();
+    rti.set_virtual_clock(clock.clone());
+    rti.physical_clock()
+}
+
+#[test]
+fn leap_second_that_steps_the_source_is_detected() {
+    let start = 1_700_000_000_000_000_000;
+    let clock = VirtualClock::new(start);
+    let physical_clock = physical_clock(&clock, "realtime");
+    physical_clock.now();
+    clock.advance(Duration::from_secs(1));
+    // An NTP correction of 10 ms is not a step.
+    clock.step(10_000_000);
+    physical_clock.now();
+    assert!(physical_clock.detected_steps().is_empty());
+    // The inserted leap second repeats the last second of the day.
+    clock.advance(Duration::from_secs(1));
+    clock.step(-1_000_000_000);
+    assert_eq!(physical_clock.now(), start + 1_010_000_000);
+    assert_eq!(physical_clock.detected_steps(), vec![-1_000_000_000]);
+    // The step is not counted as a smear once a smear window has passed.
+    for _ in 0..61 {
+        clock.advance(Duration::from_secs(1));
+        physical_clock.now();
+    }
+    assert_eq!(physical_clock.detected_steps(), vec![-1_000_000_000]);
+    assert_eq!(physical_clock.detected_smear_ppm(), None);
+}
+
+#[test]
+fn leap_smear_of_the_source_is_detected_over_a_minute() {
+    let clock = VirtualClock::new(0);
+    let physical_clock = physical_clock(&clock, "realtime");
+    physical_clock.now();
+    // The source runs 20 ppm fast, about twice as fast as a 24-hour smear.
+    for _ in 0..59 {
+        clock.advance(Duration::from_secs(1));
+        clock.step(20_000);
+        physical_clock.now();
+    }
+    assert_eq!(physical_clock.detected_smear_ppm(), None);
+    clock.advance(Duration::from_secs(1));
+    clock.step(20_000);
+    physical_clock.now();
+    assert_eq!(physical_clock.detected_smear_ppm(), Some(20));
+    assert!(physical_clock.detected_steps().is_empty());
+}
+
+#[test]
+fn drift_corrected_by_ntp_is_not_a_smear() {
+    let clock = VirtualClock::new(0);
+    let physical_clock = physical_clock(&clock, "realtime");
+    physical_clock.now();
+    for _ in 0..120 {
+        clock.advance(Duration::from_secs(1));
+        clock.step(-1_000);
+        physical_clock.now();
+    }
+    assert_eq!(physical_clock.detected_smear_ppm(), None);
+    assert!(physical_clock.detected_steps().is_empty());
+}
+
+#[test]
+fn monotonic_source_is_not_checked_for_steps_or_smears() {
+    let clock = VirtualClock::new(0);
+    let physical_clock = physical_clock(&clock, "monotonic");
+    physical_clock.now();
+    for _ in 0..61 {
+        clock.advance(Duration::from_secs(1));
+        clock.step(1_000_000_000);
+        physical_clock.now();
+    }
+    assert!(physical_clock.detected_steps().is_empty());
+    assert_eq!(physical_clock.detected_smear_ppm(), None);
+}
+
+#[test]
+fn unknown_clock_source_is_rejected() {
+    let mut rti = rti::initialize_rti();
+    let args: Vec<String> = [
+        "rti",
+        "-i",
+        FEDERATION_ID,
+        "-n",
+        "1",
+        "--clock_source",
+        "sundial",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let error = rti::process_args(&mut rti, &args).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid arguments: Fail to handle clock_source option"
+    );
+}